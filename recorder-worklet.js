@@ -0,0 +1,75 @@
+// AudioWorklet capture for geno-1: records the master bus as PCM between two
+// exact frames of the audio clock.
+//
+// The main thread (src/audio.rs, `PcmRecorder`) posts { start: frame } and
+// later { stop: frame }, both frames of `currentFrame`'s clock. Audio from
+// the render quanta overlapping [start, stop) is copied out in chunks of
+// about CHUNK_FRAMES as { frame, left, right }, where frame is the chunk's
+// first frame; once the stop frame has been rendered the rest is flushed and
+// { done: true } follows. Trimming to the exact window happens on the Rust
+// side (`Recording::push_block` in src/core/recording.rs).
+
+const CHUNK_FRAMES = 16384;
+const QUANTUM = 128;
+
+class GenoRecorder extends AudioWorkletProcessor {
+  constructor() {
+    super();
+    this.start = Infinity;
+    this.stop = Infinity;
+    this.left = new Float32Array(CHUNK_FRAMES + QUANTUM);
+    this.right = new Float32Array(CHUNK_FRAMES + QUANTUM);
+    this.fill = 0;
+    this.chunkFrame = 0;
+    this.port.onmessage = (e) => {
+      const msg = e.data;
+      if (msg.start !== undefined) {
+        this.start = msg.start;
+        this.stop = Infinity;
+        this.fill = 0;
+      }
+      if (msg.stop !== undefined) this.stop = msg.stop;
+    };
+  }
+
+  flush() {
+    if (this.fill > 0) {
+      this.port.postMessage({
+        frame: this.chunkFrame,
+        left: this.left.slice(0, this.fill),
+        right: this.right.slice(0, this.fill),
+      });
+    }
+    this.fill = 0;
+  }
+
+  process(inputs) {
+    const input = inputs[0];
+    const left = input[0];
+    const right = input[1] ?? left;
+    const frames = left ? left.length : QUANTUM;
+    const end = currentFrame + frames;
+    if (end > this.start && currentFrame < this.stop) {
+      if (this.fill === 0) this.chunkFrame = currentFrame;
+      // No input connected = silence
+      if (left) {
+        this.left.set(left, this.fill);
+        this.right.set(right, this.fill);
+      } else {
+        this.left.fill(0, this.fill, this.fill + frames);
+        this.right.fill(0, this.fill, this.fill + frames);
+      }
+      this.fill += frames;
+      if (this.fill >= CHUNK_FRAMES) this.flush();
+    }
+    if (this.stop !== Infinity && end >= this.stop) {
+      this.flush();
+      this.port.postMessage({ done: true });
+      this.start = Infinity;
+      this.stop = Infinity;
+    }
+    return true;
+  }
+}
+
+registerProcessor("geno-recorder", GenoRecorder);
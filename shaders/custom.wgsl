@@ -0,0 +1,31 @@
+// Custom visualizer: the default slot, replaced by any WGSL loaded through
+// the overlay (Visuals → Custom shader). It is appended to waves.wgsl, so the
+// uniforms `u` (voices, theme colours, clock, swirl, ripples) and helpers such
+// as `voice_uv`, `voice_pulse`, `centred`, `swirl`, `hash2` and `fbm` are in
+// scope; it must define
+//
+//     fn custom(uv: vec2<f32>, p: vec2<f32>) -> vec3<f32>
+//
+// returning the linear colour at screen `uv` (0..1, y down), where `p` is the
+// same point centred and aspect-corrected. Trails, analyser layers, stars and
+// grain are added over it as for the built-in visualizers.
+
+// Plasma bands in the theme's colours, bending round the voices and brighter
+// on each beat
+fn custom(uv: vec2<f32>, p: vec2<f32>) -> vec3<f32> {
+    let t = u.time;
+    let c = centred(u.swirl_uv);
+    let q = swirl(p, c);
+    var v = sin(6.0 * q.x + 0.7 * t) + sin(5.0 * q.y - 0.5 * t) + fbm(2.5 * q + 0.1 * t);
+    var col = mix(u.bg_deep.rgb, u.bg_lift.rgb, 0.1 + 0.1 * u.ambient);
+    for (var i = 0; i < 3; i = i + 1) {
+        let d = length(q - voice_uv(i));
+        let pulse = voice_pulse(i);
+        v += (1.0 + pulse) * sin(18.0 * d - 3.0 * t) * exp(-3.0 * d);
+        col += u.voice_colors[i].rgb * exp(-6.0 * d) * (0.15 + 0.35 * pulse);
+    }
+    let band = 0.5 + 0.5 * sin(3.1415927 * v);
+    let tint = mix(u.voice_colors[0].rgb, u.voice_colors[2].rgb, band);
+    col += tint * band * band * (0.18 + 0.5 * u.clock.z);
+    return col + u.accent.rgb * smoothstep(0.96, 1.0, band) * 0.25;
+}
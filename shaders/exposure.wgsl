@@ -0,0 +1,82 @@
+// Auto-exposure: the log-average luminance of the HDR frame, sampled on a
+// coarse grid by a single workgroup, moves a persistent exposure gain towards
+// the key value. The same measurement drives the flash limiter, which holds
+// the average brightness within a ratio of a slowly settling level. The
+// composite pass reads both gains straight from the buffer, so nothing is
+// read back to the CPU.
+
+struct ExposureUniforms {
+    dt: f32,
+    // Target average luminance (middle grey)
+    key: f32,
+    min_gain: f32,
+    max_gain: f32,
+    adapt_per_sec: f32,
+    // 1.0 while auto-exposure is on
+    auto_on: f32,
+    // Rise allowed above the settled level (0 = flash limiter off), and how
+    // fast that level follows the picture
+    flash_ratio: f32,
+    settle_per_sec: f32,
+};
+
+struct ExposureState {
+    gain: f32,
+    // Level the flash limiter has settled at (0 = not yet measured), and the
+    // gain holding this frame under it
+    settled: f32,
+    flash_gain: f32,
+};
+
+@group(0) @binding(0) var hdr_tex: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> u: ExposureUniforms;
+@group(0) @binding(2) var<storage, read_write> state: ExposureState;
+
+// Samples per side of the grid (the workgroup size)
+const GRID: u32 = 16u;
+
+var<workgroup> log_lum: array<f32, 256>;
+
+fn luminance(c: vec3<f32>) -> f32 {
+    return dot(c, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+@compute @workgroup_size(16, 16)
+fn cs_exposure(
+    @builtin(local_invocation_id) lid: vec3<u32>,
+    @builtin(local_invocation_index) li: u32,
+) {
+    let dims = textureDimensions(hdr_tex);
+    let p = (vec2<f32>(lid.xy) + 0.5) / f32(GRID) * vec2<f32>(dims);
+    let texel = min(vec2<u32>(p), dims - vec2<u32>(1u));
+    let c = textureLoad(hdr_tex, texel, 0).rgb;
+    log_lum[li] = log(max(luminance(c), 1e-4));
+    workgroupBarrier();
+
+    // Tree reduction to one sum
+    for (var stride = GRID * GRID / 2u; stride > 0u; stride = stride / 2u) {
+        if (li < stride) {
+            log_lum[li] += log_lum[li + stride];
+        }
+        workgroupBarrier();
+    }
+
+    if (li == 0u) {
+        let average = exp(log_lum[0] / f32(GRID * GRID));
+        if (u.auto_on > 0.5) {
+            let target_gain = clamp(u.key / average, u.min_gain, u.max_gain);
+            let k = 1.0 - exp(-u.dt * u.adapt_per_sec);
+            state.gain = mix(state.gain, target_gain, k);
+        }
+        if (u.flash_ratio > 0.0) {
+            if (state.settled <= 0.0) {
+                state.settled = average;
+            }
+            state.flash_gain = min(1.0, state.settled * u.flash_ratio / average);
+            // The settled level follows what is shown, so a jump is let
+            // through a little at a time
+            let k = 1.0 - exp(-u.dt * u.settle_per_sec);
+            state.settled = mix(state.settled, average * state.flash_gain, k);
+        }
+    }
+}
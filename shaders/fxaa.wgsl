@@ -0,0 +1,165 @@
+// FXAA over the finished frame: find edges from the luma of each pixel and
+// its neighbours, then blend along them by how far the pixel sits from the
+// edge's end, as a cheaper alternative to multisampling that also smooths
+// the fullscreen layers.
+
+// ============================================================================
+// STRUCTS & BINDINGS
+// ============================================================================
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+
+// Contrast below which a pixel is left alone: absolute, and relative to the
+// brightest neighbour
+const EDGE_THRESHOLD_MIN: f32 = 0.0312;
+const EDGE_THRESHOLD: f32 = 0.125;
+// Steps taken along an edge in each direction looking for its end
+const EDGE_STEPS: i32 = 10;
+// How much the sub-pixel blend (for single-pixel specks) counts
+const SUBPIXEL_QUALITY: f32 = 0.75;
+
+// ============================================================================
+// VERTEX SHADER
+// ============================================================================
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vid: u32) -> VsOut {
+    let pos = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -3.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(3.0, 1.0),
+    );
+    let uv = array<vec2<f32>, 3>(
+        vec2<f32>(0.0, 2.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(2.0, 0.0),
+    );
+
+    var out: VsOut;
+    out.pos = vec4<f32>(pos[vid], 0.0, 1.0);
+    out.uv = uv[vid];
+    return out;
+}
+
+// ============================================================================
+// UTILITY FUNCTIONS
+// ============================================================================
+
+// Perceptual luma of a linear colour (the source is sRGB, sampled linear)
+fn luma(c: vec3<f32>) -> f32 {
+    return sqrt(dot(c, vec3<f32>(0.299, 0.587, 0.114)));
+}
+
+fn luma_at(uv: vec2<f32>) -> f32 {
+    return luma(textureSampleLevel(src_tex, src_sampler, uv, 0.0).rgb);
+}
+
+// ============================================================================
+// FRAGMENT SHADER
+// ============================================================================
+
+@fragment
+fn fs_fxaa(inp: VsOut) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(src_tex));
+    let uv = inp.uv;
+    let color = textureSampleLevel(src_tex, src_sampler, uv, 0.0);
+
+    let l_c = luma(color.rgb);
+    let l_n = luma_at(uv + vec2<f32>(0.0, -texel.y));
+    let l_s = luma_at(uv + vec2<f32>(0.0, texel.y));
+    let l_w = luma_at(uv + vec2<f32>(-texel.x, 0.0));
+    let l_e = luma_at(uv + vec2<f32>(texel.x, 0.0));
+    let l_min = min(l_c, min(min(l_n, l_s), min(l_w, l_e)));
+    let l_max = max(l_c, max(max(l_n, l_s), max(l_w, l_e)));
+    let range = l_max - l_min;
+    if (range < max(EDGE_THRESHOLD_MIN, l_max * EDGE_THRESHOLD)) {
+        return color;
+    }
+
+    let l_nw = luma_at(uv + vec2<f32>(-texel.x, -texel.y));
+    let l_ne = luma_at(uv + vec2<f32>(texel.x, -texel.y));
+    let l_sw = luma_at(uv + vec2<f32>(-texel.x, texel.y));
+    let l_se = luma_at(uv + vec2<f32>(texel.x, texel.y));
+
+    // Whether the edge runs horizontally or vertically
+    let edge_h = abs(l_nw - 2.0 * l_w + l_sw) + 2.0 * abs(l_n - 2.0 * l_c + l_s)
+        + abs(l_ne - 2.0 * l_e + l_se);
+    let edge_v = abs(l_nw - 2.0 * l_n + l_ne) + 2.0 * abs(l_w - 2.0 * l_c + l_e)
+        + abs(l_sw - 2.0 * l_s + l_se);
+    let horizontal = edge_h >= edge_v;
+
+    // Which side of the pixel the edge lies on
+    let l_neg = select(l_w, l_n, horizontal);
+    let l_pos = select(l_e, l_s, horizontal);
+    let grad_neg = abs(l_neg - l_c);
+    let grad_pos = abs(l_pos - l_c);
+    var step_len = select(texel.x, texel.y, horizontal);
+    var l_local = 0.5 * (l_pos + l_c);
+    if (grad_neg >= grad_pos) {
+        step_len = -step_len;
+        l_local = 0.5 * (l_neg + l_c);
+    }
+    let gradient = 0.25 * max(grad_neg, grad_pos);
+
+    // Walk along the edge, half a pixel across, until its luma changes
+    var edge_uv = uv;
+    var along = vec2<f32>(texel.x, 0.0);
+    if (horizontal) {
+        edge_uv.y += 0.5 * step_len;
+    } else {
+        edge_uv.x += 0.5 * step_len;
+        along = vec2<f32>(0.0, texel.y);
+    }
+    var uv_neg = edge_uv - along;
+    var uv_pos = edge_uv + along;
+    var d_neg = luma_at(uv_neg) - l_local;
+    var d_pos = luma_at(uv_pos) - l_local;
+    for (var i = 0; i < EDGE_STEPS; i = i + 1) {
+        let done_neg = abs(d_neg) >= gradient;
+        let done_pos = abs(d_pos) >= gradient;
+        if (done_neg && done_pos) {
+            break;
+        }
+        if (!done_neg) {
+            uv_neg -= along;
+            d_neg = luma_at(uv_neg) - l_local;
+        }
+        if (!done_pos) {
+            uv_pos += along;
+            d_pos = luma_at(uv_pos) - l_local;
+        }
+    }
+
+    // Blend across the edge by how near its closer end the pixel is, if
+    // that end turns the way this pixel does
+    let dist_neg = select(uv.y - uv_neg.y, uv.x - uv_neg.x, horizontal);
+    let dist_pos = select(uv_pos.y - uv.y, uv_pos.x - uv.x, horizontal);
+    let nearer_neg = dist_neg < dist_pos;
+    let d_end = select(d_pos, d_neg, nearer_neg);
+    let centre_darker = l_c < l_local;
+    var edge_blend = 0.0;
+    if ((d_end < 0.0) != centre_darker) {
+        edge_blend = 0.5 - min(dist_neg, dist_pos) / (dist_neg + dist_pos);
+    }
+
+    // Single-pixel specks blend by their contrast with the neighbourhood
+    let average = (2.0 * (l_n + l_s + l_w + l_e) + l_nw + l_ne + l_sw + l_se) / 12.0;
+    let sub = clamp(abs(average - l_c) / range, 0.0, 1.0);
+    let sub_smooth = (-2.0 * sub + 3.0) * sub * sub;
+    let sub_blend = sub_smooth * sub_smooth * SUBPIXEL_QUALITY;
+
+    let blend = max(edge_blend, sub_blend);
+    var out_uv = uv;
+    if (horizontal) {
+        out_uv.y += blend * step_len;
+    } else {
+        out_uv.x += blend * step_len;
+    }
+    return textureSampleLevel(src_tex, src_sampler, out_uv, 0.0);
+}
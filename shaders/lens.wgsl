@@ -0,0 +1,119 @@
+// Lens effects after the composite: depth of field, gathering a golden-angle
+// spiral of taps as wide as the pixel is far from the focal plane (read back
+// from the scene's depth buffer), and a vignette that breathes slowly and
+// deepens on downbeats.
+
+// ============================================================================
+// STRUCTS & BINDINGS
+// ============================================================================
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+struct LensUniforms {
+    resolution: vec2<f32>,
+    time: f32,
+    // Downbeat pulse from the musical clock
+    downbeat: f32,
+    // Camera clip planes, to turn depth back into distance
+    near: f32,
+    far: f32,
+    // Focal plane and the distance from it at which blur is widest
+    focus: f32,
+    focus_range: f32,
+    // Widest blur as a fraction of the frame height; 0 = no depth of field
+    max_blur: f32,
+    taps: u32,
+    vignette: f32,
+    _pad: f32,
+}
+
+@group(0) @binding(0) var src_tex: texture_2d<f32>;
+@group(0) @binding(1) var src_sampler: sampler;
+// Swapped for texture_depth_multisampled_2d with MSAA on (see render/lens.rs)
+@group(0) @binding(2) var depth_tex: texture_depth_2d;
+@group(0) @binding(3) var<uniform> u: LensUniforms;
+
+const GOLDEN_ANGLE: f32 = 2.39996323;
+
+// ============================================================================
+// VERTEX SHADER
+// ============================================================================
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vid: u32) -> VsOut {
+    let pos = array<vec2<f32>, 3>(
+        vec2<f32>(-1.0, -3.0),
+        vec2<f32>(-1.0, 1.0),
+        vec2<f32>(3.0, 1.0),
+    );
+    let uv = array<vec2<f32>, 3>(
+        vec2<f32>(0.0, 2.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(2.0, 0.0),
+    );
+
+    var out: VsOut;
+    out.pos = vec4<f32>(pos[vid], 0.0, 1.0);
+    out.uv = uv[vid];
+    return out;
+}
+
+// ============================================================================
+// UTILITY FUNCTIONS
+// ============================================================================
+
+fn luminance(c: vec3<f32>) -> f32 {
+    return dot(c, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// Blur radius at `uv`, as a fraction of the frame height. The depth buffer
+// is at the render scale, so it is read by position rather than by pixel.
+fn blur_at(uv: vec2<f32>) -> f32 {
+    let size = vec2<f32>(textureDimensions(depth_tex));
+    let texel = vec2<i32>(clamp(uv, vec2<f32>(0.0), vec2<f32>(1.0)) * (size - 1.0));
+    let depth = textureLoad(depth_tex, texel, 0);
+    let distance = u.near * u.far / (u.far - depth * (u.far - u.near));
+    return u.max_blur * clamp(abs(distance - u.focus) / u.focus_range, 0.0, 1.0);
+}
+
+// ============================================================================
+// FRAGMENT SHADER
+// ============================================================================
+
+@fragment
+fn fs_lens(inp: VsOut) -> @location(0) vec4<f32> {
+    var color = textureSampleLevel(src_tex, src_sampler, inp.uv, 0.0).rgb;
+    let aspect = u.resolution.x / max(u.resolution.y, 1.0);
+
+    let blur = select(0.0, blur_at(inp.uv), u.taps > 0u && u.max_blur > 0.0);
+    if (blur * u.resolution.y > 0.5) {
+        var acc = color;
+        var total = 1.0;
+        for (var i = 0u; i < u.taps; i = i + 1u) {
+            let k = f32(i) + 0.5;
+            let r = blur * sqrt(k / f32(u.taps));
+            let a = k * GOLDEN_ANGLE;
+            let uv = inp.uv + vec2<f32>(cos(a) / aspect, sin(a)) * r;
+            let tap = textureSampleLevel(src_tex, src_sampler, uv, 0.0).rgb;
+            // Only taps blurred at least this wide spread here, so voices in
+            // focus don't smear into the soft background behind them
+            var w = clamp((blur_at(uv) - r) * u.resolution.y + 1.0, 0.0, 1.0);
+            // Bright taps count for more, for bokeh-like highlights
+            w *= 1.0 + 3.0 * max(luminance(tap) - 0.6, 0.0);
+            acc += tap * w;
+            total += w;
+        }
+        color = acc / total;
+    }
+
+    if (u.vignette > 0.0) {
+        let centered = (inp.uv - 0.5) * vec2<f32>(aspect, 1.0);
+        let breath = 1.0 + 0.06 * sin(u.time * 0.4) + 0.12 * u.downbeat;
+        let edge = smoothstep(0.35, 1.05, length(centered) * breath);
+        color *= 1.0 - u.vignette * edge;
+    }
+    return vec4<f32>(color, 1.0);
+}
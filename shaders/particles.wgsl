@@ -0,0 +1,117 @@
+// Note-burst particles: a fixed pool in a storage buffer, stepped by a compute
+// pass each frame and drawn as camera-facing sparks, blended additively into
+// the HDR target ahead of the bloom. Bursts are written into the pool from the
+// CPU (see `ParticleEmitter` in src/core/particles.rs).
+
+// ============================================================================
+// STRUCTS & BINDINGS
+// ============================================================================
+
+struct Particle {
+    // xyz world position, w = age (seconds)
+    pos_age: vec4<f32>,
+    // xyz velocity, w = lifetime (seconds); dead once age >= lifetime
+    vel_life: vec4<f32>,
+    // rgb colour, w = size in world units
+    color_size: vec4<f32>,
+};
+
+struct ParticleUniforms {
+    view_proj: mat4x4<f32>,
+    // Camera basis used to face the sparks (xyz used)
+    cam_right: vec4<f32>,
+    cam_up: vec4<f32>,
+    dt: f32,
+    time: f32,
+    // Velocity decay per second and downward pull
+    drag: f32,
+    gravity: f32,
+};
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+// Must match `MAX_PARTICLES` in src/core/particles.rs
+const MAX_PARTICLES: u32 = 4096u;
+
+@group(0) @binding(0) var<uniform> u: ParticleUniforms;
+// The same pool, writable for the compute step and read-only for drawing
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<storage, read> particles_in: array<Particle>;
+
+// ============================================================================
+// COMPUTE SHADER
+// ============================================================================
+
+@compute @workgroup_size(64)
+fn cs_step(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let i = gid.x;
+    if (i >= MAX_PARTICLES) {
+        return;
+    }
+    var p = particles[i];
+    if (p.pos_age.w >= p.vel_life.w) {
+        return;
+    }
+    var v = p.vel_life.xyz * exp(-u.drag * u.dt);
+    v.y -= u.gravity * u.dt;
+    // A little curl so bursts unfurl rather than fly straight
+    let curl = vec3<f32>(sin(p.pos_age.y * 3.1 + u.time), 0.0, cos(p.pos_age.x * 2.7 - u.time));
+    v += curl * 0.15 * u.dt;
+    p.pos_age = vec4<f32>(p.pos_age.xyz + v * u.dt, p.pos_age.w + u.dt);
+    p.vel_life = vec4<f32>(v, p.vel_life.w);
+    particles[i] = p;
+}
+
+// ============================================================================
+// VERTEX SHADER
+// ============================================================================
+
+@vertex
+fn vs_particle(@builtin(vertex_index) vid: u32, @builtin(instance_index) iid: u32) -> VsOut {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let p = particles_in[iid];
+    let c = corners[vid];
+
+    var out: VsOut;
+    out.local = c;
+    let life = p.vel_life.w;
+    if (p.pos_age.w >= life) {
+        // Dead: push the quad outside the clip volume
+        out.pos = vec4<f32>(0.0, 0.0, 2.0, 1.0);
+        out.color = vec3<f32>(0.0);
+        return out;
+    }
+    let k = p.pos_age.w / max(life, 1e-3);
+    // A bright flash at birth, then a slow fade
+    let fade = (1.0 - k) * (1.0 - k) * (1.0 + 1.5 * exp(-k * 20.0));
+    let size = p.color_size.w * (1.0 - 0.5 * k);
+    let world = p.pos_age.xyz + (u.cam_right.xyz * c.x + u.cam_up.xyz * c.y) * size;
+    out.pos = u.view_proj * vec4<f32>(world, 1.0);
+    out.color = p.color_size.rgb * fade;
+    return out;
+}
+
+// ============================================================================
+// FRAGMENT SHADER
+// ============================================================================
+
+@fragment
+fn fs_particle(in: VsOut) -> @location(0) vec4<f32> {
+    let r = length(in.local);
+    if (r > 1.0) {
+        discard;
+    }
+    let glow = exp(-r * r * 4.0) + 0.6 * (1.0 - smoothstep(0.0, 0.3, r));
+    return vec4<f32>(in.color * glow, glow);
+}
@@ -2,7 +2,9 @@
 // The authoritative shader content is maintained here after merge.
 // (Content pulled from former app-core/shaders/post.wgsl)
 // Fullscreen post-processing: HDR bright pass, separable blur, composite with
-// filmic tonemapping, vignette, gentle chroma shift and film grain.
+// exposure and a selectable tonemap, vignette, chromatic aberration and film
+// grain (both growing with the saturator's drive), and an optional
+// kaleidoscope fold around the swirl centre.
 
 // ============================================================================
 // STRUCTS & BINDINGS
@@ -20,6 +22,35 @@ struct PostUniforms {
     blur_dir: vec2<f32>,
     bloom_strength: f32,
     threshold: f32,
+    // Theme: rgb multiplies the bloom, w is the final saturation
+    bloom_tint: vec4<f32>,
+    // Share of the outgoing visualizer while modes crossfade (0 otherwise)
+    fade: f32,
+    // Manual exposure gain, auto-exposure flag (1.0 = the adapted gain
+    // applies on top) and the tonemap operator (TONEMAP_*)
+    exposure: f32,
+    auto_exposure: f32,
+    tonemap: u32,
+    // Musical clock: beat phase, bar phase, beat pulse, downbeat pulse
+    clock: vec4<f32>,
+    // How hard the master saturator is driven (0..1), mirrored as chromatic
+    // aberration and grain so timbre and image degrade together
+    grit: f32,
+    // Kaleidoscope segments folded around `mirror_center` (0 = off)
+    mirror_segments: u32,
+    mirror_center: vec2<f32>,
+    // Safety limits: the brightest luminance shown (0 = no cap), and 1.0
+    // while the flash limiter's gain applies
+    safe_peak: f32,
+    flash_limit: f32,
+    _pad0: f32,
+    _pad1: f32,
+}
+
+struct ExposureState {
+    gain: f32,
+    settled: f32,
+    flash_gain: f32,
 }
 
 @group(0) @binding(0) var hdr_tex: texture_2d<f32>;
@@ -28,6 +59,15 @@ struct PostUniforms {
 
 @group(1) @binding(0) var blur_tex: texture_2d<f32>;
 @group(1) @binding(1) var blur_sampler: sampler;
+// The visualizer fading out during a mode switch
+@group(1) @binding(2) var fade_tex: texture_2d<f32>;
+// Auto-exposure and flash-limiter gains, adapted on the GPU (see exposure.wgsl)
+@group(1) @binding(3) var<storage, read> exposure_state: ExposureState;
+
+// Must match `Tonemap::shader_index` in src/core/tonemap.rs
+const TONEMAP_ACES: u32 = 0u;
+const TONEMAP_REINHARD: u32 = 1u;
+const TONEMAP_NONE: u32 = 2u;
 
 // ============================================================================
 // VERTEX SHADER
@@ -69,11 +109,48 @@ fn aces_tonemap(x: vec3<f32>) -> vec3<f32> {
     return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
 }
 
+fn tonemap(x: vec3<f32>) -> vec3<f32> {
+    switch u_post.tonemap {
+        case TONEMAP_REINHARD: {
+            return x / (vec3<f32>(1.0) + x);
+        }
+        case TONEMAP_NONE: {
+            return clamp(x, vec3<f32>(0.0), vec3<f32>(1.0));
+        }
+        default: {
+            return aces_tonemap(x);
+        }
+    }
+}
+
 fn vignette(uv: vec2<f32>) -> f32 {
     let r = length(uv - 0.5);
     return smoothstep(0.95, 0.45, r);
 }
 
+// Fold `x` back into 0..1 as if the image were tiled with mirrored copies,
+// so folded samples past an edge stay on the picture
+fn mirror_repeat(x: vec2<f32>) -> vec2<f32> {
+    let m = x - 2.0 * floor(x * 0.5);
+    return 1.0 - abs(1.0 - m);
+}
+
+// Where `uv` samples from with the image folded into `mirror_segments`
+// wedges around the mirror centre, each the reflection of its neighbour
+fn kaleidoscope(uv: vec2<f32>) -> vec2<f32> {
+    if (u_post.mirror_segments < 2u) {
+        return uv;
+    }
+    let aspect = vec2<f32>(u_post.resolution.x / max(u_post.resolution.y, 1.0), 1.0);
+    let p = (uv - u_post.mirror_center) * aspect;
+    let wedge = 6.28318530718 / f32(u_post.mirror_segments);
+    var a = atan2(p.y, p.x);
+    a = a - wedge * floor(a / wedge);
+    a = min(a, wedge - a);
+    let folded = vec2<f32>(cos(a), sin(a)) * length(p) / aspect;
+    return mirror_repeat(u_post.mirror_center + folded);
+}
+
 fn hash2(p: vec2<f32>) -> f32 {
     let h = dot(p, vec2<f32>(127.1, 311.7));
     return fract(sin(h) * 43758.5453123);
@@ -133,9 +210,22 @@ fn fs_blur(inp: VsOut) -> @location(0) vec4<f32> {
 // COMPOSITE: Final composition with tonemapping, effects, and grain
 @fragment
 fn fs_composite(inp: VsOut) -> @location(0) vec4<f32> {
-    // Sample base HDR and add bloom
-    var base = textureSample(hdr_tex, hdr_sampler, inp.uv).rgb;
-    let bloom = textureSample(blur_tex, blur_sampler, inp.uv).rgb * u_post.bloom_strength;
+    // Sample base HDR, with red and blue pulled apart radially as the
+    // saturator drives harder, crossfaded with the outgoing visualizer, and
+    // add bloom, all folded by the mirror when it is on
+    let src = kaleidoscope(inp.uv);
+    let aberration = (inp.uv - 0.5) * (0.012 * u_post.grit);
+    var base = vec3<f32>(
+        textureSample(hdr_tex, hdr_sampler, src + aberration).r,
+        textureSample(hdr_tex, hdr_sampler, src).g,
+        textureSample(hdr_tex, hdr_sampler, src - aberration).b
+    );
+    let outgoing = textureSample(fade_tex, blur_sampler, src).rgb;
+    base = mix(base, outgoing, u_post.fade);
+    // Bloom swells on the downbeat and eases off through the bar
+    let bloom_strength = u_post.bloom_strength * (1.0 + 0.35 * u_post.clock.w);
+    let bloom = textureSample(blur_tex, blur_sampler, src).rgb * bloom_strength
+        * u_post.bloom_tint.rgb;
     base += bloom;
 
     // Subtle hue warp based on ambient and time
@@ -148,11 +238,18 @@ fn fs_composite(inp: VsOut) -> @location(0) vec4<f32> {
     ) * 0.05 * ambient;
     base *= (vec3<f32>(1.0) + hue);
 
-    // Pre-tonemap exposure adjustment
-    base *= 0.9;
+    // Pre-tonemap exposure: a slight baseline trim, the manual setting and,
+    // when on, the adapted auto-exposure gain
+    var exposure = 0.9 * u_post.exposure;
+    if (u_post.auto_exposure > 0.5) {
+        exposure *= exposure_state.gain;
+    }
+    if (u_post.flash_limit > 0.5) {
+        exposure *= exposure_state.flash_gain;
+    }
+    base *= exposure;
 
-    // ACES filmic tonemapping
-    var mapped = aces_tonemap(base);
+    var mapped = tonemap(base);
 
     // Contrast and gamma adjustments
     let contrast = 0.15;
@@ -177,13 +274,18 @@ fn fs_composite(inp: VsOut) -> @location(0) vec4<f32> {
     let smokeTint = vec3<f32>(0.03, 0.04, 0.06);
     mapped = mapped * (1.0 - k) + smokeTint * (k * 0.25);
 
-    // Film grain
+    // Film grain, coarser with the saturator's drive
     let noise = hash2(inp.uv * u_post.resolution + u_post.time);
-    mapped += (noise - 0.5) * 0.022;
+    mapped += (noise - 0.5) * (0.022 + 0.07 * u_post.grit);
 
-    // Final desaturation for smokier palette
+    // Final saturation from the theme (slightly under 1 for a smokier palette)
     let luma = luminance(mapped);
-    mapped = mix(vec3<f32>(luma), mapped, 0.9);
+    mapped = clamp(mix(vec3<f32>(luma), mapped, u_post.bloom_tint.w), vec3<f32>(0.0), vec3<f32>(1.0));
+
+    // Peak brightness cap, scaling the colour down so its hue is kept
+    if (u_post.safe_peak > 0.0) {
+        mapped *= min(1.0, u_post.safe_peak / max(luminance(mapped), 1e-4));
+    }
 
     return vec4<f32>(mapped, 1.0);
 }
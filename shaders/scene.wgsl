@@ -0,0 +1,182 @@
+// Instanced 3D voice scene: one solid sphere per voice, ray-traced on a
+// camera-facing quad and written to the depth buffer so the voices read in
+// depth, then a glow billboard around each, drawn additively into the HDR
+// target (and hidden where a nearer sphere covers it) so bloom picks up the
+// pulses.
+
+// ============================================================================
+// STRUCTS & BINDINGS
+// ============================================================================
+
+struct SceneUniforms {
+    view_proj: mat4x4<f32>,
+    // Camera basis used to face the billboards (xyz used)
+    cam_right: vec4<f32>,
+    cam_up: vec4<f32>,
+    // Camera position (xyz used), for the sphere rays
+    cam_eye: vec4<f32>,
+    // xyz world position, w = pulse (0..1.5)
+    voices: array<vec4<f32>, 3>,
+    // Theme colour per voice (rgb); w holds marks: +1 for the voice selected
+    // from the keyboard, +2 for a voice locked in place
+    voice_colors: array<vec4<f32>, 3>,
+    // Style per voice: x = glow size, y = ripple frequency (waves only), z =
+    // shape (0 orb, 1 ring, 2 sparkle), w = trail seconds
+    voice_styles: array<vec4<f32>, 3>,
+    time: f32,
+    ambient: f32,
+    // Sphere radius at rest, in world units
+    sphere_radius: f32,
+    _pad: f32,
+};
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) pulse: f32,
+    @location(3) @interpolate(flat) shape: u32,
+    @location(4) @interpolate(flat) selected: f32,
+    @location(5) @interpolate(flat) locked: f32,
+};
+
+struct SphereOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) world: vec3<f32>,
+    @location(1) @interpolate(flat) voice: u32,
+};
+
+struct SphereFrag {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+};
+
+@group(0) @binding(0) var<uniform> u: SceneUniforms;
+
+fn corner(vid: u32) -> vec2<f32> {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    return corners[vid];
+}
+
+// Spheres swell a little with their pulse
+fn sphere_radius(v: vec4<f32>) -> f32 {
+    return u.sphere_radius * (1.0 + 0.15 * v.w);
+}
+
+// ============================================================================
+// VERTEX SHADER
+// ============================================================================
+
+@vertex
+fn vs_sphere(@builtin(vertex_index) vid: u32, @builtin(instance_index) iid: u32) -> SphereOut {
+    let v = u.voices[iid];
+    let c = corner(vid);
+    // A little larger than the sphere, so its silhouette stays inside the
+    // quad under perspective
+    let size = sphere_radius(v) * 1.25;
+    let world = v.xyz + (u.cam_right.xyz * c.x + u.cam_up.xyz * c.y) * size;
+
+    var out: SphereOut;
+    out.pos = u.view_proj * vec4<f32>(world, 1.0);
+    out.world = world;
+    out.voice = iid;
+    return out;
+}
+
+@vertex
+fn vs_scene(@builtin(vertex_index) vid: u32, @builtin(instance_index) iid: u32) -> VsOut {
+    let v = u.voices[iid];
+    let c = corner(vid);
+    let breathe = 0.04 * sin(u.time * 1.3 + f32(iid) * 2.1);
+    let style = u.voice_styles[iid % 3u];
+    let size = (0.45 + 0.35 * v.w + breathe) * style.x;
+    let world = v.xyz + (u.cam_right.xyz * c.x + u.cam_up.xyz * c.y) * size;
+
+    var out: VsOut;
+    out.pos = u.view_proj * vec4<f32>(world, 1.0);
+    out.local = c;
+    out.color = u.voice_colors[iid % 3u].rgb;
+    out.pulse = v.w;
+    out.shape = u32(style.z + 0.5);
+    let marks = u.voice_colors[iid % 3u].w;
+    out.selected = marks % 2.0;
+    out.locked = step(2.0, marks);
+    return out;
+}
+
+// ============================================================================
+// FRAGMENT SHADER
+// ============================================================================
+
+@fragment
+fn fs_sphere(in: SphereOut) -> SphereFrag {
+    let v = u.voices[in.voice];
+    let r = sphere_radius(v);
+    // Ray from the eye through this pixel of the quad, against the sphere
+    let ro = u.cam_eye.xyz;
+    let rd = normalize(in.world - ro);
+    let oc = ro - v.xyz;
+    let b = dot(oc, rd);
+    let h = b * b - (dot(oc, oc) - r * r);
+    if (h < 0.0) {
+        discard;
+    }
+    let hit = ro + rd * (-b - sqrt(h));
+    let n = normalize(hit - v.xyz);
+    let facing = max(dot(n, -rd), 0.0);
+
+    let color = u.voice_colors[in.voice % 3u].rgb;
+    let key = max(dot(n, normalize(vec3<f32>(0.4, 0.8, 0.5))), 0.0);
+    // Dim lit shell, a core that glows through the middle with the pulse,
+    // and a bright rim where the surface turns away
+    let shell = color * (0.12 + 0.05 * u.ambient + 0.45 * key);
+    let inner = color * pow(facing, 4.0) * (0.3 + 1.5 * v.w);
+    let rim = mix(color, vec3<f32>(1.0), 0.3) * pow(1.0 - facing, 3.0) * (0.6 + 0.8 * v.w);
+
+    let clip = u.view_proj * vec4<f32>(hit, 1.0);
+    var out: SphereFrag;
+    out.color = vec4<f32>(shell + inner + rim, 1.0);
+    out.depth = clip.z / clip.w;
+    return out;
+}
+
+@fragment
+fn fs_scene(in: VsOut) -> @location(0) vec4<f32> {
+    let r = length(in.local);
+    if (r > 1.0) {
+        discard;
+    }
+    var core = pow(1.0 - smoothstep(0.0, 0.35, r), 2.0);
+    var halo = exp(-r * r * 5.0) * (0.35 + 0.15 * u.ambient);
+    var ring = smoothstep(0.08, 0.0, abs(r - 0.55 - 0.25 * in.pulse)) * 0.6 * in.pulse;
+    if (in.shape == 1u) {
+        // Ring: a hollow halo whose band is always lit and widens with the pulse
+        core = 0.0;
+        halo *= 0.5;
+        ring = smoothstep(0.10 + 0.08 * in.pulse, 0.0, abs(r - 0.6)) * (0.5 + 0.7 * in.pulse);
+    } else if (in.shape == 2u) {
+        // Sparkle: a small core with four thin rays that twinkle
+        let a = abs(in.local);
+        let rays = exp(-40.0 * min(a.x, a.y)) * (1.0 - r);
+        let twinkle = 0.6 + 0.4 * sin(u.time * 9.0 + 20.0 * r);
+        core = pow(1.0 - smoothstep(0.0, 0.2, r), 2.0);
+        halo *= 0.4;
+        ring = rays * twinkle * (0.6 + 0.9 * in.pulse);
+    }
+    let glow = (core * (0.8 + 1.2 * in.pulse) + halo + ring) * (1.0 - smoothstep(0.85, 1.0, r));
+    // Selected: a thin white ring near the edge that breathes, whatever the shape
+    let beat = 0.7 + 0.3 * sin(u.time * 4.0);
+    var mark = in.selected * smoothstep(0.04, 0.0, abs(r - 0.8)) * beat;
+    // Locked: four still ticks around the edge, like pins
+    let a = atan2(in.local.y, in.local.x);
+    let ticks = smoothstep(0.96, 0.99, cos(4.0 * a)) * smoothstep(0.06, 0.0, abs(r - 0.9));
+    mark += in.locked * ticks * 0.8;
+    return vec4<f32>(in.color * glow + vec3<f32>(mark), glow + mark);
+}
@@ -0,0 +1,68 @@
+// Bitmap text over the finished frame: one instanced quad per glyph (or
+// panel) in screen pixels, sampling the glyph atlas's coverage.
+
+// ============================================================================
+// STRUCTS & BINDINGS
+// ============================================================================
+
+struct TextUniforms {
+    // Target size in pixels
+    screen: vec2<f32>,
+    _pad: vec2<f32>,
+};
+
+struct Quad {
+    // Top left and size, in pixels from the top left of the screen
+    pos: vec2<f32>,
+    size: vec2<f32>,
+    uv_min: vec2<f32>,
+    uv_max: vec2<f32>,
+    color: vec4<f32>,
+};
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> u: TextUniforms;
+@group(0) @binding(1) var<storage, read> quads: array<Quad>;
+@group(0) @binding(2) var atlas: texture_2d<f32>;
+@group(0) @binding(3) var atlas_sampler: sampler;
+
+// ============================================================================
+// VERTEX SHADER
+// ============================================================================
+
+@vertex
+fn vs_text(@builtin(vertex_index) vid: u32, @builtin(instance_index) iid: u32) -> VsOut {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let q = quads[iid];
+    let c = corners[vid];
+    let px = q.pos + q.size * c;
+    let ndc = vec2<f32>(px.x / u.screen.x * 2.0 - 1.0, 1.0 - px.y / u.screen.y * 2.0);
+
+    var out: VsOut;
+    out.pos = vec4<f32>(ndc, 0.0, 1.0);
+    out.uv = mix(q.uv_min, q.uv_max, c);
+    out.color = q.color;
+    return out;
+}
+
+// ============================================================================
+// FRAGMENT SHADER
+// ============================================================================
+
+@fragment
+fn fs_text(inp: VsOut) -> @location(0) vec4<f32> {
+    let coverage = textureSample(atlas, atlas_sampler, inp.uv).r;
+    return vec4<f32>(inp.color.rgb, inp.color.a * coverage);
+}
@@ -1,7 +1,9 @@
 // Copy exists under app-web for bundling via core module include_str!
 // (Content pulled from former app-core/shaders/waves.wgsl)
-// Audio-reactive ribbon/heightfield aesthetic rendered in a single fullscreen pass.
-// Inspired by smooth velvet waves with golden accents.
+// Audio-reactive fullscreen visualizers sharing one set of uniforms: velvet
+// waves with golden accents (the original), a particle field, a tunnel and a
+// kaleidoscope. Each is its own fragment entry point (see `Visualizer` in
+// src/core/visualizer.rs); the analyser layers and grain go over all of them.
 
 // ============================================================================
 // STRUCTS & BINDINGS
@@ -15,6 +17,9 @@ struct VsOut {
 struct Voice {
     // xyz position (x,z used), w = pulse (0..1.5)
     pos_pulse: vec4<f32>,
+    // Voice style: x = glow reach, y = ripple frequency, z = shape (scene
+    // only), w = trail seconds (applied on the CPU); see src/core/voice_style.rs
+    style: vec4<f32>,
 };
 
 struct WaveUniforms {
@@ -25,13 +30,50 @@ struct WaveUniforms {
     swirl_uv: vec2<f32>,
     swirl_strength: f32,
     swirl_active: f32,
-    // Click/tap ripple parameters
-    ripple_uv: vec2<f32>,
-    ripple_t0: f32,
-    ripple_amp: f32,
+    // Analyser layers, 1.0 when drawn
+    spectrum_on: f32,
+    scope_on: f32,
+    // 1.0 while any voice has a motion trail
+    trails_on: f32,
+    // Starfield brightness behind the waves (0 = off)
+    stars: f32,
+    // Theme colours (rgb used): background deep/lifted tones, bass/lead/pad
+    // voices (w holds marks: +1 selected from the keyboard, +2 locked in
+    // place), accent
+    bg_deep: vec4<f32>,
+    bg_lift: vec4<f32>,
+    voice_colors: array<vec4<f32>, 3>,
+    accent: vec4<f32>,
+    // Motion trails, TRAIL_POINTS per voice: xyz engine position (the voice
+    // itself first), w = remaining brightness (0 = unused)
+    trails: array<vec4<f32>, 48>,
+    // Click/tap ripples: xy = uv centre, z = start time, w = amplitude (0 =
+    // empty slot)
+    ripples: array<vec4<f32>, MAX_RIPPLES>,
+    // Musical clock: beat phase, bar phase, beat pulse, downbeat pulse
+    clock: vec4<f32>,
+    // xy: camera offset the star layers parallax against (zw unused)
+    star_shift: vec4<f32>,
+    // Strum streak, oldest point first: xy = uv, z = time added, w = 2 when
+    // joined to the point before, 1 at the start of a strum (0 = unused or
+    // faded)
+    streak: array<vec4<f32>, STREAK_POINTS>,
 };
 
+// Must match `SPECTRUM_BANDS` and `SCOPE_POINTS` in src/core/spectrum.rs
+const SPECTRUM_BANDS: u32 = 64u;
+const SCOPE_POINTS: u32 = 128u;
+// Must match `MAX_RIPPLES` in src/core/ripple.rs
+const MAX_RIPPLES: u32 = 8u;
+// Must match `TRAIL_POINTS` in src/core/trails.rs (`trails` holds three)
+const TRAIL_POINTS: u32 = 16u;
+// Must match `STREAK_POINTS` and `STREAK_LIFETIME_SEC` in src/core/strum.rs
+const STREAK_POINTS: u32 = 24u;
+const STREAK_LIFETIME_SEC: f32 = 0.6;
+
 @group(0) @binding(0) var<uniform> u: WaveUniforms;
+// Spectrum bar levels (0..1), then oscilloscope samples (-1..1)
+@group(0) @binding(1) var<storage, read> audio_bins: array<f32>;
 
 // ============================================================================
 // VERTEX SHADER
@@ -85,19 +127,185 @@ fn fbm(p: vec2<f32>) -> f32 {
     return a;
 }
 
+// Log-scaled spectrum bars rising from the bottom edge, cool to gold with level
+fn spectrum_layer(uv: vec2<f32>, gold: vec3<f32>) -> vec3<f32> {
+    let fx = clamp(uv.x, 0.0, 0.9999) * f32(SPECTRUM_BANDS);
+    let level = audio_bins[u32(fx)];
+    let cell = fract(fx);
+    let bar = smoothstep(0.0, 0.12, cell) * smoothstep(1.0, 0.88, cell);
+    let height = 0.28 * level;
+    let y = 1.0 - uv.y;
+    let fill = smoothstep(height + 0.004, height, y) * (0.25 + 0.5 * y / max(height, 1e-3));
+    let cap = exp(-abs(y - height) * 180.0);
+    let tint = mix(u.voice_colors[0].rgb, gold, level);
+    return tint * bar * (0.35 * fill + 0.9 * cap) * step(0.002, level);
+}
+
+// Time-domain oscilloscope ribbon across the middle of the screen
+fn scope_layer(uv: vec2<f32>, gold: vec3<f32>) -> vec3<f32> {
+    let fx = clamp(uv.x, 0.0, 1.0) * f32(SCOPE_POINTS - 1u);
+    let i = u32(floor(fx));
+    let j = min(i + 1u, SCOPE_POINTS - 1u);
+    let s = mix(audio_bins[SPECTRUM_BANDS + i], audio_bins[SPECTRUM_BANDS + j], fract(fx));
+    // Distance to the trace in pixels: a sharp core inside a soft glow
+    let d = abs(uv.y - (0.5 - 0.18 * s)) * u.resolution.y;
+    let line = smoothstep(2.0, 0.5, d);
+    let glow = exp(-0.12 * d);
+    return mix(gold, vec3<f32>(1.0), 0.4) * (0.9 * line + 0.25 * glow);
+}
+
+// Voice position on the visualizer plane (engine x/z, scaled to the screen)
+fn voice_uv(i: i32) -> vec2<f32> {
+    return vec2<f32>(u.voices[i].pos_pulse.x, u.voices[i].pos_pulse.z) * 0.33;
+}
+
+fn voice_pulse(i: i32) -> f32 {
+    return clamp(u.voices[i].pos_pulse.w, 0.0, 1.5);
+}
+
+// Aspect-corrected, centred coordinates for a screen uv
+fn centred(uv: vec2<f32>) -> vec2<f32> {
+    let aspect = u.resolution.x / max(u.resolution.y, 1.0);
+    return (uv - 0.5) * vec2<f32>(aspect, 1.0);
+}
+
+// Camera breathing: a slight zoom towards the centre on beats and downbeats
+// (see `ClockPulse::zoom`)
+fn breathe(uv: vec2<f32>) -> vec2<f32> {
+    let zoom = 1.0 - 0.006 * u.clock.z - 0.018 * u.clock.w;
+    return 0.5 + (uv - 0.5) * zoom;
+}
+
+// Background lift on the beat, stronger on the downbeat
+fn beat_lift() -> f32 {
+    return 0.04 * u.clock.z + 0.08 * u.clock.w;
+}
+
+// Pointer swirl: rotate `p` about `c`, most strongly close to it
+fn swirl(p: vec2<f32>, c: vec2<f32>) -> vec2<f32> {
+    let v = p - c;
+    let ang = u.swirl_strength * 2.5 * exp(-1.8 * length(v));
+    let cs = cos(ang);
+    let sn = sin(ang);
+    return c + vec2<f32>(v.x * cs - v.y * sn, v.x * sn + v.y * cs);
+}
+
+// Luminous streaks along each voice's recent path, brightest at the voice
+// and fading with age back along the trail
+fn trail_layer(p: vec2<f32>) -> vec3<f32> {
+    var col = vec3<f32>(0.0);
+    for (var v = 0u; v < 3u; v = v + 1u) {
+        let tint = mix(u.voice_colors[v].rgb, u.accent.rgb, 0.35);
+        for (var i = 0u; i + 1u < TRAIL_POINTS; i = i + 1u) {
+            let a = u.trails[v * TRAIL_POINTS + i];
+            let b = u.trails[v * TRAIL_POINTS + i + 1u];
+            if (b.w <= 0.0) {
+                break;
+            }
+            let pa = a.xz * 0.33;
+            let pb = b.xz * 0.33;
+            let ab = pb - pa;
+            let h = clamp(dot(p - pa, ab) / max(dot(ab, ab), 1e-6), 0.0, 1.0);
+            let d = length(p - pa - ab * h);
+            let life = mix(a.w, b.w, h);
+            let width = 0.004 + 0.010 * life;
+            let core = smoothstep(width, width * 0.3, d);
+            let glow = exp(-d * 60.0) * 0.35;
+            col += tint * (core + glow) * life * life * 1.6;
+        }
+    }
+    return col;
+}
+
+// A strum's path as a bright thread, white-hot where it was just drawn and
+// cooling to the accent as it fades; separate strums aren't joined
+fn streak_layer(p: vec2<f32>) -> vec3<f32> {
+    var col = vec3<f32>(0.0);
+    for (var i = 0u; i + 1u < STREAK_POINTS; i = i + 1u) {
+        let a = u.streak[i];
+        let b = u.streak[i + 1u];
+        if (a.w <= 0.0 || b.w < 1.5) {
+            continue;
+        }
+        let pa = centred(a.xy);
+        let pb = centred(b.xy);
+        let ab = pb - pa;
+        let h = clamp(dot(p - pa, ab) / max(dot(ab, ab), 1e-6), 0.0, 1.0);
+        let d = length(p - pa - ab * h);
+        let life = clamp(1.0 - (u.time - mix(a.z, b.z, h)) / STREAK_LIFETIME_SEC, 0.0, 1.0);
+        let width = 0.002 + 0.006 * life;
+        let core = smoothstep(width, width * 0.3, d);
+        let glow = exp(-d * 90.0) * 0.4;
+        col += mix(u.accent.rgb, vec3<f32>(1.0), life * 0.6) * (core + glow) * life * life * 1.4;
+    }
+    return col;
+}
+
+// Motion trails, strum streaks, analyser layers and sparkling grain, shared
+// by every visualizer
+fn finish(col_in: vec3<f32>, uv: vec2<f32>) -> vec4<f32> {
+    var col = col_in;
+    let gold = u.accent.rgb;
+    col += streak_layer(centred(uv));
+    if (u.trails_on > 0.5) {
+        col += trail_layer(centred(uv));
+    }
+    if (u.spectrum_on > 0.5) {
+        col += spectrum_layer(uv, gold);
+    }
+    if (u.scope_on > 0.5) {
+        col += scope_layer(uv, gold);
+    }
+
+    // Film grain effect
+    let s = hash2(centred(uv) * 600.0 + u.time);
+    col += (step(0.992, s) * (s - 0.992) * 240.0) * gold * (0.35 + 0.55 * u.ambient);
+
+    return vec4<f32>(col, 1.0);
+}
+
 // ============================================================================
-// FRAGMENT SHADER
+// FRAGMENT SHADERS
 // ============================================================================
 
-@fragment
-fn fs_waves(inp: VsOut) -> @location(0) vec4<f32> {
-    let uv = inp.uv;
+// Dust and stars behind the waves at centred coordinates `p`: three layers
+// of sparse cells with at most one star each, the farther ones finer, dimmer
+// and slower to drift and to follow the camera; they glint a little brighter
+// with the ambient energy
+fn starfield(p: vec2<f32>) -> vec3<f32> {
+    var col = vec3<f32>(0.0);
+    for (var L = 0; L < 3; L = L + 1) {
+        // 1/3 for the farthest layer, 1 for the nearest
+        let near = f32(L + 1) / 3.0;
+        let drift = vec2<f32>(0.006, 0.002) * u.time * near;
+        let q = (p + drift - u.star_shift.xy * near) * mix(40.0, 14.0, near) + f32(L) * 17.31;
+        let cell = floor(q);
+        let h = hash2(cell);
+        if (h < 0.85) {
+            continue;
+        }
+        let centre = vec2<f32>(hash2(cell + 3.7), hash2(cell + 9.1)) * 0.6 + 0.2;
+        let d = length(fract(q) - centre);
+        let glint = smoothstep(mix(0.05, 0.12, near), 0.0, d);
+        let twinkle = 0.75 + 0.25 * sin(u.time * (0.8 + 2.0 * hash2(cell + 1.3)) + h * 50.0);
+        let tint = mix(u.bg_lift.rgb, vec3<f32>(1.0), 0.5 + 0.5 * fract(h * 13.0));
+        col += tint * glint * glint * twinkle * mix(0.3, 1.0, near);
+    }
+    return col * u.stars * (0.6 + 0.5 * u.ambient);
+}
+
+// Layered heightfield waves at centred coordinates `cuv0` (`uv` only shades
+// the background gradient)
+fn waves_color(uv: vec2<f32>, cuv0: vec2<f32>) -> vec3<f32> {
     let aspect = u.resolution.x / max(u.resolution.y, 1.0);
-    let cuv0 = (uv - 0.5) * vec2<f32>(aspect, 1.0);
     let t = u.time;
 
-    let gold = vec3<f32>(1.00, 0.86, 0.46);
-    var col = vec3<f32>(0.04, 0.055, 0.10);
+    let gold = u.accent.rgb;
+    // Background gradient, a little lifted towards the top
+    var col = mix(u.bg_deep.rgb, u.bg_lift.rgb, 0.04 + 0.12 * (1.0 - clamp(uv.y, 0.0, 1.0)) + beat_lift());
+    if (u.stars > 0.0) {
+        col += starfield(cuv0);
+    }
 
     // Multi-layer wave rendering with depth parallax
     // Creates 3 layers at different depths for visual richness
@@ -125,7 +333,8 @@ fn fs_waves(inp: VsOut) -> @location(0) vec4<f32> {
             let d = distance(cuv, p);
             let dir = normalize(cuv - p);
             let pulse = clamp(v.pos_pulse.w, 0.0, 1.5);
-            let str = (0.12 + 0.45 * pulse) * exp(-1.8 * d);
+            // A wider glow pushes the waves further out
+            let str = (0.12 + 0.45 * pulse) * exp(-1.8 * d / v.style.x);
             disp += dir * str;
         }
         cuv += disp;
@@ -147,17 +356,22 @@ fn fs_waves(inp: VsOut) -> @location(0) vec4<f32> {
             let p = vec2<f32>(v.pos_pulse.x, v.pos_pulse.z) * 0.33;
             let dd = distance(cuv, p);
             let pulse = clamp(v.pos_pulse.w, 0.0, 1.5);
-            h += (0.65 + 0.9 * pulse) * exp(-2.2 * dd) * sin(14.0 * dd - 2.0 * tt);
+            let f = v.style.y;
+            h += (0.65 + 0.9 * pulse) * exp(-2.2 * dd / v.style.x) * sin(14.0 * f * dd - 2.0 * f * tt);
             h += 0.22 * (1.0 / (1.0 + 6.0 * dd)) * sin(7.0 * (cuv.x - p.x) + 1.5 * tt);
         }
 
-        // Click/tap ripple effect
-        let ruv_c = (u.ripple_uv - 0.5) * vec2<f32>(aspect, 1.0) * par;
-        let rv = cuv - ruv_c;
-        let rr = length(rv);
-        let age = max(0.0, t - u.ripple_t0);
-        let ripple_env = u.ripple_amp * exp(-2.0 * age) * exp(-3.0 * rr);
-        h += ripple_env * sin(18.0 * rr - 6.0 * age);
+        // Click/tap ripple effect, one per active ripple
+        for (var j = 0u; j < MAX_RIPPLES; j = j + 1u) {
+            let rp = u.ripples[j];
+            if (rp.w <= 0.0) {
+                continue;
+            }
+            let rr = length(cuv - (rp.xy - 0.5) * vec2<f32>(aspect, 1.0) * par);
+            let age = max(0.0, t - rp.z);
+            let ripple_env = rp.w * exp(-2.0 * age) * exp(-3.0 * rr);
+            h += ripple_env * sin(18.0 * rr - 6.0 * age);
+        }
         
         // Normal calculation for lighting
         let e = 0.002;
@@ -171,9 +385,9 @@ fn fs_waves(inp: VsOut) -> @location(0) vec4<f32> {
         let diff = 0.65 * max(dot(n, l1), 0.0) + 0.35 * max(dot(n, l2), 0.0);
         
         // Base material colors
-        let base = mix(vec3<f32>(0.03, 0.04, 0.08), vec3<f32>(0.12, 0.14, 0.26), diff + 0.15 * u.ambient);
-        let cool = vec3<f32>(0.18, 0.45, 1.05);
-        let warm = vec3<f32>(1.08, 0.86, 0.40);
+        let base = mix(u.bg_deep.rgb, u.bg_lift.rgb, diff + 0.15 * u.ambient + beat_lift());
+        let cool = u.voice_colors[0].rgb;
+        let warm = u.voice_colors[2].rgb;
         let k = clamp(0.5 + 1.1 * h, 0.0, 1.0);
         var lay = base + mix(cool * 0.45, warm * 0.55, k);
         
@@ -196,24 +410,174 @@ fn fs_waves(inp: VsOut) -> @location(0) vec4<f32> {
             let p = vec2<f32>(v.pos_pulse.x, v.pos_pulse.z) * 0.33;
             let dd = distance(cuv, p);
             let pulse = clamp(v.pos_pulse.w, 0.0, 1.5);
-            lay += gold * exp(-40.0 * dd * dd) * (0.30 + 0.35 * pulse);
+            let reach = v.style.x * v.style.x;
+            lay += gold * exp(-40.0 * dd * dd / reach) * (0.30 + 0.35 * pulse);
+            // Selected voice: a breathing ring in its own colour
+            let marks = u.voice_colors[i].w;
+            let sel = marks % 2.0;
+            let sel_ring = smoothstep(0.006, 0.0, abs(dd - 0.09 * v.style.x));
+            lay += u.voice_colors[i].rgb * sel * sel_ring * (1.2 + 0.6 * sin(t * 4.0));
+            // Locked voice: a still ring broken into eight dashes
+            let rel = cuv - p;
+            let dashes = step(0.0, sin(8.0 * atan2(rel.y, rel.x)));
+            let lock_ring = smoothstep(0.004, 0.0, abs(dd - 0.12 * v.style.x)) * dashes;
+            lay += gold * step(2.0, marks) * lock_ring * 0.9;
         }
 
         // Ripple ring highlights
-        let ring = smoothstep(0.010, 0.002, abs(rr - (0.20 * age + 0.02)));
-        let ring_emiss = clamp(u.ripple_amp * exp(-1.4 * age) * ring, 0.0, 1.0);
-        lay += gold * ring_emiss * 0.6;
+        for (var j = 0u; j < MAX_RIPPLES; j = j + 1u) {
+            let rp = u.ripples[j];
+            if (rp.w <= 0.0) {
+                continue;
+            }
+            let rr = length(cuv - (rp.xy - 0.5) * vec2<f32>(aspect, 1.0) * par);
+            let age = max(0.0, t - rp.z);
+            let ring = smoothstep(0.010, 0.002, abs(rr - (0.20 * age + 0.02)));
+            let ring_emiss = clamp(rp.w * exp(-1.4 * age) * ring, 0.0, 1.0);
+            lay += gold * ring_emiss * 0.6;
+        }
         
         // Layer blending
         let a = mix(0.55, 0.28, depth / 2.0);
         col = col * (1.0 - a) + lay * a;
     }
+    return col;
+}
 
-    // Film grain effect
-    let s = hash2(cuv0 * 600.0 + t);
-    col += (step(0.992, s) * (s - 0.992) * 240.0) * gold * (0.35 + 0.55 * u.ambient);
+@fragment
+fn fs_waves(inp: VsOut) -> @location(0) vec4<f32> {
+    let uv = breathe(inp.uv);
+    return finish(waves_color(uv, centred(uv)), uv);
+}
 
-    return vec4<f32>(col, 1.0);
+// PARTICLE FIELD: three parallax layers of drifting sparks, one per cell of a
+// jittered grid, swelling and tinting towards the voice they are nearest
+@fragment
+fn fs_particles(inp: VsOut) -> @location(0) vec4<f32> {
+    let uv = breathe(inp.uv);
+    let aspect = u.resolution.x / max(u.resolution.y, 1.0);
+    let t = u.time;
+    let gold = u.accent.rgb;
+    var col = mix(u.bg_deep.rgb, u.bg_lift.rgb, 0.03 + 0.10 * (1.0 - clamp(uv.y, 0.0, 1.0)) + beat_lift());
+
+    for (var L = 0; L < 3; L = L + 1) {
+        let depth = f32(L);
+        let par = mix(0.7, 1.3, depth / 2.0);
+        let c = (u.swirl_uv - 0.5) * vec2<f32>(aspect, 1.0) * par;
+        let p = swirl(centred(uv) * par, c);
+
+        let scale = 9.0 + 5.0 * depth;
+        let drift = vec2<f32>(0.05 + 0.02 * depth, 0.03) * t;
+        let q = p * scale + drift * scale;
+        let cell = floor(q);
+        let jitter = vec2<f32>(hash2(cell), hash2(cell + 17.3));
+        let wobble = 0.25 * vec2<f32>(sin(t * 0.7 + jitter.x * 6.28), cos(t * 0.9 + jitter.y * 6.28));
+        let spark = (cell + 0.2 + 0.6 * jitter + wobble * 0.4) / scale - drift;
+        let d = length(p - spark) * scale;
+
+        // Nearest voice drives size, brightness and colour
+        var near = 0;
+        var near_d = 1e3;
+        for (var i = 0; i < 3; i = i + 1) {
+            let dv = distance(spark, voice_uv(i));
+            if (dv < near_d) {
+                near_d = dv;
+                near = i;
+            }
+        }
+        let pull = exp(-2.5 * near_d);
+        let pulse = voice_pulse(near);
+        let radius = 0.05 + 0.10 * pull * (0.4 + pulse) + 0.04 * u.ambient;
+        let twinkle = 0.6 + 0.4 * sin(t * (1.5 + 2.0 * jitter.x) + jitter.y * 6.28);
+        let core = smoothstep(radius, radius * 0.3, d);
+        let halo = exp(-d * d / max(radius * radius * 6.0, 1e-4)) * 0.35;
+        let tint = mix(u.voice_colors[near].rgb, gold, 0.35 * pulse);
+        col += tint * (core + halo) * twinkle * (0.35 + 0.9 * pull) * mix(1.0, 0.55, depth / 2.0);
+    }
+
+    // Ripple rings through the field
+    for (var j = 0u; j < MAX_RIPPLES; j = j + 1u) {
+        let rp = u.ripples[j];
+        if (rp.w <= 0.0) {
+            continue;
+        }
+        let age = max(0.0, t - rp.z);
+        let rr = length(centred(uv) - centred(rp.xy));
+        let ring = smoothstep(0.012, 0.002, abs(rr - (0.20 * age + 0.02)));
+        col += gold * clamp(rp.w * exp(-1.4 * age) * ring, 0.0, 1.0) * 0.6;
+    }
+
+    return finish(col, uv);
+}
+
+// TUNNEL: rings rushing out of a vanishing point that follows the pointer,
+// each third of the walls lit by one voice
+@fragment
+fn fs_tunnel(inp: VsOut) -> @location(0) vec4<f32> {
+    let uv = breathe(inp.uv);
+    let aspect = u.resolution.x / max(u.resolution.y, 1.0);
+    let t = u.time;
+    let gold = u.accent.rgb;
+
+    let centre = (u.swirl_uv - 0.5) * vec2<f32>(aspect, 1.0) * 0.35;
+    var p = centred(uv) - centre;
+    // Voices push the walls outward where they are
+    for (var i = 0; i < 3; i = i + 1) {
+        let dv = p - (voice_uv(i) - centre);
+        p += normalize(dv + vec2<f32>(1e-4)) * 0.05 * voice_pulse(i) * exp(-3.0 * length(dv));
+    }
+    let r = max(length(p), 1e-3);
+    let a = atan2(p.y, p.x);
+    let z = 0.35 / r + t * (0.6 + 0.5 * u.ambient);
+    let twist = a + 0.25 * sin(z * 0.7 + t * 0.3) + u.swirl_strength * 0.15 / r;
+
+    // Wall texture: rings along depth, ribs around the circumference
+    let rings = smoothstep(0.42, 0.5, abs(fract(z) - 0.5));
+    let ribs = smoothstep(0.46, 0.5, abs(fract(twist * 12.0 / 6.2831853) - 0.5));
+    let n = 0.5 + 0.5 * fbm(vec2<f32>(twist * 3.0, z * 1.5));
+
+    let sector = (twist / 6.2831853 + 0.5) * 3.0;
+    let i0 = ((i32(floor(sector)) % 3) + 3) % 3;
+    let i1 = (i0 + 1) % 3;
+    let blend = smoothstep(0.7, 1.0, fract(sector));
+    let wall = mix(u.voice_colors[i0].rgb, u.voice_colors[i1].rgb, blend);
+    let pulse = mix(voice_pulse(i0), voice_pulse(i1), blend);
+
+    let fog = smoothstep(0.02, 0.45, r);
+    var col = mix(u.bg_deep.rgb, u.bg_lift.rgb, 0.3 * n + beat_lift());
+    col += wall * (0.25 + 0.45 * n) * (0.5 + 0.8 * pulse) * fog;
+    col += gold * ((1.0 - rings) * (0.5 + 0.9 * u.ambient) + (1.0 - ribs) * 0.15) * fog;
+
+    // Ripples run down the tunnel as bright rings
+    for (var j = 0u; j < MAX_RIPPLES; j = j + 1u) {
+        let rp = u.ripples[j];
+        if (rp.w <= 0.0) {
+            continue;
+        }
+        let age = max(0.0, t - rp.z);
+        let ring = smoothstep(0.08, 0.0, abs(fract(z * 0.25 - age * 0.5) - 0.5) - 0.42);
+        col += gold * rp.w * exp(-1.2 * age) * ring * fog;
+    }
+
+    // Glow at the vanishing point
+    col += mix(u.voice_colors[2].rgb, gold, 0.5) * exp(-r * 18.0) * (0.4 + 0.6 * u.ambient);
+
+    return finish(col, uv);
+}
+
+// KALEIDOSCOPE: the waves folded into six mirrored wedges about the centre,
+// slowly turning
+@fragment
+fn fs_kaleidoscope(inp: VsOut) -> @location(0) vec4<f32> {
+    let uv = breathe(inp.uv);
+    let p = centred(uv);
+    let wedge = 6.2831853 / 6.0;
+    var a = atan2(p.y, p.x) + 0.05 * u.time;
+    a = a - wedge * floor(a / wedge);
+    a = abs(a - 0.5 * wedge);
+    let r = length(p);
+    let folded = vec2<f32>(cos(a), sin(a)) * r * 1.2;
+    return finish(waves_color(uv, folded), uv);
 }
 
 
@@ -1,15 +1,54 @@
-use crate::core::Waveform;
+use crate::constants::{CAMERA_Z, MASTER_FADE_SEC, NOTE_STEAL_FADE_SEC};
+use crate::core::bounce::{schedule_piece, ScheduledNote, BOUNCE_STEP_FRAMES};
+use crate::core::device::DeviceRequest;
+use crate::core::envelope::{Adsr, EnvelopeCurve};
+use crate::core::eq::{EqBandKind, MasterEq, EQ_BANDS};
+use crate::core::filter::{FilterMode, VoiceFilter};
+use crate::core::frame_math::{fx_levels, voice_mix, FxLevels, PARAM_SMOOTH_TAU_SEC};
+use crate::core::latency::estimate_output_latency;
+use crate::core::metronome::{Click, CLICK_SEC};
+use crate::core::mixer::MasterMixer;
+use crate::core::onset::{Onset, OnsetDetector};
+use crate::core::output::{OutputPreset, OUTPUT_PRESETS};
+use crate::core::polyphony::{auto_gain, VoiceAllocator, AUTO_GAIN_TIME_CONSTANT_SEC};
+use crate::core::recording::{frame_at, Recording, RECORD_CHANNELS};
+use crate::core::reverb::{ReverbPreset, HALL};
+use crate::core::sidechain::Sidechain;
+use crate::core::spatial::DistanceModel;
+use crate::core::stereo::{width_gains, DEFAULT_STEREO_WIDTH};
+use crate::core::{
+    EngineEvent, EventBus, MusicEngine, NoteEvent, SpatialHints, VoiceConfig, Waveform,
+};
 use glam::Vec3;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
 use web_sys as web;
 
 pub struct FxBuses {
     pub master_gain: web::GainNode,
+    pub sub_gain: web::GainNode,
+    pub compressor: web::DynamicsCompressorNode,
+    /// Master EQ bands (see `MasterEq`), between compressor and limiter
+    pub master_eq: Vec<web::BiquadFilterNode>,
+    /// Mid/side width matrix after the EQ (see `width_gains`): per channel,
+    /// the gain it keeps and the gain it lends the other side
+    pub width_direct: Vec<web::GainNode>,
+    pub width_cross: Vec<web::GainNode>,
+    pub limiter: web::DynamicsCompressorNode,
+    /// Listener volume/mute after the limiter (see `MasterVolume`)
+    pub volume: web::GainNode,
+    /// Metronome bus, straight into `volume` so clicks skip the master
+    /// processing and recordings
+    pub click: web::GainNode,
     pub sat_pre: web::GainNode,
     pub sat_wet: web::GainNode,
     pub sat_dry: web::GainNode,
     pub reverb_in: web::GainNode,
+    /// Convolver + fade per slot; one sounds while the other takes a new IR
+    pub reverb_slots: Vec<(web::ConvolverNode, web::GainNode)>,
+    reverb_active: Cell<usize>,
     pub reverb_wet: web::GainNode,
     pub delay_in: web::GainNode,
     pub delay_feedback: web::GainNode,
@@ -17,6 +56,10 @@ pub struct FxBuses {
 }
 
 pub struct VoiceRouting {
+    /// Per-voice mute/solo fade stage that every note of the voice passes through
+    pub voice_fades: Vec<web::GainNode>,
+    /// Per-voice sidechain ducking stage, after the fade
+    pub voice_ducks: Vec<web::GainNode>,
     pub voice_gains: Vec<web::GainNode>,
     pub voice_panners: Vec<web::PannerNode>,
     pub delay_sends: Vec<web::GainNode>,
@@ -24,34 +67,34 @@ pub struct VoiceRouting {
 }
 
 fn create_gain(
-    audio_ctx: &web::AudioContext,
+    audio_ctx: &web::BaseAudioContext,
     value: f32,
     label: &str,
-) -> Result<web::GainNode, ()> {
+) -> Result<web::GainNode, String> {
     match web::GainNode::new(audio_ctx) {
         Ok(g) => {
             g.gain().set_value(value);
             Ok(g)
         }
-        Err(e) => {
-            log::error!("{} GainNode error: {:?}", label, e);
-            Err(())
-        }
+        Err(e) => Err(node_error(&format!("{label} GainNode"), &e)),
     }
 }
 
-pub fn build_fx_buses(audio_ctx: &web::AudioContext) -> Result<FxBuses, ()> {
+/// Log a node that couldn't be made and describe it for the status panel.
+fn node_error(node: &str, e: &JsValue) -> String {
+    log::error!("{} error: {:?}", node, e);
+    format!("couldn't create a {node} ({})", crate::dom::error_text(e))
+}
+
+pub fn build_fx_buses(audio_ctx: &web::BaseAudioContext) -> Result<FxBuses, String> {
     // Master gain
     let master_gain = create_gain(audio_ctx, 0.25, "Master")?;
 
     // Subtle master saturation (arctan) with wet/dry mix
     let sat_pre = create_gain(audio_ctx, 0.9, "sat pre")?;
     #[allow(deprecated)]
-    let saturator = web::WaveShaperNode::new(audio_ctx)
-        .map_err(|e| {
-            log::error!("WaveShaperNode error: {:?}", e);
-        })
-        .map_err(|_| ())?;
+    let saturator =
+        web::WaveShaperNode::new(audio_ctx).map_err(|e| node_error("WaveShaperNode", &e))?;
     // Build arctan curve
     let curve_len: u32 = 2048;
     let drive: f32 = 1.6;
@@ -65,74 +108,91 @@ pub fn build_fx_buses(audio_ctx: &web::AudioContext) -> Result<FxBuses, ()> {
     let sat_wet = create_gain(audio_ctx, 0.35, "sat wet")?;
     let sat_dry = create_gain(audio_ctx, 0.65, "sat dry")?;
 
-    // Route master -> [dry,dst] and master -> pre -> shaper -> wet -> dst
+    // Output stage: compressor -> EQ bands -> width -> limiter -> volume -> dst
+    // (levels set by an OutputPreset, EQ by a MasterEq, volume by a MasterMixer)
+    let compressor = create_compressor(audio_ctx, "Compressor")?;
+    let limiter = create_compressor(audio_ctx, "Limiter")?;
+    let mut master_eq = Vec::with_capacity(EQ_BANDS);
+    let mut eq_tail: web::AudioNode = compressor.clone().into();
+    for _ in 0..EQ_BANDS {
+        let band = web::BiquadFilterNode::new(audio_ctx)
+            .map_err(|e| node_error("EQ BiquadFilterNode", &e))?;
+        _ = eq_tail.connect_with_audio_node(&band);
+        eq_tail = band.clone().into();
+        master_eq.push(band);
+    }
+    // Width: split L/R, recombine through direct and cross gains
+    let splitter = audio_ctx
+        .create_channel_splitter_with_number_of_outputs(2)
+        .map_err(|e| node_error("ChannelSplitterNode", &e))?;
+    let merger = audio_ctx
+        .create_channel_merger_with_number_of_inputs(2)
+        .map_err(|e| node_error("ChannelMergerNode", &e))?;
+    _ = eq_tail.connect_with_audio_node(&splitter);
+    let mut width_direct = Vec::with_capacity(2);
+    let mut width_cross = Vec::with_capacity(2);
+    for ch in 0..2 {
+        let direct = create_gain(audio_ctx, 1.0, "Width direct")?;
+        let cross = create_gain(audio_ctx, 0.0, "Width cross")?;
+        _ = splitter.connect_with_audio_node_and_output(&direct, ch);
+        _ = splitter.connect_with_audio_node_and_output(&cross, ch);
+        _ = direct.connect_with_audio_node_and_output_and_input(&merger, 0, ch);
+        _ = cross.connect_with_audio_node_and_output_and_input(&merger, 0, 1 - ch);
+        width_direct.push(direct);
+        width_cross.push(cross);
+    }
+    _ = merger.connect_with_audio_node(&limiter);
+    let volume = create_gain(audio_ctx, 1.0, "Volume")?;
+    _ = limiter.connect_with_audio_node(&volume);
+    _ = volume.connect_with_audio_node(&audio_ctx.destination());
+    let click = create_gain(audio_ctx, 0.5, "Click")?;
+    _ = click.connect_with_audio_node(&volume);
+
+    // Route master -> [dry,out] and master -> pre -> shaper -> wet -> out
     _ = master_gain.connect_with_audio_node(&sat_pre);
     _ = sat_pre.connect_with_audio_node(&saturator);
     _ = saturator.connect_with_audio_node(&sat_wet);
-    _ = sat_wet.connect_with_audio_node(&audio_ctx.destination());
+    _ = sat_wet.connect_with_audio_node(&compressor);
     _ = master_gain.connect_with_audio_node(&sat_dry);
-    _ = sat_dry.connect_with_audio_node(&audio_ctx.destination());
+    _ = sat_dry.connect_with_audio_node(&compressor);
 
-    // Reverb bus
+    // Parallel sub path: lowpassed master blended back in before compression
+    let sub_filter =
+        web::BiquadFilterNode::new(audio_ctx).map_err(|e| node_error("BiquadFilterNode", &e))?;
+    sub_filter.set_type(web::BiquadFilterType::Lowpass);
+    sub_filter.frequency().set_value(110.0);
+    let sub_gain = create_gain(audio_ctx, 0.0, "Sub")?;
+    _ = master_gain.connect_with_audio_node(&sub_filter);
+    _ = sub_filter.connect_with_audio_node(&sub_gain);
+    _ = sub_gain.connect_with_audio_node(&compressor);
+
+    // Reverb bus: two convolver slots crossfaded by `set_reverb_impulse`
     let reverb_in = create_gain(audio_ctx, 1.0, "Reverb in")?;
-    let reverb = web::ConvolverNode::new(audio_ctx)
-        .map_err(|e| {
-            log::error!("ConvolverNode error: {:?}", e);
-        })
-        .map_err(|_| ())?;
-    reverb.set_normalize(true);
-    // Create a long, dark stereo impulse response procedurally
-    {
-        let sr = audio_ctx.sample_rate();
-        let seconds = 5.0_f32; // lush tail
-        let len = (sr as f32 * seconds) as u32;
-        if let Ok(ir) = audio_ctx.create_buffer(2, len, sr) {
-            // simple xorshift32 for deterministic noise
-            let mut seed_l: u32 = 0x1234ABCD;
-            let mut seed_r: u32 = 0x7890FEDC;
-            for ch in 0..2 {
-                let mut buf: Vec<f32> = vec![0.0; len as usize];
-                let mut t = 0.0_f32;
-                let dt = 1.0_f32 / sr as f32;
-                for i in 0..len as usize {
-                    let s = if ch == 0 { &mut seed_l } else { &mut seed_r };
-                    let mut x = *s;
-                    x ^= x << 13;
-                    x ^= x >> 17;
-                    x ^= x << 5;
-                    *s = x;
-                    let n = ((x as f32 / std::u32::MAX as f32) * 2.0 - 1.0) as f32;
-                    // Exponential decay envelope, dark tilt
-                    let decay = (-t / 3.0).exp();
-                    let dark = (1.0 - (t / seconds)).max(0.0);
-                    let v = n * decay * (0.6 + 0.4 * dark);
-                    buf[i] = v;
-                    t += dt;
-                }
-                _ = ir.copy_to_channel(&mut buf, ch as i32);
-            }
-            reverb.set_buffer(Some(&ir));
-        }
-    }
     let reverb_wet = create_gain(audio_ctx, 0.6, "Reverb wet")?;
-    _ = reverb_in.connect_with_audio_node(&reverb);
-    _ = reverb.connect_with_audio_node(&reverb_wet);
+    let mut slots = Vec::with_capacity(2);
+    for level in [1.0, 0.0] {
+        let convolver =
+            web::ConvolverNode::new(audio_ctx).map_err(|e| node_error("ConvolverNode", &e))?;
+        convolver.set_normalize(true);
+        let fade = create_gain(audio_ctx, level, "Reverb slot")?;
+        _ = reverb_in.connect_with_audio_node(&convolver);
+        _ = convolver.connect_with_audio_node(&fade);
+        _ = fade.connect_with_audio_node(&reverb_wet);
+        slots.push((convolver, fade));
+    }
+    if let Some(ir) = reverb_buffer(audio_ctx, &HALL) {
+        slots[0].0.set_buffer(Some(&ir));
+    }
     _ = reverb_wet.connect_with_audio_node(&master_gain);
 
     // Delay bus with feedback loop and lowpass tone for darkness
     let delay_in = create_gain(audio_ctx, 1.0, "Delay in")?;
     let delay = audio_ctx
         .create_delay_with_max_delay_time(3.0)
-        .map_err(|e| {
-            log::error!("DelayNode error: {:?}", e);
-        })
-        .map_err(|_| ())?;
+        .map_err(|e| node_error("DelayNode", &e))?;
     delay.delay_time().set_value(0.55);
-    let delay_tone = web::BiquadFilterNode::new(audio_ctx)
-        .map_err(|e| {
-            log::error!("BiquadFilterNode error: {:?}", e);
-        })
-        .map_err(|_| ())?;
+    let delay_tone =
+        web::BiquadFilterNode::new(audio_ctx).map_err(|e| node_error("BiquadFilterNode", &e))?;
     delay_tone.set_type(web::BiquadFilterType::Lowpass);
     delay_tone.frequency().set_value(1400.0);
     let delay_feedback = create_gain(audio_ctx, 0.6, "Delay feedback")?;
@@ -144,47 +204,208 @@ pub fn build_fx_buses(audio_ctx: &web::AudioContext) -> Result<FxBuses, ()> {
     _ = delay_tone.connect_with_audio_node(&delay_wet);
     _ = delay_wet.connect_with_audio_node(&master_gain);
 
-    Ok(FxBuses {
+    let buses = FxBuses {
         master_gain,
+        sub_gain,
+        compressor,
+        master_eq,
+        width_direct,
+        width_cross,
+        limiter,
+        volume,
+        click,
         sat_pre,
         sat_wet,
         sat_dry,
         reverb_in,
+        reverb_slots: slots,
+        reverb_active: Cell::new(0),
         reverb_wet,
         delay_in,
         delay_feedback,
         delay_wet,
-    })
+    };
+    apply_output_preset(&buses, &OUTPUT_PRESETS[0]);
+    apply_master_eq(&buses, &MasterEq::FLAT);
+    set_stereo_width(&buses, DEFAULT_STEREO_WIDTH, None);
+    Ok(buses)
+}
+
+fn create_compressor(
+    audio_ctx: &web::BaseAudioContext,
+    label: &str,
+) -> Result<web::DynamicsCompressorNode, String> {
+    web::DynamicsCompressorNode::new(audio_ctx)
+        .map_err(|e| node_error(&format!("{label} DynamicsCompressorNode"), &e))
+}
+
+/// Configure master gain, compressor, sub level and limiter ceiling together
+pub fn apply_output_preset(buses: &FxBuses, preset: &OutputPreset) {
+    buses.master_gain.gain().set_value(preset.master_gain);
+    buses.sub_gain.gain().set_value(preset.sub_level);
+    let c = &buses.compressor;
+    c.threshold().set_value(preset.comp_threshold_db);
+    c.ratio().set_value(preset.comp_ratio);
+    c.knee().set_value(preset.comp_knee_db);
+    c.attack().set_value(preset.comp_attack_sec);
+    c.release().set_value(preset.comp_release_sec);
+    // Hard knee, max ratio and fast attack approximate a brickwall at the ceiling
+    let l = &buses.limiter;
+    l.threshold().set_value(preset.limiter_ceiling_db);
+    l.ratio().set_value(20.0);
+    l.knee().set_value(0.0);
+    l.attack().set_value(0.001);
+    l.release().set_value(0.1);
+    log::info!("[audio] output preset {}", preset.name);
+}
+
+/// Render a reverb preset into a stereo buffer at the context's rate.
+pub fn reverb_buffer(
+    audio_ctx: &web::BaseAudioContext,
+    preset: &ReverbPreset,
+) -> Option<web::AudioBuffer> {
+    let sr = audio_ctx.sample_rate();
+    let channels = preset.impulse(sr);
+    let ir = audio_ctx
+        .create_buffer(2, channels[0].len().max(1) as u32, sr)
+        .ok()?;
+    for (ch, mut data) in channels.into_iter().enumerate() {
+        _ = ir.copy_to_channel(data.as_mut_slice(), ch as i32);
+    }
+    Some(ir)
+}
+
+/// Swap the reverb's impulse response without a click: the new IR loads
+/// into the idle convolver, which then crossfades with the sounding one.
+pub fn set_reverb_impulse(
+    audio_ctx: &web::BaseAudioContext,
+    buses: &FxBuses,
+    ir: &web::AudioBuffer,
+) {
+    const CROSSFADE_SEC: f64 = 0.4;
+    let from = buses.reverb_active.get();
+    let to = 1 - from;
+    let now = audio_ctx.current_time();
+    buses.reverb_slots[to].0.set_buffer(Some(ir));
+    for (slot, target) in [(from, 0.0), (to, 1.0)] {
+        let param = buses.reverb_slots[slot].1.gain();
+        _ = param.cancel_scheduled_values(now);
+        _ = param.set_value_at_time(param.value(), now);
+        _ = param.linear_ramp_to_value_at_time(target, now + CROSSFADE_SEC);
+    }
+    buses.reverb_active.set(to);
+}
+
+/// Retune the master EQ nodes to `eq` (safe to call while playing)
+pub fn apply_master_eq(buses: &FxBuses, eq: &MasterEq) {
+    for (node, band) in buses.master_eq.iter().zip(&eq.bands) {
+        node.set_type(match band.kind {
+            EqBandKind::LowShelf => web::BiquadFilterType::Lowshelf,
+            EqBandKind::Peaking => web::BiquadFilterType::Peaking,
+            EqBandKind::HighShelf => web::BiquadFilterType::Highshelf,
+        });
+        node.frequency().set_value(band.freq_hz);
+        node.gain().set_value(band.gain_db);
+        node.q().set_value(band.q);
+    }
+}
+
+/// Set an oscillator's shape; `waves[i]` plays `Waveform::Wavetable(i)`,
+/// falling back to a sine for tables not (yet) built.
+fn set_oscillator_shape(
+    src: &web::OscillatorNode,
+    waveform: Waveform,
+    waves: &[web::PeriodicWave],
+) {
+    match waveform {
+        Waveform::Sine => src.set_type(web::OscillatorType::Sine),
+        // Waveform::Square => src.set_type(web::OscillatorType::Square),
+        Waveform::Saw => src.set_type(web::OscillatorType::Sawtooth),
+        Waveform::Triangle => src.set_type(web::OscillatorType::Triangle),
+        Waveform::Wavetable(i) => match waves.get(i as usize) {
+            Some(wave) => src.set_periodic_wave(wave),
+            None => src.set_type(web::OscillatorType::Sine),
+        },
+    }
+}
+
+/// Harmonics handed to `createPeriodicWave` per table.
+const PERIODIC_WAVE_HARMONICS: usize = 64;
+
+/// Web Audio objects built from the engine's wavetables and samples, for
+/// the node path (the worklet keeps its own copies).
+#[derive(Default)]
+pub struct SynthAssets {
+    /// `PeriodicWave` per `MusicEngine::wavetables` entry
+    periodic_waves: Vec<web::PeriodicWave>,
+    /// `AudioBuffer` per `MusicEngine::samples` entry
+    buffers: Vec<web::AudioBuffer>,
 }
 
-// Fire a simple one-shot oscillator routed through a voice's gain and sends
+impl SynthAssets {
+    /// Build objects for tables and samples added to the engine since the last call.
+    pub fn sync(&mut self, audio_ctx: &web::BaseAudioContext, engine: &MusicEngine) {
+        for table in engine.wavetables.iter().skip(self.periodic_waves.len()) {
+            let (mut real, mut imag) = table.harmonics(PERIODIC_WAVE_HARMONICS);
+            match audio_ctx.create_periodic_wave(&mut real, &mut imag) {
+                Ok(wave) => self.periodic_waves.push(wave),
+                Err(e) => {
+                    log::error!("[audio] wavetable {} rejected: {:?}", table.name, e);
+                    break;
+                }
+            }
+        }
+        for sample in engine.samples.iter().skip(self.buffers.len()) {
+            let frames = sample.frames();
+            let buffer = audio_ctx
+                .create_buffer(1, frames.len() as u32, sample.sample_rate as f32)
+                .and_then(|b| b.copy_to_channel(frames, 0).map(|_| b));
+            match buffer {
+                Ok(buffer) => self.buffers.push(buffer),
+                Err(e) => {
+                    log::error!("[audio] sample {} rejected: {:?}", sample.name, e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+// Fire a simple one-shot oscillator `delay_sec` from now, through a lowpass
+// at `cutoff_hz`, routed through a voice's gain and sends
 pub fn trigger_one_shot(
     audio_ctx: &web::AudioContext,
     waveform: Waveform,
     frequency_hz: f32,
     velocity: f32,
+    cutoff_hz: f32,
     duration_sec: f64,
+    delay_sec: f64,
     voice_gain: &web::GainNode,
     delay_send: &web::GainNode,
     reverb_send: &web::GainNode,
 ) {
     if let Ok(src) = web::OscillatorNode::new(audio_ctx) {
-        match waveform {
-            Waveform::Sine => src.set_type(web::OscillatorType::Sine),
-            // Waveform::Square => src.set_type(web::OscillatorType::Square),
-            Waveform::Saw => src.set_type(web::OscillatorType::Sawtooth),
-            Waveform::Triangle => src.set_type(web::OscillatorType::Triangle),
-        }
+        // One-shots don't carry the voice's tables; wavetable voices click as sines
+        set_oscillator_shape(&src, waveform, &[]);
         src.frequency().set_value(frequency_hz);
         if let Ok(g) = web::GainNode::new(audio_ctx) {
             g.gain().set_value(0.0);
             let now = audio_ctx.current_time();
-            let t0 = now + 0.005;
+            let t0 = now + 0.005 + delay_sec.max(0.0);
             _ = g.gain().linear_ramp_to_value_at_time(velocity, t0 + 0.02);
             _ = g
                 .gain()
                 .linear_ramp_to_value_at_time(0.0, t0 + duration_sec);
-            _ = src.connect_with_audio_node(&g);
+            match web::BiquadFilterNode::new(audio_ctx) {
+                Ok(lowpass) => {
+                    lowpass.set_type(web::BiquadFilterType::Lowpass);
+                    lowpass.frequency().set_value(cutoff_hz);
+                    _ = src.connect_with_audio_node(&lowpass);
+                    _ = lowpass.connect_with_audio_node(&g);
+                }
+                Err(_) => _ = src.connect_with_audio_node(&g),
+            }
             _ = g.connect_with_audio_node(voice_gain);
             _ = g.connect_with_audio_node(delay_send);
             _ = g.connect_with_audio_node(reverb_send);
@@ -194,6 +415,946 @@ pub fn trigger_one_shot(
     }
 }
 
+/// A scheduled note as the frontend can reach it again, e.g. to steal it.
+pub enum NoteHandle {
+    /// Its own sources and envelope gain (main-thread path)
+    Nodes(Vec<web::AudioScheduledSourceNode>, web::GainNode),
+    /// Note id inside the `WorkletSynth`
+    Worklet(u32),
+}
+
+/// Schedule a note as its voice's unison sources (each panned within the
+/// stereo spread), an optional filter and the envelope gain feeding the
+/// voice's fade stage.
+///
+/// Sources are oscillators (sharing an FM modulator if the voice has one)
+/// or, for a sampler voice, buffer sources pitched by playback rate.
+pub fn start_note_nodes(
+    audio_ctx: &web::BaseAudioContext,
+    ev: &NoteEvent,
+    config: &VoiceConfig,
+    assets: &SynthAssets,
+    t0: f64,
+    voice_fade: &web::GainNode,
+) -> Option<(Vec<web::AudioScheduledSourceNode>, web::GainNode)> {
+    let envelope = &config.envelope;
+    let gain = web::GainNode::new(audio_ctx).ok()?;
+    gain.gain().set_value(0.0);
+    let peak = ev.velocity * config.unison.layer_gain();
+    schedule_envelope(&gain.gain(), envelope, peak, t0, ev.duration_sec);
+    let filter = config
+        .filter
+        .and_then(|f| start_note_filter(audio_ctx, &f, ev, envelope, t0));
+    let stack_in: &web::AudioNode = match &filter {
+        Some(node) => {
+            _ = node.connect_with_audio_node(&gain);
+            node
+        }
+        None => &gain,
+    };
+    let stop = t0 + (ev.duration_sec + envelope.release_sec) as f64;
+    let sampler = config
+        .sampler
+        .and_then(|s| Some((s, assets.buffers.get(s.sample as usize)?)));
+    let mut sources: Vec<web::AudioScheduledSourceNode> = Vec::new();
+    let mut oscillators = Vec::new();
+    for layer in config.unison.layers() {
+        let src: web::AudioScheduledSourceNode = match sampler {
+            Some((sampler, buffer)) => {
+                let src = web::AudioBufferSourceNode::new(audio_ctx).ok()?;
+                src.set_buffer(Some(buffer));
+                src.playback_rate()
+                    .set_value(sampler.playback_rate(ev.frequency_hz));
+                src.detune().set_value(layer.detune_cents);
+                if let Some(l) = sampler.active_loop(buffer.duration() as f32) {
+                    src.set_loop(true);
+                    src.set_loop_start(l.start_sec as f64);
+                    src.set_loop_end(l.end_sec as f64);
+                }
+                connect_layer(audio_ctx, &src, layer.pan, stack_in);
+                _ = src.start_with_when_and_grain_offset(t0, sampler.start_sec as f64);
+                src.into()
+            }
+            None => {
+                let src = web::OscillatorNode::new(audio_ctx).ok()?;
+                set_oscillator_shape(&src, config.waveform, &assets.periodic_waves);
+                src.frequency().set_value(ev.frequency_hz);
+                src.detune().set_value(layer.detune_cents);
+                connect_layer(audio_ctx, &src, layer.pan, stack_in);
+                _ = src.start_with_when(t0);
+                oscillators.push(src.clone());
+                src.into()
+            }
+        };
+        _ = src.stop_with_when(stop);
+        sources.push(src);
+    }
+    if let (Some(fm), false) = (&config.fm, oscillators.is_empty()) {
+        // Frequency modulation with deviation index × modulator Hz, shared by all layers
+        let modulator = web::OscillatorNode::new(audio_ctx).ok()?;
+        let mod_hz = fm.modulator_hz(ev.frequency_hz);
+        modulator.frequency().set_value(mod_hz);
+        let depth = web::GainNode::new(audio_ctx).ok()?;
+        depth.gain().set_value(0.0);
+        let peak_hz = fm.index * mod_hz;
+        schedule_envelope(
+            &depth.gain(),
+            &fm.index_envelope,
+            peak_hz,
+            t0,
+            ev.duration_sec,
+        );
+        _ = modulator.connect_with_audio_node(&depth);
+        for src in &oscillators {
+            _ = depth.connect_with_audio_param(&src.frequency());
+        }
+        _ = modulator.start_with_when(t0);
+        _ = modulator.stop_with_when(stop);
+        sources.push(modulator.into());
+    }
+    _ = gain.connect_with_audio_node(voice_fade);
+    Some((sources, gain))
+}
+
+/// Route one unison layer into the note's stack, through a stereo panner if off-center.
+fn connect_layer(
+    audio_ctx: &web::BaseAudioContext,
+    src: &web::AudioNode,
+    pan: f32,
+    stack_in: &web::AudioNode,
+) {
+    match web::StereoPannerNode::new(audio_ctx) {
+        Ok(panner) if pan != 0.0 => {
+            panner.pan().set_value(pan);
+            _ = src.connect_with_audio_node(&panner);
+            _ = panner.connect_with_audio_node(stack_in);
+        }
+        _ => _ = src.connect_with_audio_node(stack_in),
+    }
+}
+
+/// A note's filter; the cutoff follows the amplitude envelope through the
+/// node's `detune` (cents), which scales the frequency exponentially just
+/// like `VoiceFilter::cutoff_at`.
+fn start_note_filter(
+    audio_ctx: &web::BaseAudioContext,
+    filter: &VoiceFilter,
+    ev: &NoteEvent,
+    envelope: &Adsr,
+    t0: f64,
+) -> Option<web::BiquadFilterNode> {
+    let node = web::BiquadFilterNode::new(audio_ctx).ok()?;
+    node.set_type(match filter.mode {
+        FilterMode::Lowpass => web::BiquadFilterType::Lowpass,
+        FilterMode::Bandpass => web::BiquadFilterType::Bandpass,
+    });
+    node.frequency()
+        .set_value(filter.base_cutoff(ev.frequency_hz));
+    node.q().set_value(filter.resonance);
+    if filter.env_octaves != 0.0 {
+        let peak_cents = filter.env_octaves * 1200.0;
+        schedule_envelope(&node.detune(), envelope, peak_cents, t0, ev.duration_sec);
+    }
+    Some(node)
+}
+
+/// Automate `param` through `envelope` for a note gated for `gate_sec` from `t0`.
+///
+/// Linear curves become linear ramps; exponential decay and release become
+/// `setTargetAtTime` with the same time constant `Adsr::level_at` assumes.
+fn schedule_envelope(
+    param: &web::AudioParam,
+    envelope: &Adsr,
+    velocity: f32,
+    t0: f64,
+    gate_sec: f32,
+) {
+    let attack_end = envelope.attack_sec.min(gate_sec);
+    let gate_level = velocity * envelope.gate_level(gate_sec);
+    let at = |sec: f32| t0 + sec as f64;
+    _ = param.set_value_at_time(0.0, t0);
+    _ = param
+        .linear_ramp_to_value_at_time(velocity * envelope.gate_level(attack_end), at(attack_end));
+    match envelope.curve {
+        EnvelopeCurve::Linear => {
+            if gate_sec > envelope.attack_sec {
+                let decay_end = (envelope.attack_sec + envelope.decay_sec).min(gate_sec);
+                let decay_level = velocity * envelope.gate_level(decay_end);
+                _ = param.linear_ramp_to_value_at_time(decay_level, at(decay_end));
+                // Hold the sustain until the gate closes
+                _ = param.linear_ramp_to_value_at_time(gate_level, at(gate_sec));
+            }
+            _ = param.linear_ramp_to_value_at_time(0.0, at(gate_sec + envelope.release_sec));
+        }
+        EnvelopeCurve::Exponential => {
+            if gate_sec > envelope.attack_sec {
+                _ = param.set_target_at_time(
+                    velocity * envelope.sustain.clamp(0.0, 1.0),
+                    at(envelope.attack_sec),
+                    Adsr::time_constant(envelope.decay_sec) as f64,
+                );
+            }
+            _ = param.set_target_at_time(
+                0.0,
+                at(gate_sec),
+                Adsr::time_constant(envelope.release_sec) as f64,
+            );
+        }
+    }
+}
+
+/// Cut a note stolen by the voice allocator short with a quick fade at `at_sec`.
+pub fn release_stolen_note(
+    handle: NoteHandle,
+    synth: Option<&mut WorkletSynth>,
+    at_sec: f64,
+    fade_sec: f64,
+) {
+    match handle {
+        NoteHandle::Nodes(sources, gain) => {
+            _ = gain.gain().cancel_scheduled_values(at_sec);
+            _ = gain.gain().set_target_at_time(0.0, at_sec, fade_sec / 3.0);
+            // A later stop() replaces the one scheduled at note start
+            for src in sources {
+                _ = src.stop_with_when(at_sec + fade_sec);
+            }
+        }
+        NoteHandle::Worklet(id) => {
+            if let Some(synth) = synth {
+                synth.release(id, at_sec, fade_sec);
+            }
+        }
+    }
+}
+
+// Record layout shared with `synth-worklet.js` (keep in sync)
+const WORKLET_RECORD_LEN: usize = 31;
+const WORKLET_OP_NOTE: f64 = 0.0;
+const WORKLET_OP_RELEASE: f64 = 1.0;
+const WORKLET_WAVE_TABLE_BASE: f64 = 3.0;
+
+fn curve_code(curve: EnvelopeCurve) -> f64 {
+    match curve {
+        EnvelopeCurve::Linear => 0.0,
+        EnvelopeCurve::Exponential => 1.0,
+    }
+}
+
+/// All voices rendered by one AudioWorklet processor (`synth-worklet.js`).
+///
+/// The main thread only queues note and release records and posts them once
+/// per frame; output N of the node feeds voice N's fade stage.
+pub struct WorkletSynth {
+    node: web::AudioWorkletNode,
+    next_id: u32,
+    pending: Vec<f64>,
+    /// Number of engine wavetables and samples already posted to the processor
+    tables_sent: usize,
+    samples_sent: usize,
+}
+
+impl WorkletSynth {
+    /// Load the worklet module and wire one stereo output per voice.
+    pub async fn create(
+        audio_ctx: &web::AudioContext,
+        voice_fades: &[web::GainNode],
+    ) -> Result<WorkletSynth, JsValue> {
+        let worklet = audio_ctx.audio_worklet()?;
+        JsFuture::from(worklet.add_module("synth-worklet.js")?).await?;
+        let voices = voice_fades.len() as u32;
+        let opts = web::AudioWorkletNodeOptions::new();
+        opts.set_number_of_inputs(0);
+        opts.set_number_of_outputs(voices);
+        let channels = js_sys::Array::new();
+        // Stereo, for the unison spread
+        for _ in 0..voices {
+            channels.push(&JsValue::from(2));
+        }
+        opts.set_output_channel_count(&channels);
+        let processor_opts = js_sys::Object::new();
+        js_sys::Reflect::set(&processor_opts, &"voices".into(), &voices.into())?;
+        opts.set_processor_options(Some(&processor_opts));
+        let node = web::AudioWorkletNode::new_with_options(audio_ctx, "geno-synth", &opts)?;
+        for (i, fade) in voice_fades.iter().enumerate() {
+            node.connect_with_audio_node_and_output(fade, i as u32)?;
+        }
+        Ok(WorkletSynth {
+            node,
+            next_id: 0,
+            pending: Vec::new(),
+            tables_sent: 0,
+            samples_sent: 0,
+        })
+    }
+
+    /// Queue a note starting at `t0` (one record per unison layer, all
+    /// sharing the returned id for `release`).
+    pub fn note(&mut self, ev: &NoteEvent, config: &VoiceConfig, t0: f64) -> u32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let wave = match config.waveform {
+            Waveform::Sine => 0.0,
+            Waveform::Saw => 1.0,
+            Waveform::Triangle => 2.0,
+            Waveform::Wavetable(i) => WORKLET_WAVE_TABLE_BASE + i as f64,
+        };
+        let envelope = &config.envelope;
+        let filter = match &config.filter {
+            Some(f) => [
+                match f.mode {
+                    FilterMode::Lowpass => 0.0,
+                    FilterMode::Bandpass => 1.0,
+                },
+                f.base_cutoff(ev.frequency_hz) as f64,
+                f.resonance as f64,
+                f.env_octaves as f64,
+            ],
+            None => [-1.0, 0.0, 0.0, 0.0],
+        };
+        // Index 0 turns FM off
+        let fm = match &config.fm {
+            Some(fm) => {
+                let env = &fm.index_envelope;
+                [
+                    fm.ratio as f64,
+                    fm.index as f64,
+                    env.attack_sec as f64,
+                    env.decay_sec as f64,
+                    env.sustain as f64,
+                    env.release_sec as f64,
+                    curve_code(env.curve),
+                ]
+            }
+            None => [0.0; 7],
+        };
+        let velocity = ev.velocity * config.unison.layer_gain();
+        for layer in config.unison.layers() {
+            let frequency = ev.frequency_hz * (layer.detune_cents / 1200.0).exp2();
+            self.pending.extend_from_slice(&[
+                WORKLET_OP_NOTE,
+                id as f64,
+                ev.voice_index as f64,
+                wave,
+                frequency as f64,
+                velocity as f64,
+                t0,
+                ev.duration_sec as f64,
+                envelope.attack_sec as f64,
+                envelope.decay_sec as f64,
+                envelope.sustain as f64,
+                envelope.release_sec as f64,
+                curve_code(envelope.curve),
+            ]);
+            self.pending.extend_from_slice(&filter);
+            self.pending
+                .extend_from_slice(&[layer.pan as f64, layer.phase as f64]);
+            self.pending.extend_from_slice(&fm);
+            // Sample index -1 plays the oscillator
+            let sampler = match &config.sampler {
+                Some(s) => {
+                    let l = s.loop_region;
+                    [
+                        s.sample as f64,
+                        s.playback_rate(frequency) as f64,
+                        s.start_sec as f64,
+                        l.map_or(0.0, |l| l.start_sec as f64),
+                        l.map_or(0.0, |l| l.end_sec as f64),
+                    ]
+                }
+                None => [-1.0, 0.0, 0.0, 0.0, 0.0],
+            };
+            self.pending.extend_from_slice(&sampler);
+        }
+        id
+    }
+
+    /// Queue a fade-out of note `id` starting at `at_sec`.
+    pub fn release(&mut self, id: u32, at_sec: f64, fade_sec: f64) {
+        let mut record = [0.0; WORKLET_RECORD_LEN];
+        record[..4].copy_from_slice(&[WORKLET_OP_RELEASE, id as f64, at_sec, fade_sec]);
+        self.pending.extend_from_slice(&record);
+    }
+
+    /// Post tables and samples added to the engine since the last call
+    /// (before notes use them).
+    pub fn sync_assets(&mut self, engine: &MusicEngine) {
+        let Ok(port) = self.node.port() else {
+            return;
+        };
+        let post = |fields: &[(&str, JsValue)]| {
+            let msg = js_sys::Object::new();
+            for (key, value) in fields {
+                _ = js_sys::Reflect::set(&msg, &(*key).into(), value);
+            }
+            _ = port.post_message(&msg);
+        };
+        for (i, table) in engine.wavetables.iter().enumerate().skip(self.tables_sent) {
+            post(&[
+                ("wavetable", (i as u32).into()),
+                (
+                    "samples",
+                    js_sys::Float32Array::from(table.samples()).into(),
+                ),
+            ]);
+        }
+        for (i, sample) in engine.samples.iter().enumerate().skip(self.samples_sent) {
+            post(&[
+                ("sample", (i as u32).into()),
+                ("frames", js_sys::Float32Array::from(sample.frames()).into()),
+                ("rate", sample.sample_rate.into()),
+            ]);
+        }
+        self.tables_sent = self.tables_sent.max(engine.wavetables.len());
+        self.samples_sent = self.samples_sent.max(engine.samples.len());
+    }
+
+    /// Post everything queued this frame to the processor in one message.
+    pub fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let batch = js_sys::Float64Array::from(&self.pending[..]);
+        if let Ok(port) = self.node.port() {
+            _ = port.post_message(&batch);
+        }
+        self.pending.clear();
+    }
+}
+
+/// Sample-accurate capture of the master bus into a `Recording` by an
+/// AudioWorklet processor (`recorder-worklet.js`).
+///
+/// Start and stop are audio-clock times, so a take covers exactly that
+/// window however the render quanta fall.
+pub struct PcmRecorder {
+    node: web::AudioWorkletNode,
+    sample_rate: u32,
+    take: Rc<RefCell<Option<Recording>>>,
+}
+
+impl PcmRecorder {
+    /// Load the worklet module and tap `source`; `on_done` receives each
+    /// finished take.
+    pub async fn create(
+        audio_ctx: &web::AudioContext,
+        source: &web::AudioNode,
+        mut on_done: impl FnMut(Recording) + 'static,
+    ) -> Result<PcmRecorder, JsValue> {
+        let worklet = audio_ctx.audio_worklet()?;
+        JsFuture::from(worklet.add_module("recorder-worklet.js")?).await?;
+        let opts = web::AudioWorkletNodeOptions::new();
+        opts.set_number_of_inputs(1);
+        opts.set_number_of_outputs(1);
+        opts.set_channel_count(RECORD_CHANNELS as u32);
+        opts.set_channel_count_mode(web::ChannelCountMode::Explicit);
+        let node = web::AudioWorkletNode::new_with_options(audio_ctx, "geno-recorder", &opts)?;
+        source.connect_with_audio_node(&node)?;
+        // The output is silent; connecting it keeps the processor pulled
+        node.connect_with_audio_node(&audio_ctx.destination())?;
+
+        let take: Rc<RefCell<Option<Recording>>> = Rc::new(RefCell::new(None));
+        let port = node.port()?;
+        let pending = take.clone();
+        let onmessage =
+            wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::MessageEvent| {
+                let data = ev.data();
+                let get = |key: &str| js_sys::Reflect::get(&data, &key.into()).ok();
+                if get("done").and_then(|v| v.as_bool()) == Some(true) {
+                    if let Some(recording) = pending.borrow_mut().take() {
+                        on_done(recording);
+                    }
+                    return;
+                }
+                let (Some(frame), Some(left), Some(right)) = (
+                    get("frame").and_then(|v| v.as_f64()),
+                    get("left"),
+                    get("right"),
+                ) else {
+                    return;
+                };
+                if let Some(recording) = pending.borrow_mut().as_mut() {
+                    let left = js_sys::Float32Array::from(left).to_vec();
+                    let right = js_sys::Float32Array::from(right).to_vec();
+                    recording.push_block(frame as u64, &left, &right);
+                }
+            }) as Box<dyn FnMut(_)>);
+        port.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        onmessage.forget();
+
+        Ok(PcmRecorder {
+            node,
+            sample_rate: audio_ctx.sample_rate() as u32,
+            take,
+        })
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.take.borrow().is_some()
+    }
+
+    /// Begin a take at audio-clock time `at_sec` (ignored while recording).
+    pub fn start(&self, at_sec: f64) {
+        if self.is_recording() {
+            return;
+        }
+        let frame = frame_at(at_sec, self.sample_rate);
+        *self.take.borrow_mut() = Some(Recording::new(self.sample_rate, frame));
+        self.post("start", frame);
+    }
+
+    /// End the take at audio-clock time `at_sec`; it is handed to `on_done`
+    /// once the processor has rendered that far.
+    pub fn stop(&self, at_sec: f64) {
+        let frame = frame_at(at_sec, self.sample_rate);
+        match self.take.borrow_mut().as_mut() {
+            Some(recording) => recording.stop_at(frame),
+            None => return,
+        }
+        self.post("stop", frame);
+    }
+
+    fn post(&self, key: &str, frame: u64) {
+        let msg = js_sys::Object::new();
+        _ = js_sys::Reflect::set(&msg, &key.into(), &(frame as f64).into());
+        if let Ok(port) = self.node.port() {
+            _ = port.post_message(&msg);
+        }
+    }
+}
+
+/// Set a continuously modulated param: glide from `now` (see
+/// `PARAM_SMOOTH_TAU_SEC`) when given, otherwise jump (for setup before
+/// anything sounds).
+pub fn set_param(param: &web::AudioParam, value: f32, now: Option<f64>) {
+    match now {
+        Some(now) => _ = param.set_target_at_time(value, now, PARAM_SMOOTH_TAU_SEC as f64),
+        None => param.set_value(value),
+    }
+}
+
+/// Set the global FX bus levels from swirl energy and pointer position
+/// (see `fx_levels`), gliding from `now` if given; returns the levels set.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_global_fx_swirl(
+    reverb_wet: &web::GainNode,
+    delay_wet: &web::GainNode,
+    delay_feedback: &web::GainNode,
+    sat_pre: &web::GainNode,
+    sat_wet: &web::GainNode,
+    sat_dry: &web::GainNode,
+    swirl_energy: f32,
+    uv: [f32; 2],
+    now: Option<f64>,
+) -> FxLevels {
+    let fx = fx_levels(swirl_energy, uv);
+    set_param(&reverb_wet.gain(), fx.reverb_wet, now);
+    set_param(&delay_wet.gain(), fx.delay_wet, now);
+    set_param(&delay_feedback.gain(), fx.delay_feedback, now);
+    set_param(&sat_pre.gain(), fx.sat_drive, now);
+    set_param(&sat_wet.gain(), fx.sat_wet, now);
+    set_param(&sat_dry.gain(), fx.sat_dry, now);
+    fx
+}
+
+/// Set the master's stereo width (0 = mono, 1 = as mixed, 2 = side doubled),
+/// gliding from `now` if given.
+pub fn set_stereo_width(buses: &FxBuses, width: f32, now: Option<f64>) {
+    let (direct, cross) = width_gains(width);
+    for (d, c) in buses.width_direct.iter().zip(&buses.width_cross) {
+        set_param(&d.gain(), direct, now);
+        set_param(&c.gain(), cross, now);
+    }
+}
+
+/// Place the listener at the camera, facing its target.
+pub fn update_listener_to_camera(listener: &web::AudioListener, cam_eye: Vec3, cam_target: Vec3) {
+    let fwd = (cam_target - cam_eye).normalize();
+    listener.set_position(cam_eye.x as f64, cam_eye.y as f64, cam_eye.z as f64);
+    _ = listener.set_orientation(fwd.x as f64, fwd.y as f64, fwd.z as f64, 0.0, 1.0, 0.0);
+}
+
+/// Copy the live output stage (gain staging, EQ and the sounding reverb
+/// impulse) onto freshly built buses, so an offline render sounds the same.
+fn copy_bus_settings(from: &FxBuses, to: &FxBuses) {
+    let copy = |a: &web::AudioParam, b: &web::AudioParam| b.set_value(a.value());
+    copy(&from.master_gain.gain(), &to.master_gain.gain());
+    copy(&from.sub_gain.gain(), &to.sub_gain.gain());
+    for (a, b) in [
+        (&from.compressor, &to.compressor),
+        (&from.limiter, &to.limiter),
+    ] {
+        copy(&a.threshold(), &b.threshold());
+        copy(&a.ratio(), &b.ratio());
+        copy(&a.knee(), &b.knee());
+        copy(&a.attack(), &b.attack());
+        copy(&a.release(), &b.release());
+    }
+    for (a, b) in from.width_direct.iter().zip(&to.width_direct) {
+        copy(&a.gain(), &b.gain());
+    }
+    for (a, b) in from.width_cross.iter().zip(&to.width_cross) {
+        copy(&a.gain(), &b.gain());
+    }
+    for (a, b) in from.master_eq.iter().zip(&to.master_eq) {
+        b.set_type(a.type_());
+        copy(&a.frequency(), &b.frequency());
+        copy(&a.gain(), &b.gain());
+        copy(&a.q(), &b.q());
+    }
+    let ir = from.reverb_slots[from.reverb_active.get()].0.buffer();
+    to.reverb_slots[to.reverb_active.get()]
+        .0
+        .set_buffer(ir.as_ref());
+}
+
+/// Render `seconds` of `engine` faster than real time as interleaved stereo
+/// at `sample_rate`, through the same graph as live playback (FX buses with
+/// `live`'s settings, per-voice routing and the node synth) built on an
+/// `OfflineAudioContext`.
+///
+/// Notes come from `schedule_piece`, so a given engine state always bounces
+/// to the same track. Voices stay where they are, with the FX at their
+/// resting levels (no swirl). The engine is advanced, so pass a clone.
+pub async fn bounce_stereo(
+    engine: &mut MusicEngine,
+    live: &FxBuses,
+    seconds: f64,
+    sample_rate: f32,
+) -> Result<Vec<f32>, JsValue> {
+    let frames = (seconds * sample_rate as f64).ceil() as u32;
+    let ctx = web::OfflineAudioContext::new_with_number_of_channels_and_length_and_sample_rate(
+        2,
+        frames,
+        sample_rate,
+    )?;
+    let buses = build_fx_buses(&ctx).map_err(|_| JsValue::from_str("bounce: FX buses"))?;
+    copy_bus_settings(live, &buses);
+    apply_global_fx_swirl(
+        &buses.reverb_wet,
+        &buses.delay_wet,
+        &buses.delay_feedback,
+        &buses.sat_pre,
+        &buses.sat_wet,
+        &buses.sat_dry,
+        0.0,
+        [0.5, 0.5],
+        None,
+    );
+    update_listener_to_camera(&ctx.listener(), Vec3::new(0.0, 0.0, CAMERA_Z), Vec3::ZERO);
+
+    let positions: Vec<Vec3> = engine.voices.iter().map(|v| v.position).collect();
+    let spatial: Vec<SpatialHints> = engine.configs.iter().map(|c| c.spatial).collect();
+    let routing = wire_voices(
+        &ctx,
+        &positions,
+        &spatial,
+        &buses.master_gain,
+        &buses.delay_in,
+        &buses.reverb_in,
+    )
+    .map_err(|_| JsValue::from_str("bounce: voice routing"))?;
+    let mut levels = Vec::with_capacity(engine.voices.len());
+    for (i, (voice, config)) in engine.voices.iter().zip(&engine.configs).enumerate() {
+        let mix = voice_mix(voice.position, 0.0, config.sends);
+        levels.push(mix.level);
+        routing.voice_gains[i].gain().set_value(mix.level);
+        routing.delay_sends[i].gain().set_value(mix.delay_send);
+        routing.reverb_sends[i].gain().set_value(mix.reverb_send);
+        routing.voice_fades[i]
+            .gain()
+            .set_value(voice.fade.target_gain);
+    }
+
+    let mut assets = SynthAssets::default();
+    assets.sync(&ctx, engine);
+    let sidechain = engine.sidechain();
+    let mut allocator: VoiceAllocator<NoteHandle> =
+        VoiceAllocator::new(engine.configs.iter().map(|c| c.polyphony).collect());
+    let step_sec = BOUNCE_STEP_FRAMES / sample_rate as f64;
+    let mut sounding = 0;
+    for ScheduledNote {
+        event: ev, config, ..
+    } in schedule_piece(engine, seconds, step_sec)
+    {
+        let t0 = ev.start_time_sec;
+        allocator.set_limit(ev.voice_index, config.polyphony);
+        if let Some(sc) = sidechain.filter(|sc| sc.trigger_voice == ev.voice_index) {
+            schedule_duck(&routing.voice_ducks, &sc, t0);
+        }
+        let fade = &routing.voice_fades[ev.voice_index];
+        let Some((sources, gain)) = start_note_nodes(&ctx, &ev, &config, &assets, t0, fade) else {
+            continue;
+        };
+        let t_end = t0 + (ev.duration_sec + config.envelope.release_sec) as f64;
+        let handle = NoteHandle::Nodes(sources, gain);
+        for stolen in allocator.allocate(ev.voice_index, t0, t_end, ev.velocity, handle) {
+            release_stolen_note(stolen, None, t0, NOTE_STEAL_FADE_SEC);
+        }
+        // Same polyphony compensation as live, as automation on the voice gains
+        let now_sounding = allocator.sounding(t0);
+        if now_sounding != sounding {
+            sounding = now_sounding;
+            for (gain, level) in routing.voice_gains.iter().zip(&levels) {
+                let _ = gain.gain().set_target_at_time(
+                    level * auto_gain(sounding),
+                    t0,
+                    AUTO_GAIN_TIME_CONSTANT_SEC as f64,
+                );
+            }
+        }
+    }
+
+    let rendered: web::AudioBuffer = JsFuture::from(ctx.start_rendering()?).await?.into();
+    let (left, right) = (rendered.get_channel_data(0)?, rendered.get_channel_data(1)?);
+    Ok(left
+        .into_iter()
+        .zip(right)
+        .flat_map(|(l, r)| [l, r])
+        .collect())
+}
+
+/// Decode an audio file into a buffer at the context's rate.
+pub async fn decode_audio(
+    audio_ctx: &web::AudioContext,
+    bytes: &[u8],
+) -> Result<web::AudioBuffer, JsValue> {
+    let data = js_sys::Uint8Array::from(bytes);
+    Ok(JsFuture::from(audio_ctx.decode_audio_data(&data.buffer())?)
+        .await?
+        .into())
+}
+
+/// Decode an audio file and return its first channel (at the context's rate).
+pub async fn decode_first_channel(
+    audio_ctx: &web::AudioContext,
+    bytes: &[u8],
+) -> Result<Vec<f32>, JsValue> {
+    decode_audio(audio_ctx, bytes).await?.get_channel_data(0)
+}
+
+/// Ramp each voice's fade stage whenever the engine mutes or unmutes it.
+pub fn subscribe_voice_fades(
+    bus: &mut EventBus,
+    engine: Rc<RefCell<MusicEngine>>,
+    audio_ctx: web::AudioContext,
+    voice_fades: Rc<Vec<web::GainNode>>,
+) {
+    bus.subscribe(Box::new(move |ev| {
+        if let EngineEvent::MuteChanged { voice, .. } = ev {
+            let (Some(fade), Some(node)) = (
+                engine.borrow().voices.get(*voice).map(|v| v.fade),
+                voice_fades.get(*voice),
+            ) else {
+                return;
+            };
+            // Start from where the ramp is now; the engine clock may be a frame behind
+            let now = audio_ctx.current_time();
+            let end = (fade.start_sec + fade.ramp_sec as f64).max(now);
+            let param = node.gain();
+            _ = param.cancel_scheduled_values(now);
+            _ = param.set_value_at_time(fade.gain_at(now), now);
+            _ = param.linear_ramp_to_value_at_time(fade.target_gain, end);
+        }
+    }));
+}
+
+/// Play a metronome click into `bus`: a short sine blip, higher on the
+/// accented first beat of the bar.
+pub fn schedule_click(audio_ctx: &web::BaseAudioContext, bus: &web::GainNode, click: &Click) {
+    let (Ok(osc), Ok(env)) = (
+        web::OscillatorNode::new(audio_ctx),
+        web::GainNode::new(audio_ctx),
+    ) else {
+        return;
+    };
+    let t0 = click.at_sec.max(audio_ctx.current_time());
+    osc.frequency().set_value(click.frequency_hz());
+    let gain = env.gain();
+    gain.set_value(0.0);
+    _ = gain.set_value_at_time(0.0, t0);
+    _ = gain.linear_ramp_to_value_at_time(1.0, t0 + 0.002);
+    _ = gain.set_target_at_time(0.0, t0 + 0.002, CLICK_SEC as f64 / 4.0);
+    _ = osc.connect_with_audio_node(&env);
+    _ = env.connect_with_audio_node(bus);
+    _ = osc.start_with_when(t0);
+    _ = osc.stop_with_when(t0 + CLICK_SEC as f64 * 2.0);
+}
+
+/// Follow the engine's metronome level on the click bus.
+pub fn subscribe_metronome(bus: &mut EventBus, click: web::GainNode) {
+    bus.subscribe(Box::new(move |ev| {
+        if let EngineEvent::MetronomeChanged(m) = ev {
+            click.gain().set_value(m.level);
+        }
+    }));
+}
+
+/// Duck every voice but the trigger for a trigger note starting at `t0`:
+/// down over the attack, then back to unity over the release.
+pub fn schedule_duck(voice_ducks: &[web::GainNode], sidechain: &Sidechain, t0: f64) {
+    let attack = Adsr::time_constant(sidechain.attack_sec) as f64;
+    let release = Adsr::time_constant(sidechain.release_sec) as f64;
+    for (voice, duck) in voice_ducks.iter().enumerate() {
+        if sidechain.ducks(voice) {
+            let param = duck.gain();
+            _ = param.set_target_at_time(sidechain.ducked_gain(), t0, attack);
+            _ = param.set_target_at_time(1.0, t0 + sidechain.attack_sec as f64, release);
+        }
+    }
+}
+
+/// Drop pending duck ramps and return to unity when ducking is switched off.
+pub fn subscribe_sidechain(
+    bus: &mut EventBus,
+    audio_ctx: web::AudioContext,
+    voice_ducks: Rc<Vec<web::GainNode>>,
+) {
+    bus.subscribe(Box::new(move |ev| {
+        if let EngineEvent::SidechainChanged(None) = ev {
+            let now = audio_ctx.current_time();
+            for duck in voice_ducks.iter() {
+                let param = duck.gain();
+                _ = param.cancel_scheduled_values(now);
+                _ = param.set_target_at_time(1.0, now, 0.05);
+            }
+        }
+    }));
+}
+
+/// Open the audio context, asking for `request`'s sample rate and buffer size.
+///
+/// Browsers treat both as hints: `sampleRate` may be refused on some devices
+/// (the context is then opened at the device rate) and the buffer is rounded
+/// to what the system supports, so read the actual values back afterwards.
+pub fn create_context(request: &DeviceRequest) -> Result<web::AudioContext, JsValue> {
+    let opts = web::AudioContextOptions::new();
+    if let Some(rate) = request.sample_rate {
+        opts.set_sample_rate(rate);
+    }
+    if let Some(buffer) = request.buffer {
+        let hint = match buffer.category() {
+            Some(category) => JsValue::from_str(category),
+            None => {
+                // A frame count becomes seconds at the rate the context will run at
+                let rate = request.sample_rate.unwrap_or(48_000.0);
+                JsValue::from_f64(buffer.seconds(rate).unwrap_or(0.0))
+            }
+        };
+        opts.set_latency_hint(&hint);
+    }
+    match web::AudioContext::new_with_context_options(&opts) {
+        Ok(ctx) => Ok(ctx),
+        Err(e) if request.sample_rate.is_some() => {
+            log::warn!("[audio] requested sample rate refused: {:?}", e);
+            create_context(&DeviceRequest {
+                sample_rate: None,
+                ..*request
+            })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// `(id, label)` of every audio output the browser lists. Labels stay empty
+/// until the page has had a media permission, so unnamed ones are numbered.
+pub async fn output_devices() -> Result<Vec<(String, String)>, JsValue> {
+    let devices = web::window()
+        .ok_or_else(|| JsValue::from_str("no window"))?
+        .navigator()
+        .media_devices()?;
+    let list: js_sys::Array = JsFuture::from(devices.enumerate_devices()?)
+        .await?
+        .unchecked_into();
+    let mut outputs = Vec::new();
+    for info in list.iter() {
+        let info: web::MediaDeviceInfo = info.unchecked_into();
+        // The system default is offered separately
+        if info.kind() != web::MediaDeviceKind::Audiooutput || info.device_id() == "default" {
+            continue;
+        }
+        let label = match info.label() {
+            l if l.is_empty() => format!("Output {}", outputs.len() + 1),
+            l => l,
+        };
+        outputs.push((info.device_id(), label));
+    }
+    Ok(outputs)
+}
+
+/// Move the context's output to device `id` (`""` = system default) while
+/// it keeps running.
+///
+/// `setSinkId` is not in web-sys's stable bindings, so call it reflectively;
+/// browsers without it report an error and stay on the default device.
+pub async fn set_output_device(audio_ctx: &web::AudioContext, id: &str) -> Result<(), JsValue> {
+    let set_sink_id: js_sys::Function = js_sys::Reflect::get(audio_ctx, &"setSinkId".into())?
+        .dyn_into()
+        .map_err(|_| JsValue::from_str("output device selection not supported"))?;
+    let promise: js_sys::Promise = set_sink_id
+        .call1(audio_ctx, &JsValue::from_str(id))?
+        .unchecked_into();
+    JsFuture::from(promise).await?;
+    Ok(())
+}
+
+/// The master volume/mute stage: glides `FxBuses::volume` to a `MasterMixer`
+/// and reports every change (for saving it and updating the overlay).
+///
+/// It sits after the recorder's tap on the limiter, so takes and bounces keep
+/// full level whatever the listener volume.
+pub struct MasterVolume {
+    node: web::GainNode,
+    mixer: Cell<MasterMixer>,
+    on_change: Box<dyn Fn(&MasterMixer)>,
+}
+
+impl MasterVolume {
+    /// Start at `mixer` straight away (no fade) and report it.
+    pub fn new(
+        node: web::GainNode,
+        mixer: MasterMixer,
+        on_change: impl Fn(&MasterMixer) + 'static,
+    ) -> MasterVolume {
+        node.gain().set_value(mixer.gain());
+        on_change(&mixer);
+        MasterVolume {
+            node,
+            mixer: Cell::new(mixer),
+            on_change: Box::new(on_change),
+        }
+    }
+
+    pub fn mixer(&self) -> MasterMixer {
+        self.mixer.get()
+    }
+
+    /// Change the mixer with `f` and fade the output to its new gain.
+    pub fn update(&self, f: impl FnOnce(&mut MasterMixer)) {
+        let mut mixer = self.mixer.get();
+        f(&mut mixer);
+        self.mixer.set(mixer);
+        let param = self.node.gain();
+        let now = self.node.context().current_time();
+        _ = param.cancel_scheduled_values(now);
+        _ = param.set_value_at_time(param.value(), now);
+        _ = param.set_target_at_time(mixer.gain(), now, MASTER_FADE_SEC / 3.0);
+        (self.on_change)(&mixer);
+    }
+}
+
+/// Current output latency estimate for the context (0 when the browser reports none).
+///
+/// `baseLatency`/`outputLatency` have no web-sys bindings yet, so read them
+/// reflectively; browsers update `outputLatency` as the output device changes.
+pub fn output_latency_sec(audio_ctx: &web::AudioContext) -> f64 {
+    let read = |name: &str| {
+        js_sys::Reflect::get(audio_ctx, &wasm_bindgen::JsValue::from_str(name))
+            .ok()
+            .and_then(|v| v.as_f64())
+    };
+    estimate_output_latency(read("baseLatency"), read("outputLatency"))
+}
+
 // Create analyser and an appropriately sized buffer
 pub fn create_analyser(
     audio_ctx: &web::AudioContext,
@@ -210,50 +1371,148 @@ pub fn create_analyser(
     (analyser, buf)
 }
 
-// Wire per-voice panners, gains and effect sends
-pub fn wire_voices(
+/// Onset detection on a live signal: an unsmoothed analyser whose spectrum
+/// is read once per frame into an `OnsetDetector`.
+pub struct OnsetInput {
+    analyser: web::AnalyserNode,
+    spectrum: Vec<f32>,
+    pub detector: OnsetDetector,
+}
+
+impl OnsetInput {
+    /// 1024-point FFT (~21 ms at 48 kHz): fine enough in frequency to see a
+    /// hit's broadband rise, short enough to catch it within a frame or two.
+    pub fn new(audio_ctx: &web::BaseAudioContext) -> Option<OnsetInput> {
+        let analyser = web::AnalyserNode::new(audio_ctx).ok()?;
+        analyser.set_fft_size(1024);
+        // Smoothing would blur exactly the frame-to-frame change flux looks for
+        analyser.set_smoothing_time_constant(0.0);
+        let spectrum = vec![0.0; analyser.frequency_bin_count() as usize];
+        Some(OnsetInput {
+            analyser,
+            spectrum,
+            detector: OnsetDetector::default(),
+        })
+    }
+
+    /// Node to connect the analysed signal into (it has no output).
+    pub fn node(&self) -> &web::AnalyserNode {
+        &self.analyser
+    }
+
+    /// Read the current spectrum; an onset if it holds a hit.
+    pub fn poll(&mut self, at_sec: f64) -> Option<Onset> {
+        self.analyser.get_float_frequency_data(&mut self.spectrum);
+        self.detector.process_db(&self.spectrum, at_sec)
+    }
+
+    /// Read the current spectrum (dB) and the Hz between its bins, for the
+    /// spectrum layer.
+    pub fn spectrum_db(&mut self) -> (&[f32], f32) {
+        self.analyser.get_float_frequency_data(&mut self.spectrum);
+        let bin_hz = self.analyser.context().sample_rate() / self.analyser.fft_size() as f32;
+        (&self.spectrum, bin_hz)
+    }
+
+    /// Read the latest block of samples into `samples` (resized to the FFT
+    /// size), for the oscilloscope layer.
+    pub fn waveform(&self, samples: &mut Vec<f32>) {
+        samples.resize(self.analyser.fft_size() as usize, 0.0);
+        self.analyser.get_float_time_domain_data(samples);
+    }
+}
+
+/// Ask for the microphone and feed it into `dest` (not to the speakers).
+/// Echo cancellation and noise suppression are off so hits keep their edges.
+pub async fn connect_microphone(
     audio_ctx: &web::AudioContext,
+    dest: &web::AudioNode,
+) -> Result<(), JsValue> {
+    let devices = web::window()
+        .ok_or_else(|| JsValue::from_str("no window"))?
+        .navigator()
+        .media_devices()?;
+    let audio = js_sys::Object::new();
+    for key in ["echoCancellation", "noiseSuppression", "autoGainControl"] {
+        js_sys::Reflect::set(&audio, &key.into(), &false.into())?;
+    }
+    let constraints = web::MediaStreamConstraints::new();
+    constraints.set_audio(&audio);
+    let stream: web::MediaStream =
+        JsFuture::from(devices.get_user_media_with_constraints(&constraints)?)
+            .await?
+            .unchecked_into();
+    let source = audio_ctx.create_media_stream_source(&stream)?;
+    source.connect_with_audio_node(dest)?;
+    Ok(())
+}
+
+fn web_distance_model(model: DistanceModel) -> web::DistanceModelType {
+    match model {
+        DistanceModel::Linear => web::DistanceModelType::Linear,
+        DistanceModel::Inverse => web::DistanceModelType::Inverse,
+        DistanceModel::Exponential => web::DistanceModelType::Exponential,
+    }
+}
+
+// Wire per-voice panners (configured from each voice's spatial hints), gains and effect sends
+pub fn wire_voices(
+    audio_ctx: &web::BaseAudioContext,
     initial_positions: &[Vec3],
+    spatial: &[SpatialHints],
     master_gain: &web::GainNode,
     delay_in: &web::GainNode,
     reverb_in: &web::GainNode,
-) -> Result<VoiceRouting, ()> {
+) -> Result<VoiceRouting, String> {
+    let mut voice_fades: Vec<web::GainNode> = Vec::new();
+    let mut voice_ducks: Vec<web::GainNode> = Vec::new();
     let mut voice_gains: Vec<web::GainNode> = Vec::new();
     let mut voice_panners: Vec<web::PannerNode> = Vec::new();
     let mut delay_sends_vec: Vec<web::GainNode> = Vec::new();
     let mut reverb_sends_vec: Vec<web::GainNode> = Vec::new();
 
-    for pos in initial_positions.iter() {
-        let panner = web::PannerNode::new(audio_ctx)
-            .map_err(|e| {
-                log::error!("PannerNode error: {:?}", e);
-            })
-            .map_err(|_| ())?;
+    for (pos, hints) in initial_positions.iter().zip(spatial) {
+        let panner = web::PannerNode::new(audio_ctx).map_err(|e| node_error("PannerNode", &e))?;
         panner.set_panning_model(web::PanningModelType::Hrtf);
-        panner.set_distance_model(web::DistanceModelType::Inverse);
-        panner.set_ref_distance(0.5);
-        panner.set_max_distance(50.0);
-        panner.position_x().set_value(pos.x as f32);
-        panner.position_y().set_value(pos.y as f32);
-        panner.position_z().set_value(pos.z as f32);
-
-        let gain = create_gain(audio_ctx, 0.0, "Voice gain").map_err(|_| ())?;
+        panner.set_distance_model(web_distance_model(hints.distance_model));
+        panner.set_ref_distance(hints.ref_distance as f64);
+        panner.set_max_distance(hints.max_distance as f64);
+        panner.set_rolloff_factor(hints.rolloff as f64);
+        let src = hints.source_position(*pos, audio_ctx.current_time());
+        panner.position_x().set_value(src.x);
+        panner.position_y().set_value(src.y);
+        panner.position_z().set_value(src.z);
+
+        let gain = create_gain(audio_ctx, 0.0, "Voice gain")?;
         _ = gain.connect_with_audio_node(&panner);
         _ = panner.connect_with_audio_node(master_gain);
 
-        let d_send = create_gain(audio_ctx, 0.4, "Delay send").map_err(|_| ())?;
+        let d_send = create_gain(audio_ctx, 0.4, "Delay send")?;
         _ = d_send.connect_with_audio_node(delay_in);
-        delay_sends_vec.push(d_send);
 
-        let r_send = create_gain(audio_ctx, 0.65, "Reverb send").map_err(|_| ())?;
+        let r_send = create_gain(audio_ctx, 0.65, "Reverb send")?;
         _ = r_send.connect_with_audio_node(reverb_in);
+
+        // Fade -> duck feeds the dry path and both sends, so mutes and
+        // sidechain ducking apply everywhere
+        let fade = create_gain(audio_ctx, 1.0, "Voice fade")?;
+        let duck = create_gain(audio_ctx, 1.0, "Voice duck")?;
+        _ = fade.connect_with_audio_node(&duck);
+        _ = duck.connect_with_audio_node(&gain);
+        _ = duck.connect_with_audio_node(&d_send);
+        _ = duck.connect_with_audio_node(&r_send);
+        delay_sends_vec.push(d_send);
         reverb_sends_vec.push(r_send);
 
+        voice_fades.push(fade);
+        voice_ducks.push(duck);
         voice_gains.push(gain);
         voice_panners.push(panner);
     }
 
     Ok(VoiceRouting {
+        voice_fades,
+        voice_ducks,
         voice_gains,
         voice_panners,
         delay_sends: delay_sends_vec,
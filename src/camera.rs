@@ -6,14 +6,15 @@ use web_sys as web;
 ///
 /// - `canvas`: target canvas to derive dimensions/aspect
 /// - `sx`, `sy`: pixel coordinates in the canvas' backing store space
-/// - `camera_z`: camera eye Z used by the fixed look-at used in the app
+/// - `eye`, `target`: the camera's look-at, as the renderer was last given it
 ///
 /// Returns `(ray_origin, ray_direction)` in world space.
 pub fn screen_to_world_ray(
     canvas: &web::HtmlCanvasElement,
     sx: f32,
     sy: f32,
-    camera_z: f32,
+    eye: Vec3,
+    target: Vec3,
 ) -> (Vec3, Vec3) {
     let width = canvas.width() as f32;
     let height = canvas.height() as f32;
@@ -21,13 +22,13 @@ pub fn screen_to_world_ray(
     let ndc_y = 1.0 - (2.0 * sy / height);
     let aspect = width / height.max(1.0);
     let proj = Mat4::perspective_rh(std::f32::consts::FRAC_PI_4, aspect, 0.1, 100.0);
-    let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, camera_z), Vec3::ZERO, Vec3::Y);
+    let view = Mat4::look_at_rh(eye, target, Vec3::Y);
     let inv = (proj * view).inverse();
     let p_near = inv * Vec4::new(ndc_x, ndc_y, 0.0, 1.0);
     let p_far = inv * Vec4::new(ndc_x, ndc_y, 1.0, 1.0);
     let _p0: Vec3 = p_near.truncate() / p_near.w;
     let p1: Vec3 = p_far.truncate() / p_far.w;
-    let ro = Vec3::new(0.0, 0.0, camera_z);
+    let ro = eye;
     let rd = (p1 - ro).normalize();
     (ro, rd)
 }
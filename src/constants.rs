@@ -4,61 +4,21 @@
 /// limits) and keep magic numbers out of the code, improving readability.
 use glam::Vec3;
 
-// Exponential decay rate for internal pulse energy
-pub const PULSE_ENERGY_DECAY_PER_SEC: f32 = 1.6;
-
-// Target smoothing time constants (seconds)
-pub const PULSE_RISE_TAU_SEC: f32 = 0.10;
-pub const PULSE_FALL_TAU_SEC: f32 = 0.45;
-
-// Pointer speed clamp (normalized units per second)
-pub const POINTER_SPEED_MAX: f32 = 10.0;
-
-// Inertial swirl spring parameters
-pub const SWIRL_OMEGA: f32 = 1.1; // natural frequency
-pub const SWIRL_DAMPING_RATIO: f32 = 0.5; // 0..1 critical at 1
-pub const SWIRL_MAX_STEP_PER_SEC: f32 = 0.50; // cap motion per second (in uv units)
-
-// Swirl energy blend weights
-pub const SWIRL_TARGET_WEIGHT_POINTER: f32 = 0.2;
-pub const SWIRL_TARGET_WEIGHT_VELOCITY: f32 = 0.35;
-pub const SWIRL_TARGET_CLICK_BONUS: f32 = 0.5;
-pub const SWIRL_ENERGY_BLEND_ALPHA: f32 = 0.15; // new = (1-α)*old + α*target
-
-// Global FX mapping weights
-pub const FX_REVERB_BASE: f32 = 0.35;
-pub const FX_REVERB_SPAN: f32 = 0.65;
-
-pub const FX_DELAY_WET_BASE: f32 = 0.15;
-pub const FX_DELAY_WET_SWIRL: f32 = 0.55;
-pub const FX_DELAY_WET_ECHO: f32 = 0.30;
-
-pub const FX_DELAY_FB_BASE: f32 = 0.35;
-pub const FX_DELAY_FB_SWIRL: f32 = 0.35;
-pub const FX_DELAY_FB_ECHO: f32 = 0.25;
-
-pub const FX_SAT_DRIVE_MIN: f32 = 0.2;
-pub const FX_SAT_DRIVE_MAX: f32 = 3.0;
-pub const FX_SAT_WET_BASE: f32 = 0.15;
-pub const FX_SAT_WET_SPAN: f32 = 0.85;
-
 // Visual build parameters
 
-// Per-voice spatial sends mapping
-pub const DIST_NORM_DIVISOR: f32 = 2.5;
-pub const D_SEND_BASE: f32 = 0.15;
-pub const D_SEND_SPAN: f32 = 0.85;
-pub const R_SEND_BASE: f32 = 0.25;
-pub const R_SEND_SPAN: f32 = 0.75;
-pub const SEND_BOOST_COEFF: f32 = 0.8;
-pub const D_SEND_CLAMP_MAX: f32 = 1.2;
-pub const R_SEND_CLAMP_MAX: f32 = 1.5;
+// Color adjustments
 
-// Voice level mapping
-pub const LEVEL_BASE: f32 = 0.55;
-pub const LEVEL_SPAN: f32 = 0.45;
+// Note scheduling
+// How far ahead of the audio clock grid steps are scheduled; must comfortably
+// exceed frame jitter so onsets never arrive late.
+pub const SCHEDULE_LOOKAHEAD_SEC: f64 = 0.12;
+// Fade applied to a note stolen at a voice's polyphony cap (short, but click-free)
+pub const NOTE_STEAL_FADE_SEC: f64 = 0.015;
 
-// Color adjustments
+// Pulse velocity per unit of onset strength when detected hits drive the visuals
+pub const ONSET_PULSE_GAIN: f32 = 0.4;
+// Pulse energy for a full-scale band when the microphone drives the visuals
+pub const MIC_BAND_PULSE_GAIN: f32 = 1.2;
 
 // Camera
 // Z distance used by both picking and audio listener alignment.
@@ -66,10 +26,58 @@ pub const CAMERA_Z: f32 = 6.0;
 
 // Voice interaction
 pub const PICK_SPHERE_RADIUS: f32 = 0.5;
+// Radius of the spheres drawn for voices in the 3D scene (a little inside
+// the pick radius, so the whole sphere is clickable)
+pub const VOICE_SPHERE_RADIUS: f32 = 0.32;
 pub const SPREAD: Vec3 = glam::Vec3::new(3.0, 3.0, 3.0);
 pub const Z_OFFSET: Vec3 = glam::Vec3::new(0.0, 0.0, -1.5);
 pub const ENGINE_DRAG_MAX_RADIUS: f32 = 1.0;
-
-// Post-processing defaults
-pub const BLOOM_STRENGTH: f32 = 0.9;
-pub const BLOOM_THRESHOLD: f32 = 0.6;
+// Accumulated wheel delta (px) that counts as one octave step over a voice
+pub const WHEEL_NOTCH_PX: f64 = 100.0;
+
+// Bars over which mode/tuning keys morph into the new scale
+pub const SCALE_MORPH_BARS: u32 = 2;
+// Bars over which Shift+1..4 morphs into a built-in preset
+pub const PRESET_MORPH_BARS: u32 = 4;
+
+// Polymeter preset toggled with 'L': loop length in grid steps per voice
+pub const POLYMETER_LOOP_STEPS: [u32; 3] = [5, 7, 16];
+
+// Voice whose oscillator shape 'W' cycles and imported wavetables/samples replace
+pub const WAVEFORM_VOICE: usize = 1;
+
+// Pitch-class signature glyph size on the overlay (CSS px)
+pub const SIGNATURE_SIZE_PX: u32 = 96;
+
+// Performance recording: start/stop land this far ahead of the audio clock so
+// the capture worklet hears about them before that frame is rendered
+pub const RECORD_LEAD_SEC: f64 = 0.05;
+
+// Master volume: fade for mute and volume steps (click-free, still snappy),
+// and the localStorage key the level is kept under
+pub const MASTER_FADE_SEC: f64 = 0.15;
+pub const MASTER_VOLUME_STORAGE_KEY: &str = "geno-1.master";
+// Where the chosen visual theme is remembered
+pub const THEME_STORAGE_KEY: &str = "geno-1.theme";
+// Whether the brightness and flash limits are on, remembered with the theme
+pub const SAFE_VISUALS_STORAGE_KEY: &str = "geno-1.safe";
+// Keys rebound in the Keys panel (JSON of the bindings changed from the defaults)
+pub const KEYMAP_STORAGE_KEY: &str = "geno-1.keys";
+// Hardware controllers mapped by MIDI-learn (`CcMap::encode` form)
+pub const CC_MAP_STORAGE_KEY: &str = "geno-1.cc";
+// The session as it was left or last autosaved (`SavedSession::encode` form),
+// restored on the next launch unless the URL has `?fresh`
+pub const SESSION_STORAGE_KEY: &str = "geno-1.session";
+// Set while a page is open and shown, cleared as it is hidden or left; found
+// set at launch, the last visit crashed
+pub const RUNNING_STORAGE_KEY: &str = "geno-1.running";
+// Sessions saved by name in the Presets panel (JSON of names and saved text)
+pub const PRESETS_STORAGE_KEY: &str = "geno-1.presets";
+// Custom visualizer code last applied (empty for the built-in example)
+pub const CUSTOM_SHADER_STORAGE_KEY: &str = "geno-1.custom-shader";
+
+// Ambisonic (first-order B-format) export
+pub const BFORMAT_EXPORT_SEC: f64 = 30.0;
+pub const BFORMAT_SAMPLE_RATE: u32 = 48_000;
+// Wait before the (blocking) render so the notice that it's coming is painted
+pub const BFORMAT_RENDER_DELAY_MS: i32 = 50;
@@ -0,0 +1,205 @@
+use super::bounce::{schedule_piece, ScheduledNote};
+use super::effects::{beat_delay_sec, DcBlocker, FdnReverb, TempoDelay};
+use super::filter::SvfState;
+use super::frame_math::{fx_levels, voice_mix};
+use super::music::{MusicEngine, Waveform};
+use super::sidechain::DuckFollower;
+use super::wavetable::Wavetable;
+use glam::Vec3;
+
+/// Number of channels in first-order B-format.
+pub const FOA_CHANNELS: usize = 4;
+
+/// Encode a source direction into first-order ambisonic gains.
+///
+/// `direction` is relative to the listener in engine axes (+X right, +Y up,
+/// -Z forward). Output is AmbiX: ACN channel order (W, Y, Z, X) with SN3D
+/// normalization. A zero vector yields an omnidirectional (W only) source.
+pub fn foa_encode(direction: Vec3) -> [f32; FOA_CHANNELS] {
+    let d = direction.normalize_or_zero();
+    // Ambisonic axes: X front, Y left, Z up
+    let (front, left, up) = (-d.z, -d.x, d.y);
+    [1.0, left, up, front]
+}
+
+/// Render `seconds` of the engine's output as interleaved B-format samples.
+///
+/// Voices are synthesized with the same oscillator shapes, per-voice ADSR
+/// envelopes, filters, unison stacks and FM as the web frontend, then encoded
+/// from their engine-space positions as heard from `listener`; unison layers
+/// fan out around the voice's direction by up to `UNISON_SPREAD_RAD`. Each
+/// voice is levelled and sent to a beat-synced delay and an FDN reverb by
+/// `voice_mix`, like the web buses; the echoes land in W and the reverb's
+/// decorrelated lines fill all four channels as a diffuse field. With a
+/// sidechain enabled, ducked voices (sends included) follow a `DuckFollower`
+/// on the trigger voice's onsets. Each channel is DC-blocked on the way out
+/// and the effect loops flush decayed state to zero, so long tails neither
+/// drift nor go subnormal. The engine is advanced, so pass a clone to leave
+/// live playback untouched.
+pub fn render_bformat(
+    engine: &mut MusicEngine,
+    listener: Vec3,
+    seconds: f64,
+    sample_rate: u32,
+) -> Vec<f32> {
+    const BLOCK: usize = 128;
+    const NOTE_GAIN: f32 = 0.2;
+    const UNISON_SPREAD_RAD: f32 = std::f32::consts::FRAC_PI_4;
+    const REVERB_RT60_SEC: f32 = 3.0;
+    const REVERB_DAMPING: f32 = 0.3;
+    const REVERB_GAIN: f32 = 0.5;
+
+    let sr = sample_rate as f64;
+    let total = (seconds * sr) as usize;
+
+    // Schedule the whole piece first, so the sidechain curve is known when mixing
+    let notes = schedule_piece(engine, total as f64 / sr, BLOCK as f64 / sr);
+    let sidechain = engine.sidechain();
+    let duck: Vec<f32> = match sidechain {
+        Some(sc) => {
+            let mut gate = vec![false; total];
+            let open = ((sc.attack_sec as f64 * sr) as usize).max(1);
+            for note in notes.iter().filter(|n| !sc.ducks(n.event.voice_index)) {
+                let first = (note.event.start_time_sec * sr).round() as usize;
+                for g in gate.iter_mut().skip(first).take(open) {
+                    *g = true;
+                }
+            }
+            let mut follower = DuckFollower::default();
+            let dt = (1.0 / sr) as f32;
+            gate.into_iter()
+                .map(|open| follower.next(&sc, open, dt))
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    let mut out = vec![0.0f32; total * FOA_CHANNELS];
+    let mut delay_in = vec![0.0f32; total];
+    let mut reverb_in = vec![0.0f32; total];
+    for ScheduledNote {
+        event: ev,
+        position,
+        config,
+    } in &notes
+    {
+        let gains_dir = *position - listener;
+        let mix = voice_mix(*position, 0.0, config.sends);
+        let ducked = sidechain.is_some_and(|sc| sc.ducks(ev.voice_index));
+        let (waveform, envelope, filter, fm) =
+            (config.waveform, config.envelope, config.filter, config.fm);
+        let sampler = config
+            .sampler
+            .and_then(|c| Some((c, engine.samples.get(c.sample as usize)?)));
+        let first = (ev.start_time_sec * sr).round() as usize;
+        let len = ((ev.duration_sec + envelope.release_sec) as f64 * sr) as usize;
+        let peak = ev.velocity * config.unison.layer_gain() * NOTE_GAIN;
+        for layer in config.unison.layers() {
+            // Positive pan turns toward +X (right): clockwise seen from above
+            let turn = glam::Quat::from_rotation_y(-layer.pan * UNISON_SPREAD_RAD);
+            let gains = foa_encode(turn * gains_dir);
+            let freq = ev.frequency_hz as f64 * (layer.detune_cents as f64 / 1200.0).exp2();
+            let mut svf = SvfState::default();
+            for n in 0..len.min(total.saturating_sub(first)) {
+                let t_sec = (n as f64 / sr) as f32;
+                let env = envelope.level_at(t_sec, ev.duration_sec);
+                let fm_offset = fm.map_or(0.0, |fm| {
+                    fm.phase_offset(ev.frequency_hz, t_sec, ev.duration_sec) as f64
+                });
+                let phase =
+                    (layer.phase as f64 + freq * n as f64 / sr + fm_offset).rem_euclid(1.0) as f32;
+                let mut s = match sampler {
+                    Some((c, sample)) => {
+                        let rate = c.playback_rate(freq as f32);
+                        sample.read(c.position_at(sample.duration_sec(), t_sec, rate))
+                    }
+                    None => oscillator(waveform, &engine.wavetables, phase),
+                };
+                if let Some(f) = &filter {
+                    let cutoff = f.cutoff_at(ev.frequency_hz, env);
+                    s = svf.process(s, f.mode, cutoff, f.resonance, sr as f32);
+                }
+                let idx = first + n;
+                let s = s * env * peak * if ducked { duck[idx] } else { 1.0 };
+                delay_in[idx] += s * mix.delay_send;
+                reverb_in[idx] += s * mix.reverb_send;
+                let frame = &mut out[idx * FOA_CHANNELS..][..FOA_CHANNELS];
+                for (o, g) in frame.iter_mut().zip(gains) {
+                    *o += s * mix.level * g;
+                }
+            }
+        }
+    }
+
+    // Effect returns at the web buses' resting levels
+    let fx = fx_levels(0.0, [0.5, 0.5]);
+    let mut delay = TempoDelay::new(sr as f32, beat_delay_sec(engine.params.bpm, 1.0));
+    let mut reverb = FdnReverb::new(sr as f32, REVERB_RT60_SEC, REVERB_DAMPING);
+    // A diffuse field carries a third of W's energy in each first-order channel (SN3D)
+    let diffuse = REVERB_GAIN * fx.reverb_wet;
+    let directional = diffuse / 3f32.sqrt();
+    let mut dc_blockers = [DcBlocker::new(sr as f32); FOA_CHANNELS];
+    for ((frame, d), r) in out.chunks_mut(FOA_CHANNELS).zip(delay_in).zip(reverb_in) {
+        let echo = delay.process(d, fx.delay_feedback) * fx.delay_wet;
+        let [a, b, c, e] = reverb.process(r);
+        frame[0] += echo + a * diffuse;
+        frame[1] += b * directional;
+        frame[2] += c * directional;
+        frame[3] += e * directional;
+        for (s, dc) in frame.iter_mut().zip(dc_blockers.iter_mut()) {
+            *s = dc.process(*s);
+        }
+    }
+    out
+}
+
+fn oscillator(waveform: Waveform, tables: &[Wavetable], phase: f32) -> f32 {
+    match waveform {
+        Waveform::Wavetable(i) if (i as usize) < tables.len() => tables[i as usize].sample(phase),
+        Waveform::Sine | Waveform::Wavetable(_) => (phase * std::f32::consts::TAU).sin(),
+        Waveform::Saw => 2.0 * phase - 1.0,
+        Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+    }
+}
+
+/// `KSDATAFORMAT_SUBTYPE_PCM`, the sub-format GUID of an extensible PCM WAV.
+const PCM_SUBFORMAT: [u8; 16] = [
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+/// Encode interleaved samples as a 16-bit PCM WAV file. More than two
+/// channels are written as `WAVE_FORMAT_EXTENSIBLE` with a channel mask of 0
+/// (no speaker positions), as AmbiX decoders expect of B-format.
+pub fn wav_bytes(samples: &[f32], channels: u16, sample_rate: u32) -> Vec<u8> {
+    let extensible = channels > 2;
+    let fmt_len: u32 = if extensible { 40 } else { 16 };
+    let data_len = (samples.len() * 2) as u32;
+    let block_align = channels * 2;
+    let mut bytes = Vec::with_capacity(28 + fmt_len as usize + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(20 + fmt_len + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&fmt_len.to_le_bytes());
+    // PCM, or WAVE_FORMAT_EXTENSIBLE
+    let format: u16 = if extensible { 0xFFFE } else { 1 };
+    bytes.extend_from_slice(&format.to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    if extensible {
+        // Extension size, valid bits per sample, channel mask, sub-format
+        bytes.extend_from_slice(&22u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&PCM_SUBFORMAT);
+    }
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+        let v = (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    bytes
+}
@@ -0,0 +1,65 @@
+/// Samples per pixel for MSAA on the layers drawn into the HDR target.
+pub const MSAA_SAMPLES: u32 = 4;
+
+/// How edges are smoothed: multisampling the 3D layers, or an FXAA pass at
+/// the end of the post chain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Antialias {
+    #[default]
+    Off,
+    /// Edge-detecting blur over the finished frame; cheap, and catches the
+    /// fullscreen layers' edges too
+    Fxaa,
+    /// `MSAA_SAMPLES` samples per pixel for geometry, resolved into the HDR
+    /// target
+    Msaa,
+}
+
+impl Antialias {
+    pub const ALL: [Antialias; 3] = [Antialias::Off, Antialias::Fxaa, Antialias::Msaa];
+
+    /// Short identifier used in URLs
+    pub fn id(self) -> &'static str {
+        match self {
+            Antialias::Off => "off",
+            Antialias::Fxaa => "fxaa",
+            Antialias::Msaa => "msaa",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Antialias::Off => "Off",
+            Antialias::Fxaa => "FXAA",
+            Antialias::Msaa => "MSAA 4×",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Antialias> {
+        Self::ALL
+            .into_iter()
+            .find(|aa| aa.id().eq_ignore_ascii_case(id.trim()))
+    }
+
+    /// Samples per pixel for the HDR target and its depth buffer.
+    pub fn samples(self) -> u32 {
+        match self {
+            Antialias::Msaa => MSAA_SAMPLES,
+            Antialias::Off | Antialias::Fxaa => 1,
+        }
+    }
+
+    /// Whether the FXAA pass runs.
+    pub fn fxaa(self) -> bool {
+        self == Antialias::Fxaa
+    }
+
+    /// What runs on a device that can (`msaa_supported`) or can't multisample
+    /// the HDR target: MSAA falls back to FXAA.
+    pub fn supported(self, msaa_supported: bool) -> Antialias {
+        match self {
+            Antialias::Msaa if !msaa_supported => Antialias::Fxaa,
+            aa => aa,
+        }
+    }
+}
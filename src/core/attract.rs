@@ -0,0 +1,109 @@
+use super::history::Command;
+use super::music::{AEOLIAN, DORIAN, IONIAN, LOCRIAN, LYDIAN, MIXOLYDIAN, PHRYGIAN};
+use rand::Rng;
+
+/// Seconds without input before attract mode starts, when switched on
+/// without a time.
+pub const DEFAULT_ATTRACT_IDLE_SEC: f32 = 90.0;
+
+/// Seconds between new keys and modes while attract mode runs.
+pub const ATTRACT_RETONE_SEC: f32 = 40.0;
+
+/// Roots a random tonality is drawn from: the white keys from middle C.
+const TONALITY_ROOTS: [i32; 7] = [60, 62, 64, 65, 67, 69, 71];
+
+/// What attract mode did on a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttractEvent {
+    /// The idle time ran out: take over
+    Entered,
+    /// Time for a new key and mode
+    Retone,
+    /// Input came: hand control back
+    Left,
+}
+
+/// Idle attract mode for unattended screens: after `idle_after_sec` without
+/// input the piece plays itself — voices drift, the camera moves, the key
+/// changes now and then — until any input hands control straight back.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Attract {
+    /// `None` when attract mode is off
+    idle_after_sec: Option<f32>,
+    idle_sec: f32,
+    active: bool,
+    since_retone_sec: f32,
+}
+
+impl Attract {
+    pub fn new(idle_after_sec: Option<f32>) -> Attract {
+        Attract {
+            idle_after_sec,
+            ..Attract::default()
+        }
+    }
+
+    pub fn idle_after_sec(&self) -> Option<f32> {
+        self.idle_after_sec
+    }
+
+    /// Switch attract mode on after `idle_after_sec`, or off (ending it on
+    /// the next update if it is running).
+    pub fn set_idle_after_sec(&mut self, idle_after_sec: Option<f32>) {
+        self.idle_after_sec = idle_after_sec;
+        self.idle_sec = 0.0;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Advance by `dt_sec`, with `input` true if the user did anything
+    /// since the last call.
+    pub fn update(&mut self, dt_sec: f32, input: bool) -> Option<AttractEvent> {
+        if input || self.idle_after_sec.is_none() {
+            self.idle_sec = 0.0;
+            return std::mem::take(&mut self.active).then_some(AttractEvent::Left);
+        }
+        self.idle_sec += dt_sec.max(0.0);
+        if !self.active {
+            let due = self
+                .idle_after_sec
+                .is_some_and(|after| self.idle_sec >= after);
+            if due {
+                self.active = true;
+                self.since_retone_sec = 0.0;
+            }
+            return due.then_some(AttractEvent::Entered);
+        }
+        self.since_retone_sec += dt_sec.max(0.0);
+        if self.since_retone_sec >= ATTRACT_RETONE_SEC {
+            self.since_retone_sec = 0.0;
+            return Some(AttractEvent::Retone);
+        }
+        None
+    }
+}
+
+/// Parse `?attract=`: `"on"` for `DEFAULT_ATTRACT_IDLE_SEC`, `"off"`, or the
+/// idle seconds (at least 1). The outer `None` means unreadable.
+pub fn parse_attract(s: &str) -> Option<Option<f32>> {
+    let s = s.trim();
+    match s.to_ascii_lowercase().as_str() {
+        "on" | "1" => return Some(Some(DEFAULT_ATTRACT_IDLE_SEC)),
+        "off" | "0" => return Some(None),
+        _ => {}
+    }
+    let sec: f32 = s.parse().ok()?;
+    (sec.is_finite() && sec >= 1.0).then_some(Some(sec))
+}
+
+/// A random white-key root and church mode, as one command.
+pub fn random_tonality(rng: &mut impl Rng) -> Command {
+    let modes: [&'static [f32]; 7] = [
+        IONIAN, DORIAN, PHRYGIAN, LYDIAN, MIXOLYDIAN, AEOLIAN, LOCRIAN,
+    ];
+    let root = TONALITY_ROOTS[rng.gen_range(0..TONALITY_ROOTS.len())];
+    let mode = modes[rng.gen_range(0..modes.len())];
+    Command::Batch(vec![Command::SetRootMidi(root), Command::SetScale(mode)])
+}
@@ -0,0 +1,199 @@
+use super::bus::EngineEvent;
+use super::history::Command;
+use super::music::{MusicEngine, STEPS_PER_BAR};
+
+/// Changes to the same parameter closer together than this (in grid steps)
+/// are kept as one, so a drag or a swept knob doesn't fill the timeline.
+pub const AUTOMATION_RESOLUTION_STEPS: f64 = 0.25;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AutomationState {
+    /// Nothing recorded
+    #[default]
+    Empty,
+    Recording,
+    Playing,
+    /// A take kept but not playing
+    Stopped,
+}
+
+/// The parameter a command sets (a voice's, or the whole piece's for
+/// `usize::MAX`), for the commands the timeline records; `None` for the rest.
+fn lane(cmd: &Command) -> Option<(u8, usize)> {
+    match cmd {
+        Command::SetBpm(_) => Some((0, usize::MAX)),
+        Command::SetScale(_) => Some((1, usize::MAX)),
+        Command::SetRootMidi(_) => Some((2, usize::MAX)),
+        Command::SetDetune(_) => Some((3, usize::MAX)),
+        Command::SetPosition { voice, .. } => Some((4, *voice)),
+        Command::SetSends { voice, .. } => Some((5, *voice)),
+        Command::SetDensity { voice, .. } => Some((6, *voice)),
+        _ => None,
+    }
+}
+
+/// The command that brings about an engine event, for the ones the
+/// timeline records (tempo, key, scale, detune, voice sends and density).
+pub fn event_command(ev: &EngineEvent) -> Option<Command> {
+    Some(match *ev {
+        EngineEvent::BpmChanged(bpm) => Command::SetBpm(bpm),
+        EngineEvent::ScaleChanged(scale) => Command::SetScale(scale),
+        EngineEvent::RootChanged(root) => Command::SetRootMidi(root),
+        EngineEvent::DetuneChanged(cents) => Command::SetDetune(cents),
+        EngineEvent::SendsChanged { voice, sends } => Command::SetSends { voice, sends },
+        EngineEvent::DensityChanged { voice, density } => Command::SetDensity { voice, density },
+        _ => return None,
+    })
+}
+
+/// Every recorded parameter as it stands, which a take starts from.
+pub fn automation_snapshot(engine: &MusicEngine) -> Vec<Command> {
+    let p = &engine.params;
+    let mut cmds = vec![
+        Command::SetBpm(p.bpm),
+        Command::SetScale(p.scale),
+        Command::SetRootMidi(p.root_midi),
+        Command::SetDetune(p.detune_cents),
+    ];
+    for (voice, (v, c)) in engine.voices.iter().zip(&engine.configs).enumerate() {
+        cmds.extend([
+            Command::SetPosition {
+                voice,
+                position: v.position,
+            },
+            Command::SetSends {
+                voice,
+                sends: c.sends,
+            },
+            Command::SetDensity {
+                voice,
+                density: c.trigger_probability,
+            },
+        ]);
+    }
+    cmds
+}
+
+/// A timeline of parameter changes (tempo, key, scale, detune, voice
+/// positions, sends and density) recorded against the engine's grid, played
+/// back once from the top like an arrangement. Positions are in grid steps,
+/// so tempo changes in the take carry the rest of it along.
+#[derive(Clone, Debug, Default)]
+pub struct Automation {
+    state: AutomationState,
+    /// Parameters as they stood when recording began
+    start: Vec<Command>,
+    /// Changes and their offsets in steps from the start
+    take: Vec<(f64, Command)>,
+    /// Whole bars the take lasts
+    bars: u32,
+    /// Grid step the take starts on (while recording or playing)
+    origin: f64,
+    /// Grid position played up to
+    cursor: f64,
+}
+
+impl Automation {
+    pub fn state(&self) -> AutomationState {
+        self.state
+    }
+
+    /// Changes in the take.
+    pub fn change_count(&self) -> usize {
+        self.take.len()
+    }
+
+    pub fn bars(&self) -> u32 {
+        self.bars
+    }
+
+    /// Start a new take at grid position `now`, from the bar line it falls
+    /// in, with the parameters as `start` has them. Any earlier take is gone.
+    pub fn record(&mut self, now: f64, start: Vec<Command>) {
+        let bar = STEPS_PER_BAR as f64;
+        self.origin = (now / bar).floor() * bar;
+        self.start = start;
+        self.take.clear();
+        self.bars = 0;
+        self.state = AutomationState::Recording;
+    }
+
+    /// Note `cmd` done at grid position `now`, if recording and it sets a
+    /// parameter the timeline follows. A change to a parameter changed
+    /// within `AUTOMATION_RESOLUTION_STEPS` replaces that one's value.
+    pub fn capture(&mut self, now: f64, cmd: Command) {
+        let Some(lane_of_cmd) = lane(&cmd) else {
+            return;
+        };
+        if self.state != AutomationState::Recording {
+            return;
+        }
+        let offset = (now - self.origin).max(0.0);
+        let recent = self
+            .take
+            .iter_mut()
+            .rev()
+            .take_while(|(at, _)| offset - *at < AUTOMATION_RESOLUTION_STEPS)
+            .find(|(_, c)| lane(c) == Some(lane_of_cmd));
+        match recent {
+            Some((_, kept)) => *kept = cmd,
+            None => self.take.push((offset, cmd)),
+        }
+    }
+
+    /// End recording at grid position `now`, rounding the take up to whole
+    /// bars (at least one).
+    pub fn stop_recording(&mut self, now: f64) {
+        if self.state != AutomationState::Recording {
+            return;
+        }
+        let bar = STEPS_PER_BAR as f64;
+        self.bars = (((now - self.origin) / bar).ceil() as u32).max(1);
+        self.state = AutomationState::Stopped;
+    }
+
+    /// Start playing the take from the top at the next bar line after grid
+    /// position `now` (the parameters jump to where the take started
+    /// there), or stop it.
+    pub fn toggle_play(&mut self, now: f64) {
+        match self.state {
+            AutomationState::Stopped => {
+                let bar = STEPS_PER_BAR as f64;
+                self.origin = (now / bar).ceil() * bar;
+                self.cursor = now;
+                self.state = AutomationState::Playing;
+            }
+            AutomationState::Playing => self.state = AutomationState::Stopped,
+            AutomationState::Empty | AutomationState::Recording => {}
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Move on to grid position `now`: while playing, the commands due from
+    /// the last call's position up to (not including) `now`, in order. The
+    /// take stops once its bars have gone by. A jump backwards (after a
+    /// pause) plays nothing.
+    pub fn advance(&mut self, now: f64) -> Vec<Command> {
+        let from = std::mem::replace(&mut self.cursor, now);
+        if self.state != AutomationState::Playing || now <= from {
+            return Vec::new();
+        }
+        let mut due = Vec::new();
+        if (from..now).contains(&self.origin) {
+            due.extend(self.start.iter().cloned());
+        }
+        due.extend(
+            self.take
+                .iter()
+                .filter(|(offset, _)| (from..now).contains(&(self.origin + offset)))
+                .map(|(_, cmd)| cmd.clone()),
+        );
+        if now >= self.origin + (self.bars as u64 * STEPS_PER_BAR) as f64 {
+            self.state = AutomationState::Stopped;
+        }
+        due
+    }
+}
@@ -0,0 +1,92 @@
+/// Bloom added over the image at the default setting.
+pub const DEFAULT_BLOOM_STRENGTH: f32 = 0.9;
+
+/// Luminance above which a pixel blooms, by default.
+pub const DEFAULT_BLOOM_THRESHOLD: f32 = 0.6;
+
+/// Upper limits for strength and threshold; 0 strength turns bloom off.
+pub const MAX_BLOOM_STRENGTH: f32 = 2.0;
+pub const MAX_BLOOM_THRESHOLD: f32 = 2.0;
+
+/// How far one key press moves the strength.
+pub const BLOOM_STRENGTH_STEP: f32 = 0.1;
+
+/// Blur iterations offered (each a horizontal then vertical pass); more
+/// spread the glow wider and smoother at the cost of GPU time.
+pub const MIN_BLOOM_PASSES: u32 = 1;
+pub const MAX_BLOOM_PASSES: u32 = 4;
+
+/// Bloom settings the post chain runs with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    pub strength: f32,
+    pub threshold: f32,
+    /// Blur iterations, `MIN_BLOOM_PASSES..=MAX_BLOOM_PASSES`
+    pub passes: u32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            strength: DEFAULT_BLOOM_STRENGTH,
+            threshold: DEFAULT_BLOOM_THRESHOLD,
+            passes: MIN_BLOOM_PASSES,
+        }
+    }
+}
+
+impl Bloom {
+    /// Parse `"strength[,threshold[,passes]]"` (e.g. `?bloom=1.2,0.5,3`);
+    /// values are clamped to their ranges and omitted ones keep their default.
+    pub fn parse(s: &str) -> Option<Bloom> {
+        let mut parts = s.split(',').map(str::trim);
+        let mut bloom = Bloom::default().with_strength(parse_finite(parts.next()?)?);
+        if let Some(threshold) = parts.next() {
+            bloom = bloom.with_threshold(parse_finite(threshold)?);
+        }
+        if let Some(passes) = parts.next() {
+            bloom = bloom.with_passes(passes.parse().ok()?);
+        }
+        parts.next().is_none().then_some(bloom)
+    }
+
+    /// `parse`'s form of these settings.
+    pub fn param(&self) -> String {
+        format!("{},{},{}", self.strength, self.threshold, self.passes)
+    }
+
+    pub fn with_strength(self, strength: f32) -> Bloom {
+        Bloom {
+            strength: strength.clamp(0.0, MAX_BLOOM_STRENGTH),
+            ..self
+        }
+    }
+
+    pub fn with_threshold(self, threshold: f32) -> Bloom {
+        Bloom {
+            threshold: threshold.clamp(0.0, MAX_BLOOM_THRESHOLD),
+            ..self
+        }
+    }
+
+    pub fn with_passes(self, passes: u32) -> Bloom {
+        Bloom {
+            passes: passes.clamp(MIN_BLOOM_PASSES, MAX_BLOOM_PASSES),
+            ..self
+        }
+    }
+
+    /// Next blur quality, wrapping from the most passes back to one.
+    pub fn next_quality(self) -> Bloom {
+        let passes = if self.passes >= MAX_BLOOM_PASSES {
+            MIN_BLOOM_PASSES
+        } else {
+            self.passes + 1
+        };
+        self.with_passes(passes)
+    }
+}
+
+fn parse_finite(s: &str) -> Option<f32> {
+    s.parse().ok().filter(|v: &f32| v.is_finite())
+}
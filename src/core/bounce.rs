@@ -0,0 +1,52 @@
+use super::music::{MusicEngine, NoteEvent, VoiceConfig};
+use glam::Vec3;
+
+/// Length of a bounce when none is asked for.
+pub const DEFAULT_BOUNCE_SEC: f64 = 120.0;
+
+/// Longest bounce accepted (an offline render holds the whole track in memory).
+pub const MAX_BOUNCE_SEC: f64 = 600.0;
+
+/// Engine scheduling slice of an offline bounce (one render quantum).
+pub const BOUNCE_STEP_FRAMES: f64 = 128.0;
+
+/// Parse a bounce length in seconds (e.g. `?bounce=120`), clamped to
+/// `MAX_BOUNCE_SEC`.
+pub fn parse_bounce_seconds(s: &str) -> Option<f64> {
+    let sec: f64 = s.trim().parse().ok()?;
+    (sec.is_finite() && sec > 0.0).then(|| sec.min(MAX_BOUNCE_SEC))
+}
+
+/// A note produced while stepping the engine offline, with the voice state
+/// it started under.
+#[derive(Clone, Debug)]
+pub struct ScheduledNote {
+    pub event: NoteEvent,
+    pub position: Vec3,
+    pub config: VoiceConfig,
+}
+
+/// Step `engine` from a fresh grid at time 0 through `seconds`, `step_sec`
+/// at a time, and collect every note it plays.
+///
+/// Nothing depends on a wall clock, so the same engine state always yields
+/// the same notes; offline renderers share this so a bounce of a seed is
+/// reproducible. The engine is advanced, so pass a clone to leave live
+/// playback untouched.
+pub fn schedule_piece(engine: &mut MusicEngine, seconds: f64, step_sec: f64) -> Vec<ScheduledNote> {
+    let mut notes = Vec::new();
+    let mut events = Vec::new();
+    engine.restart_grid();
+    let steps = (seconds / step_sec).ceil() as usize;
+    for i in 0..steps {
+        engine.schedule(i as f64 * step_sec, step_sec, &mut events);
+        for event in events.drain(..) {
+            notes.push(ScheduledNote {
+                position: engine.voices[event.voice_index].position,
+                config: engine.configs[event.voice_index].clone(),
+                event,
+            });
+        }
+    }
+    notes
+}
@@ -0,0 +1,105 @@
+use super::frame_math::VoiceSends;
+use super::metronome::Metronome;
+use super::motion::MotionMode;
+use super::music::Waveform;
+use super::physics::PhysicsParams;
+use super::sidechain::Sidechain;
+
+/// Engine state changes, emitted alongside `NoteEvent`s.
+///
+/// The engine queues these whenever its state is mutated through its methods;
+/// frontends drain them once per frame (`MusicEngine::drain_events`) and hand
+/// them to an `EventBus` so UI layers stay in sync without polling state.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineEvent {
+    MuteChanged {
+        voice: usize,
+        muted: bool,
+    },
+    LockChanged {
+        voice: usize,
+        locked: bool,
+    },
+    /// `None` means solo mode was cleared.
+    SoloChanged {
+        voice: Option<usize>,
+    },
+    BpmChanged(f32),
+    ScaleChanged(&'static [f32]),
+    /// Degrees in use part-way through a scale morph (the final one equals the target)
+    HybridScale(Vec<f32>),
+    RootChanged(i32),
+    DetuneChanged(f32),
+    /// Name of the newly selected groove template
+    GrooveChanged(&'static str),
+    OctaveChanged {
+        voice: usize,
+        octave: i32,
+    },
+    DensityChanged {
+        voice: usize,
+        density: f32,
+    },
+    LoopStepsChanged {
+        voice: usize,
+        steps: u32,
+    },
+    WaveformChanged {
+        voice: usize,
+        waveform: Waveform,
+    },
+    /// `sample` is `None` when the voice went back to its oscillator.
+    SamplerChanged {
+        voice: usize,
+        sample: Option<u16>,
+    },
+    SendsChanged {
+        voice: usize,
+        sends: VoiceSends,
+    },
+    /// Name of a preset that was applied or fully morphed into
+    PresetChanged(&'static str),
+    MotionChanged(MotionMode),
+    /// `None` means spring physics was switched off.
+    PhysicsChanged(Option<PhysicsParams>),
+    /// `None` means sidechain ducking was switched off.
+    SidechainChanged(Option<Sidechain>),
+    MetronomeChanged(Metronome),
+    /// A voice's note source was replaced (see `MusicEngine::set_generator`)
+    GeneratorChanged {
+        voice: usize,
+    },
+    /// `seed` is `None` when the voice RNG was restored rather than reseeded.
+    VoiceReseeded {
+        voice: usize,
+        seed: Option<u64>,
+    },
+}
+
+pub type EngineEventHandler = Box<dyn FnMut(&EngineEvent)>;
+
+/// Fan-out of engine events to subscribed handlers.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<EngineEventHandler>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a handler called for every dispatched event, in subscription order.
+    pub fn subscribe(&mut self, handler: EngineEventHandler) {
+        self.subscribers.push(handler);
+    }
+
+    /// Deliver a batch of events to all subscribers.
+    pub fn dispatch(&mut self, events: &[EngineEvent]) {
+        for ev in events {
+            for sub in self.subscribers.iter_mut() {
+                sub(ev);
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use super::musical_clock::{BarCounter, ClockPulse};
+use glam::Vec3;
+use rand::prelude::*;
+
+/// Bars in a section; the camera cuts to a new shot at each section.
+pub const SECTION_BARS: u32 = 8;
+
+/// Longest a shot is held when no bars are heard (e.g. while paused).
+pub const MAX_SHOT_SEC: f32 = 30.0;
+
+/// Seconds to ease from one shot into the next.
+pub const SHOT_BLEND_SEC: f32 = 3.0;
+
+/// How much faster a shot moves at the peak of a beat, so the camera leans
+/// into the rhythm without jolting.
+pub const BEAT_SURGE: f32 = 0.6;
+
+/// Where the camera is and what it looks at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraPose {
+    pub eye: Vec3,
+    pub target: Vec3,
+}
+
+impl CameraPose {
+    /// The resting view: on the Z axis at `camera_z`, looking at the origin.
+    pub fn home(camera_z: f32) -> Self {
+        Self {
+            eye: Vec3::new(0.0, 0.0, camera_z),
+            target: Vec3::ZERO,
+        }
+    }
+
+    /// The same view from `distance` times as far away (below 1 is closer).
+    pub fn zoomed(self, distance: f32) -> CameraPose {
+        CameraPose {
+            eye: self.target + (self.eye - self.target) * distance,
+            ..self
+        }
+    }
+
+    pub fn lerp(self, other: CameraPose, t: f32) -> CameraPose {
+        CameraPose {
+            eye: self.eye.lerp(other.eye, t),
+            target: self.target.lerp(other.target, t),
+        }
+    }
+}
+
+/// The kinds of camera move a section can get.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShotKind {
+    /// Hold the resting view
+    Still,
+    /// Swing around the scene, to one side and back
+    Orbit,
+    /// Push in towards the scene and ease back out
+    Dolly,
+    /// Rise above the scene and look down, then sink back
+    Crane,
+    /// Slow handheld-style wander
+    Drift,
+}
+
+impl ShotKind {
+    pub const ALL: [ShotKind; 5] = [
+        ShotKind::Still,
+        ShotKind::Orbit,
+        ShotKind::Dolly,
+        ShotKind::Crane,
+        ShotKind::Drift,
+    ];
+}
+
+/// One procedurally chosen camera move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shot {
+    pub kind: ShotKind,
+    /// Angular rate of the move (radians per second)
+    rate: f32,
+    /// Size of the move: radians for an orbit, a fraction of the distance
+    /// for a dolly, world units otherwise; negative mirrors it
+    amount: f32,
+}
+
+impl Shot {
+    pub const STILL: Shot = Shot {
+        kind: ShotKind::Still,
+        rate: 0.0,
+        amount: 0.0,
+    };
+
+    /// A shot of any kind; a held one now and then reads as a pause for
+    /// breath between moves.
+    pub fn random(rng: &mut StdRng) -> Shot {
+        let kind = ShotKind::ALL[rng.gen_range(0..ShotKind::ALL.len())];
+        let side = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+        let (rate, amount) = match kind {
+            ShotKind::Still => (0.0, 0.0),
+            ShotKind::Orbit => (rng.gen_range(0.08..=0.16), side * rng.gen_range(0.5..=0.9)),
+            ShotKind::Dolly => (rng.gen_range(0.12..=0.22), rng.gen_range(0.25..=0.45)),
+            ShotKind::Crane => (rng.gen_range(0.1..=0.18), side * rng.gen_range(1.5..=2.5)),
+            ShotKind::Drift => (rng.gen_range(0.15..=0.3), rng.gen_range(0.4..=0.8)),
+        };
+        Shot { kind, rate, amount }
+    }
+
+    /// Pose `t_sec` into the shot, moving about `home`. Every move starts
+    /// at (or near) `home` and stays within sight of the scene.
+    pub fn pose(&self, home: CameraPose, t_sec: f32) -> CameraPose {
+        let phase = self.rate * t_sec;
+        // 0 at the start, swelling to 1 and back
+        let swell = 0.5 - 0.5 * phase.cos();
+        let offset = home.eye - home.target;
+        match self.kind {
+            ShotKind::Still => home,
+            ShotKind::Orbit => {
+                let angle = self.amount * phase.sin();
+                let eye = home.target + glam::Quat::from_rotation_y(angle) * offset;
+                CameraPose { eye, ..home }
+            }
+            ShotKind::Dolly => CameraPose {
+                eye: home.target + offset * (1.0 - self.amount * swell),
+                ..home
+            },
+            ShotKind::Crane => CameraPose {
+                eye: home.eye + Vec3::Y * (self.amount * swell),
+                target: home.target + Vec3::Y * (0.2 * self.amount * swell),
+            },
+            ShotKind::Drift => {
+                let sway = Vec3::new(phase.sin(), (phase * 1.5).sin() * 0.6, 0.0);
+                CameraPose {
+                    eye: home.eye + sway * self.amount,
+                    target: home.target + sway * (0.3 * self.amount),
+                }
+            }
+        }
+    }
+}
+
+/// Cinematic camera: a new shot each section, eased between and pushed
+/// along by the beat.
+///
+/// Sections are counted from the clock's bars, so cuts land on downbeats;
+/// shots are drawn from a seeded generator, so a seed always gets the same
+/// choreography.
+#[derive(Clone, Debug)]
+pub struct Choreographer {
+    home: CameraPose,
+    rng: StdRng,
+    active: bool,
+    shot: Shot,
+    shot_sec: f32,
+    held_sec: f32,
+    bars: BarCounter,
+    // Bar the current shot started on, and the latest bar heard
+    shot_bar: u32,
+    bar: u32,
+    // Pose being eased away from, and how far through the ease
+    from: CameraPose,
+    blend_sec: f32,
+    pose: CameraPose,
+}
+
+impl Choreographer {
+    /// Starts inactive, at `home`.
+    pub fn new(seed: u64, home: CameraPose) -> Self {
+        Self {
+            home,
+            rng: StdRng::seed_from_u64(seed),
+            active: false,
+            shot: Shot::STILL,
+            shot_sec: 0.0,
+            held_sec: 0.0,
+            bars: BarCounter::default(),
+            shot_bar: 0,
+            bar: 0,
+            from: home,
+            blend_sec: SHOT_BLEND_SEC,
+            pose: home,
+        }
+    }
+
+    /// Turn the choreography on (cutting to a fresh shot) or off (easing
+    /// back to the resting view).
+    pub fn set_active(&mut self, active: bool) {
+        if active == self.active {
+            return;
+        }
+        self.active = active;
+        let shot = if active {
+            Shot::random(&mut self.rng)
+        } else {
+            Shot::STILL
+        };
+        self.cut_to(shot);
+    }
+
+    /// Step `dt_sec` with the music at `clock` and return the camera pose.
+    pub fn update(&mut self, dt_sec: f32, clock: &ClockPulse) -> CameraPose {
+        let dt_sec = dt_sec.max(0.0);
+        self.bar = self.bars.update(clock);
+        self.held_sec += dt_sec;
+        if self.active
+            && (self.bar - self.shot_bar >= SECTION_BARS || self.held_sec >= MAX_SHOT_SEC)
+        {
+            let shot = Shot::random(&mut self.rng);
+            self.cut_to(shot);
+        }
+
+        self.shot_sec += dt_sec * (1.0 + BEAT_SURGE * clock.beat);
+        self.blend_sec = (self.blend_sec + dt_sec).min(SHOT_BLEND_SEC);
+        let t = self.blend_sec / SHOT_BLEND_SEC;
+        let ease = t * t * (3.0 - 2.0 * t);
+        self.pose = self
+            .from
+            .lerp(self.shot.pose(self.home, self.shot_sec), ease);
+        self.pose
+    }
+
+    fn cut_to(&mut self, shot: Shot) {
+        self.from = self.pose;
+        self.blend_sec = 0.0;
+        self.shot = shot;
+        self.shot_sec = 0.0;
+        self.held_sec = 0.0;
+        self.shot_bar = self.bar;
+    }
+}
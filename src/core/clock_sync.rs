@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+/// MIDI clock resolution: pulses per quarter note.
+pub const MIDI_CLOCK_PPQ: u64 = 24;
+
+/// Pulses per engine grid step (an eighth note).
+pub const PULSES_PER_STEP: u64 = MIDI_CLOCK_PPQ / 2;
+
+/// Clock silent for this long counts as gone (cable pulled, sender quit).
+pub const CLOCK_TIMEOUT_SEC: f64 = 0.5;
+
+/// Pulses averaged for the tempo estimate (two beats): long enough to
+/// smooth USB jitter, short enough to follow a DJ's pitch fader.
+const TEMPO_WINDOW_PULSES: usize = 2 * MIDI_CLOCK_PPQ as usize;
+
+/// Fewest pulse intervals before the tempo is trusted.
+const MIN_TEMPO_INTERVALS: usize = 6;
+
+const STATUS_CLOCK: u8 = 0xF8;
+const STATUS_START: u8 = 0xFA;
+const STATUS_CONTINUE: u8 = 0xFB;
+const STATUS_STOP: u8 = 0xFC;
+
+/// Where an external transport is: grid step `step` (eighth notes since it
+/// started) fell at `at_sec`, and it is running at `bpm`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransportLock {
+    pub bpm: f32,
+    pub step: u64,
+    pub at_sec: f64,
+}
+
+impl TransportLock {
+    /// Length of one grid step at the transport's tempo.
+    pub fn step_duration_sec(&self) -> f64 {
+        60.0 / self.bpm.max(1.0) as f64 / 2.0
+    }
+}
+
+/// Follows incoming MIDI clock (Start/Continue/Stop and 24-ppq pulses) to
+/// recover the sender's tempo and beat phase.
+///
+/// Times are seconds on whatever clock the caller stamps messages with
+/// (the web frontend converts them to the audio clock); the first pulse
+/// after Start is the downbeat.
+#[derive(Clone, Debug, Default)]
+pub struct MidiClockFollower {
+    running: bool,
+    /// Pulses since Start
+    pulses: u64,
+    /// Times of the latest pulses, oldest first
+    recent: VecDeque<f64>,
+    /// Latest step boundary: (step, time)
+    anchor: Option<(u64, f64)>,
+}
+
+impl MidiClockFollower {
+    /// Feed one MIDI message's status byte received at `at_sec`; other
+    /// messages are ignored.
+    pub fn handle(&mut self, status: u8, at_sec: f64) {
+        match status {
+            STATUS_START => {
+                self.running = true;
+                self.pulses = 0;
+                self.anchor = None;
+            }
+            STATUS_CONTINUE => self.running = true,
+            STATUS_STOP => self.running = false,
+            STATUS_CLOCK => {
+                // Pulses keep the tempo fresh even while stopped
+                if self.recent.len() == TEMPO_WINDOW_PULSES + 1 {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back(at_sec);
+                if self.running {
+                    if self.pulses.is_multiple_of(PULSES_PER_STEP) {
+                        self.anchor = Some((self.pulses / PULSES_PER_STEP, at_sec));
+                    }
+                    self.pulses += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Forget everything (e.g. when the input changes).
+    pub fn reset(&mut self) {
+        *self = MidiClockFollower::default();
+    }
+
+    /// Sender's tempo from the average pulse interval, once enough pulses
+    /// have arrived.
+    pub fn bpm(&self) -> Option<f32> {
+        let intervals = self.recent.len().checked_sub(1)?;
+        if intervals < MIN_TEMPO_INTERVALS {
+            return None;
+        }
+        let span = self.recent.back()? - self.recent.front()?;
+        let pulse_sec = span / intervals as f64;
+        (pulse_sec > 0.0).then(|| (60.0 / (pulse_sec * MIDI_CLOCK_PPQ as f64)) as f32)
+    }
+
+    /// Transport position to lock the engine's grid to: only while running,
+    /// with a known tempo and a pulse within `CLOCK_TIMEOUT_SEC` of `now_sec`.
+    pub fn lock(&self, now_sec: f64) -> Option<TransportLock> {
+        let last = *self.recent.back()?;
+        if !self.running || now_sec - last > CLOCK_TIMEOUT_SEC {
+            return None;
+        }
+        let (step, at_sec) = self.anchor?;
+        Some(TransportLock {
+            bpm: self.bpm()?,
+            step,
+            at_sec,
+        })
+    }
+}
@@ -0,0 +1,117 @@
+/// Largest custom shader accepted, in bytes.
+pub const MAX_CUSTOM_SHADER_BYTES: usize = 64 * 1024;
+
+/// Entry point appended after the custom code, which the `Custom` visualizer
+/// draws with: it calls the user's `custom` and finishes the colour as every
+/// built-in visualizer does.
+pub const CUSTOM_ENTRY_WGSL: &str = "
+@fragment
+fn fs_custom(inp: VsOut) -> @location(0) vec4<f32> {
+    let uv = breathe(inp.uv);
+    return finish(custom(uv, centred(uv)), uv);
+}
+";
+
+/// Why a custom shader was turned away before compiling.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CustomShaderError {
+    Empty,
+    TooLong,
+    /// It doesn't define `fn custom(`
+    MissingFunction,
+    /// It declares bindings or entry points of its own (the attribute found)
+    Declares(&'static str),
+}
+
+impl std::fmt::Display for CustomShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CustomShaderError::Empty => write!(f, "the shader is empty"),
+            CustomShaderError::TooLong => {
+                write!(
+                    f,
+                    "the shader is over {} KB",
+                    MAX_CUSTOM_SHADER_BYTES / 1024
+                )
+            }
+            CustomShaderError::MissingFunction => write!(
+                f,
+                "define fn custom(uv: vec2<f32>, p: vec2<f32>) -> vec3<f32>"
+            ),
+            CustomShaderError::Declares(what) => write!(
+                f,
+                "{what} is not allowed; the waves shader provides the bindings and entry point"
+            ),
+        }
+    }
+}
+
+/// Check a custom shader's shape before it is compiled: something to
+/// compile, not too much of it, the `custom` function, and none of the
+/// bindings or entry points the waves shader already has.
+pub fn check_custom_shader(source: &str) -> Result<(), CustomShaderError> {
+    let code = strip_comments(source);
+    if code.trim().is_empty() {
+        return Err(CustomShaderError::Empty);
+    }
+    if source.len() > MAX_CUSTOM_SHADER_BYTES {
+        return Err(CustomShaderError::TooLong);
+    }
+    for attribute in ["@group", "@binding", "@vertex", "@fragment", "@compute"] {
+        if code.contains(attribute) {
+            return Err(CustomShaderError::Declares(attribute));
+        }
+    }
+    let words = code.split_whitespace().collect::<Vec<_>>().join(" ");
+    let defines = words.contains("fn custom(") || words.contains("fn custom (");
+    if !defines {
+        return Err(CustomShaderError::MissingFunction);
+    }
+    Ok(())
+}
+
+/// The module the waves pipelines are built from: waves.wgsl, then the
+/// custom code, then `CUSTOM_ENTRY_WGSL`.
+pub fn with_custom(waves: &str, custom: &str) -> String {
+    format!("{}\n{custom}\n{CUSTOM_ENTRY_WGSL}", waves.trim_end())
+}
+
+/// Map a 1-based line of `with_custom(waves, ..)` back to the custom code,
+/// or None if it falls in the waves shader or the entry point.
+pub fn custom_line(waves: &str, custom: &str, line: u32) -> Option<u32> {
+    let first = waves.trim_end().lines().count() as u32 + 1;
+    let count = custom.lines().count().max(1) as u32;
+    (first..first + count)
+        .contains(&line)
+        .then(|| line + 1 - first)
+}
+
+/// `source` with `//` and `/* */` comments blanked out.
+fn strip_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('*')) => {
+                chars.next();
+                depth += 1;
+            }
+            ('*', Some('/')) if depth > 0 => {
+                chars.next();
+                depth -= 1;
+            }
+            ('/', Some('/')) if depth == 0 => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            _ if depth > 0 => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
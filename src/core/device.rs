@@ -0,0 +1,87 @@
+/// Sample rates accepted for an explicit request; outside this the audio
+/// system would resample anyway or refuse the context.
+pub const MIN_SAMPLE_RATE: f32 = 8_000.0;
+pub const MAX_SAMPLE_RATE: f32 = 192_000.0;
+
+/// Largest output buffer (frames) that can be asked for.
+pub const MAX_BUFFER_FRAMES: u32 = 16_384;
+
+/// How much output buffering to ask the audio system for: less means lower
+/// latency, more means fewer dropouts on a busy machine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BufferRequest {
+    /// Lowest latency that plays without glitches (the default)
+    Interactive,
+    Balanced,
+    /// Favour uninterrupted playback over latency
+    Playback,
+    /// A buffer of about this many frames
+    Frames(u32),
+}
+
+impl BufferRequest {
+    /// Parse `interactive`, `balanced`, `playback` or a frame count (e.g.
+    /// `?buffer=512`, clamped to `MAX_BUFFER_FRAMES`).
+    pub fn parse(s: &str) -> Option<BufferRequest> {
+        let s = s.trim().to_ascii_lowercase();
+        match s.as_str() {
+            "interactive" => Some(BufferRequest::Interactive),
+            "balanced" => Some(BufferRequest::Balanced),
+            "playback" => Some(BufferRequest::Playback),
+            _ => {
+                let frames: u32 = s.parse().ok()?;
+                (frames > 0).then(|| BufferRequest::Frames(frames.min(MAX_BUFFER_FRAMES)))
+            }
+        }
+    }
+
+    /// Named latency category, for requests that aren't a frame count.
+    pub fn category(&self) -> Option<&'static str> {
+        match self {
+            BufferRequest::Interactive => Some("interactive"),
+            BufferRequest::Balanced => Some("balanced"),
+            BufferRequest::Playback => Some("playback"),
+            BufferRequest::Frames(_) => None,
+        }
+    }
+
+    /// Buffer length in seconds at `sample_rate`, for frame-count requests.
+    pub fn seconds(&self, sample_rate: f32) -> Option<f64> {
+        match self {
+            BufferRequest::Frames(frames) => Some(*frames as f64 / sample_rate.max(1.0) as f64),
+            _ => None,
+        }
+    }
+}
+
+/// What to ask for when opening the audio output; `None` leaves the choice
+/// to the system.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DeviceRequest {
+    pub sample_rate: Option<f32>,
+    pub buffer: Option<BufferRequest>,
+}
+
+impl DeviceRequest {
+    /// Parse a sample rate in Hz (e.g. `?samplerate=44100`); rates outside
+    /// `MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE` are rejected.
+    pub fn parse_sample_rate(s: &str) -> Option<f32> {
+        let rate: f32 = s.trim().parse().ok()?;
+        (MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE)
+            .contains(&rate)
+            .then_some(rate)
+    }
+}
+
+/// First of `ports` (`(id, name)`) whose name contains `wanted`, ignoring
+/// case, so URLs can name a device by part of its label.
+pub fn find_by_name<'a>(ports: &'a [(String, String)], wanted: &str) -> Option<&'a str> {
+    let wanted = wanted.trim().to_lowercase();
+    if wanted.is_empty() {
+        return None;
+    }
+    ports
+        .iter()
+        .find(|(_, name)| name.to_lowercase().contains(&wanted))
+        .map(|(id, _)| id.as_str())
+}
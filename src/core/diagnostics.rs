@@ -0,0 +1,114 @@
+/// How much a problem takes away from the session.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Something optional is missing; everything else works
+    Notice,
+    /// A part runs in a reduced form (e.g. circles instead of WebGPU)
+    Degraded,
+    /// The session can't run
+    Fatal,
+}
+
+impl Severity {
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Notice => "note",
+            Severity::Degraded => "degraded",
+            Severity::Fatal => "failed",
+        }
+    }
+}
+
+/// A problem met while starting up or running.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// What it concerns, e.g. "audio" or "graphics"
+    pub area: &'static str,
+    pub message: String,
+}
+
+/// What went wrong during startup and what the browser can do, gathered so
+/// the player sees a reason instead of a dead page and a bug report can
+/// carry the lot.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    problems: Vec<Diagnostic>,
+    /// Features checked for, and whether each was there
+    capabilities: Vec<(&'static str, bool)>,
+}
+
+impl Diagnostics {
+    pub fn report(&mut self, severity: Severity, area: &'static str, message: impl Into<String>) {
+        self.problems.push(Diagnostic {
+            severity,
+            area,
+            message: message.into(),
+        });
+    }
+
+    /// Note whether `name` is available, replacing an earlier note of it.
+    pub fn capability(&mut self, name: &'static str, available: bool) {
+        match self.capabilities.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, kept)) => *kept = available,
+            None => self.capabilities.push((name, available)),
+        }
+    }
+
+    /// The worst problem so far (the first of equally bad ones).
+    pub fn worst(&self) -> Option<&Diagnostic> {
+        self.problems.iter().rev().max_by_key(|d| d.severity)
+    }
+
+    pub fn is_fatal(&self) -> bool {
+        self.worst().is_some_and(|d| d.severity == Severity::Fatal)
+    }
+
+    /// One line for the status banner: the worst problem, and how many more
+    /// there are. `None` when nothing beyond a notice went wrong.
+    pub fn headline(&self) -> Option<String> {
+        let worst = self.worst().filter(|d| d.severity > Severity::Notice)?;
+        let mut line = format!(
+            "{} {}: {}",
+            capitalise(worst.area),
+            worst.severity.label(),
+            worst.message
+        );
+        match self.problems.len() - 1 {
+            0 => {}
+            1 => line.push_str(" (1 more in Diagnostics)"),
+            n => line.push_str(&format!(" ({n} more in Diagnostics)")),
+        }
+        Some(line)
+    }
+
+    /// Everything as plain lines, for the Diagnostics panel and for pasting
+    /// into a bug report: the capabilities, then each problem in order.
+    pub fn text(&self) -> String {
+        let mut lines: Vec<String> = self
+            .capabilities
+            .iter()
+            .map(|(name, available)| format!("{name}: {}", if *available { "yes" } else { "no" }))
+            .collect();
+        if self.problems.is_empty() {
+            lines.push("No problems".to_string());
+        }
+        for d in &self.problems {
+            lines.push(format!(
+                "[{}] {}: {}",
+                d.severity.label(),
+                d.area,
+                d.message
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+fn capitalise(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,75 @@
+use super::history::Command;
+use super::music::{
+    MusicEngine, AEOLIAN, C_MAJOR_PENTATONIC, DORIAN, IONIAN, LYDIAN, MIXOLYDIAN, PHRYGIAN,
+};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Tempo range the dice may pick from (BPM, multiples of 5).
+pub const DICE_BPM_MIN: f32 = 70.0;
+pub const DICE_BPM_MAX: f32 = 140.0;
+/// Largest detune (in cents, either direction) the dice may pick.
+pub const DICE_DETUNE_MAX_CENTS: f32 = 30.0;
+
+/// Root notes the dice picks from (C4..B4 naturals).
+const DICE_ROOTS: [i32; 7] = [60, 62, 64, 65, 67, 69, 71];
+
+/// Scales the dice picks from. Locrian and the microtonal sets are left out on
+/// purpose so a roll always lands somewhere consonant.
+const DICE_SCALES: [&[f32]; 7] = [
+    IONIAN,
+    DORIAN,
+    PHRYGIAN,
+    LYDIAN,
+    MIXOLYDIAN,
+    AEOLIAN,
+    C_MAJOR_PENTATONIC,
+];
+
+/// Per-parameter lock flags. A locked parameter is left untouched by a roll.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ParamLocks {
+    pub bpm: bool,
+    pub root: bool,
+    pub scale: bool,
+    pub detune: bool,
+    /// Voice sequences (per-voice RNG seeds)
+    pub sequences: bool,
+}
+
+/// Build a single undoable command that re-rolls every unlocked parameter
+/// within safe ranges.
+pub fn roll_dice(engine: &MusicEngine, locks: &ParamLocks, rng: &mut impl Rng) -> Command {
+    let mut cmds = Vec::new();
+    if !locks.bpm {
+        let steps = ((DICE_BPM_MAX - DICE_BPM_MIN) / 5.0) as u32;
+        let bpm = DICE_BPM_MIN + 5.0 * rng.gen_range(0..=steps) as f32;
+        cmds.push(Command::SetBpm(bpm));
+    }
+    if !locks.root {
+        cmds.push(Command::SetRootMidi(*DICE_ROOTS.choose(rng).unwrap_or(&60)));
+    }
+    if !locks.scale {
+        cmds.push(Command::SetScale(
+            DICE_SCALES.choose(rng).copied().unwrap_or(IONIAN),
+        ));
+    }
+    if !locks.detune {
+        // Mostly in tune, occasionally a gentle microtonal offset
+        let cents = if rng.gen::<f32>() < 0.7 {
+            0.0
+        } else {
+            (rng.gen_range(-DICE_DETUNE_MAX_CENTS..=DICE_DETUNE_MAX_CENTS) / 5.0).round() * 5.0
+        };
+        cmds.push(Command::SetDetune(cents));
+    }
+    if !locks.sequences {
+        for voice in 0..engine.voices.len() {
+            cmds.push(Command::Reseed {
+                voice,
+                seed: Some(rng.gen()),
+            });
+        }
+    }
+    Command::Batch(cmds)
+}
@@ -0,0 +1,145 @@
+use super::choreography::CameraPose;
+use super::musical_clock::ClockPulse;
+use glam::{Mat4, Vec3};
+
+/// BroadcastChannel the control window and its display windows share.
+pub const DISPLAY_CHANNEL: &str = "geno-1-displays";
+
+/// Most windows one span can be split across.
+pub const MAX_DISPLAYS: u32 = 8;
+
+/// One window's part of a scene spanned across several side by side
+/// (`?display=<n>/<count>`, n from 1 at the left).
+///
+/// Each window draws its own tile of one wide camera, so the 3D layers line
+/// up across projector edges as if drawn on a single canvas `count` times as
+/// wide.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplaySlice {
+    /// 0-based, from the left
+    pub index: u32,
+    pub count: u32,
+}
+
+impl DisplaySlice {
+    /// Parse `"<n>/<count>"`, with n from 1 and count up to `MAX_DISPLAYS`.
+    pub fn parse(s: &str) -> Option<DisplaySlice> {
+        let (n, count) = s.trim().split_once('/')?;
+        let n: u32 = n.trim().parse().ok()?;
+        let count: u32 = count.trim().parse().ok()?;
+        let fits = (1..=MAX_DISPLAYS).contains(&count) && (1..=count).contains(&n);
+        fits.then(|| DisplaySlice {
+            index: n - 1,
+            count,
+        })
+    }
+
+    /// As `parse` reads it, for the windows the control window opens.
+    pub fn param(&self) -> String {
+        format!("{}/{}", self.index + 1, self.count)
+    }
+
+    /// Projection for this tile: the span's camera (vertical `fov`, each
+    /// window `aspect` wide) narrowed to this window's part of it.
+    pub fn projection(&self, fov: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let n = self.count as f32;
+        let wide = Mat4::perspective_rh(fov, aspect * n, near, far);
+        // Stretch the span n times and slide this tile to the middle
+        let shift = n - 1.0 - 2.0 * self.index as f32;
+        Mat4::from_translation(Vec3::new(shift, 0.0, 0.0))
+            * Mat4::from_scale(Vec3::new(n, 1.0, 1.0))
+            * wide
+    }
+}
+
+/// A note burst fired on the control window this frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DisplayBurst {
+    pub voice: usize,
+    pub position: Vec3,
+    pub frequency_hz: f32,
+    pub velocity: f32,
+}
+
+/// What the control window sends its display windows every frame, so they
+/// draw the same scene from the one engine and audio stream.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisplayFrame {
+    /// Engine-space voice positions
+    pub positions: [[f32; 3]; 3],
+    /// Pulse energy before smoothing (each window smooths it as the control
+    /// window does)
+    pub pulse_energy: [f32; 3],
+    pub clock: ClockPulse,
+    pub camera: CameraPose,
+    /// Swirl centre (uv) and strength
+    pub swirl: [f32; 3],
+    /// Positions in the theme, visualizer and render mode lists
+    pub theme: u32,
+    pub visualizer: u32,
+    pub render_mode: u32,
+    pub bursts: Vec<DisplayBurst>,
+}
+
+// Floats before the bursts, and per burst
+const FRAME_LEN: usize = 3 * 3 + 3 + 4 + 6 + 3 + 3;
+const BURST_LEN: usize = 6;
+
+impl DisplayFrame {
+    /// Flattened for posting as a `Float32Array`.
+    pub fn pack(&self) -> Vec<f32> {
+        let mut out = Vec::with_capacity(FRAME_LEN + BURST_LEN * self.bursts.len());
+        out.extend(self.positions.iter().flatten());
+        out.extend(self.pulse_energy);
+        let c = &self.clock;
+        out.extend([c.beat_phase, c.bar_phase, c.beat, c.downbeat]);
+        out.extend(self.camera.eye.to_array());
+        out.extend(self.camera.target.to_array());
+        out.extend(self.swirl);
+        out.extend([self.theme, self.visualizer, self.render_mode].map(|i| i as f32));
+        for b in &self.bursts {
+            out.push(b.voice as f32);
+            out.extend(b.position.to_array());
+            out.extend([b.frequency_hz, b.velocity]);
+        }
+        out
+    }
+
+    /// Read back what `pack` wrote; None if it is cut short.
+    pub fn unpack(data: &[f32]) -> Option<DisplayFrame> {
+        if data.len() < FRAME_LEN || !(data.len() - FRAME_LEN).is_multiple_of(BURST_LEN) {
+            return None;
+        }
+        let (head, bursts) = data.split_at(FRAME_LEN);
+        let v3 = |at: usize| Vec3::new(head[at], head[at + 1], head[at + 2]);
+        Some(DisplayFrame {
+            positions: [0, 3, 6].map(|at| v3(at).to_array()),
+            pulse_energy: [head[9], head[10], head[11]],
+            clock: ClockPulse {
+                beat_phase: head[12],
+                bar_phase: head[13],
+                beat: head[14],
+                downbeat: head[15],
+            },
+            camera: CameraPose {
+                eye: v3(16),
+                target: v3(19),
+            },
+            swirl: [head[22], head[23], head[24]],
+            theme: head[25] as u32,
+            visualizer: head[26] as u32,
+            render_mode: head[27] as u32,
+            bursts: bursts
+                .as_chunks::<BURST_LEN>()
+                .0
+                .iter()
+                .map(|b| DisplayBurst {
+                    voice: b[0] as usize,
+                    position: Vec3::new(b[1], b[2], b[3]),
+                    frequency_hz: b[4],
+                    velocity: b[5],
+                })
+                .collect(),
+        })
+    }
+}
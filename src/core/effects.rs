@@ -0,0 +1,147 @@
+use super::filter::{FilterMode, SvfState};
+
+/// Cutoff of the lowpass inside the delay's feedback loop (darker each repeat).
+pub const DELAY_TONE_HZ: f32 = 1400.0;
+
+/// Magnitude below which feedback state is flushed to zero (about -400 dB):
+/// far below audibility, far above where floats go subnormal and slow.
+pub const DENORMAL_FLOOR: f32 = 1e-20;
+
+/// Corner of the DC-blocking highpass; well under anything musical.
+pub const DC_BLOCK_HZ: f32 = 10.0;
+
+/// Zero `x` if it has decayed below `DENORMAL_FLOOR`, so long silent tails
+/// settle to exact zero instead of grinding through subnormal arithmetic.
+pub fn flush_denormal(x: f32) -> f32 {
+    if x.abs() < DENORMAL_FLOOR {
+        0.0
+    } else {
+        x
+    }
+}
+
+/// One-pole DC blocker (`y = x - x₁ + r·y₁`): removes offset that builds up
+/// in long tails without touching the audible band.
+#[derive(Clone, Copy, Debug)]
+pub struct DcBlocker {
+    r: f32,
+    x1: f32,
+    y1: f32,
+}
+
+impl DcBlocker {
+    pub fn new(sample_rate: f32) -> DcBlocker {
+        DcBlocker {
+            r: (-std::f32::consts::TAU * DC_BLOCK_HZ / sample_rate.max(1.0)).exp(),
+            x1: 0.0,
+            y1: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let out = flush_denormal(input - self.x1 + self.r * self.y1);
+        self.x1 = input;
+        self.y1 = out;
+        out
+    }
+}
+
+/// Delay time for `beats` beats at `bpm`.
+pub fn beat_delay_sec(bpm: f32, beats: f32) -> f32 {
+    60.0 / bpm.max(1.0) * beats
+}
+
+/// Feedback echo with a lowpass in the loop, the offline counterpart of the
+/// web delay bus (delay → tone → feedback → delay, wet taken after the tone).
+#[derive(Clone, Debug)]
+pub struct TempoDelay {
+    buf: Vec<f32>,
+    pos: usize,
+    tone: SvfState,
+    sample_rate: f32,
+}
+
+impl TempoDelay {
+    pub fn new(sample_rate: f32, delay_sec: f32) -> TempoDelay {
+        TempoDelay {
+            buf: vec![0.0; ((delay_sec * sample_rate) as usize).max(1)],
+            pos: 0,
+            tone: SvfState::default(),
+            sample_rate,
+        }
+    }
+
+    /// Push one input sample and return the wet echo; `feedback` below 1 keeps
+    /// the repeats decaying.
+    pub fn process(&mut self, input: f32, feedback: f32) -> f32 {
+        let delayed = self.buf[self.pos];
+        let wet = self.tone.process(
+            delayed,
+            FilterMode::Lowpass,
+            DELAY_TONE_HZ,
+            1.0,
+            self.sample_rate,
+        );
+        self.buf[self.pos] = flush_denormal(input + wet * feedback);
+        self.pos = (self.pos + 1) % self.buf.len();
+        wet
+    }
+}
+
+/// Delay-line lengths (ms) of the reverb network; mutually prime in samples
+/// at common rates so the echoes don't pile up on a comb.
+const FDN_LINES_MS: [f32; 4] = [29.7, 37.1, 41.1, 43.7];
+
+/// Four-line feedback delay network reverb with a Householder mix and
+/// damping in the loop: a dense, darkening tail without an impulse response.
+#[derive(Clone, Debug)]
+pub struct FdnReverb {
+    lines: [FdnLine; 4],
+    damping: f32,
+}
+
+#[derive(Clone, Debug)]
+struct FdnLine {
+    buf: Vec<f32>,
+    pos: usize,
+    /// Loop gain giving the requested decay time for this line's length
+    gain: f32,
+    lowpass: f32,
+}
+
+impl FdnReverb {
+    /// `rt60_sec` is the time for the tail to fall 60 dB; `damping` (0..1)
+    /// is how much high end each pass through the loop loses.
+    pub fn new(sample_rate: f32, rt60_sec: f32, damping: f32) -> FdnReverb {
+        FdnReverb {
+            lines: FDN_LINES_MS.map(|ms| {
+                let len = ((ms / 1000.0 * sample_rate) as usize).max(1);
+                FdnLine {
+                    buf: vec![0.0; len],
+                    pos: 0,
+                    gain: 10f32.powf(-3.0 * len as f32 / (rt60_sec.max(0.01) * sample_rate)),
+                    lowpass: 0.0,
+                }
+            }),
+            damping: damping.clamp(0.0, 0.99),
+        }
+    }
+
+    /// Push one input sample; returns the four (mutually decorrelated) line
+    /// outputs, suitable for spreading over output channels.
+    pub fn process(&mut self, input: f32) -> [f32; 4] {
+        for line in self.lines.iter_mut() {
+            line.lowpass = flush_denormal(
+                line.lowpass + (line.buf[line.pos] - line.lowpass) * (1.0 - self.damping),
+            );
+        }
+        let outs = self.lines.each_ref().map(|line| line.lowpass);
+        // Householder reflection: lossless, every line feeds every other
+        let half_sum = outs.iter().sum::<f32>() * 0.5;
+        for (line, out) in self.lines.iter_mut().zip(outs) {
+            line.buf[line.pos] = flush_denormal(input + (out - half_sum) * line.gain);
+            line.pos = (line.pos + 1) % line.buf.len();
+        }
+        outs
+    }
+}
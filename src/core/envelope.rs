@@ -0,0 +1,84 @@
+/// Shape of the decay and release segments (the attack is always linear).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvelopeCurve {
+    /// Straight-line ramps that land exactly on their target
+    Linear,
+    /// RC-style approach (Web Audio `setTargetAtTime` with a quarter of the
+    /// segment as time constant): fast at first, then easing off
+    Exponential,
+}
+
+/// Attack/decay/sustain/release amplitude envelope of a voice's notes.
+///
+/// The note's duration is the gate: attack and decay run while it is held
+/// (cut short if the gate closes first), then the release fades from
+/// wherever the level got to. Levels are relative to the note velocity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adsr {
+    pub attack_sec: f32,
+    pub decay_sec: f32,
+    /// Level held after the decay (0..1)
+    pub sustain: f32,
+    pub release_sec: f32,
+    pub curve: EnvelopeCurve,
+}
+
+impl Adsr {
+    /// Short pluck that settles to a moderate sustain.
+    pub const DEFAULT: Adsr = Adsr {
+        attack_sec: 0.01,
+        decay_sec: 0.12,
+        sustain: 0.6,
+        release_sec: 0.1,
+        curve: EnvelopeCurve::Exponential,
+    };
+
+    /// Time constant used for exponential segments of length `seg_sec`.
+    pub fn time_constant(seg_sec: f32) -> f32 {
+        (seg_sec / 4.0).max(1e-4)
+    }
+
+    /// Level `t_sec` after note-on while the gate is still open.
+    fn held_level(&self, t_sec: f32) -> f32 {
+        if t_sec < self.attack_sec {
+            return t_sec / self.attack_sec;
+        }
+        let td = t_sec - self.attack_sec;
+        let s = self.sustain.clamp(0.0, 1.0);
+        if self.decay_sec <= 0.0 {
+            return s;
+        }
+        match self.curve {
+            EnvelopeCurve::Linear => 1.0 + (s - 1.0) * (td / self.decay_sec).min(1.0),
+            EnvelopeCurve::Exponential => {
+                s + (1.0 - s) * (-td / Self::time_constant(self.decay_sec)).exp()
+            }
+        }
+    }
+
+    /// Envelope level (0..1) `t_sec` after note-on for a gate of `gate_sec`.
+    pub fn level_at(&self, t_sec: f32, gate_sec: f32) -> f32 {
+        if t_sec < 0.0 {
+            return 0.0;
+        }
+        if t_sec < gate_sec {
+            return self.held_level(t_sec);
+        }
+        let tr = t_sec - gate_sec;
+        if tr >= self.release_sec {
+            return 0.0;
+        }
+        let from = self.held_level(gate_sec);
+        match self.curve {
+            EnvelopeCurve::Linear => from * (1.0 - tr / self.release_sec),
+            EnvelopeCurve::Exponential => {
+                from * (-tr / Self::time_constant(self.release_sec)).exp()
+            }
+        }
+    }
+
+    /// Level at the moment the gate closes (where the release starts).
+    pub fn gate_level(&self, gate_sec: f32) -> f32 {
+        self.held_level(gate_sec.max(0.0))
+    }
+}
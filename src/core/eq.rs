@@ -0,0 +1,168 @@
+use super::effects::flush_denormal;
+use std::f32::consts::{FRAC_1_SQRT_2, TAU};
+
+/// Shape of one master EQ band.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EqBandKind {
+    LowShelf,
+    Peaking,
+    HighShelf,
+}
+
+/// One band of the master EQ.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EqBand {
+    pub kind: EqBandKind,
+    /// Corner (shelves) or center (peaking) frequency
+    pub freq_hz: f32,
+    /// Boost (positive) or cut (negative)
+    pub gain_db: f32,
+    /// Bandwidth of a peaking band; shelves use a fixed slope (as Web Audio does)
+    pub q: f32,
+}
+
+/// Number of bands in `MasterEq`: low shelf, mid peak, high shelf.
+pub const EQ_BANDS: usize = 3;
+
+/// Largest boost or cut a band accepts.
+pub const MAX_EQ_GAIN_DB: f32 = 12.0;
+
+/// Three-band parametric EQ on the master bus, for compensating speakers
+/// and rooms (after the compressor, so the limiter still catches boosts).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MasterEq {
+    pub bands: [EqBand; EQ_BANDS],
+}
+
+impl MasterEq {
+    /// All bands at 0 dB: leaves the signal untouched.
+    pub const FLAT: MasterEq = MasterEq {
+        bands: [
+            EqBand {
+                kind: EqBandKind::LowShelf,
+                freq_hz: 120.0,
+                gain_db: 0.0,
+                q: FRAC_1_SQRT_2,
+            },
+            EqBand {
+                kind: EqBandKind::Peaking,
+                freq_hz: 1000.0,
+                gain_db: 0.0,
+                q: 0.9,
+            },
+            EqBand {
+                kind: EqBandKind::HighShelf,
+                freq_hz: 8000.0,
+                gain_db: 0.0,
+                q: FRAC_1_SQRT_2,
+            },
+        ],
+    };
+
+    /// Flat EQ with band gains from `"low,mid,high"` in dB (e.g. from a URL),
+    /// clamped to ±`MAX_EQ_GAIN_DB`.
+    pub fn parse(s: &str) -> Option<MasterEq> {
+        let gains: Vec<f32> = s
+            .split(',')
+            .map(|g| g.trim().parse::<f32>().ok().filter(|g| g.is_finite()))
+            .collect::<Option<_>>()?;
+        if gains.len() != EQ_BANDS {
+            return None;
+        }
+        let mut eq = MasterEq::FLAT;
+        for (band, gain_db) in gains.into_iter().enumerate() {
+            eq.set_gain(band, gain_db);
+        }
+        Some(eq)
+    }
+
+    /// `parse`'s form of the band gains.
+    pub fn param(&self) -> String {
+        let gains: Vec<String> = self.bands.iter().map(|b| b.gain_db.to_string()).collect();
+        gains.join(",")
+    }
+
+    /// Set one band's gain (clamped); out-of-range bands are ignored.
+    pub fn set_gain(&mut self, band: usize, gain_db: f32) {
+        if let Some(b) = self.bands.get_mut(band) {
+            b.gain_db = gain_db.clamp(-MAX_EQ_GAIN_DB, MAX_EQ_GAIN_DB);
+        }
+    }
+
+    /// Filter interleaved audio in place with a biquad cascade per channel
+    /// (the offline counterpart of the web `BiquadFilterNode` chain).
+    pub fn process_interleaved(&self, samples: &mut [f32], channels: usize, sample_rate: u32) {
+        let filters: Vec<Biquad> = self
+            .bands
+            .iter()
+            .map(|b| Biquad::for_band(b, sample_rate as f32))
+            .collect();
+        for ch in 0..channels.max(1) {
+            let mut cascade = filters.clone();
+            for s in samples.iter_mut().skip(ch).step_by(channels.max(1)) {
+                *s = cascade.iter_mut().fold(*s, |x, f| f.process(x));
+            }
+        }
+    }
+}
+
+/// Direct-form I biquad with Audio EQ Cookbook coefficients (normalized by a0).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+impl Biquad {
+    pub fn for_band(band: &EqBand, sample_rate: f32) -> Biquad {
+        let a = 10f32.powf(band.gain_db / 40.0);
+        let w0 = TAU * band.freq_hz.min(sample_rate * 0.49) / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * band.q.max(0.1));
+        let sq = 2.0 * a.sqrt() * alpha;
+        let (b, a0, a1, a2) = match band.kind {
+            EqBandKind::Peaking => (
+                [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+                1.0 + alpha / a,
+                -2.0 * cos,
+                1.0 - alpha / a,
+            ),
+            EqBandKind::LowShelf => (
+                [
+                    a * ((a + 1.0) - (a - 1.0) * cos + sq),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - sq),
+                ],
+                (a + 1.0) + (a - 1.0) * cos + sq,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - sq,
+            ),
+            EqBandKind::HighShelf => (
+                [
+                    a * ((a + 1.0) + (a - 1.0) * cos + sq),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - sq),
+                ],
+                (a + 1.0) - (a - 1.0) * cos + sq,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - sq,
+            ),
+        };
+        Biquad {
+            b: b.map(|c| c / a0),
+            a: [a1 / a0, a2 / a0],
+            ..Biquad::default()
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let out = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [flush_denormal(out), self.y[0]];
+        out
+    }
+}
@@ -0,0 +1,81 @@
+use super::effects::flush_denormal;
+use std::f32::consts::PI;
+
+/// Response of a voice's filter stage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterMode {
+    Lowpass,
+    Bandpass,
+}
+
+/// Per-note filter of a voice: a resonant lowpass/bandpass whose cutoff
+/// follows the note's pitch and its amplitude envelope.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoiceFilter {
+    pub mode: FilterMode,
+    /// Cutoff (or center) frequency for a note at `KEY_TRACK_REF_HZ` with
+    /// the envelope closed
+    pub cutoff_hz: f32,
+    /// Resonance as filter Q (0.707 = no peak)
+    pub resonance: f32,
+    /// Octaves the cutoff opens by at the envelope's peak (may be negative)
+    pub env_octaves: f32,
+    /// How far the cutoff follows the note pitch: 0 = fixed, 1 = moves
+    /// octave for octave with the note
+    pub key_tracking: f32,
+}
+
+/// Pitch at which key tracking leaves the cutoff unchanged (middle C).
+pub const KEY_TRACK_REF_HZ: f32 = 261.63;
+
+const MIN_CUTOFF_HZ: f32 = 20.0;
+const MAX_CUTOFF_HZ: f32 = 20_000.0;
+
+impl VoiceFilter {
+    /// Cutoff for a note at `frequency_hz` with the envelope closed.
+    pub fn base_cutoff(&self, frequency_hz: f32) -> f32 {
+        let ratio = (frequency_hz / KEY_TRACK_REF_HZ).max(1e-3);
+        (self.cutoff_hz * ratio.powf(self.key_tracking)).clamp(MIN_CUTOFF_HZ, MAX_CUTOFF_HZ)
+    }
+
+    /// Cutoff for a note at `frequency_hz` when the envelope is at `env_level` (0..1).
+    pub fn cutoff_at(&self, frequency_hz: f32, env_level: f32) -> f32 {
+        (self.base_cutoff(frequency_hz) * (self.env_octaves * env_level).exp2())
+            .clamp(MIN_CUTOFF_HZ, MAX_CUTOFF_HZ)
+    }
+}
+
+/// Topology-preserving state-variable filter (one per sounding note) for
+/// the offline renderers; the web frontend uses a `BiquadFilterNode`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SvfState {
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+impl SvfState {
+    /// Filter one sample with the given cutoff and Q.
+    pub fn process(
+        &mut self,
+        input: f32,
+        mode: FilterMode,
+        cutoff_hz: f32,
+        q: f32,
+        sample_rate: f32,
+    ) -> f32 {
+        let g = (PI * cutoff_hz.min(sample_rate * 0.49) / sample_rate).tan();
+        let k = 1.0 / q.max(0.1);
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        let v3 = input - self.ic2eq;
+        let v1 = a1 * self.ic1eq + a2 * v3;
+        let v2 = self.ic2eq + a2 * self.ic1eq + a3 * v3;
+        self.ic1eq = flush_denormal(2.0 * v1 - self.ic1eq);
+        self.ic2eq = flush_denormal(2.0 * v2 - self.ic2eq);
+        match mode {
+            FilterMode::Lowpass => v2,
+            FilterMode::Bandpass => v1,
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use super::envelope::{Adsr, EnvelopeCurve};
+use std::f32::consts::TAU;
+
+/// Two-operator FM: a sine modulator bends the phase of the voice's
+/// oscillator (the carrier).
+///
+/// The depth follows its own envelope, so notes can start bright and mellow
+/// out (bells, electric pianos) independently of the amplitude envelope.
+/// Frontends may implement it as frequency modulation with a peak deviation
+/// of `index × modulator frequency`, which sounds the same.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FmParams {
+    /// Modulator frequency as a multiple of the carrier's
+    pub ratio: f32,
+    /// Peak modulation index (radians of phase deviation)
+    pub index: f32,
+    /// Shape of the index over the note, scaled by `index`
+    pub index_envelope: Adsr,
+}
+
+impl FmParams {
+    /// Inharmonic ratio with a long fading index: struck-metal bell.
+    pub const BELL: FmParams = FmParams {
+        ratio: 3.5,
+        index: 4.0,
+        index_envelope: Adsr {
+            attack_sec: 0.001,
+            decay_sec: 1.2,
+            sustain: 0.0,
+            release_sec: 0.5,
+            curve: EnvelopeCurve::Exponential,
+        },
+    };
+
+    /// 1:1 with a quick bright attack settling to a soft tine.
+    pub const ELECTRIC_PIANO: FmParams = FmParams {
+        ratio: 1.0,
+        index: 2.5,
+        index_envelope: Adsr {
+            attack_sec: 0.002,
+            decay_sec: 0.6,
+            sustain: 0.15,
+            release_sec: 0.3,
+            curve: EnvelopeCurve::Exponential,
+        },
+    };
+
+    pub fn modulator_hz(&self, carrier_hz: f32) -> f32 {
+        carrier_hz * self.ratio
+    }
+
+    /// Modulation index `t_sec` after note-on for a gate of `gate_sec`.
+    pub fn index_at(&self, t_sec: f32, gate_sec: f32) -> f32 {
+        self.index * self.index_envelope.level_at(t_sec, gate_sec)
+    }
+
+    /// Carrier phase offset in cycles `t_sec` after note-on.
+    pub fn phase_offset(&self, carrier_hz: f32, t_sec: f32, gate_sec: f32) -> f32 {
+        let modulator = (TAU * self.modulator_hz(carrier_hz) * t_sec).sin();
+        self.index_at(t_sec, gate_sec) * modulator / TAU
+    }
+}
@@ -0,0 +1,267 @@
+// Per-frame smoothing and FX modulation math driven by the render loop.
+//
+// Pure functions of their inputs and `dt`, so they can be exercised without a
+// browser; `FrameContext::frame` wires the results to Web Audio and the GPU.
+
+// Exponential decay rate for internal pulse energy
+pub const PULSE_ENERGY_DECAY_PER_SEC: f32 = 1.6;
+
+// Most pulse energy stacked notes can build up
+pub const MAX_PULSE_ENERGY: f32 = 1.8;
+
+// Target smoothing time constants (seconds)
+pub const PULSE_RISE_TAU_SEC: f32 = 0.10;
+pub const PULSE_FALL_TAU_SEC: f32 = 0.45;
+
+// Glide for continuously modulated audio params, so per-frame updates
+// don't step (zipper noise); short enough to still track the pointer
+pub const PARAM_SMOOTH_TAU_SEC: f32 = 0.02;
+
+// Pointer speed clamp (normalized units per second)
+pub const POINTER_SPEED_MAX: f32 = 10.0;
+
+// Inertial swirl spring parameters
+pub const SWIRL_OMEGA: f32 = 1.1; // natural frequency
+pub const SWIRL_DAMPING_RATIO: f32 = 0.5; // 0..1 critical at 1
+pub const SWIRL_MAX_STEP_PER_SEC: f32 = 0.50; // cap motion per second (in uv units)
+
+// Swirl energy blend weights
+pub const SWIRL_TARGET_WEIGHT_POINTER: f32 = 0.2;
+pub const SWIRL_TARGET_WEIGHT_VELOCITY: f32 = 0.35;
+pub const SWIRL_TARGET_CLICK_BONUS: f32 = 0.5;
+pub const SWIRL_ENERGY_BLEND_ALPHA: f32 = 0.15; // new = (1-α)*old + α*target
+
+// Per-voice spatial sends mapping
+pub const DIST_NORM_DIVISOR: f32 = 2.5;
+pub const D_SEND_BASE: f32 = 0.15;
+pub const D_SEND_SPAN: f32 = 0.85;
+pub const R_SEND_BASE: f32 = 0.25;
+pub const R_SEND_SPAN: f32 = 0.75;
+pub const SEND_BOOST_COEFF: f32 = 0.8;
+pub const D_SEND_CLAMP_MAX: f32 = 1.2;
+pub const R_SEND_CLAMP_MAX: f32 = 1.5;
+
+// Voice level mapping
+pub const LEVEL_BASE: f32 = 0.55;
+pub const LEVEL_SPAN: f32 = 0.45;
+
+// Global FX mapping weights
+pub const FX_REVERB_BASE: f32 = 0.35;
+pub const FX_REVERB_SPAN: f32 = 0.65;
+
+pub const FX_DELAY_WET_BASE: f32 = 0.15;
+pub const FX_DELAY_WET_SWIRL: f32 = 0.55;
+pub const FX_DELAY_WET_ECHO: f32 = 0.30;
+
+pub const FX_DELAY_FB_BASE: f32 = 0.35;
+pub const FX_DELAY_FB_SWIRL: f32 = 0.35;
+pub const FX_DELAY_FB_ECHO: f32 = 0.25;
+
+pub const FX_SAT_DRIVE_MIN: f32 = 0.2;
+pub const FX_SAT_DRIVE_MAX: f32 = 3.0;
+pub const FX_SAT_WET_BASE: f32 = 0.15;
+pub const FX_SAT_WET_SPAN: f32 = 0.85;
+
+/// One-pole smoothing: move `current` toward `target` as an exponential with
+/// time constant `tau_sec` would over `dt_sec` (the same curve as Web Audio's
+/// `setTargetAtTime`, so native and web smoothing agree).
+#[inline]
+pub fn one_pole(current: f32, target: f32, dt_sec: f32, tau_sec: f32) -> f32 {
+    if tau_sec <= 0.0 {
+        return target;
+    }
+    current + (target - current) * (1.0 - (-dt_sec.max(0.0) / tau_sec).exp())
+}
+
+/// Ease per-voice visual pulses toward their (decaying) note energy.
+///
+/// `pulse_energy` accumulates note velocities and decays exponentially; the
+/// displayed `pulses` follow it with separate rise/fall time constants.
+#[inline]
+pub fn smooth_pulses(pulses: &mut [f32], pulse_energy: &mut [f32; 3], dt_sec: f32) {
+    let n = pulses.len().min(3);
+    let energy_decay = (-dt_sec * PULSE_ENERGY_DECAY_PER_SEC).exp();
+    for e in pulse_energy.iter_mut().take(n) {
+        *e *= energy_decay;
+    }
+    for (p, e) in pulses.iter_mut().zip(pulse_energy.iter()).take(n) {
+        let target = e.clamp(0.0, 1.5);
+        let tau = if target > *p {
+            PULSE_RISE_TAU_SEC
+        } else {
+            PULSE_FALL_TAU_SEC
+        };
+        *p = one_pole(*p, target, dt_sec, tau);
+    }
+}
+
+/// Advance the damped spring that makes the swirl trail the pointer.
+///
+/// The first call snaps to `target_uv`. Motion per step is capped and the
+/// position is kept inside the unit square.
+pub fn step_inertial_swirl(
+    initialized: &mut bool,
+    swirl_pos: &mut [f32; 2],
+    swirl_vel: &mut [f32; 2],
+    target_uv: [f32; 2],
+    dt_sec: f32,
+) {
+    if !*initialized {
+        *swirl_pos = target_uv;
+        swirl_vel[0] = 0.0;
+        swirl_vel[1] = 0.0;
+        *initialized = true;
+        return;
+    }
+    let omega = SWIRL_OMEGA;
+    let k = omega * omega;
+    let c = 2.0 * omega * SWIRL_DAMPING_RATIO;
+    let dx = target_uv[0] - swirl_pos[0];
+    let dy = target_uv[1] - swirl_pos[1];
+    let ax = k * dx - c * swirl_vel[0];
+    let ay = k * dy - c * swirl_vel[1];
+    swirl_vel[0] += ax * dt_sec;
+    swirl_vel[1] += ay * dt_sec;
+    let mut nx = swirl_pos[0] + swirl_vel[0] * dt_sec;
+    let mut ny = swirl_pos[1] + swirl_vel[1] * dt_sec;
+    let sdx = nx - swirl_pos[0];
+    let sdy = ny - swirl_pos[1];
+    let step = (sdx * sdx + sdy * sdy).sqrt();
+    let max_step = SWIRL_MAX_STEP_PER_SEC * dt_sec;
+    if step > max_step {
+        let inv = 1.0 / (step + 1e-6);
+        nx = swirl_pos[0] + sdx * inv * max_step;
+        ny = swirl_pos[1] + sdy * inv * max_step;
+    }
+    swirl_pos[0] = nx.clamp(0.0, 1.0);
+    swirl_pos[1] = ny.clamp(0.0, 1.0);
+}
+
+/// Blend the swirl energy (0..1) toward a target derived from pointer speed,
+/// swirl velocity and whether the pointer is pressed.
+pub fn blend_swirl_energy(
+    energy: f32,
+    prev_uv: [f32; 2],
+    uv: [f32; 2],
+    swirl_vel: [f32; 2],
+    dt_sec: f32,
+    mouse_down: bool,
+) -> f32 {
+    let du = uv[0] - prev_uv[0];
+    let dv = uv[1] - prev_uv[1];
+    let pointer_speed = ((du * du + dv * dv).sqrt() / (dt_sec + 1e-5)).min(POINTER_SPEED_MAX);
+    let swirl_speed = (swirl_vel[0] * swirl_vel[0] + swirl_vel[1] * swirl_vel[1]).sqrt();
+    let target = ((pointer_speed * SWIRL_TARGET_WEIGHT_POINTER)
+        + (swirl_speed * SWIRL_TARGET_WEIGHT_VELOCITY)
+        + if mouse_down {
+            SWIRL_TARGET_CLICK_BONUS
+        } else {
+            0.0
+        })
+    .clamp(0.0, 1.0);
+    (1.0 - SWIRL_ENERGY_BLEND_ALPHA) * energy + SWIRL_ENERGY_BLEND_ALPHA * target
+}
+
+/// Global FX gain targets for one frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FxLevels {
+    pub reverb_wet: f32,
+    pub delay_wet: f32,
+    pub delay_feedback: f32,
+    pub sat_drive: f32,
+    pub sat_wet: f32,
+    pub sat_dry: f32,
+}
+
+impl FxLevels {
+    /// How hard the saturator is colouring the sound: drive above its
+    /// minimum, scaled by the wet mix (0 clean; 0.75 with the pointer in the
+    /// fizziest corner). The composite mirrors it as chromatic aberration
+    /// and grain.
+    pub fn grit(&self) -> f32 {
+        let drive = (self.sat_drive - FX_SAT_DRIVE_MIN) / (FX_SAT_DRIVE_MAX - FX_SAT_DRIVE_MIN);
+        (drive * self.sat_wet).clamp(0.0, 1.0)
+    }
+}
+
+/// Map swirl energy and pointer position to global FX levels.
+///
+/// Reverb follows energy, delay also follows the pointer's distance from the
+/// diagonal ("echo"), and saturation follows its position along it ("fizz").
+pub fn fx_levels(swirl_energy: f32, uv: [f32; 2]) -> FxLevels {
+    let echo = (uv[0] - uv[1]).abs();
+    let fizz = ((uv[0] + uv[1]) * 0.5).clamp(0.0, 1.0);
+    let sat_wet = (FX_SAT_WET_BASE + FX_SAT_WET_SPAN * fizz).clamp(0.0, 1.0);
+    FxLevels {
+        reverb_wet: FX_REVERB_BASE + FX_REVERB_SPAN * swirl_energy,
+        delay_wet: (FX_DELAY_WET_BASE
+            + FX_DELAY_WET_SWIRL * swirl_energy
+            + FX_DELAY_WET_ECHO * echo)
+            .clamp(0.0, 1.0),
+        delay_feedback: (FX_DELAY_FB_BASE
+            + FX_DELAY_FB_SWIRL * swirl_energy
+            + FX_DELAY_FB_ECHO * echo)
+            .clamp(0.0, 0.95),
+        sat_drive: (FX_SAT_DRIVE_MIN
+            + (FX_SAT_DRIVE_MAX - FX_SAT_DRIVE_MIN) * ((fizz - 0.25).clamp(0.0, 1.0)))
+        .clamp(FX_SAT_DRIVE_MIN, FX_SAT_DRIVE_MAX),
+        sat_wet,
+        sat_dry: 1.0 - sat_wet,
+    }
+}
+
+/// A voice's own delay/reverb send levels and its volume, before position
+/// and swirl modulation is layered on top (see `voice_mix`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoiceSends {
+    pub delay: f32,
+    pub reverb: f32,
+    /// Gain on the voice's dry level (1 = as placed)
+    pub level: f32,
+}
+
+impl VoiceSends {
+    pub const DEFAULT: VoiceSends = VoiceSends {
+        delay: D_SEND_BASE,
+        reverb: R_SEND_BASE,
+        level: 1.0,
+    };
+
+    /// Largest send level (or volume) a voice can be set to.
+    pub const MAX: f32 = 1.0;
+
+    /// Every level clamped to 0..`MAX`.
+    pub fn clamped(self) -> VoiceSends {
+        VoiceSends {
+            delay: self.delay.clamp(0.0, Self::MAX),
+            reverb: self.reverb.clamp(0.0, Self::MAX),
+            level: self.level.clamp(0.0, Self::MAX),
+        }
+    }
+}
+
+/// Dry level and effect sends of one voice for one frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoiceMix {
+    pub level: f32,
+    pub delay_send: f32,
+    pub reverb_send: f32,
+}
+
+/// Map a voice's send levels and engine-space position to its mix.
+///
+/// Starting from `sends`, voices further out to the sides echo more, distant
+/// ones are quieter and wetter, and swirl energy boosts both sends. The
+/// voice's own volume scales its level.
+pub fn voice_mix(position: glam::Vec3, swirl_energy: f32, sends: VoiceSends) -> VoiceMix {
+    let dist = (position.x * position.x + position.z * position.z).sqrt();
+    let far = (dist / DIST_NORM_DIVISOR).clamp(0.0, 1.0);
+    let delay = (sends.delay + D_SEND_SPAN * position.x.abs().min(1.0)).clamp(0.0, 1.0);
+    let reverb = (sends.reverb + R_SEND_SPAN * far).clamp(0.0, R_SEND_CLAMP_MAX);
+    let boost = 1.0 + SEND_BOOST_COEFF * swirl_energy;
+    VoiceMix {
+        level: (LEVEL_BASE + LEVEL_SPAN * (1.0 - far)) * sends.level,
+        delay_send: (delay * boost).clamp(0.0, D_SEND_CLAMP_MAX),
+        reverb_send: (reverb * boost).clamp(0.0, R_SEND_CLAMP_MAX),
+    }
+}
@@ -0,0 +1,194 @@
+use rand::prelude::*;
+use rand::seq::SliceRandom;
+use std::fmt;
+
+/// Everything a generator may look at when deciding a voice's next grid step.
+///
+/// The engine has already applied beat weights, hysteresis, scale morphs and
+/// transposition; tempo, groove and detune are applied after the generator
+/// returns, so a generator only decides *whether* and *what* to play.
+#[derive(Clone, Debug)]
+pub struct StepContext<'a> {
+    pub voice: usize,
+    /// Global grid step (eighth notes since the engine started)
+    pub step: u64,
+    /// Grid time of the step on the scheduling clock
+    pub time_sec: f64,
+    pub bpm: f32,
+    /// Trigger probability for this step after beat weights and hysteresis
+    pub probability: f32,
+    /// Degrees (semitones above the root) currently in use
+    pub scale: &'a [f32],
+    pub root_midi: i32,
+    pub octave_offset: i32,
+    pub base_duration: f32,
+}
+
+/// A note chosen by a generator, before tempo, groove and detune are applied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoteSpec {
+    /// Pitch as a (possibly fractional) MIDI note number
+    pub midi: f32,
+    /// Normalized loudness 0..1
+    pub velocity: f32,
+    pub duration_sec: f32,
+}
+
+/// Per-voice note source driven once per grid step by `MusicEngine`.
+///
+/// Implement this to plug in other algorithms (fractals, data sonification)
+/// and install it with `MusicEngine::set_generator`. Looping voices restore a
+/// clone of the generator at each loop start, so `box_clone` must capture all
+/// state that affects future output.
+pub trait VoiceGenerator {
+    /// Decide the note for this step, or `None` to rest.
+    fn next_step(&mut self, ctx: &StepContext) -> Option<NoteSpec>;
+
+    /// Restart from `seed`. Generators without randomness can ignore it.
+    fn reseed(&mut self, _seed: u64) {}
+
+    fn box_clone(&self) -> Box<dyn VoiceGenerator>;
+}
+
+impl Clone for Box<dyn VoiceGenerator> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+impl fmt::Debug for dyn VoiceGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("VoiceGenerator")
+    }
+}
+
+/// The built-in generator: a coin flip against the step probability, then a
+/// random scale degree, velocity and duration.
+#[derive(Clone, Debug)]
+pub struct StochasticGenerator {
+    rng: StdRng,
+}
+
+impl StochasticGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl VoiceGenerator for StochasticGenerator {
+    fn next_step(&mut self, ctx: &StepContext) -> Option<NoteSpec> {
+        if self.rng.gen::<f32>() >= ctx.probability {
+            return None;
+        }
+        let degree = *ctx.scale.choose(&mut self.rng).unwrap_or(&0.0);
+        let midi = ctx.root_midi as f32 + degree + (ctx.octave_offset * 12) as f32;
+        let velocity = 0.4 + self.rng.gen::<f32>() * 0.6;
+        let duration_sec = ctx.base_duration + self.rng.gen::<f32>() * 0.2;
+        Some(NoteSpec {
+            midi,
+            velocity,
+            duration_sec,
+        })
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    fn box_clone(&self) -> Box<dyn VoiceGenerator> {
+        Box::new(self.clone())
+    }
+}
+
+/// Plays a melody's note-to-note movement rather than its exact sequence.
+///
+/// Built from a key sequence (e.g. an imported MIDI part): each pitch maps to
+/// the pitches that followed it, weighted by how often. Pitches are relative
+/// to the melody's first note and re-rooted on the engine's root, so the clip
+/// follows transposition and octave shifts. Trigger density still comes from
+/// the step probability.
+#[derive(Clone, Debug)]
+pub struct MarkovGenerator {
+    /// (pitch offset, [(next offset, count)])
+    table: Vec<(i32, Vec<(i32, u32)>)>,
+    current: i32,
+    rng: StdRng,
+}
+
+impl MarkovGenerator {
+    /// `None` if the melody is empty.
+    pub fn from_melody(keys: &[u8], seed: u64) -> Option<Self> {
+        let first = *keys.first()? as i32;
+        let offsets: Vec<i32> = keys.iter().map(|&k| k as i32 - first).collect();
+        let mut table: Vec<(i32, Vec<(i32, u32)>)> = Vec::new();
+        // Wrap the last note back to the first so there is no dead end
+        for (i, &from) in offsets.iter().enumerate() {
+            let to = offsets[(i + 1) % offsets.len()];
+            let row = match table.iter().position(|(s, _)| *s == from) {
+                Some(r) => &mut table[r].1,
+                None => {
+                    table.push((from, Vec::new()));
+                    &mut table.last_mut().expect("just pushed").1
+                }
+            };
+            match row.iter_mut().find(|(n, _)| *n == to) {
+                Some((_, count)) => *count += 1,
+                None => row.push((to, 1)),
+            }
+        }
+        Some(Self {
+            table,
+            current: 0,
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    fn advance(&mut self) -> i32 {
+        let row = self
+            .table
+            .iter()
+            .find(|(s, _)| *s == self.current)
+            .map(|(_, row)| row.as_slice())
+            .unwrap_or_default();
+        let total: u32 = row.iter().map(|(_, c)| c).sum();
+        if total > 0 {
+            let mut pick = self.rng.gen_range(0..total);
+            for &(next, count) in row {
+                if pick < count {
+                    self.current = next;
+                    break;
+                }
+                pick -= count;
+            }
+        }
+        self.current
+    }
+}
+
+impl VoiceGenerator for MarkovGenerator {
+    fn next_step(&mut self, ctx: &StepContext) -> Option<NoteSpec> {
+        if self.rng.gen::<f32>() >= ctx.probability {
+            return None;
+        }
+        let offset = self.advance();
+        let midi = ctx.root_midi as f32 + offset as f32 + (ctx.octave_offset * 12) as f32;
+        let velocity = 0.4 + self.rng.gen::<f32>() * 0.6;
+        let duration_sec = ctx.base_duration + self.rng.gen::<f32>() * 0.2;
+        Some(NoteSpec {
+            midi,
+            velocity,
+            duration_sec,
+        })
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.current = 0;
+    }
+
+    fn box_clone(&self) -> Box<dyn VoiceGenerator> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,106 @@
+use glam::{Quat, Vec3};
+
+/// Range of the pinch zoom, as a multiple of the camera's distance from
+/// what it looks at (below 1 is closer).
+pub const ZOOM_RANGE: (f32, f32) = (0.5, 2.0);
+
+// Fingers closer than this (CSS pixels) give no reliable scale or angle
+const MIN_SPAN_PX: f32 = 8.0;
+
+/// How the two fingers moved since the last step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GestureStep {
+    /// New spread over old: above 1 when the fingers move apart
+    pub scale: f32,
+    /// Radians the line between the fingers turned (clockwise on screen)
+    pub rotate_rad: f32,
+}
+
+impl GestureStep {
+    /// Camera zoom after this step: spreading the fingers moves closer.
+    pub fn zoom(&self, zoom: f32) -> f32 {
+        (zoom / self.scale).clamp(ZOOM_RANGE.0, ZOOM_RANGE.1)
+    }
+
+    /// An engine-space voice position swirled around the vertical axis as
+    /// the fingers turned.
+    pub fn rotate(&self, position: Vec3) -> Vec3 {
+        Quat::from_rotation_y(-self.rotate_rad) * position
+    }
+}
+
+/// Two-finger pinch and rotate over the scene. Only pointers that land off
+/// the voices take part, so a finger dragging a voice never starts one and
+/// a gesture never grabs a voice; a third free finger is ignored.
+#[derive(Clone, Debug, Default)]
+pub struct TwoFingerGesture {
+    /// Free pointers down (at most two) and where they are, in CSS pixels
+    pointers: Vec<(i32, [f32; 2])>,
+    /// Spread and angle between the fingers at the last step, while two are
+    /// down
+    span: Option<(f32, f32)>,
+    /// A gesture ran since the first of these pointers went down
+    gestured: bool,
+    /// Engine-space voice positions when the gesture began (for undo)
+    pub origins: Vec<Vec3>,
+}
+
+impl TwoFingerGesture {
+    pub fn is_active(&self) -> bool {
+        self.span.is_some()
+    }
+
+    /// A pointer landed off the voices; true if it starts a gesture.
+    pub fn down(&mut self, pointer_id: i32, px: [f32; 2]) -> bool {
+        if self.pointers.len() >= 2 || self.pointers.iter().any(|p| p.0 == pointer_id) {
+            return false;
+        }
+        self.pointers.push((pointer_id, px));
+        self.span = self.measure();
+        self.gestured |= self.span.is_some();
+        self.span.is_some()
+    }
+
+    /// A pointer moved to `px`; the step if it is one of two in a gesture.
+    pub fn moved(&mut self, pointer_id: i32, px: [f32; 2]) -> Option<GestureStep> {
+        let pointer = self.pointers.iter_mut().find(|p| p.0 == pointer_id)?;
+        pointer.1 = px;
+        let (spread, angle) = self.span?;
+        let (new_spread, new_angle) = self.measure()?;
+        self.span = Some((new_spread, new_angle));
+        if spread < MIN_SPAN_PX || new_spread < MIN_SPAN_PX {
+            return None;
+        }
+        let turn = new_angle - angle;
+        // The shorter way round, across the ±π seam
+        let rotate_rad =
+            (turn + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        Some(GestureStep {
+            scale: new_spread / spread,
+            rotate_rad,
+        })
+    }
+
+    /// A pointer lifted (or was cancelled); true if it took part in a
+    /// gesture, so its release is not a tap.
+    pub fn up(&mut self, pointer_id: i32) -> bool {
+        let Some(i) = self.pointers.iter().position(|p| p.0 == pointer_id) else {
+            return false;
+        };
+        self.pointers.remove(i);
+        self.span = None;
+        let gestured = self.gestured;
+        if self.pointers.is_empty() {
+            self.gestured = false;
+        }
+        gestured
+    }
+
+    fn measure(&self) -> Option<(f32, f32)> {
+        let [(_, a), (_, b)] = self.pointers.as_slice() else {
+            return None;
+        };
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        Some(((dx * dx + dy * dy).sqrt(), dy.atan2(dx)))
+    }
+}
@@ -0,0 +1,212 @@
+/// Size of a glyph in font pixels, and the spacing between glyphs and lines.
+pub const GLYPH_W: u32 = 5;
+pub const GLYPH_H: u32 = 7;
+pub const GLYPH_ADVANCE: u32 = 6;
+pub const LINE_HEIGHT: u32 = 9;
+
+/// Atlas cells hold a glyph with a pixel of clear space all round, so
+/// neighbours never bleed in when sampling the edges.
+const CELL_W: u32 = GLYPH_W + 2;
+const CELL_H: u32 = GLYPH_H + 2;
+const ATLAS_COLUMNS: u32 = 16;
+
+/// Filled cell, drawn stretched behind text as a panel.
+const SOLID: char = '█';
+
+/// Glyph drawn for characters the font doesn't have.
+const MISSING: char = '?';
+
+/// 5×7 bitmap font: one row per byte, top row first, the leftmost pixel in
+/// bit 4. Lowercase letters use the capitals.
+const FONT: &[(char, [u8; 7])] = &[
+    (SOLID, [0x1f; 7]),
+    (' ', [0x00; 7]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]),
+    ('#', [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('\'', [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('*', [0x00, 0x04, 0x15, 0x0e, 0x15, 0x04, 0x00]),
+    ('+', [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08]),
+    ('-', [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('0', [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e]),
+    ('1', [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('2', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f]),
+    ('3', [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e]),
+    ('4', [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02]),
+    ('5', [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e]),
+    ('6', [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e]),
+    ('7', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e]),
+    ('9', [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c]),
+    (':', [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00]),
+    (';', [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x04, 0x08]),
+    ('=', [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00]),
+    ('?', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('A', [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11]),
+    ('B', [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e]),
+    ('C', [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e]),
+    ('D', [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c]),
+    ('E', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f]),
+    ('F', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10]),
+    ('G', [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f]),
+    ('H', [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('I', [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f]),
+    ('M', [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('P', [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10]),
+    ('Q', [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d]),
+    ('R', [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11]),
+    ('S', [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e]),
+    ('T', [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a]),
+    ('X', [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04]),
+    ('Z', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f]),
+    ('|', [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('•', [0x00, 0x00, 0x0e, 0x0e, 0x0e, 0x00, 0x00]),
+];
+
+/// One textured quad in screen pixels (origin top left).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphQuad {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// The font rasterised into a single-channel texture, one cell per glyph.
+#[derive(Clone, Debug)]
+pub struct GlyphAtlas {
+    pub width: u32,
+    pub height: u32,
+    /// Coverage, 0 or 255, row-major
+    pub pixels: Vec<u8>,
+}
+
+impl Default for GlyphAtlas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GlyphAtlas {
+    pub fn new() -> GlyphAtlas {
+        let rows = (FONT.len() as u32).div_ceil(ATLAS_COLUMNS);
+        let width = ATLAS_COLUMNS * CELL_W;
+        let height = rows * CELL_H;
+        let mut pixels = vec![0u8; (width * height) as usize];
+        for (i, (_, bits)) in FONT.iter().enumerate() {
+            let (x0, y0) = cell_origin(i);
+            for (y, row) in bits.iter().enumerate() {
+                for x in 0..GLYPH_W {
+                    if row & (0x10 >> x) != 0 {
+                        let px = x0 + 1 + x;
+                        let py = y0 + 1 + y as u32;
+                        pixels[(py * width + px) as usize] = 255;
+                    }
+                }
+            }
+        }
+        GlyphAtlas {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Width and height of `text` at `scale` screen pixels per font pixel.
+    pub fn measure(text: &str, scale: f32) -> [f32; 2] {
+        let lines = text.lines().count().max(1) as u32;
+        let columns = text.lines().map(|l| l.chars().count()).max().unwrap_or(0) as u32;
+        if columns == 0 {
+            return [0.0, 0.0];
+        }
+        let w = (columns - 1) * GLYPH_ADVANCE + GLYPH_W;
+        let h = (lines - 1) * LINE_HEIGHT + GLYPH_H;
+        [w as f32 * scale, h as f32 * scale]
+    }
+
+    /// Quads for `text` with its top left at `origin`, on a panel reaching
+    /// `padding` pixels past the text (skipped when the panel is transparent).
+    #[allow(clippy::too_many_arguments)]
+    pub fn layout_panel(
+        &self,
+        text: &str,
+        origin: [f32; 2],
+        scale: f32,
+        color: [f32; 4],
+        panel: [f32; 4],
+        padding: f32,
+        out: &mut Vec<GlyphQuad>,
+    ) {
+        let size = Self::measure(text, scale);
+        if size[0] == 0.0 {
+            return;
+        }
+        if panel[3] > 0.0 {
+            // Sample the middle of the solid cell, so the stretched quad is flat
+            let (x0, y0) = cell_origin(glyph_index(SOLID));
+            let center = [
+                (x0 as f32 + CELL_W as f32 / 2.0) / self.width as f32,
+                (y0 as f32 + CELL_H as f32 / 2.0) / self.height as f32,
+            ];
+            out.push(GlyphQuad {
+                pos: [origin[0] - padding, origin[1] - padding],
+                size: [size[0] + 2.0 * padding, size[1] + 2.0 * padding],
+                uv_min: center,
+                uv_max: center,
+                color: panel,
+            });
+        }
+        for (line_no, line) in text.lines().enumerate() {
+            let y = origin[1] + (line_no as u32 * LINE_HEIGHT) as f32 * scale;
+            for (col, c) in line.chars().enumerate() {
+                if c == ' ' {
+                    continue;
+                }
+                let (x0, y0) = cell_origin(glyph_index(c));
+                out.push(GlyphQuad {
+                    pos: [origin[0] + (col as u32 * GLYPH_ADVANCE) as f32 * scale, y],
+                    size: [GLYPH_W as f32 * scale, GLYPH_H as f32 * scale],
+                    uv_min: [
+                        (x0 + 1) as f32 / self.width as f32,
+                        (y0 + 1) as f32 / self.height as f32,
+                    ],
+                    uv_max: [
+                        (x0 + 1 + GLYPH_W) as f32 / self.width as f32,
+                        (y0 + 1 + GLYPH_H) as f32 / self.height as f32,
+                    ],
+                    color,
+                });
+            }
+        }
+    }
+}
+
+/// Top left pixel of glyph `index`'s cell.
+fn cell_origin(index: usize) -> (u32, u32) {
+    let i = index as u32;
+    ((i % ATLAS_COLUMNS) * CELL_W, (i / ATLAS_COLUMNS) * CELL_H)
+}
+
+fn glyph_index(c: char) -> usize {
+    let c = c.to_ascii_uppercase();
+    FONT.iter()
+        .position(|(g, _)| *g == c)
+        .or_else(|| FONT.iter().position(|(g, _)| *g == MISSING))
+        .unwrap_or(0)
+}
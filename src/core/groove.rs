@@ -0,0 +1,50 @@
+/// A named micro-timing feel applied on top of the eighth-note grid.
+///
+/// `offsets[voice % offsets.len()]` is that voice's table of onset offsets in
+/// fractions of a grid step, cycled by the global step index. Positive values
+/// play late (laid back), negative values early (rushed).
+#[derive(Debug, PartialEq)]
+pub struct Groove {
+    pub name: &'static str,
+    pub offsets: &'static [&'static [f32]],
+}
+
+impl Groove {
+    /// Timing offset for `voice` on global grid step `step`, in fractions of a step.
+    pub fn offset(&self, voice: usize, step: u64) -> f32 {
+        match self.offsets.get(voice % self.offsets.len().max(1)) {
+            Some(table) if !table.is_empty() => table[(step % table.len() as u64) as usize],
+            _ => 0.0,
+        }
+    }
+}
+
+pub const STRAIGHT: Groove = Groove {
+    name: "Straight",
+    offsets: &[&[0.0]],
+};
+
+/// MPC-style 55% swing: every second eighth is pushed from 50% to 55% of the beat.
+pub const SWING_55: Groove = Groove {
+    name: "Swing 55%",
+    offsets: &[&[0.0, 0.1]],
+};
+
+/// Loose, uneven timing that differs per voice so parts smear against each other.
+pub const DRUNK: Groove = Groove {
+    name: "Drunk",
+    offsets: &[
+        &[0.0, 0.12, -0.04, 0.18, 0.05, -0.08, 0.1, 0.02],
+        &[0.06, -0.05, 0.14, 0.0, -0.1, 0.08, 0.03, 0.16],
+        &[-0.06, 0.1, 0.02, -0.12, 0.15, 0.0, -0.03, 0.09],
+    ],
+};
+
+/// Lower voices on the grid while the top voice (the "hats") leans ahead of the beat.
+pub const RUSHED_HATS: Groove = Groove {
+    name: "Rushed hats",
+    offsets: &[&[0.0], &[0.0], &[-0.06, -0.1]],
+};
+
+/// All built-in grooves, in selection order.
+pub const GROOVES: &[&Groove] = &[&STRAIGHT, &SWING_55, &DRUNK, &RUSHED_HATS];
@@ -0,0 +1,190 @@
+use super::frame_math::VoiceSends;
+use super::generator::VoiceGenerator;
+use super::groove::Groove;
+use super::motion::MotionMode;
+use super::music::{MusicEngine, Waveform};
+use super::physics::PhysicsParams;
+use super::preset::Preset;
+use super::sampler::SamplerConfig;
+use super::sidechain::Sidechain;
+use glam::Vec3;
+
+/// Maximum number of undoable steps kept in a `History`.
+pub const HISTORY_CAPACITY: usize = 128;
+
+/// A reversible interaction with the engine.
+///
+/// Frontends express user actions as commands and run them through
+/// `History::execute` so they can be undone/redone. `MusicEngine::apply`
+/// performs a command and returns its inverse; the `Restore*` variants exist
+/// only as inverses and capture the state they put back.
+#[derive(Clone, Debug)]
+pub enum Command {
+    ToggleMute {
+        voice: usize,
+    },
+    ToggleSolo {
+        voice: usize,
+    },
+    /// Pin a voice against pointer drags, or free it
+    ToggleLock {
+        voice: usize,
+    },
+    /// Reseed a voice RNG. `None` is resolved to a concrete seed on execute so redo repeats it.
+    Reseed {
+        voice: usize,
+        seed: Option<u64>,
+    },
+    SetPosition {
+        voice: usize,
+        position: Vec3,
+    },
+    /// Set how often a voice plays (its trigger probability, 0..=1)
+    SetDensity {
+        voice: usize,
+        density: f32,
+    },
+    /// Set a voice's loop length in grid steps (0 = free-running)
+    SetLoopSteps {
+        voice: usize,
+        steps: u32,
+    },
+    /// Change a voice's oscillator shape (basic or wavetable)
+    SetWaveform {
+        voice: usize,
+        waveform: Waveform,
+    },
+    /// Play a loaded sample on a voice (`None` returns it to its oscillator)
+    SetSampler {
+        voice: usize,
+        sampler: Option<SamplerConfig>,
+    },
+    /// Set a voice's delay/reverb send levels
+    SetSends {
+        voice: usize,
+        sends: VoiceSends,
+    },
+    /// Switch to a preset at once
+    ApplyPreset(Box<Preset>),
+    /// Move to a preset gradually over a number of bars (undo switches back instantly)
+    MorphToPreset {
+        preset: Box<Preset>,
+        bars: u32,
+    },
+    /// Select how unheld voices move on their own
+    SetMotion(MotionMode),
+    /// Enable (`Some`) or disable spring physics on voice positions
+    SetPhysics(Option<PhysicsParams>),
+    SetSidechain(Option<Sidechain>),
+    SetBpm(f32),
+    SetRootMidi(i32),
+    /// Shift the root by a number of semitones (clamped to the playable range)
+    Transpose(i32),
+    /// Move a voice up or down by whole octaves
+    ShiftOctave {
+        voice: usize,
+        octaves: i32,
+    },
+    SetOctave {
+        voice: usize,
+        octave: i32,
+    },
+    SetScale(&'static [f32]),
+    /// Change scale gradually over a number of bars (undo switches back instantly)
+    MorphScale {
+        scale: &'static [f32],
+        bars: u32,
+    },
+    SetDetune(f32),
+    SetGroove(&'static Groove),
+    AdjustDetune(f32),
+    ResetDetune,
+    /// Replace a voice's note source (e.g. with one seeded from an imported MIDI clip)
+    SetGenerator {
+        voice: usize,
+        generator: Box<dyn VoiceGenerator>,
+    },
+    /// Several commands treated as one undo step (applied in order).
+    Batch(Vec<Command>),
+    /// Restore mute flags and solo selection (inverse of `ToggleSolo`).
+    RestoreMutes {
+        muted: Vec<bool>,
+        solo: Option<usize>,
+    },
+    /// Restore a voice generator to a previous state (inverse of `Reseed`).
+    RestoreGenerator {
+        voice: usize,
+        generator: Box<dyn VoiceGenerator>,
+    },
+}
+
+/// Undo/redo stacks of executed commands.
+///
+/// Each entry keeps the forward command together with the inverse returned
+/// when it was applied. Executing a new command clears the redo stack.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<(Command, Command)>,
+    redo: Vec<Command>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply `cmd` to the engine and record it for undo.
+    pub fn execute(&mut self, engine: &mut MusicEngine, mut cmd: Command) {
+        resolve_seeds(engine, &mut cmd);
+        let inverse = engine.apply(&cmd);
+        self.record(cmd, inverse);
+    }
+
+    /// Record a command that has already been applied incrementally (e.g. a drag),
+    /// together with the command that reverts it.
+    pub fn record(&mut self, forward: Command, inverse: Command) {
+        self.undo.push((forward, inverse));
+        if self.undo.len() > HISTORY_CAPACITY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// Revert the most recent command. Returns false when there is nothing to undo.
+    pub fn undo(&mut self, engine: &mut MusicEngine) -> bool {
+        match self.undo.pop() {
+            Some((forward, inverse)) => {
+                engine.apply(&inverse);
+                self.redo.push(forward);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-apply the most recently undone command. Returns false when there is nothing to redo.
+    pub fn redo(&mut self, engine: &mut MusicEngine) -> bool {
+        match self.redo.pop() {
+            Some(forward) => {
+                let inverse = engine.apply(&forward);
+                self.undo.push((forward, inverse));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn resolve_seeds(engine: &MusicEngine, cmd: &mut Command) {
+    match cmd {
+        Command::Reseed { voice, seed } if seed.is_none() => {
+            *seed = Some(engine.fresh_seed(*voice));
+        }
+        Command::Batch(cmds) => {
+            for c in cmds {
+                resolve_seeds(engine, c);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,336 @@
+/// Something a single key does, rebindable from the Keys panel.
+///
+/// Root notes (A–G), modes and tunings (0–9), presets (Shift+1–4) and the
+/// Ctrl/Cmd shortcuts stay on their keys; a key bound here takes precedence
+/// over them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Pentatonic,
+    ReseedAll,
+    RandomTonality,
+    RollDice,
+    Metronome,
+    Mute,
+    Motion,
+    Waveform,
+    Springs,
+    Sidechain,
+    TransposeDown,
+    TransposeUp,
+    OctaveDown,
+    OctaveUp,
+    Groove,
+    Polymeter,
+    Pause,
+    TempoDown,
+    TempoUp,
+    DetuneDown,
+    DetuneUp,
+    DetuneReset,
+    VolumeDown,
+    VolumeUp,
+    Fullscreen,
+    ExitFullscreen,
+    Overlay,
+    Record,
+    Theme,
+    Visualizer,
+    BloomQuality,
+    BloomUp,
+    BloomDown,
+    PerfHud,
+    Screenshot,
+    Wallpaper,
+    RenderMode,
+    VisualLayers,
+    SelectNextVoice,
+    SelectPrevVoice,
+}
+
+impl Action {
+    pub const ALL: [Action; 40] = [
+        Action::Pentatonic,
+        Action::ReseedAll,
+        Action::RandomTonality,
+        Action::RollDice,
+        Action::Metronome,
+        Action::Mute,
+        Action::Motion,
+        Action::Waveform,
+        Action::Springs,
+        Action::Sidechain,
+        Action::TransposeDown,
+        Action::TransposeUp,
+        Action::OctaveDown,
+        Action::OctaveUp,
+        Action::Groove,
+        Action::Polymeter,
+        Action::Pause,
+        Action::TempoDown,
+        Action::TempoUp,
+        Action::DetuneDown,
+        Action::DetuneUp,
+        Action::DetuneReset,
+        Action::VolumeDown,
+        Action::VolumeUp,
+        Action::Fullscreen,
+        Action::ExitFullscreen,
+        Action::Overlay,
+        Action::Record,
+        Action::Theme,
+        Action::Visualizer,
+        Action::BloomQuality,
+        Action::BloomUp,
+        Action::BloomDown,
+        Action::PerfHud,
+        Action::Screenshot,
+        Action::Wallpaper,
+        Action::RenderMode,
+        Action::VisualLayers,
+        Action::SelectNextVoice,
+        Action::SelectPrevVoice,
+    ];
+
+    /// Short id used when saving bindings.
+    pub fn id(self) -> &'static str {
+        self.info().0
+    }
+
+    /// Label shown in the Keys panel.
+    pub fn name(self) -> &'static str {
+        self.info().1
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.id() == id)
+    }
+
+    fn info(self) -> (&'static str, &'static str) {
+        match self {
+            Action::Pentatonic => ("pentatonic", "C major pentatonic"),
+            Action::ReseedAll => ("reseed", "New sequences"),
+            Action::RandomTonality => ("random-key", "Random root + mode"),
+            Action::RollDice => ("dice", "Roll the dice"),
+            Action::Metronome => ("metronome", "Metronome"),
+            Action::Mute => ("mute", "Mute output"),
+            Action::Motion => ("motion", "Voice motion"),
+            Action::Waveform => ("waveform", "Lead waveform"),
+            Action::Springs => ("springs", "Springs"),
+            Action::Sidechain => ("sidechain", "Sidechain"),
+            Action::TransposeDown => ("transpose-down", "Transpose down"),
+            Action::TransposeUp => ("transpose-up", "Transpose up"),
+            Action::OctaveDown => ("octave-down", "Octave down"),
+            Action::OctaveUp => ("octave-up", "Octave up"),
+            Action::Groove => ("groove", "Groove"),
+            Action::Polymeter => ("polymeter", "Polymeter loops"),
+            Action::Pause => ("pause", "Pause/resume"),
+            Action::TempoDown => ("tempo-down", "Tempo down"),
+            Action::TempoUp => ("tempo-up", "Tempo up"),
+            Action::DetuneDown => ("detune-down", "Detune down"),
+            Action::DetuneUp => ("detune-up", "Detune up"),
+            Action::DetuneReset => ("detune-reset", "Reset detune"),
+            Action::VolumeDown => ("volume-down", "Volume down"),
+            Action::VolumeUp => ("volume-up", "Volume up"),
+            Action::Fullscreen => ("fullscreen", "Full screen"),
+            Action::ExitFullscreen => ("exit-fullscreen", "Exit full screen"),
+            Action::Overlay => ("overlay", "Show/hide this panel"),
+            Action::Record => ("record", "Record WAV"),
+            Action::Theme => ("theme", "Colour theme"),
+            Action::Visualizer => ("visualizer", "Visualizer"),
+            Action::BloomQuality => ("bloom-quality", "Bloom quality"),
+            Action::BloomUp => ("bloom-up", "Bloom stronger"),
+            Action::BloomDown => ("bloom-down", "Bloom weaker"),
+            Action::PerfHud => ("perf-hud", "Performance HUD"),
+            Action::Screenshot => ("screenshot", "Screenshot"),
+            Action::Wallpaper => ("wallpaper", "Screenshot at 2×"),
+            Action::RenderMode => ("render-mode", "Render mode"),
+            Action::VisualLayers => ("layers", "Spectrum/scope layers"),
+            Action::SelectNextVoice => ("select-next", "Select next voice"),
+            Action::SelectPrevVoice => ("select-prev", "Select previous voice"),
+        }
+    }
+}
+
+/// A key as `KeyboardEvent.key` names it, with or without Shift.
+///
+/// Letters are kept lower-case so `M` and `m` are one key; Shift is what
+/// tells them apart.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyChord {
+    pub key: String,
+    pub shift: bool,
+}
+
+impl KeyChord {
+    pub fn new(key: &str, shift: bool) -> Self {
+        let key = if key.chars().count() == 1 {
+            key.to_lowercase()
+        } else {
+            key.to_string()
+        };
+        Self { key, shift }
+    }
+
+    /// Parse a label such as `m`, `Shift+M`, `Space` or `ArrowUp`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (key, shift) = match text.strip_prefix("Shift+") {
+            Some(rest) => (rest, true),
+            None => (text, false),
+        };
+        match key {
+            "" => None,
+            "Space" => Some(Self::new(" ", shift)),
+            key => Some(Self::new(key, shift)),
+        }
+    }
+
+    /// Human-readable form, e.g. `Shift+M` (round-trips through `parse`).
+    pub fn label(&self) -> String {
+        let key = match self.key.as_str() {
+            " " => "Space".to_string(),
+            k if k.chars().count() == 1 => k.to_uppercase(),
+            k => k.to_string(),
+        };
+        if self.shift {
+            format!("Shift+{key}")
+        } else {
+            key
+        }
+    }
+}
+
+/// Which keys do which `Action`s. An action may have several keys (tempo is
+/// on ←/→ and +/−) and a key does at most one action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: Vec<(Action, KeyChord)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .filter_map(|(action, label)| Some((*action, KeyChord::parse(label)?)))
+            .collect();
+        Self { bindings }
+    }
+}
+
+const DEFAULT_BINDINGS: &[(Action, &str)] = &[
+    (Action::Pentatonic, "p"),
+    (Action::ReseedAll, "r"),
+    (Action::RandomTonality, "t"),
+    (Action::RollDice, "x"),
+    (Action::Metronome, "n"),
+    (Action::Mute, "Shift+M"),
+    (Action::Motion, "m"),
+    (Action::Waveform, "w"),
+    (Action::Springs, "k"),
+    (Action::Sidechain, "s"),
+    (Action::TransposeDown, "["),
+    (Action::TransposeUp, "]"),
+    (Action::OctaveDown, "{"),
+    (Action::OctaveUp, "}"),
+    (Action::Groove, "q"),
+    (Action::Polymeter, "l"),
+    (Action::Pause, "Space"),
+    (Action::TempoDown, "ArrowLeft"),
+    (Action::TempoDown, "-"),
+    (Action::TempoDown, "_"),
+    (Action::TempoUp, "ArrowRight"),
+    (Action::TempoUp, "+"),
+    (Action::TempoUp, "="),
+    (Action::DetuneDown, ","),
+    (Action::DetuneUp, "."),
+    (Action::DetuneReset, "/"),
+    (Action::VolumeDown, "ArrowDown"),
+    (Action::VolumeUp, "ArrowUp"),
+    (Action::Fullscreen, "Enter"),
+    (Action::ExitFullscreen, "Escape"),
+    (Action::Overlay, "h"),
+    (Action::Record, "o"),
+    (Action::Theme, "i"),
+    (Action::Visualizer, "u"),
+    (Action::BloomQuality, "j"),
+    (Action::BloomUp, "y"),
+    (Action::BloomDown, "Shift+Y"),
+    (Action::PerfHud, "Shift+P"),
+    (Action::Screenshot, "z"),
+    (Action::Wallpaper, "Shift+Z"),
+    (Action::RenderMode, "v"),
+    (Action::VisualLayers, "Shift+V"),
+    (Action::SelectNextVoice, "Tab"),
+    (Action::SelectPrevVoice, "Shift+Tab"),
+];
+
+impl Keymap {
+    /// The action for `key` (as `KeyboardEvent.key` gives it) pressed with or
+    /// without Shift. A key bound without Shift also answers with it, unless
+    /// Shift+key is bound to something else (so `{`, typed with Shift, still
+    /// finds its binding).
+    pub fn action(&self, key: &str, shift: bool) -> Option<Action> {
+        let exact = KeyChord::new(key, shift);
+        self.find(&exact).or_else(|| {
+            shift
+                .then(|| self.find(&KeyChord::new(key, false)))
+                .flatten()
+        })
+    }
+
+    /// Keys bound to `action`, in the order they were bound.
+    pub fn chords(&self, action: Action) -> impl Iterator<Item = &KeyChord> {
+        self.bindings
+            .iter()
+            .filter(move |(a, _)| *a == action)
+            .map(|(_, c)| c)
+    }
+
+    /// Make `chord` the only key for `action` (or leave it unbound with
+    /// `None`). An action that had the key loses it and is returned.
+    pub fn bind(&mut self, action: Action, chord: Option<KeyChord>) -> Option<Action> {
+        self.bindings.retain(|(a, _)| *a != action);
+        let chord = chord?;
+        let displaced = self.find(&chord).filter(|a| *a != action);
+        self.bindings.retain(|(_, c)| *c != chord);
+        self.bindings.push((action, chord));
+        displaced
+    }
+
+    /// Bindings that differ from the defaults as `(action id, key labels)`,
+    /// the labels joined by spaces (empty for an unbound action), for saving.
+    pub fn changes(&self) -> Vec<(&'static str, String)> {
+        let defaults = Keymap::default();
+        Action::ALL
+            .into_iter()
+            .filter(|a| !self.chords(*a).eq(defaults.chords(*a)))
+            .map(|a| {
+                let labels: Vec<String> = self.chords(a).map(KeyChord::label).collect();
+                (a.id(), labels.join(" "))
+            })
+            .collect()
+    }
+
+    /// The defaults with saved `changes` applied; unknown actions and keys
+    /// are skipped, so older or newer saves still load.
+    pub fn with_changes<'a>(changes: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut keymap = Keymap::default();
+        for (id, labels) in changes {
+            let Some(action) = Action::from_id(id) else {
+                continue;
+            };
+            keymap.bind(action, None);
+            for chord in labels.split(' ').filter_map(KeyChord::parse) {
+                keymap.bindings.retain(|(_, c)| *c != chord);
+                keymap.bindings.push((action, chord));
+            }
+        }
+        keymap
+    }
+
+    fn find(&self, chord: &KeyChord) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, c)| c == chord)
+            .map(|(a, _)| *a)
+    }
+}
@@ -0,0 +1,14 @@
+/// Upper bound on the output latency we compensate for. Anything larger is
+/// almost certainly a bogus report, and delaying visuals further feels broken.
+pub const MAX_OUTPUT_LATENCY_SEC: f64 = 0.5;
+
+/// Estimate how long after its scheduled time a sound actually reaches the ears.
+///
+/// Combines the browser-reported `AudioContext.baseLatency` (processing) and
+/// `outputLatency` (device/transport, large on Bluetooth headphones). Either
+/// may be missing or non-finite depending on the platform; missing parts count
+/// as zero and the total is clamped to `0..=MAX_OUTPUT_LATENCY_SEC`.
+pub fn estimate_output_latency(base_latency: Option<f64>, output_latency: Option<f64>) -> f64 {
+    let part = |v: Option<f64>| v.filter(|x| x.is_finite() && *x > 0.0).unwrap_or(0.0);
+    (part(base_latency) + part(output_latency)).min(MAX_OUTPUT_LATENCY_SEC)
+}
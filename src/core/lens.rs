@@ -0,0 +1,148 @@
+/// Focal plane by default, in world units from the camera: the middle of the
+/// space the voices move in.
+pub const DEFAULT_FOCUS_DISTANCE: f32 = 7.5;
+
+/// Nearest and farthest focal plane offered.
+pub const MIN_FOCUS_DISTANCE: f32 = 2.0;
+pub const MAX_FOCUS_DISTANCE: f32 = 14.0;
+
+/// Distance either side of the focal plane at which the blur is widest.
+pub const DOF_FOCUS_RANGE: f32 = 4.0;
+
+/// Widest blur, as a fraction of the frame height (so the look holds at any
+/// resolution or render scale).
+pub const MAX_DOF_BLUR: f32 = 0.012;
+
+/// Vignette strength when switched on without a level.
+pub const DEFAULT_VIGNETTE: f32 = 0.5;
+
+/// How many taps the depth-of-field pass gathers per pixel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DofQuality {
+    /// No depth of field (and no pass, unless the vignette is on)
+    #[default]
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl DofQuality {
+    pub const ALL: [DofQuality; 4] = [
+        DofQuality::Off,
+        DofQuality::Low,
+        DofQuality::Medium,
+        DofQuality::High,
+    ];
+
+    /// Short identifier used in URLs
+    pub fn id(self) -> &'static str {
+        match self {
+            DofQuality::Off => "off",
+            DofQuality::Low => "low",
+            DofQuality::Medium => "medium",
+            DofQuality::High => "high",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DofQuality::Off => "Off",
+            DofQuality::Low => "Low (12 taps)",
+            DofQuality::Medium => "Medium (24 taps)",
+            DofQuality::High => "High (48 taps)",
+        }
+    }
+
+    /// Taps on the gather spiral (`taps` in lens.wgsl)
+    pub fn taps(self) -> u32 {
+        match self {
+            DofQuality::Off => 0,
+            DofQuality::Low => 12,
+            DofQuality::Medium => 24,
+            DofQuality::High => 48,
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<DofQuality> {
+        Self::ALL
+            .into_iter()
+            .find(|q| q.id().eq_ignore_ascii_case(id.trim()))
+    }
+}
+
+/// Lens effects applied after the composite: depth of field around a focal
+/// plane, and a vignette that breathes with the music.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lens {
+    pub dof: DofQuality,
+    /// Focal plane, `MIN_FOCUS_DISTANCE..=MAX_FOCUS_DISTANCE` from the camera
+    pub focus: f32,
+    /// Vignette strength, 0 (off) to 1
+    pub vignette: f32,
+}
+
+impl Default for Lens {
+    fn default() -> Self {
+        Lens {
+            dof: DofQuality::Off,
+            focus: DEFAULT_FOCUS_DISTANCE,
+            vignette: 0.0,
+        }
+    }
+}
+
+impl Lens {
+    /// Apply `"<quality>[,<focus>]"` (e.g. `?dof=high,6`); an omitted focus
+    /// keeps the current one.
+    pub fn with_dof_param(self, s: &str) -> Option<Lens> {
+        let mut parts = s.split(',').map(str::trim);
+        let mut lens = Lens {
+            dof: DofQuality::from_id(parts.next()?)?,
+            ..self
+        };
+        if let Some(focus) = parts.next() {
+            lens = lens.with_focus(parse_finite(focus)?);
+        }
+        parts.next().is_none().then_some(lens)
+    }
+
+    /// Apply `"<strength>"` (e.g. `?vignette=0.6`), or `"on"` for the default.
+    pub fn with_vignette_param(self, s: &str) -> Option<Lens> {
+        let s = s.trim();
+        let vignette = if s.eq_ignore_ascii_case("on") {
+            DEFAULT_VIGNETTE
+        } else {
+            parse_finite(s)?
+        };
+        Some(self.with_vignette(vignette))
+    }
+
+    /// `with_dof_param`'s form of the depth of field.
+    pub fn dof_param(&self) -> String {
+        format!("{},{}", self.dof.id(), self.focus)
+    }
+
+    pub fn with_focus(self, focus: f32) -> Lens {
+        Lens {
+            focus: focus.clamp(MIN_FOCUS_DISTANCE, MAX_FOCUS_DISTANCE),
+            ..self
+        }
+    }
+
+    pub fn with_vignette(self, vignette: f32) -> Lens {
+        Lens {
+            vignette: vignette.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Whether the lens pass runs at all.
+    pub fn active(&self) -> bool {
+        self.dof != DofQuality::Off || self.vignette > 0.0
+    }
+}
+
+fn parse_finite(s: &str) -> Option<f32> {
+    s.parse().ok().filter(|v: &f32| v.is_finite())
+}
@@ -0,0 +1,57 @@
+/// What the scheduler may do this frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchedulerGate {
+    /// The page is hidden or the audio isn't running: schedule nothing
+    Hold,
+    /// Running again after a hold: move the grid onto the audio clock first
+    Resync,
+    Run,
+}
+
+/// Follows the page being hidden and shown and the audio context stopping
+/// and starting, so the scheduler pauses with them and picks the grid up
+/// where the audio is on return instead of catching up on missed steps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AudioLifecycle {
+    hidden: bool,
+    /// The audio was suspended for the page being hidden
+    suspended: bool,
+    held: bool,
+}
+
+impl AudioLifecycle {
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// The page was hidden; whether to suspend the audio (only while
+    /// `playing`, so a paused session isn't resumed behind the player's back).
+    pub fn hide(&mut self, playing: bool) -> bool {
+        self.hidden = true;
+        if playing && !self.suspended {
+            self.suspended = true;
+            return true;
+        }
+        false
+    }
+
+    /// The page was shown again; whether to resume the audio, also when the
+    /// system stopped it meanwhile (an iOS interruption) while `playing`.
+    pub fn show(&mut self, playing: bool, audio_running: bool) -> bool {
+        self.hidden = false;
+        std::mem::take(&mut self.suspended) || (playing && !audio_running)
+    }
+
+    /// Whether the scheduler may run this frame, given whether the audio
+    /// clock is moving.
+    pub fn gate(&mut self, audio_running: bool) -> SchedulerGate {
+        if self.hidden || !audio_running {
+            self.held = true;
+            SchedulerGate::Hold
+        } else if std::mem::take(&mut self.held) {
+            SchedulerGate::Resync
+        } else {
+            SchedulerGate::Run
+        }
+    }
+}
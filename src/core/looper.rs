@@ -0,0 +1,157 @@
+use super::music::STEPS_PER_BAR;
+use super::note_repeat::BackgroundNote;
+use glam::Vec3;
+
+/// Loop lengths offered in the Looper panel, in bars.
+pub const LOOP_BAR_CHOICES: [u32; 4] = [1, 2, 4, 8];
+
+/// Something the player did that the looper can do again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoopAction {
+    /// A note played on the background (tapped or strummed), as the
+    /// one-shot it played and the ripple (screen uv and size) it threw if any
+    Tap {
+        note: BackgroundNote,
+        ripple: Option<([f32; 2], f32)>,
+    },
+    /// A voice dragged through `position`
+    Move { voice: usize, position: Vec3 },
+    /// A voice muted or unmuted
+    Mute { voice: usize },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopState {
+    /// Nothing recorded
+    #[default]
+    Empty,
+    /// Capturing until the loop's bars have gone by
+    Recording,
+    Playing,
+    /// A take kept but not playing
+    Stopped,
+}
+
+/// Records the player's actions against the engine's grid (positions in
+/// steps, so tempo changes carry the loop along) and plays them back every
+/// `bars` bars on top of whatever the voices generate.
+#[derive(Clone, Debug)]
+pub struct Looper {
+    /// Length of the next take
+    bars: u32,
+    /// Length of the current one
+    take_bars: u32,
+    state: LoopState,
+    /// Grid step the loop starts on: the bar line recording began in
+    origin: f64,
+    /// Actions and their offsets in steps from the start of the loop
+    take: Vec<(f64, LoopAction)>,
+    /// Grid position played (or recorded) up to
+    cursor: f64,
+}
+
+impl Default for Looper {
+    fn default() -> Self {
+        Self {
+            bars: 2,
+            take_bars: 2,
+            state: LoopState::Empty,
+            origin: 0.0,
+            take: Vec::new(),
+            cursor: 0.0,
+        }
+    }
+}
+
+impl Looper {
+    pub fn bars(&self) -> u32 {
+        self.bars
+    }
+
+    /// Length for the next recording; a take already made keeps its own.
+    pub fn set_bars(&mut self, bars: u32) {
+        self.bars = bars.max(1);
+    }
+
+    pub fn state(&self) -> LoopState {
+        self.state
+    }
+
+    /// Actions in the take.
+    pub fn action_count(&self) -> usize {
+        self.take.len()
+    }
+
+    fn length_steps(&self) -> f64 {
+        (self.take_bars as u64 * STEPS_PER_BAR) as f64
+    }
+
+    /// Start a new take at grid position `now`, from the bar line it falls
+    /// in so actions keep their place in the bar. Any earlier take is gone.
+    pub fn record(&mut self, now: f64) {
+        let bar = STEPS_PER_BAR as f64;
+        self.origin = (now / bar).floor() * bar;
+        self.take_bars = self.bars;
+        self.take.clear();
+        self.cursor = now;
+        self.state = LoopState::Recording;
+    }
+
+    /// Note `action` done at grid position `now`, if recording.
+    pub fn capture(&mut self, now: f64, action: LoopAction) {
+        if self.state == LoopState::Recording {
+            let len = self.length_steps();
+            self.take
+                .push(((now - self.origin).clamp(0.0, len) % len, action));
+        }
+    }
+
+    /// Play from the grid position reached, or stop; an empty take has
+    /// nothing to play.
+    pub fn toggle_play(&mut self, now: f64) {
+        self.state = match self.state {
+            LoopState::Empty => LoopState::Empty,
+            LoopState::Playing => LoopState::Stopped,
+            LoopState::Recording | LoopState::Stopped if self.take.is_empty() => LoopState::Empty,
+            LoopState::Recording | LoopState::Stopped => LoopState::Playing,
+        };
+        self.cursor = now;
+    }
+
+    pub fn clear(&mut self) {
+        self.take.clear();
+        self.state = LoopState::Empty;
+    }
+
+    /// Move on to grid position `now`: a recording whose bars have gone by
+    /// starts playing, and a playing loop returns the actions due from the
+    /// last call's position up to (not including) `now`, in order. A jump of a whole loop or more (or backwards,
+    /// after a pause) plays nothing.
+    pub fn advance(&mut self, now: f64) -> Vec<LoopAction> {
+        let len = self.length_steps();
+        if self.state == LoopState::Recording && now >= self.origin + len {
+            self.state = if self.take.is_empty() {
+                LoopState::Empty
+            } else {
+                LoopState::Playing
+            };
+            self.cursor = self.origin + len;
+        }
+        let from = std::mem::replace(&mut self.cursor, now);
+        if self.state != LoopState::Playing || now <= from || now - from > len {
+            return Vec::new();
+        }
+        let mut due: Vec<(f64, LoopAction)> = self
+            .take
+            .iter()
+            .filter_map(|&(offset, action)| {
+                // The first time round this action comes at or after `from`
+                let k = ((from - self.origin - offset) / len).ceil();
+                let at = self.origin + offset + k * len;
+                (at < now).then_some((at, action))
+            })
+            .collect();
+        due.sort_by(|a, b| a.0.total_cmp(&b.0));
+        due.into_iter().map(|(_, action)| action).collect()
+    }
+}
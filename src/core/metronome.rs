@@ -0,0 +1,86 @@
+use super::music::STEPS_PER_BAR;
+
+/// Grid steps (eighth notes) per metronome beat.
+pub const STEPS_PER_BEAT: u64 = 2;
+
+/// Longest count-in accepted.
+pub const MAX_COUNT_IN_BARS: u32 = 4;
+
+/// Click pitches: the bar's first beat stands out a fifth above the rest.
+pub const ACCENT_CLICK_HZ: f32 = 1980.0;
+pub const BEAT_CLICK_HZ: f32 = 1320.0;
+
+/// Length of one click.
+pub const CLICK_SEC: f32 = 0.04;
+
+/// A click track to play along to, on a bus of its own so its level is
+/// independent of the music (and it stays out of recordings and exports).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metronome {
+    /// Click every beat while playing
+    pub enabled: bool,
+    /// Linear level of the click bus, 0..=1
+    pub level: f32,
+    /// Bars of clicks, without notes, before playback resumes from a pause
+    pub count_in_bars: u32,
+}
+
+impl Default for Metronome {
+    fn default() -> Self {
+        Metronome {
+            enabled: false,
+            level: 0.5,
+            count_in_bars: 0,
+        }
+    }
+}
+
+impl Metronome {
+    /// Parse `"on|off[,count-in bars[,level]]"` (e.g. `?click=on,1`).
+    pub fn parse(s: &str) -> Option<Metronome> {
+        let mut parts = s.split(',').map(str::trim);
+        let enabled = match parts.next()? {
+            "on" | "1" => true,
+            "off" | "0" => false,
+            _ => return None,
+        };
+        let mut m = Metronome {
+            enabled,
+            ..Metronome::default()
+        };
+        if let Some(bars) = parts.next() {
+            m.count_in_bars = bars.parse::<u32>().ok()?.min(MAX_COUNT_IN_BARS);
+        }
+        if let Some(level) = parts.next() {
+            let level: f32 = level.parse().ok().filter(|l: &f32| l.is_finite())?;
+            m.level = level.clamp(0.0, 1.0);
+        }
+        parts.next().is_none().then_some(m)
+    }
+}
+
+/// One metronome tick on the scheduling clock.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Click {
+    pub at_sec: f64,
+    /// First beat of a bar
+    pub accent: bool,
+}
+
+impl Click {
+    /// The click for grid step `step` at `at_sec`, if the step starts a beat.
+    pub fn on_step(step: u64, at_sec: f64) -> Option<Click> {
+        step.is_multiple_of(STEPS_PER_BEAT).then_some(Click {
+            at_sec,
+            accent: step.is_multiple_of(STEPS_PER_BAR),
+        })
+    }
+
+    pub fn frequency_hz(&self) -> f32 {
+        if self.accent {
+            ACCENT_CLICK_HZ
+        } else {
+            BEAT_CLICK_HZ
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use super::history::Command;
+use super::music::MusicEngine;
+use super::selection::VOICE_NAMES;
+
+/// Tempo range a controller sweeps, in BPM (the same as the tempo keys).
+pub const CC_BPM_MIN: f32 = 40.0;
+pub const CC_BPM_MAX: f32 = 240.0;
+
+/// A parameter a hardware knob or fader can be mapped to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CcTarget {
+    Bpm,
+    /// A voice's volume
+    Level(usize),
+    /// How often a voice plays
+    Density(usize),
+    DelaySend(usize),
+    ReverbSend(usize),
+}
+
+impl CcTarget {
+    /// Every target for `voices` voices, in the order the panel lists them.
+    pub fn all(voices: usize) -> Vec<CcTarget> {
+        let mut targets = vec![CcTarget::Bpm];
+        for voice in 0..voices {
+            targets.extend([
+                CcTarget::Level(voice),
+                CcTarget::Density(voice),
+                CcTarget::DelaySend(voice),
+                CcTarget::ReverbSend(voice),
+            ]);
+        }
+        targets
+    }
+
+    /// Short id used when saving mappings, e.g. `bpm` or `delay-2`.
+    pub fn id(self) -> String {
+        match self {
+            CcTarget::Bpm => "bpm".to_string(),
+            CcTarget::Level(v) => format!("level-{v}"),
+            CcTarget::Density(v) => format!("density-{v}"),
+            CcTarget::DelaySend(v) => format!("delay-{v}"),
+            CcTarget::ReverbSend(v) => format!("reverb-{v}"),
+        }
+    }
+
+    pub fn parse(id: &str) -> Option<Self> {
+        if id == "bpm" {
+            return Some(CcTarget::Bpm);
+        }
+        let (kind, voice) = id.split_once('-')?;
+        let voice = voice.parse().ok()?;
+        match kind {
+            "level" => Some(CcTarget::Level(voice)),
+            "density" => Some(CcTarget::Density(voice)),
+            "delay" => Some(CcTarget::DelaySend(voice)),
+            "reverb" => Some(CcTarget::ReverbSend(voice)),
+            _ => None,
+        }
+    }
+
+    /// Label shown in the MIDI controls panel, e.g. `Lead delay`.
+    pub fn label(self) -> String {
+        let name = |v: usize| VOICE_NAMES.get(v).copied().unwrap_or("Voice");
+        match self {
+            CcTarget::Bpm => "Tempo".to_string(),
+            CcTarget::Level(v) => format!("{} volume", name(v)),
+            CcTarget::Density(v) => format!("{} density", name(v)),
+            CcTarget::DelaySend(v) => format!("{} delay", name(v)),
+            CcTarget::ReverbSend(v) => format!("{} reverb", name(v)),
+        }
+    }
+
+    /// The command that sets this parameter from a controller `value`
+    /// (0..=127 sweeping the full range), or `None` for a voice `engine`
+    /// doesn't have.
+    pub fn command(self, value: u8, engine: &MusicEngine) -> Option<Command> {
+        let t = f32::from(value.min(127)) / 127.0;
+        let sends = |voice: usize| engine.configs.get(voice).map(|c| c.sends);
+        Some(match self {
+            CcTarget::Bpm => Command::SetBpm((CC_BPM_MIN + (CC_BPM_MAX - CC_BPM_MIN) * t).round()),
+            CcTarget::Density(voice) => {
+                engine.configs.get(voice)?;
+                Command::SetDensity { voice, density: t }
+            }
+            CcTarget::Level(voice) => {
+                let mut sends = sends(voice)?;
+                sends.level = t;
+                Command::SetSends { voice, sends }
+            }
+            CcTarget::DelaySend(voice) => {
+                let mut sends = sends(voice)?;
+                sends.delay = t;
+                Command::SetSends { voice, sends }
+            }
+            CcTarget::ReverbSend(voice) => {
+                let mut sends = sends(voice)?;
+                sends.reverb = t;
+                Command::SetSends { voice, sends }
+            }
+        })
+    }
+}
+
+/// A controller as it arrives: 0-based channel and controller number.
+pub type CcSource = (u8, u8);
+
+/// `(channel, controller, value)` of a MIDI control change message.
+pub fn parse_cc(bytes: &[u8]) -> Option<(u8, u8, u8)> {
+    match *bytes {
+        [status, cc, value, ..] if status & 0xF0 == 0xB0 => Some((status & 0x0F, cc, value)),
+        _ => None,
+    }
+}
+
+/// Which controllers drive which parameters. A controller drives at most
+/// one parameter and a parameter follows at most one controller.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CcMap {
+    bindings: Vec<(CcSource, CcTarget)>,
+}
+
+impl CcMap {
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// Map `source` to `target`, replacing whatever either had before.
+    pub fn bind(&mut self, source: CcSource, target: CcTarget) {
+        self.bindings.retain(|(s, t)| *s != source && *t != target);
+        self.bindings.push((source, target));
+    }
+
+    pub fn target(&self, source: CcSource) -> Option<CcTarget> {
+        self.bindings
+            .iter()
+            .find(|(s, _)| *s == source)
+            .map(|(_, t)| *t)
+    }
+
+    pub fn source(&self, target: CcTarget) -> Option<CcSource> {
+        self.bindings
+            .iter()
+            .find(|(_, t)| *t == target)
+            .map(|(s, _)| *s)
+    }
+
+    /// Saved form: `channel:cc=target` pairs separated by spaces, channels
+    /// 1-based as shown (e.g. `1:74=bpm 10:7=level-2`).
+    pub fn encode(&self) -> String {
+        let pairs: Vec<String> = self
+            .bindings
+            .iter()
+            .map(|((ch, cc), t)| format!("{}:{}={}", ch + 1, cc, t.id()))
+            .collect();
+        pairs.join(" ")
+    }
+
+    /// Read back `encode`'s form; pairs that don't parse are skipped, so
+    /// older or newer saves still load.
+    pub fn decode(text: &str) -> Self {
+        let mut map = CcMap::default();
+        for pair in text.split_whitespace() {
+            let parsed = pair.split_once('=').and_then(|(source, target)| {
+                let (ch, cc) = source.split_once(':')?;
+                let ch = ch.parse::<u8>().ok().filter(|c| (1..=16).contains(c))?;
+                let cc = cc.parse::<u8>().ok().filter(|c| *c < 128)?;
+                Some(((ch - 1, cc), CcTarget::parse(target)?))
+            });
+            if let Some((source, target)) = parsed {
+                map.bind(source, target);
+            }
+        }
+        map
+    }
+}
+
+/// MIDI-learn: once started, the next controller moved and the next
+/// parameter picked (in either order) are mapped together.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MidiLearn {
+    pub active: bool,
+    pub source: Option<CcSource>,
+    pub target: Option<CcTarget>,
+}
+
+impl MidiLearn {
+    pub fn start(&mut self) {
+        *self = MidiLearn {
+            active: true,
+            ..MidiLearn::default()
+        };
+    }
+
+    pub fn cancel(&mut self) {
+        *self = MidiLearn::default();
+    }
+
+    /// A controller moved; returns the pair to map once a parameter has been
+    /// picked too. The latest controller moved is the one learnt.
+    pub fn heard(&mut self, source: CcSource) -> Option<(CcSource, CcTarget)> {
+        if !self.active {
+            return None;
+        }
+        self.source = Some(source);
+        self.finish()
+    }
+
+    /// A parameter was picked; returns the pair to map once a controller has
+    /// been moved too.
+    pub fn picked(&mut self, target: CcTarget) -> Option<(CcSource, CcTarget)> {
+        if !self.active {
+            return None;
+        }
+        self.target = Some(target);
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Option<(CcSource, CcTarget)> {
+        let pair = (self.source?, self.target?);
+        self.cancel();
+        Some(pair)
+    }
+}
@@ -0,0 +1,110 @@
+use super::music::NoteEvent;
+use super::smf::{key_and_bend, midi_velocity};
+
+/// Number of MIDI channels (0-based on the wire, shown as 1..16).
+pub const MIDI_CHANNELS: u8 = 16;
+
+/// Largest timing offset accepted either way.
+pub const MAX_MIDI_LATENCY_MS: f32 = 500.0;
+
+/// One MIDI message and when to send it, on the clock the notes were
+/// stamped with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedMidi {
+    pub at_sec: f64,
+    pub bytes: [u8; 3],
+}
+
+/// How generated notes leave as live MIDI: which channel each voice plays
+/// on, and an offset to line external synths up with the audio.
+///
+/// Notes use the same encoding as the SMF export (a pitch bend before each
+/// note-on for microtonal pitches), so a receiver should use the General
+/// MIDI bend range of `SMF_BEND_RANGE_SEMITONES`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MidiOutMap {
+    /// 0-based channel per voice
+    pub channels: Vec<u8>,
+    /// Added to every send time: positive delays MIDI (slow audio output),
+    /// negative sends it early (slow hardware)
+    pub latency_ms: f32,
+}
+
+impl MidiOutMap {
+    /// Voice N on channel N + 1, no offset.
+    pub fn new(voices: usize) -> MidiOutMap {
+        MidiOutMap {
+            channels: (0..voices)
+                .map(|v| (v % MIDI_CHANNELS as usize) as u8)
+                .collect(),
+            latency_ms: 0.0,
+        }
+    }
+
+    /// Parse 1-based channels per voice from `"1,2,10"` (e.g. from a URL);
+    /// every voice must be given a channel in 1..=16.
+    pub fn parse_channels(s: &str, voices: usize) -> Option<Vec<u8>> {
+        let channels: Vec<u8> = s
+            .split(',')
+            .map(|c| {
+                c.trim()
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|c| (1..=MIDI_CHANNELS).contains(c))
+                    .map(|c| c - 1)
+            })
+            .collect::<Option<_>>()?;
+        (channels.len() == voices).then_some(channels)
+    }
+
+    /// Set the offset, clamped to ±`MAX_MIDI_LATENCY_MS`.
+    pub fn set_latency_ms(&mut self, ms: f32) {
+        if ms.is_finite() {
+            self.latency_ms = ms.clamp(-MAX_MIDI_LATENCY_MS, MAX_MIDI_LATENCY_MS);
+        }
+    }
+
+    /// Pitch bend, note-on and note-off for `ev` on its voice's channel,
+    /// shifted by the offset; `None` for a voice without a channel.
+    pub fn note_messages(&self, ev: &NoteEvent) -> Option<[TimedMidi; 3]> {
+        let ch = *self.channels.get(ev.voice_index)?;
+        let (key, bend) = key_and_bend(ev.frequency_hz);
+        let on = ev.start_time_sec + self.latency_ms as f64 / 1000.0;
+        let off = on + ev.duration_sec.max(0.0) as f64;
+        Some([
+            TimedMidi {
+                at_sec: on,
+                bytes: [0xE0 | ch, (bend & 0x7F) as u8, (bend >> 7) as u8],
+            },
+            TimedMidi {
+                at_sec: on,
+                bytes: [0x90 | ch, key, midi_velocity(ev.velocity)],
+            },
+            TimedMidi {
+                at_sec: off,
+                bytes: [0x80 | ch, key, 0],
+            },
+        ])
+    }
+
+    /// "All notes off" (CC 123) on every channel in use, for when output
+    /// stops or moves to another port.
+    pub fn all_notes_off(&self) -> Vec<[u8; 3]> {
+        let mut channels = self.channels.clone();
+        channels.sort_unstable();
+        channels.dedup();
+        channels.into_iter().map(|ch| [0xB0 | ch, 123, 0]).collect()
+    }
+}
+
+/// Time on a clock that reads `clock_now_sec` at `perf_now_ms` for a
+/// `performance.now()`-style timestamp (e.g. an incoming MIDI message's).
+pub fn from_performance_ms(timestamp_ms: f64, clock_now_sec: f64, perf_now_ms: f64) -> f64 {
+    clock_now_sec + (timestamp_ms - perf_now_ms) / 1000.0
+}
+
+/// `performance.now()`-style timestamp (ms) for `at_sec` on a clock that
+/// reads `clock_now_sec` at `perf_now_ms`; past times map to now.
+pub fn to_performance_ms(at_sec: f64, clock_now_sec: f64, perf_now_ms: f64) -> f64 {
+    perf_now_ms + (at_sec - clock_now_sec).max(0.0) * 1000.0
+}
@@ -0,0 +1,60 @@
+/// Segment counts the mirror offers, in the order bar stepping visits them.
+pub const MIRROR_SEGMENTS: [u32; 3] = [2, 4, 6];
+
+/// Kaleidoscope symmetry in the composite: the image folded N ways around
+/// the swirl centre.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Mirror {
+    /// 0 for off, otherwise one of `MIRROR_SEGMENTS`
+    pub segments: u32,
+    /// Step to the next segment count on every bar line
+    pub step_on_bars: bool,
+}
+
+impl Mirror {
+    /// Parse `"off"`, `"<segments>"` or `"<segments>,bars"` (e.g.
+    /// `?mirror=4,bars`).
+    pub fn from_param(s: &str) -> Option<Mirror> {
+        let mut parts = s.split(',').map(str::trim);
+        let segments = parse_segments(parts.next()?)?;
+        let step_on_bars = match parts.next() {
+            None => false,
+            Some(p) if p.eq_ignore_ascii_case("bars") => true,
+            Some(_) => return None,
+        };
+        parts.next().is_none().then_some(Mirror {
+            segments,
+            step_on_bars,
+        })
+    }
+
+    /// `from_param`'s form of these settings.
+    pub fn param(&self) -> String {
+        match (self.segments, self.step_on_bars) {
+            (0, _) => "off".to_string(),
+            (n, false) => n.to_string(),
+            (n, true) => format!("{n},bars"),
+        }
+    }
+
+    /// Segments to fold into once `bars` bar lines have passed, stepping on
+    /// from the chosen count (`mirror_segments` in post.wgsl; 0 = off).
+    pub fn segments_at(&self, bars: u32) -> u32 {
+        let Some(start) = MIRROR_SEGMENTS.iter().position(|&n| n == self.segments) else {
+            return 0;
+        };
+        if !self.step_on_bars {
+            return self.segments;
+        }
+        MIRROR_SEGMENTS[(start + bars as usize) % MIRROR_SEGMENTS.len()]
+    }
+}
+
+/// `"off"` (0) or one of `MIRROR_SEGMENTS`.
+pub fn parse_segments(s: &str) -> Option<u32> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("off") {
+        return Some(0);
+    }
+    s.parse().ok().filter(|n| MIRROR_SEGMENTS.contains(n))
+}
@@ -0,0 +1,77 @@
+/// Volume change per ↑/↓ press.
+pub const VOLUME_STEP: f32 = 0.05;
+
+/// Listener-facing master level: a volume after the limiter (so venue gain
+/// staging is untouched) and a mute that remembers it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MasterMixer {
+    /// Linear gain, 0..=1
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for MasterMixer {
+    fn default() -> Self {
+        MasterMixer {
+            volume: 1.0,
+            muted: false,
+        }
+    }
+}
+
+impl MasterMixer {
+    /// Set the volume, clamped to 0..=1 (non-finite values are ignored).
+    pub fn set_volume(&mut self, volume: f32) {
+        if volume.is_finite() {
+            self.volume = volume.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Move the volume by `steps` × `VOLUME_STEP`; turning it up unmutes.
+    pub fn nudge(&mut self, steps: i32) {
+        self.set_volume(self.volume + steps as f32 * VOLUME_STEP);
+        if steps > 0 {
+            self.muted = false;
+        }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+    }
+
+    /// Gain the output should sit at.
+    pub fn gain(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume
+        }
+    }
+
+    /// Compact form for storage: `"0.80"`, or `"0.80,muted"`.
+    pub fn encode(&self) -> String {
+        let volume = format!("{:.2}", self.volume);
+        if self.muted {
+            volume + ",muted"
+        } else {
+            volume
+        }
+    }
+
+    /// Parse what `encode` wrote.
+    pub fn parse(s: &str) -> Option<MasterMixer> {
+        let mut parts = s.trim().split(',');
+        let volume: f32 = parts.next()?.trim().parse().ok()?;
+        let muted = match parts.next().map(str::trim) {
+            None => false,
+            Some("muted") => true,
+            Some(_) => return None,
+        };
+        if !volume.is_finite() || parts.next().is_some() {
+            return None;
+        }
+        let mut mixer = MasterMixer { volume: 1.0, muted };
+        mixer.set_volume(volume);
+        Some(mixer)
+    }
+}
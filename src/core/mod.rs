@@ -1,7 +1,100 @@
+pub mod ambisonics;
+pub mod antialias;
+pub mod attract;
+pub mod automation;
+pub mod bloom;
+pub mod bounce;
+pub mod bus;
+pub mod choreography;
+pub mod clock_sync;
+pub mod custom_shader;
+pub mod device;
+pub mod diagnostics;
+pub mod dice;
+pub mod displays;
+pub mod effects;
+pub mod envelope;
+pub mod eq;
+pub mod filter;
+pub mod fm;
+pub mod frame_math;
+pub mod generator;
+pub mod gesture;
+pub mod glyphs;
+pub mod groove;
+pub mod history;
+pub mod keymap;
+pub mod latency;
+pub mod lens;
+pub mod lifecycle;
+pub mod looper;
+pub mod metronome;
+pub mod midi_learn;
+pub mod midi_out;
+pub mod mirror;
+pub mod mixer;
+pub mod morph;
+pub mod motion;
 pub mod music;
+pub mod musical_clock;
+pub mod named_presets;
+pub mod note_repeat;
+pub mod onset;
+pub mod output;
+pub mod particles;
+pub mod physics;
+pub mod polyphony;
+pub mod preset;
+pub mod profiler;
+pub mod recording;
+pub mod recovery;
+pub mod render_scale;
+pub mod replay;
+pub mod reverb;
+pub mod ripple;
+pub mod sampler;
+pub mod saved_session;
+pub mod screenshot;
+pub mod selection;
+pub mod session;
+pub mod shader_reload;
+pub mod share;
+pub mod sidechain;
+pub mod signature;
+pub mod sketch;
+pub mod smf;
+pub mod spatial;
+pub mod spectrum;
+pub mod starfield;
+pub mod stereo;
+pub mod strum;
+pub mod theme;
+pub mod tonemap;
+pub mod touch;
+pub mod trails;
+pub mod unison;
+pub mod video;
+pub mod visualizer;
+pub mod voice_style;
+pub mod wavetable;
+pub mod wheel;
 
+pub use bus::{EngineEvent, EventBus};
+pub use dice::{roll_dice, ParamLocks};
+pub use groove::GROOVES;
+pub use history::{Command, History};
 pub use music::*;
+pub use session::SessionConfig;
+pub use share::ShareState;
+pub use spatial::SpatialHints;
 
 // Shaders bundled as string constants
 pub static POST_WGSL: &str = include_str!("../../shaders/post.wgsl");
 pub static WAVES_WGSL: &str = include_str!("../../shaders/waves.wgsl");
+pub static SCENE_WGSL: &str = include_str!("../../shaders/scene.wgsl");
+pub static EXPOSURE_WGSL: &str = include_str!("../../shaders/exposure.wgsl");
+pub static PARTICLES_WGSL: &str = include_str!("../../shaders/particles.wgsl");
+pub static TEXT_WGSL: &str = include_str!("../../shaders/text.wgsl");
+pub static LENS_WGSL: &str = include_str!("../../shaders/lens.wgsl");
+pub static FXAA_WGSL: &str = include_str!("../../shaders/fxaa.wgsl");
+pub static CUSTOM_WGSL: &str = include_str!("../../shaders/custom.wgsl");
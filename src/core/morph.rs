@@ -0,0 +1,48 @@
+/// Intermediate ("hybrid") scale part-way from `from` to `to`.
+///
+/// Each degree of `from` is remapped to its nearest degree in `to`, and degrees
+/// of `to` that nothing maps onto are added. These changes are applied one at a
+/// time, smallest pitch movement first, as `progress` goes from 0 to 1, so the
+/// harmony drifts through scales sharing most of their notes with the previous
+/// one. Common tones never move. Returns sorted, de-duplicated degrees; at
+/// `progress >= 1` the result is exactly `to`.
+pub fn hybrid_scale(from: &[f32], to: &[f32], progress: f32) -> Vec<f32> {
+    if progress >= 1.0 || from.is_empty() {
+        return to.to_vec();
+    }
+    let nearest = |d: f32, set: &[f32]| {
+        set.iter()
+            .copied()
+            .min_by(|a, b| (a - d).abs().total_cmp(&(b - d).abs()))
+            .unwrap_or(d)
+    };
+
+    // (distance, index into `from` or None for an added degree, target degree)
+    let mut changes: Vec<(f32, Option<usize>, f32)> = Vec::new();
+    for (i, &d) in from.iter().enumerate() {
+        let t = nearest(d, to);
+        if (t - d).abs() > 1e-4 {
+            changes.push(((t - d).abs(), Some(i), t));
+        }
+    }
+    for &t in to {
+        let reached = from.iter().any(|&d| (nearest(d, to) - t).abs() < 1e-4);
+        if !reached {
+            changes.push(((nearest(t, from) - t).abs(), None, t));
+        }
+    }
+    changes.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let applied =
+        ((progress.max(0.0) * (changes.len() + 1) as f32).floor() as usize).min(changes.len());
+    let mut out = from.to_vec();
+    for &(_, idx, t) in &changes[..applied] {
+        match idx {
+            Some(i) => out[i] = t,
+            None => out.push(t),
+        }
+    }
+    out.sort_by(|a, b| a.total_cmp(b));
+    out.dedup_by(|a, b| (*a - *b).abs() < 1e-4);
+    out
+}
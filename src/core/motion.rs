@@ -0,0 +1,64 @@
+use glam::Vec3;
+
+/// Engine-driven movement of voices that nobody is holding.
+///
+/// Voices trace their path around an anchor (where they were last placed),
+/// so the position-reactive FX sends and panners keep evolving on their own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MotionMode {
+    /// Voices stay where they are put
+    #[default]
+    Still,
+    /// Slow circles, each voice a third of a turn apart
+    Orbit,
+    /// Figure-of-eight style drift with a different frequency ratio per voice
+    Lissajous,
+}
+
+/// Size of the motion paths in engine units.
+pub const MOTION_RADIUS: f32 = 0.35;
+/// Base angular rate of the motion paths (radians per second).
+pub const MOTION_RATE: f32 = 0.25;
+
+/// (x, z) frequency ratios for Lissajous drift, cycled by voice.
+const LISSAJOUS_RATIOS: &[(f32, f32)] = &[(1.0, 2.0), (3.0, 2.0), (2.0, 3.0)];
+
+impl MotionMode {
+    pub const ALL: [MotionMode; 3] = [MotionMode::Still, MotionMode::Orbit, MotionMode::Lissajous];
+
+    /// Short identifier used in URLs and logs.
+    pub fn id(self) -> &'static str {
+        match self {
+            MotionMode::Still => "still",
+            MotionMode::Orbit => "orbit",
+            MotionMode::Lissajous => "lissajous",
+        }
+    }
+
+    pub fn next(self) -> MotionMode {
+        let i = Self::ALL.iter().position(|m| *m == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Displacement of `voice` from its anchor after `t_sec` of motion.
+    ///
+    /// Zero at `t_sec = 0`, so starting motion never makes a voice jump.
+    pub fn displacement(self, voice: usize, t_sec: f64) -> Vec3 {
+        self.path(voice, t_sec) - self.path(voice, 0.0)
+    }
+
+    fn path(self, voice: usize, t_sec: f64) -> Vec3 {
+        let phase = voice as f32 * std::f32::consts::TAU / 3.0;
+        let wt = (t_sec * MOTION_RATE as f64) as f32;
+        match self {
+            MotionMode::Still => Vec3::ZERO,
+            MotionMode::Orbit => {
+                Vec3::new((wt + phase).cos(), 0.0, (wt + phase).sin()) * MOTION_RADIUS
+            }
+            MotionMode::Lissajous => {
+                let (a, b) = LISSAJOUS_RATIOS[voice % LISSAJOUS_RATIOS.len()];
+                Vec3::new((a * wt + phase).sin(), 0.0, (b * wt).sin()) * MOTION_RADIUS
+            }
+        }
+    }
+}
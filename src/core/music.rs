@@ -1,15 +1,89 @@
+use super::bus::EngineEvent;
+use super::clock_sync::TransportLock;
+use super::envelope::Adsr;
+use super::filter::VoiceFilter;
+use super::fm::FmParams;
+use super::frame_math::VoiceSends;
+use super::generator::{StepContext, StochasticGenerator, VoiceGenerator};
+use super::groove::{Groove, STRAIGHT};
+use super::history::Command;
+use super::metronome::{Click, Metronome};
+use super::midi_learn::CcMap;
+use super::morph::hybrid_scale;
+use super::motion::MotionMode;
+use super::note_repeat::{BackgroundNote, RepeatHit};
+use super::physics::{physics_step, PhysicsParams};
+use super::polyphony::Polyphony;
+use super::preset::Preset;
+use super::replay::{ReplayInput, MAX_REPLAY_INPUTS};
+use super::sampler::{Sample, SamplerConfig};
+use super::sidechain::Sidechain;
+use super::spatial::SpatialHints;
+use super::unison::Unison;
+use super::voice_style::VoiceStyle;
+use super::wavetable::{builtin_wavetables, Wavetable};
 use glam::Vec3;
 use rand::prelude::*;
-use rand::seq::SliceRandom;
+use std::collections::VecDeque;
 use std::time::Duration;
 
+/// Mixed into the master seed for the `fork_rng` stream, keeping it
+/// independent of the per-voice generator seeds.
+const SESSION_RNG_STREAM: u64 = 0x5E55_1011_D1CE_0001;
+
 /// Basic oscillator shape used by synths in the web front-end.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Waveform {
     Sine,
     //Square,
     Saw,
     Triangle,
+    /// Single-cycle table at this index in `MusicEngine::wavetables`
+    /// (renderers fall back to a sine if it is missing)
+    Wavetable(u16),
+}
+
+impl Waveform {
+    /// Following shape in the cycle sine → saw → triangle → each of
+    /// `table_count` wavetables → sine.
+    pub fn next(self, table_count: usize) -> Waveform {
+        let next_table = |i: usize| {
+            if i < table_count {
+                Waveform::Wavetable(i as u16)
+            } else {
+                Waveform::Sine
+            }
+        };
+        match self {
+            Waveform::Sine => Waveform::Saw,
+            Waveform::Saw => Waveform::Triangle,
+            Waveform::Triangle => next_table(0),
+            Waveform::Wavetable(i) => next_table(i as usize + 1),
+        }
+    }
+
+    /// Short id used when saving, e.g. `saw` or `table2`.
+    pub fn id(self) -> String {
+        match self {
+            Waveform::Sine => "sine".to_string(),
+            Waveform::Saw => "saw".to_string(),
+            Waveform::Triangle => "triangle".to_string(),
+            Waveform::Wavetable(i) => format!("table{i}"),
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Waveform> {
+        match id {
+            "sine" => Some(Waveform::Sine),
+            "saw" => Some(Waveform::Saw),
+            "triangle" => Some(Waveform::Triangle),
+            _ => id
+                .strip_prefix("table")?
+                .parse()
+                .ok()
+                .map(Waveform::Wavetable),
+        }
+    }
 }
 
 /// Static configuration for a voice used at engine construction time.
@@ -20,6 +94,28 @@ pub enum Waveform {
 /// - `trigger_probability`: chance (0.0-1.0) that this voice triggers on each grid step
 /// - `octave_offset`: octave adjustment relative to root note (-2 to +2)
 /// - `base_duration`: base note duration in seconds
+/// - `loop_steps`: pattern length in grid steps after which the voice repeats
+///   itself (0 = free-running, never repeats). Different lengths per voice
+///   phase against each other (polymeter)
+/// - `beat_weights`: multipliers on `trigger_probability` by position in the bar
+///   (one entry per grid step, cycled; empty = same probability on every step)
+/// - `hysteresis`: how a note changes the odds of the next few (see `Hysteresis`)
+/// - `spatial`: panning, orbit and distance-attenuation hints read by every
+///   frontend's panner (see `SpatialHints`)
+/// - `polyphony`: cap on this voice's overlapping notes and which note a new
+///   one steals from (see `VoiceAllocator`)
+/// - `envelope`: attack/decay/sustain/release shape of every note, followed
+///   by both the WebAudio gain ramps and the worklet/offline renderers
+/// - `filter`: optional per-note lowpass/bandpass with envelope and key
+///   tracking (`None` leaves the oscillator raw)
+/// - `unison`: how many detuned, stereo-spread oscillators play each note
+/// - `fm`: optional two-operator FM, with `waveform` as the carrier
+/// - `sampler`: play a loaded recording instead of the oscillator (`waveform`
+///   and `fm` are then ignored)
+/// - `sends`: the voice's delay/reverb send levels, which its position then
+///   modulates; mixer state, so kept when a preset is applied
+/// - `style`: how the voice is drawn (glow, ripple frequency, shape, trail
+///   length), read by the waves and scene shaders
 #[derive(Clone, Debug)]
 pub struct VoiceConfig {
     pub waveform: Waveform,
@@ -27,6 +123,36 @@ pub struct VoiceConfig {
     pub trigger_probability: f32,
     pub octave_offset: i32,
     pub base_duration: f32,
+    pub loop_steps: u32,
+    pub beat_weights: &'static [f32],
+    pub hysteresis: Hysteresis,
+    pub spatial: SpatialHints,
+    pub polyphony: Polyphony,
+    pub envelope: Adsr,
+    pub filter: Option<VoiceFilter>,
+    pub unison: Unison,
+    pub fm: Option<FmParams>,
+    pub sampler: Option<SamplerConfig>,
+    pub sends: VoiceSends,
+    pub style: VoiceStyle,
+}
+
+/// Short-term memory in the trigger logic.
+///
+/// For `steps` grid steps after a voice fires, its trigger probability is
+/// multiplied by `factor`: below 1 spaces notes out, above 1 makes them cluster.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hysteresis {
+    pub steps: u32,
+    pub factor: f32,
+}
+
+impl Hysteresis {
+    /// Memoryless: every step is an independent coin flip.
+    pub const NONE: Hysteresis = Hysteresis {
+        steps: 0,
+        factor: 1.0,
+    };
 }
 
 /// A scheduled musical event produced by the engine for playback.
@@ -35,14 +161,16 @@ pub struct VoiceConfig {
 /// - `voice_index`: which voice this event belongs to (index into `voices`)
 /// - `frequency_hz`: target pitch in Hertz (already converted from MIDI)
 /// - `velocity`: normalized loudness 0..1 (mapped to gain envelope)
-/// - `start_time_sec`: absolute start time (AudioContext time) in seconds
 /// - `duration_sec`: nominal duration in seconds (envelope length)
+/// - `start_time_sec`: onset on the musical grid (shifted by the groove), in the
+///   clock passed to `schedule` (for `tick`, the engine's own elapsed time)
 #[derive(Clone, Debug, Default)]
 pub struct NoteEvent {
     pub voice_index: usize,
     pub frequency_hz: f32,
     pub velocity: f32,
     pub duration_sec: f32,
+    pub start_time_sec: f64,
 }
 
 /// Mutable runtime state per voice.
@@ -50,6 +178,47 @@ pub struct NoteEvent {
 pub struct VoiceState {
     pub position: Vec3,
     pub muted: bool,
+    /// Pinned where it was placed: drags, swirls and motion leave it alone
+    pub locked: bool,
+    /// Output gain ramp started by the last mute/solo change
+    pub fade: VoiceFade,
+}
+
+/// How long mute/solo changes take to crossfade a voice in or out.
+pub const MUTE_FADE_SEC: f32 = 0.25;
+
+/// A linear gain ramp on a voice's output, timed on the scheduling clock.
+///
+/// Frontends apply it to whatever sits after the voice's notes (a gain node,
+/// a mixer channel) so muting never cuts a sounding note mid-phrase.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoiceFade {
+    pub from_gain: f32,
+    pub target_gain: f32,
+    pub ramp_sec: f32,
+    /// Clock time (as passed to `schedule`) at which the ramp starts
+    pub start_sec: f64,
+}
+
+impl VoiceFade {
+    /// A fade that has already settled at `gain`.
+    pub fn steady(gain: f32) -> Self {
+        Self {
+            from_gain: gain,
+            target_gain: gain,
+            ramp_sec: 0.0,
+            start_sec: 0.0,
+        }
+    }
+
+    /// Gain at clock time `t_sec`.
+    pub fn gain_at(&self, t_sec: f64) -> f32 {
+        if self.ramp_sec <= 0.0 {
+            return self.target_gain;
+        }
+        let p = ((t_sec - self.start_sec) / self.ramp_sec as f64).clamp(0.0, 1.0) as f32;
+        self.from_gain + (self.target_gain - self.from_gain) * p
+    }
 }
 
 /// Global engine parameters controlling tempo and scale.
@@ -58,12 +227,14 @@ pub struct VoiceState {
 /// - `scale` is the allowed pitch degree set, expressed as semitone offsets
 /// - `root_midi` is the MIDI note number of the tonal center (e.g., 60 for C4)
 /// - `detune_cents` is the global detune offset in cents (-200 to +200)
+/// - `groove` is the micro-timing feel applied to scheduled onsets
 #[derive(Clone, Debug)]
 pub struct EngineParams {
     pub bpm: f32,
     pub scale: &'static [f32],
     pub root_midi: i32,
     pub detune_cents: f32,
+    pub groove: &'static Groove,
 }
 
 impl Default for EngineParams {
@@ -73,10 +244,52 @@ impl Default for EngineParams {
             scale: C_MAJOR_PENTATONIC,
             root_midi: 60, // Middle C
             detune_cents: 0.0,
+            groove: &STRAIGHT,
         }
     }
 }
 
+/// How far the scheduler may fall behind the caller's clock before it gives up
+/// on the missed steps and restarts the grid at the current time (e.g. after a
+/// pause or a throttled background tab), instead of emitting a burst of notes.
+pub const SCHEDULE_MAX_LAG_SEC: f64 = 0.25;
+
+/// Tempo drift from an external transport tolerated before following it, so
+/// jitter in a measured clock doesn't flood listeners with tempo changes.
+pub const SYNC_BPM_TOLERANCE: f32 = 0.1;
+
+/// Lowest and highest root note reachable by `MusicEngine::transpose` (C1..C7).
+pub const MIN_ROOT_MIDI: i32 = 24;
+pub const MAX_ROOT_MIDI: i32 = 96;
+
+/// Largest per-voice octave shift either way.
+pub const MAX_OCTAVE_OFFSET: i32 = 2;
+
+/// Grid steps (eighth notes) in one 4/4 bar.
+pub const STEPS_PER_BAR: u64 = 8;
+
+/// A scale transition in progress (see `MusicEngine::morph_scale`).
+#[derive(Clone, Debug)]
+struct ScaleMorph {
+    from: Vec<f32>,
+    /// Hybrid scale currently in use
+    current: Vec<f32>,
+    start_step: u64,
+    steps: u64,
+}
+
+/// A preset transition in progress (see `MusicEngine::morph_to_preset`).
+#[derive(Clone, Debug)]
+struct PresetMorph {
+    from: Preset,
+    to: Preset,
+    start_step: u64,
+    steps: u64,
+}
+
+/// Beat weights for a 4/4 bar of eighths favouring the downbeat and beat 3.
+pub const STRONG_BEATS: &[f32] = &[1.6, 0.5, 0.9, 0.5, 1.3, 0.5, 0.9, 0.5];
+
 /// Default five-note scale centered around middle C.
 pub const C_MAJOR_PENTATONIC: &[f32] = &[0.0, 2.0, 4.0, 7.0, 9.0, 12.0];
 
@@ -103,16 +316,80 @@ pub const TET31_PENTATONIC: &[f32] = &[0.0, 2.4, 4.8, 7.2, 9.6, 12.0];
 ///
 /// Typical usage:
 /// - Construct with `MusicEngine::new(configs, params, seed)`
-/// - Call `tick(dt, now_sec, &mut out_events)` regularly to schedule audio
+/// - Call `schedule(now_sec, lookahead_sec, &mut out_events)` regularly with an
+///   audio clock to schedule ahead of time, or `tick(dt, &mut out_events)` to
+///   advance on the engine's own clock (use one or the other, not both)
 /// - Use `toggle_mute`, `toggle_solo`, `reseed_voice`, and `set_voice_position`
 ///   to interact with the engine state
+/// - Call `drain_events()` to collect the `EngineEvent`s those changes produced
+#[derive(Clone)]
 pub struct MusicEngine {
     pub voices: Vec<VoiceState>,
     pub configs: Vec<VoiceConfig>,
     pub params: EngineParams,
-    rngs: Vec<StdRng>,
+    /// Note source for each voice, asked once per grid step
+    generators: Vec<Box<dyn VoiceGenerator>>,
+    /// Generator state at the start of each voice's loop, restored when it wraps
+    loop_origins: Vec<Box<dyn VoiceGenerator>>,
+    /// Per-voice source of fresh seeds for `reseed_voice(_, None)`
+    seed_rngs: Vec<StdRng>,
+    /// Per-voice step counter within its loop
+    loop_positions: Vec<u32>,
+    /// Grid steps since each voice last fired (`u32::MAX` = not yet, or forgotten)
+    steps_since_fire: Vec<u32>,
+    seed: u64,
+    /// Stream behind `fork_rng`, for user-triggered randomization
+    session_rng: StdRng,
     solo_index: Option<usize>,
-    beat_accum: f64,
+    /// Engine-owned clock advanced by `tick`
+    clock_sec: f64,
+    /// Caller clock at the latest `schedule` call; mute fades start from here
+    now_sec: f64,
+    /// Grid time of the next step not yet scheduled
+    next_step_sec: Option<f64>,
+    /// Number of grid steps scheduled so far (drives groove tables)
+    step_index: u64,
+    /// In-progress gradual scale change, if any
+    morph: Option<ScaleMorph>,
+    /// In-progress gradual preset change, if any
+    preset_morph: Option<PresetMorph>,
+    motion: MotionMode,
+    /// Where each voice's motion path is centred (its position at motion time 0)
+    motion_anchors: Vec<Vec3>,
+    /// Per-voice motion time; stands still while the voice is held
+    motion_clocks: Vec<f64>,
+    /// Voices currently grabbed by the user, exempt from motion
+    held: Vec<bool>,
+    /// Springs pulling voices back toward their base positions, if enabled
+    physics: Option<PhysicsParams>,
+    velocities: Vec<Vec3>,
+    /// Ducking of the other voices by a trigger voice, if enabled
+    sidechain: Option<Sidechain>,
+    metronome: Metronome,
+    /// Grid steps of count-in still to play before notes resume
+    count_in_steps: u64,
+    /// Metronome clicks scheduled but not yet collected by `drain_clicks`
+    pending_clicks: Vec<Click>,
+    /// Every beat scheduled, clicking or not, until collected by `drain_beats`
+    pending_beats: Vec<Click>,
+    /// Background note held down to repeat on the grid, and how often it has
+    held_note: Option<BackgroundNote>,
+    held_repeats: u32,
+    /// Retriggers of the held note not yet collected by `drain_repeats`
+    pending_repeats: Vec<RepeatHit>,
+    /// Tables behind `Waveform::Wavetable`; starts with the built-ins
+    pub wavetables: Vec<Wavetable>,
+    /// Recordings played by `VoiceConfig::sampler`
+    pub samples: Vec<Sample>,
+    /// Hardware controllers mapped to parameters by MIDI-learn
+    pub controls: CcMap,
+    /// Everything done to the engine since it was built, with the grid step
+    /// each came before, for writing out as a replay; stops at
+    /// `MAX_REPLAY_INPUTS`
+    input_log: Vec<(u64, ReplayInput)>,
+    /// Inputs of a replay being played, done again as the grid reaches them
+    replay_queue: VecDeque<(u64, ReplayInput)>,
+    pending_events: Vec<EngineEvent>,
 }
 
 impl MusicEngine {
@@ -123,36 +400,382 @@ impl MusicEngine {
             .map(|c| VoiceState {
                 position: c.base_position,
                 muted: false,
+                locked: false,
+                fade: VoiceFade::steady(1.0),
             })
             .collect::<Vec<_>>();
 
-        // Derive per-voice RNGs from base seed so we can reseed voices independently
-        let rngs = (0..voices.len())
-            .map(|i| {
-                let mix = seed ^ (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
-                StdRng::seed_from_u64(mix)
-            })
+        // Derive per-voice seeds from base seed so we can reseed voices independently
+        let mixes = (0..voices.len())
+            .map(|i| seed ^ (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
             .collect::<Vec<_>>();
+        let generators = mixes
+            .iter()
+            .map(|&mix| Box::new(StochasticGenerator::new(mix)) as Box<dyn VoiceGenerator>)
+            .collect::<Vec<_>>();
+        let motion_anchors = voices.iter().map(|v| v.position).collect::<Vec<_>>();
+        let voice_count = voices.len();
 
         Self {
             voices,
             configs,
             params,
-            rngs,
+            loop_origins: generators.clone(),
+            loop_positions: vec![0; generators.len()],
+            steps_since_fire: vec![u32::MAX; generators.len()],
+            seed_rngs: mixes
+                .iter()
+                .map(|&mix| StdRng::seed_from_u64(!mix))
+                .collect(),
+            generators,
+            seed,
+            session_rng: StdRng::seed_from_u64(seed ^ SESSION_RNG_STREAM),
             solo_index: None,
-            beat_accum: 0.0,
+            clock_sec: 0.0,
+            now_sec: 0.0,
+            next_step_sec: None,
+            step_index: 0,
+            morph: None,
+            preset_morph: None,
+            motion: MotionMode::Still,
+            motion_anchors,
+            motion_clocks: vec![0.0; voice_count],
+            held: vec![false; voice_count],
+            physics: None,
+            velocities: vec![Vec3::ZERO; voice_count],
+            sidechain: None,
+            metronome: Metronome::default(),
+            count_in_steps: 0,
+            pending_clicks: Vec::new(),
+            pending_beats: Vec::new(),
+            held_note: None,
+            held_repeats: 0,
+            pending_repeats: Vec::new(),
+            wavetables: builtin_wavetables(),
+            samples: Vec::new(),
+            controls: CcMap::default(),
+            input_log: Vec::new(),
+            replay_queue: VecDeque::new(),
+            pending_events: Vec::new(),
+        }
+    }
+
+    /// Master seed the engine was constructed with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// A fresh RNG for one randomized user action (dice roll, random key).
+    ///
+    /// Drawn from a stream derived from the master seed, so the same sequence
+    /// of actions gives the same results in every session with that seed.
+    pub fn fork_rng(&mut self) -> StdRng {
+        StdRng::seed_from_u64(self.session_rng.gen())
+    }
+
+    /// Visual signature glyph for the current scale, root and construction seed.
+    pub fn signature(&self) -> super::signature::Signature {
+        super::signature::pitch_class_signature(self.params.scale, self.params.root_midi, self.seed)
+    }
+
+    /// Take all state-change events queued since the last call.
+    pub fn drain_events(&mut self) -> Vec<EngineEvent> {
+        std::mem::take(&mut self.pending_events)
+    }
+
+    fn emit(&mut self, ev: EngineEvent) {
+        self.pending_events.push(ev);
+    }
+
+    /// Become `fresh` (an engine rebuilt from a seed, e.g. to load a preset or
+    /// a replay), queuing an event for each setting it changes so subscribers
+    /// follow the swap as they would any command. Voices it unmutes or mutes
+    /// fade from their current gain. Events queued but not yet drained are
+    /// kept, ahead of those of the swap.
+    pub fn replace_with(&mut self, fresh: MusicEngine) {
+        let mut events = std::mem::take(&mut self.pending_events);
+        let old = std::mem::replace(self, fresh);
+        let now_sec = old.now_sec;
+        for voice in 0..self.voices.len() {
+            let (Some(was), Some(was_config)) = (old.voices.get(voice), old.configs.get(voice))
+            else {
+                continue;
+            };
+            let (v, c) = (&mut self.voices[voice], &self.configs[voice]);
+            if v.muted != was.muted {
+                v.fade = VoiceFade {
+                    from_gain: was.fade.gain_at(now_sec),
+                    target_gain: if v.muted { 0.0 } else { 1.0 },
+                    ramp_sec: MUTE_FADE_SEC,
+                    start_sec: now_sec,
+                };
+                events.push(EngineEvent::MuteChanged {
+                    voice,
+                    muted: v.muted,
+                });
+            }
+            if v.locked != was.locked {
+                events.push(EngineEvent::LockChanged {
+                    voice,
+                    locked: v.locked,
+                });
+            }
+            if c.octave_offset != was_config.octave_offset {
+                events.push(EngineEvent::OctaveChanged {
+                    voice,
+                    octave: c.octave_offset,
+                });
+            }
+            if c.trigger_probability != was_config.trigger_probability {
+                events.push(EngineEvent::DensityChanged {
+                    voice,
+                    density: c.trigger_probability,
+                });
+            }
+            if c.loop_steps != was_config.loop_steps {
+                events.push(EngineEvent::LoopStepsChanged {
+                    voice,
+                    steps: c.loop_steps,
+                });
+            }
+            if c.waveform != was_config.waveform {
+                events.push(EngineEvent::WaveformChanged {
+                    voice,
+                    waveform: c.waveform,
+                });
+            }
+            if c.sends != was_config.sends {
+                events.push(EngineEvent::SendsChanged {
+                    voice,
+                    sends: c.sends,
+                });
+            }
+        }
+        if self.solo_index != old.solo_index {
+            events.push(EngineEvent::SoloChanged {
+                voice: self.solo_index,
+            });
+        }
+        let (p, was) = (&self.params, &old.params);
+        if p.bpm != was.bpm {
+            events.push(EngineEvent::BpmChanged(p.bpm));
+        }
+        if p.scale != was.scale {
+            events.push(EngineEvent::ScaleChanged(p.scale));
+        }
+        if p.root_midi != was.root_midi {
+            events.push(EngineEvent::RootChanged(p.root_midi));
+        }
+        if p.detune_cents != was.detune_cents {
+            events.push(EngineEvent::DetuneChanged(p.detune_cents));
+        }
+        if p.groove.name != was.groove.name {
+            events.push(EngineEvent::GrooveChanged(p.groove.name));
+        }
+        if self.motion() != old.motion() {
+            events.push(EngineEvent::MotionChanged(self.motion()));
         }
+        events.append(&mut self.pending_events);
+        self.pending_events = events;
     }
 
     /// Set beats-per-minute for the internal scheduler.
     pub fn set_bpm(&mut self, bpm: f32) {
+        // A NaN or zero tempo would make every step's duration meaningless
+        if !bpm.is_finite() || bpm <= 0.0 {
+            return;
+        }
         self.params.bpm = bpm;
+        self.emit(EngineEvent::BpmChanged(bpm));
+    }
+
+    /// Set the allowed scale degrees (semitone offsets from the root).
+    pub fn set_scale(&mut self, scale: &'static [f32]) {
+        self.morph = None;
+        self.params.scale = scale;
+        self.emit(EngineEvent::ScaleChanged(scale));
+    }
+
+    /// Move to `scale` gradually over `bars` bars, passing through hybrid scales.
+    ///
+    /// `params.scale` reports the target immediately; notes are drawn from the
+    /// in-between scale (announced via `EngineEvent::HybridScale`) until the morph
+    /// completes. Zero bars switches instantly.
+    pub fn morph_scale(&mut self, scale: &'static [f32], bars: u32) {
+        if bars == 0 {
+            self.set_scale(scale);
+            return;
+        }
+        let from = self.active_scale().to_vec();
+        self.params.scale = scale;
+        self.morph = Some(ScaleMorph {
+            current: from.clone(),
+            from,
+            start_step: self.step_index,
+            steps: bars as u64 * STEPS_PER_BAR,
+        });
+        self.emit(EngineEvent::ScaleChanged(scale));
+    }
+
+    /// Degrees notes are currently drawn from (the hybrid scale while morphing).
+    pub fn active_scale(&self) -> &[f32] {
+        match &self.morph {
+            Some(m) => &m.current,
+            None => self.params.scale,
+        }
+    }
+
+    fn advance_morph(&mut self, step: u64) {
+        let Some(m) = self.morph.as_mut() else {
+            return;
+        };
+        let progress = (step - m.start_step.min(step)) as f32 / m.steps as f32;
+        if progress >= 1.0 {
+            self.morph = None;
+            self.emit(EngineEvent::HybridScale(self.params.scale.to_vec()));
+            return;
+        }
+        let hybrid = hybrid_scale(&m.from, self.params.scale, progress);
+        if hybrid != m.current {
+            m.current = hybrid.clone();
+            self.emit(EngineEvent::HybridScale(hybrid));
+        }
+    }
+
+    /// Switch to `preset` at once (cancels any preset morph in progress).
+    pub fn apply_preset(&mut self, preset: &Preset) {
+        self.preset_morph = None;
+        self.set_scale(preset.params.scale);
+        self.set_preset_values(preset);
+        self.emit(EngineEvent::PresetChanged(preset.name));
+    }
+
+    /// Move to `preset` gradually over `bars` bars.
+    ///
+    /// Each grid step applies `Preset::lerp` between the state at the start
+    /// and the target; the scale morphs note by note as with `morph_scale`.
+    /// Zero bars applies the preset instantly.
+    pub fn morph_to_preset(&mut self, preset: &Preset, bars: u32) {
+        if bars == 0 {
+            self.apply_preset(preset);
+            return;
+        }
+        let from = Preset::from_engine(self, "");
+        self.morph_scale(preset.params.scale, bars);
+        self.preset_morph = Some(PresetMorph {
+            from,
+            to: preset.clone(),
+            start_step: self.step_index,
+            steps: bars as u64 * STEPS_PER_BAR,
+        });
+    }
+
+    fn advance_preset_morph(&mut self, step: u64) {
+        let Some(m) = self.preset_morph.take() else {
+            return;
+        };
+        let progress = (step - m.start_step.min(step)) as f32 / m.steps as f32;
+        if progress >= 1.0 {
+            self.set_preset_values(&m.to);
+            self.emit(EngineEvent::PresetChanged(m.to.name));
+            return;
+        }
+        self.set_preset_values(&Preset::lerp(&m.from, &m.to, progress));
+        self.preset_morph = Some(m);
+    }
+
+    /// Apply everything in `preset` except the scale, only touching (and
+    /// announcing) values that actually change.
+    fn set_preset_values(&mut self, preset: &Preset) {
+        let p = &preset.params;
+        if p.bpm != self.params.bpm {
+            self.set_bpm(p.bpm);
+        }
+        if p.root_midi != self.params.root_midi {
+            self.set_root_midi(p.root_midi);
+        }
+        if p.detune_cents != self.params.detune_cents {
+            self.set_detune_cents(p.detune_cents);
+        }
+        if p.groove.name != self.params.groove.name {
+            self.set_groove(p.groove);
+        }
+        if !preset.controls.is_empty() {
+            self.controls = preset.controls.clone();
+        }
+        for (i, cfg) in preset.voices.iter().enumerate().take(self.configs.len()) {
+            let prev = &self.configs[i];
+            let (loop_steps, octave, waveform, sampler, sends) = (
+                prev.loop_steps,
+                prev.octave_offset,
+                prev.waveform,
+                prev.sampler,
+                prev.sends,
+            );
+            self.configs[i] = VoiceConfig {
+                loop_steps,
+                octave_offset: octave,
+                waveform,
+                sampler,
+                sends,
+                ..cfg.clone()
+            };
+            if cfg.loop_steps != loop_steps {
+                self.set_loop_steps(i, cfg.loop_steps);
+            }
+            self.set_octave_offset(i, cfg.octave_offset);
+            self.set_waveform(i, cfg.waveform);
+            self.set_sampler(i, cfg.sampler);
+        }
+    }
+
+    /// Set the tonal center as a MIDI note number.
+    pub fn set_root_midi(&mut self, root_midi: i32) {
+        self.params.root_midi = root_midi;
+        self.emit(EngineEvent::RootChanged(root_midi));
+    }
+
+    /// Shift the whole texture by `semitones`, keeping the root within
+    /// `MIN_ROOT_MIDI..=MAX_ROOT_MIDI`.
+    pub fn transpose(&mut self, semitones: i32) {
+        let root = (self.params.root_midi + semitones).clamp(MIN_ROOT_MIDI, MAX_ROOT_MIDI);
+        if root != self.params.root_midi {
+            self.set_root_midi(root);
+        }
+    }
+
+    /// Set a voice's octave relative to the root (clamped to ±`MAX_OCTAVE_OFFSET`).
+    pub fn set_octave_offset(&mut self, voice_index: usize, octave: i32) {
+        let octave = octave.clamp(-MAX_OCTAVE_OFFSET, MAX_OCTAVE_OFFSET);
+        if let Some(c) = self.configs.get_mut(voice_index) {
+            if c.octave_offset != octave {
+                c.octave_offset = octave;
+                self.emit(EngineEvent::OctaveChanged {
+                    voice: voice_index,
+                    octave,
+                });
+            }
+        }
+    }
+
+    /// Move a voice up or down by `octaves` from where it is now.
+    pub fn shift_octave(&mut self, voice_index: usize, octaves: i32) {
+        if let Some(c) = self.configs.get(voice_index) {
+            self.set_octave_offset(voice_index, c.octave_offset + octaves);
+        }
     }
 
     /// Set the global detune offset in cents.
     /// Range: -200 to +200 cents (±2 semitones)
     pub fn set_detune_cents(&mut self, detune_cents: f32) {
         self.params.detune_cents = detune_cents.clamp(-200.0, 200.0);
+        self.emit(EngineEvent::DetuneChanged(self.params.detune_cents));
+    }
+
+    /// Select the groove template applied to subsequently scheduled steps.
+    pub fn set_groove(&mut self, groove: &'static Groove) {
+        self.params.groove = groove;
+        self.emit(EngineEvent::GrooveChanged(groove.name));
     }
 
     /// Adjust the global detune offset by the specified amount in cents.
@@ -164,83 +787,865 @@ impl MusicEngine {
 
     /// Reset the global detune offset to 0 cents (no detune).
     pub fn reset_detune(&mut self) {
-        self.params.detune_cents = 0.0;
+        self.set_detune_cents(0.0);
     }
 
     /// Toggle mute flag for a voice.
     pub fn toggle_mute(&mut self, voice_index: usize) {
+        if let Some(muted) = self.voices.get(voice_index).map(|v| v.muted) {
+            self.set_muted(voice_index, !muted);
+        }
+    }
+
+    /// Pin a voice where it is, or free it again: a locked voice ignores
+    /// pointer drags, swirls, auto-motion and springs (its position can still
+    /// be set directly, e.g. by undo or a keyboard nudge).
+    pub fn toggle_lock(&mut self, voice_index: usize) {
+        if let Some(v) = self.voices.get_mut(voice_index) {
+            v.locked = !v.locked;
+            let locked = v.locked;
+            self.emit(EngineEvent::LockChanged {
+                voice: voice_index,
+                locked,
+            });
+        }
+    }
+
+    /// Whether a voice is locked in place.
+    pub fn is_locked(&self, voice_index: usize) -> bool {
+        self.voices.get(voice_index).is_some_and(|v| v.locked)
+    }
+
+    // Set a voice's mute flag, emitting an event only when it changes. The voice
+    // fades from its current gain rather than cutting off.
+    fn set_muted(&mut self, voice_index: usize, muted: bool) {
+        let now_sec = self.now_sec;
         if let Some(v) = self.voices.get_mut(voice_index) {
-            v.muted = !v.muted;
+            if v.muted != muted {
+                v.muted = muted;
+                v.fade = VoiceFade {
+                    from_gain: v.fade.gain_at(now_sec),
+                    target_gain: if muted { 0.0 } else { 1.0 },
+                    ramp_sec: MUTE_FADE_SEC,
+                    start_sec: now_sec,
+                };
+                self.emit(EngineEvent::MuteChanged {
+                    voice: voice_index,
+                    muted,
+                });
+            }
         }
     }
 
     /// Update the engine-space position of a voice.
+    ///
+    /// Under auto-motion the voice carries on along its path from here.
     pub fn set_voice_position(&mut self, voice_index: usize, pos: Vec3) {
+        self.log_position(voice_index, pos);
         if let Some(v) = self.voices.get_mut(voice_index) {
             v.position = pos;
+            let t = self.motion_clocks[voice_index];
+            self.motion_anchors[voice_index] = pos - self.motion.displacement(voice_index, t);
         }
     }
 
-    /// Reseed the per-voice RNG. If `seed` is None, a new random seed is chosen.
+    /// Mark a voice as held (e.g. being dragged) so auto-motion leaves it alone.
+    pub fn set_held(&mut self, voice_index: usize, held: bool) {
+        if let Some(h) = self.held.get_mut(voice_index) {
+            *h = held;
+        }
+    }
+
+    pub fn is_held(&self, voice_index: usize) -> bool {
+        self.held.get(voice_index).copied().unwrap_or(false)
+    }
+
+    pub fn motion(&self) -> MotionMode {
+        self.motion
+    }
+
+    /// Switch auto-motion mode; voices set off from where they are now.
+    pub fn set_motion(&mut self, mode: MotionMode) {
+        self.motion = mode;
+        for (i, v) in self.voices.iter().enumerate() {
+            self.motion_anchors[i] = v.position;
+            self.motion_clocks[i] = 0.0;
+        }
+        self.emit(EngineEvent::MotionChanged(mode));
+    }
+
+    pub fn physics(&self) -> Option<PhysicsParams> {
+        self.physics
+    }
+
+    /// Enable (`Some`) or disable spring physics.
+    ///
+    /// While enabled, unheld voices are pulled toward their base positions
+    /// (following auto-motion) and pushed apart, so dropped voices drift back
+    /// instead of staying put. Disabling leaves voices where they are.
+    pub fn set_physics(&mut self, params: Option<PhysicsParams>) {
+        self.physics = params;
+        self.velocities.fill(Vec3::ZERO);
+        for (i, v) in self.voices.iter().enumerate() {
+            self.motion_anchors[i] = v.position;
+            self.motion_clocks[i] = 0.0;
+        }
+        self.emit(EngineEvent::PhysicsChanged(params));
+    }
+
+    pub fn sidechain(&self) -> Option<Sidechain> {
+        self.sidechain
+    }
+
+    /// Enable (`Some`) or disable sidechain ducking; frontends apply it.
+    pub fn set_sidechain(&mut self, sidechain: Option<Sidechain>) {
+        self.sidechain = sidechain;
+        self.emit(EngineEvent::SidechainChanged(sidechain));
+    }
+
+    pub fn metronome(&self) -> Metronome {
+        self.metronome
+    }
+
+    /// Change the click track; frontends set the click bus level.
+    pub fn set_metronome(&mut self, metronome: Metronome) {
+        self.metronome = metronome;
+        self.emit(EngineEvent::MetronomeChanged(metronome));
+    }
+
+    /// Before resuming from a pause: play the metronome's count-in bars
+    /// (clicks only) from the next bar line, then carry on with the notes.
+    /// Does nothing when the count-in is zero bars.
+    pub fn start_count_in(&mut self) {
+        let bars = self.metronome.count_in_bars;
+        if bars == 0 {
+            return;
+        }
+        self.log_input(ReplayInput::CountIn(bars));
+        self.count_in(bars as u64);
+    }
+
+    fn count_in(&mut self, bars: u64) {
+        self.count_in_steps = bars * STEPS_PER_BAR;
+        self.step_index = self.step_index.next_multiple_of(STEPS_PER_BAR);
+        self.restart_grid();
+    }
+
+    /// Take the metronome clicks scheduled since the last call.
+    pub fn drain_clicks(&mut self) -> Vec<Click> {
+        std::mem::take(&mut self.pending_clicks)
+    }
+
+    /// Take the beats scheduled since the last call, whether or not the
+    /// metronome is on, for visuals that follow the bar.
+    pub fn drain_beats(&mut self) -> Vec<Click> {
+        std::mem::take(&mut self.pending_beats)
+    }
+
+    /// Retrigger `note` on every grid step from the next one scheduled until
+    /// `release_note`.
+    pub fn hold_note(&mut self, note: BackgroundNote) {
+        self.held_note = Some(note);
+        self.held_repeats = 0;
+    }
+
+    /// Stop repeating the held note; true if it repeated at least once.
+    pub fn release_note(&mut self) -> bool {
+        self.held_note = None;
+        std::mem::take(&mut self.held_repeats) > 0
+    }
+
+    /// Take the held note's retriggers scheduled since the last call.
+    pub fn drain_repeats(&mut self) -> Vec<RepeatHit> {
+        std::mem::take(&mut self.pending_repeats)
+    }
+
+    /// Move every unheld, unlocked voice `dt` further along its motion path,
+    /// through the spring physics if enabled.
+    ///
+    /// Called by `tick`; frontends that drive the engine with `schedule`
+    /// call it once per frame instead.
+    pub fn advance_motion(&mut self, dt: Duration) {
+        if self.motion == MotionMode::Still && self.physics.is_none() {
+            return;
+        }
+        // A locked voice stays where it was placed, as a held one does
+        let pinned: Vec<bool> = self
+            .held
+            .iter()
+            .zip(&self.voices)
+            .map(|(held, v)| *held || v.locked)
+            .collect();
+        for (clock, pinned) in self.motion_clocks.iter_mut().zip(&pinned) {
+            if !pinned {
+                *clock += dt.as_secs_f64();
+            }
+        }
+        let Some(params) = self.physics else {
+            for (i, v) in self.voices.iter_mut().enumerate() {
+                if !pinned[i] {
+                    v.position =
+                        self.motion_anchors[i] + self.motion.displacement(i, self.motion_clocks[i]);
+                }
+            }
+            return;
+        };
+        let targets: Vec<Vec3> = self
+            .configs
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c.base_position + self.motion.displacement(i, self.motion_clocks[i]))
+            .collect();
+        let mut positions: Vec<Vec3> = self.voices.iter().map(|v| v.position).collect();
+        physics_step(
+            &mut positions,
+            &mut self.velocities,
+            &targets,
+            &pinned,
+            &params,
+            dt.as_secs_f32(),
+        );
+        for (v, p) in self.voices.iter_mut().zip(positions) {
+            v.position = p;
+        }
+    }
+
+    /// Reseed a voice's generator. If `seed` is None, a new random seed is chosen.
     pub fn reseed_voice(&mut self, voice_index: usize, seed: Option<u64>) {
-        if let Some(r) = self.rngs.get_mut(voice_index) {
-            let new_seed = seed.unwrap_or_else(|| r.gen());
-            *r = StdRng::seed_from_u64(new_seed);
+        if let Some(g) = self.generators.get_mut(voice_index) {
+            let new_seed = seed.unwrap_or_else(|| self.seed_rngs[voice_index].gen());
+            g.reseed(new_seed);
+            self.restart_loop(voice_index);
+            self.emit(EngineEvent::VoiceReseeded {
+                voice: voice_index,
+                seed: Some(new_seed),
+            });
+        }
+    }
+
+    /// Change the oscillator shape of a voice.
+    pub fn set_waveform(&mut self, voice_index: usize, waveform: Waveform) {
+        if let Some(c) = self.configs.get_mut(voice_index) {
+            if c.waveform != waveform {
+                c.waveform = waveform;
+                self.emit(EngineEvent::WaveformChanged {
+                    voice: voice_index,
+                    waveform,
+                });
+            }
         }
     }
 
+    /// Add a custom single-cycle table; returns the waveform that plays it.
+    pub fn load_wavetable(&mut self, table: Wavetable) -> Waveform {
+        self.wavetables.push(table);
+        Waveform::Wavetable((self.wavetables.len() - 1) as u16)
+    }
+
+    /// Add a recording for voices to play; returns its index for `SamplerConfig::sample`.
+    pub fn load_sample(&mut self, sample: Sample) -> u16 {
+        self.samples.push(sample);
+        (self.samples.len() - 1) as u16
+    }
+
+    /// Switch a voice to sample playback (`Some`) or back to its oscillator.
+    pub fn set_sampler(&mut self, voice_index: usize, sampler: Option<SamplerConfig>) {
+        if let Some(c) = self.configs.get_mut(voice_index) {
+            if c.sampler != sampler {
+                c.sampler = sampler;
+                self.emit(EngineEvent::SamplerChanged {
+                    voice: voice_index,
+                    sample: sampler.map(|s| s.sample),
+                });
+            }
+        }
+    }
+
+    /// Set a voice's delay/reverb send levels (clamped to 0..`VoiceSends::MAX`).
+    pub fn set_sends(&mut self, voice_index: usize, sends: VoiceSends) {
+        let sends = sends.clamped();
+        if let Some(c) = self.configs.get_mut(voice_index) {
+            if c.sends != sends {
+                c.sends = sends;
+                self.emit(EngineEvent::SendsChanged {
+                    voice: voice_index,
+                    sends,
+                });
+            }
+        }
+    }
+
+    /// Set how often a voice plays: its trigger probability per grid step,
+    /// clamped to 0..=1.
+    pub fn set_density(&mut self, voice_index: usize, density: f32) {
+        if !density.is_finite() {
+            return;
+        }
+        let density = density.clamp(0.0, 1.0);
+        if let Some(c) = self.configs.get_mut(voice_index) {
+            if c.trigger_probability != density {
+                c.trigger_probability = density;
+                self.emit(EngineEvent::DensityChanged {
+                    voice: voice_index,
+                    density,
+                });
+            }
+        }
+    }
+
+    /// Set a voice's loop length in grid steps (0 = free-running).
+    ///
+    /// The loop starts from the voice's current generator state on the next step.
+    pub fn set_loop_steps(&mut self, voice_index: usize, steps: u32) {
+        if let Some(c) = self.configs.get_mut(voice_index) {
+            c.loop_steps = steps;
+            self.restart_loop(voice_index);
+            self.emit(EngineEvent::LoopStepsChanged {
+                voice: voice_index,
+                steps,
+            });
+        }
+    }
+
+    /// Replace a voice's note source, e.g. with a third-party algorithm.
+    ///
+    /// The new generator starts on the next grid step (and a looping voice
+    /// loops over its output from there).
+    pub fn set_generator(&mut self, voice_index: usize, generator: Box<dyn VoiceGenerator>) {
+        if let Some(g) = self.generators.get_mut(voice_index) {
+            *g = generator;
+            self.restart_loop(voice_index);
+            self.emit(EngineEvent::GeneratorChanged { voice: voice_index });
+        }
+    }
+
+    fn restart_loop(&mut self, voice_index: usize) {
+        if let Some(g) = self.generators.get(voice_index) {
+            self.loop_origins[voice_index] = g.clone();
+            self.loop_positions[voice_index] = 0;
+        }
+    }
+
+    /// The seed `reseed_voice(voice_index, None)` would pick next, without consuming it.
+    pub fn fresh_seed(&self, voice_index: usize) -> u64 {
+        self.seed_rngs
+            .get(voice_index)
+            .map(|r| r.clone().gen())
+            .unwrap_or_default()
+    }
+
     /// Solo a voice. Toggling solo on the same voice clears solo mode.
     pub fn toggle_solo(&mut self, voice_index: usize) {
         match self.solo_index {
             Some(idx) if idx == voice_index => {
                 // Clear solo -> unmute all
                 self.solo_index = None;
-                for v in &mut self.voices {
-                    v.muted = false;
+                for i in 0..self.voices.len() {
+                    self.set_muted(i, false);
                 }
             }
             _ => {
                 self.solo_index = Some(voice_index);
-                for (i, v) in self.voices.iter_mut().enumerate() {
-                    v.muted = i != voice_index;
+                for i in 0..self.voices.len() {
+                    self.set_muted(i, i != voice_index);
                 }
             }
         }
+        self.emit(EngineEvent::SoloChanged {
+            voice: self.solo_index,
+        });
     }
 
-    /// Advance the scheduler by `dt`, pushing any newly scheduled `NoteEvent`s into `out_events`.
+    /// Perform a `Command` and return the command that reverts it.
+    pub fn apply(&mut self, cmd: &Command) -> Command {
+        // A batch is logged as the commands in it, and positions by
+        // `set_voice_position`
+        if !matches!(cmd, Command::Batch(_) | Command::SetPosition { .. }) {
+            self.log_input(ReplayInput::Command(cmd.clone()));
+        }
+        match cmd {
+            Command::ToggleMute { voice } => {
+                self.toggle_mute(*voice);
+                Command::ToggleMute { voice: *voice }
+            }
+            Command::ToggleLock { voice } => {
+                self.toggle_lock(*voice);
+                Command::ToggleLock { voice: *voice }
+            }
+            Command::ToggleSolo { voice } => {
+                let inverse = self.mutes_memento();
+                self.toggle_solo(*voice);
+                inverse
+            }
+            Command::Reseed { voice, seed } => {
+                // A looping voice is restored to the start of its loop
+                let prev = match self.configs.get(*voice) {
+                    Some(c) if c.loop_steps > 0 => self.loop_origins.get(*voice),
+                    _ => self.generators.get(*voice),
+                };
+                let inverse = match prev {
+                    Some(g) => Command::RestoreGenerator {
+                        voice: *voice,
+                        generator: g.clone(),
+                    },
+                    None => Command::Batch(Vec::new()),
+                };
+                self.reseed_voice(*voice, *seed);
+                inverse
+            }
+            Command::SetWaveform { voice, waveform } => {
+                let prev = self.configs.get(*voice).map(|c| c.waveform);
+                self.set_waveform(*voice, *waveform);
+                match prev {
+                    Some(waveform) => Command::SetWaveform {
+                        voice: *voice,
+                        waveform,
+                    },
+                    None => Command::Batch(Vec::new()),
+                }
+            }
+            Command::SetSampler { voice, sampler } => {
+                let prev = self.configs.get(*voice).map(|c| c.sampler);
+                self.set_sampler(*voice, *sampler);
+                match prev {
+                    Some(sampler) => Command::SetSampler {
+                        voice: *voice,
+                        sampler,
+                    },
+                    None => Command::Batch(Vec::new()),
+                }
+            }
+            Command::SetSends { voice, sends } => {
+                let prev = self.configs.get(*voice).map(|c| c.sends);
+                self.set_sends(*voice, *sends);
+                match prev {
+                    Some(sends) => Command::SetSends {
+                        voice: *voice,
+                        sends,
+                    },
+                    None => Command::Batch(Vec::new()),
+                }
+            }
+            Command::SetDensity { voice, density } => {
+                let prev = self.configs.get(*voice).map(|c| c.trigger_probability);
+                self.set_density(*voice, *density);
+                match prev {
+                    Some(density) => Command::SetDensity {
+                        voice: *voice,
+                        density,
+                    },
+                    None => Command::Batch(Vec::new()),
+                }
+            }
+            Command::SetLoopSteps { voice, steps } => {
+                let prev = self.configs.get(*voice).map(|c| c.loop_steps);
+                self.set_loop_steps(*voice, *steps);
+                match prev {
+                    Some(prev) => Command::SetLoopSteps {
+                        voice: *voice,
+                        steps: prev,
+                    },
+                    None => Command::Batch(Vec::new()),
+                }
+            }
+            Command::SetPosition { voice, position } => {
+                let prev = self.voices.get(*voice).map(|v| v.position);
+                self.set_voice_position(*voice, *position);
+                match prev {
+                    Some(p) => Command::SetPosition {
+                        voice: *voice,
+                        position: p,
+                    },
+                    None => Command::Batch(Vec::new()),
+                }
+            }
+            Command::SetMotion(mode) => {
+                let prev = self.motion;
+                self.set_motion(*mode);
+                Command::SetMotion(prev)
+            }
+            Command::SetPhysics(params) => {
+                let prev = self.physics;
+                self.set_physics(*params);
+                Command::SetPhysics(prev)
+            }
+            Command::SetSidechain(sidechain) => {
+                let prev = self.sidechain;
+                self.set_sidechain(*sidechain);
+                Command::SetSidechain(prev)
+            }
+            Command::ApplyPreset(preset) => {
+                let inverse = Command::ApplyPreset(Box::new(Preset::from_engine(self, "")));
+                self.apply_preset(preset);
+                inverse
+            }
+            Command::MorphToPreset { preset, bars } => {
+                let inverse = Command::ApplyPreset(Box::new(Preset::from_engine(self, "")));
+                self.morph_to_preset(preset, *bars);
+                inverse
+            }
+            Command::SetBpm(bpm) => {
+                let prev = self.params.bpm;
+                self.set_bpm(*bpm);
+                Command::SetBpm(prev)
+            }
+            Command::SetRootMidi(root) => {
+                let prev = self.params.root_midi;
+                self.set_root_midi(*root);
+                Command::SetRootMidi(prev)
+            }
+            Command::Transpose(semitones) => {
+                let prev = self.params.root_midi;
+                self.transpose(*semitones);
+                Command::SetRootMidi(prev)
+            }
+            Command::ShiftOctave { voice, octaves } => {
+                let prev = self.configs.get(*voice).map(|c| c.octave_offset);
+                self.shift_octave(*voice, *octaves);
+                match prev {
+                    Some(octave) => Command::SetOctave {
+                        voice: *voice,
+                        octave,
+                    },
+                    None => Command::Batch(Vec::new()),
+                }
+            }
+            Command::SetOctave { voice, octave } => {
+                let prev = self.configs.get(*voice).map(|c| c.octave_offset);
+                self.set_octave_offset(*voice, *octave);
+                match prev {
+                    Some(octave) => Command::SetOctave {
+                        voice: *voice,
+                        octave,
+                    },
+                    None => Command::Batch(Vec::new()),
+                }
+            }
+            Command::MorphScale { scale, bars } => {
+                let prev = self.params.scale;
+                self.morph_scale(scale, *bars);
+                Command::SetScale(prev)
+            }
+            Command::SetScale(scale) => {
+                let prev = self.params.scale;
+                self.set_scale(scale);
+                Command::SetScale(prev)
+            }
+            Command::SetDetune(cents) => {
+                let prev = self.params.detune_cents;
+                self.set_detune_cents(*cents);
+                Command::SetDetune(prev)
+            }
+            Command::SetGroove(groove) => {
+                let prev = self.params.groove;
+                self.set_groove(groove);
+                Command::SetGroove(prev)
+            }
+            Command::AdjustDetune(delta) => {
+                let prev = self.params.detune_cents;
+                self.adjust_detune_cents(*delta);
+                Command::SetDetune(prev)
+            }
+            Command::ResetDetune => {
+                let prev = self.params.detune_cents;
+                self.reset_detune();
+                Command::SetDetune(prev)
+            }
+            Command::Batch(cmds) => {
+                let mut inverses: Vec<Command> = cmds.iter().map(|c| self.apply(c)).collect();
+                inverses.reverse();
+                Command::Batch(inverses)
+            }
+            Command::RestoreMutes { muted, solo } => {
+                let inverse = self.mutes_memento();
+                for (i, m) in muted.iter().enumerate() {
+                    self.set_muted(i, *m);
+                }
+                if self.solo_index != *solo {
+                    self.solo_index = *solo;
+                    self.emit(EngineEvent::SoloChanged { voice: *solo });
+                }
+                inverse
+            }
+            Command::SetGenerator { voice, generator } => match self.generators.get(*voice) {
+                Some(prev) => {
+                    let inverse = Command::SetGenerator {
+                        voice: *voice,
+                        generator: prev.clone(),
+                    };
+                    self.set_generator(*voice, generator.clone());
+                    inverse
+                }
+                None => Command::Batch(Vec::new()),
+            },
+            Command::RestoreGenerator { voice, generator } => match self.generators.get_mut(*voice)
+            {
+                Some(g) => {
+                    let prev = std::mem::replace(g, generator.clone());
+                    self.restart_loop(*voice);
+                    self.emit(EngineEvent::VoiceReseeded {
+                        voice: *voice,
+                        seed: None,
+                    });
+                    Command::RestoreGenerator {
+                        voice: *voice,
+                        generator: prev,
+                    }
+                }
+                None => Command::Batch(Vec::new()),
+            },
+        }
+    }
+
+    fn mutes_memento(&self) -> Command {
+        Command::RestoreMutes {
+            muted: self.voices.iter().map(|v| v.muted).collect(),
+            solo: self.solo_index,
+        }
+    }
+
+    /// Duration of one grid step (an eighth note) at the current tempo.
+    pub fn step_duration_sec(&self) -> f64 {
+        60.0 / self.params.bpm as f64 / 2.0
+    }
+
+    /// Schedule every grid step that falls before `now_sec + lookahead_sec`.
+    ///
+    /// `now_sec` is any monotonic clock (typically `AudioContext.currentTime`);
+    /// events are stamped with their exact grid time on that clock, so callers
+    /// can hand them straight to the audio backend regardless of frame jitter.
+    /// Steps are spaced by the tempo at the time they are reached, so tempo
+    /// changes take effect from the next unscheduled step.
+    pub fn schedule(&mut self, now_sec: f64, lookahead_sec: f64, out_events: &mut Vec<NoteEvent>) {
+        self.now_sec = now_sec;
+        let horizon = now_sec + lookahead_sec;
+        let mut t = match self.next_step_sec {
+            Some(t) if now_sec - t <= SCHEDULE_MAX_LAG_SEC => t,
+            _ => now_sec,
+        };
+        while t <= horizon {
+            self.schedule_step(t, out_events);
+            t += self.step_duration_sec();
+        }
+        self.next_step_sec = Some(t);
+    }
+
+    /// Where `at_sec` (on the `schedule` clock) falls on the grid, in steps
+    /// counted like `step_index`; fractional between steps.
+    pub fn grid_position(&self, at_sec: f64) -> f64 {
+        match self.next_step_sec {
+            Some(next) => self.step_index as f64 - (next - at_sec) / self.step_duration_sec(),
+            None => self.step_index as f64,
+        }
+    }
+
+    /// Everything done to the engine since it was built (see `Replay`).
+    pub fn input_log(&self) -> &[(u64, ReplayInput)] {
+        &self.input_log
+    }
+
+    /// Whether the input log reached `MAX_REPLAY_INPUTS` and stopped, so a
+    /// replay of it drifts after its last step.
+    pub fn input_log_is_full(&self) -> bool {
+        self.input_log.len() >= MAX_REPLAY_INPUTS
+    }
+
+    /// Do `inputs` again, each just before the grid step it came before.
+    /// Meant for an engine freshly built from the replay's seed, whose grid
+    /// starts at step 0 as the original's did.
+    pub fn queue_replay(&mut self, inputs: Vec<(u64, ReplayInput)>) {
+        self.replay_queue = inputs.into();
+    }
+
+    /// Whether a replay still has inputs to come.
+    pub fn is_replaying(&self) -> bool {
+        !self.replay_queue.is_empty()
+    }
+
+    fn play_replay_inputs(&mut self) {
+        while let Some((step, _)) = self.replay_queue.front() {
+            if *step > self.step_index {
+                break;
+            }
+            let Some((_, input)) = self.replay_queue.pop_front() else {
+                break;
+            };
+            match input {
+                ReplayInput::Command(cmd) => {
+                    self.apply(&cmd);
+                }
+                ReplayInput::CountIn(bars) => {
+                    self.log_input(ReplayInput::CountIn(bars));
+                    self.count_in(bars as u64);
+                }
+            }
+        }
+    }
+
+    fn log_input(&mut self, input: ReplayInput) {
+        if !self.input_log_is_full() {
+            self.input_log.push((self.step_index, input));
+        }
+    }
+
+    // A voice moved more than once before the same step only needs its last
+    // place logged, so drop an earlier move of it among the positions logged
+    // last for this step (gestures and loop passes move several voices at once)
+    fn log_position(&mut self, voice: usize, position: Vec3) {
+        let moves_this_step = self
+            .input_log
+            .iter()
+            .rev()
+            .take_while(|(step, input)| {
+                *step == self.step_index
+                    && matches!(input, ReplayInput::Command(Command::SetPosition { .. }))
+            })
+            .count();
+        let start = self.input_log.len() - moves_this_step;
+        if let Some(i) = self.input_log[start..].iter().position(|(_, input)| {
+            matches!(input, ReplayInput::Command(Command::SetPosition { voice: v, .. }) if *v == voice)
+        }) {
+            self.input_log.remove(start + i);
+        }
+        self.log_input(ReplayInput::Command(Command::SetPosition {
+            voice,
+            position,
+        }));
+    }
+
+    /// Forget the scheduling clock so the next `schedule` call starts a fresh grid.
+    pub fn restart_grid(&mut self) {
+        self.next_step_sec = None;
+    }
+
+    /// Carry the grid on from `now_sec` after its clock stood still or jumped
+    /// (a hidden tab, a suspended audio context): steps already scheduled
+    /// ahead stay, and missed ones are dropped rather than played all at
+    /// once. The step count carries on, so nothing on the grid moves.
+    pub fn resync_grid(&mut self, now_sec: f64) {
+        self.now_sec = now_sec;
+        if let Some(next) = self.next_step_sec {
+            self.next_step_sec = Some(next.max(now_sec));
+        }
+    }
+
+    /// Follow an external transport instead of the internal tempo.
+    ///
+    /// Adopts its tempo (to 0.1 BPM, once it drifts past `SYNC_BPM_TOLERANCE`)
+    /// and moves the next unscheduled step onto its grid, numbered from the
+    /// transport's start so beat weights and grooves fall on the sender's bars.
+    /// Call before each `schedule` while synced; steps already scheduled stay.
+    pub fn lock_to_transport(&mut self, lock: &TransportLock) {
+        if (lock.bpm - self.params.bpm).abs() > SYNC_BPM_TOLERANCE {
+            self.set_bpm((lock.bpm * 10.0).round() / 10.0);
+        }
+        let step_sec = lock.step_duration_sec();
+        let next = self.next_step_sec.unwrap_or(self.now_sec);
+        let k = ((next - lock.at_sec) / step_sec).round().max(0.0);
+        self.next_step_sec = Some(lock.at_sec + k * step_sec);
+        self.step_index = lock.step + k as u64;
+    }
+
+    /// Advance the engine's own clock by `dt` and schedule the steps it covers.
+    ///
+    /// The first step falls one grid step after the first tick.
+    #[allow(dead_code)] // The web frontend schedules against the audio clock instead
     pub fn tick(&mut self, dt: Duration, out_events: &mut Vec<NoteEvent>) {
-        let seconds_per_beat = 60.0 / self.params.bpm as f64;
-        self.beat_accum += dt.as_secs_f64();
-        while self.beat_accum >= seconds_per_beat / 2.0 {
-            // eighth notes grid
-            self.beat_accum -= seconds_per_beat / 2.0;
-            self.schedule_step(out_events);
+        if self.next_step_sec.is_none() {
+            self.next_step_sec = Some(self.clock_sec + self.step_duration_sec());
         }
+        self.clock_sec += dt.as_secs_f64();
+        self.advance_motion(dt);
+        self.schedule(self.clock_sec, 0.0, out_events);
+    }
+
+    /// Trigger probability of `voice` on global grid step `step`, after beat
+    /// weighting and the voice's hysteresis following its last note.
+    pub fn trigger_probability_at(&self, voice: usize, step: u64) -> f32 {
+        let c = &self.configs[voice];
+        let weight = match c.beat_weights.len() {
+            0 => 1.0,
+            n => c.beat_weights[(step % n as u64) as usize],
+        };
+        let since = self.steps_since_fire[voice];
+        let memory = if (1..=c.hysteresis.steps).contains(&since) {
+            c.hysteresis.factor
+        } else {
+            1.0
+        };
+        (c.trigger_probability * weight * memory).clamp(0.0, 1.0)
     }
 
     /// Schedule a single grid step for all voices.
-    fn schedule_step(&mut self, out_events: &mut Vec<NoteEvent>) {
-        for (i, voice) in self.voices.iter().enumerate() {
-            if voice.muted {
+    fn schedule_step(&mut self, step_time_sec: f64, out_events: &mut Vec<NoteEvent>) {
+        self.play_replay_inputs();
+        let step = self.step_index;
+        self.step_index += 1;
+        self.advance_preset_morph(step);
+        self.advance_morph(step);
+        if let Some(beat) = Click::on_step(step, step_time_sec) {
+            self.pending_beats.push(beat);
+            if self.metronome.enabled || self.count_in_steps > 0 {
+                self.pending_clicks.push(beat);
+            }
+        }
+        if self.count_in_steps > 0 {
+            self.count_in_steps -= 1;
+            return;
+        }
+        if let Some(note) = self.held_note {
+            self.held_repeats += 1;
+            self.pending_repeats.push(RepeatHit {
+                note,
+                time_sec: step_time_sec,
+            });
+        }
+        let step_sec = self.step_duration_sec();
+        for i in 0..self.voices.len() {
+            // A muted voice keeps playing until its fade-out has finished
+            let silent = self.voices[i].muted && self.voices[i].fade.gain_at(step_time_sec) <= 0.0;
+            let loop_steps = self.configs[i].loop_steps;
+            if loop_steps > 0 {
+                // Each voice counts its own steps, so loops of different lengths drift apart
+                if self.loop_positions[i] == 0 {
+                    self.generators[i] = self.loop_origins[i].clone();
+                    self.steps_since_fire[i] = u32::MAX;
+                }
+                self.loop_positions[i] = (self.loop_positions[i] + 1) % loop_steps;
+            } else if silent {
                 continue;
             }
-            let prob = self.configs[i].trigger_probability;
-            let rng = &mut self.rngs[i];
-            if rng.gen::<f32>() < prob {
-                let degree = *self.params.scale.choose(rng).unwrap_or(&0.0);
-                let octave = self.configs[i].octave_offset;
-                let midi = self.params.root_midi as f32 + degree + (octave * 12) as f32;
-                let freq = midi_to_hz_with_detune(midi, self.params.detune_cents);
-                let vel = 0.4 + rng.gen::<f32>() * 0.6;
-                let dur = self.configs[i].base_duration + rng.gen::<f32>() * 0.2;
-                out_events.push(NoteEvent {
-                    voice_index: i,
-                    frequency_hz: freq,
-                    velocity: vel,
-                    duration_sec: dur,
-                });
+            // Looping voices keep generating while muted so they stay in phase
+            self.steps_since_fire[i] = self.steps_since_fire[i].saturating_add(1);
+            let ctx = StepContext {
+                voice: i,
+                step,
+                time_sec: step_time_sec,
+                bpm: self.params.bpm,
+                probability: self.trigger_probability_at(i, step),
+                scale: match &self.morph {
+                    Some(m) => &m.current[..],
+                    None => self.params.scale,
+                },
+                root_midi: self.params.root_midi,
+                octave_offset: self.configs[i].octave_offset,
+                base_duration: self.configs[i].base_duration,
+            };
+            let Some(note) = self.generators[i].next_step(&ctx) else {
+                continue;
+            };
+            self.steps_since_fire[i] = 0;
+            if silent {
+                continue;
             }
+            let offset = self.params.groove.offset(i, step) as f64 * step_sec;
+            out_events.push(NoteEvent {
+                voice_index: i,
+                frequency_hz: midi_to_hz_with_detune(note.midi, self.params.detune_cents),
+                velocity: note.velocity,
+                duration_sec: note.duration_sec,
+                start_time_sec: step_time_sec + offset,
+            });
         }
     }
 }
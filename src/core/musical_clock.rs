@@ -0,0 +1,122 @@
+use super::metronome::{Click, STEPS_PER_BEAT};
+use super::music::STEPS_PER_BAR;
+
+/// Beats in a bar of the engine's grid.
+pub const BEATS_PER_BAR: u32 = (STEPS_PER_BAR / STEPS_PER_BEAT) as u32;
+
+/// Seconds for a beat's and a downbeat's pulse to fall to 1/e.
+pub const BEAT_PULSE_DECAY_SEC: f32 = 0.15;
+pub const DOWNBEAT_PULSE_DECAY_SEC: f32 = 0.4;
+
+/// Camera zoom-in (fraction of the field of view) at the peak of a beat and
+/// of a downbeat; kept small so the picture breathes rather than jumps.
+pub const BEAT_ZOOM: f32 = 0.006;
+pub const DOWNBEAT_ZOOM: f32 = 0.018;
+
+/// Beat length assumed until two beats have been heard (120 BPM).
+const DEFAULT_BEAT_SEC: f64 = 0.5;
+
+/// Where the music is in its beat and bar, for visuals that move with it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClockPulse {
+    /// Progress through the current beat, 0..1
+    pub beat_phase: f32,
+    /// Progress through the current bar, 0..1
+    pub bar_phase: f32,
+    /// Decaying flash from the latest beat, 1 on the beat
+    pub beat: f32,
+    /// Decaying swell from the latest downbeat, 1 on the downbeat
+    pub downbeat: f32,
+}
+
+impl ClockPulse {
+    /// Field-of-view scale: just under 1 on a beat, most of all on a
+    /// downbeat, easing back to 1 between them.
+    pub fn zoom(&self) -> f32 {
+        1.0 - BEAT_ZOOM * self.beat - DOWNBEAT_ZOOM * self.downbeat
+    }
+
+    /// As the shaders read it (`clock` in waves.wgsl and post.wgsl).
+    pub fn pack(&self) -> [f32; 4] {
+        [self.beat_phase, self.bar_phase, self.beat, self.downbeat]
+    }
+}
+
+/// Counts bar lines as the clock's bar phase wraps, for visuals that change
+/// on them.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BarCounter {
+    bars: u32,
+    last_bar_phase: f32,
+}
+
+impl BarCounter {
+    /// Note the clock at `clock` and return the bar lines passed so far.
+    pub fn update(&mut self, clock: &ClockPulse) -> u32 {
+        if clock.bar_phase + 0.5 < self.last_bar_phase {
+            self.bars += 1;
+        }
+        self.last_bar_phase = clock.bar_phase;
+        self.bars
+    }
+}
+
+/// Follows the engine's scheduled beats as they are heard.
+///
+/// Beats are queued ahead of time with their audio-clock stamps (the same
+/// ones the metronome clicks on) and only counted once `advance` reaches
+/// them, so the pulse lands with the sound rather than the scheduler.
+#[derive(Clone, Debug, Default)]
+pub struct MusicalClock {
+    pending: Vec<Click>,
+    last_beat_sec: Option<f64>,
+    last_downbeat_sec: Option<f64>,
+    beat_sec: Option<f64>,
+    /// Beats since the latest downbeat
+    beat_in_bar: u32,
+}
+
+impl MusicalClock {
+    pub fn queue(&mut self, beats: impl IntoIterator<Item = Click>) {
+        self.pending.extend(beats);
+    }
+
+    /// Count every queued beat heard by `heard_sec`.
+    pub fn advance(&mut self, heard_sec: f64) {
+        self.pending.sort_by(|a, b| a.at_sec.total_cmp(&b.at_sec));
+        let heard = self.pending.partition_point(|b| b.at_sec <= heard_sec);
+        for beat in self.pending.drain(..heard) {
+            if let Some(last) = self.last_beat_sec {
+                let gap = beat.at_sec - last;
+                if gap > 0.0 {
+                    self.beat_sec = Some(gap);
+                }
+            }
+            self.last_beat_sec = Some(beat.at_sec);
+            if beat.accent {
+                self.last_downbeat_sec = Some(beat.at_sec);
+                self.beat_in_bar = 0;
+            } else {
+                self.beat_in_bar = (self.beat_in_bar + 1) % BEATS_PER_BAR;
+            }
+        }
+    }
+
+    /// The pulse at `heard_sec`; all zero until the first beat is heard.
+    pub fn sample(&self, heard_sec: f64) -> ClockPulse {
+        let Some(last_beat) = self.last_beat_sec else {
+            return ClockPulse::default();
+        };
+        let since_beat = (heard_sec - last_beat).max(0.0);
+        let beat_phase = (since_beat / self.beat_sec.unwrap_or(DEFAULT_BEAT_SEC)).min(1.0) as f32;
+        let downbeat = self.last_downbeat_sec.map_or(0.0, |t| {
+            (-((heard_sec - t).max(0.0) as f32) / DOWNBEAT_PULSE_DECAY_SEC).exp()
+        });
+        ClockPulse {
+            beat_phase,
+            bar_phase: (self.beat_in_bar as f32 + beat_phase) / BEATS_PER_BAR as f32,
+            beat: (-(since_beat as f32) / BEAT_PULSE_DECAY_SEC).exp(),
+            downbeat,
+        }
+    }
+}
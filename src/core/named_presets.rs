@@ -0,0 +1,67 @@
+use super::saved_session::SavedSession;
+
+/// Longest preset name kept; longer names are cut short.
+pub const MAX_PRESET_NAME_CHARS: usize = 40;
+
+/// Sessions saved under names of the player's choosing, in the order they
+/// were first saved. Kept in the browser and exported or imported as JSON
+/// holding each name with its `SavedSession::encode` text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NamedPresets {
+    presets: Vec<(String, SavedSession)>,
+}
+
+impl NamedPresets {
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.presets.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SavedSession> {
+        self.presets
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, session)| session)
+    }
+
+    /// Keep `session` as `name` (trimmed and cut to `MAX_PRESET_NAME_CHARS`),
+    /// replacing any preset already called that. Returns the name used, or
+    /// `None` for a blank name.
+    pub fn save(&mut self, name: &str, session: SavedSession) -> Option<String> {
+        let name: String = name.trim().chars().take(MAX_PRESET_NAME_CHARS).collect();
+        if name.is_empty() {
+            return None;
+        }
+        match self.presets.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, kept)) => *kept = session,
+            None => self.presets.push((name.clone(), session)),
+        }
+        Some(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.presets.len();
+        self.presets.retain(|(n, _)| n != name);
+        self.presets.len() != before
+    }
+
+    /// Every preset as its name and saved text, for writing out as JSON.
+    pub fn to_pairs(&self) -> Vec<(String, String)> {
+        self.presets
+            .iter()
+            .map(|(name, session)| (name.clone(), session.encode()))
+            .collect()
+    }
+
+    /// Add presets read back from `to_pairs`' form, replacing ones with the
+    /// same name; pairs whose text doesn't decode are skipped. Returns how
+    /// many were added.
+    pub fn merge_pairs<'a>(
+        &mut self,
+        pairs: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> usize {
+        pairs
+            .into_iter()
+            .filter_map(|(name, text)| self.save(name, SavedSession::decode(text)?))
+            .count()
+    }
+}
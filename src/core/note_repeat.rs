@@ -0,0 +1,87 @@
+use super::touch::TAP_SLOP_PX;
+
+/// A one-shot played on the background: held down, it is retriggered on
+/// every grid step like a note repeat pad until the pointer lets go.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BackgroundNote {
+    /// Voice whose waveform, gain and sends it plays through
+    pub voice: usize,
+    pub frequency_hz: f32,
+    pub velocity: f32,
+    pub cutoff_hz: f32,
+    pub duration_sec: f64,
+}
+
+/// One retrigger of the held note, at its grid time on the scheduling clock.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RepeatHit {
+    pub note: BackgroundNote,
+    pub time_sec: f64,
+}
+
+/// The primary pointer pressed on the background: it starts repeating its
+/// note once held still for the long-press time, but not once it has moved
+/// past `TAP_SLOP_PX`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BackgroundHold {
+    press: Option<HoldPress>,
+    serial: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct HoldPress {
+    pointer_id: i32,
+    start_px: [f32; 2],
+    /// Where it landed (screen uv), which picks the note
+    uv: [f32; 2],
+    moved: bool,
+    repeating: bool,
+}
+
+impl BackgroundHold {
+    /// A press at `px` (CSS pixels) and `uv`; returns the serial to `ripen`
+    /// once the long-press time has passed.
+    pub fn press(&mut self, pointer_id: i32, px: [f32; 2], uv: [f32; 2]) -> u32 {
+        self.serial = self.serial.wrapping_add(1);
+        self.press = Some(HoldPress {
+            pointer_id,
+            start_px: px,
+            uv,
+            moved: false,
+            repeating: false,
+        });
+        self.serial
+    }
+
+    pub fn moved_to(&mut self, pointer_id: i32, px: [f32; 2]) {
+        if let Some(p) = self.press.as_mut().filter(|p| p.pointer_id == pointer_id) {
+            let (dx, dy) = (px[0] - p.start_px[0], px[1] - p.start_px[1]);
+            p.moved |= dx * dx + dy * dy > TAP_SLOP_PX * TAP_SLOP_PX;
+        }
+    }
+
+    /// The long-press time for press `serial` has passed: where it landed if
+    /// it is still down and held still, and it now repeats.
+    pub fn ripen(&mut self, serial: u32) -> Option<[f32; 2]> {
+        let p = self.press.as_mut().filter(|_| serial == self.serial)?;
+        if p.moved || p.repeating {
+            return None;
+        }
+        p.repeating = true;
+        Some(p.uv)
+    }
+
+    /// Let go of `pointer_id`'s press; true if it had started repeating.
+    pub fn release(&mut self, pointer_id: i32) -> bool {
+        match self.press {
+            Some(p) if p.pointer_id == pointer_id => self.cancel(),
+            _ => false,
+        }
+    }
+
+    /// Drop the press whichever pointer it was (it became part of a
+    /// gesture); true if it had started repeating.
+    pub fn cancel(&mut self) -> bool {
+        self.press.take().is_some_and(|p| p.repeating)
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+
+/// Flux values the adaptive threshold is computed over (about 0.7 s of
+/// frames at 60 fps).
+pub const ONSET_HISTORY: usize = 40;
+
+/// Flux frames needed before anything counts as an onset.
+const MIN_HISTORY: usize = 8;
+
+/// Shortest gap between two onsets; anything closer is the same hit ringing.
+pub const MIN_ONSET_INTERVAL_SEC: f64 = 0.08;
+
+/// Flux below this is silence or noise, however quiet the recent past was.
+pub const MIN_ONSET_FLUX: f32 = 1e-4;
+
+/// Default standard deviations above the recent mean a hit must reach.
+pub const DEFAULT_ONSET_SENSITIVITY: f32 = 1.5;
+
+/// What makes the voices pulse on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PulseSource {
+    /// Scheduled notes, as they become audible
+    #[default]
+    Notes,
+    /// Hits detected in the master output
+    Onsets,
+    /// Hits detected in the microphone input (play along, or visualize any
+    /// sound in the room)
+    Mic,
+}
+
+impl PulseSource {
+    pub fn id(&self) -> &'static str {
+        match self {
+            PulseSource::Notes => "notes",
+            PulseSource::Onsets => "onsets",
+            PulseSource::Mic => "mic",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<PulseSource> {
+        [PulseSource::Notes, PulseSource::Onsets, PulseSource::Mic]
+            .into_iter()
+            .find(|s| s.id().eq_ignore_ascii_case(id.trim()))
+    }
+}
+
+/// A perceived hit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Onset {
+    pub at_sec: f64,
+    /// How far the flux cleared the threshold (1 = just, 2 = twice over)
+    pub strength: f32,
+}
+
+/// Spectral-flux onset detector over successive magnitude spectra (e.g. an
+/// analyser's frequency data, one frame per call).
+///
+/// The flux is the rise in magnitude summed over bins (falls are ignored, so
+/// decays don't trigger); a frame is an onset when its flux stands
+/// `sensitivity` standard deviations above the mean of the recent frames.
+#[derive(Clone, Debug)]
+pub struct OnsetDetector {
+    pub sensitivity: f32,
+    previous: Vec<f32>,
+    history: VecDeque<f32>,
+    last_onset_sec: Option<f64>,
+}
+
+impl Default for OnsetDetector {
+    fn default() -> Self {
+        OnsetDetector::new(DEFAULT_ONSET_SENSITIVITY)
+    }
+}
+
+impl OnsetDetector {
+    pub fn new(sensitivity: f32) -> OnsetDetector {
+        OnsetDetector {
+            sensitivity,
+            previous: Vec::new(),
+            history: VecDeque::with_capacity(ONSET_HISTORY + 1),
+            last_onset_sec: None,
+        }
+    }
+
+    /// Feed one spectrum in dB (`-inf` for silent bins) taken at `at_sec`.
+    pub fn process_db(&mut self, spectrum_db: &[f32], at_sec: f64) -> Option<Onset> {
+        let magnitudes: Vec<f32> = spectrum_db
+            .iter()
+            .map(|db| {
+                if db.is_finite() {
+                    10f32.powf(db / 20.0)
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        self.process(&magnitudes, at_sec)
+    }
+
+    /// Feed one linear magnitude spectrum taken at `at_sec`.
+    pub fn process(&mut self, magnitudes: &[f32], at_sec: f64) -> Option<Onset> {
+        let flux = if self.previous.len() == magnitudes.len() {
+            spectral_flux(&self.previous, magnitudes)
+        } else {
+            0.0
+        };
+        self.previous.clear();
+        self.previous.extend_from_slice(magnitudes);
+
+        let onset = self.threshold().and_then(|threshold| {
+            let spaced = self
+                .last_onset_sec
+                .is_none_or(|last| at_sec - last >= MIN_ONSET_INTERVAL_SEC);
+            (spaced && flux > threshold).then(|| Onset {
+                at_sec,
+                strength: flux / threshold,
+            })
+        });
+        if onset.is_some() {
+            self.last_onset_sec = Some(at_sec);
+        }
+        if self.history.len() == ONSET_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(flux);
+        onset
+    }
+
+    /// Flux a frame must exceed, once enough history has built up.
+    fn threshold(&self) -> Option<f32> {
+        let n = self.history.len();
+        if n < MIN_HISTORY {
+            return None;
+        }
+        let mean = self.history.iter().sum::<f32>() / n as f32;
+        let var = self.history.iter().map(|f| (f - mean).powi(2)).sum::<f32>() / n as f32;
+        Some((mean + self.sensitivity * var.sqrt()).max(MIN_ONSET_FLUX))
+    }
+}
+
+/// Upper edges of the bands that drive the voices (bass, lead, pad) when
+/// the microphone feeds the visuals.
+pub const BAND_EDGES_HZ: [f32; 2] = [250.0, 2000.0];
+
+/// Loudness of the low, mid and high bands (0..=1) of a dB spectrum whose
+/// bins are `bin_hz` apart, on the same -100..0 dB scale as the ambient glow.
+pub fn band_levels(spectrum_db: &[f32], bin_hz: f32) -> [f32; 3] {
+    let mut sums = [0.0f32; 3];
+    let mut counts = [0usize; 3];
+    for (i, db) in spectrum_db.iter().enumerate() {
+        let hz = (i as f32 + 0.5) * bin_hz;
+        let band = BAND_EDGES_HZ.iter().take_while(|&&edge| hz >= edge).count();
+        let level = if db.is_finite() {
+            ((db + 100.0) / 100.0).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        sums[band] += level;
+        counts[band] += 1;
+    }
+    std::array::from_fn(|b| {
+        if counts[b] == 0 {
+            0.0
+        } else {
+            sums[b] / counts[b] as f32
+        }
+    })
+}
+
+/// Half-wave rectified spectral flux: total rise in magnitude per bin from
+/// `previous` to `current`, averaged over the bins.
+pub fn spectral_flux(previous: &[f32], current: &[f32]) -> f32 {
+    let bins = current.len().min(previous.len());
+    if bins == 0 {
+        return 0.0;
+    }
+    let rise: f32 = previous
+        .iter()
+        .zip(current)
+        .map(|(p, c)| (c - p).max(0.0))
+        .sum();
+    rise / bins as f32
+}
@@ -0,0 +1,84 @@
+/// Gain-staging for a playback environment, applied to the master chain
+/// (master gain → saturation → sub shelf → compressor → limiter → output).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutputPreset {
+    /// Short identifier used in URLs and the parameter API
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Linear master bus gain
+    pub master_gain: f32,
+    pub comp_threshold_db: f32,
+    pub comp_ratio: f32,
+    pub comp_knee_db: f32,
+    pub comp_attack_sec: f32,
+    pub comp_release_sec: f32,
+    /// Linear gain of the parallel low-end (sub) path
+    pub sub_level: f32,
+    /// Peak ceiling of the brickwall-style limiter (dBFS)
+    pub limiter_ceiling_db: f32,
+}
+
+pub const HEADPHONES: OutputPreset = OutputPreset {
+    id: "headphones",
+    name: "Headphones",
+    master_gain: 0.25,
+    comp_threshold_db: -18.0,
+    comp_ratio: 2.0,
+    comp_knee_db: 12.0,
+    comp_attack_sec: 0.02,
+    comp_release_sec: 0.3,
+    sub_level: 0.1,
+    limiter_ceiling_db: -3.0,
+};
+
+/// Small drivers can't reproduce lows: no sub, more compression for loudness.
+pub const LAPTOP_SPEAKERS: OutputPreset = OutputPreset {
+    id: "laptop",
+    name: "Laptop speakers",
+    master_gain: 0.3,
+    comp_threshold_db: -24.0,
+    comp_ratio: 4.0,
+    comp_knee_db: 6.0,
+    comp_attack_sec: 0.01,
+    comp_release_sec: 0.2,
+    sub_level: 0.0,
+    limiter_ceiling_db: -1.0,
+};
+
+/// Full-range system: generous sub, gentle compression, headroom for the PA.
+pub const CLUB_PA: OutputPreset = OutputPreset {
+    id: "club",
+    name: "Club PA",
+    master_gain: 0.3,
+    comp_threshold_db: -14.0,
+    comp_ratio: 3.0,
+    comp_knee_db: 8.0,
+    comp_attack_sec: 0.005,
+    comp_release_sec: 0.15,
+    sub_level: 0.45,
+    limiter_ceiling_db: -0.5,
+};
+
+/// Installation at low level: quiet, wide dynamic range, hard ceiling.
+pub const GALLERY_QUIET: OutputPreset = OutputPreset {
+    id: "gallery",
+    name: "Gallery (quiet)",
+    master_gain: 0.12,
+    comp_threshold_db: -30.0,
+    comp_ratio: 1.5,
+    comp_knee_db: 20.0,
+    comp_attack_sec: 0.05,
+    comp_release_sec: 0.6,
+    sub_level: 0.05,
+    limiter_ceiling_db: -12.0,
+};
+
+/// All built-in output presets; the first is the default.
+pub const OUTPUT_PRESETS: &[OutputPreset] = &[HEADPHONES, LAPTOP_SPEAKERS, CLUB_PA, GALLERY_QUIET];
+
+/// Look up an output preset by id (case-insensitive).
+pub fn output_preset_by_id(id: &str) -> Option<&'static OutputPreset> {
+    OUTPUT_PRESETS
+        .iter()
+        .find(|p| p.id.eq_ignore_ascii_case(id))
+}
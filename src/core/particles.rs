@@ -0,0 +1,132 @@
+use super::smf::hz_to_midi;
+use rand::prelude::*;
+
+/// Particles in the GPU pool (`MAX_PARTICLES` in particles.wgsl); bursts
+/// recycle the oldest slots once it is full.
+pub const MAX_PARTICLES: usize = 4096;
+
+/// Particles in a burst at zero and full velocity.
+pub const BURST_MIN_PARTICLES: usize = 12;
+pub const BURST_MAX_PARTICLES: usize = 64;
+
+/// Launch speed (world units per second) at zero and full velocity.
+pub const BURST_MIN_SPEED: f32 = 0.6;
+pub const BURST_MAX_SPEED: f32 = 2.2;
+
+/// Lifetime range of a particle; quieter notes fade sooner.
+pub const PARTICLE_MIN_LIFE_SEC: f32 = 0.8;
+pub const PARTICLE_MAX_LIFE_SEC: f32 = 2.4;
+
+/// Velocity decay per second and downward pull (world units per second²)
+/// applied by the compute step.
+pub const PARTICLE_DRAG_PER_SEC: f32 = 1.6;
+pub const PARTICLE_GRAVITY: f32 = 0.7;
+
+/// Pitch range mapped onto the burst colour wheel and upward lift.
+const LOW_MIDI: f32 = 28.0;
+const HIGH_MIDI: f32 = 96.0;
+
+/// One particle's starting state, laid out as the GPU pool stores it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Particle {
+    /// World position and age in seconds
+    pub pos_age: [f32; 4],
+    /// Velocity and lifetime in seconds
+    pub vel_life: [f32; 4],
+    /// Linear RGB (may exceed 1 to bloom); w is the size in world units
+    pub color_size: [f32; 4],
+}
+
+/// Where 0 (low) .. 1 (high) a pitch sits in the burst range.
+pub fn pitch_position(frequency_hz: f32) -> f32 {
+    ((hz_to_midi(frequency_hz) - LOW_MIDI) / (HIGH_MIDI - LOW_MIDI)).clamp(0.0, 1.0)
+}
+
+/// Burst colour: the voice's theme colour turned round the hue wheel by pitch
+/// class, so each note of a chord sparks a little differently, and
+/// brightened with velocity.
+pub fn burst_color(voice_color: [f32; 3], frequency_hz: f32, velocity: f32) -> [f32; 3] {
+    let pitch_class = hz_to_midi(frequency_hz).rem_euclid(12.0) / 12.0;
+    let angle = pitch_class * std::f32::consts::TAU;
+    // Rotate about the grey axis (Rodrigues), which keeps the luminance close
+    let (s, c) = angle.sin_cos();
+    let k = (1.0 - c) / 3.0;
+    let r = s / 3f32.sqrt();
+    let [x, y, z] = voice_color;
+    let rotated = [
+        x * (c + k) + y * (k - r) + z * (k + r),
+        x * (k + r) + y * (c + k) + z * (k - r),
+        x * (k - r) + y * (k + r) + z * (c + k),
+    ];
+    let gain = 0.6 + 0.9 * velocity.clamp(0.0, 1.0);
+    std::array::from_fn(|i| (0.5 * voice_color[i] + 0.5 * rotated[i]).max(0.0) * gain)
+}
+
+/// Hands out pool slots and fills them with note bursts.
+#[derive(Clone, Debug)]
+pub struct ParticleEmitter {
+    next_slot: usize,
+    rng: StdRng,
+}
+
+impl ParticleEmitter {
+    pub fn new(seed: u64) -> ParticleEmitter {
+        ParticleEmitter {
+            next_slot: 0,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Fill `out` with a burst for a note at `origin` and return the pool slot
+    /// its first particle goes in. Bursts never straddle the end of the pool,
+    /// so each is one contiguous upload.
+    ///
+    /// Velocity sets the count, speed and lifetime; pitch lifts the spray
+    /// upward and shrinks the sparks, so high notes fountain and low notes
+    /// spread in a heavier ring.
+    pub fn burst(
+        &mut self,
+        origin: [f32; 3],
+        frequency_hz: f32,
+        velocity: f32,
+        voice_color: [f32; 3],
+        out: &mut Vec<Particle>,
+    ) -> usize {
+        let velocity = velocity.clamp(0.0, 1.0);
+        let pitch = pitch_position(frequency_hz);
+        let count = BURST_MIN_PARTICLES
+            + ((BURST_MAX_PARTICLES - BURST_MIN_PARTICLES) as f32 * velocity).round() as usize;
+        let speed = BURST_MIN_SPEED + (BURST_MAX_SPEED - BURST_MIN_SPEED) * velocity;
+        let life =
+            PARTICLE_MIN_LIFE_SEC + (PARTICLE_MAX_LIFE_SEC - PARTICLE_MIN_LIFE_SEC) * velocity;
+        let color = burst_color(voice_color, frequency_hz, velocity);
+        let size = 0.05 + 0.05 * (1.0 - pitch);
+
+        out.clear();
+        for _ in 0..count {
+            // Uniform direction on the sphere, then lifted by pitch
+            let z: f32 = self.rng.gen_range(-1.0..=1.0);
+            let theta: f32 = self.rng.gen_range(0.0..std::f32::consts::TAU);
+            let ring = (1.0 - z * z).sqrt();
+            let dir = [ring * theta.cos(), z + 0.8 * pitch, ring * theta.sin()];
+            let jitter: f32 = self.rng.gen_range(0.6..=1.0);
+            out.push(Particle {
+                pos_age: [origin[0], origin[1], origin[2], 0.0],
+                vel_life: [
+                    dir[0] * speed * jitter,
+                    dir[1] * speed * jitter,
+                    dir[2] * speed * jitter,
+                    life * self.rng.gen_range(0.7..=1.0),
+                ],
+                color_size: [color[0], color[1], color[2], size * jitter],
+            });
+        }
+
+        if self.next_slot + count > MAX_PARTICLES {
+            self.next_slot = 0;
+        }
+        let first = self.next_slot;
+        self.next_slot += count;
+        first
+    }
+}
@@ -0,0 +1,99 @@
+use glam::Vec3;
+
+/// Spring/repulsion model that lets dropped voices settle back into place.
+///
+/// Each voice is pulled toward its target (its base position, plus any
+/// auto-motion displacement) by a damped spring and pushed away from voices
+/// closer than `repulsion_radius`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicsParams {
+    /// Spring constant toward the target (1/s²); higher snaps back faster
+    pub stiffness: f32,
+    /// Velocity damping (1/s); around 2·√stiffness settles without overshoot
+    pub damping: f32,
+    /// Strength of the push between voices that get too close
+    pub repulsion: f32,
+    /// Distance (engine units) below which voices repel each other
+    pub repulsion_radius: f32,
+}
+
+impl PhysicsParams {
+    /// Slightly under-damped: a dropped voice swings back with a small overshoot.
+    pub const DEFAULT: PhysicsParams = PhysicsParams {
+        stiffness: 6.0,
+        damping: 3.5,
+        repulsion: 4.0,
+        repulsion_radius: 0.6,
+    };
+
+    /// Parse `"stiffness,damping"` (e.g. from a URL), keeping the default
+    /// repulsion. Both values must be finite and non-negative.
+    pub fn parse(s: &str) -> Option<PhysicsParams> {
+        let (k, c) = s.split_once(',')?;
+        let stiffness: f32 = k.trim().parse().ok()?;
+        let damping: f32 = c.trim().parse().ok()?;
+        let valid = |x: f32| x.is_finite() && x >= 0.0;
+        (valid(stiffness) && valid(damping)).then_some(PhysicsParams {
+            stiffness,
+            damping,
+            ..Self::DEFAULT
+        })
+    }
+}
+
+/// Longest integration step; larger frame deltas are split into substeps.
+const MAX_SUBSTEP_SEC: f32 = 1.0 / 120.0;
+/// Frame deltas beyond this (e.g. a backgrounded tab) are clamped.
+const MAX_STEP_SEC: f32 = 0.1;
+
+/// Advance positions and velocities by `dt_sec` (semi-implicit Euler).
+///
+/// Held voices keep their position and lose their velocity but still repel
+/// the others, so dragging one voice through a cluster nudges it apart.
+pub fn physics_step(
+    positions: &mut [Vec3],
+    velocities: &mut [Vec3],
+    targets: &[Vec3],
+    held: &[bool],
+    params: &PhysicsParams,
+    dt_sec: f32,
+) {
+    let dt_sec = dt_sec.clamp(0.0, MAX_STEP_SEC);
+    let substeps = (dt_sec / MAX_SUBSTEP_SEC).ceil().max(1.0) as usize;
+    let h = dt_sec / substeps as f32;
+    let n = positions.len();
+    for _ in 0..substeps {
+        let mut forces = vec![Vec3::ZERO; n];
+        for i in 0..n {
+            forces[i] =
+                -params.stiffness * (positions[i] - targets[i]) - params.damping * velocities[i];
+            for j in 0..n {
+                if i == j {
+                    continue;
+                }
+                let d = positions[i] - positions[j];
+                let dist = d.length();
+                if dist < params.repulsion_radius {
+                    // Coincident voices get split along x, in index order
+                    let dir = if dist > 1e-5 {
+                        d / dist
+                    } else if i < j {
+                        Vec3::NEG_X
+                    } else {
+                        Vec3::X
+                    };
+                    let overlap = 1.0 - dist / params.repulsion_radius;
+                    forces[i] += dir * params.repulsion * overlap;
+                }
+            }
+        }
+        for i in 0..n {
+            if held[i] {
+                velocities[i] = Vec3::ZERO;
+                continue;
+            }
+            velocities[i] += forces[i] * h;
+            positions[i] += velocities[i] * h;
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use super::frame_math::one_pole;
+
+/// Which sounding note gives way when a voice is at its polyphony cap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StealPolicy {
+    /// The note that started first
+    Oldest,
+    /// The note with the lowest velocity (oldest among equals)
+    Quietest,
+}
+
+/// Per-voice cap on simultaneously sounding notes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Polyphony {
+    pub max_notes: usize,
+    pub steal: StealPolicy,
+}
+
+impl Polyphony {
+    /// Generous enough never to steal at normal densities.
+    pub const DEFAULT: Polyphony = Polyphony {
+        max_notes: 8,
+        steal: StealPolicy::Oldest,
+    };
+}
+
+/// Sounding notes the mix has headroom for before auto-gain turns voices down.
+pub const HEADROOM_NOTES: f32 = 4.0;
+
+/// Time constant of the auto-gain: fast enough to catch a build-up within a
+/// beat, slow enough not to pump on every note.
+pub const AUTO_GAIN_TIME_CONSTANT_SEC: f32 = 0.3;
+
+/// Gain that keeps `sounding` notes within the headroom of `HEADROOM_NOTES`.
+///
+/// Notes are roughly uncorrelated, so their power adds: the level grows with
+/// the square root of the count and is scaled back by the same.
+pub fn auto_gain(sounding: usize) -> f32 {
+    (HEADROOM_NOTES / sounding.max(1) as f32).sqrt().min(1.0)
+}
+
+/// Per-voice gain compensation for active polyphony, smoothed so adding
+/// voices or density never needs the master volume ridden.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoGain {
+    pub gain: f32,
+}
+
+impl Default for AutoGain {
+    fn default() -> Self {
+        AutoGain { gain: 1.0 }
+    }
+}
+
+impl AutoGain {
+    /// Move toward `auto_gain(sounding)` over `dt_sec` and return the new gain.
+    pub fn update(&mut self, sounding: usize, dt_sec: f32) -> f32 {
+        self.gain = one_pole(
+            self.gain,
+            auto_gain(sounding),
+            dt_sec,
+            AUTO_GAIN_TIME_CONSTANT_SEC,
+        );
+        self.gain
+    }
+}
+
+/// A sounding note; notes are kept in start order, so index 0 is the oldest.
+#[derive(Clone, Debug)]
+struct ActiveNote<H> {
+    handle: H,
+    end_sec: f64,
+    velocity: f32,
+}
+
+/// Tracks sounding notes per voice and picks which to cut at the cap.
+///
+/// Backend-agnostic: `H` is whatever the frontend needs to silence a note
+/// early (audio nodes on the web, a slot index in a native mixer).
+#[derive(Clone, Debug)]
+pub struct VoiceAllocator<H> {
+    limits: Vec<Polyphony>,
+    active: Vec<Vec<ActiveNote<H>>>,
+}
+
+impl<H> VoiceAllocator<H> {
+    pub fn new(limits: Vec<Polyphony>) -> Self {
+        let active = limits.iter().map(|_| Vec::new()).collect();
+        Self { limits, active }
+    }
+
+    /// Change a voice's cap; notes already sounding are kept until the next allocation.
+    pub fn set_limit(&mut self, voice: usize, limit: Polyphony) {
+        if let Some(l) = self.limits.get_mut(voice) {
+            *l = limit;
+        }
+    }
+
+    /// Notes across all voices still sounding at `at_sec`, including ones
+    /// already scheduled to start (so compensation leads the build-up).
+    pub fn sounding(&self, at_sec: f64) -> usize {
+        self.active
+            .iter()
+            .flatten()
+            .filter(|n| n.end_sec > at_sec)
+            .count()
+    }
+
+    /// Notes on `voice` still sounding (or scheduled) at `at_sec`.
+    pub fn sounding_on(&self, voice: usize, at_sec: f64) -> usize {
+        self.active.get(voice).map_or(0, |notes| {
+            notes.iter().filter(|n| n.end_sec > at_sec).count()
+        })
+    }
+
+    /// Register a note on `voice` sounding from `start_sec` to `end_sec`.
+    ///
+    /// Notes finished by `start_sec` are forgotten first. Returns the handles
+    /// of notes stolen to stay within the cap; the caller must silence them
+    /// at `start_sec`. Notes must be allocated in start-time order.
+    pub fn allocate(
+        &mut self,
+        voice: usize,
+        start_sec: f64,
+        end_sec: f64,
+        velocity: f32,
+        handle: H,
+    ) -> Vec<H> {
+        let (Some(limit), Some(active)) = (self.limits.get(voice), self.active.get_mut(voice))
+        else {
+            return Vec::new();
+        };
+        active.retain(|n| n.end_sec > start_sec);
+        let mut stolen = Vec::new();
+        while !active.is_empty() && active.len() >= limit.max_notes.max(1) {
+            let victim = match limit.steal {
+                StealPolicy::Oldest => 0,
+                StealPolicy::Quietest => active
+                    .iter()
+                    .enumerate()
+                    .min_by(|a, b| a.1.velocity.total_cmp(&b.1.velocity))
+                    .map_or(0, |(i, _)| i),
+            };
+            stolen.push(active.remove(victim).handle);
+        }
+        active.push(ActiveNote {
+            handle,
+            end_sec,
+            velocity,
+        });
+        stolen
+    }
+}
@@ -0,0 +1,389 @@
+use super::envelope::{Adsr, EnvelopeCurve};
+use super::filter::{FilterMode, VoiceFilter};
+use super::fm::FmParams;
+use super::frame_math::VoiceSends;
+use super::groove::{STRAIGHT, SWING_55};
+use super::midi_learn::CcMap;
+use super::music::{
+    EngineParams, Hysteresis, MusicEngine, VoiceConfig, Waveform, AEOLIAN, C_MAJOR_PENTATONIC,
+    DORIAN, LYDIAN, STRONG_BEATS,
+};
+use super::polyphony::{Polyphony, StealPolicy};
+use super::spatial::{DistanceModel, SpatialHints};
+use super::unison::Unison;
+use super::voice_style::{VoiceShape, VoiceStyle};
+use super::wavetable::{WAVETABLE_GLASS, WAVETABLE_HOLLOW, WAVETABLE_ORGAN};
+use glam::Vec3;
+
+/// Engine parameters together with every voice's configuration.
+///
+/// Presets can be applied outright or morphed into over a number of bars
+/// (see `MusicEngine::morph_to_preset`), passing through `Preset::lerp`.
+#[derive(Clone, Debug)]
+pub struct Preset {
+    pub name: &'static str,
+    pub params: EngineParams,
+    pub voices: Vec<VoiceConfig>,
+    /// Controller mappings kept with the preset; an empty map leaves the
+    /// player's own mappings alone when applied
+    pub controls: CcMap,
+}
+
+impl Preset {
+    /// Snapshot of the engine's current parameters and voice configs.
+    pub fn from_engine(engine: &MusicEngine, name: &'static str) -> Self {
+        Self {
+            name,
+            params: engine.params.clone(),
+            voices: engine.configs.clone(),
+            controls: engine.controls.clone(),
+        }
+    }
+
+    /// Blend of `a` (t = 0) and `b` (t = 1).
+    ///
+    /// Continuous values (tempo, detune, probabilities, durations, positions,
+    /// spatial distances, envelope times and levels, filter settings, unison
+    /// detune and spread, FM ratio and index, glow, ripple frequency and trail
+    /// length) are interpolated; root and octaves move in whole
+    /// semitones/octaves; anything discrete (scale, groove, waveform, loop
+    /// length, beat weights, polyphony, envelope curve, filter mode or whether
+    /// there is a filter or FM at all, unison count, sampler, voice shape
+    /// and controller mappings) switches over at the halfway point.
+    /// Voices beyond the shorter preset's count are taken from `a`.
+    pub fn lerp(a: &Preset, b: &Preset, t: f32) -> Preset {
+        let t = t.clamp(0.0, 1.0);
+        let late = t >= 0.5;
+        let params = EngineParams {
+            bpm: lerp(a.params.bpm, b.params.bpm, t),
+            scale: pick(late, a.params.scale, b.params.scale),
+            root_midi: lerp_int(a.params.root_midi, b.params.root_midi, t),
+            detune_cents: lerp(a.params.detune_cents, b.params.detune_cents, t),
+            groove: pick(late, a.params.groove, b.params.groove),
+        };
+        let mut voices = a.voices.clone();
+        for (v, (va, vb)) in voices.iter_mut().zip(a.voices.iter().zip(&b.voices)) {
+            *v = lerp_voice(va, vb, t);
+        }
+        Preset {
+            name: pick(late, a.name, b.name),
+            params,
+            voices,
+            controls: pick(late, &a.controls, &b.controls).clone(),
+        }
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// `a` before the halfway point of a blend, `b` from it on.
+fn pick<T>(late: bool, a: T, b: T) -> T {
+    if late {
+        b
+    } else {
+        a
+    }
+}
+
+fn lerp_int(a: i32, b: i32, t: f32) -> i32 {
+    lerp(a as f32, b as f32, t).round() as i32
+}
+
+fn lerp_voice(a: &VoiceConfig, b: &VoiceConfig, t: f32) -> VoiceConfig {
+    let late = t >= 0.5;
+    let (sa, sb) = (&a.spatial, &b.spatial);
+    VoiceConfig {
+        base_position: a.base_position.lerp(b.base_position, t),
+        trigger_probability: lerp(a.trigger_probability, b.trigger_probability, t),
+        octave_offset: lerp_int(a.octave_offset, b.octave_offset, t),
+        base_duration: lerp(a.base_duration, b.base_duration, t),
+        hysteresis: Hysteresis {
+            steps: pick(late, a.hysteresis.steps, b.hysteresis.steps),
+            factor: lerp(a.hysteresis.factor, b.hysteresis.factor, t),
+        },
+        spatial: SpatialHints {
+            orbit_radius: lerp(sa.orbit_radius, sb.orbit_radius, t),
+            orbit_speed: lerp(sa.orbit_speed, sb.orbit_speed, t),
+            ref_distance: lerp(sa.ref_distance, sb.ref_distance, t),
+            max_distance: lerp(sa.max_distance, sb.max_distance, t),
+            rolloff: lerp(sa.rolloff, sb.rolloff, t),
+            ..pick(late, *sa, *sb)
+        },
+        envelope: lerp_adsr(&a.envelope, &b.envelope, t),
+        filter: match (a.filter, b.filter) {
+            (Some(fa), Some(fb)) => Some(VoiceFilter {
+                mode: pick(late, fa.mode, fb.mode),
+                // Cutoff moves evenly in octaves
+                cutoff_hz: fa.cutoff_hz * (fb.cutoff_hz / fa.cutoff_hz).powf(t),
+                resonance: lerp(fa.resonance, fb.resonance, t),
+                env_octaves: lerp(fa.env_octaves, fb.env_octaves, t),
+                key_tracking: lerp(fa.key_tracking, fb.key_tracking, t),
+            }),
+            (fa, fb) => pick(late, fa, fb),
+        },
+        unison: Unison {
+            voices: pick(late, a.unison.voices, b.unison.voices),
+            detune_cents: lerp(a.unison.detune_cents, b.unison.detune_cents, t),
+            stereo_spread: lerp(a.unison.stereo_spread, b.unison.stereo_spread, t),
+        },
+        fm: match (a.fm, b.fm) {
+            (Some(fa), Some(fb)) => Some(FmParams {
+                ratio: lerp(fa.ratio, fb.ratio, t),
+                index: lerp(fa.index, fb.index, t),
+                index_envelope: lerp_adsr(&fa.index_envelope, &fb.index_envelope, t),
+            }),
+            (fa, fb) => pick(late, fa, fb),
+        },
+        style: a.style.lerp(&b.style, t),
+        ..pick(late, a, b).clone()
+    }
+}
+
+fn lerp_adsr(a: &Adsr, b: &Adsr, t: f32) -> Adsr {
+    Adsr {
+        attack_sec: lerp(a.attack_sec, b.attack_sec, t),
+        decay_sec: lerp(a.decay_sec, b.decay_sec, t),
+        sustain: lerp(a.sustain, b.sustain, t),
+        release_sec: lerp(a.release_sec, b.release_sec, t),
+        curve: pick(t >= 0.5, a.curve, b.curve),
+    }
+}
+
+/// The startup voice set: sine bass, saw lead and triangle pad.
+fn geno_voices() -> Vec<VoiceConfig> {
+    vec![
+        VoiceConfig {
+            waveform: Waveform::Sine,
+            base_position: Vec3::new(-1.0, 0.0, 0.0),
+            trigger_probability: 0.4,
+            octave_offset: -1,
+            base_duration: 0.4,
+            loop_steps: 0,
+            beat_weights: STRONG_BEATS,
+            // Bass leaves room after each note
+            hysteresis: Hysteresis {
+                steps: 2,
+                factor: 0.35,
+            },
+            // Bass stays present wherever it is dragged
+            spatial: SpatialHints {
+                distance_model: DistanceModel::Linear,
+                rolloff: 0.5,
+                ..SpatialHints::DEFAULT
+            },
+            // Long bass notes would otherwise pile up into mud
+            polyphony: Polyphony {
+                max_notes: 2,
+                steal: StealPolicy::Oldest,
+            },
+            // Soft-edged, mostly sustained thump
+            envelope: Adsr {
+                attack_sec: 0.015,
+                decay_sec: 0.2,
+                sustain: 0.7,
+                release_sec: 0.15,
+                curve: EnvelopeCurve::Exponential,
+            },
+            // Rounds off the sine's attack click and keeps the low end warm
+            filter: Some(VoiceFilter {
+                mode: FilterMode::Lowpass,
+                cutoff_hz: 900.0,
+                resonance: 0.8,
+                env_octaves: 1.0,
+                key_tracking: 1.0,
+            }),
+            unison: Unison::OFF,
+            fm: None,
+            sampler: None,
+            sends: VoiceSends::DEFAULT,
+            // Heavy and slow: a wide orb, broad lazy ripples, a long wake
+            style: VoiceStyle {
+                glow: 1.6,
+                wave_frequency: 0.6,
+                shape: VoiceShape::Orb,
+                trail_sec: 3.0,
+            },
+        },
+        VoiceConfig {
+            waveform: Waveform::Saw,
+            base_position: Vec3::new(1.0, 0.0, 0.0),
+            trigger_probability: 0.6,
+            octave_offset: 0,
+            base_duration: 0.25,
+            loop_steps: 0,
+            beat_weights: &[],
+            // Lead notes tend to come in short bursts
+            hysteresis: Hysteresis {
+                steps: 1,
+                factor: 1.5,
+            },
+            spatial: SpatialHints::DEFAULT,
+            // Bursts drop their softest notes first
+            polyphony: Polyphony {
+                max_notes: 4,
+                steal: StealPolicy::Quietest,
+            },
+            envelope: Adsr::DEFAULT,
+            // Tames the raw saw; a resonant sweep on each note's attack
+            filter: Some(VoiceFilter {
+                mode: FilterMode::Lowpass,
+                cutoff_hz: 1200.0,
+                resonance: 4.0,
+                env_octaves: 2.0,
+                key_tracking: 0.5,
+            }),
+            // A slightly doubled lead, just off center
+            unison: Unison {
+                voices: 2,
+                detune_cents: 8.0,
+                stereo_spread: 0.3,
+            },
+            fm: None,
+            sampler: None,
+            sends: VoiceSends::DEFAULT,
+            // Sparkles: tight glow, quick fine ripples, a short trail
+            style: VoiceStyle {
+                glow: 0.7,
+                wave_frequency: 1.6,
+                shape: VoiceShape::Sparkle,
+                trail_sec: 1.0,
+            },
+        },
+        VoiceConfig {
+            waveform: Waveform::Triangle,
+            base_position: Vec3::new(0.0, 0.0, -1.0),
+            trigger_probability: 0.3,
+            octave_offset: 1,
+            base_duration: 0.6,
+            loop_steps: 0,
+            beat_weights: &[],
+            hysteresis: Hysteresis::NONE,
+            // High pad drifts slowly around its spot and recedes quickly
+            spatial: SpatialHints {
+                orbit_radius: 0.4,
+                orbit_speed: 0.15,
+                distance_model: DistanceModel::Exponential,
+                ..SpatialHints::DEFAULT
+            },
+            polyphony: Polyphony::DEFAULT,
+            // Pad swells in and lingers after the gate closes
+            envelope: Adsr {
+                attack_sec: 0.25,
+                decay_sec: 0.3,
+                sustain: 0.8,
+                release_sec: 0.6,
+                curve: EnvelopeCurve::Linear,
+            },
+            filter: None,
+            // Wide, shimmering pad
+            unison: Unison {
+                voices: 4,
+                detune_cents: 18.0,
+                stereo_spread: 0.8,
+            },
+            fm: None,
+            sampler: None,
+            sends: VoiceSends::DEFAULT,
+            // A soft halo that lingers like the pad itself
+            style: VoiceStyle {
+                glow: 1.3,
+                wave_frequency: 0.9,
+                shape: VoiceShape::Ring,
+                trail_sec: 2.5,
+            },
+        },
+    ]
+}
+
+/// Built-in presets; the first is the startup state.
+pub fn preset_bank() -> Vec<Preset> {
+    let geno = Preset {
+        name: "Geno",
+        params: EngineParams {
+            bpm: 110.0,
+            scale: C_MAJOR_PENTATONIC,
+            root_midi: 60,
+            detune_cents: 0.0,
+            groove: &STRAIGHT,
+        },
+        voices: geno_voices(),
+        controls: CcMap::default(),
+    };
+
+    // Slow and sparse: long notes, voices spread wide
+    let mut nocturne = Preset {
+        name: "Nocturne",
+        params: EngineParams {
+            bpm: 72.0,
+            scale: AEOLIAN,
+            root_midi: 57,
+            ..geno.params.clone()
+        },
+        voices: geno_voices(),
+        controls: CcMap::default(),
+    };
+    for v in nocturne.voices.iter_mut() {
+        v.trigger_probability *= 0.6;
+        v.base_duration *= 2.0;
+        v.base_position *= 1.4;
+    }
+    nocturne.voices[2].waveform = Waveform::Wavetable(WAVETABLE_ORGAN);
+    // Distant bells in place of the saw lead
+    nocturne.voices[1].waveform = Waveform::Sine;
+    nocturne.voices[1].filter = None;
+    nocturne.voices[1].fm = Some(FmParams::BELL);
+
+    // Busy swung lead over a tight bass
+    let mut pulse = Preset {
+        name: "Pulse",
+        params: EngineParams {
+            bpm: 132.0,
+            scale: DORIAN,
+            root_midi: 62,
+            detune_cents: 0.0,
+            groove: &SWING_55,
+        },
+        voices: geno_voices(),
+        controls: CcMap::default(),
+    };
+    pulse.voices[0].base_duration = 0.2;
+    pulse.voices[1].trigger_probability = 0.8;
+    pulse.voices[1].base_duration = 0.15;
+    pulse.voices[2].trigger_probability = 0.2;
+    // Electric-piano stabs instead of the pad
+    pulse.voices[2].waveform = Waveform::Sine;
+    pulse.voices[2].unison = Unison::OFF;
+    pulse.voices[2].fm = Some(FmParams::ELECTRIC_PIANO);
+
+    // Bright and slightly sharp, everything an octave up
+    let mut glass = Preset {
+        name: "Glass",
+        params: EngineParams {
+            bpm: 96.0,
+            scale: LYDIAN,
+            root_midi: 64,
+            detune_cents: 25.0,
+            ..geno.params.clone()
+        },
+        voices: geno_voices(),
+        controls: CcMap::default(),
+    };
+    for v in glass.voices.iter_mut() {
+        v.octave_offset += 1;
+        v.waveform = Waveform::Triangle;
+    }
+    glass.voices[2].waveform = Waveform::Wavetable(WAVETABLE_GLASS);
+    // Lead rings through a narrow band instead of a lowpass
+    glass.voices[1].waveform = Waveform::Wavetable(WAVETABLE_HOLLOW);
+    glass.voices[1].filter = Some(VoiceFilter {
+        mode: FilterMode::Bandpass,
+        cutoff_hz: 2400.0,
+        resonance: 6.0,
+        env_octaves: -1.0,
+        key_tracking: 1.0,
+    });
+
+    vec![geno, nocturne, pulse, glass]
+}
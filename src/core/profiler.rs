@@ -0,0 +1,141 @@
+use super::music::VoiceConfig;
+use std::collections::VecDeque;
+
+/// How often the performance HUD's figures are refreshed.
+pub const HUD_REFRESH_SEC: f32 = 0.25;
+
+/// Frames the averages (and the typical frame interval) are taken over.
+pub const PROFILED_FRAMES: usize = 120;
+
+/// A frame counts as dropping refreshes when it takes this many times the
+/// typical frame interval.
+pub const DROPPED_FRAME_FACTOR: f32 = 1.5;
+
+/// Gaps longer than this are a hidden tab or a debugger pause, not slow
+/// frames, and are left out.
+const MAX_PROFILED_FRAME_SEC: f32 = 1.0;
+
+/// GPU time of each stage of the render, from timestamp queries.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GpuTimings {
+    /// Visualizer layers into the HDR target (and the outgoing one while
+    /// crossfading)
+    pub scene_ms: f32,
+    /// Bright pass and blur iterations
+    pub bloom_ms: f32,
+    /// Tonemap and composite to the screen
+    pub composite_ms: f32,
+}
+
+impl GpuTimings {
+    pub fn total_ms(&self) -> f32 {
+        self.scene_ms + self.bloom_ms + self.composite_ms
+    }
+}
+
+/// Figures shown on the performance HUD.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PerfStats {
+    pub fps: f32,
+    /// Average and longest frame interval
+    pub frame_ms: f32,
+    pub worst_frame_ms: f32,
+    /// Average main-thread time spent in the frame callback
+    pub cpu_ms: f32,
+    /// Refreshes missed since profiling started
+    pub dropped: u32,
+    /// Oscillators (and sample sources) of the notes sounding now
+    pub oscillators: usize,
+    /// `None` where the GPU can't time its passes
+    pub gpu: Option<GpuTimings>,
+}
+
+impl PerfStats {
+    /// The HUD text, one figure per line.
+    pub fn hud_text(&self) -> String {
+        let mut lines = vec![
+            format!("{:.0} fps", self.fps),
+            format!(
+                "frame {:.1} ms (max {:.1})",
+                self.frame_ms, self.worst_frame_ms
+            ),
+            format!("cpu {:.1} ms", self.cpu_ms),
+        ];
+        match self.gpu {
+            Some(gpu) => lines.push(format!(
+                "gpu {:.2} ms (scene {:.2}, bloom {:.2}, composite {:.2})",
+                gpu.total_ms(),
+                gpu.scene_ms,
+                gpu.bloom_ms,
+                gpu.composite_ms
+            )),
+            None => lines.push("gpu n/a".to_string()),
+        }
+        lines.push(format!("oscillators {}", self.oscillators));
+        lines.push(format!("dropped {}", self.dropped));
+        lines.join("\n")
+    }
+}
+
+/// Oscillators one note of `config` runs: its unison layers, plus the FM
+/// modulator when there is one.
+pub fn note_oscillators(config: &VoiceConfig) -> usize {
+    config.unison.voices.max(1) as usize + usize::from(config.fm.is_some())
+}
+
+/// Frame intervals and CPU times over the last `PROFILED_FRAMES` frames, and
+/// a count of the refreshes missed.
+#[derive(Clone, Debug, Default)]
+pub struct FrameProfiler {
+    /// (interval, cpu) per frame, in seconds
+    frames: VecDeque<(f32, f32)>,
+    /// Median interval, refreshed with the HUD
+    typical_sec: Option<f32>,
+    dropped: u32,
+    since_refresh_sec: f32,
+}
+
+impl FrameProfiler {
+    /// Record one frame: the time since the previous one and the time spent
+    /// computing it. Returns true when the HUD is due a refresh.
+    pub fn record(&mut self, interval_sec: f32, cpu_sec: f32) -> bool {
+        if !(0.0..=MAX_PROFILED_FRAME_SEC).contains(&interval_sec) {
+            return false;
+        }
+        if let Some(typical) = self.typical_sec {
+            if interval_sec > typical * DROPPED_FRAME_FACTOR {
+                self.dropped += ((interval_sec / typical).round() as u32).saturating_sub(1);
+            }
+        }
+        if self.frames.len() == PROFILED_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((interval_sec, cpu_sec.max(0.0)));
+        self.since_refresh_sec += interval_sec;
+        if self.since_refresh_sec < HUD_REFRESH_SEC {
+            return false;
+        }
+        self.since_refresh_sec = 0.0;
+        let mut intervals: Vec<f32> = self.frames.iter().map(|f| f.0).collect();
+        intervals.sort_by(f32::total_cmp);
+        self.typical_sec = Some(intervals[intervals.len() / 2]).filter(|t| *t > 0.0);
+        true
+    }
+
+    /// Averages over the recorded frames, with this frame's oscillator count
+    /// and the latest GPU timings.
+    pub fn stats(&self, oscillators: usize, gpu: Option<GpuTimings>) -> PerfStats {
+        let n = self.frames.len().max(1) as f32;
+        let total: f32 = self.frames.iter().map(|f| f.0).sum();
+        let frame_ms = total / n * 1000.0;
+        PerfStats {
+            fps: if total > 0.0 { n / total } else { 0.0 },
+            frame_ms,
+            worst_frame_ms: self.frames.iter().map(|f| f.0).fold(0.0, f32::max) * 1000.0,
+            cpu_ms: self.frames.iter().map(|f| f.1).sum::<f32>() / n * 1000.0,
+            dropped: self.dropped,
+            oscillators,
+            gpu,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use super::ambisonics::wav_bytes;
+
+/// Channels captured from the master bus (stereo).
+pub const RECORD_CHANNELS: usize = 2;
+
+/// Index of the audio-clock frame at `time_sec` (rounded to the nearest).
+pub fn frame_at(time_sec: f64, sample_rate: u32) -> u64 {
+    (time_sec.max(0.0) * sample_rate as f64).round() as u64
+}
+
+/// A take of the master bus covering exactly the frames from `start_frame`
+/// up to (not including) the stop frame, whatever block boundaries the
+/// audio arrives in.
+///
+/// Blocks are placed by their first frame, so a block the capture missed
+/// leaves silence rather than shifting everything after it.
+#[derive(Clone, Debug)]
+pub struct Recording {
+    pub sample_rate: u32,
+    start_frame: u64,
+    stop_frame: Option<u64>,
+    /// Interleaved left/right
+    samples: Vec<f32>,
+}
+
+impl Recording {
+    pub fn new(sample_rate: u32, start_frame: u64) -> Recording {
+        Recording {
+            sample_rate,
+            start_frame,
+            stop_frame: None,
+            samples: Vec::new(),
+        }
+    }
+
+    /// End the take before `frame` (never before it started); audio past it
+    /// is dropped.
+    pub fn stop_at(&mut self, frame: u64) {
+        let frame = frame.max(self.start_frame);
+        self.stop_frame = Some(frame);
+        self.samples
+            .truncate((frame - self.start_frame) as usize * RECORD_CHANNELS);
+    }
+
+    /// Add a block of audio whose first sample is audio-clock frame
+    /// `first_frame`, keeping only the part inside the take.
+    pub fn push_block(&mut self, first_frame: u64, left: &[f32], right: &[f32]) {
+        for (i, (l, r)) in left.iter().zip(right).enumerate() {
+            let frame = first_frame + i as u64;
+            if frame < self.start_frame {
+                continue;
+            }
+            if self.stop_frame.is_some_and(|stop| frame >= stop) {
+                break;
+            }
+            let at = (frame - self.start_frame) as usize * RECORD_CHANNELS;
+            if self.samples.len() < at + RECORD_CHANNELS {
+                self.samples.resize(at + RECORD_CHANNELS, 0.0);
+            }
+            self.samples[at..at + RECORD_CHANNELS].copy_from_slice(&[*l, *r]);
+        }
+    }
+
+    /// Length of the take: the whole start..stop window once stopped,
+    /// otherwise what has arrived so far.
+    pub fn frames(&self) -> usize {
+        match self.stop_frame {
+            Some(stop) => (stop - self.start_frame) as usize,
+            None => self.samples.len() / RECORD_CHANNELS,
+        }
+    }
+
+    pub fn duration_sec(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate.max(1) as f32
+    }
+
+    /// Encode the take as a stereo 16-bit WAV, padding any audio that never
+    /// arrived with silence.
+    pub fn to_wav(&self) -> Vec<u8> {
+        let mut samples = self.samples.clone();
+        samples.resize(self.frames() * RECORD_CHANNELS, 0.0);
+        wav_bytes(&samples, RECORD_CHANNELS as u16, self.sample_rate)
+    }
+}
@@ -0,0 +1,56 @@
+use super::saved_session::SavedSession;
+
+/// How often the session is saved while the page is open, so a crash loses
+/// at most this much.
+pub const AUTOSAVE_INTERVAL_SEC: u32 = 30;
+
+/// What a visit starts from, given the session saved by the last one and
+/// whether that visit was still marked running (its dirty flag never
+/// cleared by leaving or hiding the page).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resume {
+    Fresh,
+    /// The last visit ended normally: pick up where it left off
+    Restore(SavedSession),
+    /// The last visit stopped abruptly (the tab crashed or was killed):
+    /// start fresh but offer its last autosave
+    Offer(SavedSession),
+}
+
+impl Resume {
+    pub fn decide(saved: Option<SavedSession>, dirty: bool) -> Self {
+        match saved {
+            None => Resume::Fresh,
+            Some(saved) if dirty => Resume::Offer(saved),
+            Some(saved) => Resume::Restore(saved),
+        }
+    }
+
+    /// A `?seed=` link replays that seed's run, so a session saved with
+    /// another seed is neither restored nor offered over it; one saved with
+    /// the same seed (a reload of the link) still is.
+    pub fn for_url_seed(self, seed: Option<u64>) -> Self {
+        match (&self, seed) {
+            (Resume::Restore(saved) | Resume::Offer(saved), Some(seed)) if saved.seed != seed => {
+                Resume::Fresh
+            }
+            _ => self,
+        }
+    }
+
+    /// The session to start from now.
+    pub fn restored(&self) -> Option<&SavedSession> {
+        match self {
+            Resume::Restore(saved) => Some(saved),
+            _ => None,
+        }
+    }
+
+    /// The session to offer back after an abrupt exit.
+    pub fn offered(&self) -> Option<&SavedSession> {
+        match self {
+            Resume::Offer(saved) => Some(saved),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,163 @@
+/// Range of the HDR/bloom chain's resolution, as a fraction of the canvas.
+pub const MIN_RENDER_SCALE: f32 = 0.5;
+pub const MAX_RENDER_SCALE: f32 = 1.0;
+
+/// Size of one automatic adjustment; the scale only ever sits on these
+/// steps, so targets are rebuilt rarely.
+pub const RENDER_SCALE_STEP: f32 = 0.125;
+
+/// Smoothed frame time above which automatic scaling steps down (just under
+/// 50 fps, so a 60 Hz display that is keeping up never trips it).
+pub const FRAME_BUDGET_SEC: f32 = 0.020;
+
+/// Seconds over budget before stepping down, and within budget before
+/// trying a step back up.
+pub const SCALE_DOWN_AFTER_SEC: f32 = 1.0;
+pub const SCALE_UP_AFTER_SEC: f32 = 4.0;
+
+/// Longest wait before retrying a step up that had to be undone; each undo
+/// doubles the wait, so a scale the GPU can't hold isn't retried every few
+/// seconds.
+pub const MAX_SCALE_UP_AFTER_SEC: f32 = 32.0;
+
+/// Time constant of the frame-time smoothing.
+const FRAME_SMOOTHING_SEC: f32 = 0.5;
+
+/// Frames longer than this (a hidden tab, a debugger pause) say nothing
+/// about rendering cost and are ignored.
+const MAX_MEASURED_FRAME_SEC: f32 = 0.25;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderScaleMode {
+    /// Follow the measured frame time
+    Auto,
+    /// A fixed fraction, `MIN_RENDER_SCALE..=MAX_RENDER_SCALE`
+    Fixed(f32),
+}
+
+impl Default for RenderScaleMode {
+    fn default() -> Self {
+        RenderScaleMode::Fixed(MAX_RENDER_SCALE)
+    }
+}
+
+impl RenderScaleMode {
+    /// Parse `"auto"` or a fraction such as `"0.75"` (`?scale=`); fractions
+    /// are clamped to the range.
+    pub fn parse(s: &str) -> Option<RenderScaleMode> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("auto") {
+            return Some(RenderScaleMode::Auto);
+        }
+        let scale: f32 = s.parse().ok().filter(|v: &f32| v.is_finite())?;
+        Some(RenderScaleMode::Fixed(
+            scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE),
+        ))
+    }
+
+    /// Identifier used by URLs and the overlay select
+    pub fn id(self) -> String {
+        match self {
+            RenderScaleMode::Auto => "auto".to_string(),
+            RenderScaleMode::Fixed(scale) => scale.to_string(),
+        }
+    }
+}
+
+/// Render scale for the HDR/bloom chain, adapted to the frame time in
+/// `Auto` mode.
+#[derive(Clone, Debug)]
+pub struct RenderScale {
+    mode: RenderScaleMode,
+    scale: f32,
+    smoothed_sec: Option<f32>,
+    over_budget_sec: f32,
+    within_budget_sec: f32,
+    /// Time within budget needed before the next step up
+    up_after_sec: f32,
+    since_step_up_sec: Option<f32>,
+}
+
+impl RenderScale {
+    pub fn new(mode: RenderScaleMode) -> RenderScale {
+        let mut rs = RenderScale {
+            mode,
+            scale: MAX_RENDER_SCALE,
+            smoothed_sec: None,
+            over_budget_sec: 0.0,
+            within_budget_sec: 0.0,
+            up_after_sec: SCALE_UP_AFTER_SEC,
+            since_step_up_sec: None,
+        };
+        rs.set_mode(mode);
+        rs
+    }
+
+    pub fn mode(&self) -> RenderScaleMode {
+        self.mode
+    }
+
+    /// Switch mode; `Auto` starts from the current scale.
+    pub fn set_mode(&mut self, mode: RenderScaleMode) {
+        self.mode = mode;
+        if let RenderScaleMode::Fixed(scale) = mode {
+            self.scale = scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+        }
+        self.over_budget_sec = 0.0;
+        self.within_budget_sec = 0.0;
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Feed one frame's duration and return the scale to render at.
+    pub fn update(&mut self, frame_sec: f32) -> f32 {
+        if self.mode != RenderScaleMode::Auto
+            || !(0.0..=MAX_MEASURED_FRAME_SEC).contains(&frame_sec)
+        {
+            return self.scale;
+        }
+        let smoothed = match self.smoothed_sec {
+            Some(s) => s + (frame_sec - s) * (1.0 - (-frame_sec / FRAME_SMOOTHING_SEC).exp()),
+            None => frame_sec,
+        };
+        self.smoothed_sec = Some(smoothed);
+        if let Some(t) = &mut self.since_step_up_sec {
+            *t += frame_sec;
+        }
+
+        if smoothed > FRAME_BUDGET_SEC {
+            self.over_budget_sec += frame_sec;
+            self.within_budget_sec = 0.0;
+        } else {
+            self.within_budget_sec += frame_sec;
+            self.over_budget_sec = 0.0;
+        }
+
+        if self.over_budget_sec >= SCALE_DOWN_AFTER_SEC && self.scale > MIN_RENDER_SCALE {
+            // Undoing a recent step up: wait longer before the next try
+            if self
+                .since_step_up_sec
+                .is_some_and(|t| t < self.up_after_sec)
+            {
+                self.up_after_sec = (self.up_after_sec * 2.0).min(MAX_SCALE_UP_AFTER_SEC);
+            }
+            self.step(-RENDER_SCALE_STEP);
+            self.since_step_up_sec = None;
+        } else if self.within_budget_sec >= self.up_after_sec && self.scale < MAX_RENDER_SCALE {
+            self.step(RENDER_SCALE_STEP);
+            self.since_step_up_sec = Some(0.0);
+        }
+        self.scale
+    }
+
+    fn step(&mut self, delta: f32) {
+        let steps = ((self.scale + delta) / RENDER_SCALE_STEP).round();
+        self.scale = (steps * RENDER_SCALE_STEP).clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+        self.over_budget_sec = 0.0;
+        self.within_budget_sec = 0.0;
+        // The new resolution's frame time starts fresh
+        self.smoothed_sec = None;
+    }
+}
@@ -0,0 +1,252 @@
+use super::frame_math::VoiceSends;
+use super::groove::GROOVES;
+use super::history::Command;
+use super::motion::MotionMode;
+use super::music::Waveform;
+use super::preset::preset_bank;
+use super::share::{scale_by_id, scale_id};
+use glam::Vec3;
+
+/// First line of a `.replay` file.
+pub const REPLAY_HEADER: &str = "geno-1 replay 1";
+
+/// Most inputs an engine logs. Past this the log stops growing, and a replay
+/// of it only holds up to the step it stopped at.
+pub const MAX_REPLAY_INPUTS: usize = 50_000;
+
+/// Something done to the engine that a replay does again before the same
+/// grid step.
+#[derive(Clone, Debug)]
+pub enum ReplayInput {
+    Command(Command),
+    /// A count-in of this many bars was started
+    CountIn(u32),
+}
+
+/// Everything done to an engine since it was built: its seed and each input
+/// with the grid step it came before. An engine built from the same seed
+/// and fed the same inputs at the same steps plays the same notes, which is
+/// what a bug report's `.replay` file reproduces.
+///
+/// Saved as text: the header, `seed=…`, then one `<step> <input>` line per
+/// input (e.g. `16 bpm 120`, `40 pos 1 0.5,0,-1.25`).
+#[derive(Clone, Debug, Default)]
+pub struct Replay {
+    pub seed: u64,
+    pub inputs: Vec<(u64, ReplayInput)>,
+}
+
+impl Replay {
+    /// The replay file, and how many inputs had to be left out because they
+    /// can't be written down (imported clips and samples, undoing a reseed
+    /// or preset change); a replay missing any may drift from the original.
+    pub fn encode(&self) -> (String, usize) {
+        let mut lines = vec![REPLAY_HEADER.to_string(), format!("seed={}", self.seed)];
+        let mut skipped = 0;
+        for (step, input) in &self.inputs {
+            match encode_input(input) {
+                Some(text) => lines.push(format!("{step} {text}")),
+                None => skipped += 1,
+            }
+        }
+        (lines.join("\n"), skipped)
+    }
+
+    /// Read back `encode`'s form; `None` without the header and seed. Lines
+    /// that don't parse are skipped.
+    pub fn decode(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()?.trim() != REPLAY_HEADER {
+            return None;
+        }
+        let seed = lines.next()?.strip_prefix("seed=")?.trim().parse().ok()?;
+        let inputs = lines
+            .filter_map(|line| {
+                let (step, input) = line.trim().split_once(' ')?;
+                Some((step.parse().ok()?, decode_input(input)?))
+            })
+            .collect();
+        Some(Self { seed, inputs })
+    }
+}
+
+fn encode_input(input: &ReplayInput) -> Option<String> {
+    match input {
+        ReplayInput::Command(cmd) => encode_command(cmd),
+        ReplayInput::CountIn(bars) => Some(format!("countin {bars}")),
+    }
+}
+
+fn decode_input(text: &str) -> Option<ReplayInput> {
+    match text.strip_prefix("countin ") {
+        Some(bars) => Some(ReplayInput::CountIn(bars.parse().ok()?)),
+        None => decode_command(text).map(ReplayInput::Command),
+    }
+}
+
+/// A command as a replay line (`name args`), for the commands that can be
+/// written down; `None` for the rest.
+pub fn encode_command(cmd: &Command) -> Option<String> {
+    let vec3 = |v: &Vec3| format!("{},{},{}", v.x, v.y, v.z);
+    Some(match cmd {
+        Command::ToggleMute { voice } => format!("mute {voice}"),
+        Command::ToggleSolo { voice } => format!("solo {voice}"),
+        Command::ToggleLock { voice } => format!("lock {voice}"),
+        Command::Reseed { voice, seed: None } => format!("reseed {voice}"),
+        Command::Reseed {
+            voice,
+            seed: Some(seed),
+        } => format!("reseed {voice} {seed}"),
+        Command::SetPosition { voice, position } => format!("pos {voice} {}", vec3(position)),
+        Command::SetDensity { voice, density } => format!("density {voice} {density}"),
+        Command::SetLoopSteps { voice, steps } => format!("loop {voice} {steps}"),
+        Command::SetWaveform { voice, waveform } => format!("wave {voice} {}", waveform.id()),
+        Command::SetSends { voice, sends } => {
+            format!(
+                "sends {voice} {},{},{}",
+                sends.level, sends.delay, sends.reverb
+            )
+        }
+        Command::ApplyPreset(preset) if is_builtin_preset(preset.name) => {
+            format!("preset {}", preset.name)
+        }
+        Command::MorphToPreset { preset, bars } if is_builtin_preset(preset.name) => {
+            format!("morph {bars} {}", preset.name)
+        }
+        Command::SetMotion(mode) => format!("motion {}", mode.id()),
+        Command::SetBpm(bpm) => format!("bpm {bpm}"),
+        Command::SetRootMidi(root) => format!("root {root}"),
+        Command::Transpose(semitones) => format!("transpose {semitones}"),
+        Command::ShiftOctave { voice, octaves } => format!("shift {voice} {octaves}"),
+        Command::SetOctave { voice, octave } => format!("octave {voice} {octave}"),
+        Command::SetScale(scale) => format!("scale {}", scale_id(scale)?),
+        Command::MorphScale { scale, bars } => format!("morphscale {bars} {}", scale_id(scale)?),
+        Command::SetDetune(cents) => format!("detune {cents}"),
+        Command::AdjustDetune(cents) => format!("nudge {cents}"),
+        Command::ResetDetune => "resetdetune".to_string(),
+        Command::SetGroove(groove) => {
+            let index = GROOVES.iter().position(|g| g.name == groove.name)?;
+            format!("groove {index}")
+        }
+        Command::RestoreMutes { muted, solo } => {
+            let flags: String = muted.iter().map(|m| if *m { '1' } else { '0' }).collect();
+            match solo {
+                Some(voice) => format!("mutes {flags} {voice}"),
+                None => format!("mutes {flags}"),
+            }
+        }
+        _ => return None,
+    })
+}
+
+fn is_builtin_preset(name: &str) -> bool {
+    preset_bank().iter().any(|p| p.name == name)
+}
+
+/// Read back a line from `encode_command`.
+pub fn decode_command(text: &str) -> Option<Command> {
+    let (name, args) = text.split_once(' ').unwrap_or((text, ""));
+    let mut fields = args.split(' ');
+    let mut next = || fields.next().filter(|f| !f.is_empty());
+    let floats = |s: &str| -> Option<Vec<f32>> {
+        s.split(',')
+            .map(|f| f.parse::<f32>().ok().filter(|f| f.is_finite()))
+            .collect()
+    };
+    let builtin_preset = |name: &str| preset_bank().into_iter().find(|p| p.name == name);
+    Some(match name {
+        "mute" => Command::ToggleMute {
+            voice: next()?.parse().ok()?,
+        },
+        "solo" => Command::ToggleSolo {
+            voice: next()?.parse().ok()?,
+        },
+        "lock" => Command::ToggleLock {
+            voice: next()?.parse().ok()?,
+        },
+        "reseed" => Command::Reseed {
+            voice: next()?.parse().ok()?,
+            seed: match next() {
+                Some(seed) => Some(seed.parse().ok()?),
+                None => None,
+            },
+        },
+        "pos" => {
+            let voice = next()?.parse().ok()?;
+            match floats(next()?)?[..] {
+                [x, y, z] => Command::SetPosition {
+                    voice,
+                    position: Vec3::new(x, y, z),
+                },
+                _ => return None,
+            }
+        }
+        "density" => Command::SetDensity {
+            voice: next()?.parse().ok()?,
+            density: next()?.parse().ok()?,
+        },
+        "loop" => Command::SetLoopSteps {
+            voice: next()?.parse().ok()?,
+            steps: next()?.parse().ok()?,
+        },
+        "wave" => Command::SetWaveform {
+            voice: next()?.parse().ok()?,
+            waveform: Waveform::from_id(next()?)?,
+        },
+        "sends" => {
+            let voice = next()?.parse().ok()?;
+            match floats(next()?)?[..] {
+                [level, delay, reverb] => Command::SetSends {
+                    voice,
+                    sends: VoiceSends {
+                        level,
+                        delay,
+                        reverb,
+                    }
+                    .clamped(),
+                },
+                _ => return None,
+            }
+        }
+        "preset" => Command::ApplyPreset(Box::new(builtin_preset(args)?)),
+        "morph" => {
+            let (bars, name) = args.split_once(' ')?;
+            Command::MorphToPreset {
+                preset: Box::new(builtin_preset(name)?),
+                bars: bars.parse().ok()?,
+            }
+        }
+        "motion" => {
+            let id = next()?;
+            Command::SetMotion(MotionMode::ALL.into_iter().find(|m| m.id() == id)?)
+        }
+        "bpm" => Command::SetBpm(next()?.parse().ok()?),
+        "root" => Command::SetRootMidi(next()?.parse().ok()?),
+        "transpose" => Command::Transpose(next()?.parse().ok()?),
+        "shift" => Command::ShiftOctave {
+            voice: next()?.parse().ok()?,
+            octaves: next()?.parse().ok()?,
+        },
+        "octave" => Command::SetOctave {
+            voice: next()?.parse().ok()?,
+            octave: next()?.parse().ok()?,
+        },
+        "scale" => Command::SetScale(scale_by_id(next()?)?),
+        "morphscale" => Command::MorphScale {
+            bars: next()?.parse().ok()?,
+            scale: scale_by_id(next()?)?,
+        },
+        "detune" => Command::SetDetune(next()?.parse().ok()?),
+        "nudge" => Command::AdjustDetune(next()?.parse().ok()?),
+        "resetdetune" => Command::ResetDetune,
+        "groove" => Command::SetGroove(GROOVES.get(next()?.parse::<usize>().ok()?)?),
+        "mutes" => Command::RestoreMutes {
+            muted: next()?.chars().map(|c| c == '1').collect(),
+            solo: match next() {
+                Some(voice) => Some(voice.parse().ok()?),
+                None => None,
+            },
+        },
+        _ => return None,
+    })
+}
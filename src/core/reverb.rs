@@ -0,0 +1,135 @@
+/// Recipe for a procedurally generated stereo impulse response for the
+/// reverb convolver.
+///
+/// Each channel is decaying noise (from its own fixed seed, so the result is
+/// deterministic and decorrelated left/right), optionally preceded by a gap
+/// and discrete early reflections, and tone-shaped as it decays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReverbPreset {
+    /// Short identifier used in URLs and the overlay selector
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Length of the generated response
+    pub length_sec: f32,
+    /// Time constant of the exponential amplitude decay
+    pub decay_sec: f32,
+    /// Silence before the response starts
+    pub predelay_sec: f32,
+    /// Discrete wall reflections at the start of the tail (0 = none)
+    pub early_reflections: u32,
+    /// 0 = only the lowpassed noise (dark), 1 = full-band noise
+    pub brightness: f32,
+    /// Extra darkening toward the end: fraction of the level lost linearly
+    /// over the length
+    pub tilt: f32,
+    /// Growth of the high band over the tail (0 = none): a rising shimmer
+    pub bloom: f32,
+}
+
+/// Small room: short and dark with audible early reflections.
+pub const ROOM: ReverbPreset = ReverbPreset {
+    id: "room",
+    name: "Room",
+    length_sec: 1.2,
+    decay_sec: 0.35,
+    predelay_sec: 0.004,
+    early_reflections: 8,
+    brightness: 0.4,
+    tilt: 0.2,
+    bloom: 0.0,
+};
+
+/// The long, dark tail geno-1 has always used.
+pub const HALL: ReverbPreset = ReverbPreset {
+    id: "hall",
+    name: "Hall",
+    length_sec: 5.0,
+    decay_sec: 3.0,
+    predelay_sec: 0.0,
+    early_reflections: 0,
+    brightness: 1.0,
+    tilt: 0.4,
+    bloom: 0.0,
+};
+
+/// Plate: dense and bright from the first sample, medium decay.
+pub const PLATE: ReverbPreset = ReverbPreset {
+    id: "plate",
+    name: "Plate",
+    length_sec: 2.5,
+    decay_sec: 1.0,
+    predelay_sec: 0.0,
+    early_reflections: 0,
+    brightness: 1.0,
+    tilt: 0.0,
+    bloom: 0.0,
+};
+
+/// Very long tail whose top end swells as the low end fades.
+pub const SHIMMER: ReverbPreset = ReverbPreset {
+    id: "shimmer",
+    name: "Shimmer",
+    length_sec: 6.0,
+    decay_sec: 2.5,
+    predelay_sec: 0.02,
+    early_reflections: 0,
+    brightness: 0.3,
+    tilt: 0.3,
+    bloom: 1.5,
+};
+
+/// All built-in reverb presets; `HALL` is the default.
+pub const REVERB_PRESETS: &[ReverbPreset] = &[ROOM, HALL, PLATE, SHIMMER];
+
+/// Look up a reverb preset by id (case-insensitive).
+pub fn reverb_preset_by_id(id: &str) -> Option<&'static ReverbPreset> {
+    REVERB_PRESETS
+        .iter()
+        .find(|p| p.id.eq_ignore_ascii_case(id))
+}
+
+const IR_SEEDS: [u32; 2] = [0x1234_ABCD, 0x7890_FEDC];
+
+impl ReverbPreset {
+    /// Generate the stereo response at `sample_rate` as `[left, right]`.
+    pub fn impulse(&self, sample_rate: f32) -> [Vec<f32>; 2] {
+        let len = (sample_rate * self.length_sec) as usize;
+        IR_SEEDS.map(|seed| self.channel(seed, len, sample_rate))
+    }
+
+    fn channel(&self, mut seed: u32, len: usize, sample_rate: f32) -> Vec<f32> {
+        let dt = 1.0 / sample_rate;
+        let predelay = (self.predelay_sec * sample_rate) as usize;
+        let mut buf = vec![0.0; len];
+        let mut low = 0.0_f32;
+        for (i, out) in buf.iter_mut().enumerate().skip(predelay) {
+            // xorshift32 noise
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let n = (seed as f32 / u32::MAX as f32) * 2.0 - 1.0;
+            low += (n - low) * 0.2;
+            let t = (i - predelay) as f32 * dt;
+            let progress = t / self.length_sec;
+            let high = (n - low) * (self.brightness + self.bloom * progress);
+            let decay = (-t / self.decay_sec.max(1e-3)).exp();
+            let dark = (1.0 - progress).max(0.0);
+            *out = (low + high) * decay * (1.0 - self.tilt + self.tilt * dark);
+        }
+        // Early reflections: sparse taps over the first ~60 ms, fading out,
+        // spaced differently per channel by the seed
+        for k in 0..self.early_reflections {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let jitter = seed as f32 / u32::MAX as f32;
+            let t = (k as f32 + jitter) * 0.06 / self.early_reflections as f32;
+            let i = predelay + (t * sample_rate) as usize;
+            if let Some(s) = buf.get_mut(i) {
+                let sign = if seed & 1 == 0 { 1.0 } else { -1.0 };
+                *s += sign * (1.0 - k as f32 / self.early_reflections as f32);
+            }
+        }
+        buf
+    }
+}
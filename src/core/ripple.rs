@@ -0,0 +1,58 @@
+/// Ripples on screen at once (`MAX_RIPPLES` in waves.wgsl); a tap beyond
+/// this replaces the oldest.
+pub const MAX_RIPPLES: usize = 8;
+
+/// Seconds before a ripple has decayed to nothing and its slot is free.
+pub const RIPPLE_LIFETIME_SEC: f32 = 3.0;
+
+/// One click/tap ripple.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Ripple {
+    /// Centre in screen uv (0..1)
+    pub uv: [f32; 2],
+    /// Render time it started at
+    pub t0: f32,
+    /// Strength; 0 marks an empty slot
+    pub amp: f32,
+}
+
+/// The active ripples, so rapid taps each get their own ring instead of
+/// restarting one.
+#[derive(Clone, Debug, Default)]
+pub struct Ripples {
+    slots: [Ripple; MAX_RIPPLES],
+}
+
+impl Ripples {
+    /// Start a ripple at `now`, in a free slot if there is one, otherwise in
+    /// place of the oldest.
+    pub fn push(&mut self, uv: [f32; 2], amp: f32, now: f32) {
+        let ripple = Ripple {
+            uv,
+            t0: now,
+            amp: amp.clamp(0.0, 1.5),
+        };
+        let free = self
+            .slots
+            .iter()
+            .position(|r| r.amp <= 0.0 || now - r.t0 >= RIPPLE_LIFETIME_SEC);
+        let slot = free.unwrap_or_else(|| {
+            (0..MAX_RIPPLES)
+                .min_by(|&a, &b| self.slots[a].t0.total_cmp(&self.slots[b].t0))
+                .unwrap_or(0)
+        });
+        self.slots[slot] = ripple;
+    }
+
+    /// Slots as the shader reads them: uv, start time and amplitude, with
+    /// expired ripples zeroed so they cost nothing.
+    pub fn pack(&self, now: f32) -> [[f32; 4]; MAX_RIPPLES] {
+        self.slots.map(|r| {
+            if r.amp > 0.0 && now - r.t0 < RIPPLE_LIFETIME_SEC {
+                [r.uv[0], r.uv[1], r.t0, r.amp]
+            } else {
+                [0.0; 4]
+            }
+        })
+    }
+}
@@ -0,0 +1,111 @@
+/// A mono recording voices can play back (see `SamplerConfig`).
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub name: String,
+    pub sample_rate: u32,
+    frames: Vec<f32>,
+}
+
+impl Sample {
+    /// `None` for an empty recording or a zero sample rate.
+    pub fn new(name: &str, sample_rate: u32, frames: Vec<f32>) -> Option<Sample> {
+        (!frames.is_empty() && sample_rate > 0).then(|| Sample {
+            name: name.to_string(),
+            sample_rate,
+            frames,
+        })
+    }
+
+    pub fn frames(&self) -> &[f32] {
+        &self.frames
+    }
+
+    pub fn duration_sec(&self) -> f32 {
+        self.frames.len() as f32 / self.sample_rate as f32
+    }
+
+    /// Value `pos_sec` into the recording, linearly interpolated (0 outside it).
+    pub fn read(&self, pos_sec: f32) -> f32 {
+        let pos = pos_sec * self.sample_rate as f32;
+        if pos < 0.0 {
+            return 0.0;
+        }
+        let i = pos as usize;
+        let Some(&a) = self.frames.get(i) else {
+            return 0.0;
+        };
+        let b = self.frames.get(i + 1).copied().unwrap_or(0.0);
+        a + (b - a) * (pos - i as f32)
+    }
+}
+
+/// Pitch the recording is assumed to be at when nothing else is known (middle C).
+pub const DEFAULT_SAMPLE_ROOT_HZ: f32 = 261.63;
+
+/// Section of a sample repeated while a note is held.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoopRegion {
+    pub start_sec: f32,
+    pub end_sec: f32,
+}
+
+impl LoopRegion {
+    /// Parse `"start,end"` in seconds (e.g. from a URL).
+    pub fn parse(s: &str) -> Option<LoopRegion> {
+        let (a, b) = s.split_once(',')?;
+        let start_sec: f32 = a.trim().parse().ok()?;
+        let end_sec: f32 = b.trim().parse().ok()?;
+        (start_sec.is_finite() && end_sec.is_finite() && 0.0 <= start_sec && start_sec < end_sec)
+            .then_some(LoopRegion { start_sec, end_sec })
+    }
+}
+
+/// Plays a recording in place of the voice's oscillator, transposed by
+/// playback rate (so higher notes are also shorter).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerConfig {
+    /// Index into `MusicEngine::samples`
+    pub sample: u16,
+    /// Pitch of the recording as played back at rate 1
+    pub root_hz: f32,
+    /// Where playback starts in the recording
+    pub start_sec: f32,
+    /// Repeat this section until the note ends; `None` plays through once
+    pub loop_region: Option<LoopRegion>,
+}
+
+impl SamplerConfig {
+    /// Plays sample `sample` from the beginning, once, pitched as `root_hz`.
+    pub fn one_shot(sample: u16, root_hz: f32) -> SamplerConfig {
+        SamplerConfig {
+            sample,
+            root_hz,
+            start_sec: 0.0,
+            loop_region: None,
+        }
+    }
+
+    /// Playback rate that sounds the recording at `frequency_hz`.
+    pub fn playback_rate(&self, frequency_hz: f32) -> f32 {
+        frequency_hz / self.root_hz.max(1e-3)
+    }
+
+    /// Loop to use with a recording `duration_sec` long, if it is a valid section.
+    pub fn active_loop(&self, duration_sec: f32) -> Option<LoopRegion> {
+        self.loop_region.filter(|l| {
+            l.start_sec >= 0.0 && l.end_sec <= duration_sec && l.end_sec - l.start_sec > 1e-3
+        })
+    }
+
+    /// Read position (seconds into the recording) `t_sec` after note-on at
+    /// `rate`, wrapping inside the loop once playback reaches its end.
+    pub fn position_at(&self, duration_sec: f32, t_sec: f32, rate: f32) -> f32 {
+        let pos = self.start_sec + t_sec * rate;
+        match self.active_loop(duration_sec) {
+            Some(l) if pos >= l.end_sec => {
+                l.start_sec + (pos - l.start_sec).rem_euclid(l.end_sec - l.start_sec)
+            }
+            _ => pos,
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use super::frame_math::VoiceSends;
+use super::history::Command;
+use super::midi_learn::CcMap;
+use super::motion::MotionMode;
+use super::music::{MusicEngine, Waveform};
+use super::preset::preset_bank;
+use super::share::ShareState;
+use glam::Vec3;
+
+/// What a voice was left as.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SavedVoice {
+    pub position: Vec3,
+    pub density: f32,
+    pub octave: i32,
+    pub sends: VoiceSends,
+    pub waveform: Waveform,
+}
+
+/// The whole session as it was left, restored on the next launch: the seed,
+/// the engine's tempo, key and voices, and the effect settings.
+///
+/// Saved as lines of text: the share string (`g1;bpm=…`) first, then
+/// `key=value` lines, so a newer save still loads (unknown lines are skipped).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SavedSession {
+    pub seed: u64,
+    pub share: ShareState,
+    pub motion: MotionMode,
+    pub voices: Vec<SavedVoice>,
+    /// Controllers mapped by MIDI-learn
+    pub controls: CcMap,
+    /// Effect settings in the form their URL parameter takes, e.g.
+    /// `("bloom", "1.2,0.5,3")`, used on the next launch where the URL
+    /// doesn't give one
+    pub settings: Vec<(String, String)>,
+}
+
+impl SavedSession {
+    pub fn capture(engine: &MusicEngine, settings: Vec<(String, String)>) -> Self {
+        let voices = engine
+            .voices
+            .iter()
+            .zip(&engine.configs)
+            .map(|(v, c)| SavedVoice {
+                position: v.position,
+                density: c.trigger_probability,
+                octave: c.octave_offset,
+                sends: c.sends,
+                waveform: c.waveform,
+            })
+            .collect();
+        Self {
+            seed: engine.seed(),
+            share: ShareState::from_engine(engine),
+            motion: engine.motion(),
+            voices,
+            controls: engine.controls.clone(),
+            settings,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let mut lines = vec![
+            self.share.encode(),
+            format!("seed={}", self.seed),
+            format!("motion={}", self.motion.id()),
+        ];
+        for v in &self.voices {
+            let p = v.position;
+            let s = v.sends;
+            lines.push(format!(
+                "voice={},{},{};{};{};{},{},{};{}",
+                p.x,
+                p.y,
+                p.z,
+                v.density,
+                v.octave,
+                s.level,
+                s.delay,
+                s.reverb,
+                v.waveform.id()
+            ));
+        }
+        if !self.controls.is_empty() {
+            lines.push(format!("cc={}", self.controls.encode()));
+        }
+        for (name, value) in &self.settings {
+            lines.push(format!("set.{name}={value}"));
+        }
+        lines.join("\n")
+    }
+
+    /// Read back `encode`'s form; `None` unless it starts with a share string.
+    /// Voices that don't parse are dropped along with the ones after them.
+    pub fn decode(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let share = ShareState::decode(lines.next()?).ok()?;
+        let mut saved = Self {
+            seed: super::session::SessionConfig::default().seed,
+            share,
+            motion: MotionMode::Still,
+            voices: Vec::new(),
+            controls: CcMap::default(),
+            settings: Vec::new(),
+        };
+        let mut voices_ok = true;
+        for line in lines {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match key {
+                "seed" => saved.seed = value.parse().unwrap_or(saved.seed),
+                "motion" => {
+                    if let Some(m) = MotionMode::ALL.into_iter().find(|m| m.id() == value) {
+                        saved.motion = m;
+                    }
+                }
+                "cc" => saved.controls = CcMap::decode(value),
+                "voice" if voices_ok => match parse_voice(value) {
+                    Some(v) => saved.voices.push(v),
+                    None => voices_ok = false,
+                },
+                _ => {
+                    if let Some(name) = key.strip_prefix("set.") {
+                        saved.settings.push((name.to_string(), value.to_string()));
+                    }
+                }
+            }
+        }
+        Some(saved)
+    }
+
+    /// The saved value of URL parameter `name`, if any.
+    pub fn setting(&self, name: &str) -> Option<&str> {
+        self.settings
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// A new engine as the session was left, built as a launch restoring it
+    /// builds one: the first built-in preset with the saved seed, so the
+    /// voices generate as they did, then `restore_command`. Loaded tables and
+    /// samples, controller mappings and the springs, ducking and metronome
+    /// settings are kept from `current`.
+    pub fn rebuild(&self, current: &MusicEngine) -> MusicEngine {
+        let startup = preset_bank().swap_remove(0);
+        let mut engine = MusicEngine::new(startup.voices, startup.params, self.seed);
+        engine.wavetables = current.wavetables.clone();
+        engine.samples = current.samples.clone();
+        engine.controls = current.controls.clone();
+        engine.set_physics(current.physics());
+        engine.set_sidechain(current.sidechain());
+        engine.set_metronome(current.metronome());
+        let voices = engine.voices.len();
+        engine.apply(&self.restore_command(voices));
+        engine
+    }
+
+    /// A command putting the engine back as saved (the seed is the engine's
+    /// own, given when it is built, and controller mappings aren't engine
+    /// state an undo should touch). Voices beyond `voices` are skipped.
+    pub fn restore_command(&self, voices: usize) -> Command {
+        let mut cmds = vec![self.share.to_command(), Command::SetMotion(self.motion)];
+        for (voice, v) in self.voices.iter().enumerate().take(voices) {
+            cmds.extend([
+                Command::SetPosition {
+                    voice,
+                    position: v.position,
+                },
+                Command::SetDensity {
+                    voice,
+                    density: v.density,
+                },
+                Command::SetOctave {
+                    voice,
+                    octave: v.octave,
+                },
+                Command::SetSends {
+                    voice,
+                    sends: v.sends,
+                },
+                Command::SetWaveform {
+                    voice,
+                    waveform: v.waveform,
+                },
+            ]);
+        }
+        Command::Batch(cmds)
+    }
+}
+
+// `x,y,z;density;octave;level,delay,reverb;waveform`
+fn parse_voice(text: &str) -> Option<SavedVoice> {
+    let floats = |s: &str| -> Option<Vec<f32>> {
+        s.split(',')
+            .map(|f| f.trim().parse::<f32>().ok().filter(|f| f.is_finite()))
+            .collect()
+    };
+    let mut fields = text.split(';');
+    let position = match floats(fields.next()?)?[..] {
+        [x, y, z] => Vec3::new(x, y, z),
+        _ => return None,
+    };
+    let density = fields.next()?.parse::<f32>().ok()?.clamp(0.0, 1.0);
+    let octave = fields.next()?.parse().ok()?;
+    let sends = match floats(fields.next()?)?[..] {
+        [level, delay, reverb] => VoiceSends {
+            level,
+            delay,
+            reverb,
+        }
+        .clamped(),
+        _ => return None,
+    };
+    let waveform = Waveform::from_id(fields.next()?)?;
+    Some(SavedVoice {
+        position,
+        density,
+        octave,
+        sends,
+        waveform,
+    })
+}
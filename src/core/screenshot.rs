@@ -0,0 +1,182 @@
+/// Multiple of the canvas size for wallpaper screenshots.
+pub const WALLPAPER_SCALE: u32 = 2;
+
+/// Side of the signature stamped on a still, as a fraction of its shorter edge.
+const STAMP_FRACTION: f32 = 0.12;
+
+/// Largest deflate "stored" block.
+const MAX_STORED_BLOCK: usize = 65535;
+
+use super::signature::Signature;
+
+/// A captured frame as 8-bit RGBA, top row first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Screenshot {
+    /// Unpack a texture readback: rows `row_stride` bytes apart (GPU copies
+    /// pad rows), in BGRA order when `bgra` (the usual swapchain format).
+    pub fn from_rows(width: u32, height: u32, data: &[u8], row_stride: usize, bgra: bool) -> Self {
+        let row_bytes = width as usize * 4;
+        let mut rgba = Vec::with_capacity(row_bytes * height as usize);
+        for row in data.chunks(row_stride).take(height as usize) {
+            rgba.extend_from_slice(&row[..row_bytes]);
+        }
+        if bgra {
+            for px in rgba.as_chunks_mut::<4>().0 {
+                px.swap(0, 2);
+            }
+        }
+        Screenshot {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    /// Draw the session's signature glyph into the bottom-right corner, so a
+    /// still carries the tonality and seed it was taken from.
+    pub fn stamp_signature(&mut self, signature: &Signature) {
+        let size = (self.width.min(self.height) as f32 * STAMP_FRACTION).floor();
+        if size < 8.0 {
+            return;
+        }
+        let margin = size * 0.25;
+        let left = self.width as f32 - size - margin;
+        let top = self.height as f32 - size - margin;
+        let side = size as usize + 1;
+
+        // Coverage first, then one blend, so crossing strokes don't stack
+        let mut coverage = vec![0.0f32; side * side];
+        let mut plot = |(x0, y0): (f32, f32), (x1, y1): (f32, f32), alpha: f32| {
+            let steps = ((x1 - x0).hypot(y1 - y0) * 2.0).ceil().max(1.0) as usize;
+            for i in 0..=steps {
+                let t = i as f32 / steps as f32;
+                let (x, y) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+                let (px, py) = (x.round() as usize, y.round() as usize);
+                if px < side && py < side {
+                    let cell = &mut coverage[py * side + px];
+                    *cell = cell.max(alpha);
+                }
+            }
+        };
+        // The faint ring the SVG draws around the glyph
+        let c = size * 0.5;
+        let r_max = c * 0.9;
+        let ring_steps = (r_max * 0.5).ceil().max(12.0) as usize;
+        for i in 0..ring_steps {
+            let point = |j: usize| {
+                let a = j as f32 / ring_steps as f32 * std::f32::consts::TAU;
+                (c + a.cos() * r_max, c + a.sin() * r_max)
+            };
+            plot(point(i), point(i + 1), 0.35);
+        }
+        for outline in signature.outlines(size) {
+            for (i, &from) in outline.iter().enumerate() {
+                plot(from, outline[(i + 1) % outline.len()], 0.9);
+            }
+        }
+
+        let colour = signature.stroke_rgb();
+        for (row, line) in coverage.chunks_exact(side).enumerate() {
+            for (col, &alpha) in line.iter().enumerate() {
+                let (x, y) = (left as usize + col, top as usize + row);
+                if alpha == 0.0 || x >= self.width as usize || y >= self.height as usize {
+                    continue;
+                }
+                let at = (y * self.width as usize + x) * 4;
+                for (channel, &value) in self.rgba[at..at + 3].iter_mut().zip(&colour) {
+                    *channel =
+                        (*channel as f32 + (value as f32 - *channel as f32) * alpha).round() as u8;
+                }
+            }
+        }
+    }
+
+    /// Encode as an opaque PNG. The image data is stored uncompressed (a
+    /// zlib stream of stored blocks): larger files, but no encoder to carry.
+    pub fn to_png(&self) -> Vec<u8> {
+        // Scanlines, each with filter byte 0; alpha is dropped as the frame
+        // is always opaque
+        let mut raw = Vec::with_capacity((self.width as usize * 3 + 1) * self.height as usize);
+        for row in self.rgba.chunks_exact(self.width.max(1) as usize * 4) {
+            raw.push(0);
+            for px in row.as_chunks::<4>().0 {
+                raw.extend_from_slice(&px[..3]);
+            }
+        }
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&self.width.to_be_bytes());
+        ihdr.extend_from_slice(&self.height.to_be_bytes());
+        // 8-bit RGB, default compression and filtering, no interlace
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut png, b"IHDR", &ihdr);
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// `data` as a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+    // Deflate, 32K window, no preset dictionary; FCHECK makes it a multiple of 31
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(u8::from(last));
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // Reduce before the sums can overflow
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,55 @@
+use glam::Vec3;
+
+/// Engine units an arrow key moves the selected voice, and with Shift held
+/// for fine placement.
+pub const NUDGE_STEP: f32 = 0.1;
+pub const NUDGE_FINE_STEP: f32 = 0.02;
+
+/// The voice after (or before) `current` among `count`, wrapping around;
+/// with nothing selected, forward starts at the first voice and backward at
+/// the last.
+pub fn cycle_voice(current: Option<usize>, count: usize, forward: bool) -> Option<usize> {
+    if count == 0 {
+        return None;
+    }
+    Some(match (current.filter(|&i| i < count), forward) {
+        (Some(i), true) => (i + 1) % count,
+        (Some(i), false) => (i + count - 1) % count,
+        (None, true) => 0,
+        (None, false) => count - 1,
+    })
+}
+
+/// Which way an arrow key nudges a voice on the ground plane, as (x, z)
+/// unit steps: up moves away from the camera.
+pub fn arrow_direction(key: &str) -> Option<(f32, f32)> {
+    match key {
+        "ArrowLeft" => Some((-1.0, 0.0)),
+        "ArrowRight" => Some((1.0, 0.0)),
+        "ArrowUp" => Some((0.0, -1.0)),
+        "ArrowDown" => Some((0.0, 1.0)),
+        _ => None,
+    }
+}
+
+/// `position` moved `step` engine units along `(dx, dz)`, kept on the ground
+/// plane and within `max_radius` of the centre as a drag is.
+pub fn nudge_position(position: Vec3, (dx, dz): (f32, f32), step: f32, max_radius: f32) -> Vec3 {
+    let mut x = position.x + dx * step;
+    let mut z = position.z + dz * step;
+    let len = (x * x + z * z).sqrt();
+    if len > max_radius {
+        x *= max_radius / len;
+        z *= max_radius / len;
+    }
+    Vec3::new(x, 0.0, z)
+}
+
+/// What the voices are called where they are named one by one.
+pub const VOICE_NAMES: [&str; 3] = ["Bass", "Lead", "Pad"];
+
+/// The selection as read out to screen readers, e.g. `Lead at x 0.30, z -0.20`.
+pub fn selection_label(voice: usize, position: Vec3) -> String {
+    let name = VOICE_NAMES.get(voice).copied().unwrap_or("Voice");
+    format!("{name} at x {:.2}, z {:.2}", position.x, position.z)
+}
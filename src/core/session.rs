@@ -0,0 +1,28 @@
+/// Settings fixed for a whole session, chosen before the engine is built.
+///
+/// The master `seed` determines every voice sequence, reseed and randomized
+/// control (dice, random key), so sharing it as `seed=4217` reproduces a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionConfig {
+    pub seed: u64,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self { seed: 42 }
+    }
+}
+
+impl SessionConfig {
+    /// Parse a shared seed, either bare (`4217`) or labelled (`seed=4217`).
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let digits = text.strip_prefix("seed=").unwrap_or(text);
+        digits.parse().ok().map(|seed| Self { seed })
+    }
+
+    /// Human-readable form for logs, overlays and sharing (`seed=4217`).
+    pub fn label(&self) -> String {
+        format!("seed={}", self.seed)
+    }
+}
@@ -0,0 +1,98 @@
+/// Seconds between fetches of the shader files while reloading is on.
+pub const SHADER_POLL_SEC: f32 = 1.0;
+
+/// Where `?shaders=on` fetches from: the repo's `shaders/` directory, as
+/// served from its root.
+pub const DEFAULT_SHADER_BASE: &str = "shaders/";
+
+/// A WGSL file the renderer builds pipelines from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shader {
+    Post,
+    Waves,
+    Scene,
+    Exposure,
+    Particles,
+    Text,
+    Lens,
+    Fxaa,
+    /// The custom visualizer's code, built into the waves module
+    Custom,
+}
+
+impl Shader {
+    pub const ALL: [Shader; 9] = [
+        Shader::Post,
+        Shader::Waves,
+        Shader::Scene,
+        Shader::Exposure,
+        Shader::Particles,
+        Shader::Text,
+        Shader::Lens,
+        Shader::Fxaa,
+        Shader::Custom,
+    ];
+
+    /// File name under `shaders/`.
+    pub fn file(self) -> &'static str {
+        match self {
+            Shader::Post => "post.wgsl",
+            Shader::Waves => "waves.wgsl",
+            Shader::Scene => "scene.wgsl",
+            Shader::Exposure => "exposure.wgsl",
+            Shader::Particles => "particles.wgsl",
+            Shader::Text => "text.wgsl",
+            Shader::Lens => "lens.wgsl",
+            Shader::Fxaa => "fxaa.wgsl",
+            Shader::Custom => "custom.wgsl",
+        }
+    }
+
+    pub fn index(self) -> usize {
+        Shader::ALL.iter().position(|&s| s == self).unwrap_or(0)
+    }
+}
+
+/// Shader sources loaded at run time in place of the ones built in.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderSources {
+    loaded: [Option<String>; Shader::ALL.len()],
+}
+
+impl ShaderSources {
+    /// The loaded source, if any; otherwise the built-in one applies.
+    pub fn get(&self, shader: Shader) -> Option<&str> {
+        self.loaded[shader.index()].as_deref()
+    }
+
+    /// Keep `source` for `shader`; false if it is what was already there.
+    pub fn set(&mut self, shader: Shader, source: String) -> bool {
+        let slot = &mut self.loaded[shader.index()];
+        if slot.as_deref() == Some(source.as_str()) {
+            return false;
+        }
+        *slot = Some(source);
+        true
+    }
+}
+
+/// Parse `?shaders=`: `"on"` (or `"1"`) for `DEFAULT_SHADER_BASE`, `"off"`
+/// (or `"0"`) for none, or the URL of a directory to fetch the files from.
+pub fn parse_shader_base(s: &str) -> Option<String> {
+    let s = s.trim();
+    match s.to_ascii_lowercase().as_str() {
+        "" | "off" | "0" | "false" => None,
+        "on" | "1" | "true" => Some(DEFAULT_SHADER_BASE.to_string()),
+        _ if s.ends_with('/') => Some(s.to_string()),
+        _ => Some(format!("{s}/")),
+    }
+}
+
+/// One compiler message as shown in the error overlay:
+/// `file:line:column: message` (the location left out when there is none).
+pub fn compile_message(shader: Shader, location: Option<(u32, u32)>, message: &str) -> String {
+    match location {
+        Some((line, column)) => format!("{}:{line}:{column}: {message}", shader.file()),
+        None => format!("{}: {message}", shader.file()),
+    }
+}
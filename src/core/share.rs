@@ -0,0 +1,149 @@
+use super::history::Command;
+use super::music::{
+    MusicEngine, AEOLIAN, C_MAJOR_PENTATONIC, DORIAN, IONIAN, LOCRIAN, LYDIAN, MIXOLYDIAN,
+    PHRYGIAN, TET19_PENTATONIC, TET24_PENTATONIC, TET31_PENTATONIC,
+};
+
+/// Version tag prefixed to every share string.
+pub const SHARE_PREFIX: &str = "g1";
+
+/// Short ids for the built-in scales used in share strings.
+///
+/// Note: 19-TET and 31-TET pentatonic currently share the same degrees, so a
+/// 31-TET state round-trips as `t19` (identical pitches).
+pub const SCALE_IDS: &[(&str, &[f32])] = &[
+    ("ion", IONIAN),
+    ("dor", DORIAN),
+    ("phr", PHRYGIAN),
+    ("lyd", LYDIAN),
+    ("mix", MIXOLYDIAN),
+    ("aeo", AEOLIAN),
+    ("loc", LOCRIAN),
+    ("pen", C_MAJOR_PENTATONIC),
+    ("t19", TET19_PENTATONIC),
+    ("t24", TET24_PENTATONIC),
+    ("t31", TET31_PENTATONIC),
+];
+
+/// Look up the share id of a scale.
+pub fn scale_id(scale: &[f32]) -> Option<&'static str> {
+    SCALE_IDS
+        .iter()
+        .find(|(_, s)| *s == scale)
+        .map(|(id, _)| *id)
+}
+
+/// Look up a built-in scale by share id.
+pub fn scale_by_id(id: &str) -> Option<&'static [f32]> {
+    SCALE_IDS.iter().find(|(i, _)| *i == id).map(|(_, s)| *s)
+}
+
+/// The user-facing engine state exchanged as a compact, human-readable string,
+/// e.g. `g1;bpm=110;root=60;scale=ion;detune=0;mute=010`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShareState {
+    pub bpm: f32,
+    pub root_midi: i32,
+    pub scale: &'static [f32],
+    pub detune_cents: f32,
+    pub muted: Vec<bool>,
+}
+
+impl ShareState {
+    pub fn from_engine(engine: &MusicEngine) -> Self {
+        Self {
+            bpm: engine.params.bpm,
+            root_midi: engine.params.root_midi,
+            scale: engine.params.scale,
+            detune_cents: engine.params.detune_cents,
+            muted: engine.voices.iter().map(|v| v.muted).collect(),
+        }
+    }
+
+    /// Serialize to the share string. Unknown (custom) scales fall back to Ionian.
+    pub fn encode(&self) -> String {
+        let mute: String = self
+            .muted
+            .iter()
+            .map(|m| if *m { '1' } else { '0' })
+            .collect();
+        format!(
+            "{};bpm={};root={};scale={};detune={};mute={}",
+            SHARE_PREFIX,
+            self.bpm,
+            self.root_midi,
+            scale_id(self.scale).unwrap_or("ion"),
+            self.detune_cents,
+            mute
+        )
+    }
+
+    /// Parse a share string. Surrounding whitespace is ignored; missing fields
+    /// keep their defaults, unknown fields are skipped for forward compatibility.
+    pub fn decode(text: &str) -> Result<Self, String> {
+        let mut parts = text.trim().split(';');
+        if parts.next() != Some(SHARE_PREFIX) {
+            return Err(format!(
+                "not a geno-1 share string (expected '{SHARE_PREFIX};...')"
+            ));
+        }
+        let defaults = super::music::EngineParams::default();
+        let mut state = Self {
+            bpm: defaults.bpm,
+            root_midi: defaults.root_midi,
+            scale: defaults.scale,
+            detune_cents: defaults.detune_cents,
+            muted: Vec::new(),
+        };
+        for part in parts {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("malformed field '{part}'"))?;
+            match key {
+                "bpm" => state.bpm = parse_finite(key, value)?.clamp(40.0, 240.0),
+                "root" => state.root_midi = parse_field::<i32>(key, value)?.clamp(0, 127),
+                "scale" => {
+                    state.scale =
+                        scale_by_id(value).ok_or_else(|| format!("unknown scale '{value}'"))?
+                }
+                "detune" => state.detune_cents = parse_finite(key, value)?,
+                "mute" => state.muted = value.chars().map(|c| c == '1').collect(),
+                _ => {}
+            }
+        }
+        Ok(state)
+    }
+
+    /// A single undoable command that applies this state to an engine.
+    pub fn to_command(&self) -> Command {
+        let mut cmds = vec![
+            Command::SetBpm(self.bpm),
+            Command::SetRootMidi(self.root_midi),
+            Command::SetScale(self.scale),
+            Command::SetDetune(self.detune_cents),
+        ];
+        if !self.muted.is_empty() {
+            cmds.push(Command::RestoreMutes {
+                muted: self.muted.clone(),
+                solo: None,
+            });
+        }
+        Command::Batch(cmds)
+    }
+}
+
+// Clamping leaves NaN as it is, so "NaN" and "inf" are refused outright
+fn parse_finite(key: &str, value: &str) -> Result<f32, String> {
+    let v: f32 = parse_field(key, value)?;
+    if v.is_finite() {
+        Ok(v)
+    } else {
+        Err(format!("invalid value for '{key}': '{value}'"))
+    }
+}
+
+fn parse_field<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for '{key}': '{value}'"))
+}
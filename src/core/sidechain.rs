@@ -0,0 +1,83 @@
+use super::envelope::Adsr;
+
+/// Ducks every other voice whenever the trigger voice plays a note, the
+/// classic pad "pump" under a kick.
+///
+/// Each trigger note pulls the other voices down to `depth_db` below unity
+/// over `attack_sec`, then lets them recover over `release_sec`. Both
+/// segments are exponential (see `Adsr::time_constant`), so scheduled gain
+/// ramps and `DuckFollower` trace the same curve.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sidechain {
+    /// Voice whose notes trigger the ducking (it is never ducked itself)
+    pub trigger_voice: usize,
+    /// How far the other voices drop (positive dB)
+    pub depth_db: f32,
+    pub attack_sec: f32,
+    pub release_sec: f32,
+}
+
+impl Sidechain {
+    /// The bass (voice 0) pumps the lead and pad by 9 dB.
+    pub const DEFAULT: Sidechain = Sidechain {
+        trigger_voice: 0,
+        depth_db: 9.0,
+        attack_sec: 0.01,
+        release_sec: 0.3,
+    };
+
+    /// Parse `"voice,depth_db[,attack,release]"` (e.g. from a URL), keeping
+    /// the default timing when only two values are given.
+    pub fn parse(s: &str) -> Option<Sidechain> {
+        let mut parts = s.split(',').map(str::trim);
+        let trigger_voice: usize = parts.next()?.parse().ok()?;
+        let nums: Vec<f32> = parts
+            .map(|p| p.parse::<f32>().ok().filter(|x| x.is_finite() && *x >= 0.0))
+            .collect::<Option<_>>()?;
+        let (attack_sec, release_sec) = match nums[..] {
+            [_] => (Self::DEFAULT.attack_sec, Self::DEFAULT.release_sec),
+            [_, a, r] => (a, r),
+            _ => return None,
+        };
+        Some(Sidechain {
+            trigger_voice,
+            depth_db: nums[0],
+            attack_sec,
+            release_sec,
+        })
+    }
+
+    /// Whether notes of `voice` are pushed down by this sidechain.
+    pub fn ducks(&self, voice: usize) -> bool {
+        voice != self.trigger_voice
+    }
+
+    /// Linear gain of a fully ducked voice.
+    pub fn ducked_gain(&self) -> f32 {
+        10f32.powf(-self.depth_db / 20.0)
+    }
+}
+
+/// One-pole envelope follower on the trigger voice's gate, for the offline
+/// mix: rises toward full ducking while a trigger note's attack is open and
+/// falls back while it is closed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DuckFollower {
+    /// 0 = not ducked, 1 = fully ducked
+    level: f32,
+}
+
+impl DuckFollower {
+    /// Advance `dt_sec` with the gate `open` and return the gain to apply to
+    /// ducked voices.
+    pub fn next(&mut self, sidechain: &Sidechain, open: bool, dt_sec: f32) -> f32 {
+        let (target, seg) = if open {
+            (1.0, sidechain.attack_sec)
+        } else {
+            (0.0, sidechain.release_sec)
+        };
+        let k = 1.0 - (-dt_sec / Adsr::time_constant(seg)).exp();
+        self.level += (target - self.level) * k;
+        1.0 + (sidechain.ducked_gain() - 1.0) * self.level
+    }
+}
@@ -0,0 +1,140 @@
+/// Visual identity derived from the active pitch-class set and engine seed.
+///
+/// Each (scale, root, seed) triple maps to a small symmetrical glyph so that a
+/// generated piece is recognizable at a glance. The glyph is described
+/// geometrically here and rendered to SVG markup so any frontend can display it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    /// Bitmask of the 12-TET pitch classes present (bit 0 = C, bit 11 = B).
+    pub pitch_class_mask: u16,
+    /// Angle of each scale degree around the circle, in turns (0..1).
+    pub spokes: Vec<f32>,
+    /// Relative radius (0.35..1.0) of each spoke, varied by seed.
+    pub radii: Vec<f32>,
+    /// N-fold rotational symmetry used when drawing the glyph.
+    pub symmetry: u32,
+    /// Hue in turns (0..1).
+    pub hue: f32,
+    /// Global rotation offset in turns (0..1).
+    pub rotation: f32,
+}
+
+// SplitMix64 step: cheap, well-distributed bits for deriving glyph features
+#[inline]
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[inline]
+fn unit_f32(bits: u64) -> f32 {
+    (bits >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Derive the signature glyph for a scale (semitone offsets), root and seed.
+///
+/// Degrees are placed on the pitch-class circle (fractional degrees from
+/// alternative tunings land between semitones). The octave duplicate (12.0)
+/// collapses onto the root and is ignored.
+pub fn pitch_class_signature(scale: &[f32], root_midi: i32, seed: u64) -> Signature {
+    let mut spokes: Vec<f32> = Vec::new();
+    let mut mask: u16 = 0;
+    for degree in scale {
+        let pc = (root_midi as f32 + degree).rem_euclid(12.0);
+        let turn = pc / 12.0;
+        if spokes.iter().any(|s| (s - turn).abs() < 1e-4) {
+            continue;
+        }
+        spokes.push(turn);
+        mask |= 1 << ((pc.round() as u16) % 12);
+    }
+    spokes.sort_by(|a, b| a.total_cmp(b));
+
+    let mut state = seed ^ ((mask as u64) << 48);
+    let radii = spokes
+        .iter()
+        .map(|_| 0.35 + 0.65 * unit_f32(splitmix64(&mut state)))
+        .collect();
+    let symmetry = 2 + (splitmix64(&mut state) % 5) as u32;
+    let hue = unit_f32(splitmix64(&mut state));
+    let rotation = unit_f32(splitmix64(&mut state));
+
+    Signature {
+        pitch_class_mask: mask,
+        spokes,
+        radii,
+        symmetry,
+        hue,
+        rotation,
+    }
+}
+
+impl Signature {
+    /// The glyph's strokes in a `size`×`size` square: one closed outline per
+    /// symmetry copy, as points joined in order.
+    pub fn outlines(&self, size: f32) -> Vec<Vec<(f32, f32)>> {
+        let c = size * 0.5;
+        let r_max = c * 0.9;
+        (0..self.symmetry)
+            .map(|k| {
+                let offset = self.rotation + k as f32 / self.symmetry as f32;
+                self.spokes
+                    .iter()
+                    .zip(&self.radii)
+                    .map(|(turn, radius)| {
+                        let a = (turn + offset) * std::f32::consts::TAU;
+                        (c + a.cos() * radius * r_max, c + a.sin() * radius * r_max)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Stroke colour as 8-bit RGB (the SVG's `hsl(hue, 70%, 70%)`).
+    pub fn stroke_rgb(&self) -> [u8; 3] {
+        let (s, l) = (0.7, 0.7);
+        let chroma = (1.0 - (2.0 * l - 1.0_f32).abs()) * s;
+        let channel = |n: f32| {
+            let k = (n + self.hue * 12.0) % 12.0;
+            let v = l - chroma * 0.5 * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0);
+            (v * 255.0).round() as u8
+        };
+        [channel(0.0), channel(8.0), channel(4.0)]
+    }
+
+    /// Render the glyph as a standalone SVG element of `size`×`size` pixels.
+    pub fn to_svg(&self, size: u32) -> String {
+        let c = size as f32 * 0.5;
+        let r_max = c * 0.9;
+        let hue_deg = self.hue * 360.0;
+        let mut paths = String::new();
+        for outline in self.outlines(size as f32) {
+            let mut d = String::new();
+            for (i, (x, y)) in outline.iter().enumerate() {
+                d.push_str(&format!(
+                    "{}{:.1},{:.1} ",
+                    if i == 0 { "M" } else { "L" },
+                    x,
+                    y
+                ));
+            }
+            d.push('Z');
+            paths.push_str(&format!(
+                "<path d='{}' fill='hsla({:.0},70%,60%,0.08)' stroke='hsl({:.0},70%,70%)' stroke-width='1'/>",
+                d, hue_deg, hue_deg
+            ));
+        }
+        format!(
+            "<svg xmlns='http://www.w3.org/2000/svg' width='{s}' height='{s}' viewBox='0 0 {s} {s}'>\
+             <circle cx='{c:.1}' cy='{c:.1}' r='{r:.1}' fill='none' stroke='hsla({h:.0},60%,70%,0.35)'/>{p}</svg>",
+            s = size,
+            c = c,
+            r = r_max,
+            h = hue_deg,
+            p = paths
+        )
+    }
+}
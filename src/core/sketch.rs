@@ -0,0 +1,121 @@
+use super::theme::Theme;
+use super::trails::TRAIL_POINTS;
+
+/// Engine units to the shorter side of the canvas, as the waves place their
+/// voices (the `* 0.33` in waves.wgsl).
+pub const SKETCH_SCALE: f32 = 0.33;
+
+/// Radius of a resting voice, and how much a full pulse adds, as fractions of
+/// the shorter canvas side.
+pub const SKETCH_RADIUS: f32 = 0.035;
+pub const SKETCH_PULSE_RADIUS: f32 = 0.04;
+
+/// Radius of the pale disc behind the voice selected from the keyboard, as a
+/// fraction of the shorter canvas side (clear of a full pulse).
+pub const SKETCH_SELECTION_RADIUS: f32 = 0.09;
+
+/// Brightest a trail point is drawn (0..1 alpha); older points fade and
+/// shrink from there.
+pub const SKETCH_TRAIL_ALPHA: f32 = 0.45;
+
+/// One filled circle of the Canvas2D fallback, in canvas pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SketchDot {
+    pub x: f32,
+    pub y: f32,
+    pub radius: f32,
+    /// CSS `rgba(..)` fill
+    pub fill: String,
+}
+
+/// Where a voice at `position` (engine units, x and z used) lands on a
+/// `width`×`height` canvas.
+pub fn sketch_point(position: [f32; 3], width: f32, height: f32) -> (f32, f32) {
+    let unit = width.min(height) * SKETCH_SCALE;
+    (
+        0.5 * width + position[0] * unit,
+        0.5 * height + position[2] * unit,
+    )
+}
+
+/// CSS colour for a linear theme colour, clipped to 0..1 and encoded sRGB.
+pub fn css_rgba(linear: [f32; 3], alpha: f32) -> String {
+    let [r, g, b] = linear.map(|c| {
+        let c = c.clamp(0.0, 1.0);
+        let s = if c <= 0.003_130_8 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (s * 255.0).round() as u8
+    });
+    format!("rgba({r},{g},{b},{:.3})", alpha.clamp(0.0, 1.0))
+}
+
+/// The fallback picture, back to front: each voice's trail (packed as
+/// `VoiceTrail::pack` lays it out, `TRAIL_POINTS` per voice) and then the
+/// voices themselves, swelling with their pulse. Voice colours cycle through
+/// the theme's bass, lead and pad.
+pub fn sketch_dots(
+    theme: &Theme,
+    positions: &[[f32; 3]],
+    pulses: &[f32],
+    trails: &[[f32; 4]],
+    width: f32,
+    height: f32,
+) -> Vec<SketchDot> {
+    let side = width.min(height);
+    let color = |i: usize| theme.voices[i % theme.voices.len()];
+    let mut dots = Vec::new();
+    for (i, trail) in trails.chunks(TRAIL_POINTS).enumerate() {
+        // The head is the voice itself, drawn below
+        for p in trail.iter().skip(1).filter(|p| p[3] > 0.0) {
+            let (x, y) = sketch_point([p[0], p[1], p[2]], width, height);
+            dots.push(SketchDot {
+                x,
+                y,
+                radius: side * SKETCH_RADIUS * (0.25 + 0.5 * p[3]),
+                fill: css_rgba(color(i), SKETCH_TRAIL_ALPHA * p[3]),
+            });
+        }
+    }
+    for (i, &position) in positions.iter().enumerate() {
+        let pulse = pulses.get(i).copied().unwrap_or(0.0).clamp(0.0, 1.5);
+        let (x, y) = sketch_point(position, width, height);
+        dots.push(SketchDot {
+            x,
+            y,
+            radius: side * (SKETCH_RADIUS + SKETCH_PULSE_RADIUS * pulse),
+            fill: css_rgba(color(i), 0.75 + 0.25 * pulse.min(1.0)),
+        });
+    }
+    dots
+}
+
+/// The pale disc drawn behind a selected voice at `position`, so it stands
+/// out without Canvas2D strokes.
+pub fn sketch_selection(position: [f32; 3], width: f32, height: f32) -> SketchDot {
+    let (x, y) = sketch_point(position, width, height);
+    SketchDot {
+        x,
+        y,
+        radius: width.min(height) * SKETCH_SELECTION_RADIUS,
+        fill: css_rgba([1.0; 3], 0.25),
+    }
+}
+
+/// Four small dots around a locked voice at `position`, like pins.
+pub fn sketch_lock_marks(position: [f32; 3], width: f32, height: f32) -> Vec<SketchDot> {
+    let (x, y) = sketch_point(position, width, height);
+    let side = width.min(height);
+    let reach = side * SKETCH_SELECTION_RADIUS;
+    [(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)]
+        .into_iter()
+        .map(|(dx, dy)| SketchDot {
+            x: x + dx * reach,
+            y: y + dy * reach,
+            radius: side * 0.008,
+            fill: css_rgba([1.0; 3], 0.7),
+        })
+        .collect()
+}
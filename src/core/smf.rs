@@ -0,0 +1,366 @@
+use super::music::NoteEvent;
+
+/// Ticks per quarter note in exported files.
+pub const SMF_PPQ: u16 = 480;
+
+/// Pitch-bend range (semitones) assumed by receivers; the General MIDI default.
+pub const SMF_BEND_RANGE_SEMITONES: f32 = 2.0;
+
+/// Notes kept by a `MidiRecorder` before it stops capturing.
+pub const MIDI_RECORDER_MAX_NOTES: usize = 50_000;
+
+/// Convert a frequency in Hertz to a (fractional) MIDI note number (A4 = 69).
+pub fn hz_to_midi(hz: f32) -> f32 {
+    69.0 + 12.0 * (hz.max(1e-3) / 440.0).log2()
+}
+
+/// Nearest MIDI key to `hz` and the 14-bit pitch bend (centre 8192, range
+/// `SMF_BEND_RANGE_SEMITONES`) that reaches the exact pitch from it.
+pub fn key_and_bend(hz: f32) -> (u8, u16) {
+    let midi = hz_to_midi(hz);
+    let key = midi.round().clamp(0.0, 127.0);
+    let bend = (((midi - key) / SMF_BEND_RANGE_SEMITONES) * 8192.0 + 8192.0)
+        .round()
+        .clamp(0.0, 16383.0) as u16;
+    (key as u8, bend)
+}
+
+/// MIDI note-on velocity (1..=127) for a 0..1 engine velocity.
+pub fn midi_velocity(velocity: f32) -> u8 {
+    (velocity.clamp(0.0, 1.0) * 126.0).round() as u8 + 1
+}
+
+/// Collects generated notes and tempo changes for Standard MIDI File export.
+///
+/// Times are on whatever clock the `NoteEvent`s were stamped with; the file
+/// starts at the first recorded note. Each voice gets its own track and
+/// channel, and microtonal pitches are written as a pitch bend before the note.
+#[derive(Clone, Debug)]
+pub struct MidiRecorder {
+    voices: usize,
+    notes: Vec<NoteEvent>,
+    /// (clock time, bpm), in recording order
+    tempos: Vec<(f64, f32)>,
+}
+
+impl MidiRecorder {
+    pub fn new(voices: usize, bpm: f32) -> Self {
+        Self {
+            voices: voices.clamp(1, 16),
+            notes: Vec::new(),
+            tempos: vec![(f64::NEG_INFINITY, bpm)],
+        }
+    }
+
+    pub fn record(&mut self, ev: &NoteEvent) {
+        if self.notes.len() < MIDI_RECORDER_MAX_NOTES && ev.voice_index < self.voices {
+            self.notes.push(ev.clone());
+        }
+    }
+
+    /// Note a tempo change taking effect at `at_sec`.
+    pub fn record_tempo(&mut self, at_sec: f64, bpm: f32) {
+        if self.tempos.last().map(|t| t.1) != Some(bpm) {
+            self.tempos.push((at_sec, bpm));
+        }
+    }
+
+    pub fn note_count(&self) -> usize {
+        self.notes.len()
+    }
+
+    /// Encode everything recorded so far as a type 1 SMF: a conductor track
+    /// with the tempo map, then one track per voice.
+    pub fn to_smf(&self) -> Vec<u8> {
+        let origin = self
+            .notes
+            .iter()
+            .map(|n| n.start_time_sec)
+            .fold(f64::INFINITY, f64::min);
+        let origin = if origin.is_finite() { origin } else { 0.0 };
+        let tempo_map = self.tempo_map(origin);
+        let to_ticks = |t_sec: f64| seconds_to_ticks(&tempo_map, t_sec - origin);
+
+        let mut tracks = Vec::with_capacity(self.voices + 1);
+
+        let mut conductor: Vec<(u64, Vec<u8>)> = vec![
+            (0, meta(0x03, b"geno-1")),
+            // 4/4, 24 clocks per click, 8 thirty-seconds per quarter
+            (0, meta(0x58, &[4, 2, 24, 8])),
+        ];
+        for &(at, bpm) in &tempo_map {
+            let usec = (60_000_000.0 / bpm.max(1.0) as f64).round() as u32;
+            let at_ticks = seconds_to_ticks(&tempo_map, at);
+            conductor.push((at_ticks, meta(0x51, &usec.to_be_bytes()[1..])));
+        }
+        tracks.push(conductor);
+
+        for voice in 0..self.voices {
+            let ch = voice as u8;
+            let name = format!("Voice {}", voice + 1);
+            let mut events: Vec<(u64, Vec<u8>)> = vec![(0, meta(0x03, name.as_bytes()))];
+            for n in self.notes.iter().filter(|n| n.voice_index == voice) {
+                let (key, bend) = key_and_bend(n.frequency_hz);
+                let vel = midi_velocity(n.velocity);
+                let on = to_ticks(n.start_time_sec);
+                let off = to_ticks(n.start_time_sec + n.duration_sec.max(0.0) as f64).max(on + 1);
+                events.push((on, vec![0xE0 | ch, (bend & 0x7F) as u8, (bend >> 7) as u8]));
+                events.push((on, vec![0x90 | ch, key, vel]));
+                events.push((off, vec![0x80 | ch, key, 0]));
+            }
+            tracks.push(events);
+        }
+
+        let mut out = Vec::new();
+        out.extend_from_slice(b"MThd");
+        out.extend_from_slice(&6u32.to_be_bytes());
+        out.extend_from_slice(&1u16.to_be_bytes());
+        out.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+        out.extend_from_slice(&SMF_PPQ.to_be_bytes());
+        for mut events in tracks {
+            // Stable sort keeps bend → note-on order; note-offs sort before
+            // note-ons at the same tick so repeated keys retrigger cleanly
+            events.sort_by_key(|(t, e)| (*t, e[0] & 0xF0 != 0x80));
+            write_track(&mut out, &events);
+        }
+        out
+    }
+
+    /// Tempo changes relative to `origin`, starting with the tempo in force there.
+    fn tempo_map(&self, origin: f64) -> Vec<(f64, f32)> {
+        let mut map: Vec<(f64, f32)> = Vec::new();
+        for &(at, bpm) in &self.tempos {
+            let at = (at - origin).max(0.0);
+            match map.last_mut() {
+                Some(last) if last.0 >= at => last.1 = bpm,
+                _ => map.push((at, bpm)),
+            }
+        }
+        map
+    }
+}
+
+/// Seconds from the file start to ticks, following the tempo map.
+fn seconds_to_ticks(tempo_map: &[(f64, f32)], t_sec: f64) -> u64 {
+    let mut ticks = 0.0;
+    for (i, &(start, bpm)) in tempo_map.iter().enumerate() {
+        let end = tempo_map.get(i + 1).map_or(f64::INFINITY, |n| n.0);
+        if t_sec <= start {
+            break;
+        }
+        let span = t_sec.min(end) - start;
+        ticks += span * bpm as f64 / 60.0 * SMF_PPQ as f64;
+    }
+    ticks.max(0.0).round() as u64
+}
+
+fn meta(kind: u8, data: &[u8]) -> Vec<u8> {
+    let mut e = vec![0xFF, kind];
+    write_vlq(&mut e, data.len() as u32);
+    e.extend_from_slice(data);
+    e
+}
+
+fn write_track(out: &mut Vec<u8>, events: &[(u64, Vec<u8>)]) {
+    let mut body = Vec::new();
+    let mut prev = 0;
+    for (t, e) in events {
+        write_vlq(&mut body, (t - prev).min(0x0FFF_FFFF) as u32);
+        body.extend_from_slice(e);
+        prev = *t;
+    }
+    write_vlq(&mut body, 0);
+    body.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+    out.extend_from_slice(b"MTrk");
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(&body);
+}
+
+/// MIDI variable-length quantity: 7 bits per byte, high bit set on all but the last.
+fn write_vlq(out: &mut Vec<u8>, mut value: u32) {
+    let mut buf = [0u8; 5];
+    let mut i = buf.len() - 1;
+    buf[i] = (value & 0x7F) as u8;
+    value >>= 7;
+    while value > 0 {
+        i -= 1;
+        buf[i] = (value & 0x7F) as u8 | 0x80;
+        value >>= 7;
+    }
+    out.extend_from_slice(&buf[i..]);
+}
+
+/// A note read from an imported MIDI file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedNote {
+    /// Index of the track chunk the note came from
+    pub track: usize,
+    pub channel: u8,
+    pub key: u8,
+    pub velocity: u8,
+    /// Onset in quarter notes from the start of the file
+    pub start_beats: f64,
+    pub duration_beats: f64,
+}
+
+/// Read every note from a Standard MIDI File (type 0 or 1), in time order.
+///
+/// Tempo is ignored: times are in beats, which is all the melody import needs.
+pub fn parse_smf(bytes: &[u8]) -> Result<Vec<ImportedNote>, String> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.take(4)? != b"MThd" {
+        return Err("not a MIDI file".into());
+    }
+    let header_len = r.u32()? as usize;
+    let header = r.take(header_len)?;
+    if header.len() < 6 {
+        return Err("short MIDI header".into());
+    }
+    let division = u16::from_be_bytes([header[4], header[5]]);
+    if division & 0x8000 != 0 || division == 0 {
+        return Err("SMPTE time division is not supported".into());
+    }
+
+    let mut notes = Vec::new();
+    let mut track = 0;
+    while r.pos < bytes.len() {
+        let id = r.take(4)?;
+        let len = r.u32()? as usize;
+        let body = r.take(len)?;
+        // Skip unknown chunks, as the spec requires
+        if id == b"MTrk" {
+            parse_track(body, track, division as f64, &mut notes)?;
+            track += 1;
+        }
+    }
+    notes.sort_by(|a, b| {
+        a.start_beats
+            .total_cmp(&b.start_beats)
+            .then(a.key.cmp(&b.key))
+    });
+    Ok(notes)
+}
+
+fn parse_track(
+    body: &[u8],
+    track: usize,
+    ticks_per_beat: f64,
+    notes: &mut Vec<ImportedNote>,
+) -> Result<(), String> {
+    let mut r = Reader {
+        bytes: body,
+        pos: 0,
+    };
+    let mut tick: u64 = 0;
+    let mut running: Option<u8> = None;
+    // Index into `notes` of the sounding note per (channel, key)
+    let mut open: Vec<((u8, u8), usize)> = Vec::new();
+    while r.pos < body.len() {
+        tick += r.vlq()? as u64;
+        let beats = tick as f64 / ticks_per_beat;
+        let first = r.u8()?;
+        match first {
+            0xFF => {
+                let kind = r.u8()?;
+                let len = r.vlq()? as usize;
+                r.take(len)?;
+                if kind == 0x2F {
+                    break;
+                }
+            }
+            0xF0 | 0xF7 => {
+                let len = r.vlq()? as usize;
+                r.take(len)?;
+            }
+            _ => {
+                let (status, data0) = if first & 0x80 != 0 {
+                    running = Some(first);
+                    (first, r.u8()?)
+                } else {
+                    (running.ok_or("data byte without status")?, first)
+                };
+                let channel = status & 0x0F;
+                match status & 0xF0 {
+                    0xC0 | 0xD0 => {}
+                    kind => {
+                        let data1 = r.u8()?;
+                        let key = (channel, data0);
+                        let is_on = kind == 0x90 && data1 > 0;
+                        let is_off = kind == 0x80 || (kind == 0x90 && data1 == 0);
+                        if is_on || is_off {
+                            if let Some(i) = open.iter().position(|(k, _)| *k == key) {
+                                let (_, idx) = open.swap_remove(i);
+                                notes[idx].duration_beats = beats - notes[idx].start_beats;
+                            }
+                        }
+                        if is_on {
+                            open.push((key, notes.len()));
+                            notes.push(ImportedNote {
+                                track,
+                                channel,
+                                key: data0,
+                                velocity: data1,
+                                start_beats: beats,
+                                duration_beats: 0.0,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Split imported notes into one key sequence per (track, channel) part, in
+/// time order, so each part can seed a voice.
+pub fn melodies(notes: &[ImportedNote]) -> Vec<Vec<u8>> {
+    let mut parts: Vec<((usize, u8), Vec<u8>)> = Vec::new();
+    for n in notes {
+        let part = (n.track, n.channel);
+        match parts.iter_mut().find(|(p, _)| *p == part) {
+            Some((_, keys)) => keys.push(n.key),
+            None => parts.push((part, vec![n.key])),
+        }
+    }
+    parts.into_iter().map(|(_, keys)| keys).collect()
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&e| e <= self.bytes.len())
+            .ok_or("truncated MIDI file")?;
+        let out = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn vlq(&mut self) -> Result<u32, String> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let b = self.u8()?;
+            value = (value << 7) | (b & 0x7F) as u32;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("invalid variable-length quantity".into())
+    }
+}
@@ -0,0 +1,66 @@
+use glam::Vec3;
+
+/// How a voice gets quieter with distance from the listener.
+///
+/// Mirrors the Web Audio `PannerNode` distance models so every frontend
+/// attenuates the same way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DistanceModel {
+    /// Straight line from full level at `ref_distance` to silence-by-rolloff at `max_distance`
+    Linear,
+    /// Natural 1/r falloff beyond `ref_distance`
+    Inverse,
+    /// Steeper (r / ref)^-rolloff falloff, for voices that should feel far away
+    Exponential,
+}
+
+/// Per-voice spatialization, shared by all frontends.
+///
+/// - `fixed_pan`: stereo position (-1 left .. 1 right) that overrides the
+///   voice's position for panning; `None` pans from the position
+/// - `orbit_radius` / `orbit_speed`: circle (engine units, radians per second)
+///   traced around the voice's position by its sound source; radius 0 = static
+/// - `distance_model`, `ref_distance`, `max_distance`, `rolloff`: distance
+///   attenuation preference
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpatialHints {
+    pub fixed_pan: Option<f32>,
+    pub orbit_radius: f32,
+    pub orbit_speed: f32,
+    pub distance_model: DistanceModel,
+    pub ref_distance: f32,
+    pub max_distance: f32,
+    pub rolloff: f32,
+}
+
+impl SpatialHints {
+    /// Pan from the voice position, no orbit, inverse-distance falloff.
+    pub const DEFAULT: SpatialHints = SpatialHints {
+        fixed_pan: None,
+        orbit_radius: 0.0,
+        orbit_speed: 0.0,
+        distance_model: DistanceModel::Inverse,
+        ref_distance: 0.5,
+        max_distance: 50.0,
+        rolloff: 1.0,
+    };
+
+    /// Where the voice's sound source sits at time `t_sec`, given its
+    /// engine-space position.
+    pub fn source_position(&self, position: Vec3, t_sec: f64) -> Vec3 {
+        let base = match self.fixed_pan {
+            // Same distance as the voice, at the pan angle in front of the listener
+            Some(pan) => {
+                let angle = pan.clamp(-1.0, 1.0) * std::f32::consts::FRAC_PI_2;
+                let r = position.length().max(self.ref_distance);
+                Vec3::new(angle.sin() * r, 0.0, -angle.cos() * r)
+            }
+            None => position,
+        };
+        if self.orbit_radius <= 0.0 {
+            return base;
+        }
+        let phase = (t_sec * self.orbit_speed as f64) as f32;
+        base + Vec3::new(phase.cos(), 0.0, phase.sin()) * self.orbit_radius
+    }
+}
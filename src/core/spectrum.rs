@@ -0,0 +1,148 @@
+use super::frame_math::one_pole;
+
+/// Log-spaced bars in the spectrum layer (`SPECTRUM_BANDS` in waves.wgsl).
+pub const SPECTRUM_BANDS: usize = 64;
+
+/// Points along the oscilloscope ribbon (`SCOPE_POINTS` in waves.wgsl).
+pub const SCOPE_POINTS: usize = 128;
+
+/// Frequency range the spectrum bars span.
+pub const SPECTRUM_MIN_HZ: f32 = 30.0;
+pub const SPECTRUM_MAX_HZ: f32 = 16_000.0;
+
+/// Levels mapped to empty and full bars.
+pub const SPECTRUM_FLOOR_DB: f32 = -100.0;
+pub const SPECTRUM_CEIL_DB: f32 = -20.0;
+
+/// How quickly bars fall back once a sound stops (they rise at once).
+pub const SPECTRUM_FALL_TAU_SEC: f32 = 0.12;
+
+/// Optional analyser layers drawn by the waves shader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VisualLayers {
+    /// Log-scaled spectrum bars along the bottom edge
+    pub spectrum: bool,
+    /// Time-domain oscilloscope ribbon across the middle
+    pub scope: bool,
+}
+
+impl VisualLayers {
+    /// Parse a comma list of `spectrum` and `scope` (e.g. `?layers=spectrum,scope`);
+    /// `none` or an empty list turns both off.
+    pub fn parse(s: &str) -> Option<VisualLayers> {
+        let mut layers = VisualLayers::default();
+        for part in s.split(',').map(|p| p.trim().to_ascii_lowercase()) {
+            match part.as_str() {
+                "spectrum" => layers.spectrum = true,
+                "scope" => layers.scope = true,
+                "none" | "" => {}
+                _ => return None,
+            }
+        }
+        Some(layers)
+    }
+
+    pub fn any(&self) -> bool {
+        self.spectrum || self.scope
+    }
+
+    /// Next in the cycle none → spectrum → scope → both.
+    pub fn next(self) -> VisualLayers {
+        match (self.spectrum, self.scope) {
+            (false, false) => VisualLayers {
+                spectrum: true,
+                scope: false,
+            },
+            (true, false) => VisualLayers {
+                spectrum: false,
+                scope: true,
+            },
+            (false, true) => VisualLayers {
+                spectrum: true,
+                scope: true,
+            },
+            (true, true) => VisualLayers::default(),
+        }
+    }
+}
+
+/// Level (0..=1) of each of `out.len()` log-spaced bands between
+/// `SPECTRUM_MIN_HZ` and `SPECTRUM_MAX_HZ`, from a dB spectrum whose bins
+/// are `bin_hz` apart.
+///
+/// Each band takes its loudest bin; low bands narrower than a bin share the
+/// bin they fall in, so the bass isn't left with gaps.
+pub fn log_bands(spectrum_db: &[f32], bin_hz: f32, out: &mut [f32]) {
+    let n = out.len();
+    if spectrum_db.is_empty() || bin_hz <= 0.0 {
+        out.fill(0.0);
+        return;
+    }
+    let ratio = SPECTRUM_MAX_HZ / SPECTRUM_MIN_HZ;
+    let edge = |b: usize| SPECTRUM_MIN_HZ * ratio.powf(b as f32 / n as f32);
+    let last = spectrum_db.len() - 1;
+    for (b, level) in out.iter_mut().enumerate() {
+        let lo = ((edge(b) / bin_hz) as usize).min(last);
+        let hi = ((edge(b + 1) / bin_hz).ceil() as usize).clamp(lo + 1, last + 1);
+        let db = spectrum_db[lo..hi]
+            .iter()
+            .copied()
+            .filter(|db| db.is_finite())
+            .fold(SPECTRUM_FLOOR_DB, f32::max);
+        *level =
+            ((db - SPECTRUM_FLOOR_DB) / (SPECTRUM_CEIL_DB - SPECTRUM_FLOOR_DB)).clamp(0.0, 1.0);
+    }
+}
+
+/// Spectrum bars as drawn: `log_bands` with an instant rise and an eased fall,
+/// so an unsmoothed analyser reads as steady bars rather than flicker.
+#[derive(Clone, Debug)]
+pub struct SpectrumBars {
+    pub bands: [f32; SPECTRUM_BANDS],
+    target: [f32; SPECTRUM_BANDS],
+}
+
+impl Default for SpectrumBars {
+    fn default() -> Self {
+        SpectrumBars {
+            bands: [0.0; SPECTRUM_BANDS],
+            target: [0.0; SPECTRUM_BANDS],
+        }
+    }
+}
+
+impl SpectrumBars {
+    pub fn update(&mut self, spectrum_db: &[f32], bin_hz: f32, dt_sec: f32) {
+        log_bands(spectrum_db, bin_hz, &mut self.target);
+        for (band, &target) in self.bands.iter_mut().zip(&self.target) {
+            *band = if target >= *band {
+                target
+            } else {
+                one_pole(*band, target, dt_sec, SPECTRUM_FALL_TAU_SEC)
+            };
+        }
+    }
+}
+
+/// Resample a time-domain block into `out` for the oscilloscope, starting at
+/// the first rising zero crossing in its first half so a steady tone holds
+/// still instead of scrolling. The window is half the block.
+pub fn scope_trace(samples: &[f32], out: &mut [f32]) {
+    let window = samples.len() / 2;
+    if window < 2 || out.is_empty() {
+        out.fill(0.0);
+        return;
+    }
+    let start = (1..window)
+        .find(|&i| samples[i - 1] < 0.0 && samples[i] >= 0.0)
+        .unwrap_or(0);
+    let step = (window - 1) as f32 / (out.len().max(2) - 1) as f32;
+    for (k, o) in out.iter_mut().enumerate() {
+        let pos = k as f32 * step;
+        let i = pos as usize;
+        let frac = pos - i as f32;
+        let a = samples[start + i];
+        let b = samples[(start + i + 1).min(samples.len() - 1)];
+        *o = (a + (b - a) * frac).clamp(-1.0, 1.0);
+    }
+}
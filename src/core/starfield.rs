@@ -0,0 +1,30 @@
+use glam::Vec3;
+
+/// Starfield brightness when switched on without a level: faint enough to
+/// stay behind the waves.
+pub const DEFAULT_STARS: f32 = 0.5;
+
+/// How far the nearest star layer shifts per world unit the camera moves
+/// off its resting view; farther layers shift proportionally less.
+pub const STAR_PARALLAX: f32 = 0.03;
+
+/// Parse a starfield brightness (e.g. `?stars=0.3`), clamped to 0..=1, or
+/// `"on"` for the default and `"off"` for none.
+pub fn parse_stars(s: &str) -> Option<f32> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("on") {
+        return Some(DEFAULT_STARS);
+    }
+    if s.eq_ignore_ascii_case("off") {
+        return Some(0.0);
+    }
+    let stars: f32 = s.parse().ok()?;
+    stars.is_finite().then(|| stars.clamp(0.0, 1.0))
+}
+
+/// Offset the star layers parallax against (`star_shift` in waves.wgsl):
+/// how far the eye is off the Z axis the resting view sits on, scaled by
+/// `STAR_PARALLAX`.
+pub fn star_shift(eye: Vec3) -> [f32; 2] {
+    [eye.x * STAR_PARALLAX, eye.y * STAR_PARALLAX]
+}
@@ -0,0 +1,31 @@
+/// Widest stereo image offered (side doubled); 0 folds to mono.
+pub const MAX_STEREO_WIDTH: f32 = 2.0;
+
+/// Width the master starts at: a touch wider than the panners alone.
+pub const DEFAULT_STEREO_WIDTH: f32 = 1.15;
+
+/// Extra width at full swirl energy, so stirring opens the image up a little.
+pub const SWIRL_WIDTH_DEPTH: f32 = 0.25;
+
+/// Parse a width (e.g. `?width=1.4`), clamped to `0..=MAX_STEREO_WIDTH`.
+pub fn parse_width(s: &str) -> Option<f32> {
+    let width: f32 = s.trim().parse().ok()?;
+    width
+        .is_finite()
+        .then(|| width.clamp(0.0, MAX_STEREO_WIDTH))
+}
+
+/// Width to run at for a base setting and the current swirl energy (0..=1).
+pub fn swirl_width(base: f32, swirl_energy: f32) -> f32 {
+    (base + SWIRL_WIDTH_DEPTH * swirl_energy.clamp(0.0, 1.0)).clamp(0.0, MAX_STEREO_WIDTH)
+}
+
+/// Mid/side width as a 2×2 matrix on left/right: `(direct, cross)` gains
+/// with `L' = direct·L + cross·R` and `R' = direct·R + cross·L`.
+///
+/// Equivalent to `M = (L+R)/2`, `S = (L−R)/2`, `L' = M + width·S`,
+/// `R' = M − width·S`: the mid (and so the mono fold-down `L'+R'`) is the same
+/// at any width; only the side is scaled.
+pub fn width_gains(width: f32) -> (f32, f32) {
+    ((1.0 + width) / 2.0, (1.0 - width) / 2.0)
+}
@@ -0,0 +1,136 @@
+/// The background plays across two octaves up from middle C, left to right,
+/// for taps and strums alike.
+pub const BACKGROUND_LOW_MIDI: f32 = 60.0;
+pub const BACKGROUND_SPAN_SEMITONES: f32 = 24.0;
+
+/// Pointer speed (CSS pixels a second) a Shift-drag must reach before the
+/// strings it crosses sound, so easing into a strum stays quiet.
+pub const STRUM_MIN_PX_PER_SEC: f32 = 400.0;
+
+/// Longest a single pointer move is spread over, so a stalled event doesn't
+/// smear its notes out.
+pub const STRUM_MAX_MOVE_SEC: f64 = 0.1;
+
+/// Points kept along a strum's streak (`STREAK_POINTS` in waves.wgsl), how
+/// long each stays lit, and the longest gap between points still joined.
+pub const STREAK_POINTS: usize = 24;
+pub const STREAK_LIFETIME_SEC: f32 = 0.6;
+pub const STREAK_JOIN_SEC: f32 = 0.12;
+
+/// Pitch played at `uvx` across the background (0 at the left edge).
+pub fn background_midi(uvx: f32) -> f32 {
+    BACKGROUND_LOW_MIDI + uvx * BACKGROUND_SPAN_SEMITONES
+}
+
+/// Where across the background `midi` is played (`background_midi` undone).
+pub fn background_uvx(midi: f32) -> f32 {
+    (midi - BACKGROUND_LOW_MIDI) / BACKGROUND_SPAN_SEMITONES
+}
+
+/// The "strings" a strum can cross: every pitch of `scale` on `root_midi`
+/// within the background's range, low to high.
+pub fn strum_strings(scale: &[f32], root_midi: i32) -> Vec<f32> {
+    let lo = BACKGROUND_LOW_MIDI;
+    let hi = lo + BACKGROUND_SPAN_SEMITONES;
+    let mut strings: Vec<f32> = (-3..=3)
+        .flat_map(|octave| {
+            scale
+                .iter()
+                .filter(|&&degree| degree < 12.0)
+                .map(move |&degree| root_midi as f32 + 12.0 * octave as f32 + degree)
+        })
+        .filter(|&midi| (lo..=hi).contains(&midi))
+        .collect();
+    strings.sort_by(f32::total_cmp);
+    strings.dedup();
+    strings
+}
+
+/// One Shift-drag across the background, tracking where and when it last
+/// was so each move reports the strings it crossed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Strum {
+    last: Option<(f32, f64)>,
+    crossed_any: bool,
+}
+
+impl Strum {
+    pub fn start(&mut self, uvx: f32, now_sec: f64) {
+        self.last = Some((background_midi(uvx), now_sec));
+        self.crossed_any = false;
+    }
+
+    /// End the strum; true if it crossed any strings, false if it was only
+    /// a Shift-click.
+    pub fn stop(&mut self) -> bool {
+        self.last = None;
+        std::mem::take(&mut self.crossed_any)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.last.is_some()
+    }
+
+    /// Move to `uvx` at `now_sec`: the strings passed on the way, in the
+    /// order they were crossed, each with how long after now to play it so
+    /// the notes keep the spacing the pointer crossed them with (faster
+    /// strums play closer together). A string exactly where the last move
+    /// ended isn't played twice.
+    pub fn sweep(&mut self, uvx: f32, now_sec: f64, strings: &[f32]) -> Vec<(f32, f64)> {
+        let Some((from, at)) = self.last else {
+            return Vec::new();
+        };
+        let to = background_midi(uvx);
+        self.last = Some((to, now_sec));
+        let span = to - from;
+        let move_sec = (now_sec - at).clamp(0.0, STRUM_MAX_MOVE_SEC);
+        let mut crossed: Vec<(f32, f64)> = strings
+            .iter()
+            .filter(|&&s| {
+                if span > 0.0 {
+                    s > from && s <= to
+                } else {
+                    s < from && s >= to
+                }
+            })
+            .map(|&s| (s, ((s - from) / span) as f64 * move_sec))
+            .collect();
+        crossed.sort_by(|a, b| a.1.total_cmp(&b.1));
+        self.crossed_any |= !crossed.is_empty();
+        crossed
+    }
+}
+
+/// The path of recent strums, drawn as a fading streak: points in the order
+/// they were added, oldest overwritten first.
+#[derive(Clone, Debug, Default)]
+pub struct Streak {
+    points: [[f32; 4]; STREAK_POINTS],
+    next: usize,
+}
+
+impl Streak {
+    /// Add `uv` (screen uv) at render time `now`; it continues the streak if
+    /// the last point came less than `STREAK_JOIN_SEC` before, otherwise it
+    /// starts a new one.
+    pub fn push(&mut self, uv: [f32; 2], now: f32) {
+        let last = self.points[(self.next + STREAK_POINTS - 1) % STREAK_POINTS];
+        let joined = last[3] > 0.0 && now - last[2] < STREAK_JOIN_SEC;
+        self.points[self.next] = [uv[0], uv[1], now, if joined { 2.0 } else { 1.0 }];
+        self.next = (self.next + 1) % STREAK_POINTS;
+    }
+
+    /// Points as the shader reads them, oldest first: uv, time added, and 2
+    /// for a point joined to the one before or 1 for the start of a streak
+    /// (expired and unused points zeroed).
+    pub fn pack(&self, now: f32) -> [[f32; 4]; STREAK_POINTS] {
+        std::array::from_fn(|i| {
+            let p = self.points[(self.next + i) % STREAK_POINTS];
+            if p[3] > 0.0 && now - p[2] < STREAK_LIFETIME_SEC {
+                p
+            } else {
+                [0.0; 4]
+            }
+        })
+    }
+}
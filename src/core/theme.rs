@@ -0,0 +1,117 @@
+/// A colour scheme for every render layer (linear RGB; values above 1 glow
+/// through the bloom).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    /// Short identifier used in URLs and storage
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Background gradient: the deep clear colour and the lifted tone lit
+    /// wave faces reach
+    pub background: [[f32; 3]; 2],
+    /// Bass, lead and pad colours (scene billboards; the waves shade from
+    /// the bass colour in their troughs to the pad colour on their peaks)
+    pub voices: [[f32; 3]; 3],
+    /// Crests, stripes, rings and grain in the waves
+    pub accent: [f32; 3],
+    /// Colour the bloom is multiplied by
+    pub bloom_tint: [f32; 3],
+    /// Final saturation (1 = as rendered, 0 = greyscale)
+    pub saturation: f32,
+}
+
+impl Theme {
+    /// Look a built-in up by id, ignoring case.
+    pub fn from_id(id: &str) -> Option<&'static Theme> {
+        THEMES.iter().find(|t| t.id.eq_ignore_ascii_case(id.trim()))
+    }
+
+    /// The built-in after this one, wrapping round.
+    pub fn next(&self) -> &'static Theme {
+        let i = THEMES.iter().position(|t| t.id == self.id).unwrap_or(0);
+        &THEMES[(i + 1) % THEMES.len()]
+    }
+}
+
+/// Deep blue with cool, warm and gold voices (the original look).
+pub const NEBULA: Theme = Theme {
+    id: "nebula",
+    name: "Nebula",
+    background: [[0.03, 0.04, 0.08], [0.12, 0.14, 0.26]],
+    voices: [[0.18, 0.45, 1.05], [1.08, 0.50, 0.40], [1.00, 0.86, 0.46]],
+    accent: [1.00, 0.86, 0.46],
+    bloom_tint: [1.0, 1.0, 1.0],
+    saturation: 0.9,
+};
+
+/// Smouldering reds and oranges over near-black.
+pub const EMBER: Theme = Theme {
+    id: "ember",
+    name: "Ember",
+    background: [[0.05, 0.02, 0.02], [0.26, 0.09, 0.05]],
+    voices: [[0.90, 0.22, 0.08], [1.15, 0.55, 0.12], [1.05, 0.85, 0.35]],
+    accent: [1.10, 0.62, 0.20],
+    bloom_tint: [1.1, 0.8, 0.6],
+    saturation: 1.0,
+};
+
+/// Greys only.
+pub const MONO: Theme = Theme {
+    id: "mono",
+    name: "Mono",
+    background: [[0.03, 0.03, 0.03], [0.16, 0.16, 0.16]],
+    voices: [[0.55, 0.55, 0.55], [0.85, 0.85, 0.85], [1.10, 1.10, 1.10]],
+    accent: [1.0, 1.0, 1.0],
+    bloom_tint: [1.0, 1.0, 1.0],
+    saturation: 0.0,
+};
+
+/// Black background with saturated, widely separated voice colours.
+pub const HIGH_CONTRAST: Theme = Theme {
+    id: "contrast",
+    name: "High contrast",
+    background: [[0.0, 0.0, 0.0], [0.08, 0.08, 0.10]],
+    voices: [[0.0, 0.85, 1.30], [1.40, 0.10, 0.75], [1.40, 1.20, 0.0]],
+    accent: [1.30, 1.30, 1.30],
+    bloom_tint: [0.8, 0.8, 0.8],
+    saturation: 1.1,
+};
+
+/// Navy with blue, orange and pale-yellow voices, told apart by lightness as
+/// well as hue so they stay distinct with deuteranopia and protanopia.
+pub const HARBOUR: Theme = Theme {
+    id: "harbour",
+    name: "Harbour (colour-blind safe)",
+    background: [[0.02, 0.03, 0.06], [0.10, 0.13, 0.22]],
+    voices: [[0.02, 0.22, 0.95], [1.10, 0.42, 0.0], [0.95, 0.95, 0.80]],
+    accent: [1.00, 0.80, 0.35],
+    bloom_tint: [0.9, 0.95, 1.1],
+    saturation: 1.0,
+};
+
+/// The Okabe–Ito sky blue, orange and yellow over charcoal.
+pub const OKABE_ITO: Theme = Theme {
+    id: "okabe-ito",
+    name: "Okabe–Ito (colour-blind safe)",
+    background: [[0.02, 0.02, 0.03], [0.12, 0.12, 0.14]],
+    voices: [[0.10, 0.50, 0.90], [0.95, 0.38, 0.0], [0.95, 0.85, 0.06]],
+    accent: [1.00, 0.90, 0.60],
+    bloom_tint: [1.0, 1.0, 1.0],
+    saturation: 1.0,
+};
+
+/// Built-in themes in the order the key cycles through them.
+pub const THEMES: [Theme; 6] = [NEBULA, EMBER, MONO, HIGH_CONTRAST, HARBOUR, OKABE_ITO];
+
+/// Brightest a pixel is shown with the safety limits on (linear luminance,
+/// where 1 is full white).
+pub const SAFE_PEAK_LUMA: f32 = 0.7;
+
+/// With the safety limits on, how far above the level it has settled at the
+/// average brightness may rise at once; beats, hits and ripples are held
+/// under it rather than flashing.
+pub const SAFE_FLASH_RATIO: f32 = 1.25;
+
+/// How fast that settled level follows the picture (1/e time is the
+/// inverse), so a brighter scene is let in over a second or two instead of
+/// flashing.
+pub const SAFE_SETTLE_PER_SEC: f32 = 1.0;
@@ -0,0 +1,114 @@
+/// Widest manual exposure offered, in stops either way.
+pub const MAX_EXPOSURE_EV: f32 = 3.0;
+
+/// Average scene luminance auto-exposure steers towards (middle grey).
+pub const AUTO_EXPOSURE_KEY: f32 = 0.18;
+
+/// Limits on the auto-exposure gain, so a black screen isn't lifted into
+/// noise and a flash doesn't crush everything else.
+pub const AUTO_EXPOSURE_MIN_GAIN: f32 = 0.25;
+pub const AUTO_EXPOSURE_MAX_GAIN: f32 = 2.5;
+
+/// How fast auto-exposure follows the scene (1/e time is the inverse).
+pub const AUTO_EXPOSURE_ADAPT_PER_SEC: f32 = 1.5;
+
+/// Curve mapping the HDR image onto the display in the composite pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tonemap {
+    /// Filmic, with a soft shoulder (the original look)
+    #[default]
+    Aces,
+    /// `x / (1 + x)`: flatter, never fully white
+    Reinhard,
+    /// Clip at 1, for displays or captures that do their own mapping
+    None,
+}
+
+impl Tonemap {
+    pub const ALL: [Tonemap; 3] = [Tonemap::Aces, Tonemap::Reinhard, Tonemap::None];
+
+    /// Short identifier used in URLs
+    pub fn id(self) -> &'static str {
+        match self {
+            Tonemap::Aces => "aces",
+            Tonemap::Reinhard => "reinhard",
+            Tonemap::None => "none",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tonemap::Aces => "ACES filmic",
+            Tonemap::Reinhard => "Reinhard",
+            Tonemap::None => "None (clip)",
+        }
+    }
+
+    /// Operator number the composite switches on (`TONEMAP_*` in post.wgsl)
+    pub fn shader_index(self) -> u32 {
+        match self {
+            Tonemap::Aces => 0,
+            Tonemap::Reinhard => 1,
+            Tonemap::None => 2,
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Tonemap> {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.id().eq_ignore_ascii_case(id.trim()))
+    }
+}
+
+/// Exposure ahead of the tonemap: a manual offset in stops, optionally on
+/// top of auto-exposure from the average HDR luminance.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Exposure {
+    /// Stops, `-MAX_EXPOSURE_EV..=MAX_EXPOSURE_EV`
+    pub ev: f32,
+    pub auto: bool,
+}
+
+impl Exposure {
+    /// Parse `"<ev>"`, `"auto"` or `"auto,<ev>"` (e.g. `?exposure=auto,-0.5`).
+    pub fn parse(s: &str) -> Option<Exposure> {
+        let mut parts = s.split(',').map(str::trim);
+        let first = parts.next()?;
+        let (auto, ev) = if first.eq_ignore_ascii_case("auto") {
+            (true, parts.next())
+        } else {
+            (false, Some(first))
+        };
+        let ev = match ev {
+            Some(ev) => parse_ev(ev)?,
+            None => 0.0,
+        };
+        parts.next().is_none().then_some(Exposure { ev, auto })
+    }
+
+    /// `parse`'s form of these settings.
+    pub fn param(&self) -> String {
+        if self.auto {
+            format!("auto,{}", self.ev)
+        } else {
+            self.ev.to_string()
+        }
+    }
+
+    pub fn with_ev(self, ev: f32) -> Exposure {
+        Exposure {
+            ev: ev.clamp(-MAX_EXPOSURE_EV, MAX_EXPOSURE_EV),
+            ..self
+        }
+    }
+
+    /// Linear gain of the manual offset.
+    pub fn gain(&self) -> f32 {
+        self.ev.exp2()
+    }
+}
+
+fn parse_ev(s: &str) -> Option<f32> {
+    let ev: f32 = s.parse().ok().filter(|v: &f32| v.is_finite())?;
+    Some(ev.clamp(-MAX_EXPOSURE_EV, MAX_EXPOSURE_EV))
+}
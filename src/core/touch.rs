@@ -0,0 +1,261 @@
+use glam::Vec3;
+
+/// CSS pixels a pointer may wander before a press on a voice becomes a drag,
+/// so a finger's wobble still counts as a tap.
+pub const TAP_SLOP_PX: f32 = 10.0;
+
+/// Seconds a press must be held still to count as a long press.
+pub const LONG_PRESS_SEC: f64 = 0.5;
+
+/// Seconds within which a second tap on the same voice makes a double tap.
+pub const DOUBLE_TAP_SEC: f64 = 0.3;
+
+/// How long presses take to become long presses and double taps, adjustable
+/// for players who need more (or less) time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PressTimings {
+    pub long_press_sec: f64,
+    pub double_tap_sec: f64,
+}
+
+impl Default for PressTimings {
+    fn default() -> Self {
+        Self {
+            long_press_sec: LONG_PRESS_SEC,
+            double_tap_sec: DOUBLE_TAP_SEC,
+        }
+    }
+}
+
+impl PressTimings {
+    /// Parse `"long press ms,double tap ms"` (e.g. from a URL); the double
+    /// tap window may be left out. Both must be between 50 ms and 5 s.
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.split(',').map(|p| p.trim().parse::<f64>().ok());
+        let ms = |v: f64| ((50.0..=5000.0).contains(&v)).then_some(v / 1000.0);
+        let long_press_sec = ms(parts.next()??)?;
+        let double_tap_sec = match parts.next() {
+            Some(v) => ms(v?)?,
+            None => DOUBLE_TAP_SEC,
+        };
+        Some(Self {
+            long_press_sec,
+            double_tap_sec,
+        })
+    }
+}
+
+/// How much larger a voice's hit sphere is for a finger than for a mouse.
+pub const TOUCH_PICK_SCALE: f32 = 1.8;
+
+/// What a press on a voice turned out to be once released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PressKind {
+    /// Let go quickly without moving: mute (or solo/reseed with Alt/Shift)
+    Tap,
+    /// Held still for the long-press time: the voice's context action
+    /// (cycle its waveform, or lock it with Shift), as a right-click is
+    LongPress,
+    /// Moved past `TAP_SLOP_PX`: the voice was dragged
+    Drag,
+}
+
+/// One pointer (mouse, pen or finger) pressed on a voice.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoicePress {
+    pub pointer_id: i32,
+    pub voice: usize,
+    /// Where the press began, in CSS pixels
+    pub start_px: [f32; 2],
+    pub start_sec: f64,
+    /// Engine-space position when pressed (for undo)
+    pub origin: Vec3,
+    /// World-space depth of the plane the voice is dragged across
+    pub plane_z_world: f32,
+    pub dragging: bool,
+}
+
+impl VoicePress {
+    /// Note the pointer at `px` (CSS pixels); once past `TAP_SLOP_PX` the
+    /// press is a drag for good. Returns whether it is one.
+    pub fn moved_to(&mut self, px: [f32; 2]) -> bool {
+        let (dx, dy) = (px[0] - self.start_px[0], px[1] - self.start_px[1]);
+        self.dragging |= dx * dx + dy * dy > TAP_SLOP_PX * TAP_SLOP_PX;
+        self.dragging
+    }
+
+    /// What the press is if let go at `now_sec`.
+    pub fn kind(&self, now_sec: f64, timings: &PressTimings) -> PressKind {
+        if self.dragging {
+            PressKind::Drag
+        } else if now_sec - self.start_sec >= timings.long_press_sec {
+            PressKind::LongPress
+        } else {
+            PressKind::Tap
+        }
+    }
+}
+
+/// Voices pressed at once, by any number of pointers, so two fingers can
+/// drag two voices. A voice is held by one pointer at a time.
+#[derive(Clone, Debug, Default)]
+pub struct VoicePresses {
+    presses: Vec<VoicePress>,
+}
+
+impl VoicePresses {
+    /// Start `press`; false (and ignored) if its voice or pointer already
+    /// has one.
+    pub fn press(&mut self, press: VoicePress) -> bool {
+        let taken = self
+            .presses
+            .iter()
+            .any(|p| p.voice == press.voice || p.pointer_id == press.pointer_id);
+        if !taken {
+            self.presses.push(press);
+        }
+        !taken
+    }
+
+    /// Whether some pointer holds `voice`.
+    pub fn holds(&self, voice: usize) -> bool {
+        self.presses.iter().any(|p| p.voice == voice)
+    }
+
+    pub fn get_mut(&mut self, pointer_id: i32) -> Option<&mut VoicePress> {
+        self.presses.iter_mut().find(|p| p.pointer_id == pointer_id)
+    }
+
+    /// End the press by `pointer_id`, if it has one.
+    pub fn release(&mut self, pointer_id: i32) -> Option<VoicePress> {
+        let i = self
+            .presses
+            .iter()
+            .position(|p| p.pointer_id == pointer_id)?;
+        Some(self.presses.swap_remove(i))
+    }
+}
+
+/// A tap that stays a single tap only if no second tap on the same voice
+/// follows within the double-tap window, so a double tap never mutes first.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TapTracker {
+    pending: Option<(usize, f64)>,
+    serial: u32,
+}
+
+/// What a tap on a voice turned out to be.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TapOutcome {
+    /// Waiting for a possible second tap; `TapTracker::expire` with this
+    /// serial once the window has passed says whether it stayed single
+    Pending(u32),
+    /// The second tap on the same voice in time: reseed
+    Double,
+}
+
+impl TapTracker {
+    /// Note a tap on `voice` at `now_sec`.
+    pub fn tap(&mut self, voice: usize, now_sec: f64, timings: &PressTimings) -> TapOutcome {
+        match self.pending.take() {
+            Some((v, at)) if v == voice && now_sec - at <= timings.double_tap_sec => {
+                TapOutcome::Double
+            }
+            _ => {
+                self.serial = self.serial.wrapping_add(1);
+                self.pending = Some((voice, now_sec));
+                TapOutcome::Pending(self.serial)
+            }
+        }
+    }
+
+    /// The window for tap `serial` has passed: the voice it was on if it is
+    /// still a single tap (not doubled, nor replaced by a later tap).
+    pub fn expire(&mut self, serial: u32) -> Option<usize> {
+        if serial != self.serial {
+            return None;
+        }
+        self.pending.take().map(|(voice, _)| voice)
+    }
+}
+
+/// Pointer speed (CSS pixels a second) at which a background tap is as hard
+/// as speed alone can make it.
+pub const TAP_FAST_PX_PER_SEC: f32 = 2000.0;
+
+/// Seconds for a pointer's speed to fall to about a third once it stops.
+const SPEED_DECAY_SEC: f64 = 0.08;
+
+/// Darkest and brightest lowpass on a background tap's one-shot.
+pub const TAP_CUTOFF_MIN_HZ: f32 = 1200.0;
+pub const TAP_CUTOFF_MAX_HZ: f32 = 12000.0;
+
+/// How fast a pointer is moving, smoothed over its last few moves and
+/// fading once it comes to rest.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PointerSpeed {
+    last: Option<([f32; 2], f64)>,
+    px_per_sec: f32,
+}
+
+impl PointerSpeed {
+    /// The pointer reached `px` (CSS pixels) at `now_sec`.
+    pub fn moved(&mut self, px: [f32; 2], now_sec: f64) {
+        if let Some((from, at)) = self.last {
+            let dt = now_sec - at;
+            if dt > 0.0 {
+                let dist = ((px[0] - from[0]).powi(2) + (px[1] - from[1]).powi(2)).sqrt();
+                let speed = dist / dt as f32;
+                // Weigh the newest move by how much time it covers
+                let keep = (-dt / SPEED_DECAY_SEC).exp() as f32;
+                self.px_per_sec = self.px_per_sec * keep + speed * (1.0 - keep);
+            }
+        }
+        self.last = Some((px, now_sec));
+    }
+
+    /// Speed at `now_sec`, decayed for the time since the last move.
+    pub fn speed(&self, now_sec: f64) -> f32 {
+        match self.last {
+            Some((_, at)) => {
+                self.px_per_sec * (-(now_sec - at).max(0.0) / SPEED_DECAY_SEC).exp() as f32
+            }
+            None => 0.0,
+        }
+    }
+}
+
+/// How hard a background tap lands: louder, brighter and with a bigger
+/// ripple the faster the pointer was moving and the harder it pressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TapExpression {
+    pub velocity: f32,
+    /// Lowpass cutoff on the one-shot
+    pub cutoff_hz: f32,
+    pub ripple_amp: f32,
+}
+
+impl TapExpression {
+    /// From the tap's height on screen (`uvy`, 0 at the top), the pointer's
+    /// speed and its pressure where the device reports one (`None` for a
+    /// mouse, counted as the 0.5 a pressed button reports).
+    pub fn new(uvy: f32, speed_px_per_sec: f32, pressure: Option<f32>) -> Self {
+        let speed = (speed_px_per_sec / TAP_FAST_PX_PER_SEC).clamp(0.0, 1.0);
+        let pressure = pressure.unwrap_or(0.5).clamp(0.0, 1.0);
+        let base = 0.35 + 0.65 * uvy.clamp(0.0, 1.0);
+        let push = 0.5 * speed + (pressure - 0.5);
+        let velocity = (base * (1.0 + 0.6 * push)).clamp(0.05, 1.0);
+        let bright = (0.5 + 0.5 * speed + (pressure - 0.5)).clamp(0.0, 1.0);
+        Self {
+            velocity,
+            cutoff_hz: TAP_CUTOFF_MIN_HZ * (TAP_CUTOFF_MAX_HZ / TAP_CUTOFF_MIN_HZ).powf(bright),
+            ripple_amp: velocity * (1.0 + speed),
+        }
+    }
+}
+
+/// The pressure a pointer reports, if it really measures one: a mouse only
+/// says 0.5 while a button is down, and 0 means nothing was measured.
+pub fn measured_pressure(pointer_type: &str, pressure: f32) -> Option<f32> {
+    (pointer_type != "mouse" && pressure > 0.0).then_some(pressure)
+}
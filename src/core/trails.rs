@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+/// Points per voice trail, the voice itself included (`TRAIL_POINTS` in
+/// waves.wgsl).
+pub const TRAIL_POINTS: usize = 16;
+
+/// Seconds a trail point takes to fade out, unless the voice's style says
+/// otherwise (see `VoiceTrail::set_fade_sec`).
+pub const TRAIL_FADE_SEC: f32 = 2.0;
+
+/// Distance (engine units) a voice must move before another point is laid,
+/// so a still voice leaves nothing and a fast drag still spans the trail.
+pub const TRAIL_MIN_STEP: f32 = 0.03;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrailPoint {
+    pub position: [f32; 3],
+    pub age_sec: f32,
+}
+
+/// Recent positions of one voice, newest first, for the luminous streak it
+/// leaves when dragged or otherwise moved.
+#[derive(Clone, Debug)]
+pub struct VoiceTrail {
+    points: VecDeque<TrailPoint>,
+    /// Where the voice was when the last point was laid (or first seen)
+    anchor: Option<[f32; 3]>,
+    fade_sec: f32,
+}
+
+impl Default for VoiceTrail {
+    fn default() -> Self {
+        Self {
+            points: VecDeque::new(),
+            anchor: None,
+            fade_sec: TRAIL_FADE_SEC,
+        }
+    }
+}
+
+impl VoiceTrail {
+    /// Seconds each point takes to fade from now on (a long wake for a heavy
+    /// voice, a short flick for a quick one).
+    pub fn set_fade_sec(&mut self, sec: f32) {
+        self.fade_sec = sec.max(0.01);
+    }
+
+    /// Age the trail by `dt_sec` and lay a point where the voice was if it
+    /// has since moved far enough.
+    pub fn update(&mut self, position: [f32; 3], dt_sec: f32) {
+        for p in &mut self.points {
+            p.age_sec += dt_sec.max(0.0);
+        }
+        let fade_sec = self.fade_sec;
+        self.points.retain(|p| p.age_sec < fade_sec);
+        let Some(anchor) = self.anchor else {
+            self.anchor = Some(position);
+            return;
+        };
+        let moved = anchor
+            .iter()
+            .zip(&position)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f32>()
+            .sqrt();
+        if moved >= TRAIL_MIN_STEP {
+            self.points.push_front(TrailPoint {
+                position: anchor,
+                age_sec: 0.0,
+            });
+            self.points.truncate(TRAIL_POINTS - 1);
+            self.anchor = Some(position);
+        }
+    }
+
+    /// Trail as the shader reads it: the voice at `head` first, then the laid
+    /// points newest first, each as xyz position and w = remaining brightness
+    /// (1 fresh .. 0 gone). A trail with no points is all zero, including the
+    /// head, so nothing is drawn.
+    pub fn pack(&self, head: [f32; 3], out: &mut [[f32; 4]]) {
+        out.fill([0.0; 4]);
+        if self.points.is_empty() {
+            return;
+        }
+        let head = std::iter::once([head[0], head[1], head[2], 1.0]);
+        let laid = self.points.iter().map(|p| {
+            let life = 1.0 - p.age_sec / self.fade_sec;
+            let [x, y, z] = p.position;
+            [x, y, z, life.clamp(0.0, 1.0)]
+        });
+        for (slot, point) in out.iter_mut().zip(head.chain(laid)) {
+            *slot = point;
+        }
+    }
+}
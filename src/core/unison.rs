@@ -0,0 +1,58 @@
+/// Stack of detuned copies of each note (supersaw-style thickening).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Unison {
+    /// Oscillators per note (1 = no unison)
+    pub voices: u32,
+    /// Distance in cents between the lowest and highest copy
+    pub detune_cents: f32,
+    /// How far apart the copies sit in the stereo field (0 = all centered,
+    /// 1 = outermost copies hard left/right)
+    pub stereo_spread: f32,
+}
+
+/// One oscillator of a unison stack.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnisonLayer {
+    pub detune_cents: f32,
+    /// Stereo position, -1 (left) to 1 (right)
+    pub pan: f32,
+    /// Start phase (0..1), staggered so the copies don't begin in lockstep
+    pub phase: f32,
+}
+
+impl Unison {
+    /// A single centered oscillator.
+    pub const OFF: Unison = Unison {
+        voices: 1,
+        detune_cents: 0.0,
+        stereo_spread: 0.0,
+    };
+
+    /// Copies spread evenly across the detune range, lowest on the left.
+    pub fn layers(&self) -> Vec<UnisonLayer> {
+        let n = self.voices.max(1);
+        if n == 1 {
+            return vec![UnisonLayer {
+                detune_cents: 0.0,
+                pan: 0.0,
+                phase: 0.0,
+            }];
+        }
+        let spread = self.stereo_spread.clamp(0.0, 1.0);
+        (0..n)
+            .map(|i| {
+                let x = 2.0 * i as f32 / (n - 1) as f32 - 1.0;
+                UnisonLayer {
+                    detune_cents: x * self.detune_cents * 0.5,
+                    pan: x * spread,
+                    phase: i as f32 / n as f32,
+                }
+            })
+            .collect()
+    }
+
+    /// Per-copy gain keeping the stack about as loud as a single oscillator.
+    pub fn layer_gain(&self) -> f32 {
+        1.0 / (self.voices.max(1) as f32).sqrt()
+    }
+}
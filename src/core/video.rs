@@ -0,0 +1,135 @@
+use super::bounce::{schedule_piece, ScheduledNote};
+use super::frame_math::{smooth_pulses, MAX_PULSE_ENERGY};
+use super::music::MusicEngine;
+use super::musical_clock::{ClockPulse, MusicalClock};
+
+/// Frame rate of a video export when none is asked for.
+pub const DEFAULT_VIDEO_FPS: u32 = 30;
+
+/// Highest frame rate accepted.
+pub const MAX_VIDEO_FPS: u32 = 60;
+
+/// Recorder formats in order of preference; the first the browser can write
+/// is used.
+pub const VIDEO_MIME_TYPES: [&str; 3] = [
+    "video/webm;codecs=vp9,opus",
+    "video/webm;codecs=vp8,opus",
+    "video/webm",
+];
+
+/// Parse an export frame rate (e.g. `?fps=60`), clamped to `MAX_VIDEO_FPS`.
+pub fn parse_video_fps(s: &str) -> Option<u32> {
+    let fps: u32 = s.trim().parse().ok()?;
+    (fps > 0).then(|| fps.min(MAX_VIDEO_FPS))
+}
+
+/// Everything the renderer takes from the music for one exported frame.
+#[derive(Clone, Debug)]
+pub struct VideoFrame {
+    pub index: u32,
+    /// Piece time since the previous frame handed out (a whole number of
+    /// frame steps)
+    pub dt_sec: f32,
+    pub pulses: [f32; 3],
+    pub clock: ClockPulse,
+    /// Notes heard since the previous frame handed out, for particle bursts
+    pub bursts: Vec<ScheduledNote>,
+}
+
+/// A piece's visuals stepped at a fixed frame rate.
+///
+/// The notes and beats come from `schedule_piece`, as in the offline bounce,
+/// and pulses and the beat clock are stepped exactly as the live frame loop
+/// steps them, but `1 / fps` at a time; so a seed gives the same frames on
+/// every export, and they line up with its bounce.
+#[derive(Clone, Debug)]
+pub struct FrameSequence {
+    fps: u32,
+    frame_count: u32,
+    // By start time
+    notes: Vec<ScheduledNote>,
+    next_note: usize,
+    clock: MusicalClock,
+    pulses: [f32; 3],
+    pulse_energy: [f32; 3],
+    // Frames stepped so far
+    stepped: u32,
+}
+
+impl FrameSequence {
+    /// Schedule `seconds` of `engine` (scheduling `step_sec` at a time, as
+    /// the bounce does) for frames at `fps`. The engine is advanced, so pass
+    /// a clone.
+    pub fn new(engine: &mut MusicEngine, seconds: f64, step_sec: f64, fps: u32) -> Self {
+        let fps = fps.clamp(1, MAX_VIDEO_FPS);
+        // Beats left from live playback are stamped on the live clock
+        engine.drain_beats();
+        let mut notes = schedule_piece(engine, seconds, step_sec);
+        notes.sort_by(|a, b| a.event.start_time_sec.total_cmp(&b.event.start_time_sec));
+        let mut clock = MusicalClock::default();
+        clock.queue(engine.drain_beats());
+        Self {
+            fps,
+            frame_count: (seconds * fps as f64).ceil() as u32,
+            notes,
+            next_note: 0,
+            clock,
+            pulses: [0.0; 3],
+            pulse_energy: [0.0; 3],
+            stepped: 0,
+        }
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Whether every frame has been handed out.
+    pub fn finished(&self) -> bool {
+        self.stepped >= self.frame_count
+    }
+
+    /// Step every frame due `elapsed_sec` into the piece and return the
+    /// latest, or `None` if no new frame is due yet. Frames skipped over
+    /// still count their notes, so a slow renderer drops frames rather than
+    /// drifting from the audio.
+    pub fn advance(&mut self, elapsed_sec: f64) -> Option<VideoFrame> {
+        let due =
+            ((elapsed_sec.max(0.0) * self.fps as f64).floor() as u32 + 1).min(self.frame_count);
+        if due <= self.stepped {
+            return None;
+        }
+        let step = 1.0 / self.fps as f32;
+        let first = self.stepped;
+        let mut bursts = Vec::new();
+        let mut at = 0.0;
+        while self.stepped < due {
+            at = self.stepped as f64 / self.fps as f64;
+            while let Some(note) = self
+                .notes
+                .get(self.next_note)
+                .filter(|n| n.event.start_time_sec <= at)
+            {
+                if let Some(energy) = self.pulse_energy.get_mut(note.event.voice_index) {
+                    *energy = (*energy + note.event.velocity).min(MAX_PULSE_ENERGY);
+                }
+                bursts.push(note.clone());
+                self.next_note += 1;
+            }
+            smooth_pulses(&mut self.pulses, &mut self.pulse_energy, step);
+            self.stepped += 1;
+        }
+        self.clock.advance(at);
+        Some(VideoFrame {
+            index: due - 1,
+            dt_sec: (due - first) as f32 * step,
+            pulses: self.pulses,
+            clock: self.clock.sample(at),
+            bursts,
+        })
+    }
+}
@@ -0,0 +1,142 @@
+/// Seconds a switch between visualizers takes to crossfade.
+pub const VISUALIZER_FADE_SEC: f32 = 0.8;
+
+/// Fullscreen visualizers. Every mode is a fragment entry point in the waves
+/// module (waves.wgsl and the custom code) sharing its uniforms and analyser
+/// bindings, so switching is only a change of pipeline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Visualizer {
+    /// Layered velvet waves with golden crests
+    #[default]
+    Waves,
+    /// Drifting sparks that gather round the voices
+    Particles,
+    /// Rings rushing towards the viewer, bent by the voices
+    Tunnel,
+    /// The waves folded into six mirrored wedges
+    Kaleidoscope,
+    /// User-supplied WGSL (see src/core/custom_shader.rs)
+    Custom,
+}
+
+impl Visualizer {
+    pub const ALL: [Visualizer; 5] = [
+        Visualizer::Waves,
+        Visualizer::Particles,
+        Visualizer::Tunnel,
+        Visualizer::Kaleidoscope,
+        Visualizer::Custom,
+    ];
+
+    /// Short identifier used in URLs
+    pub fn id(self) -> &'static str {
+        match self {
+            Visualizer::Waves => "waves",
+            Visualizer::Particles => "particles",
+            Visualizer::Tunnel => "tunnel",
+            Visualizer::Kaleidoscope => "kaleidoscope",
+            Visualizer::Custom => "custom",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Visualizer::Waves => "Waves",
+            Visualizer::Particles => "Particle field",
+            Visualizer::Tunnel => "Tunnel",
+            Visualizer::Kaleidoscope => "Kaleidoscope",
+            Visualizer::Custom => "Custom shader",
+        }
+    }
+
+    /// Fragment entry point in waves.wgsl (`Custom`'s is appended after the
+    /// custom code)
+    pub fn entry_point(self) -> &'static str {
+        match self {
+            Visualizer::Waves => "fs_waves",
+            Visualizer::Particles => "fs_particles",
+            Visualizer::Tunnel => "fs_tunnel",
+            Visualizer::Kaleidoscope => "fs_kaleidoscope",
+            Visualizer::Custom => "fs_custom",
+        }
+    }
+
+    /// Position in `ALL`
+    pub fn index(self) -> usize {
+        Self::ALL.iter().position(|v| *v == self).unwrap_or(0)
+    }
+
+    pub fn from_id(id: &str) -> Option<Visualizer> {
+        Self::ALL
+            .into_iter()
+            .find(|v| v.id().eq_ignore_ascii_case(id.trim()))
+    }
+
+    pub fn next(self) -> Visualizer {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+}
+
+/// The visualizer on screen and, while a switch is under way, the one fading
+/// out beneath it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crossfade {
+    pub current: Visualizer,
+    /// Mode fading out, until the fade completes
+    pub previous: Option<Visualizer>,
+    /// Linear progress through the fade, 0..=1
+    progress: f32,
+}
+
+impl Crossfade {
+    pub fn new(visualizer: Visualizer) -> Crossfade {
+        Crossfade {
+            current: visualizer,
+            previous: None,
+            progress: 1.0,
+        }
+    }
+
+    /// Start fading to `to`. Switching mid-fade fades out whichever mode is
+    /// more visible at that moment; switching back to the outgoing mode
+    /// reverses the fade from where it is.
+    pub fn switch(&mut self, to: Visualizer) {
+        if to == self.current {
+            return;
+        }
+        match self.previous {
+            Some(previous) if previous == to => {
+                self.progress = 1.0 - self.progress;
+                self.previous = Some(self.current);
+            }
+            Some(previous) if self.progress < 0.5 => {
+                self.progress = 0.0;
+                self.previous = Some(previous);
+            }
+            _ => {
+                self.progress = 0.0;
+                self.previous = Some(self.current);
+            }
+        }
+        self.current = to;
+    }
+
+    pub fn advance(&mut self, dt_sec: f32) {
+        if self.previous.is_some() {
+            self.progress += dt_sec.max(0.0) / VISUALIZER_FADE_SEC;
+            if self.progress >= 1.0 {
+                self.progress = 1.0;
+                self.previous = None;
+            }
+        }
+    }
+
+    /// Eased share (0..=1) the outgoing mode still has in the composite.
+    pub fn outgoing_weight(&self) -> f32 {
+        if self.previous.is_none() {
+            return 0.0;
+        }
+        let p = self.progress.clamp(0.0, 1.0);
+        1.0 - p * p * (3.0 - 2.0 * p)
+    }
+}
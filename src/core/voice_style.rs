@@ -0,0 +1,90 @@
+use super::trails::TRAIL_FADE_SEC;
+
+/// How a voice's glow is drawn in the 3D scene (`style.z` in scene.wgsl).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VoiceShape {
+    /// A soft orb that swells with the pulse
+    #[default]
+    Orb,
+    /// A hollow halo, its ring widening with the pulse
+    Ring,
+    /// A four-pointed sparkle that twinkles
+    Sparkle,
+}
+
+impl VoiceShape {
+    pub const ALL: [VoiceShape; 3] = [VoiceShape::Orb, VoiceShape::Ring, VoiceShape::Sparkle];
+
+    /// Position in `ALL`, as the shaders read it
+    pub fn index(self) -> usize {
+        Self::ALL.iter().position(|s| *s == self).unwrap_or(0)
+    }
+}
+
+/// How a voice looks, set alongside how it sounds: a bass can be drawn heavy
+/// and slow while a lead sparkles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoiceStyle {
+    /// Reach of the voice's glow and of its push on the waves (1 = as drawn
+    /// by default)
+    pub glow: f32,
+    /// Spatial frequency and speed of the ripples the voice sets off in the
+    /// waves (1 = default; lower is broader and slower)
+    pub wave_frequency: f32,
+    pub shape: VoiceShape,
+    /// Seconds the voice's motion trail takes to fade
+    pub trail_sec: f32,
+}
+
+impl VoiceStyle {
+    pub const DEFAULT: VoiceStyle = VoiceStyle {
+        glow: 1.0,
+        wave_frequency: 1.0,
+        shape: VoiceShape::Orb,
+        trail_sec: TRAIL_FADE_SEC,
+    };
+
+    /// Ranges each field is kept within.
+    pub const GLOW_RANGE: (f32, f32) = (0.25, 3.0);
+    pub const WAVE_FREQUENCY_RANGE: (f32, f32) = (0.25, 4.0);
+    pub const TRAIL_SEC_RANGE: (f32, f32) = (0.25, 8.0);
+
+    pub fn clamped(self) -> VoiceStyle {
+        let clamp = |v: f32, (lo, hi): (f32, f32)| v.clamp(lo, hi);
+        VoiceStyle {
+            glow: clamp(self.glow, Self::GLOW_RANGE),
+            wave_frequency: clamp(self.wave_frequency, Self::WAVE_FREQUENCY_RANGE),
+            shape: self.shape,
+            trail_sec: clamp(self.trail_sec, Self::TRAIL_SEC_RANGE),
+        }
+    }
+
+    /// Glow, wave frequency, shape index and trail seconds, as the shaders'
+    /// per-voice `style` vector.
+    pub fn pack(&self) -> [f32; 4] {
+        let s = self.clamped();
+        [
+            s.glow,
+            s.wave_frequency,
+            s.shape.index() as f32,
+            s.trail_sec,
+        ]
+    }
+
+    /// Part way from `self` to `other` (the shape switches half way).
+    pub fn lerp(&self, other: &VoiceStyle, t: f32) -> VoiceStyle {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        VoiceStyle {
+            glow: mix(self.glow, other.glow),
+            wave_frequency: mix(self.wave_frequency, other.wave_frequency),
+            shape: if t >= 0.5 { other.shape } else { self.shape },
+            trail_sec: mix(self.trail_sec, other.trail_sec),
+        }
+    }
+}
+
+impl Default for VoiceStyle {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
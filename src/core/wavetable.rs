@@ -0,0 +1,117 @@
+use std::f32::consts::TAU;
+
+/// Samples per stored single-cycle table.
+pub const WAVETABLE_LEN: usize = 2048;
+
+/// Indices of the built-in tables in `builtin_wavetables()` (and so in a
+/// fresh engine's `wavetables`).
+pub const WAVETABLE_ORGAN: u16 = 0;
+pub const WAVETABLE_HOLLOW: u16 = 1;
+pub const WAVETABLE_GLASS: u16 = 2;
+
+/// One cycle of a custom oscillator shape.
+///
+/// Stored resampled to `WAVETABLE_LEN` points with DC removed and the peak
+/// normalized to 1. The web frontend turns it into a `PeriodicWave` via
+/// `harmonics`; offline/worklet renderers read it with `sample`.
+#[derive(Clone, Debug)]
+pub struct Wavetable {
+    pub name: String,
+    samples: Vec<f32>,
+}
+
+impl Wavetable {
+    /// Build a table from one cycle of any length (e.g. a decoded audio file).
+    ///
+    /// Returns `None` for fewer than two samples or a silent cycle.
+    pub fn from_samples(name: &str, cycle: &[f32]) -> Option<Wavetable> {
+        if cycle.len() < 2 {
+            return None;
+        }
+        let mut samples: Vec<f32> = (0..WAVETABLE_LEN)
+            .map(|i| lerp_cyclic(cycle, i as f32 / WAVETABLE_LEN as f32))
+            .collect();
+        let dc = samples.iter().sum::<f32>() / WAVETABLE_LEN as f32;
+        let peak = samples.iter().map(|s| (s - dc).abs()).fold(0.0, f32::max);
+        if !peak.is_finite() || peak < 1e-6 {
+            return None;
+        }
+        for s in samples.iter_mut() {
+            *s = (*s - dc) / peak;
+        }
+        Some(Wavetable {
+            name: name.to_string(),
+            samples,
+        })
+    }
+
+    /// Additive table: `amplitudes[k]` is the level of harmonic k + 1 (sines).
+    pub fn from_harmonics(name: &str, amplitudes: &[f32]) -> Wavetable {
+        let cycle: Vec<f32> = (0..WAVETABLE_LEN)
+            .map(|i| {
+                let phase = i as f32 / WAVETABLE_LEN as f32;
+                amplitudes
+                    .iter()
+                    .enumerate()
+                    .map(|(k, a)| a * (TAU * (k + 1) as f32 * phase).sin())
+                    .sum()
+            })
+            .collect();
+        Self::from_samples(name, &cycle).unwrap_or_else(|| Wavetable {
+            name: name.to_string(),
+            samples: vec![0.0; WAVETABLE_LEN],
+        })
+    }
+
+    /// Value at `phase` (0..1, wrapped), linearly interpolated.
+    pub fn sample(&self, phase: f32) -> f32 {
+        lerp_cyclic(&self.samples, phase)
+    }
+
+    /// Fourier coefficients `(real, imag)` for harmonics 0..count (index 0
+    /// is DC), laid out as `createPeriodicWave` expects.
+    pub fn harmonics(&self, count: usize) -> (Vec<f32>, Vec<f32>) {
+        let n = self.samples.len() as f32;
+        (0..count)
+            .map(|k| {
+                if k == 0 {
+                    return (0.0, 0.0);
+                }
+                let (mut re, mut im) = (0.0, 0.0);
+                for (i, s) in self.samples.iter().enumerate() {
+                    let w = TAU * k as f32 * i as f32 / n;
+                    re += s * w.cos();
+                    im += s * w.sin();
+                }
+                (2.0 * re / n, 2.0 * im / n)
+            })
+            .unzip()
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+}
+
+fn lerp_cyclic(cycle: &[f32], phase: f32) -> f32 {
+    let pos = phase.rem_euclid(1.0) * cycle.len() as f32;
+    let i = (pos as usize).min(cycle.len() - 1);
+    let frac = pos - i as f32;
+    let next = cycle[(i + 1) % cycle.len()];
+    cycle[i] + (next - cycle[i]) * frac
+}
+
+/// Tables every engine starts with (see the `WAVETABLE_*` indices).
+pub fn builtin_wavetables() -> Vec<Wavetable> {
+    vec![
+        // Drawbar-style: fundamental, octave, twelfth and two octaves
+        Wavetable::from_harmonics("organ", &[1.0, 0.6, 0.45, 0.3]),
+        // Odd harmonics only, falling off fast: a soft, hollow square
+        Wavetable::from_harmonics("hollow", &[1.0, 0.0, 0.25, 0.0, 0.11, 0.0, 0.06, 0.0, 0.04]),
+        // Sparse upper partials for a bell-like shimmer
+        Wavetable::from_harmonics(
+            "glass",
+            &[1.0, 0.0, 0.0, 0.35, 0.0, 0.0, 0.0, 0.2, 0.0, 0.0, 0.12],
+        ),
+    ]
+}
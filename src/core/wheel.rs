@@ -0,0 +1,69 @@
+/// Trigger probability one wheel notch adds to or takes from a voice.
+pub const DENSITY_STEP: f32 = 0.05;
+
+/// Tempo change per wheel notch, in BPM.
+pub const WHEEL_BPM_STEP: f32 = 2.0;
+
+/// Pixels one `WheelEvent` line counts as (Firefox scrolls a mouse wheel in
+/// lines, three to a notch) and one page, so every browser steps alike.
+const LINE_PX: f64 = 40.0;
+const PAGE_PX: f64 = 800.0;
+
+/// What a turn of the wheel changes, by where the pointer is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WheelTarget {
+    /// Over a voice: shift it up/down an octave
+    Octave(usize),
+    /// Over a voice with Alt: how often it plays
+    Density(usize),
+    /// Over empty space: the master volume
+    Volume,
+    /// Over empty space with Alt: the tempo
+    Tempo,
+}
+
+impl WheelTarget {
+    pub fn pick(hovered: Option<usize>, alt: bool) -> Self {
+        match (hovered, alt) {
+            (Some(voice), false) => WheelTarget::Octave(voice),
+            (Some(voice), true) => WheelTarget::Density(voice),
+            (None, false) => WheelTarget::Volume,
+            (None, true) => WheelTarget::Tempo,
+        }
+    }
+}
+
+/// A `WheelEvent` delta in pixels, whatever its `deltaMode` (0 pixels,
+/// 1 lines, 2 pages).
+pub fn wheel_delta_px(delta: f64, mode: u32) -> f64 {
+    match mode {
+        1 => delta * LINE_PX,
+        2 => delta * PAGE_PX,
+        _ => delta,
+    }
+}
+
+/// Wheel movement gathered into whole notches, so a trackpad's many small
+/// deltas step as a mouse wheel's clicks do. Moving to another target starts
+/// the count afresh.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WheelNotches {
+    accum: f64,
+    target: Option<WheelTarget>,
+}
+
+impl WheelNotches {
+    /// Add `delta_px` (positive scrolls down) towards `target`; returns the
+    /// notches completed, positive for up (away from the player), once
+    /// `notch_px` has built up.
+    pub fn scroll(&mut self, target: WheelTarget, delta_px: f64, notch_px: f64) -> i32 {
+        if self.target != Some(target) {
+            self.target = Some(target);
+            self.accum = 0.0;
+        }
+        self.accum += delta_px;
+        let notches = (self.accum / notch_px).trunc();
+        self.accum -= notches * notch_px;
+        -(notches as i32)
+    }
+}
@@ -0,0 +1,125 @@
+use crate::core::displays::{DisplayFrame, DisplaySlice, DISPLAY_CHANNEL};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys as web;
+
+// What a display window says when it opens, so the control window starts
+// sending frames
+const HELLO: &str = "hello";
+
+/// The link between the control window, which runs the engine and the
+/// audio, and display windows (`?display=<n>/<count>`) that draw its scene
+/// across several screens. The control window posts each frame once a
+/// display window has said hello; a display window keeps the latest frame
+/// for its own next one.
+pub struct DisplayLink {
+    channel: web::BroadcastChannel,
+    /// This window's part of the span, if it is a display window
+    slice: Option<DisplaySlice>,
+    /// A display window is listening (control window)
+    listened: Rc<Cell<bool>>,
+    /// Latest frame from the control window, with the bursts of any frames
+    /// not yet drawn (display window)
+    latest: Rc<RefCell<Option<DisplayFrame>>>,
+    _on_message: Closure<dyn FnMut(web::MessageEvent)>,
+}
+
+impl DisplayLink {
+    pub fn new(slice: Option<DisplaySlice>) -> Option<DisplayLink> {
+        let channel = web::BroadcastChannel::new(DISPLAY_CHANNEL).ok()?;
+        let listened = Rc::new(Cell::new(false));
+        let latest = Rc::new(RefCell::new(None::<DisplayFrame>));
+        let on_message = {
+            let listened = listened.clone();
+            let latest = latest.clone();
+            Closure::wrap(Box::new(move |e: web::MessageEvent| {
+                let data = e.data();
+                if slice.is_none() {
+                    if data.as_string().as_deref() == Some(HELLO) {
+                        listened.set(true);
+                    }
+                    return;
+                }
+                let Some(array) = data.dyn_ref::<js_sys::Float32Array>() else {
+                    return;
+                };
+                let Some(mut frame) = DisplayFrame::unpack(&array.to_vec()) else {
+                    return;
+                };
+                let mut latest = latest.borrow_mut();
+                if let Some(missed) = latest.take() {
+                    frame.bursts.splice(0..0, missed.bursts);
+                }
+                *latest = Some(frame);
+            }) as Box<dyn FnMut(web::MessageEvent)>)
+        };
+        channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        if let Some(slice) = slice {
+            _ = channel.post_message(&JsValue::from_str(HELLO));
+            log::info!("[displays] showing part {} of the span", slice.param());
+        }
+        Some(DisplayLink {
+            channel,
+            slice,
+            listened,
+            latest,
+            _on_message: on_message,
+        })
+    }
+
+    pub fn slice(&self) -> Option<DisplaySlice> {
+        self.slice
+    }
+
+    /// Whether this window sends frames rather than drawing them.
+    pub fn leads(&self) -> bool {
+        self.slice.is_none() && self.listened.get()
+    }
+
+    /// Send this frame to the display windows (control window).
+    pub fn post(&self, frame: &DisplayFrame) {
+        let data = js_sys::Float32Array::from(frame.pack().as_slice());
+        _ = self.channel.post_message(&data);
+    }
+
+    /// The control window's latest frame, with the bursts fired since the
+    /// last call (display window).
+    pub fn take(&self) -> Option<DisplayFrame> {
+        let mut latest = self.latest.borrow_mut();
+        let frame = latest.clone();
+        if let Some(kept) = latest.as_mut() {
+            kept.bursts.clear();
+        }
+        frame
+    }
+}
+
+/// Open `count` display windows on this page, each drawing its part of the
+/// span, with the rest of the URL (theme, visuals) carried over.
+pub fn open_windows(count: u32) {
+    let Some(window) = web::window() else {
+        return;
+    };
+    let location = window.location();
+    let (Ok(path), Ok(search)) = (location.pathname(), location.search()) else {
+        return;
+    };
+    let Ok(params) = web::UrlSearchParams::new_with_str(&search) else {
+        return;
+    };
+    for index in 0..count {
+        params.set("display", &DisplaySlice { index, count }.param());
+        let url = format!("{path}?{}", String::from(params.to_string()));
+        let name = format!("geno-1-display-{}", index + 1);
+        if window
+            .open_with_url_and_target_and_features(&url, &name, "popup")
+            .ok()
+            .flatten()
+            .is_none()
+        {
+            log::warn!("[displays] window {} was blocked", index + 1);
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use wasm_bindgen::JsCast;
 use web_sys as web;
 
+/// How long a download's object URL outlives the click that started it
+const DOWNLOAD_REVOKE_DELAY_MS: i32 = 10_000;
+
 #[inline]
 pub fn window_document() -> Option<web::Document> {
     web::window().and_then(|w| w.document())
@@ -20,6 +23,184 @@ pub fn add_click_listener(
     }
 }
 
+/// Call `handler` with the element's new value whenever a `<select>` changes
+pub fn add_select_change_listener(
+    document: &web::Document,
+    element_id: &str,
+    mut handler: impl FnMut(String) + 'static,
+) {
+    if let Some(el) = document.get_element_by_id(element_id) {
+        let select: web::HtmlSelectElement = el.clone().unchecked_into();
+        let closure = wasm_bindgen::closure::Closure::wrap(
+            Box::new(move || handler(select.value())) as Box<dyn FnMut()>,
+        );
+        _ = el.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
+
+/// Replace a `<select>`'s options with `(value, label)` pairs
+pub fn set_select_options(
+    document: &web::Document,
+    element_id: &str,
+    options: &[(String, String)],
+) {
+    let Some(select) = document.get_element_by_id(element_id) else {
+        return;
+    };
+    select.set_inner_html("");
+    for (value, label) in options {
+        if let Ok(option) = document.create_element("option") {
+            _ = option.set_attribute("value", value);
+            option.set_text_content(Some(label));
+            _ = select.append_child(&option);
+        }
+    }
+}
+
+/// The selected value of a `<select>` element (`None` when nothing is picked)
+pub fn select_value(document: &web::Document, element_id: &str) -> Option<String> {
+    let el = document.get_element_by_id(element_id)?;
+    Some(el.unchecked_into::<web::HtmlSelectElement>().value()).filter(|v| !v.is_empty())
+}
+
+/// Set the selected value of a `<select>` element
+pub fn set_select_value(document: &web::Document, element_id: &str, value: &str) {
+    if let Some(el) = document.get_element_by_id(element_id) {
+        let select: web::HtmlSelectElement = el.unchecked_into();
+        select.set_value(value);
+    }
+}
+
+/// Call `handler` with an `<input>`'s value on `event`: `"input"` while it is
+/// edited (e.g. as a slider moves), `"change"` once the edit is committed
+pub fn add_input_listener(
+    document: &web::Document,
+    element_id: &str,
+    event: &str,
+    mut handler: impl FnMut(String) + 'static,
+) {
+    if let Some(el) = document.get_element_by_id(element_id) {
+        let input: web::HtmlInputElement = el.clone().unchecked_into();
+        let closure = wasm_bindgen::closure::Closure::wrap(
+            Box::new(move || handler(input.value())) as Box<dyn FnMut()>,
+        );
+        _ = el.add_event_listener_with_callback(event, closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
+
+/// Text in a `<textarea>`
+pub fn text_area_value(document: &web::Document, element_id: &str) -> Option<String> {
+    let el = document.get_element_by_id(element_id)?;
+    Some(el.unchecked_into::<web::HtmlTextAreaElement>().value())
+}
+
+pub fn set_text_area_value(document: &web::Document, element_id: &str, value: &str) {
+    if let Some(el) = document.get_element_by_id(element_id) {
+        el.unchecked_into::<web::HtmlTextAreaElement>()
+            .set_value(value);
+    }
+}
+
+/// Call `handler` with the text of a file dropped onto an element (instead of
+/// the page's audio drop)
+pub fn add_text_drop_listener(
+    document: &web::Document,
+    element_id: &str,
+    handler: impl Fn(String) + 'static,
+) {
+    let Some(el) = document.get_element_by_id(element_id) else {
+        return;
+    };
+    let handler = std::rc::Rc::new(handler);
+    let on_drop = wasm_bindgen::closure::Closure::wrap(Box::new(move |e: web::DragEvent| {
+        let Some(file) = e
+            .data_transfer()
+            .and_then(|d| d.files())
+            .and_then(|f| f.get(0))
+        else {
+            return;
+        };
+        e.prevent_default();
+        e.stop_propagation();
+        let handler = handler.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match wasm_bindgen_futures::JsFuture::from(file.text()).await {
+                Ok(text) => handler(text.as_string().unwrap_or_default()),
+                Err(e) => log::error!("[file] read failed: {:?}", e),
+            }
+        });
+    }) as Box<dyn FnMut(_)>);
+    _ = el.add_event_listener_with_callback("drop", on_drop.as_ref().unchecked_ref());
+    on_drop.forget();
+}
+
+/// Call `handler` with a checkbox's new state whenever it is toggled
+pub fn add_checkbox_listener(
+    document: &web::Document,
+    element_id: &str,
+    mut handler: impl FnMut(bool) + 'static,
+) {
+    if let Some(el) = document.get_element_by_id(element_id) {
+        let input: web::HtmlInputElement = el.clone().unchecked_into();
+        let closure =
+            wasm_bindgen::closure::Closure::wrap(
+                Box::new(move || handler(input.checked())) as Box<dyn FnMut()>
+            );
+        _ = el.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
+
+/// Tick or clear a checkbox
+pub fn set_checked(document: &web::Document, element_id: &str, checked: bool) {
+    if let Some(el) = document.get_element_by_id(element_id) {
+        let input: web::HtmlInputElement = el.unchecked_into();
+        input.set_checked(checked);
+    }
+}
+
+/// Text in an `<input>` element
+pub fn input_value(document: &web::Document, element_id: &str) -> Option<String> {
+    let el = document.get_element_by_id(element_id)?;
+    Some(el.unchecked_into::<web::HtmlInputElement>().value())
+}
+
+/// Set the value of an `<input>` element
+pub fn set_input_value(document: &web::Document, element_id: &str, value: &str) {
+    if let Some(el) = document.get_element_by_id(element_id) {
+        let input: web::HtmlInputElement = el.unchecked_into();
+        input.set_value(value);
+    }
+}
+
+/// Read a value saved in `localStorage` (`None` if storage is unavailable)
+pub fn local_storage_get(key: &str) -> Option<String> {
+    web::window()?.local_storage().ok()??.get_item(key).ok()?
+}
+
+/// Save a value in `localStorage`; private browsing may refuse, which is ignored
+pub fn local_storage_set(key: &str, value: &str) {
+    if let Some(storage) = web::window().and_then(|w| w.local_storage().ok().flatten()) {
+        _ = storage.set_item(key, value);
+    }
+}
+
+/// A JS exception's message (or the value itself), for showing to the player
+pub fn error_text(e: &wasm_bindgen::JsValue) -> String {
+    match e.dyn_ref::<js_sys::Error>() {
+        Some(err) => err.message().into(),
+        None => e.as_string().unwrap_or_else(|| format!("{e:?}")),
+    }
+}
+
+/// Read a query-string parameter from the page URL
+pub fn query_param(name: &str) -> Option<String> {
+    let search = web::window()?.location().search().ok()?;
+    web::UrlSearchParams::new_with_str(&search).ok()?.get(name)
+}
+
 pub fn sync_canvas_backing_size(canvas: &web::HtmlCanvasElement) {
     if let Some(w) = web::window() {
         let dpr = w.device_pixel_ratio();
@@ -31,3 +212,101 @@ pub fn sync_canvas_backing_size(canvas: &web::HtmlCanvasElement) {
         canvas.set_height(h_px.max(1));
     }
 }
+
+/// Offer `bytes` to the user as a file download
+pub fn download_bytes(document: &web::Document, filename: &str, mime: &str, bytes: &[u8]) {
+    let array = js_sys::Uint8Array::from(bytes);
+    let parts = js_sys::Array::of1(&array);
+    let opts = web::BlobPropertyBag::new();
+    opts.set_type(mime);
+    let Ok(blob) = web::Blob::new_with_u8_array_sequence_and_options(&parts, &opts) else {
+        return;
+    };
+    download_blob(document, filename, &blob);
+}
+
+/// Offer `blob` to the user as a file download
+pub fn download_blob(document: &web::Document, filename: &str, blob: &web::Blob) {
+    let Ok(url) = web::Url::create_object_url_with_blob(blob) else {
+        return;
+    };
+    if let Ok(a) = document.create_element("a") {
+        let a: web::HtmlAnchorElement = a.unchecked_into();
+        a.set_href(&url);
+        a.set_download(filename);
+        a.click();
+    }
+    // Some browsers start reading the blob only after `click` returns, so
+    // revoking straight away can cancel the download
+    let revoke = wasm_bindgen::closure::Closure::once_into_js(move || {
+        _ = web::Url::revoke_object_url(&url);
+    });
+    if let Some(wnd) = document.default_view() {
+        _ = wnd.set_timeout_with_callback_and_timeout_and_arguments_0(
+            revoke.unchecked_ref(),
+            DOWNLOAD_REVOKE_DELAY_MS,
+        );
+    }
+}
+
+/// Call `handler` with the contents of an audio file dropped anywhere on the page
+pub fn add_audio_drop_listener(document: &web::Document, handler: impl Fn(Vec<u8>) + 'static) {
+    let handler = std::rc::Rc::new(handler);
+    // Without cancelling `dragover` the browser never fires `drop`
+    let over = wasm_bindgen::closure::Closure::wrap(Box::new(|e: web::DragEvent| {
+        e.prevent_default();
+    }) as Box<dyn FnMut(_)>);
+    _ = document.add_event_listener_with_callback("dragover", over.as_ref().unchecked_ref());
+    over.forget();
+    let on_drop = wasm_bindgen::closure::Closure::wrap(Box::new(move |e: web::DragEvent| {
+        let Some(file) = e
+            .data_transfer()
+            .and_then(|d| d.files())
+            .and_then(|f| f.get(0))
+        else {
+            return;
+        };
+        e.prevent_default();
+        if !file.type_().starts_with("audio/") {
+            log::warn!("[file] ignoring dropped {}", file.name());
+            return;
+        }
+        let handler = handler.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            match wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await {
+                Ok(buf) => handler(js_sys::Uint8Array::new(&buf).to_vec()),
+                Err(e) => log::error!("[file] read failed: {:?}", e),
+            }
+        });
+    }) as Box<dyn FnMut(_)>);
+    _ = document.add_event_listener_with_callback("drop", on_drop.as_ref().unchecked_ref());
+    on_drop.forget();
+}
+
+/// Call `handler` with the contents of the file picked in an `<input type="file">`
+pub fn add_file_listener(
+    document: &web::Document,
+    element_id: &str,
+    handler: impl Fn(Vec<u8>) + 'static,
+) {
+    if let Some(el) = document.get_element_by_id(element_id) {
+        let input: web::HtmlInputElement = el.clone().unchecked_into();
+        let handler = std::rc::Rc::new(handler);
+        let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move || {
+            let Some(file) = input.files().and_then(|f| f.get(0)) else {
+                return;
+            };
+            // Clear so picking the same file again still fires `change`
+            input.set_value("");
+            let handler = handler.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                match wasm_bindgen_futures::JsFuture::from(file.array_buffer()).await {
+                    Ok(buf) => handler(js_sys::Uint8Array::new(&buf).to_vec()),
+                    Err(e) => log::error!("[file] read failed: {:?}", e),
+                }
+            });
+        }) as Box<dyn FnMut()>);
+        _ = el.add_event_listener_with_callback("change", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
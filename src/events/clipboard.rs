@@ -0,0 +1,66 @@
+use crate::core::{History, MusicEngine, ShareState};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys as web;
+
+/// Copy the compact settings string for the current state to the system clipboard
+pub fn copy_settings(engine: &Rc<RefCell<MusicEngine>>) {
+    let text = ShareState::from_engine(&engine.borrow()).encode();
+    match web::window() {
+        Some(window) => {
+            // Resolution of the promise is not awaited; failures surface in the console
+            _ = window.navigator().clipboard().write_text(&text);
+            log::info!("[clipboard] copied {}", text);
+        }
+        None => log::warn!("[clipboard] no window; cannot copy"),
+    }
+}
+
+/// Whether some of the page's text is selected, which Ctrl/Cmd+C copies instead
+pub fn has_text_selection() -> bool {
+    web::window()
+        .and_then(|w| w.get_selection().ok().flatten())
+        .is_some_and(|s| !s.is_collapsed())
+}
+
+/// Apply a settings string as a single undoable step
+pub fn paste_settings(
+    engine: &Rc<RefCell<MusicEngine>>,
+    history: &Rc<RefCell<History>>,
+    text: &str,
+) {
+    match ShareState::decode(text) {
+        Ok(state) => {
+            history
+                .borrow_mut()
+                .execute(&mut engine.borrow_mut(), state.to_command());
+            log::info!("[clipboard] applied {}", text.trim());
+        }
+        Err(e) => log::warn!("[clipboard] ignored paste: {}", e),
+    }
+}
+
+// Listen for paste events (Ctrl/Cmd+V or the browser menu) anywhere in the
+// page but the form controls, which take pastes as text
+pub fn wire_clipboard_paste(engine: Rc<RefCell<MusicEngine>>, history: Rc<RefCell<History>>) {
+    if let Some(window) = web::window() {
+        let closure =
+            wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::ClipboardEvent| {
+                if super::keyboard::typing_in_control(&ev) {
+                    return;
+                }
+                let text = ev
+                    .clipboard_data()
+                    .and_then(|dt| dt.get_data("text/plain").ok())
+                    .unwrap_or_default();
+                if text.trim().is_empty() {
+                    return;
+                }
+                paste_settings(&engine, &history, &text);
+                ev.prevent_default();
+            }) as Box<dyn FnMut(_)>);
+        _ = window.add_event_listener_with_callback("paste", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
@@ -1,16 +1,36 @@
-use crate::core::MusicEngine;
+use crate::audio;
+use crate::constants::{ENGINE_DRAG_MAX_RADIUS, KEYMAP_STORAGE_KEY};
+use crate::constants::{
+    POLYMETER_LOOP_STEPS, PRESET_MORPH_BARS, SCALE_MORPH_BARS, SIGNATURE_SIZE_PX, WAVEFORM_VOICE,
+};
+use crate::core::attract::random_tonality;
+use crate::core::bloom::{Bloom, BLOOM_STRENGTH_STEP};
+use crate::core::keymap::{Action, KeyChord, Keymap};
+use crate::core::physics::PhysicsParams;
+use crate::core::preset::preset_bank;
+use crate::core::screenshot::WALLPAPER_SCALE;
+use crate::core::selection::{
+    arrow_direction, cycle_voice, nudge_position, selection_label, NUDGE_FINE_STEP, NUDGE_STEP,
+};
+use crate::core::sidechain::Sidechain;
+use crate::core::spectrum::VisualLayers;
+use crate::core::theme::Theme;
+use crate::core::visualizer::Visualizer;
+use crate::core::{Command, EngineEvent, EventBus, History, MusicEngine, ParamLocks, GROOVES};
 use crate::core::{
     AEOLIAN, C_MAJOR_PENTATONIC, DORIAN, IONIAN, LOCRIAN, LYDIAN, MIXOLYDIAN, PHRYGIAN,
     TET19_PENTATONIC, TET24_PENTATONIC, TET31_PENTATONIC,
 };
+use crate::dom;
 use crate::overlay;
-use std::cell::RefCell;
+use crate::render::RenderMode;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use wasm_bindgen::JsCast;
 use web_sys as web;
 
 /// Get the name of the current scale for display purposes
-fn get_scale_name(scale: &[f32]) -> &'static str {
+pub(crate) fn get_scale_name(scale: &[f32]) -> &'static str {
     match scale {
         s if s == IONIAN => "Ionian (major)",
         s if s == DORIAN => "Dorian",
@@ -31,20 +51,35 @@ fn get_scale_name(scale: &[f32]) -> &'static str {
 fn update_hint_after_change(engine: &Rc<RefCell<MusicEngine>>) {
     if let Some(window) = web::window() {
         if let Some(document) = window.document() {
-            let (detune, bpm, scale_name) = {
+            let (detune, bpm, scale_name, groove_name, signature) = {
                 let eng = engine.borrow();
                 (
                     eng.params.detune_cents,
                     eng.params.bpm,
                     get_scale_name(eng.params.scale),
+                    eng.params.groove.name,
+                    eng.signature(),
                 )
             };
-            overlay::update_hint(&document, detune, bpm, scale_name);
+            overlay::update_hint(&document, detune, bpm, scale_name, groove_name);
+            overlay::update_signature(&document, &signature.to_svg(SIGNATURE_SIZE_PX));
             overlay::show_hint(&document);
         }
     }
 }
 
+/// Keep the hint overlay in sync with tempo/tonality changes, whatever their source
+pub fn subscribe_hint_overlay(bus: &mut EventBus, engine: Rc<RefCell<MusicEngine>>) {
+    bus.subscribe(Box::new(move |ev| match ev {
+        EngineEvent::BpmChanged(_)
+        | EngineEvent::ScaleChanged(_)
+        | EngineEvent::RootChanged(_)
+        | EngineEvent::DetuneChanged(_)
+        | EngineEvent::GrooveChanged(_) => update_hint_after_change(&engine),
+        _ => {}
+    }));
+}
+
 #[inline]
 pub fn root_midi_for_key(key: &str) -> Option<i32> {
     match key {
@@ -76,98 +111,468 @@ pub fn mode_scale_for_digit(key: &str) -> Option<&'static [f32]> {
     }
 }
 
+/// Preset slot for a digit key, by physical key so it works with Shift held
+#[inline]
+pub fn preset_index_for_code(code: &str) -> Option<usize> {
+    let digit = code.strip_prefix("Digit")?.parse::<usize>().ok()?;
+    (1..=9).contains(&digit).then(|| digit - 1)
+}
+
+/// Re-roll every parameter not locked in the overlay's dice panel (undoable)
+pub fn roll_dice(engine: &Rc<RefCell<MusicEngine>>, history: &Rc<RefCell<History>>) {
+    let locks = match web::window().and_then(|w| w.document()) {
+        Some(doc) => overlay::read_dice_locks(&doc),
+        None => ParamLocks::default(),
+    };
+    // Drawn from the session seed so a shared `seed=` replays the same rolls
+    let mut rng = engine.borrow_mut().fork_rng();
+    let cmd = crate::core::roll_dice(&engine.borrow(), &locks, &mut rng);
+    execute(engine, history, cmd);
+    log::info!("[keys] dice rolled locks={:?}", locks);
+}
+
+/// Run a command through the undo history
+fn execute(engine: &Rc<RefCell<MusicEngine>>, history: &Rc<RefCell<History>>, cmd: Command) {
+    history.borrow_mut().execute(&mut engine.borrow_mut(), cmd);
+}
+
+/// The keymap every keydown handler reads, and the action waiting for a key
+/// while one is being rebound in the Keys panel
+#[derive(Default)]
+pub struct KeyBindings {
+    pub keymap: Keymap,
+    pub capturing: Option<Action>,
+}
+
+pub type SharedKeys = Rc<RefCell<KeyBindings>>;
+
+/// The keymap saved in this browser (as JSON of the changed bindings), or
+/// the defaults
+pub fn load_keymap() -> Keymap {
+    let Some(json) = dom::local_storage_get(KEYMAP_STORAGE_KEY) else {
+        return Keymap::default();
+    };
+    let Ok(saved) = js_sys::JSON::parse(&json) else {
+        log::warn!("[keys] ignoring unreadable saved keymap");
+        return Keymap::default();
+    };
+    let changes: Vec<(String, String)> = js_sys::Object::entries(saved.unchecked_ref())
+        .iter()
+        .filter_map(|entry| {
+            let pair: js_sys::Array = entry.unchecked_into();
+            Some((pair.get(0).as_string()?, pair.get(1).as_string()?))
+        })
+        .collect();
+    Keymap::with_changes(changes.iter().map(|(a, k)| (a.as_str(), k.as_str())))
+}
+
+fn save_keymap(keymap: &Keymap) {
+    let changes = js_sys::Object::new();
+    for (id, labels) in keymap.changes() {
+        _ = js_sys::Reflect::set(&changes, &id.into(), &labels.into());
+    }
+    if let Some(json) = js_sys::JSON::stringify(&changes)
+        .ok()
+        .and_then(|j| j.as_string())
+    {
+        dom::local_storage_set(KEYMAP_STORAGE_KEY, &json);
+    }
+}
+
+/// The rebindable action a key press asks for (none with Ctrl/Cmd held, or
+/// while typing in a form control)
+fn key_action(keys: &SharedKeys, ev: &web::KeyboardEvent) -> Option<Action> {
+    if ev.ctrl_key() || ev.meta_key() || typing_in_control(ev) {
+        return None;
+    }
+    keys.borrow().keymap.action(&ev.key(), ev.shift_key())
+}
+
+/// The Keys panel's rebinding: clicking an action's key waits for the next
+/// key press (Escape cancels, Backspace leaves it unbound), which no other
+/// handler sees; Reset restores the defaults. Changes are saved in the
+/// browser.
+pub fn wire_key_rebinding(document: &web::Document, keys: SharedKeys) {
+    {
+        let k = keys.borrow();
+        overlay::update_keymap(document, &k.keymap, k.capturing);
+    }
+    let Some(window) = web::window() else {
+        return;
+    };
+    {
+        let keys = keys.clone();
+        let doc = document.clone();
+        // Registered for the capture phase so it runs before every other
+        // keydown listener on the window
+        let closure =
+            wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
+                let Some(action) = keys.borrow().capturing else {
+                    return;
+                };
+                ev.prevent_default();
+                ev.stop_immediate_propagation();
+                let key = ev.key();
+                if matches!(key.as_str(), "Shift" | "Control" | "Alt" | "Meta") {
+                    return;
+                }
+                let mut k = keys.borrow_mut();
+                k.capturing = None;
+                match key.as_str() {
+                    "Escape" => {}
+                    "Backspace" | "Delete" => {
+                        k.keymap.bind(action, None);
+                        save_keymap(&k.keymap);
+                        log::info!("[keys] {} unbound", action.id());
+                    }
+                    _ => {
+                        let chord = KeyChord::new(&key, ev.shift_key());
+                        log::info!("[keys] {} -> {}", action.id(), chord.label());
+                        if let Some(other) = k.keymap.bind(action, Some(chord)) {
+                            log::info!("[keys] {} unbound", other.id());
+                        }
+                        save_keymap(&k.keymap);
+                    }
+                }
+                overlay::update_keymap(&doc, &k.keymap, k.capturing);
+            }) as Box<dyn FnMut(_)>);
+        _ = window.add_event_listener_with_callback_and_bool(
+            "keydown",
+            closure.as_ref().unchecked_ref(),
+            true,
+        );
+        closure.forget();
+    }
+    if let Some(list) = document.get_element_by_id("keymap") {
+        let keys = keys.clone();
+        let doc = document.clone();
+        let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::Event| {
+            let action = ev
+                .target()
+                .and_then(|t| t.dyn_into::<web::Element>().ok())
+                .and_then(|el| el.get_attribute("data-action"))
+                .and_then(|id| Action::from_id(&id));
+            if let Some(action) = action {
+                let mut k = keys.borrow_mut();
+                k.capturing = (k.capturing != Some(action)).then_some(action);
+                overlay::update_keymap(&doc, &k.keymap, k.capturing);
+            }
+        }) as Box<dyn FnMut(_)>);
+        _ = list.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+    let doc = document.clone();
+    dom::add_click_listener(document, "keymap-reset", move || {
+        let mut k = keys.borrow_mut();
+        *k = KeyBindings::default();
+        save_keymap(&k.keymap);
+        overlay::update_keymap(&doc, &k.keymap, k.capturing);
+        log::info!("[keys] keymap reset");
+    });
+}
+
+/// True when a key press (or paste) is meant for a form control (Tab moves
+/// focus between the panel's controls, arrows step its sliders, text goes
+/// into fields)
+pub fn typing_in_control(ev: &web::Event) -> bool {
+    ev.target()
+        .and_then(|t| t.dyn_into::<web::Element>().ok())
+        .is_some_and(|el| {
+            matches!(
+                el.tag_name().as_str(),
+                "INPUT" | "SELECT" | "TEXTAREA" | "BUTTON"
+            )
+        })
+}
+
+/// Keyboard voice selection, so voices can be placed without a pointer:
+/// Tab/Shift+Tab (rebindable) or Alt+1..3 select a voice, the arrows then
+/// nudge it on the ground plane (Shift for fine steps, each one undoable)
+/// instead of changing tempo and volume, and Escape lets it go. The
+/// selection is named in an on-screen readout that screen readers announce.
+pub fn wire_voice_selection(
+    document: &web::Document,
+    keys: SharedKeys,
+    engine: Rc<RefCell<MusicEngine>>,
+    history: Rc<RefCell<History>>,
+    selected: Rc<Cell<Option<usize>>>,
+) {
+    let Some(window) = web::window() else {
+        return;
+    };
+    let doc = document.clone();
+    // Registered for the capture phase, after the rebinding listener, so the
+    // keys it takes never reach the tempo, volume and mode handlers
+    let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
+        if ev.ctrl_key() || ev.meta_key() || typing_in_control(&ev) {
+            return;
+        }
+        let count = engine.borrow().voices.len();
+        let current = selected.get().filter(|&i| i < count);
+        let key = ev.key();
+        let next = match key_action(&keys, &ev) {
+            Some(Action::SelectNextVoice) => cycle_voice(current, count, true),
+            Some(Action::SelectPrevVoice) => cycle_voice(current, count, false),
+            _ => {
+                let digit = ev
+                    .code()
+                    .strip_prefix("Digit")
+                    .and_then(|d| d.parse::<usize>().ok());
+                match (digit, current) {
+                    (Some(n), _) if ev.alt_key() && !ev.shift_key() && (1..=count).contains(&n) => {
+                        Some(n - 1)
+                    }
+                    (_, Some(_)) if key == "Escape" => None,
+                    (_, Some(voice)) => {
+                        let Some(dir) = arrow_direction(&key) else {
+                            return;
+                        };
+                        let step = if ev.shift_key() {
+                            NUDGE_FINE_STEP
+                        } else {
+                            NUDGE_STEP
+                        };
+                        let from = engine.borrow().voices[voice].position;
+                        let position = nudge_position(from, dir, step, ENGINE_DRAG_MAX_RADIUS);
+                        execute(&engine, &history, Command::SetPosition { voice, position });
+                        Some(voice)
+                    }
+                    _ => return,
+                }
+            }
+        };
+        ev.prevent_default();
+        ev.stop_immediate_propagation();
+        if next != current {
+            log::info!("[keys] selected voice={:?}", next);
+        }
+        selected.set(next);
+        let label = next.map(|i| selection_label(i, engine.borrow().voices[i].position));
+        overlay::update_voice_selection(&doc, label.as_deref());
+    }) as Box<dyn FnMut(_)>);
+    _ = window.add_event_listener_with_callback_and_bool(
+        "keydown",
+        closure.as_ref().unchecked_ref(),
+        true,
+    );
+    closure.forget();
+}
+
 pub fn handle_global_keydown(
     ev: &web::KeyboardEvent,
+    keys: &SharedKeys,
     engine: &Rc<RefCell<MusicEngine>>,
+    history: &Rc<RefCell<History>>,
     paused: &Rc<RefCell<bool>>,
-    master_gain: &web::GainNode,
+    volume: &audio::MasterVolume,
     canvas: &web::HtmlCanvasElement,
 ) {
+    // Text typed into a field (a preset name, MIDI channels, shader code)
+    // and its undo, redo and copy keys are the field's own
+    if typing_in_control(ev) {
+        return;
+    }
     let key = ev.key();
-    if let Some(midi) = root_midi_for_key(&key) {
-        engine.borrow_mut().params.root_midi = midi;
-        update_hint_after_change(engine);
+    if ev.ctrl_key() || ev.meta_key() {
+        let done = match key.as_str() {
+            "z" | "Z" if ev.shift_key() => history.borrow_mut().redo(&mut engine.borrow_mut()),
+            "z" | "Z" => history.borrow_mut().undo(&mut engine.borrow_mut()),
+            "y" | "Y" => history.borrow_mut().redo(&mut engine.borrow_mut()),
+            // Selected text copies as usual
+            "c" | "C" if super::clipboard::has_text_selection() => {
+                return;
+            }
+            "c" | "C" => {
+                super::clipboard::copy_settings(engine);
+                ev.prevent_default();
+                return;
+            }
+            _ => return,
+        };
+        log::info!("[keys] history step applied={}", done);
+        ev.prevent_default();
         return;
     }
-    if let Some(scale) = mode_scale_for_digit(&key) {
-        engine.borrow_mut().params.scale = scale;
-        update_hint_after_change(engine);
+    if let Some(action) = key_action(keys, ev) {
+        run_engine_action(action, ev, engine, history, paused, volume, canvas);
         return;
     }
-    match key.as_str() {
-        "p" | "P" => {
-            engine.borrow_mut().params.scale = C_MAJOR_PENTATONIC;
-            update_hint_after_change(engine);
+    // Shift+1..4 morphs into a built-in preset, Alt+Shift+1..4 jumps straight to it
+    if ev.shift_key() {
+        if let Some(preset) =
+            preset_index_for_code(&ev.code()).and_then(|i| preset_bank().get(i).cloned())
+        {
+            log::info!("[keys] preset {}", preset.name);
+            let cmd = if ev.alt_key() {
+                Command::ApplyPreset(Box::new(preset))
+            } else {
+                Command::MorphToPreset {
+                    preset: Box::new(preset),
+                    bars: PRESET_MORPH_BARS,
+                }
+            };
+            execute(engine, history, cmd);
+            ev.prevent_default();
             return;
         }
-        "r" | "R" => {
+    }
+    if let Some(midi) = root_midi_for_key(&key) {
+        execute(engine, history, Command::SetRootMidi(midi));
+        return;
+    }
+    if let Some(scale) = mode_scale_for_digit(&key) {
+        // Drift into the new mode over a couple of bars rather than jumping
+        let cmd = Command::MorphScale {
+            scale,
+            bars: SCALE_MORPH_BARS,
+        };
+        execute(engine, history, cmd);
+    }
+}
+
+/// Carry out a keymap action on the engine, playback or page; the visual
+/// actions are left to their own handlers below
+fn run_engine_action(
+    action: Action,
+    ev: &web::KeyboardEvent,
+    engine: &Rc<RefCell<MusicEngine>>,
+    history: &Rc<RefCell<History>>,
+    paused: &Rc<RefCell<bool>>,
+    volume: &audio::MasterVolume,
+    canvas: &web::HtmlCanvasElement,
+) {
+    match action {
+        Action::Pentatonic => {
+            execute(engine, history, Command::SetScale(C_MAJOR_PENTATONIC));
+        }
+        Action::ReseedAll => {
             let voice_len = engine.borrow().voices.len();
-            let mut eng = engine.borrow_mut();
-            for i in 0..voice_len {
-                eng.reseed_voice(i, None);
-            }
+            let reseeds = (0..voice_len)
+                .map(|voice| Command::Reseed { voice, seed: None })
+                .collect();
+            execute(engine, history, Command::Batch(reseeds));
             log::info!("[keys] reseeded all voices");
         }
-        "t" | "T" => {
-            let roots: [i32; 7] = [60, 62, 64, 65, 67, 69, 71]; // C, D, E, F, G, A, B
-            let modes: [&'static [f32]; 7] = [
-                IONIAN, DORIAN, PHRYGIAN, LYDIAN, MIXOLYDIAN, AEOLIAN, LOCRIAN,
-            ];
-            let ri = (js_sys::Math::random() * roots.len() as f64).floor() as usize;
-            let mi = (js_sys::Math::random() * modes.len() as f64).floor() as usize;
-            let mut eng = engine.borrow_mut();
-            eng.params.root_midi = roots[ri];
-            eng.params.scale = modes[mi];
-            drop(eng);
-            update_hint_after_change(engine);
-        }
-        " " => {
+        Action::RandomTonality => {
+            let mut rng = engine.borrow_mut().fork_rng();
+            execute(engine, history, random_tonality(&mut rng));
+        }
+        Action::RollDice => roll_dice(engine, history),
+        Action::Metronome => {
+            let mut m = engine.borrow().metronome();
+            m.enabled = !m.enabled;
+            engine.borrow_mut().set_metronome(m);
+            log::info!("[keys] metronome={}", m.enabled);
+        }
+        Action::Mute => {
+            volume.update(|m| m.toggle_mute());
+            log::info!("[keys] muted={}", volume.mixer().muted);
+        }
+        Action::Motion => {
+            // Cycle auto-motion of unheld voices (still → orbit → Lissajous drift)
+            let next = engine.borrow().motion().next();
+            execute(engine, history, Command::SetMotion(next));
+            log::info!("[keys] motion={}", next.id());
+        }
+        Action::Waveform => {
+            // Cycle the lead's shape through the basic waves and every wavetable
+            let next = {
+                let eng = engine.borrow();
+                let Some(c) = eng.configs.get(WAVEFORM_VOICE) else {
+                    return;
+                };
+                c.waveform.next(eng.wavetables.len())
+            };
+            let cmd = Command::SetWaveform {
+                voice: WAVEFORM_VOICE,
+                waveform: next,
+            };
+            execute(engine, history, cmd);
+            log::info!("[keys] waveform={:?}", next);
+        }
+        Action::Springs => {
+            // Toggle springs pulling dropped voices back toward their base positions
+            let next = match engine.borrow().physics() {
+                Some(_) => None,
+                None => Some(PhysicsParams::DEFAULT),
+            };
+            execute(engine, history, Command::SetPhysics(next));
+            log::info!("[keys] physics={:?}", next);
+        }
+        Action::Sidechain => {
+            // Toggle the bass pumping the other voices
+            let next = match engine.borrow().sidechain() {
+                Some(_) => None,
+                None => Some(Sidechain::DEFAULT),
+            };
+            execute(engine, history, Command::SetSidechain(next));
+            log::info!("[keys] sidechain={:?}", next);
+        }
+        Action::TransposeDown | Action::TransposeUp => {
+            let semitones = if action == Action::TransposeUp { 1 } else { -1 };
+            execute(engine, history, Command::Transpose(semitones));
+        }
+        Action::OctaveDown | Action::OctaveUp => {
+            let semitones = if action == Action::OctaveUp { 12 } else { -12 };
+            execute(engine, history, Command::Transpose(semitones));
+        }
+        Action::Groove => {
+            // Cycle groove templates (Straight → Swing → Drunk → Rushed hats),
+            // found by name as a `const` has no fixed address to compare
+            let current = engine.borrow().params.groove.name;
+            let idx = GROOVES.iter().position(|g| g.name == current).unwrap_or(0);
+            let next = GROOVES[(idx + 1) % GROOVES.len()];
+            execute(engine, history, Command::SetGroove(next));
+        }
+        Action::Polymeter => {
+            // Toggle polymeter: each voice loops over its own length, or all run free
+            let cmds = {
+                let eng = engine.borrow();
+                let looping = eng.configs.iter().any(|c| c.loop_steps > 0);
+                (0..eng.voices.len())
+                    .map(|voice| Command::SetLoopSteps {
+                        voice,
+                        steps: if looping {
+                            0
+                        } else {
+                            POLYMETER_LOOP_STEPS[voice % POLYMETER_LOOP_STEPS.len()]
+                        },
+                    })
+                    .collect()
+            };
+            execute(engine, history, Command::Batch(cmds));
+        }
+        Action::Pause => {
             let mut p = paused.borrow_mut();
             *p = !*p;
+            if !*p {
+                engine.borrow_mut().start_count_in();
+            }
             log::info!("[keys] paused={}", *p);
             ev.prevent_default();
         }
-        "ArrowRight" | "+" | "=" => {
-            let mut eng = engine.borrow_mut();
-            let new_bpm = (eng.params.bpm + 5.0).min(240.0);
-            eng.set_bpm(new_bpm);
-            drop(eng);
-            update_hint_after_change(engine);
-        }
-        "ArrowLeft" | "-" | "_" => {
-            let mut eng = engine.borrow_mut();
-            let new_bpm = (eng.params.bpm - 5.0).max(40.0);
-            eng.set_bpm(new_bpm);
-            drop(eng);
-            update_hint_after_change(engine);
-        }
-        "," => {
-            let mut eng = engine.borrow_mut();
-            if ev.shift_key() {
-                eng.adjust_detune_cents(-10.0); // Fine adjustment
-            } else {
-                eng.adjust_detune_cents(-50.0); // Coarse adjustment
-            }
-            drop(eng);
-            update_hint_after_change(engine);
+        Action::TempoUp => {
+            let new_bpm = (engine.borrow().params.bpm + 5.0).min(240.0);
+            execute(engine, history, Command::SetBpm(new_bpm));
         }
-        "." => {
-            let mut eng = engine.borrow_mut();
-            if ev.shift_key() {
-                eng.adjust_detune_cents(10.0); // Fine adjustment
+        Action::TempoDown => {
+            let new_bpm = (engine.borrow().params.bpm - 5.0).max(40.0);
+            execute(engine, history, Command::SetBpm(new_bpm));
+        }
+        Action::DetuneDown | Action::DetuneUp => {
+            // Shift for fine adjustment, otherwise coarse
+            let step = if ev.shift_key() { 10.0 } else { 50.0 };
+            let sign = if action == Action::DetuneUp {
+                1.0
             } else {
-                eng.adjust_detune_cents(50.0); // Coarse adjustment
-            }
-            drop(eng);
-            update_hint_after_change(engine);
+                -1.0
+            };
+            execute(engine, history, Command::AdjustDetune(sign * step));
         }
-        "/" => {
-            let mut eng = engine.borrow_mut();
-            eng.reset_detune();
-            drop(eng);
-            update_hint_after_change(engine);
+        Action::DetuneReset => {
+            execute(engine, history, Command::ResetDetune);
         }
-        "Enter" => {
+        Action::Fullscreen => {
             if let Some(win) = web::window() {
                 if let Some(doc) = win.document() {
                     if doc.fullscreen_element().is_some() {
@@ -179,40 +584,32 @@ pub fn handle_global_keydown(
             }
             ev.prevent_default();
         }
-        "Escape" => {
+        Action::ExitFullscreen => {
             if let Some(win) = web::window() {
                 if let Some(doc) = win.document() {
                     _ = doc.exit_fullscreen();
                 }
             }
         }
-        _ => {}
-    }
-    match key.as_str() {
-        "ArrowUp" => {
-            let v = master_gain.gain().value();
-            let nv = (v + 0.05).min(1.0);
-            _ = master_gain.gain().set_value(nv);
+        Action::VolumeUp => {
+            volume.update(|m| m.nudge(1));
             ev.prevent_default();
         }
-        "ArrowDown" => {
-            let v = master_gain.gain().value();
-            let nv = (v - 0.05).max(0.0);
-            _ = master_gain.gain().set_value(nv);
+        Action::VolumeDown => {
+            volume.update(|m| m.nudge(-1));
             ev.prevent_default();
         }
         _ => {}
     }
 }
 
-// Wire an 'H' key handler to toggle the overlay without affecting pause state
-pub fn wire_overlay_toggle_h(document: &web::Document) {
+// Wire the overlay toggle (H) without affecting pause state
+pub fn wire_overlay_toggle_h(document: &web::Document, keys: SharedKeys) {
     if let Some(window) = web::window() {
         let doc = document.clone();
         let closure =
             wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
-                let key = ev.key();
-                if key == "h" || key == "H" {
+                if key_action(&keys, &ev) == Some(Action::Overlay) {
                     crate::overlay::toggle(&doc);
                     ev.prevent_default();
                 }
@@ -222,21 +619,171 @@ pub fn wire_overlay_toggle_h(document: &web::Document) {
     }
 }
 
+// Wire the record key (O) starting/stopping a performance recording
+pub fn wire_record_toggle_o(keys: SharedKeys, toggle: Rc<dyn Fn()>) {
+    if let Some(window) = web::window() {
+        let closure =
+            wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
+                if key_action(&keys, &ev) == Some(Action::Record) {
+                    toggle();
+                    ev.prevent_default();
+                }
+            }) as Box<dyn FnMut(_)>);
+        _ = window.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
+
+// Wire the theme key (I) cycling the visual theme; `on_change` persists and
+// reflects the choice
+pub fn wire_theme_toggle_i(
+    keys: SharedKeys,
+    theme: Rc<Cell<&'static Theme>>,
+    on_change: impl Fn(&'static Theme) + 'static,
+) {
+    if let Some(window) = web::window() {
+        let closure =
+            wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
+                if key_action(&keys, &ev) == Some(Action::Theme) {
+                    theme.set(theme.get().next());
+                    on_change(theme.get());
+                    ev.prevent_default();
+                }
+            }) as Box<dyn FnMut(_)>);
+        _ = window.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
+
+// Wire the visualizer key (U) cycling the fullscreen visualizer (waves →
+// particles → tunnel → kaleidoscope); the renderer crossfades to each
+pub fn wire_visualizer_toggle_u(
+    keys: SharedKeys,
+    visualizer: Rc<Cell<Visualizer>>,
+    on_change: impl Fn(Visualizer) + 'static,
+) {
+    if let Some(window) = web::window() {
+        let closure =
+            wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
+                if key_action(&keys, &ev) == Some(Action::Visualizer) {
+                    visualizer.set(visualizer.get().next());
+                    on_change(visualizer.get());
+                    ev.prevent_default();
+                }
+            }) as Box<dyn FnMut(_)>);
+        _ = window.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
+
+/// J cycles the bloom quality (blur iterations); Y / Shift+Y raise and lower
+/// the bloom strength. `on_change` reflects the new settings.
+pub fn wire_bloom_keys_j_y(
+    keys: SharedKeys,
+    bloom: Rc<Cell<Bloom>>,
+    on_change: impl Fn(Bloom) + 'static,
+) {
+    if let Some(window) = web::window() {
+        let closure =
+            wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
+                let b = bloom.get();
+                let next = match key_action(&keys, &ev) {
+                    Some(Action::BloomQuality) => b.next_quality(),
+                    Some(Action::BloomDown) => b.with_strength(b.strength - BLOOM_STRENGTH_STEP),
+                    Some(Action::BloomUp) => b.with_strength(b.strength + BLOOM_STRENGTH_STEP),
+                    _ => return,
+                };
+                bloom.set(next);
+                on_change(next);
+                ev.prevent_default();
+            }) as Box<dyn FnMut(_)>);
+        _ = window.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
+
+/// Shift+P shows or hides the performance HUD; `on_change` reflects it.
+pub fn wire_perf_hud_toggle_p(
+    keys: SharedKeys,
+    show: Rc<Cell<bool>>,
+    on_change: impl Fn(bool) + 'static,
+) {
+    if let Some(window) = web::window() {
+        let closure =
+            wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
+                if key_action(&keys, &ev) == Some(Action::PerfHud) {
+                    show.set(!show.get());
+                    on_change(show.get());
+                    ev.prevent_default();
+                }
+            }) as Box<dyn FnMut(_)>);
+        _ = window.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
+
+/// Z asks for a screenshot at the canvas size, Shift+Z for a wallpaper at
+/// `WALLPAPER_SCALE` times it (Ctrl/Cmd+Z stays undo).
+pub fn wire_screenshot_keys_z(keys: SharedKeys, request: Rc<Cell<Option<u32>>>) {
+    if let Some(window) = web::window() {
+        let closure =
+            wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
+                let scale = match key_action(&keys, &ev) {
+                    Some(Action::Screenshot) => 1,
+                    Some(Action::Wallpaper) => WALLPAPER_SCALE,
+                    _ => return,
+                };
+                request.set(Some(scale));
+                log::info!("[keys] screenshot scale={scale}");
+                ev.prevent_default();
+            }) as Box<dyn FnMut(_)>);
+        _ = window.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
+
+/// V cycles the render mode; Shift+V cycles the analyser layers.
+pub fn wire_render_mode_toggle_v(
+    keys: SharedKeys,
+    render_mode: Rc<RefCell<RenderMode>>,
+    layers: Rc<Cell<VisualLayers>>,
+) {
+    if let Some(window) = web::window() {
+        let closure =
+            wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
+                match key_action(&keys, &ev) {
+                    Some(Action::VisualLayers) => {
+                        layers.set(layers.get().next());
+                        if let Some(doc) = web::window().and_then(|w| w.document()) {
+                            overlay::update_visual_layers(&doc, layers.get());
+                        }
+                    }
+                    Some(Action::RenderMode) => {
+                        let mut mode = render_mode.borrow_mut();
+                        *mode = mode.next();
+                    }
+                    _ => return,
+                }
+                ev.prevent_default();
+            }) as Box<dyn FnMut(_)>);
+        _ = window.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+}
+
 pub fn wire_global_keydown(
+    keys: SharedKeys,
     engine: Rc<RefCell<MusicEngine>>,
+    history: Rc<RefCell<History>>,
     paused: Rc<RefCell<bool>>,
-    master_gain: web::GainNode,
+    volume: Rc<audio::MasterVolume>,
     canvas: web::HtmlCanvasElement,
 ) {
     if let Some(window) = web::window() {
         let closure =
             wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::KeyboardEvent| {
                 super::keyboard::handle_global_keydown(
-                    &ev,
-                    &engine,
-                    &paused,
-                    &master_gain,
-                    &canvas,
+                    &ev, &keys, &engine, &history, &paused, &volume, &canvas,
                 );
             }) as Box<dyn FnMut(_)>);
         _ = window.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref());
@@ -1,5 +1,12 @@
+pub mod clipboard;
 pub mod keyboard;
 pub mod pointer;
 
-pub use keyboard::{wire_global_keydown, wire_overlay_toggle_h};
-pub use pointer::{wire_input_handlers, InputWiring};
+pub use clipboard::wire_clipboard_paste;
+pub use keyboard::{
+    load_keymap, roll_dice, subscribe_hint_overlay, wire_bloom_keys_j_y, wire_global_keydown,
+    wire_key_rebinding, wire_overlay_toggle_h, wire_perf_hud_toggle_p, wire_record_toggle_o,
+    wire_render_mode_toggle_v, wire_screenshot_keys_z, wire_theme_toggle_i,
+    wire_visualizer_toggle_u, wire_voice_selection, KeyBindings, SharedKeys,
+};
+pub use pointer::{wire_input_handlers, InputWiring, QueuedRipple, QueuedStreak};
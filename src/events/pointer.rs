@@ -1,31 +1,165 @@
 use crate::audio;
-use crate::constants::{CAMERA_Z, ENGINE_DRAG_MAX_RADIUS, PICK_SPHERE_RADIUS, SPREAD, Z_OFFSET};
-use crate::core::{midi_to_hz, MusicEngine};
+use crate::constants::{
+    ENGINE_DRAG_MAX_RADIUS, PICK_SPHERE_RADIUS, SPREAD, WHEEL_NOTCH_PX, Z_OFFSET,
+};
+use crate::core::automation::Automation;
+use crate::core::choreography::CameraPose;
+use crate::core::gesture::{GestureStep, TwoFingerGesture};
+use crate::core::looper::{LoopAction, Looper};
+use crate::core::note_repeat::{BackgroundHold, BackgroundNote};
+use crate::core::selection::VOICE_NAMES;
+use crate::core::strum::{
+    background_midi, background_uvx, strum_strings, Strum, STRUM_MIN_PX_PER_SEC,
+};
+use crate::core::touch::{
+    measured_pressure, PointerSpeed, PressKind, PressTimings, TapExpression, TapOutcome,
+    TapTracker, VoicePress, VoicePresses, TOUCH_PICK_SCALE,
+};
+use crate::core::wheel::{wheel_delta_px, WheelNotches, WheelTarget, DENSITY_STEP, WHEEL_BPM_STEP};
+use crate::core::{midi_to_hz, midi_to_hz_with_detune, Command, History, MusicEngine};
 use crate::input;
+use crate::overlay;
 use crate::render;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use wasm_bindgen::JsCast;
 use web_sys as web;
 
+/// Where the last background tap landed (screen uv) and how big its ripple
+/// is, until the next frame draws it
+pub type QueuedRipple = Rc<RefCell<Option<([f32; 2], f32)>>>;
+
+/// Points a strum swept through since the last frame (screen uv), for its
+/// streak
+pub type QueuedStreak = Rc<RefCell<Vec<[f32; 2]>>>;
+
 #[derive(Clone)]
 pub struct InputWiring {
     pub canvas: web::HtmlCanvasElement,
     pub engine: Rc<RefCell<MusicEngine>>,
+    pub history: Rc<RefCell<History>>,
     pub mouse_state: Rc<RefCell<input::MouseState>>,
     pub hover_index: Rc<RefCell<Option<usize>>>,
-    pub drag_state: Rc<RefCell<input::DragState>>,
+    /// Voices held by each pointer, so several fingers can drag at once
+    pub presses: Rc<RefCell<VoicePresses>>,
+    /// Two free fingers pinching (zoom) or turning (swirl the voices)
+    pub gesture: Rc<RefCell<TwoFingerGesture>>,
+    /// Long-press and double-tap times (`?press=`)
+    pub timings: PressTimings,
+    /// A tap waiting to see whether it becomes a double tap
+    pub taps: Rc<RefCell<TapTracker>>,
+    /// How fast the primary pointer is moving, and the hardest it has
+    /// pressed since going down, for background taps
+    pub pointer_speed: Rc<Cell<PointerSpeed>>,
+    pub press_pressure: Rc<Cell<Option<f32>>>,
+    /// Scrolled over empty space
+    pub volume: Rc<audio::MasterVolume>,
     pub voice_gains: Rc<Vec<web::GainNode>>,
     pub delay_sends: Rc<Vec<web::GainNode>>,
     pub reverb_sends: Rc<Vec<web::GainNode>>,
     pub audio_ctx: web::AudioContext,
-    pub queued_ripple_uv: Rc<RefCell<Option<[f32; 2]>>>,
+    pub queued_ripple_uv: QueuedRipple,
+    /// A Shift-drag across the background in progress
+    pub strum: Rc<RefCell<Strum>>,
+    pub queued_streak: QueuedStreak,
+    /// A press on the background that repeats its note once held
+    pub hold: Rc<RefCell<BackgroundHold>>,
+    /// Takes down taps, drags and mutes while it is recording
+    pub looper: Rc<RefCell<Looper>>,
+    /// Takes down voice drags while it is recording
+    pub automation: Rc<RefCell<Automation>>,
+    /// Where the camera is this frame, so voices are picked as they are seen
+    pub camera: Rc<Cell<CameraPose>>,
+    pub camera_zoom: Rc<Cell<f32>>,
 }
 
 pub fn wire_input_handlers(w: InputWiring) {
     wire_pointermove(&w);
     wire_pointerdown(&w);
     wire_pointerup(&w);
+    wire_pointercancel(&w);
+    wire_wheel(&w);
+    wire_contextmenu(&w);
+}
+
+// Right-click is a voice action, so the browser's menu stays away from the
+// canvas (and from a long press on a phone)
+fn wire_contextmenu(w: &InputWiring) {
+    let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::Event| {
+        ev.prevent_default();
+    }) as Box<dyn FnMut(_)>);
+    _ = w
+        .canvas
+        .add_event_listener_with_callback("contextmenu", closure.as_ref().unchecked_ref());
+    closure.forget();
+}
+
+// Scrolling over a voice shifts it up/down an octave (Alt: its density);
+// over empty space it sets the master volume (Alt: the tempo). Each notch
+// is shown in a toast.
+fn wire_wheel(w: &InputWiring) {
+    let w = w.clone();
+    let canvas_for_listener = w.canvas.clone();
+    let mut notches = WheelNotches::default();
+
+    let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::WheelEvent| {
+        ev.prevent_default();
+        let target = WheelTarget::pick(*w.hover_index.borrow(), ev.alt_key());
+        // Shift+wheel scrolls sideways in some browsers
+        let delta = if ev.delta_y() != 0.0 {
+            ev.delta_y()
+        } else {
+            ev.delta_x()
+        };
+        let steps = notches.scroll(
+            target,
+            wheel_delta_px(delta, ev.delta_mode()),
+            WHEEL_NOTCH_PX,
+        );
+        if steps == 0 {
+            return;
+        }
+        let toast = match target {
+            WheelTarget::Octave(voice) => {
+                execute(
+                    &w,
+                    Command::ShiftOctave {
+                        voice,
+                        octaves: steps,
+                    },
+                );
+                let octave = w.engine.borrow().configs[voice].octave_offset;
+                format!("{} octave {:+}", voice_name(voice), octave)
+            }
+            WheelTarget::Density(voice) => {
+                let density = w.engine.borrow().configs[voice].trigger_probability
+                    + steps as f32 * DENSITY_STEP;
+                execute(&w, Command::SetDensity { voice, density });
+                let density = w.engine.borrow().configs[voice].trigger_probability;
+                format!("{} density {:.0}%", voice_name(voice), density * 100.0)
+            }
+            WheelTarget::Volume => {
+                w.volume.update(|m| m.nudge(steps));
+                format!("Volume {:.0}%", w.volume.mixer().volume * 100.0)
+            }
+            WheelTarget::Tempo => {
+                let bpm = (w.engine.borrow().params.bpm + steps as f32 * WHEEL_BPM_STEP)
+                    .clamp(40.0, 240.0);
+                execute(&w, Command::SetBpm(bpm));
+                format!("Tempo {:.0} BPM", bpm)
+            }
+        };
+        if let Some(document) = w.canvas.owner_document() {
+            overlay::show_toast(&document, &toast);
+        }
+    }) as Box<dyn FnMut(_)>);
+    _ = canvas_for_listener
+        .add_event_listener_with_callback("wheel", closure.as_ref().unchecked_ref());
+    closure.forget();
+}
+
+fn voice_name(voice: usize) -> &'static str {
+    VOICE_NAMES.get(voice).copied().unwrap_or("Voice")
 }
 
 fn wire_pointermove(w: &InputWiring) {
@@ -39,61 +173,48 @@ fn wire_pointermove(w: &InputWiring) {
             return;
         }
 
-        {
+        // The swirl follows the mouse or the first finger down, but not a
+        // pinch
+        if ev.is_primary() && !w.gesture.borrow().is_active() {
             let mut ms = w.mouse_state.borrow_mut();
             ms.x = pos.x;
             ms.y = pos.y;
         }
-
-        let (ro, rd) = render::screen_to_world_ray(&w.canvas, pos.x, pos.y, CAMERA_Z);
-        let mut best = None::<(usize, f32)>;
-        let z_offset = Z_OFFSET;
-
-        let engine_snapshot = w.engine.borrow();
-        for (i, v) in engine_snapshot.voices.iter().enumerate() {
-            let center_world = v.position * SPREAD + z_offset;
-
-            if let Some(t) = input::ray_sphere(ro, rd, center_world, PICK_SPHERE_RADIUS) {
-                if t >= 0.0 {
-                    match best {
-                        Some((_, bt)) if t >= bt => {}
-                        _ => best = Some((i, t)),
-                    }
-                }
+        if ev.is_primary() {
+            note_pointer_feel(&w, &ev);
+            w.hold
+                .borrow_mut()
+                .moved_to(ev.pointer_id(), client_px(&ev));
+            if w.strum.borrow().is_active() {
+                strum_to(&w, &ev);
+                return;
             }
         }
-        if w.drag_state.borrow().active {
-            let plane_z = w.drag_state.borrow().plane_z_world;
-
-            if rd.z.abs() > 1e-6 {
-                let t = (plane_z - ro.z) / rd.z;
-
-                if t >= 0.0 {
-                    let hit_world = ro + rd * t;
-                    let mut eng_pos = (hit_world - Z_OFFSET) / SPREAD;
-                    let max_r = ENGINE_DRAG_MAX_RADIUS;
-                    let len = (eng_pos.x * eng_pos.x + eng_pos.z * eng_pos.z).sqrt();
 
-                    if len > max_r {
-                        let scale = max_r / len;
-                        eng_pos.x *= scale;
-                        eng_pos.z *= scale;
-                    }
-
-                    let vi = w.drag_state.borrow().voice;
-                    let mut eng = w.engine.borrow_mut();
-                    eng.set_voice_position(vi, glam::Vec3::new(eng_pos.x, 0.0, eng_pos.z));
+        let press = {
+            let mut presses = w.presses.borrow_mut();
+            presses.get_mut(ev.pointer_id()).map(|p| {
+                p.moved_to(client_px(&ev));
+                *p
+            })
+        };
+        match press {
+            Some(press) if press.dragging => drag_voice(&w, &press, pos),
+            Some(_) => {}
+            None if w.gesture.borrow().is_active() => {
+                let step = w
+                    .gesture
+                    .borrow_mut()
+                    .moved(ev.pointer_id(), client_px(&ev));
+                if let Some(step) = step {
+                    apply_gesture_step(&w, step);
                 }
             }
-        } else {
-            match best {
-                Some((i, _t)) => {
-                    *w.hover_index.borrow_mut() = Some(i);
-                }
-                None => {
-                    *w.hover_index.borrow_mut() = None;
-                }
+            // Only a mouse or pen hovers; fingers pick where they land
+            None if ev.pointer_type() != "touch" => {
+                *w.hover_index.borrow_mut() = pick_voice(&w, pos, PICK_SPHERE_RADIUS);
             }
+            None => {}
         }
     }) as Box<dyn FnMut(_)>);
 
@@ -109,14 +230,67 @@ fn wire_pointerdown(w: &InputWiring) {
     let canvas_for_listener = w.canvas.clone();
 
     let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::PointerEvent| {
-        if let Some(i) = *w.hover_index.borrow() {
-            let mut ds = w.drag_state.borrow_mut();
-            ds.active = true;
-            ds.voice = i;
-            ds.plane_z_world = w.engine.borrow().voices[i].position.z * SPREAD.z + Z_OFFSET.z;
-            log::info!("[mouse] begin drag on voice {}", i);
-        }
-        w.mouse_state.borrow_mut().down = true;
+        let pos = input::pointer_canvas_px(&ev, &w.canvas);
+        if ev.button() == 2 {
+            // Right-click acts on a voice at once and never drags
+            if let Some(voice) = pick_voice(&w, pos, PICK_SPHERE_RADIUS) {
+                let cmd = context_command(&w, voice, ev.shift_key());
+                execute(&w, cmd);
+            }
+            ev.prevent_default();
+            return;
+        }
+        let touch = ev.pointer_type() == "touch";
+        let radius = PICK_SPHERE_RADIUS * if touch { TOUCH_PICK_SCALE } else { 1.0 };
+        if let Some(voice) = pick_voice(&w, pos, radius) {
+            let origin = w.engine.borrow().voices[voice].position;
+            let pressed = w.presses.borrow_mut().press(VoicePress {
+                pointer_id: ev.pointer_id(),
+                voice,
+                start_px: client_px(&ev),
+                start_sec: ev.time_stamp() / 1000.0,
+                origin,
+                plane_z_world: origin.z * SPREAD.z + Z_OFFSET.z,
+                dragging: false,
+            });
+            if pressed {
+                // Auto-motion lets go of the voice until it is released
+                w.engine.borrow_mut().set_held(voice, true);
+                log::info!("[pointer] press on voice {voice}");
+            }
+        } else if ev.shift_key() && ev.is_primary() {
+            let [uvx, uvy] = input::pointer_canvas_uv(&ev, &w.canvas);
+            w.strum.borrow_mut().start(uvx, ev.time_stamp() / 1000.0);
+            w.queued_streak.borrow_mut().push([uvx, uvy]);
+            log::info!("[pointer] strum");
+        } else if w.gesture.borrow_mut().down(ev.pointer_id(), client_px(&ev)) {
+            let origins = w
+                .engine
+                .borrow()
+                .voices
+                .iter()
+                .map(|v| v.position)
+                .collect();
+            w.gesture.borrow_mut().origins = origins;
+            // The first finger's hold became half of the gesture
+            release_hold(&w);
+            log::info!("[pointer] two-finger gesture");
+        } else if ev.is_primary() {
+            let uv = input::pointer_canvas_uv(&ev, &w.canvas);
+            let serial = w
+                .hold
+                .borrow_mut()
+                .press(ev.pointer_id(), client_px(&ev), uv);
+            ripen_hold(&w, serial);
+        }
+        if ev.is_primary() {
+            let mut ms = w.mouse_state.borrow_mut();
+            ms.x = pos.x;
+            ms.y = pos.y;
+            ms.down = true;
+            w.press_pressure.set(None);
+            note_pointer_feel(&w, &ev);
+        }
         _ = w.canvas.set_pointer_capture(ev.pointer_id());
         ev.prevent_default();
     }) as Box<dyn FnMut(_)>);
@@ -129,53 +303,82 @@ fn wire_pointerup(w: &InputWiring) {
     let w = w.clone();
 
     let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::PointerEvent| {
-        let was_dragging = w.drag_state.borrow().active;
-
-        if was_dragging {
-            w.drag_state.borrow_mut().active = false;
-        } else if let Some(i) = *w.hover_index.borrow() {
-            let shift = ev.shift_key();
-            let alt = ev.alt_key();
-            if alt {
-                w.engine.borrow_mut().toggle_solo(i);
-                log::info!("[click] solo voice {}", i);
-            } else if shift {
-                w.engine.borrow_mut().reseed_voice(i, None);
-                log::info!("[click] reseed voice {}", i);
-            } else {
-                w.engine.borrow_mut().toggle_mute(i);
-                log::info!("[click] toggle mute voice {}", i);
+        // Right-clicks were handled on the way down
+        if ev.button() == 2 {
+            return;
+        }
+        let released = w.presses.borrow_mut().release(ev.pointer_id());
+        // A strum played its own notes, so no tap note on letting go (a
+        // Shift-click that crossed nothing still plays one)
+        let strummed = ev.is_primary() && w.strum.borrow_mut().stop();
+        // Nor after a held note has been repeating (if the engine is paused
+        // it never did, and the tap note plays)
+        let repeated =
+            w.hold.borrow_mut().release(ev.pointer_id()) && w.engine.borrow_mut().release_note();
+        if let Some(press) = released {
+            let i = press.voice;
+            w.engine.borrow_mut().set_held(i, false);
+            let now_sec = ev.time_stamp() / 1000.0;
+            let cmd = match press.kind(now_sec, &w.timings) {
+                PressKind::Drag => {
+                    // The drag was applied incrementally; record it as a single undo step
+                    let position = w.engine.borrow().voices[i].position;
+                    if position != press.origin {
+                        w.history.borrow_mut().record(
+                            Command::SetPosition { voice: i, position },
+                            Command::SetPosition {
+                                voice: i,
+                                position: press.origin,
+                            },
+                        );
+                    }
+                    None
+                }
+                PressKind::Tap if ev.alt_key() => {
+                    log::info!("[click] solo voice {}", i);
+                    Some(Command::ToggleSolo { voice: i })
+                }
+                PressKind::Tap if ev.shift_key() => {
+                    log::info!("[click] reseed voice {}", i);
+                    Some(Command::Reseed {
+                        voice: i,
+                        seed: None,
+                    })
+                }
+                // A plain tap mutes only once no second tap has followed
+                PressKind::Tap => match w.taps.borrow_mut().tap(i, now_sec, &w.timings) {
+                    TapOutcome::Double => {
+                        log::info!("[click] double-click reseed voice {}", i);
+                        Some(Command::Reseed {
+                            voice: i,
+                            seed: None,
+                        })
+                    }
+                    TapOutcome::Pending(serial) => {
+                        settle_tap(&w, serial);
+                        None
+                    }
+                },
+                // A finger (or button) held still does what a right-click does
+                PressKind::LongPress => Some(context_command(&w, i, ev.shift_key())),
+            };
+            if let Some(cmd) = cmd {
+                execute(&w, cmd);
             }
-        } else {
+        } else if !end_gesture(&w, ev.pointer_id()) && !strummed && !repeated {
             let [uvx, uvy] = input::pointer_canvas_uv(&ev, &w.canvas);
             if uvx.is_finite() && uvy.is_finite() {
-                let midi = 60.0 + uvx * 24.0;
-                let freq = midi_to_hz(midi as f32);
-                let vel = (0.35 + 0.65 * uvy) as f32;
-                let eng = w.engine.borrow();
-                let norm_xs: Vec<f32> = eng
-                    .voices
-                    .iter()
-                    .map(|v| (v.position.x / 3.0).clamp(-1.0, 1.0) * 0.5 + 0.5)
-                    .collect();
-                let best_i = crate::input::nearest_index_by_uvx(&norm_xs, uvx);
+                let freq = midi_to_hz(background_midi(uvx));
+                let speed = w.pointer_speed.get().speed(ev.time_stamp() / 1000.0);
+                let feel = TapExpression::new(uvy, speed, w.press_pressure.get());
                 let dur = 0.35 + 0.25 * (1.0 - uvy as f64);
-                let wf = eng.configs[best_i].waveform;
-                drop(eng);
-                audio::trigger_one_shot(
-                    &w.audio_ctx,
-                    wf,
-                    freq,
-                    vel,
-                    dur,
-                    &w.voice_gains[best_i],
-                    &w.delay_sends[best_i],
-                    &w.reverb_sends[best_i],
-                );
-                *w.queued_ripple_uv.borrow_mut() = Some([uvx, uvy]);
+                let ripple = Some(([uvx, uvy], feel.ripple_amp));
+                play_background_note(&w, uvx, freq, &feel, dur, 0.0, ripple);
             }
         }
-        w.mouse_state.borrow_mut().down = false;
+        if ev.is_primary() {
+            w.mouse_state.borrow_mut().down = false;
+        }
         ev.prevent_default();
     }) as Box<dyn FnMut(_)>);
 
@@ -185,3 +388,318 @@ fn wire_pointerup(w: &InputWiring) {
 
     closure.forget();
 }
+
+// The browser took the pointer away (a scroll gesture, a palm): let go of its
+// voice without acting on it
+fn wire_pointercancel(w: &InputWiring) {
+    let w = w.clone();
+
+    let closure = wasm_bindgen::closure::Closure::wrap(Box::new(move |ev: web::PointerEvent| {
+        if let Some(press) = w.presses.borrow_mut().release(ev.pointer_id()) {
+            w.engine.borrow_mut().set_held(press.voice, false);
+        }
+        end_gesture(&w, ev.pointer_id());
+        if ev.is_primary() {
+            w.mouse_state.borrow_mut().down = false;
+            _ = w.strum.borrow_mut().stop();
+        }
+        if w.hold.borrow_mut().release(ev.pointer_id()) {
+            w.engine.borrow_mut().release_note();
+        }
+    }) as Box<dyn FnMut(_)>);
+
+    if let Some(wnd) = web::window() {
+        _ = wnd.add_event_listener_with_callback("pointercancel", closure.as_ref().unchecked_ref());
+    }
+
+    closure.forget();
+}
+
+fn execute(w: &InputWiring, cmd: Command) {
+    w.history
+        .borrow_mut()
+        .execute(&mut w.engine.borrow_mut(), cmd);
+}
+
+// Mute the tapped voice once the double-tap window has passed without a
+// second tap
+fn settle_tap(w: &InputWiring, serial: u32) {
+    let Some(wnd) = web::window() else {
+        return;
+    };
+    let delay_ms = (w.timings.double_tap_sec * 1000.0).round() as i32;
+    let w = w.clone();
+    let settle = wasm_bindgen::closure::Closure::once_into_js(move || {
+        let voice = w.taps.borrow_mut().expire(serial);
+        if let Some(voice) = voice {
+            log::info!("[click] toggle mute voice {}", voice);
+            execute(&w, Command::ToggleMute { voice });
+            capture(&w, 0.0, LoopAction::Mute { voice });
+        }
+    });
+    _ = wnd.set_timeout_with_callback_and_timeout_and_arguments_0(settle.unchecked_ref(), delay_ms);
+}
+
+// Once the long-press time has passed with the background press `serial`
+// still down and still, repeat its note on the grid until it is let go
+fn ripen_hold(w: &InputWiring, serial: u32) {
+    let Some(wnd) = web::window() else {
+        return;
+    };
+    let delay_ms = (w.timings.long_press_sec * 1000.0).round() as i32;
+    let w = w.clone();
+    let ripen = wasm_bindgen::closure::Closure::once_into_js(move || {
+        let Some([uvx, uvy]) = w.hold.borrow_mut().ripen(serial) else {
+            return;
+        };
+        if !(uvx.is_finite() && uvy.is_finite()) {
+            return;
+        }
+        let feel = TapExpression::new(uvy, 0.0, w.press_pressure.get());
+        let note = BackgroundNote {
+            voice: background_voice(&w, uvx),
+            frequency_hz: midi_to_hz(background_midi(uvx)),
+            velocity: feel.velocity,
+            cutoff_hz: feel.cutoff_hz,
+            duration_sec: 0.35 + 0.25 * (1.0 - uvy as f64),
+        };
+        w.engine.borrow_mut().hold_note(note);
+        *w.queued_ripple_uv.borrow_mut() = Some(([uvx, uvy], feel.ripple_amp));
+        log::info!("[pointer] note repeat");
+    });
+    _ = wnd.set_timeout_with_callback_and_timeout_and_arguments_0(ripen.unchecked_ref(), delay_ms);
+}
+
+// Drop the background hold for a two-finger gesture, stopping its repeats
+fn release_hold(w: &InputWiring) {
+    let repeating = w.hold.borrow_mut().cancel();
+    if repeating {
+        w.engine.borrow_mut().release_note();
+    }
+}
+
+// A right-click or long press: cycle the voice's waveform, or with Shift
+// lock/unlock its position
+fn context_command(w: &InputWiring, voice: usize, shift: bool) -> Command {
+    if shift {
+        log::info!("[click] toggle lock voice {}", voice);
+        return Command::ToggleLock { voice };
+    }
+    let eng = w.engine.borrow();
+    let waveform = eng.configs[voice].waveform.next(eng.wavetables.len());
+    log::info!("[click] voice {} waveform={:?}", voice, waveform);
+    Command::SetWaveform { voice, waveform }
+}
+
+// Pinch zooms the camera; turning the fingers swirls every voice not held by
+// another finger (nor locked) around the centre
+fn apply_gesture_step(w: &InputWiring, step: GestureStep) {
+    w.camera_zoom.set(step.zoom(w.camera_zoom.get()));
+    let presses = w.presses.borrow();
+    let mut eng = w.engine.borrow_mut();
+    for i in 0..eng.voices.len() {
+        if !presses.holds(i) && !eng.is_locked(i) {
+            let position = step.rotate(eng.voices[i].position);
+            eng.set_voice_position(i, position);
+        }
+    }
+}
+
+// Lift a pointer from the gesture, recording the swirl as one undo step when
+// the gesture ends. True if the pointer took part in one.
+fn end_gesture(w: &InputWiring, pointer_id: i32) -> bool {
+    let mut gesture = w.gesture.borrow_mut();
+    let was_active = gesture.is_active();
+    let gestured = gesture.up(pointer_id);
+    if was_active {
+        let origins = std::mem::take(&mut gesture.origins);
+        let eng = w.engine.borrow();
+        let (forward, inverse): (Vec<_>, Vec<_>) = eng
+            .voices
+            .iter()
+            .zip(origins)
+            .enumerate()
+            .filter(|(_, (v, origin))| v.position != *origin)
+            .map(|(voice, (v, origin))| {
+                (
+                    Command::SetPosition {
+                        voice,
+                        position: v.position,
+                    },
+                    Command::SetPosition {
+                        voice,
+                        position: origin,
+                    },
+                )
+            })
+            .unzip();
+        drop(eng);
+        if !forward.is_empty() {
+            w.history
+                .borrow_mut()
+                .record(Command::Batch(forward), Command::Batch(inverse));
+        }
+    }
+    gestured
+}
+
+// A one-shot on the voice nearest `uvx` across the screen, through its gain
+// and sends, `delay_sec` from now, with the ripple (screen uv and size) it
+// throws if any. The looper takes it down as played.
+fn play_background_note(
+    w: &InputWiring,
+    uvx: f32,
+    frequency_hz: f32,
+    feel: &TapExpression,
+    duration_sec: f64,
+    delay_sec: f64,
+    ripple: Option<([f32; 2], f32)>,
+) {
+    let best_i = background_voice(w, uvx);
+    if ripple.is_some() {
+        *w.queued_ripple_uv.borrow_mut() = ripple;
+    }
+    let note = BackgroundNote {
+        voice: best_i,
+        frequency_hz,
+        velocity: feel.velocity,
+        cutoff_hz: feel.cutoff_hz,
+        duration_sec,
+    };
+    capture(w, delay_sec, LoopAction::Tap { note, ripple });
+    let wf = w.engine.borrow().configs[best_i].waveform;
+    audio::trigger_one_shot(
+        &w.audio_ctx,
+        wf,
+        frequency_hz,
+        feel.velocity,
+        feel.cutoff_hz,
+        duration_sec,
+        delay_sec,
+        &w.voice_gains[best_i],
+        &w.delay_sends[best_i],
+        &w.reverb_sends[best_i],
+    );
+}
+
+// The voice whose place across the screen is nearest `uvx`
+fn background_voice(w: &InputWiring, uvx: f32) -> usize {
+    let norm_xs: Vec<f32> = w
+        .engine
+        .borrow()
+        .voices
+        .iter()
+        .map(|v| (v.position.x / 3.0).clamp(-1.0, 1.0) * 0.5 + 0.5)
+        .collect();
+    crate::input::nearest_index_by_uvx(&norm_xs, uvx)
+}
+
+// Shift-drag across the background: pluck every scale note the pointer
+// crossed, once it moves fast enough, and extend the streak behind it
+fn strum_to(w: &InputWiring, ev: &web::PointerEvent) {
+    let [uvx, uvy] = input::pointer_canvas_uv(ev, &w.canvas);
+    if !(uvx.is_finite() && uvy.is_finite()) {
+        return;
+    }
+    let now_sec = ev.time_stamp() / 1000.0;
+    let strings = {
+        let eng = w.engine.borrow();
+        strum_strings(eng.params.scale, eng.params.root_midi)
+    };
+    let crossed = w.strum.borrow_mut().sweep(uvx, now_sec, &strings);
+    w.queued_streak.borrow_mut().push([uvx, uvy]);
+    let speed = w.pointer_speed.get().speed(now_sec);
+    if speed < STRUM_MIN_PX_PER_SEC {
+        return;
+    }
+    let feel = TapExpression::new(uvy, speed, w.press_pressure.get());
+    let detune = w.engine.borrow().params.detune_cents;
+    for (midi, delay_sec) in crossed {
+        let freq = midi_to_hz_with_detune(midi, detune);
+        play_background_note(w, background_uvx(midi), freq, &feel, 0.6, delay_sec, None);
+    }
+}
+
+// Follow the primary pointer's speed, and its pressure while it is down
+fn note_pointer_feel(w: &InputWiring, ev: &web::PointerEvent) {
+    let mut speed = w.pointer_speed.get();
+    speed.moved(client_px(ev), ev.time_stamp() / 1000.0);
+    w.pointer_speed.set(speed);
+    if let Some(pressure) = measured_pressure(&ev.pointer_type(), ev.pressure()) {
+        let peak = w.press_pressure.get().map_or(pressure, |p| p.max(pressure));
+        w.press_pressure.set(Some(peak));
+    }
+}
+
+fn client_px(ev: &web::PointerEvent) -> [f32; 2] {
+    [ev.client_x() as f32, ev.client_y() as f32]
+}
+
+// The nearest voice whose hit sphere the ray through `pos` meets
+fn pick_voice(w: &InputWiring, pos: glam::Vec2, radius: f32) -> Option<usize> {
+    let camera = w.camera.get();
+    let (ro, rd) = render::screen_to_world_ray(&w.canvas, pos.x, pos.y, camera.eye, camera.target);
+    let mut best = None::<(usize, f32)>;
+    for (i, v) in w.engine.borrow().voices.iter().enumerate() {
+        let center_world = v.position * SPREAD + Z_OFFSET;
+        if let Some(t) = input::ray_sphere(ro, rd, center_world, radius) {
+            if t >= 0.0 {
+                match best {
+                    Some((_, bt)) if t >= bt => {}
+                    _ => best = Some((i, t)),
+                }
+            }
+        }
+    }
+    best.map(|(i, _)| i)
+}
+
+// Move the pressed voice to where the ray through `pos` meets its drag plane.
+// A locked voice is left out of the drag, though its press still taps it
+// (mute, solo, reseed)
+fn drag_voice(w: &InputWiring, press: &VoicePress, pos: glam::Vec2) {
+    if w.engine.borrow().is_locked(press.voice) {
+        return;
+    }
+    let camera = w.camera.get();
+    let (ro, rd) = render::screen_to_world_ray(&w.canvas, pos.x, pos.y, camera.eye, camera.target);
+    if rd.z.abs() <= 1e-6 {
+        return;
+    }
+    let t = (press.plane_z_world - ro.z) / rd.z;
+    if t < 0.0 {
+        return;
+    }
+    let hit_world = ro + rd * t;
+    let mut eng_pos = (hit_world - Z_OFFSET) / SPREAD;
+    let max_r = ENGINE_DRAG_MAX_RADIUS;
+    let len = (eng_pos.x * eng_pos.x + eng_pos.z * eng_pos.z).sqrt();
+
+    if len > max_r {
+        let scale = max_r / len;
+        eng_pos.x *= scale;
+        eng_pos.z *= scale;
+    }
+
+    let position = glam::Vec3::new(eng_pos.x, 0.0, eng_pos.z);
+    w.engine
+        .borrow_mut()
+        .set_voice_position(press.voice, position);
+    let voice = press.voice;
+    capture(w, 0.0, LoopAction::Move { voice, position });
+}
+
+// Hand `action`, done `delay_sec` from now, to the looper (and a drag to
+// the automation timeline too)
+fn capture(w: &InputWiring, delay_sec: f64, action: LoopAction) {
+    let at = w
+        .engine
+        .borrow()
+        .grid_position(w.audio_ctx.current_time() + delay_sec);
+    if let LoopAction::Move { voice, position } = action {
+        w.automation
+            .borrow_mut()
+            .capture(at, Command::SetPosition { voice, position });
+    }
+    w.looper.borrow_mut().capture(at, action);
+}
@@ -1,10 +1,51 @@
+use crate::audio;
 use crate::constants::*;
-use crate::core::{MusicEngine, Waveform};
+use crate::core::antialias::Antialias;
+use crate::core::attract::{random_tonality, Attract, AttractEvent};
+use crate::core::automation::Automation;
+use crate::core::bloom::Bloom;
+use crate::core::choreography::{CameraPose, Choreographer};
+use crate::core::clock_sync::MidiClockFollower;
+use crate::core::displays::{DisplayBurst, DisplayFrame};
+use crate::core::frame_math::{
+    blend_swirl_energy, fx_levels, smooth_pulses, step_inertial_swirl, voice_mix, MAX_PULSE_ENERGY,
+};
+use crate::core::lens::Lens;
+use crate::core::lifecycle::{AudioLifecycle, SchedulerGate};
+use crate::core::looper::{LoopAction, Looper};
+use crate::core::mirror::Mirror;
+use crate::core::motion::MotionMode;
+use crate::core::musical_clock::{BarCounter, MusicalClock};
+use crate::core::note_repeat::BackgroundNote;
+use crate::core::onset::{band_levels, PulseSource};
+use crate::core::polyphony::{AutoGain, VoiceAllocator};
+use crate::core::profiler::{note_oscillators, FrameProfiler};
+use crate::core::render_scale::{RenderScale, RenderScaleMode, MAX_RENDER_SCALE};
+use crate::core::screenshot::Screenshot;
+use crate::core::sketch::{sketch_dots, sketch_lock_marks, sketch_selection};
+use crate::core::smf::MidiRecorder;
+use crate::core::spectrum::{scope_trace, SpectrumBars, VisualLayers, SCOPE_POINTS};
+use crate::core::stereo::swirl_width;
+use crate::core::theme::{Theme, THEMES};
+use crate::core::tonemap::{Exposure, Tonemap};
+use crate::core::trails::{VoiceTrail, TRAIL_POINTS};
+use crate::core::visualizer::Visualizer;
+use crate::core::voice_style::VoiceStyle;
+use crate::core::{Command, EventBus, MusicEngine, NoteEvent};
+use crate::displays::DisplayLink;
+use crate::dom;
+use crate::events::keyboard::get_scale_name;
+use crate::events::{QueuedRipple, QueuedStreak};
 use crate::input;
+use crate::midi;
+use crate::overlay;
 use crate::render;
+use crate::shader_reload::ShaderReload;
+use crate::sketch::Sketch;
+use crate::video::VideoExport;
 use glam::Vec3;
 use instant::Instant;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
@@ -14,22 +55,35 @@ use crate::constants::CAMERA_Z;
 
 pub struct FrameContext<'a> {
     pub engine: Rc<RefCell<MusicEngine>>,
+    pub event_bus: EventBus,
     pub paused: Rc<RefCell<bool>>,
+    /// The page being hidden and the audio stopping, which hold the scheduler
+    pub lifecycle: Rc<Cell<AudioLifecycle>>,
     pub pulses: Rc<RefCell<Vec<f32>>>,
     #[allow(dead_code)] // Used in pointer events, not directly in frame module
     pub hover_index: Rc<RefCell<Option<usize>>>,
+    /// Voice selected from the keyboard, ringed on screen
+    pub selected_voice: Rc<Cell<Option<usize>>>,
 
     pub canvas: web::HtmlCanvasElement,
     pub mouse: Rc<RefCell<input::MouseState>>,
 
     pub audio_ctx: web::AudioContext,
     pub listener: web::AudioListener,
+    pub voice_fades: Rc<Vec<web::GainNode>>,
+    pub voice_ducks: Rc<Vec<web::GainNode>>,
     pub voice_gains: Rc<Vec<web::GainNode>>,
     pub delay_sends: Rc<Vec<web::GainNode>>,
     pub reverb_sends: Rc<Vec<web::GainNode>>,
     pub voice_panners: Vec<web::PannerNode>,
 
     pub reverb_wet: web::GainNode,
+    /// Master buses, for the swirl-modulated stereo width
+    pub fx: Rc<audio::FxBuses>,
+    /// Width setting the swirl modulates around
+    pub stereo_width: Rc<Cell<f32>>,
+    /// Metronome bus that drained clicks are played into
+    pub click_bus: web::GainNode,
     pub delay_wet: web::GainNode,
     pub delay_feedback: web::GainNode,
     pub sat_pre: web::GainNode,
@@ -38,9 +92,80 @@ pub struct FrameContext<'a> {
 
     pub analyser: Option<web::AnalyserNode>,
     pub analyser_buf: Rc<RefCell<Vec<f32>>>,
+    /// Hit detection on the master output or the microphone
+    pub onset_input: Option<audio::OnsetInput>,
+    /// Whether scheduled notes or detected onsets drive the pulses
+    pub pulse_source: Rc<Cell<PulseSource>>,
+    /// Hold the music (no new notes) and just visualize the input
+    pub listen_only: Rc<Cell<bool>>,
+    /// Spectrum and oscilloscope layers over the waves, read from the same
+    /// signal as the onsets (the master output, or the microphone)
+    pub visual_layers: Rc<Cell<VisualLayers>>,
+    pub spectrum_bars: SpectrumBars,
+    pub scope_samples: Vec<f32>,
+    pub scope_points: [f32; SCOPE_POINTS],
+    /// Recent positions of each voice, drawn as fading streaks
+    pub voice_trails: Vec<VoiceTrail>,
+    pub packed_trails: Vec<[f32; 4]>,
 
     pub gpu: Option<render::GpuState<'a>>,
-    pub queued_ripple_uv: Rc<RefCell<Option<[f32; 2]>>>,
+    /// Display windows spanning the scene across several screens: this
+    /// window either sends them each frame or is one of them
+    pub displays: Option<DisplayLink>,
+    /// Idle attract mode, and the camera and motion to go back to after it
+    pub attract: Rc<Cell<Attract>>,
+    pub attract_restore: Option<(bool, MotionMode)>,
+    /// Any pointer, key or wheel input since the last frame
+    pub input_seen: Rc<Cell<bool>>,
+    /// Canvas2D circles drawn instead when WebGPU is unavailable
+    pub sketch: Option<Sketch>,
+    /// Compiles and swaps in custom visualizer code, and refetches the
+    /// shaders while developing them
+    pub shader_reload: ShaderReload,
+    pub render_mode: Rc<RefCell<render::RenderMode>>,
+    pub theme: Rc<Cell<&'static Theme>>,
+    pub bloom: Rc<Cell<Bloom>>,
+    pub exposure: Rc<Cell<Exposure>>,
+    pub tonemap: Rc<Cell<Tonemap>>,
+    /// Depth of field and vignette after the composite
+    pub lens: Rc<Cell<Lens>>,
+    /// Kaleidoscope symmetry in the composite, and the bars it steps on
+    pub mirror: Rc<Cell<Mirror>>,
+    pub mirror_bars: BarCounter,
+    /// Starfield brightness behind the waves
+    pub stars: Rc<Cell<f32>>,
+    /// Anti-aliasing asked for (the renderer falls back where unsupported)
+    pub antialias: Rc<Cell<Antialias>>,
+    /// Peak brightness and flash limits in the post chain
+    pub safe_visuals: Rc<Cell<bool>>,
+    /// Chosen render scale (fixed or automatic) and the controller that
+    /// follows the frame time when it is automatic
+    pub render_scale_mode: Rc<Cell<RenderScaleMode>>,
+    pub render_scale: RenderScale,
+    /// Whether the status line is drawn on the canvas by the renderer
+    pub canvas_status: Rc<Cell<bool>>,
+    /// Whether the performance HUD shows, and the frame timings behind it
+    pub perf_hud: Rc<Cell<bool>>,
+    pub profiler: FrameProfiler,
+    /// Screenshot asked for from the keyboard, at this multiple of the
+    /// canvas size
+    pub screenshot: Rc<Cell<Option<u32>>>,
+    /// Video export in progress, started from the Export panel
+    pub video: Rc<RefCell<Option<VideoExport>>>,
+    /// Whether the cinematic camera is on, the choreography it follows, and
+    /// the pose it gave this frame (shared with picking)
+    pub cinematic: Rc<Cell<bool>>,
+    pub choreographer: Choreographer,
+    pub camera: Rc<Cell<CameraPose>>,
+    /// Pinch zoom over whichever camera is in use (1 = as it moves)
+    pub camera_zoom: Rc<Cell<f32>>,
+    pub visualizer: Rc<Cell<Visualizer>>,
+    pub queued_ripple_uv: QueuedRipple,
+    pub queued_streak: QueuedStreak,
+    /// Taps, drags and mutes played back on the grid
+    pub looper: Rc<RefCell<Looper>>,
+    /// Parameter changes played back as an arrangement
+    pub automation: Rc<RefCell<Automation>>,
 
     pub last_instant: Instant,
     pub prev_uv: [f32; 2],
@@ -49,6 +174,26 @@ pub struct FrameContext<'a> {
     pub swirl_vel: [f32; 2],
     pub swirl_initialized: bool,
     pub pulse_energy: [f32; 3],
+    /// Session capture for MIDI file export
+    pub midi_recorder: Rc<RefCell<MidiRecorder>>,
+    /// Live MIDI output of generated notes (silent until a port is chosen)
+    pub midi_out: Rc<RefCell<midi::MidiOut>>,
+    /// Incoming MIDI clock; while it runs the beat grid follows it
+    pub midi_clock: Rc<RefCell<MidiClockFollower>>,
+    /// Scheduled notes not yet audible: (start time, voice, velocity)
+    pub pending_pulses: Vec<(f64, usize, f32)>,
+    /// Scheduled notes whose particle bursts wait until they are heard
+    pub pending_bursts: Vec<NoteEvent>,
+    /// Scheduled beats, counted as they are heard for the beat-synced pulse
+    pub musical_clock: MusicalClock,
+    /// Sounding notes per voice, for the polyphony cap
+    pub note_allocator: VoiceAllocator<audio::NoteHandle>,
+    /// Voice gain compensation for how many notes are sounding
+    pub auto_gain: AutoGain,
+    /// Off-main-thread synth; `None` falls back to a node pair per note
+    pub worklet_synth: Option<audio::WorkletSynth>,
+    /// Wavetables and samples as Web Audio objects, for the node path
+    pub synth_assets: audio::SynthAssets,
 }
 
 impl<'a> FrameContext<'a> {
@@ -58,22 +203,130 @@ impl<'a> FrameContext<'a> {
         self.last_instant = now;
         let dt_sec = dt.as_secs_f32();
 
+        // Deliver state changes from input handlers before scheduling
+        let engine_events = self.engine.borrow_mut().drain_events();
+        self.event_bus.dispatch(&engine_events);
+
+        // A display window draws the control window's scene, not its own
+        let shown = self
+            .displays
+            .as_ref()
+            .filter(|d| d.slice().is_some())
+            .and_then(|d| d.take());
+        if let Some(f) = &shown {
+            self.show_display_frame(f);
+        }
+
         let audio_time = self.audio_ctx.current_time();
+        let heard_time = audio_time - audio::output_latency_sec(&self.audio_ctx);
         let mut note_events = Vec::new();
-        if !*self.paused.borrow() {
-            self.engine.borrow_mut().tick(dt, &mut note_events);
+        // A video export draws the piece from its own schedule, so live
+        // scheduling holds until it ends
+        let exporting = self.video.borrow().is_some();
+        let mut attract = self.attract.get();
+        let input = self.input_seen.replace(false);
+        if let Some(event) = attract.update(dt_sec, input || exporting) {
+            self.attract_event(event);
+        }
+        self.attract.set(attract);
+        if attract.is_active() && *self.paused.borrow() {
+            // Voices drift even before the audio has been started
+            self.engine.borrow_mut().advance_motion(dt);
+        }
+        let mut lifecycle = self.lifecycle.get();
+        let gate = lifecycle.gate(self.audio_ctx.state() == web::AudioContextState::Running);
+        self.lifecycle.set(lifecycle);
+        if gate == SchedulerGate::Resync {
+            // Back from a hidden tab or a suspended context: the grid carries on
+            // from here, and pulses and bursts that came due meanwhile are
+            // dropped rather than landing all at once
+            self.engine.borrow_mut().resync_grid(audio_time);
+            self.pending_pulses.retain(|p| p.0 > heard_time);
+            self.pending_bursts
+                .retain(|ev| ev.start_time_sec > heard_time);
+        }
+        let held = gate == SchedulerGate::Hold;
+        if !*self.paused.borrow() && !self.listen_only.get() && !exporting && !held {
+            {
+                let mut eng = self.engine.borrow_mut();
+                eng.advance_motion(dt);
+                if let Some(lock) = self.midi_clock.borrow().lock(heard_time) {
+                    eng.lock_to_transport(&lock);
+                }
+                eng.schedule(audio_time, SCHEDULE_LOOKAHEAD_SEC, &mut note_events);
+                for click in eng.drain_clicks() {
+                    audio::schedule_click(&self.audio_ctx, &self.click_bus, &click);
+                }
+                self.musical_clock.queue(eng.drain_beats());
+                // A background note held down plays on each step it repeats on
+                for hit in eng.drain_repeats() {
+                    self.play_background_note(&eng, &hit.note, hit.time_sec - audio_time);
+                }
+                self.play_loop(&mut eng, audio_time);
+                self.play_automation(&mut eng, audio_time);
+            }
+            // Visual pulses wait until their note is actually audible, including
+            // the device output latency (large on Bluetooth headphones)
+            if self.pulse_source.get() == PulseSource::Notes {
+                self.pending_pulses.extend(
+                    note_events
+                        .iter()
+                        .map(|ev| (ev.start_time_sec, ev.voice_index, ev.velocity)),
+                );
+            }
+            // Particle bursts too, when there is a GPU to draw them
+            if self.gpu.is_some() {
+                self.pending_bursts.extend(note_events.iter().cloned());
+            }
+            let mut recorder = self.midi_recorder.borrow_mut();
+            for ev in &note_events {
+                recorder.record(ev);
+            }
+            self.midi_out
+                .borrow()
+                .send_notes(&note_events, audio_time, audio_time - heard_time);
+        }
+
+        // Detected hits flash every voice: output onsets once they are heard,
+        // microphone onsets straight away
+        let source = self.pulse_source.get();
+        if let (Some(input), true) = (&mut self.onset_input, source != PulseSource::Notes) {
+            if let Some(onset) = input.poll(audio_time) {
+                let at = match source {
+                    PulseSource::Mic => heard_time,
+                    _ => onset.at_sec,
+                };
+                let velocity = (onset.strength * ONSET_PULSE_GAIN).min(1.0);
+                let voices = self.pulses.borrow().len();
+                self.pending_pulses
+                    .extend((0..voices).map(|voice| (at, voice, velocity)));
+            }
+        }
+        // Reactive mode: the microphone's bass, mids and highs keep the bass,
+        // lead and pad voices lit between hits
+        if let (Some(a), PulseSource::Mic) = (&self.analyser, source) {
+            let mut buf = self.analyser_buf.borrow_mut();
+            buf.resize(a.frequency_bin_count() as usize, 0.0);
+            a.get_float_frequency_data(&mut buf);
+            let bin_hz = self.audio_ctx.sample_rate() / a.fft_size() as f32;
+            for (e, level) in self.pulse_energy.iter_mut().zip(band_levels(&buf, bin_hz)) {
+                *e = e.max(level * MIC_BAND_PULSE_GAIN);
+            }
         }
 
         {
             let pulses_copy: Vec<f32> = {
                 let mut pulses_ref = self.pulses.borrow_mut();
                 let n = pulses_ref.len().min(3);
-                for ev in &note_events {
-                    if ev.voice_index < n {
-                        self.pulse_energy[ev.voice_index] =
-                            (self.pulse_energy[ev.voice_index] + ev.velocity as f32).min(1.8);
+                for &(_, voice, velocity) in
+                    self.pending_pulses.iter().filter(|p| p.0 <= heard_time)
+                {
+                    if voice < n {
+                        self.pulse_energy[voice] =
+                            (self.pulse_energy[voice] + velocity).min(MAX_PULSE_ENERGY);
                     }
                 }
+                self.pending_pulses.retain(|p| p.0 > heard_time);
                 smooth_pulses(&mut pulses_ref, &mut self.pulse_energy, dt_sec);
                 pulses_ref.clone()
             }; // drop pulses_ref here
@@ -86,7 +339,7 @@ impl<'a> FrameContext<'a> {
             self.update_swirl(uv, dt_sec, mouse_down);
 
             // Global FX modulation
-            apply_global_fx_swirl(
+            let fx = audio::apply_global_fx_swirl(
                 &self.reverb_wet,
                 &self.delay_wet,
                 &self.delay_feedback,
@@ -95,32 +348,43 @@ impl<'a> FrameContext<'a> {
                 &self.sat_dry,
                 self.swirl_energy,
                 uv,
+                Some(audio_time),
+            );
+            audio::set_stereo_width(
+                &self.fx,
+                swirl_width(self.stereo_width.get(), self.swirl_energy),
+                Some(audio_time),
             );
 
-            // Per-voice audio positioning and sends
-            let voice_positions_snapshot: Vec<Vec3> = {
+            // Per-voice audio positioning and sends, glided so per-frame
+            // updates don't zipper
+            let now = Some(audio_time);
+            let (voice_positions_snapshot, sources, sends): (Vec<Vec3>, Vec<Vec3>, Vec<_>) = {
                 let eng = self.engine.borrow();
-                eng.voices.iter().map(|v| v.position).collect()
+                let mut snapshot = (Vec::new(), Vec::new(), Vec::new());
+                for (v, c) in eng.voices.iter().zip(&eng.configs) {
+                    snapshot.0.push(v.position);
+                    snapshot
+                        .1
+                        .push(c.spatial.source_position(v.position, audio_time));
+                    snapshot.2.push(c.sends);
+                }
+                snapshot
             };
+            let headroom = self
+                .auto_gain
+                .update(self.note_allocator.sounding(audio_time), dt_sec);
             for i in 0..self.voice_panners.len() {
                 let pos = voice_positions_snapshot[i];
-                self.voice_panners[i].position_x().set_value(pos.x as f32);
-                self.voice_panners[i].position_y().set_value(pos.y as f32);
-                self.voice_panners[i].position_z().set_value(pos.z as f32);
-                let dist = (pos.x * pos.x + pos.z * pos.z).sqrt();
-                let mut d_amt = (D_SEND_BASE + D_SEND_SPAN * pos.x.abs().min(1.0)).clamp(0.0, 1.0);
-                let mut r_amt = (R_SEND_BASE
-                    + R_SEND_SPAN * (dist / DIST_NORM_DIVISOR).clamp(0.0, 1.0))
-                .clamp(0.0, R_SEND_CLAMP_MAX);
-                let boost = 1.0 + SEND_BOOST_COEFF * self.swirl_energy;
-                d_amt = (d_amt * boost).clamp(0.0, D_SEND_CLAMP_MAX);
-                r_amt = (r_amt * boost).clamp(0.0, R_SEND_CLAMP_MAX);
-                self.delay_sends[i].gain().set_value(d_amt);
-                self.reverb_sends[i].gain().set_value(r_amt);
-                let lvl = (LEVEL_BASE
-                    + LEVEL_SPAN * (1.0 - (dist / DIST_NORM_DIVISOR).clamp(0.0, 1.0)))
-                    as f32;
-                self.voice_gains[i].gain().set_value(lvl);
+                let src = sources[i];
+                let panner = &self.voice_panners[i];
+                audio::set_param(&panner.position_x(), src.x, now);
+                audio::set_param(&panner.position_y(), src.y, now);
+                audio::set_param(&panner.position_z(), src.z, now);
+                let mix = voice_mix(pos, self.swirl_energy, sends[i]);
+                audio::set_param(&self.delay_sends[i].gain(), mix.delay_send, now);
+                audio::set_param(&self.reverb_sends[i].gain(), mix.reverb_send, now);
+                audio::set_param(&self.voice_gains[i].gain(), mix.level * headroom, now);
             }
 
             // Optional analyser-driven ambient energy
@@ -156,79 +420,542 @@ impl<'a> FrameContext<'a> {
 
             // Voice positions are now only used for audio spatialization and wave displacement
 
-            // Camera + listener
-            let cam_eye = Vec3::new(0.0, 0.0, CAMERA_Z);
-            let cam_target = Vec3::ZERO;
-            update_listener_to_camera(&self.listener, cam_eye, cam_target);
+            // The listener stays at the resting view, so the mix holds still
+            // while the cinematic camera moves
+            let home = CameraPose::home(CAMERA_Z);
+            audio::update_listener_to_camera(&self.listener, home.eye, home.target);
+
+            let layers = self.visual_layers.get();
+            if let (Some(input), true) = (&mut self.onset_input, layers.any()) {
+                if layers.spectrum {
+                    let (spectrum, bin_hz) = input.spectrum_db();
+                    self.spectrum_bars.update(spectrum, bin_hz, dt_sec);
+                }
+                if layers.scope {
+                    input.waveform(&mut self.scope_samples);
+                    scope_trace(&self.scope_samples, &mut self.scope_points);
+                }
+            }
 
-            if let Some(g) = &mut self.gpu {
-                g.set_camera(cam_eye, cam_target);
-                if let Some(uvr) = self.queued_ripple_uv.borrow_mut().take() {
-                    g.set_ripple(uvr, 1.0);
+            self.musical_clock.advance(heard_time);
+            let status = if self.canvas_status.get() {
+                self.status_text()
+            } else {
+                String::new()
+            };
+            // While exporting, only frames of the piece are drawn, each as it
+            // falls due
+            let (video_frame, restart_visuals) = match self.video.borrow_mut().as_mut() {
+                Some(video) => {
+                    let frame = video.next_frame(audio_time);
+                    let restart = frame.is_some() && video.take_restart();
+                    (frame, restart)
+                }
+                None => (None, false),
+            };
+            let gpu = self
+                .gpu
+                .as_mut()
+                .filter(|_| !exporting || video_frame.is_some());
+            if let Some(g) = gpu {
+                if restart_visuals {
+                    let seed = self.engine.borrow().seed();
+                    g.restart_visuals(seed);
+                    self.choreographer = Choreographer::new(seed, CameraPose::home(CAMERA_Z));
+                    self.mirror_bars = BarCounter::default();
+                    self.pending_bursts.clear();
+                }
+                self.shader_reload.poll(dt_sec, g);
+                for (shader, source) in self.shader_reload.take_ready() {
+                    let validation = g.reload_shader(shader, source);
+                    self.shader_reload.watch_rebuild(shader, validation);
+                }
+                g.set_render_mode(*self.render_mode.borrow());
+                g.set_theme(self.theme.get());
+                g.set_bloom(self.bloom.get());
+                g.set_exposure(self.exposure.get(), self.tonemap.get());
+                g.set_lens(self.lens.get());
+                g.set_stars(self.stars.get());
+                g.set_antialias(self.antialias.get());
+                g.set_safe_visuals(self.safe_visuals.get());
+                g.set_visualizer(self.visualizer.get());
+                g.set_audio_layers(layers, &self.spectrum_bars.bands, &self.scope_points);
+                let clock = match &video_frame {
+                    Some(frame) => frame.clock,
+                    None => self.musical_clock.sample(heard_time),
+                };
+                let render_dt = video_frame.as_ref().map_or(dt_sec, |f| f.dt_sec);
+                self.choreographer.set_active(self.cinematic.get());
+                let camera = self
+                    .choreographer
+                    .update(render_dt, &clock)
+                    .zoomed(self.camera_zoom.get());
+                let (camera, clock) = shown
+                    .as_ref()
+                    .map_or((camera, clock), |f| (f.camera, f.clock));
+                self.camera.set(camera);
+                g.set_camera(camera.eye, camera.target);
+                g.set_clock(clock);
+                let bars = self.mirror_bars.update(&clock);
+                g.set_mirror(self.mirror.get().segments_at(bars));
+                if let Some((uvr, amp)) = self.queued_ripple_uv.borrow_mut().take() {
+                    if !exporting {
+                        g.set_ripple(uvr, amp);
+                    }
                 }
+                for uv in self.queued_streak.borrow_mut().drain(..) {
+                    if !exporting {
+                        g.add_streak_point(uv);
+                    }
+                }
+                for note in video_frame.iter().flat_map(|f| &f.bursts) {
+                    g.note_burst(
+                        note.event.voice_index,
+                        note.position,
+                        note.event.frequency_hz,
+                        note.event.velocity,
+                    );
+                }
+                let mut fired = Vec::new();
+                {
+                    let engine_ref = self.engine.borrow();
+                    for ev in self
+                        .pending_bursts
+                        .iter()
+                        .filter(|ev| ev.start_time_sec <= heard_time)
+                    {
+                        if let Some(voice) = engine_ref.voices.get(ev.voice_index) {
+                            g.note_burst(
+                                ev.voice_index,
+                                voice.position,
+                                ev.frequency_hz,
+                                ev.velocity,
+                            );
+                            fired.push(DisplayBurst {
+                                voice: ev.voice_index,
+                                position: voice.position,
+                                frequency_hz: ev.frequency_hz,
+                                velocity: ev.velocity,
+                            });
+                        }
+                    }
+                }
+                for b in shown.iter().flat_map(|f| &f.bursts) {
+                    g.note_burst(b.voice, b.position, b.frequency_hz, b.velocity);
+                }
+                self.pending_bursts
+                    .retain(|ev| ev.start_time_sec > heard_time);
                 let speed_norm = ((self.swirl_vel[0] * self.swirl_vel[0]
                     + self.swirl_vel[1] * self.swirl_vel[1])
                     .sqrt()
                     / 1.0)
                     .clamp(0.0, 1.0);
                 let strength = 0.28 + 0.85 * self.swirl_energy + 0.15 * speed_norm;
-                g.set_swirl(self.swirl_pos, strength, true);
+                match &shown {
+                    Some(f) => g.set_swirl([f.swirl[0], f.swirl[1]], f.swirl[2], true),
+                    None => g.set_swirl(self.swirl_pos, strength, !exporting),
+                }
+                // The image frays with the saturator; an export matches its
+                // bounce, which is mixed with the pointer at rest
+                let grit = if exporting {
+                    fx_levels(0.0, [0.5, 0.5]).grit()
+                } else {
+                    fx.grit()
+                };
+                g.set_grit(grit);
                 let w = self.canvas.width();
                 let h = self.canvas.height();
                 g.resize_if_needed(w, h);
+                let previous_scale = self.render_scale.scale();
+                let mode = self.render_scale_mode.get();
+                if mode != self.render_scale.mode() {
+                    self.render_scale.set_mode(mode);
+                }
+                let scale = self.render_scale.update(dt_sec);
+                if scale != previous_scale {
+                    if let Some(document) = self.canvas.owner_document() {
+                        overlay::update_render_scale_readout(&document, scale);
+                    }
+                }
+                // Exports always draw at full resolution
+                g.set_render_scale(if exporting { MAX_RENDER_SCALE } else { scale });
+                g.set_overlay_text(&status);
                 // Get current voice positions and pulse energy for rendering
-                let voice_positions: Vec<Vec3> = {
+                let (voice_positions, voice_styles): (Vec<Vec3>, Vec<VoiceStyle>) = {
                     let engine_ref = self.engine.borrow();
-                    engine_ref.voices.iter().map(|v| v.position).collect()
+                    engine_ref
+                        .voices
+                        .iter()
+                        .zip(&engine_ref.configs)
+                        .map(|(v, c)| (v.position, c.style))
+                        .unzip()
                 };
-                let pulse_energy_snapshot: Vec<f32> = {
-                    let pulses_ref = self.pulses.borrow();
-                    pulses_ref.clone()
+                step_trails(
+                    &mut self.voice_trails,
+                    &mut self.packed_trails,
+                    &voice_positions,
+                    &voice_styles,
+                    dt_sec,
+                );
+                g.set_trails(&self.packed_trails);
+                g.set_voice_styles(&voice_styles);
+                g.set_selected_voice(self.selected_voice.get());
+                let locked: Vec<bool> = self
+                    .engine
+                    .borrow()
+                    .voices
+                    .iter()
+                    .map(|v| v.locked)
+                    .collect();
+                g.set_locked_voices(&locked);
+                if let Some(link) = self.displays.as_ref().filter(|d| d.leads()) {
+                    link.post(&DisplayFrame {
+                        positions: [0, 1, 2].map(|i| {
+                            voice_positions
+                                .get(i)
+                                .copied()
+                                .unwrap_or(Vec3::ZERO)
+                                .to_array()
+                        }),
+                        pulse_energy: self.pulse_energy,
+                        clock,
+                        camera,
+                        swirl: [self.swirl_pos[0], self.swirl_pos[1], strength],
+                        theme: index_of(&THEMES.map(|t| t.id), &self.theme.get().id),
+                        visualizer: index_of(&Visualizer::ALL, &self.visualizer.get()),
+                        render_mode: index_of(&render::RenderMode::ALL, &self.render_mode.borrow()),
+                        bursts: fired,
+                    });
+                }
+                let pulse_energy_snapshot: Vec<f32> = match &video_frame {
+                    Some(frame) => frame.pulses.to_vec(),
+                    None => self.pulses.borrow().clone(),
                 };
 
-                if let Err(e) = g.render(dt_sec, &voice_positions, &pulse_energy_snapshot) {
+                if let Some(scale) = self.screenshot.take() {
+                    g.request_screenshot(scale);
+                }
+                if let Err(e) = g.render(render_dt, &voice_positions, &pulse_energy_snapshot) {
                     log::error!("render error: {:?}", e);
                 }
+                if let Some(mut shot) = g.take_screenshot() {
+                    shot.stamp_signature(&self.engine.borrow().signature());
+                    self.save_screenshot(&shot);
+                }
+                if let (Some(video), Some(frame)) = (self.video.borrow().as_ref(), &video_frame) {
+                    let progress = video.frame_drawn(frame);
+                    if let Some(document) = self.canvas.owner_document() {
+                        overlay::update_video_button(&document, Some(progress));
+                    }
+                }
+            } else if let Some(sketch) = &self.sketch {
+                let (voice_positions, voice_styles): (Vec<Vec3>, Vec<VoiceStyle>) = {
+                    let engine_ref = self.engine.borrow();
+                    engine_ref
+                        .voices
+                        .iter()
+                        .zip(&engine_ref.configs)
+                        .map(|(v, c)| (v.position, c.style))
+                        .unzip()
+                };
+                step_trails(
+                    &mut self.voice_trails,
+                    &mut self.packed_trails,
+                    &voice_positions,
+                    &voice_styles,
+                    dt_sec,
+                );
+                let (w, h) = (self.canvas.width(), self.canvas.height());
+                let positions: Vec<[f32; 3]> =
+                    voice_positions.iter().map(|p| p.to_array()).collect();
+                let mut dots = sketch_dots(
+                    self.theme.get(),
+                    &positions,
+                    &self.pulses.borrow(),
+                    &self.packed_trails,
+                    w as f32,
+                    h as f32,
+                );
+                if let Some(&position) = self.selected_voice.get().and_then(|i| positions.get(i)) {
+                    // Under the voices, which sketch_dots draws last
+                    let at = dots.len() - positions.len();
+                    dots.insert(at, sketch_selection(position, w as f32, h as f32));
+                }
+                for (v, &position) in self.engine.borrow().voices.iter().zip(&positions) {
+                    if v.locked {
+                        dots.extend(sketch_lock_marks(position, w as f32, h as f32));
+                    }
+                }
+                sketch.draw(self.theme.get(), &dots, w, h);
+            }
+            if self
+                .video
+                .borrow()
+                .as_ref()
+                .is_some_and(VideoExport::finished)
+            {
+                self.stop_video();
             }
         }
 
         if !*self.paused.borrow() {
+            {
+                // Tables and samples may have been loaded since the last frame
+                let eng = self.engine.borrow();
+                match self.worklet_synth.as_mut() {
+                    Some(synth) => synth.sync_assets(&eng),
+                    None => self.synth_assets.sync(&self.audio_ctx, &eng),
+                }
+            }
+            let sidechain = self.engine.borrow().sidechain();
             for ev in &note_events {
-                let src = match web::OscillatorNode::new(&self.audio_ctx) {
-                    Ok(s) => s,
-                    Err(_) => continue,
-                };
-                match self.engine.borrow().configs[ev.voice_index].waveform {
-                    Waveform::Sine => src.set_type(web::OscillatorType::Sine),
-                    // Waveform::Square => src.set_type(web::OscillatorType::Square),
-                    Waveform::Saw => src.set_type(web::OscillatorType::Sawtooth),
-                    Waveform::Triangle => src.set_type(web::OscillatorType::Triangle),
-                }
-                src.frequency().set_value(ev.frequency_hz);
-                let gain = match web::GainNode::new(&self.audio_ctx) {
-                    Ok(g) => g,
-                    Err(_) => continue,
+                let config = self.engine.borrow().configs[ev.voice_index].clone();
+                // Presets may change the cap at any time
+                self.note_allocator
+                    .set_limit(ev.voice_index, config.polyphony);
+                // Onsets come pre-stamped on the grid; only late ones are pulled to now
+                let t0 = ev.start_time_sec.max(audio_time);
+                if let Some(sc) = sidechain.filter(|sc| sc.trigger_voice == ev.voice_index) {
+                    audio::schedule_duck(&self.voice_ducks, &sc, t0);
+                }
+                let handle = match self.worklet_synth.as_mut() {
+                    Some(synth) => audio::NoteHandle::Worklet(synth.note(ev, &config, t0)),
+                    None => {
+                        let fade = &self.voice_fades[ev.voice_index];
+                        match audio::start_note_nodes(
+                            &self.audio_ctx,
+                            ev,
+                            &config,
+                            &self.synth_assets,
+                            t0,
+                            fade,
+                        ) {
+                            Some((sources, gain)) => audio::NoteHandle::Nodes(sources, gain),
+                            None => continue,
+                        }
+                    }
                 };
-                gain.gain().set_value(0.0);
-                let t0 = audio_time + 0.01;
-                _ = gain
-                    .gain()
-                    .linear_ramp_to_value_at_time(ev.velocity as f32, t0 + 0.02);
-                _ = gain
-                    .gain()
-                    .linear_ramp_to_value_at_time(0.0_f32, t0 + ev.duration_sec as f64);
-                _ = src.connect_with_audio_node(&gain);
-                _ = gain.connect_with_audio_node(&self.voice_gains[ev.voice_index]);
-                _ = gain.connect_with_audio_node(&self.delay_sends[ev.voice_index]);
-                _ = gain.connect_with_audio_node(&self.reverb_sends[ev.voice_index]);
-                _ = src.start_with_when(t0);
-                _ = src.stop_with_when(t0 + ev.duration_sec as f64 + 0.02);
+                // The note keeps sounding through its release
+                let t_end = t0 + (ev.duration_sec + config.envelope.release_sec) as f64;
+                let stolen =
+                    self.note_allocator
+                        .allocate(ev.voice_index, t0, t_end, ev.velocity, handle);
+                for handle in stolen {
+                    audio::release_stolen_note(
+                        handle,
+                        self.worklet_synth.as_mut(),
+                        t0,
+                        NOTE_STEAL_FADE_SEC,
+                    );
+                }
+            }
+            if let Some(synth) = self.worklet_synth.as_mut() {
+                synth.flush();
             }
         }
+        self.profile_frame(now, dt_sec, audio_time);
     }
 }
 
 impl<'a> FrameContext<'a> {
+    /// Take over when attract mode starts (cinematic camera, drifting
+    /// voices, a new key, the prompt), change key while it runs, and put the
+    /// camera and motion back when it ends.
+    fn attract_event(&mut self, event: AttractEvent) {
+        let document = self.canvas.owner_document();
+        let mut engine = self.engine.borrow_mut();
+        match event {
+            AttractEvent::Entered => {
+                self.attract_restore = Some((self.cinematic.get(), engine.motion()));
+                self.cinematic.set(true);
+                if engine.motion() == MotionMode::Still {
+                    engine.set_motion(MotionMode::Lissajous);
+                }
+                let mut rng = engine.fork_rng();
+                engine.apply(&random_tonality(&mut rng));
+                log::info!("[attract] started");
+            }
+            AttractEvent::Retone => {
+                let mut rng = engine.fork_rng();
+                engine.apply(&random_tonality(&mut rng));
+            }
+            AttractEvent::Left => {
+                if let Some((cinematic, motion)) = self.attract_restore.take() {
+                    self.cinematic.set(cinematic);
+                    if engine.motion() != motion {
+                        engine.set_motion(motion);
+                    }
+                }
+                log::info!("[attract] ended");
+            }
+        }
+        if let Some(document) = document {
+            if event != AttractEvent::Left {
+                let svg = engine.signature().to_svg(SIGNATURE_SIZE_PX);
+                overlay::update_attract_signature(&document, &svg);
+            }
+            overlay::show_attract_prompt(&document, event != AttractEvent::Left);
+        }
+    }
+
+    /// Take the voices, pulses and looks from the control window's frame.
+    fn show_display_frame(&mut self, f: &DisplayFrame) {
+        for (voice, p) in self.engine.borrow_mut().voices.iter_mut().zip(f.positions) {
+            voice.position = Vec3::from_array(p);
+        }
+        self.pulse_energy = f.pulse_energy;
+        if let Some(theme) = THEMES.get(f.theme as usize) {
+            self.theme.set(theme);
+        }
+        if let Some(&v) = Visualizer::ALL.get(f.visualizer as usize) {
+            self.visualizer.set(v);
+        }
+        if let Some(&mode) = render::RenderMode::ALL.get(f.render_mode as usize) {
+            *self.render_mode.borrow_mut() = mode;
+        }
+    }
+
+    /// A background one-shot through its voice, `delay_sec` from now.
+    fn play_background_note(&self, eng: &MusicEngine, note: &BackgroundNote, delay_sec: f64) {
+        let i = note.voice;
+        audio::trigger_one_shot(
+            &self.audio_ctx,
+            eng.configs[i].waveform,
+            note.frequency_hz,
+            note.velocity,
+            note.cutoff_hz,
+            note.duration_sec,
+            delay_sec,
+            &self.voice_gains[i],
+            &self.delay_sends[i],
+            &self.reverb_sends[i],
+        );
+    }
+
+    /// Move the looper on to the grid position at `audio_time` and do again
+    /// what it recorded there. Replayed drags leave locked voices and voices
+    /// someone is holding alone, and replayed mutes stay out of the undo
+    /// history.
+    fn play_loop(&self, eng: &mut MusicEngine, audio_time: f64) {
+        let mut looper = self.looper.borrow_mut();
+        let state = looper.state();
+        for action in looper.advance(eng.grid_position(audio_time)) {
+            match action {
+                LoopAction::Tap { note, ripple } => {
+                    self.play_background_note(eng, &note, 0.0);
+                    if ripple.is_some() {
+                        *self.queued_ripple_uv.borrow_mut() = ripple;
+                    }
+                }
+                LoopAction::Move { voice, position } => {
+                    if !eng.is_locked(voice) && !eng.is_held(voice) {
+                        eng.set_voice_position(voice, position);
+                    }
+                }
+                LoopAction::Mute { voice } => {
+                    eng.apply(&Command::ToggleMute { voice });
+                }
+            }
+        }
+        if looper.state() != state {
+            if let Some(document) = self.canvas.owner_document() {
+                overlay::update_looper(&document, &looper);
+            }
+        }
+    }
+
+    /// Move the automation timeline on to the grid position at
+    /// `audio_time` and make the changes it recorded there, outside the undo
+    /// history. Like the looper's drags, recorded positions leave locked and
+    /// held voices alone.
+    fn play_automation(&self, eng: &mut MusicEngine, audio_time: f64) {
+        let mut automation = self.automation.borrow_mut();
+        let state = automation.state();
+        for cmd in automation.advance(eng.grid_position(audio_time)) {
+            match cmd {
+                Command::SetPosition { voice, .. }
+                    if eng.is_locked(voice) || eng.is_held(voice) => {}
+                cmd => {
+                    eng.apply(&cmd);
+                }
+            }
+        }
+        if automation.state() != state {
+            if let Some(document) = self.canvas.owner_document() {
+                overlay::update_automation(&document, &automation);
+            }
+        }
+    }
+
+    /// Tempo, scale, render mode and pause state, for the canvas status line.
+    fn status_text(&self) -> String {
+        let eng = self.engine.borrow();
+        let mut status = format!(
+            "{:.0} BPM • {} • {}",
+            eng.params.bpm,
+            get_scale_name(eng.params.scale),
+            self.render_mode.borrow().id()
+        );
+        if *self.paused.borrow() {
+            status.push_str(" • paused");
+        }
+        if eng.is_replaying() {
+            status.push_str(" • replaying");
+        }
+        status
+    }
+
+    /// End the video export; the recorder then hands over the file.
+    fn stop_video(&self) {
+        if let Some(video) = self.video.borrow_mut().take() {
+            video.stop();
+        }
+        if let Some(document) = self.canvas.owner_document() {
+            overlay::update_video_button(&document, None);
+        }
+    }
+
+    /// Download a finished screenshot as a PNG named for the session seed.
+    fn save_screenshot(&self, shot: &Screenshot) {
+        let Some(document) = self.canvas.owner_document() else {
+            return;
+        };
+        let name = format!(
+            "geno-1-seed{}-{}x{}.png",
+            self.engine.borrow().seed(),
+            shot.width,
+            shot.height
+        );
+        dom::download_bytes(&document, &name, "image/png", &shot.to_png());
+    }
+
+    /// Record this frame's timings and, while the performance HUD shows,
+    /// refresh its figures a few times a second.
+    fn profile_frame(&mut self, started: Instant, dt_sec: f32, audio_time: f64) {
+        let cpu_sec = started.elapsed().as_secs_f32();
+        let show = self.perf_hud.get();
+        if let Some(g) = &mut self.gpu {
+            g.set_profiling(show);
+        }
+        if !self.profiler.record(dt_sec, cpu_sec) || !show {
+            return;
+        }
+        let oscillators = {
+            let eng = self.engine.borrow();
+            eng.configs
+                .iter()
+                .enumerate()
+                .map(|(voice, config)| {
+                    self.note_allocator.sounding_on(voice, audio_time) * note_oscillators(config)
+                })
+                .sum()
+        };
+        let gpu = self.gpu.as_ref().and_then(|g| g.gpu_timings());
+        if let Some(document) = self.canvas.owner_document() {
+            overlay::update_perf_hud(&document, &self.profiler.stats(oscillators, gpu));
+        }
+    }
+
     fn update_swirl(&mut self, uv: [f32; 2], dt_sec: f32, mouse_down: bool) {
         step_inertial_swirl(
             &mut self.swirl_initialized,
@@ -237,54 +964,55 @@ impl<'a> FrameContext<'a> {
             uv,
             dt_sec,
         );
-        let du = uv[0] - self.prev_uv[0];
-        let dv = uv[1] - self.prev_uv[1];
-        let pointer_speed = ((du * du + dv * dv).sqrt() / (dt_sec + 1e-5)).min(POINTER_SPEED_MAX);
-        let swirl_speed =
-            (self.swirl_vel[0] * self.swirl_vel[0] + self.swirl_vel[1] * self.swirl_vel[1]).sqrt();
-        let target = ((pointer_speed * SWIRL_TARGET_WEIGHT_POINTER)
-            + (swirl_speed * SWIRL_TARGET_WEIGHT_VELOCITY)
-            + if mouse_down {
-                SWIRL_TARGET_CLICK_BONUS
-            } else {
-                0.0
-            })
-        .clamp(0.0, 1.0);
-        self.swirl_energy = (1.0 - SWIRL_ENERGY_BLEND_ALPHA) * self.swirl_energy
-            + SWIRL_ENERGY_BLEND_ALPHA * target;
+        self.swirl_energy = blend_swirl_energy(
+            self.swirl_energy,
+            self.prev_uv,
+            uv,
+            self.swirl_vel,
+            dt_sec,
+            mouse_down,
+        );
         self.prev_uv = uv;
     }
 }
 
-#[inline]
-fn smooth_pulses(pulses: &mut [f32], pulse_energy: &mut [f32; 3], dt_sec: f32) {
-    let n = pulses.len().min(3);
-    let energy_decay = (-dt_sec * PULSE_ENERGY_DECAY_PER_SEC).exp();
-    for i in 0..n {
-        pulse_energy[i] *= energy_decay;
-    }
-    let tau_up = PULSE_RISE_TAU_SEC;
-    let tau_down = PULSE_FALL_TAU_SEC;
-    let alpha_up = 1.0 - (-dt_sec / tau_up).exp();
-    let alpha_down = 1.0 - (-dt_sec / tau_down).exp();
-    for i in 0..n {
-        let target = pulse_energy[i].clamp(0.0, 1.5);
-        let alpha = if target > pulses[i] {
-            alpha_up
-        } else {
-            alpha_down
-        };
-        pulses[i] += (target - pulses[i]) * alpha;
+/// Position of `item` in `all`, as display frames carry it.
+fn index_of<T: PartialEq>(all: &[T], item: &T) -> u32 {
+    all.iter().position(|x| x == item).unwrap_or(0) as u32
+}
+
+/// Age each voice's trail, fading over its style's trail length, and pack it
+/// for drawing.
+fn step_trails(
+    trails: &mut Vec<VoiceTrail>,
+    packed: &mut Vec<[f32; 4]>,
+    positions: &[Vec3],
+    styles: &[VoiceStyle],
+    dt_sec: f32,
+) {
+    trails.resize_with(positions.len(), VoiceTrail::default);
+    packed.resize(positions.len() * TRAIL_POINTS, [0.0; 4]);
+    for (((trail, pos), style), out) in trails
+        .iter_mut()
+        .zip(positions)
+        .zip(styles)
+        .zip(packed.chunks_mut(TRAIL_POINTS))
+    {
+        trail.set_fade_sec(style.clamped().trail_sec);
+        trail.update(pos.to_array(), dt_sec);
+        trail.pack(pos.to_array(), out);
     }
 }
 
-pub async fn init_gpu(canvas: &web::HtmlCanvasElement) -> Option<render::GpuState<'static>> {
+pub async fn init_gpu(
+    canvas: &web::HtmlCanvasElement,
+) -> anyhow::Result<render::GpuState<'static>> {
     // leak a canvas clone to satisfy 'static lifetime for surface
     let leaked_canvas = Box::leak(Box::new(canvas.clone()));
     match render::GpuState::new(leaked_canvas, CAMERA_Z).await {
         Ok(g) => {
             log::info!("WebGPU initialized successfully");
-            Some(g)
+            Ok(g)
         }
         Err(e) => {
             log::error!("WebGPU init error: {:?}", e);
@@ -297,7 +1025,7 @@ pub async fn init_gpu(canvas: &web::HtmlCanvasElement) -> Option<render::GpuStat
                     }
                 }
             }
-            None
+            Err(e)
         }
     }
 }
@@ -323,80 +1051,3 @@ pub fn start_loop(frame_ctx: Rc<RefCell<FrameContext<'static>>>) {
         _ = w.request_animation_frame(tick.borrow().as_ref().unwrap().as_ref().unchecked_ref());
     }
 }
-
-// --- helpers private to frame ---
-fn step_inertial_swirl(
-    initialized: &mut bool,
-    swirl_pos: &mut [f32; 2],
-    swirl_vel: &mut [f32; 2],
-    target_uv: [f32; 2],
-    dt_sec: f32,
-) {
-    if !*initialized {
-        *swirl_pos = target_uv;
-        swirl_vel[0] = 0.0;
-        swirl_vel[1] = 0.0;
-        *initialized = true;
-        return;
-    }
-    let omega = SWIRL_OMEGA;
-    let k = omega * omega;
-    let c = 2.0 * omega * SWIRL_DAMPING_RATIO;
-    let dx = target_uv[0] - swirl_pos[0];
-    let dy = target_uv[1] - swirl_pos[1];
-    let ax = k * dx - c * swirl_vel[0];
-    let ay = k * dy - c * swirl_vel[1];
-    swirl_vel[0] += ax * dt_sec;
-    swirl_vel[1] += ay * dt_sec;
-    let mut nx = swirl_pos[0] + swirl_vel[0] * dt_sec;
-    let mut ny = swirl_pos[1] + swirl_vel[1] * dt_sec;
-    let sdx = nx - swirl_pos[0];
-    let sdy = ny - swirl_pos[1];
-    let step = (sdx * sdx + sdy * sdy).sqrt();
-    let max_step = SWIRL_MAX_STEP_PER_SEC * dt_sec;
-    if step > max_step {
-        let inv = 1.0 / (step + 1e-6);
-        nx = swirl_pos[0] + sdx * inv * max_step;
-        ny = swirl_pos[1] + sdy * inv * max_step;
-    }
-    swirl_pos[0] = nx.clamp(0.0, 1.0);
-    swirl_pos[1] = ny.clamp(0.0, 1.0);
-}
-
-fn apply_global_fx_swirl(
-    reverb_wet: &web::GainNode,
-    delay_wet: &web::GainNode,
-    delay_feedback: &web::GainNode,
-    sat_pre: &web::GainNode,
-    sat_wet: &web::GainNode,
-    sat_dry: &web::GainNode,
-    swirl_energy: f32,
-    uv: [f32; 2],
-) {
-    _ = reverb_wet
-        .gain()
-        .set_value(FX_REVERB_BASE + FX_REVERB_SPAN * swirl_energy);
-    let echo = (uv[0] - uv[1]).abs();
-    let delay_wet_val =
-        (FX_DELAY_WET_BASE + FX_DELAY_WET_SWIRL * swirl_energy + FX_DELAY_WET_ECHO * echo)
-            .clamp(0.0, 1.0);
-    let delay_fb_val =
-        (FX_DELAY_FB_BASE + FX_DELAY_FB_SWIRL * swirl_energy + FX_DELAY_FB_ECHO * echo)
-            .clamp(0.0, 0.95);
-    _ = delay_wet.gain().set_value(delay_wet_val);
-    _ = delay_feedback.gain().set_value(delay_fb_val);
-    let fizz = ((uv[0] + uv[1]) * 0.5).clamp(0.0, 1.0);
-    let drive = (FX_SAT_DRIVE_MIN
-        + (FX_SAT_DRIVE_MAX - FX_SAT_DRIVE_MIN) * ((fizz - 0.25).clamp(0.0, 1.0)))
-    .clamp(FX_SAT_DRIVE_MIN, FX_SAT_DRIVE_MAX);
-    _ = sat_pre.gain().set_value(drive);
-    let wet = (FX_SAT_WET_BASE + FX_SAT_WET_SPAN * fizz).clamp(0.0, 1.0);
-    _ = sat_wet.gain().set_value(wet);
-    _ = sat_dry.gain().set_value(1.0 - wet);
-}
-
-fn update_listener_to_camera(listener: &web::AudioListener, cam_eye: Vec3, cam_target: Vec3) {
-    let fwd = (cam_target - cam_eye).normalize();
-    listener.set_position(cam_eye.x as f64, cam_eye.y as f64, cam_eye.z as f64);
-    _ = listener.set_orientation(fwd.x as f64, fwd.y as f64, fwd.z as f64, 0.0, 1.0, 0.0);
-}
@@ -8,12 +8,6 @@ pub struct MouseState {
     pub y: f32,
     pub down: bool,
 }
-#[derive(Default, Clone, Copy)]
-pub struct DragState {
-    pub active: bool,
-    pub voice: usize,
-    pub plane_z_world: f32,
-}
 #[inline]
 pub fn ray_sphere(ray_origin: Vec3, ray_dir: Vec3, center: Vec3, radius: f32) -> Option<f32> {
     let oc = ray_origin - center;
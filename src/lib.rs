@@ -1,8 +1,59 @@
 #![cfg(target_arch = "wasm32")]
-use crate::core::{EngineParams, MusicEngine, VoiceConfig, Waveform, C_MAJOR_PENTATONIC};
+use crate::core::antialias::Antialias;
+use crate::core::attract::{parse_attract, Attract, DEFAULT_ATTRACT_IDLE_SEC};
+use crate::core::automation::{automation_snapshot, event_command, Automation, AutomationState};
+use crate::core::bloom::Bloom;
+use crate::core::bounce::{parse_bounce_seconds, BOUNCE_STEP_FRAMES, DEFAULT_BOUNCE_SEC};
+use crate::core::choreography::{CameraPose, Choreographer};
+use crate::core::device::{find_by_name, BufferRequest, DeviceRequest};
+use crate::core::diagnostics::{Diagnostics, Severity};
+use crate::core::displays::DisplaySlice;
+use crate::core::eq::MasterEq;
+use crate::core::generator::MarkovGenerator;
+use crate::core::gesture::TwoFingerGesture;
+use crate::core::lens::{DofQuality, Lens};
+use crate::core::lifecycle::AudioLifecycle;
+use crate::core::looper::{Looper, LOOP_BAR_CHOICES};
+use crate::core::metronome::{Metronome, MAX_COUNT_IN_BARS};
+use crate::core::midi_learn::{CcMap, CcSource, CcTarget, MidiLearn};
+use crate::core::midi_out::MidiOutMap;
+use crate::core::mirror::{self, Mirror};
+use crate::core::mixer::MasterMixer;
+use crate::core::musical_clock::{BarCounter, MusicalClock};
+use crate::core::named_presets::NamedPresets;
+use crate::core::note_repeat::BackgroundHold;
+use crate::core::onset::PulseSource;
+use crate::core::output::{output_preset_by_id, OUTPUT_PRESETS};
+use crate::core::physics::PhysicsParams;
+use crate::core::polyphony::{AutoGain, VoiceAllocator};
+use crate::core::preset::preset_bank;
+use crate::core::profiler::FrameProfiler;
+use crate::core::recovery::{Resume, AUTOSAVE_INTERVAL_SEC};
+use crate::core::render_scale::{RenderScale, RenderScaleMode};
+use crate::core::replay::Replay;
+use crate::core::reverb::{reverb_preset_by_id, ReverbPreset};
+use crate::core::sampler::{LoopRegion, Sample, SamplerConfig, DEFAULT_SAMPLE_ROOT_HZ};
+use crate::core::saved_session::SavedSession;
+use crate::core::shader_reload::{parse_shader_base, Shader};
+use crate::core::sidechain::Sidechain;
+use crate::core::smf::{melodies, parse_smf, MidiRecorder};
+use crate::core::spectrum::{SpectrumBars, VisualLayers, SCOPE_POINTS};
+use crate::core::starfield::parse_stars;
+use crate::core::stereo::{parse_width, DEFAULT_STEREO_WIDTH};
+use crate::core::strum::Strum;
+use crate::core::theme::{Theme, THEMES};
+use crate::core::tonemap::{Exposure, Tonemap};
+use crate::core::touch::{PointerSpeed, PressTimings, TapTracker, VoicePresses};
+use crate::core::video::{parse_video_fps, FrameSequence, DEFAULT_VIDEO_FPS};
+use crate::core::visualizer::Visualizer;
+use crate::core::wavetable::Wavetable;
+use crate::core::{
+    Command, EngineEvent, EventBus, History, MusicEngine, SessionConfig, SpatialHints,
+};
+use crate::video::VideoExport;
 use glam::Vec3;
 use instant::Instant;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use wasm_bindgen::prelude::*;
@@ -14,12 +65,17 @@ mod audio;
 mod camera;
 mod constants;
 mod core;
+mod displays;
 mod dom;
 mod events;
 mod frame;
 mod input;
+mod midi;
 mod overlay;
 mod render;
+mod shader_reload;
+mod sketch;
+mod video;
 
 fn wire_canvas_resize(canvas: &web::HtmlCanvasElement) {
     dom::sync_canvas_backing_size(canvas);
@@ -39,47 +95,299 @@ struct InitParts {
     listener_for_tick: web::AudioListener,
     engine: Rc<RefCell<MusicEngine>>,
     paused: Rc<RefCell<bool>>,
+    resume: Resume,
 }
 
-async fn build_audio_and_engine(_document: web::Document) -> anyhow::Result<InitParts> {
-    let audio_ctx = web::AudioContext::new().map_err(|e| anyhow::anyhow!("{:?}", e))?;
+/// What this visit starts from: the session saved by the last one, or, if
+/// that one never got to mark itself left, its last autosave to offer back.
+/// Nothing when the URL asks for a fresh start (`?fresh`).
+fn resume() -> Resume {
+    if dom::query_param("fresh").is_some() {
+        return Resume::Fresh;
+    }
+    let saved = dom::local_storage_get(constants::SESSION_STORAGE_KEY)
+        .and_then(|t| SavedSession::decode(&t));
+    let dirty = dom::local_storage_get(constants::RUNNING_STORAGE_KEY).is_some_and(|f| f == "1");
+    Resume::decide(saved, dirty)
+}
+
+/// Save the session every `AUTOSAVE_INTERVAL_SEC` and whenever the page is
+/// hidden or left (closing the tab, switching apps on a phone), with the
+/// effect `settings` of the moment, while `saving` allows it. The page is
+/// marked running while it is shown, so a visit that stops any other way (a
+/// crash) is known for one on the next.
+fn wire_session_save(
+    engine: &Rc<RefCell<MusicEngine>>,
+    settings: impl Fn() -> Vec<(String, String)> + 'static,
+    saving: Rc<Cell<bool>>,
+) {
+    let Some(window) = web::window() else {
+        return;
+    };
+    let engine = engine.clone();
+    let save = Rc::new(move || {
+        if saving.get() {
+            let saved = SavedSession::capture(&engine.borrow(), settings());
+            dom::local_storage_set(constants::SESSION_STORAGE_KEY, &saved.encode());
+        }
+    });
+    let mark_running = |running: bool| {
+        dom::local_storage_set(
+            constants::RUNNING_STORAGE_KEY,
+            if running { "1" } else { "" },
+        );
+    };
+    mark_running(true);
+    let on_hide = {
+        let save = save.clone();
+        Closure::wrap(Box::new(move |ev: web::Event| {
+            let hidden = dom::window_document().is_none_or(|d| d.hidden());
+            let left = hidden || ev.type_() == "pagehide";
+            if left {
+                save();
+            }
+            mark_running(!left);
+        }) as Box<dyn FnMut(_)>)
+    };
+    for event in ["pagehide", "visibilitychange"] {
+        _ = window.add_event_listener_with_callback(event, on_hide.as_ref().unchecked_ref());
+    }
+    on_hide.forget();
+    let autosave = Closure::wrap(Box::new(move || save()) as Box<dyn FnMut()>);
+    _ = window.set_interval_with_callback_and_timeout_and_arguments_0(
+        autosave.as_ref().unchecked_ref(),
+        (AUTOSAVE_INTERVAL_SEC * 1000) as i32,
+    );
+    autosave.forget();
+}
+
+/// After a crash the start overlay offers the last autosave: Restore reloads
+/// the page into it, Start fresh lets saving carry on over it. Until one is
+/// picked nothing is saved, so the autosave stays to be had.
+fn wire_recovery_prompt(document: &web::Document, saving: &Rc<Cell<bool>>) {
+    overlay::show_recovery(document, true);
+    dom::add_click_listener(document, "recovery-restore", || {
+        log::info!("[session] restoring the autosave");
+        // The autosave is left as the saved session, so the reload restores it
+        dom::local_storage_set(constants::RUNNING_STORAGE_KEY, "");
+        if let Some(window) = web::window() {
+            _ = window.location().reload();
+        }
+    });
+    let saving = saving.clone();
+    let doc = document.clone();
+    dom::add_click_listener(document, "recovery-dismiss", move || {
+        saving.set(true);
+        overlay::show_recovery(&doc, false);
+    });
+}
+
+/// Add the presets in `json` (`[{"name": …, "session": …}, …]`, as
+/// `presets_json` writes); how many were added, or `None` if it isn't a list.
+fn merge_presets_json(presets: &mut NamedPresets, json: &str) -> Option<usize> {
+    let list = js_sys::JSON::parse(json).ok()?;
+    if !js_sys::Array::is_array(&list) {
+        return None;
+    }
+    let pairs: Vec<(String, String)> = js_sys::Array::from(&list)
+        .iter()
+        .filter_map(|entry| {
+            let field = |key: &str| js_sys::Reflect::get(&entry, &key.into()).ok()?.as_string();
+            Some((field("name")?, field("session")?))
+        })
+        .collect();
+    Some(presets.merge_pairs(pairs.iter().map(|(n, s)| (n.as_str(), s.as_str()))))
+}
+
+fn presets_json(presets: &NamedPresets) -> Option<String> {
+    let list = js_sys::Array::new();
+    for (name, session) in presets.to_pairs() {
+        let entry = js_sys::Object::new();
+        _ = js_sys::Reflect::set(&entry, &"name".into(), &name.into());
+        _ = js_sys::Reflect::set(&entry, &"session".into(), &session.into());
+        list.push(&entry);
+    }
+    js_sys::JSON::stringify_with_replacer_and_space(&list, &JsValue::NULL, &2.into())
+        .ok()?
+        .as_string()
+}
+
+/// The Presets panel: save the session under the name typed, load or delete
+/// the one picked, and export or import the lot as JSON. The list is kept in
+/// this browser. Loading rebuilds the engine from the preset's seed (so it
+/// plays as it did, and `history` starts over) and hands each saved effect
+/// setting to `apply_setting`; MIDI-learn mappings saved with a preset
+/// replace the current ones.
+fn wire_presets(
+    document: &web::Document,
+    engine: &Rc<RefCell<MusicEngine>>,
+    history: &Rc<RefCell<History>>,
+    capture_settings: Rc<dyn Fn() -> Vec<(String, String)>>,
+    apply_setting: impl Fn(&str, &str) + 'static,
+) {
+    let presets = Rc::new(RefCell::new(NamedPresets::default()));
+    if let Some(json) = dom::local_storage_get(constants::PRESETS_STORAGE_KEY) {
+        if merge_presets_json(&mut presets.borrow_mut(), &json).is_none() {
+            log::warn!("[presets] ignoring unreadable saved presets");
+        }
+    }
+    let store = {
+        let presets = presets.clone();
+        let doc = document.clone();
+        Rc::new(move |selected: Option<&str>| {
+            if let Some(json) = presets_json(&presets.borrow()) {
+                dom::local_storage_set(constants::PRESETS_STORAGE_KEY, &json);
+            }
+            overlay::update_presets(&doc, &presets.borrow(), selected);
+        })
+    };
+    overlay::update_presets(document, &presets.borrow(), None);
+    {
+        let presets = presets.clone();
+        let engine = engine.clone();
+        let store = store.clone();
+        let doc = document.clone();
+        dom::add_click_listener(document, "preset-save", move || {
+            let name = dom::input_value(&doc, "preset-name").unwrap_or_default();
+            let session = SavedSession::capture(&engine.borrow(), capture_settings());
+            let saved = presets.borrow_mut().save(&name, session);
+            match saved {
+                Some(name) => {
+                    store(Some(&name));
+                    overlay::show_toast(&doc, &format!("Saved preset {name}"));
+                }
+                None => overlay::show_toast(&doc, "Name the preset to save it"),
+            }
+        });
+    }
+    {
+        let presets = presets.clone();
+        let engine = engine.clone();
+        let history = history.clone();
+        let doc = document.clone();
+        dom::add_click_listener(document, "preset-load", move || {
+            let Some(name) = dom::select_value(&doc, "preset-list") else {
+                return;
+            };
+            let Some(session) = presets.borrow().get(&name).cloned() else {
+                return;
+            };
+            let rebuilt = session.rebuild(&engine.borrow());
+            engine.borrow_mut().replace_with(rebuilt);
+            *history.borrow_mut() = History::new();
+            let voices = engine.borrow().configs.len();
+            overlay::update_session_seed(&doc, &SessionConfig { seed: session.seed }.label());
+            overlay::update_signature(
+                &doc,
+                &engine
+                    .borrow()
+                    .signature()
+                    .to_svg(constants::SIGNATURE_SIZE_PX),
+            );
+            if !session.controls.is_empty() {
+                dom::local_storage_set(constants::CC_MAP_STORAGE_KEY, &session.controls.encode());
+                overlay::update_cc_map(&doc, &session.controls, &MidiLearn::default(), voices);
+                engine.borrow_mut().controls = session.controls;
+            }
+            for (setting, value) in &session.settings {
+                apply_setting(setting, value);
+            }
+            dom::set_input_value(&doc, "preset-name", &name);
+            log::info!("[presets] loaded {name}");
+            overlay::show_toast(&doc, &format!("Loaded preset {name}"));
+        });
+    }
+    {
+        let presets = presets.clone();
+        let store = store.clone();
+        let doc = document.clone();
+        dom::add_click_listener(document, "preset-delete", move || {
+            let Some(name) = dom::select_value(&doc, "preset-list") else {
+                return;
+            };
+            let removed = presets.borrow_mut().remove(&name);
+            if removed {
+                store(None);
+                overlay::show_toast(&doc, &format!("Deleted preset {name}"));
+            }
+        });
+    }
+    {
+        let presets = presets.clone();
+        let doc = document.clone();
+        dom::add_click_listener(document, "preset-export", move || {
+            if let Some(json) = presets_json(&presets.borrow()) {
+                dom::download_bytes(
+                    &doc,
+                    "geno-1-presets.json",
+                    "application/json",
+                    json.as_bytes(),
+                );
+            }
+        });
+    }
+    {
+        let doc = document.clone();
+        dom::add_file_listener(document, "preset-import", move |bytes| {
+            let json = String::from_utf8_lossy(&bytes);
+            let added = merge_presets_json(&mut presets.borrow_mut(), &json);
+            match added {
+                Some(n) => {
+                    store(None);
+                    log::info!("[presets] imported {n}");
+                    overlay::show_toast(&doc, &format!("Imported {n} presets"));
+                }
+                None => overlay::show_toast(&doc, "Not a presets file"),
+            }
+        });
+    }
+}
+
+async fn build_audio_and_engine(document: web::Document) -> anyhow::Result<InitParts> {
+    // `?samplerate=<Hz>` and `?buffer=<frames>|interactive|balanced|playback`
+    // ask the output for a rate and buffer size (hints the browser may round)
+    let device_request = DeviceRequest {
+        sample_rate: dom::query_param("samplerate")
+            .and_then(|s| DeviceRequest::parse_sample_rate(&s)),
+        buffer: dom::query_param("buffer").and_then(|s| BufferRequest::parse(&s)),
+    };
+    let audio_ctx =
+        audio::create_context(&device_request).map_err(|e| anyhow::anyhow!(dom::error_text(&e)))?;
     _ = audio_ctx.resume();
+    log::info!(
+        "[audio] {} Hz, output latency {:.1} ms",
+        audio_ctx.sample_rate(),
+        audio::output_latency_sec(&audio_ctx) * 1000.0
+    );
     let listener = audio_ctx.listener();
     listener.set_position(0.0, 0.0, 1.5);
 
-    let voice_configs = vec![
-        VoiceConfig {
-            waveform: Waveform::Sine,
-            base_position: Vec3::new(-1.0, 0.0, 0.0),
-            trigger_probability: 0.4,
-            octave_offset: -1,
-            base_duration: 0.4,
-        },
-        VoiceConfig {
-            waveform: Waveform::Saw,
-            base_position: Vec3::new(1.0, 0.0, 0.0),
-            trigger_probability: 0.6,
-            octave_offset: 0,
-            base_duration: 0.25,
-        },
-        VoiceConfig {
-            waveform: Waveform::Triangle,
-            base_position: Vec3::new(0.0, 0.0, -1.0),
-            trigger_probability: 0.3,
-            octave_offset: 1,
-            base_duration: 0.6,
-        },
-    ];
+    // `?seed=4217` replays a shared session, which a session saved with
+    // another seed doesn't restore over; otherwise the seed the last session
+    // was left with, or the default
+    let url_session = dom::query_param("seed").and_then(|s| SessionConfig::parse(&s));
+    let resume = resume().for_url_seed(url_session.map(|s| s.seed));
+    let saved = resume.restored();
+    if resume.offered().is_some() {
+        log::warn!("[session] the last session ended abruptly; offering its autosave");
+    }
+    let session = url_session
+        .or_else(|| saved.as_ref().map(|s| SessionConfig { seed: s.seed }))
+        .unwrap_or_default();
+    log::info!("[session] {}", session.label());
+    overlay::update_session_seed(&document, &session.label());
+    // Start from the first built-in preset
+    let startup = preset_bank().swap_remove(0);
     let engine = Rc::new(RefCell::new(MusicEngine::new(
-        voice_configs,
-        EngineParams {
-            bpm: 110.0,
-            scale: C_MAJOR_PENTATONIC,
-            root_midi: 60,
-            detune_cents: 0.0,
-        },
-        42,
+        startup.voices,
+        startup.params,
+        session.seed,
     )));
+    if let Some(saved) = saved {
+        let voices = engine.borrow().voices.len();
+        engine.borrow_mut().apply(&saved.restore_command(voices));
+        log::info!("[session] restored the last session");
+    }
     {
         let e = engine.borrow();
         log::info!(
@@ -89,6 +397,22 @@ async fn build_audio_and_engine(_document: web::Document) -> anyhow::Result<Init
             e.voices[1].position.x, e.voices[1].position.y, e.voices[1].position.z,
             e.voices[2].position.x, e.voices[2].position.y, e.voices[2].position.z
         );
+        overlay::update_signature(
+            &document,
+            &e.signature().to_svg(constants::SIGNATURE_SIZE_PX),
+        );
+    }
+    // Spring physics from `?springs=<stiffness>,<damping>`; 'K' toggles it
+    if let Some(params) = dom::query_param("springs").and_then(|s| PhysicsParams::parse(&s)) {
+        engine.borrow_mut().set_physics(Some(params));
+    }
+    // Sidechain ducking from `?duck=<voice>,<depth dB>[,<attack>,<release>]`; 'S' toggles it
+    if let Some(sidechain) = dom::query_param("duck").and_then(|s| Sidechain::parse(&s)) {
+        engine.borrow_mut().set_sidechain(Some(sidechain));
+    }
+    // Metronome from `?click=on|off[,count-in bars[,level]]`; 'N' toggles it
+    if let Some(metronome) = dom::query_param("click").and_then(|s| Metronome::parse(&s)) {
+        engine.borrow_mut().set_metronome(metronome);
     }
     let paused = Rc::new(RefCell::new(true));
     Ok(InitParts {
@@ -96,15 +420,23 @@ async fn build_audio_and_engine(_document: web::Document) -> anyhow::Result<Init
         listener_for_tick: listener,
         engine,
         paused,
+        resume,
     })
 }
 
-fn wire_overlay_buttons(audio_ctx: &web::AudioContext, paused: &Rc<RefCell<bool>>) {
+fn wire_overlay_buttons(
+    audio_ctx: &web::AudioContext,
+    paused: &Rc<RefCell<bool>>,
+    engine: &Rc<RefCell<MusicEngine>>,
+) {
     if let Some(doc2) = dom::window_document() {
         let paused_ok = paused.clone();
         let audio_ok = audio_ctx.clone();
+        let engine_ok = engine.clone();
         dom::add_click_listener(&doc2, "overlay-ok", move || {
-            *paused_ok.borrow_mut() = false;
+            if std::mem::replace(&mut *paused_ok.borrow_mut(), false) {
+                engine_ok.borrow_mut().start_count_in();
+            }
             _ = audio_ok.resume();
             if let Some(w2) = web::window() {
                 if let Some(d2) = w2.document() {
@@ -115,8 +447,11 @@ fn wire_overlay_buttons(audio_ctx: &web::AudioContext, paused: &Rc<RefCell<bool>
 
         let paused_close = paused.clone();
         let audio_close = audio_ctx.clone();
+        let engine_close = engine.clone();
         dom::add_click_listener(&doc2, "overlay-close", move || {
-            *paused_close.borrow_mut() = false;
+            if std::mem::replace(&mut *paused_close.borrow_mut(), false) {
+                engine_close.borrow_mut().start_count_in();
+            }
             _ = audio_close.resume();
             if let Some(w2) = web::window() {
                 if let Some(d2) = w2.document() {
@@ -127,25 +462,218 @@ fn wire_overlay_buttons(audio_ctx: &web::AudioContext, paused: &Rc<RefCell<bool>
     }
 }
 
+/// Suspend the audio while the page is hidden, which holds the scheduler
+/// (see `AudioLifecycle`), and resume it when the page is shown again. iOS
+/// may refuse a resume outside a user gesture, or stop the audio itself for
+/// a call or another app, so while playing the next touch, click or key
+/// press resumes it too.
+fn wire_audio_lifecycle(
+    audio_ctx: &web::AudioContext,
+    paused: &Rc<RefCell<bool>>,
+) -> Rc<Cell<AudioLifecycle>> {
+    let lifecycle = Rc::new(Cell::new(AudioLifecycle::default()));
+    let Some(window) = web::window() else {
+        return lifecycle;
+    };
+    let running = |ctx: &web::AudioContext| ctx.state() == web::AudioContextState::Running;
+    {
+        let lifecycle = lifecycle.clone();
+        let audio_ctx = audio_ctx.clone();
+        let paused = paused.clone();
+        let on_visibility = Closure::wrap(Box::new(move || {
+            let hidden = dom::window_document().is_none_or(|d| d.hidden());
+            let playing = !*paused.borrow();
+            let mut state = lifecycle.get();
+            if hidden {
+                if state.hide(playing) {
+                    log::info!("[audio] suspended while hidden");
+                    _ = audio_ctx.suspend();
+                }
+            } else if state.show(playing, running(&audio_ctx)) {
+                log::info!("[audio] resuming");
+                _ = audio_ctx.resume();
+            }
+            lifecycle.set(state);
+        }) as Box<dyn FnMut()>);
+        _ = window.add_event_listener_with_callback(
+            "visibilitychange",
+            on_visibility.as_ref().unchecked_ref(),
+        );
+        on_visibility.forget();
+    }
+    {
+        let lifecycle = lifecycle.clone();
+        let audio_ctx = audio_ctx.clone();
+        let paused = paused.clone();
+        let unlock = Closure::wrap(Box::new(move || {
+            if !*paused.borrow() && !lifecycle.get().is_hidden() && !running(&audio_ctx) {
+                log::info!("[audio] resuming on a gesture");
+                _ = audio_ctx.resume();
+            }
+        }) as Box<dyn FnMut()>);
+        for event in ["pointerdown", "touchend", "keydown"] {
+            _ = window.add_event_listener_with_callback(event, unlock.as_ref().unchecked_ref());
+        }
+        unlock.forget();
+    }
+    lifecycle
+}
+
+/// MIDI-learn and mapped knobs: the controllers saved in this browser are
+/// loaded into the engine, the MIDI controls panel picks parameters and the
+/// returned `ControlIn` hears knobs once MIDI is connected. Knobs set values
+/// directly, outside undo, as incoming clock does.
+fn wire_midi_learn(document: &web::Document, engine: &Rc<RefCell<MusicEngine>>) -> midi::ControlIn {
+    if let Some(saved) = dom::local_storage_get(constants::CC_MAP_STORAGE_KEY) {
+        engine.borrow_mut().controls = CcMap::decode(&saved);
+    }
+    let voices = engine.borrow().configs.len();
+    let learn = Rc::new(RefCell::new(MidiLearn::default()));
+    let refresh = {
+        let engine = engine.clone();
+        let learn = learn.clone();
+        let doc = document.clone();
+        Rc::new(move || {
+            overlay::update_cc_map(&doc, &engine.borrow().controls, &learn.borrow(), voices)
+        })
+    };
+    let bind = {
+        let engine = engine.clone();
+        let doc = document.clone();
+        Rc::new(move |(source, target): (CcSource, CcTarget)| {
+            let mut e = engine.borrow_mut();
+            e.controls.bind(source, target);
+            dom::local_storage_set(constants::CC_MAP_STORAGE_KEY, &e.controls.encode());
+            let (ch, cc) = source;
+            log::info!("[midi] CC {} ch {} -> {}", cc, ch + 1, target.id());
+            overlay::show_toast(&doc, &format!("CC {} → {}", cc, target.label()));
+        })
+    };
+    refresh();
+    {
+        let learn = learn.clone();
+        let refresh = refresh.clone();
+        dom::add_click_listener(document, "midi-learn", move || {
+            let active = learn.borrow().active;
+            if active {
+                learn.borrow_mut().cancel();
+            } else {
+                learn.borrow_mut().start();
+            }
+            refresh();
+        });
+    }
+    {
+        let engine = engine.clone();
+        let refresh = refresh.clone();
+        dom::add_click_listener(document, "cc-clear", move || {
+            engine.borrow_mut().controls = CcMap::default();
+            dom::local_storage_set(constants::CC_MAP_STORAGE_KEY, "");
+            log::info!("[midi] controller mappings cleared");
+            refresh();
+        });
+    }
+    if let Some(list) = document.get_element_by_id("cc-map") {
+        let learn = learn.clone();
+        let refresh = refresh.clone();
+        let bind = bind.clone();
+        // Picking a parameter starts learning if it wasn't already
+        let closure = Closure::wrap(Box::new(move |ev: web::Event| {
+            let target = ev
+                .target()
+                .and_then(|t| t.dyn_into::<web::Element>().ok())
+                .and_then(|el| el.get_attribute("data-target"))
+                .and_then(|id| CcTarget::parse(&id));
+            let Some(target) = target else {
+                return;
+            };
+            let learnt = {
+                let mut l = learn.borrow_mut();
+                if !l.active {
+                    l.start();
+                }
+                l.picked(target)
+            };
+            if let Some(pair) = learnt {
+                bind(pair);
+            }
+            refresh();
+        }) as Box<dyn FnMut(_)>);
+        _ = list.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref());
+        closure.forget();
+    }
+    let engine = engine.clone();
+    midi::ControlIn::new(move |channel, cc, value| {
+        if learn.borrow().active {
+            let learnt = learn.borrow_mut().heard((channel, cc));
+            if let Some(pair) = learnt {
+                bind(pair);
+            }
+            refresh();
+            return;
+        }
+        let mut e = engine.borrow_mut();
+        let cmd = e
+            .controls
+            .target((channel, cc))
+            .and_then(|t| t.command(value, &e));
+        if let Some(cmd) = cmd {
+            e.apply(&cmd);
+        }
+    })
+}
+
 #[wasm_bindgen(start)]
 pub fn start() -> Result<(), JsValue> {
     console_error_panic_hook::set_once();
     console_log::init_with_level(log::Level::Info).ok();
     log::info!("app-web starting");
 
+    let diagnostics = Rc::new(RefCell::new(Diagnostics::default()));
     spawn_local(async move {
-        if let Err(e) = init().await {
+        if let Err(e) = init(diagnostics.clone()).await {
             log::error!("init error: {:?}", e);
+            report_problem(&diagnostics, Severity::Fatal, "startup", e.to_string());
         }
     });
     Ok(())
 }
 
-async fn init() -> anyhow::Result<()> {
+/// Note a problem for the status banner and the Diagnostics panel.
+fn report_problem(
+    diagnostics: &Rc<RefCell<Diagnostics>>,
+    severity: Severity,
+    area: &'static str,
+    message: impl Into<String>,
+) {
+    let mut d = diagnostics.borrow_mut();
+    d.report(severity, area, message);
+    if let Some(document) = dom::window_document() {
+        overlay::update_diagnostics(&document, &d);
+    }
+}
+
+/// What the browser offers that parts of the app depend on.
+fn note_capabilities(window: &web::Window, diagnostics: &mut Diagnostics) {
+    let navigator = window.navigator();
+    let has =
+        |name: &str| js_sys::Reflect::has(&navigator, &JsValue::from_str(name)).unwrap_or(false);
+    diagnostics.capability("WebGPU", has("gpu"));
+    diagnostics.capability("Web MIDI", has("requestMIDIAccess"));
+    diagnostics.capability("Secure context", window.is_secure_context());
+    diagnostics.capability(
+        "Local storage",
+        window.local_storage().ok().flatten().is_some(),
+    );
+}
+
+async fn init(diagnostics: Rc<RefCell<Diagnostics>>) -> anyhow::Result<()> {
     let window = web::window().ok_or_else(|| anyhow::anyhow!("no window"))?;
     let document = window
         .document()
         .ok_or_else(|| anyhow::anyhow!("no document"))?;
+    note_capabilities(&window, &mut diagnostics.borrow_mut());
+    overlay::update_diagnostics(&document, &diagnostics.borrow());
 
     let canvas_el = document
         .get_element_by_id("app-canvas")
@@ -175,18 +703,41 @@ async fn init() -> anyhow::Result<()> {
                     listener_for_tick,
                     engine,
                     paused,
+                    resume,
                 } = match build_audio_and_engine(document.clone()).await {
                     Ok(p) => p,
-                    Err(_) => return,
+                    Err(e) => {
+                        let message = format!("couldn't start ({e})");
+                        report_problem(&diagnostics, Severity::Fatal, "audio", message);
+                        return;
+                    }
+                };
+
+                wire_overlay_buttons(&audio_ctx, &paused, &engine);
+                let lifecycle = wire_audio_lifecycle(&audio_ctx, &paused);
+
+                // Effect settings come from the URL, else from the last session
+                let saved = resume.restored();
+                let setting = |name: &str| {
+                    dom::query_param(name).or_else(|| saved?.setting(name).map(str::to_string))
                 };
 
-                wire_overlay_buttons(&audio_ctx, &paused);
-                events::wire_overlay_toggle_h(&document);
+                // Key bindings, rebindable in the Keys panel and remembered in
+                // the browser; every keydown handler reads them
+                let keys: events::SharedKeys = Rc::new(RefCell::new(events::KeyBindings {
+                    keymap: events::load_keymap(),
+                    capturing: None,
+                }));
+                events::wire_key_rebinding(&document, keys.clone());
+                events::wire_overlay_toggle_h(&document, keys.clone());
 
                 // FX buses
                 let fx = match audio::build_fx_buses(&audio_ctx) {
                     Ok(f) => f,
-                    Err(_) => return,
+                    Err(e) => {
+                        report_problem(&diagnostics, Severity::Fatal, "audio", e);
+                        return;
+                    }
                 };
                 let master_gain = fx.master_gain.clone();
                 let sat_pre = fx.sat_pre.clone();
@@ -198,76 +749,1880 @@ async fn init() -> anyhow::Result<()> {
                 let delay_feedback = fx.delay_feedback.clone();
                 let delay_wet = fx.delay_wet.clone();
 
+                // Output gain staging: `?output=<id>` at startup, or the overlay selector
+                let fx = Rc::new(fx);
+                {
+                    let preset = dom::query_param("output")
+                        .and_then(|id| output_preset_by_id(&id))
+                        .unwrap_or(&OUTPUT_PRESETS[0]);
+                    audio::apply_output_preset(&fx, preset);
+                    dom::set_select_value(&document, "output-preset", preset.id);
+                    let fx = fx.clone();
+                    dom::add_select_change_listener(&document, "output-preset", move |id| {
+                        if let Some(preset) = output_preset_by_id(&id) {
+                            audio::apply_output_preset(&fx, preset);
+                        }
+                    });
+                }
+
+                // Listener volume and mute (↑/↓, Shift+M or the Output panel), kept in
+                // localStorage across reloads
+                let master_volume = Rc::new(audio::MasterVolume::new(
+                    fx.volume.clone(),
+                    dom::local_storage_get(constants::MASTER_VOLUME_STORAGE_KEY)
+                        .and_then(|s| MasterMixer::parse(&s))
+                        .unwrap_or_default(),
+                    {
+                        let doc = document.clone();
+                        move |mixer: &MasterMixer| {
+                            dom::local_storage_set(
+                                constants::MASTER_VOLUME_STORAGE_KEY,
+                                &mixer.encode(),
+                            );
+                            overlay::update_volume(&doc, mixer);
+                        }
+                    },
+                ));
+                {
+                    let volume = master_volume.clone();
+                    dom::add_input_listener(&document, "master-volume", "input", move |s| {
+                        if let Ok(percent) = s.parse::<f32>() {
+                            volume.update(|m| {
+                                m.set_volume(percent / 100.0);
+                                m.muted = false;
+                            });
+                        }
+                    });
+                    let volume = master_volume.clone();
+                    dom::add_click_listener(&document, "master-mute", move || {
+                        volume.update(|m| m.toggle_mute());
+                    });
+                }
+
+                // Output device: the overlay lists the browser's audio outputs (again
+                // whenever devices come and go) and `?device=<name>` picks one at startup
+                {
+                    let refresh = {
+                        let doc = document.clone();
+                        let ctx = audio_ctx.clone();
+                        move |wanted: Option<String>| {
+                            let doc = doc.clone();
+                            let ctx = ctx.clone();
+                            spawn_local(async move {
+                                let devices = match audio::output_devices().await {
+                                    Ok(devices) => devices,
+                                    Err(e) => {
+                                        log::warn!("[audio] cannot list outputs: {:?}", e);
+                                        return;
+                                    }
+                                };
+                                let mut options = vec![(String::new(), "Default".to_string())];
+                                options.extend(devices.iter().cloned());
+                                dom::set_select_options(&doc, "output-device", &options);
+                                if let Some(id) = wanted.and_then(|w| find_by_name(&devices, &w)) {
+                                    if let Err(e) = audio::set_output_device(&ctx, id).await {
+                                        log::warn!("[audio] output device: {:?}", e);
+                                        return;
+                                    }
+                                    log::info!("[audio] output device {}", id);
+                                }
+                                // Keep showing the device in use across refreshes
+                                if let Some(id) = js_sys::Reflect::get(&ctx, &"sinkId".into())
+                                    .ok()
+                                    .and_then(|v| v.as_string())
+                                {
+                                    dom::set_select_value(&doc, "output-device", &id);
+                                }
+                            });
+                        }
+                    };
+                    refresh(dom::query_param("device"));
+                    if let Ok(devices) = web::window()
+                        .ok_or(JsValue::NULL)
+                        .and_then(|w| w.navigator().media_devices())
+                    {
+                        let refresh = refresh.clone();
+                        let on_change =
+                            Closure::wrap(Box::new(move || refresh(None)) as Box<dyn FnMut()>);
+                        devices.set_ondevicechange(Some(on_change.as_ref().unchecked_ref()));
+                        on_change.forget();
+                    }
+                    let ctx = audio_ctx.clone();
+                    dom::add_select_change_listener(&document, "output-device", move |id| {
+                        let ctx = ctx.clone();
+                        spawn_local(async move {
+                            match audio::set_output_device(&ctx, &id).await {
+                                Ok(()) => log::info!("[audio] output device {:?}", id),
+                                Err(e) => log::warn!("[audio] output device: {:?}", e),
+                            }
+                        });
+                    });
+                }
+
+                // Master stereo width (`?width=0..2` or the overlay slider), opened up a
+                // little more while the swirl is stirred
+                let stereo_width = Rc::new(Cell::new(
+                    setting("width")
+                        .and_then(|s| parse_width(&s))
+                        .unwrap_or(DEFAULT_STEREO_WIDTH),
+                ));
+                dom::set_input_value(&document, "stereo-width", &stereo_width.get().to_string());
+                {
+                    let stereo_width = stereo_width.clone();
+                    dom::add_input_listener(&document, "stereo-width", "input", move |s| {
+                        if let Some(width) = parse_width(&s) {
+                            stereo_width.set(width);
+                        }
+                    });
+                }
+
+                // Master EQ for speaker/room compensation: `?eq=low,mid,high` (dB)
+                // at startup, or the overlay sliders
+                let master_eq = Rc::new(RefCell::new(
+                    setting("eq")
+                        .and_then(|s| MasterEq::parse(&s))
+                        .unwrap_or(MasterEq::FLAT),
+                ));
+                audio::apply_master_eq(&fx, &master_eq.borrow());
+                for (band, id) in ["eq-low", "eq-mid", "eq-high"].into_iter().enumerate() {
+                    let gain_db = master_eq.borrow().bands[band].gain_db;
+                    dom::set_input_value(&document, id, &gain_db.to_string());
+                    let fx = fx.clone();
+                    let master_eq = master_eq.clone();
+                    dom::add_input_listener(&document, id, "input", move |value| {
+                        if let Ok(gain_db) = value.parse::<f32>() {
+                            master_eq.borrow_mut().set_gain(band, gain_db);
+                            audio::apply_master_eq(&fx, &master_eq.borrow());
+                        }
+                    });
+                }
+
+                // Reverb character: `?reverb=<id>` at startup, or the overlay selector
+                // (the last preset picked is saved with the session; an imported IR isn't)
+                let reverb_id = Rc::new(Cell::new(None));
+                let select_reverb = {
+                    let ctx = audio_ctx.clone();
+                    let fx = fx.clone();
+                    let reverb_id = reverb_id.clone();
+                    Rc::new(move |preset: &'static ReverbPreset| {
+                        reverb_id.set(Some(preset.id));
+                        if let Some(ir) = audio::reverb_buffer(&ctx, preset) {
+                            audio::set_reverb_impulse(&ctx, &fx, &ir);
+                            log::info!("[audio] reverb {}", preset.name);
+                        }
+                    })
+                };
+                if let Some(preset) = setting("reverb").and_then(|id| reverb_preset_by_id(&id)) {
+                    select_reverb(preset);
+                    dom::set_select_value(&document, "reverb-preset", preset.id);
+                }
+                {
+                    let select_reverb = select_reverb.clone();
+                    dom::add_select_change_listener(&document, "reverb-preset", move |id| {
+                        if let Some(preset) = reverb_preset_by_id(&id) {
+                            select_reverb(preset);
+                        }
+                    });
+                }
+
                 // Per-voice master gains -> master bus, plus effect sends
                 let initial_positions: Vec<Vec3> =
                     engine.borrow().voices.iter().map(|v| v.position).collect();
+                let spatial: Vec<SpatialHints> =
+                    engine.borrow().configs.iter().map(|c| c.spatial).collect();
                 let routing = match audio::wire_voices(
                     &audio_ctx,
                     &initial_positions,
+                    &spatial,
                     &master_gain,
                     &delay_in,
                     &reverb_in,
                 ) {
                     Ok(r) => r,
-                    Err(_) => return,
+                    Err(e) => {
+                        report_problem(&diagnostics, Severity::Fatal, "audio", e);
+                        return;
+                    }
                 };
                 let delay_sends = Rc::new(routing.delay_sends);
                 let reverb_sends = Rc::new(routing.reverb_sends);
                 let voice_panners = routing.voice_panners;
                 let voice_gains = Rc::new(routing.voice_gains);
+                let voice_fades = Rc::new(routing.voice_fades);
+                let voice_ducks = Rc::new(routing.voice_ducks);
+
+                // Synthesize in an AudioWorklet unless `?synth=nodes` asks for the
+                // per-note OscillatorNode path (also the fallback if the worklet fails)
+                let worklet_synth = if dom::query_param("synth").as_deref() == Some("nodes") {
+                    None
+                } else {
+                    match audio::WorkletSynth::create(&audio_ctx, &voice_fades).await {
+                        Ok(synth) => Some(synth),
+                        Err(e) => {
+                            log::warn!("[audio] worklet synth unavailable, using nodes: {:?}", e);
+                            let message = format!(
+                                "the AudioWorklet synth couldn't load ({}); notes play through Web Audio nodes",
+                                dom::error_text(&e)
+                            );
+                            report_problem(&diagnostics, Severity::Notice, "audio", message);
+                            None
+                        }
+                    }
+                };
+                log::info!(
+                    "[audio] synth={}",
+                    if worklet_synth.is_some() {
+                        "worklet"
+                    } else {
+                        "nodes"
+                    }
+                );
+
+                // Performance recording of the master output ('O' or the Export button),
+                // downloaded as a stereo WAV once the stop frame has been captured
+                match audio::PcmRecorder::create(&audio_ctx, &fx.limiter, {
+                    let doc = document.clone();
+                    let engine = engine.clone();
+                    let takes = Cell::new(0u32);
+                    move |recording| {
+                        takes.set(takes.get() + 1);
+                        let wav = recording.to_wav();
+                        let name = format!(
+                            "geno-1-seed{}-take{}.wav",
+                            engine.borrow().seed(),
+                            takes.get()
+                        );
+                        dom::download_bytes(&doc, &name, "audio/wav", &wav);
+                        log::info!(
+                            "[record] {:.2} s, {} bytes",
+                            recording.duration_sec(),
+                            wav.len()
+                        );
+                    }
+                })
+                .await
+                {
+                    Ok(recorder) => {
+                        let ctx = audio_ctx.clone();
+                        let doc = document.clone();
+                        let toggle: Rc<dyn Fn()> = Rc::new(move || {
+                            let at = ctx.current_time() + constants::RECORD_LEAD_SEC;
+                            if recorder.is_recording() {
+                                recorder.stop(at);
+                            } else {
+                                recorder.start(at);
+                            }
+                            overlay::update_record_button(&doc, recorder.is_recording());
+                        });
+                        events::wire_record_toggle_o(keys.clone(), toggle.clone());
+                        dom::add_click_listener(&document, "export-record", move || toggle());
+                    }
+                    Err(e) => {
+                        log::warn!("[audio] recorder unavailable: {:?}", e);
+                        let message = format!("unavailable ({})", dom::error_text(&e));
+                        report_problem(&diagnostics, Severity::Notice, "recording", message);
+                    }
+                }
 
                 // Initialize WebGPU
-                let gpu: Option<render::GpuState> = frame::init_gpu(&canvas_for_click_inner).await;
+                let mut gpu: Option<render::GpuState> =
+                    match frame::init_gpu(&canvas_for_click_inner).await {
+                        Ok(g) => Some(g),
+                        Err(e) => {
+                            // The adapter error goes on to list likely causes
+                            let reason =
+                                e.to_string().lines().next().unwrap_or_default().to_string();
+                            let message =
+                                format!("{reason}; the voices are drawn as plain circles");
+                            report_problem(&diagnostics, Severity::Degraded, "graphics", message);
+                            None
+                        }
+                    };
+                // Without WebGPU the voices are still drawn, as plain circles
+                let sketch = gpu
+                    .is_none()
+                    .then(|| sketch::Sketch::new(&canvas_for_click_inner))
+                    .flatten();
+                if gpu.is_none() && sketch.is_none() {
+                    let message =
+                        "no 2D canvas either, so nothing is drawn (the music still plays)";
+                    report_problem(&diagnostics, Severity::Degraded, "graphics", message);
+                }
+
+                // Render layers: `?render=waves|scene|hybrid` at startup, 'V' cycles
+                let render_mode = Rc::new(RefCell::new(
+                    dom::query_param("render")
+                        .and_then(|id| render::RenderMode::from_id(&id))
+                        .unwrap_or_default(),
+                ));
+                // Analyser layers: `?layers=spectrum,scope`, the Visuals checkboxes,
+                // or Shift+V to cycle them
+                let visual_layers = Rc::new(Cell::new(
+                    dom::query_param("layers")
+                        .and_then(|s| VisualLayers::parse(&s))
+                        .unwrap_or_default(),
+                ));
+                overlay::update_visual_layers(&document, visual_layers.get());
+                for (id, spectrum) in [("layer-spectrum", true), ("layer-scope", false)] {
+                    let visual_layers = visual_layers.clone();
+                    dom::add_checkbox_listener(&document, id, move |on| {
+                        let mut layers = visual_layers.get();
+                        if spectrum {
+                            layers.spectrum = on;
+                        } else {
+                            layers.scope = on;
+                        }
+                        visual_layers.set(layers);
+                    });
+                }
+                events::wire_render_mode_toggle_v(
+                    keys.clone(),
+                    render_mode.clone(),
+                    visual_layers.clone(),
+                );
+
+                // Fullscreen visualizer: `?visual=` at startup, 'U' or the Visuals
+                // select to switch (the renderer crossfades)
+                let visualizer = Rc::new(Cell::new(
+                    setting("visual")
+                        .and_then(|id| Visualizer::from_id(&id))
+                        .unwrap_or_default(),
+                ));
+                let visualizer_options: Vec<(String, String)> = Visualizer::ALL
+                    .iter()
+                    .map(|v| (v.id().to_string(), v.name().to_string()))
+                    .collect();
+                dom::set_select_options(&document, "visualizer", &visualizer_options);
+                overlay::update_visualizer(&document, visualizer.get());
+                {
+                    let visualizer = visualizer.clone();
+                    dom::add_select_change_listener(&document, "visualizer", move |id| {
+                        if let Some(v) = Visualizer::from_id(&id) {
+                            visualizer.set(v);
+                        }
+                    });
+                }
+                {
+                    let doc = document.clone();
+                    events::wire_visualizer_toggle_u(keys.clone(), visualizer.clone(), move |v| {
+                        overlay::update_visualizer(&doc, v);
+                    });
+                }
+                // Shader development: `?shaders=on` refetches shaders/*.wgsl
+                // and rebuilds the pipelines when they change
+                let shader_reload = shader_reload::ShaderReload::new(
+                    dom::query_param("shaders").and_then(|s| parse_shader_base(&s)),
+                );
+                // Custom visualizer: WGSL typed, picked or dropped into Visuals →
+                // Custom shader, compiled and swapped in, and remembered in the
+                // browser
+                {
+                    let queue = shader_reload.queue();
+                    let saved = dom::local_storage_get(constants::CUSTOM_SHADER_STORAGE_KEY)
+                        .filter(|s| !s.trim().is_empty());
+                    dom::set_text_area_value(
+                        &document,
+                        "custom-shader",
+                        saved.as_deref().unwrap_or(crate::core::CUSTOM_WGSL),
+                    );
+                    if let Some(source) = saved {
+                        queue.borrow_mut().push((Shader::Custom, source));
+                    }
+                    let apply = {
+                        let visualizer = visualizer.clone();
+                        let doc = document.clone();
+                        Rc::new(move |source: String| {
+                            let stored = if source == crate::core::CUSTOM_WGSL {
+                                ""
+                            } else {
+                                &source
+                            };
+                            dom::local_storage_set(constants::CUSTOM_SHADER_STORAGE_KEY, stored);
+                            queue.borrow_mut().push((Shader::Custom, source));
+                            visualizer.set(Visualizer::Custom);
+                            overlay::update_visualizer(&doc, Visualizer::Custom);
+                        })
+                    };
+                    {
+                        let apply = apply.clone();
+                        let doc = document.clone();
+                        dom::add_click_listener(&document, "custom-shader-apply", move || {
+                            if let Some(source) = dom::text_area_value(&doc, "custom-shader") {
+                                apply(source);
+                            }
+                        });
+                    }
+                    {
+                        let apply = apply.clone();
+                        let doc = document.clone();
+                        dom::add_click_listener(&document, "custom-shader-reset", move || {
+                            dom::set_text_area_value(
+                                &doc,
+                                "custom-shader",
+                                crate::core::CUSTOM_WGSL,
+                            );
+                            apply(crate::core::CUSTOM_WGSL.to_string());
+                        });
+                    }
+                    let load = {
+                        let doc = document.clone();
+                        Rc::new(move |source: String| {
+                            dom::set_text_area_value(&doc, "custom-shader", &source);
+                            apply(source);
+                        })
+                    };
+                    {
+                        let load = load.clone();
+                        dom::add_text_drop_listener(&document, "custom-shader", move |s| load(s));
+                    }
+                    dom::add_file_listener(&document, "custom-shader-file", move |bytes| {
+                        load(String::from_utf8_lossy(&bytes).into_owned());
+                    });
+                }
+
+                // Colour theme: `?theme=` for this visit, otherwise the one last
+                // picked (key I or the Visuals select), remembered in the browser
+                let theme: Rc<Cell<&'static Theme>> = Rc::new(Cell::new(
+                    dom::query_param("theme")
+                        .or_else(|| dom::local_storage_get(constants::THEME_STORAGE_KEY))
+                        .and_then(|id| Theme::from_id(&id))
+                        .unwrap_or(&THEMES[0]),
+                ));
+                let theme_options: Vec<(String, String)> = THEMES
+                    .iter()
+                    .map(|t| (t.id.to_string(), t.name.to_string()))
+                    .collect();
+                dom::set_select_options(&document, "theme", &theme_options);
+                overlay::update_theme(&document, theme.get());
+                let theme_changed = {
+                    let doc = document.clone();
+                    move |t: &'static Theme| {
+                        dom::local_storage_set(constants::THEME_STORAGE_KEY, t.id);
+                        overlay::update_theme(&doc, t);
+                    }
+                };
+                {
+                    let theme = theme.clone();
+                    let changed = theme_changed.clone();
+                    dom::add_select_change_listener(&document, "theme", move |id| {
+                        if let Some(t) = Theme::from_id(&id) {
+                            theme.set(t);
+                            changed(t);
+                        }
+                    });
+                }
+                events::wire_theme_toggle_i(keys.clone(), theme.clone(), theme_changed);
+
+                // Peak brightness and flash limits for photosensitive viewers:
+                // `?safe=1` for this visit, otherwise the checkbox beside the
+                // theme, remembered in the browser
+                let safe_visuals = Rc::new(Cell::new(
+                    dom::query_param("safe")
+                        .or_else(|| dom::local_storage_get(constants::SAFE_VISUALS_STORAGE_KEY))
+                        .is_some_and(|v| v == "1" || v == "on"),
+                ));
+                dom::set_checked(&document, "safe-visuals", safe_visuals.get());
+                {
+                    let safe_visuals = safe_visuals.clone();
+                    dom::add_checkbox_listener(&document, "safe-visuals", move |on| {
+                        safe_visuals.set(on);
+                        dom::local_storage_set(
+                            constants::SAFE_VISUALS_STORAGE_KEY,
+                            if on { "1" } else { "0" },
+                        );
+                    });
+                }
+
+                // Bloom strength, threshold and blur quality: `?bloom=` at startup,
+                // the Visuals sliders, or J (quality) and Y / Shift+Y (strength)
+                let bloom = Rc::new(Cell::new(
+                    setting("bloom")
+                        .and_then(|s| Bloom::parse(&s))
+                        .unwrap_or_default(),
+                ));
+                overlay::update_bloom(&document, &bloom.get());
+                for (id, threshold) in [("bloom-strength", false), ("bloom-threshold", true)] {
+                    let bloom = bloom.clone();
+                    dom::add_input_listener(&document, id, "input", move |s| {
+                        if let Ok(v) = s.parse::<f32>() {
+                            let b = bloom.get();
+                            bloom.set(if threshold {
+                                b.with_threshold(v)
+                            } else {
+                                b.with_strength(v)
+                            });
+                        }
+                    });
+                }
+                {
+                    let bloom = bloom.clone();
+                    dom::add_select_change_listener(&document, "bloom-quality", move |s| {
+                        if let Ok(passes) = s.parse::<u32>() {
+                            bloom.set(bloom.get().with_passes(passes));
+                        }
+                    });
+                }
+                {
+                    let doc = document.clone();
+                    events::wire_bloom_keys_j_y(keys.clone(), bloom.clone(), move |b| {
+                        overlay::update_bloom(&doc, &b);
+                    });
+                }
+
+                // Tonemap and exposure in the composite: `?tonemap=` and
+                // `?exposure=<ev>|auto[,<ev>]` at startup, or the Visuals controls
+                let tonemap = Rc::new(Cell::new(
+                    setting("tonemap")
+                        .and_then(|id| Tonemap::from_id(&id))
+                        .unwrap_or_default(),
+                ));
+                let exposure = Rc::new(Cell::new(
+                    setting("exposure")
+                        .and_then(|s| Exposure::parse(&s))
+                        .unwrap_or_default(),
+                ));
+                let tonemap_options: Vec<(String, String)> = Tonemap::ALL
+                    .iter()
+                    .map(|t| (t.id().to_string(), t.name().to_string()))
+                    .collect();
+                dom::set_select_options(&document, "tonemap", &tonemap_options);
+                overlay::update_exposure(&document, tonemap.get(), &exposure.get());
+                {
+                    let tonemap = tonemap.clone();
+                    dom::add_select_change_listener(&document, "tonemap", move |id| {
+                        if let Some(t) = Tonemap::from_id(&id) {
+                            tonemap.set(t);
+                        }
+                    });
+                }
+                {
+                    let exposure = exposure.clone();
+                    dom::add_input_listener(&document, "exposure-ev", "input", move |s| {
+                        if let Ok(ev) = s.parse::<f32>() {
+                            exposure.set(exposure.get().with_ev(ev));
+                        }
+                    });
+                }
+                {
+                    let exposure = exposure.clone();
+                    dom::add_checkbox_listener(&document, "exposure-auto", move |auto| {
+                        exposure.set(Exposure {
+                            auto,
+                            ..exposure.get()
+                        });
+                    });
+                }
+
+                // Lens effects after the composite: `?dof=<quality>[,<focus>]` and
+                // `?vignette=<0..1>|on` at startup, or the Visuals controls
+                let mut initial_lens = Lens::default();
+                if let Some(l) = setting("dof").and_then(|s| initial_lens.with_dof_param(&s)) {
+                    initial_lens = l;
+                }
+                if let Some(l) =
+                    setting("vignette").and_then(|s| initial_lens.with_vignette_param(&s))
+                {
+                    initial_lens = l;
+                }
+                let lens = Rc::new(Cell::new(initial_lens));
+                let dof_options: Vec<(String, String)> = DofQuality::ALL
+                    .iter()
+                    .map(|q| (q.id().to_string(), q.name().to_string()))
+                    .collect();
+                dom::set_select_options(&document, "dof", &dof_options);
+                overlay::update_lens(&document, &lens.get());
+                {
+                    let lens = lens.clone();
+                    dom::add_select_change_listener(&document, "dof", move |id| {
+                        if let Some(dof) = DofQuality::from_id(&id) {
+                            lens.set(Lens { dof, ..lens.get() });
+                        }
+                    });
+                }
+                {
+                    let lens = lens.clone();
+                    dom::add_input_listener(&document, "dof-focus", "input", move |s| {
+                        if let Ok(focus) = s.parse::<f32>() {
+                            lens.set(lens.get().with_focus(focus));
+                        }
+                    });
+                }
+                {
+                    let lens = lens.clone();
+                    dom::add_input_listener(&document, "vignette", "input", move |s| {
+                        if let Ok(v) = s.parse::<f32>() {
+                            lens.set(lens.get().with_vignette(v));
+                        }
+                    });
+                }
+
+                // Kaleidoscope symmetry in the composite: `?mirror=<2|4|6>[,bars]`
+                // at startup, or the Visuals controls
+                let mirror = Rc::new(Cell::new(
+                    setting("mirror")
+                        .and_then(|s| Mirror::from_param(&s))
+                        .unwrap_or_default(),
+                ));
+                overlay::update_mirror(&document, &mirror.get());
+                {
+                    let mirror = mirror.clone();
+                    dom::add_select_change_listener(&document, "mirror", move |id| {
+                        if let Some(segments) = mirror::parse_segments(&id) {
+                            mirror.set(Mirror {
+                                segments,
+                                ..mirror.get()
+                            });
+                        }
+                    });
+                }
+                {
+                    let mirror = mirror.clone();
+                    dom::add_checkbox_listener(&document, "mirror-bars", move |step_on_bars| {
+                        mirror.set(Mirror {
+                            step_on_bars,
+                            ..mirror.get()
+                        });
+                    });
+                }
+
+                // Anti-aliasing: `?aa=off|fxaa|msaa` at startup, or the Visuals select
+                let antialias = Rc::new(Cell::new(
+                    setting("aa")
+                        .and_then(|s| Antialias::from_id(&s))
+                        .unwrap_or_default(),
+                ));
+                let aa_options: Vec<(String, String)> = Antialias::ALL
+                    .iter()
+                    .map(|aa| (aa.id().to_string(), aa.name().to_string()))
+                    .collect();
+                dom::set_select_options(&document, "antialias", &aa_options);
+                dom::set_select_value(&document, "antialias", antialias.get().id());
+                {
+                    let antialias = antialias.clone();
+                    dom::add_select_change_listener(&document, "antialias", move |id| {
+                        if let Some(aa) = Antialias::from_id(&id) {
+                            antialias.set(aa);
+                        }
+                    });
+                }
+
+                // Starfield behind the waves: `?stars=<0..1>|on|off` at startup, or
+                // the Visuals slider
+                let stars = Rc::new(Cell::new(
+                    setting("stars")
+                        .and_then(|s| parse_stars(&s))
+                        .unwrap_or(0.0),
+                ));
+                dom::set_input_value(&document, "stars", &stars.get().to_string());
+                {
+                    let stars = stars.clone();
+                    dom::add_input_listener(&document, "stars", "input", move |s| {
+                        if let Some(v) = parse_stars(&s) {
+                            stars.set(v);
+                        }
+                    });
+                }
+
+                // Resolution of the HDR/bloom chain: `?scale=auto|0.5..1` at
+                // startup, or the Visuals select; Auto follows the frame time
+                let render_scale_mode = Rc::new(Cell::new(
+                    dom::query_param("scale")
+                        .and_then(|s| RenderScaleMode::parse(&s))
+                        .unwrap_or_default(),
+                ));
+                let render_scale = RenderScale::new(render_scale_mode.get());
+                overlay::update_render_scale(
+                    &document,
+                    render_scale_mode.get(),
+                    render_scale.scale(),
+                );
+                {
+                    let render_scale_mode = render_scale_mode.clone();
+                    dom::add_select_change_listener(&document, "render-scale", move |id| {
+                        if let Some(mode) = RenderScaleMode::parse(&id) {
+                            render_scale_mode.set(mode);
+                        }
+                    });
+                }
+
+                // Status line drawn on the canvas itself: `?status=1` or the
+                // Visuals checkbox
+                let canvas_status = Rc::new(Cell::new(
+                    dom::query_param("status").is_some_and(|v| v == "1" || v == "on"),
+                ));
+                dom::set_checked(&document, "canvas-status", canvas_status.get());
+                {
+                    let canvas_status = canvas_status.clone();
+                    dom::add_checkbox_listener(&document, "canvas-status", move |on| {
+                        canvas_status.set(on);
+                    });
+                }
+
+                // Cinematic camera for unattended play: `?cinematic=1` or the
+                // Visuals checkbox
+                let cinematic = Rc::new(Cell::new(
+                    dom::query_param("cinematic").is_some_and(|v| v == "1" || v == "on"),
+                ));
+                dom::set_checked(&document, "cinematic", cinematic.get());
+                {
+                    let cinematic = cinematic.clone();
+                    dom::add_checkbox_listener(&document, "cinematic", move |on| {
+                        cinematic.set(on);
+                        log::info!("[camera] cinematic={on}");
+                    });
+                }
+                let camera = Rc::new(Cell::new(CameraPose::home(constants::CAMERA_Z)));
+                let camera_zoom = Rc::new(Cell::new(1.0_f32));
+
+                // Attract mode for unattended screens: `?attract=on|off|<idle sec>`
+                // or the Visuals checkbox; any pointer, key or wheel input
+                // ends it
+                let attract = Rc::new(Cell::new(Attract::new(
+                    dom::query_param("attract")
+                        .and_then(|s| parse_attract(&s))
+                        .flatten(),
+                )));
+                dom::set_checked(
+                    &document,
+                    "attract",
+                    attract.get().idle_after_sec().is_some(),
+                );
+                {
+                    let attract = attract.clone();
+                    dom::add_checkbox_listener(&document, "attract", move |on| {
+                        let mut a = attract.get();
+                        a.set_idle_after_sec(on.then_some(DEFAULT_ATTRACT_IDLE_SEC));
+                        attract.set(a);
+                        log::info!("[attract] on={on}");
+                    });
+                }
+                let input_seen = Rc::new(Cell::new(false));
+                if let Some(window) = web::window() {
+                    for event in [
+                        "pointerdown",
+                        "pointermove",
+                        "keydown",
+                        "wheel",
+                        "touchstart",
+                    ] {
+                        let input_seen = input_seen.clone();
+                        let closure = Closure::wrap(
+                            Box::new(move || input_seen.set(true)) as Box<dyn FnMut()>
+                        );
+                        _ = window.add_event_listener_with_callback(
+                            event,
+                            closure.as_ref().unchecked_ref(),
+                        );
+                        closure.forget();
+                    }
+                }
+
+                // Spanning several screens: `?display=<n>/<count>` makes this a
+                // display window drawing its part of the control window's
+                // scene (silent, no overlay); the control window opens them
+                // from Visuals → Displays
+                let display_slice =
+                    dom::query_param("display").and_then(|s| DisplaySlice::parse(&s));
+                let displays = displays::DisplayLink::new(display_slice);
+                if let Some(g) = gpu.as_mut() {
+                    g.set_display_slice(display_slice);
+                }
+                if display_slice.is_some() {
+                    overlay::hide(&document);
+                } else {
+                    let count = Rc::new(Cell::new(2));
+                    {
+                        let count = count.clone();
+                        dom::add_select_change_listener(&document, "display-count", move |v| {
+                            if let Ok(n) = v.parse() {
+                                count.set(n);
+                            }
+                        });
+                    }
+                    dom::add_click_listener(&document, "open-displays", move || {
+                        displays::open_windows(count.get());
+                    });
+                }
+
+                // Performance HUD: Shift+P or `?hud=1`
+                let perf_hud = Rc::new(Cell::new(
+                    dom::query_param("hud").is_some_and(|v| v == "1" || v == "on"),
+                ));
+                overlay::show_perf_hud(&document, perf_hud.get());
+                {
+                    let doc = document.clone();
+                    events::wire_perf_hud_toggle_p(keys.clone(), perf_hud.clone(), move |show| {
+                        overlay::show_perf_hud(&doc, show);
+                    });
+                }
+
+                // Screenshots: Z at the canvas size, Shift+Z at twice it
+                let screenshot = Rc::new(Cell::new(None));
+                events::wire_screenshot_keys_z(keys.clone(), screenshot.clone());
 
                 // Visual pulses per voice and optional analyser for ambient effects
                 let pulses = Rc::new(RefCell::new(vec![0.0_f32; engine.borrow().voices.len()]));
                 let (analyser, analyser_buf) = audio::create_analyser(&audio_ctx);
 
+                // Pulse source (`?pulses=notes|onsets|mic` or the overlay): onsets come
+                // from a spectral-flux detector fed through one of two gates, the master
+                // output or the microphone (asked for the first time it is chosen). The
+                // microphone also feeds the ambient analyser, so in that mode the waves
+                // and glow follow whatever is playing in the room; `?listen=1` or the
+                // Listen only box holds the music meanwhile
+                let listen_only = Rc::new(Cell::new(
+                    dom::query_param("listen").is_some_and(|v| v == "1" || v == "on"),
+                ));
+                dom::set_checked(&document, "listen-only", listen_only.get());
+                {
+                    let listen_only = listen_only.clone();
+                    dom::add_checkbox_listener(&document, "listen-only", move |on| {
+                        listen_only.set(on);
+                    });
+                }
+                let pulse_source = Rc::new(Cell::new(
+                    dom::query_param("pulses")
+                        .and_then(|s| PulseSource::from_id(&s))
+                        .unwrap_or_default(),
+                ));
+                let onset_input = audio::OnsetInput::new(&audio_ctx);
+                if let Some(input) = &onset_input {
+                    let gates = [
+                        web::GainNode::new(&audio_ctx),
+                        web::GainNode::new(&audio_ctx),
+                    ];
+                    if let [Ok(output_gate), Ok(mic_gate)] = gates {
+                        _ = fx.limiter.connect_with_audio_node(&output_gate);
+                        _ = output_gate.connect_with_audio_node(input.node());
+                        _ = mic_gate.connect_with_audio_node(input.node());
+                        if let Some(a) = &analyser {
+                            _ = mic_gate.connect_with_audio_node(a);
+                        }
+                        let mic_requested = Cell::new(false);
+                        let ctx = audio_ctx.clone();
+                        let select = move |source: PulseSource| {
+                            let mic = source == PulseSource::Mic;
+                            output_gate.gain().set_value(if mic { 0.0 } else { 1.0 });
+                            mic_gate.gain().set_value(if mic { 1.0 } else { 0.0 });
+                            if mic && !mic_requested.replace(true) {
+                                let ctx = ctx.clone();
+                                let gate = mic_gate.clone();
+                                spawn_local(async move {
+                                    if let Err(e) = audio::connect_microphone(&ctx, &gate).await {
+                                        log::warn!("[onset] microphone unavailable: {:?}", e);
+                                    }
+                                });
+                            }
+                            log::info!("[onset] pulses from {}", source.id());
+                        };
+                        select(pulse_source.get());
+                        dom::set_select_value(&document, "pulse-source", pulse_source.get().id());
+                        let pulse_source = pulse_source.clone();
+                        dom::add_select_change_listener(&document, "pulse-source", move |id| {
+                            if let Some(source) = PulseSource::from_id(&id) {
+                                pulse_source.set(source);
+                                select(source);
+                            }
+                        });
+                    }
+                }
+
                 // Queued ripple UV from pointer taps (read by render tick)
-                let queued_ripple_uv: Rc<RefCell<Option<[f32; 2]>>> = Rc::new(RefCell::new(None));
+                let queued_ripple_uv: events::QueuedRipple = Rc::new(RefCell::new(None));
+                let queued_streak: events::QueuedStreak = Rc::new(RefCell::new(Vec::new()));
 
                 // ---------------- Interaction state ----------------
                 let mouse_state = Rc::new(RefCell::new(input::MouseState::default()));
                 let hover_index = Rc::new(RefCell::new(None::<usize>));
-                let drag_state = Rc::new(RefCell::new(input::DragState::default()));
+                let selected_voice = Rc::new(Cell::new(None::<usize>));
+                let presses = Rc::new(RefCell::new(VoicePresses::default()));
+                let history = Rc::new(RefCell::new(History::new()));
+
+                // Dice button on the overlay panel (respects lock checkboxes)
+                {
+                    let engine = engine.clone();
+                    let history = history.clone();
+                    dom::add_click_listener(&document, "dice-roll", move || {
+                        events::roll_dice(&engine, &history);
+                    });
+                }
+
+                // Per-voice send sliders: committed on release as undoable commands
+                for voice in 0..engine.borrow().configs.len() {
+                    let sends = engine.borrow().configs[voice].sends;
+                    let sliders = [
+                        ("level", sends.level),
+                        ("delay", sends.delay),
+                        ("reverb", sends.reverb),
+                    ];
+                    for (name, value) in sliders {
+                        let id = format!("send-{}-{}", name, voice);
+                        dom::set_input_value(&document, &id, &value.to_string());
+                        let engine = engine.clone();
+                        let history = history.clone();
+                        dom::add_input_listener(&document, &id, "change", move |value| {
+                            let Ok(level) = value.parse::<f32>() else {
+                                return;
+                            };
+                            let mut sends = engine.borrow().configs[voice].sends;
+                            match name {
+                                "level" => sends.level = level,
+                                "delay" => sends.delay = level,
+                                _ => sends.reverb = level,
+                            }
+                            let cmd = Command::SetSends { voice, sends };
+                            history.borrow_mut().execute(&mut engine.borrow_mut(), cmd);
+                        });
+                    }
+                }
+
+                // Per-voice position locks, for players without a right button
+                for voice in 0..engine.borrow().voices.len() {
+                    let id = format!("lock-voice-{}", voice);
+                    let engine = engine.clone();
+                    let history = history.clone();
+                    dom::add_checkbox_listener(&document, &id, move |locked| {
+                        if engine.borrow().is_locked(voice) != locked {
+                            let cmd = Command::ToggleLock { voice };
+                            history.borrow_mut().execute(&mut engine.borrow_mut(), cmd);
+                        }
+                    });
+                }
+
+                // Ambisonic export renders a copy of the engine offline. The render
+                // runs on the main thread in one go, so audio and visuals stall for
+                // the few seconds it takes; a toast says so first, and the render
+                // waits a moment for it to be painted
+                {
+                    let engine = engine.clone();
+                    let doc = document.clone();
+                    let master_eq = master_eq.clone();
+                    dom::add_click_listener(&document, "export-bformat", move || {
+                        overlay::show_toast(&doc, "Rendering B-format… playback pauses meanwhile");
+                        let engine = engine.clone();
+                        let doc = doc.clone();
+                        let master_eq = master_eq.clone();
+                        let render = wasm_bindgen::closure::Closure::once_into_js(move || {
+                            let mut offline = engine.borrow().clone();
+                            // Camera/listener position expressed in engine space
+                            let listener = (Vec3::new(0.0, 0.0, constants::CAMERA_Z)
+                                - constants::Z_OFFSET)
+                                / constants::SPREAD;
+                            let mut samples = crate::core::ambisonics::render_bformat(
+                                &mut offline,
+                                listener,
+                                constants::BFORMAT_EXPORT_SEC,
+                                constants::BFORMAT_SAMPLE_RATE,
+                            );
+                            // Same speaker compensation as live playback
+                            master_eq.borrow().process_interleaved(
+                                &mut samples,
+                                crate::core::ambisonics::FOA_CHANNELS,
+                                constants::BFORMAT_SAMPLE_RATE,
+                            );
+                            let wav = crate::core::ambisonics::wav_bytes(
+                                &samples,
+                                crate::core::ambisonics::FOA_CHANNELS as u16,
+                                constants::BFORMAT_SAMPLE_RATE,
+                            );
+                            dom::download_bytes(&doc, "geno-1-bformat.wav", "audio/wav", &wav);
+                            log::info!("[export] B-format WAV {} bytes", wav.len());
+                        });
+                        if let Some(window) = web::window() {
+                            _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+                                render.unchecked_ref(),
+                                constants::BFORMAT_RENDER_DELAY_MS,
+                            );
+                        }
+                    });
+                }
+
+                let video: Rc<RefCell<Option<VideoExport>>> = Rc::new(RefCell::new(None));
+
+                // Offline bounce: a fixed-length stereo WAV of the current piece rendered
+                // faster than real time; `?bounce=<seconds>` bounces right after start
+                {
+                    let bounce_param = dom::query_param("bounce");
+                    let seconds = Rc::new(Cell::new(
+                        bounce_param
+                            .as_deref()
+                            .and_then(parse_bounce_seconds)
+                            .unwrap_or(DEFAULT_BOUNCE_SEC),
+                    ));
+                    dom::set_input_value(&document, "bounce-seconds", &seconds.get().to_string());
+                    {
+                        let seconds = seconds.clone();
+                        dom::add_input_listener(&document, "bounce-seconds", "change", move |v| {
+                            if let Some(sec) = parse_bounce_seconds(&v) {
+                                seconds.set(sec);
+                            }
+                        });
+                    }
+                    let busy = Rc::new(Cell::new(false));
+                    let sample_rate = audio_ctx.sample_rate();
+
+                    // Video export of the same length: bounced for the soundtrack, then
+                    // drawn frame by frame into a WebM at `?fps=` (or the Export panel's)
+                    let fps = Rc::new(Cell::new(
+                        dom::query_param("fps")
+                            .as_deref()
+                            .and_then(parse_video_fps)
+                            .unwrap_or(DEFAULT_VIDEO_FPS),
+                    ));
+                    dom::set_select_value(&document, "video-fps", &fps.get().to_string());
+                    {
+                        let fps = fps.clone();
+                        dom::add_select_change_listener(&document, "video-fps", move |v| {
+                            if let Some(f) = parse_video_fps(&v) {
+                                fps.set(f);
+                            }
+                        });
+                    }
+                    {
+                        let (engine, fx, doc) = (engine.clone(), fx.clone(), document.clone());
+                        let (busy, seconds, video) = (busy.clone(), seconds.clone(), video.clone());
+                        let audio_ctx = audio_ctx.clone();
+                        let canvas = canvas_for_click_inner.clone();
+                        dom::add_click_listener(&document, "export-video", move || {
+                            // Pressed again, it stops early and keeps what was drawn
+                            if let Some(running) = video.borrow_mut().take() {
+                                running.stop();
+                                overlay::update_video_button(&doc, None);
+                                return;
+                            }
+                            if busy.replace(true) {
+                                return;
+                            }
+                            let seconds = seconds.get();
+                            let mut offline = engine.borrow().clone();
+                            let sequence = FrameSequence::new(
+                                &mut engine.borrow().clone(),
+                                seconds,
+                                BOUNCE_STEP_FRAMES / sample_rate as f64,
+                                fps.get(),
+                            );
+                            let (fx, doc, busy, video) =
+                                (fx.clone(), doc.clone(), busy.clone(), video.clone());
+                            let (audio_ctx, canvas) = (audio_ctx.clone(), canvas.clone());
+                            spawn_local(async move {
+                                let seed = offline.seed();
+                                match audio::bounce_stereo(&mut offline, &fx, seconds, sample_rate)
+                                    .await
+                                {
+                                    Ok(samples) => {
+                                        let name = format!("geno-1-seed{}-{}s.webm", seed, seconds);
+                                        let save_doc = doc.clone();
+                                        let on_done = move |blob: web::Blob| {
+                                            log::info!("[video] {} bytes", blob.size());
+                                            dom::download_blob(&save_doc, &name, &blob);
+                                        };
+                                        match VideoExport::start(
+                                            &audio_ctx, &canvas, sequence, &samples, on_done,
+                                        ) {
+                                            Ok(started) => {
+                                                *video.borrow_mut() = Some(started);
+                                                overlay::update_video_button(&doc, Some(0.0));
+                                            }
+                                            Err(e) => log::warn!("[video] {:?}", e),
+                                        }
+                                    }
+                                    Err(e) => log::warn!("[video] bounce failed: {:?}", e),
+                                }
+                                busy.set(false);
+                            });
+                        });
+                    }
+
+                    let engine = engine.clone();
+                    let fx = fx.clone();
+                    let doc = document.clone();
+                    let bounce: Rc<dyn Fn()> = Rc::new(move || {
+                        if busy.replace(true) {
+                            return;
+                        }
+                        let mut offline = engine.borrow().clone();
+                        let (fx, doc, busy, seconds) =
+                            (fx.clone(), doc.clone(), busy.clone(), seconds.get());
+                        spawn_local(async move {
+                            let started = instant::Instant::now();
+                            let seed = offline.seed();
+                            match audio::bounce_stereo(&mut offline, &fx, seconds, sample_rate)
+                                .await
+                            {
+                                Ok(samples) => {
+                                    let wav = crate::core::ambisonics::wav_bytes(
+                                        &samples,
+                                        2,
+                                        sample_rate as u32,
+                                    );
+                                    let name = format!("geno-1-seed{}-{}s.wav", seed, seconds);
+                                    dom::download_bytes(&doc, &name, "audio/wav", &wav);
+                                    log::info!(
+                                        "[export] bounced {} s in {:.1} s",
+                                        seconds,
+                                        started.elapsed().as_secs_f64()
+                                    );
+                                }
+                                Err(e) => log::warn!("[export] bounce failed: {:?}", e),
+                            }
+                            busy.set(false);
+                        });
+                    });
+                    {
+                        let bounce = bounce.clone();
+                        dom::add_click_listener(&document, "export-bounce", move || bounce());
+                    }
+                    if bounce_param.is_some() {
+                        bounce();
+                    }
+                }
+
+                // Everything played this session, downloadable as a MIDI file
+                let midi_recorder = Rc::new(RefCell::new(MidiRecorder::new(
+                    engine.borrow().voices.len(),
+                    engine.borrow().params.bpm,
+                )));
+                {
+                    let recorder = midi_recorder.clone();
+                    let doc = document.clone();
+                    let engine = engine.clone();
+                    dom::add_click_listener(&document, "export-midi", move || {
+                        let recorder = recorder.borrow();
+                        let smf = recorder.to_smf();
+                        // The seed in the name lets the run be replayed with `?seed=`
+                        let name = format!("geno-1-seed{}.mid", engine.borrow().seed());
+                        dom::download_bytes(&doc, &name, "audio/midi", &smf);
+                        log::info!("[export] MIDI {} notes", recorder.note_count());
+                    });
+                }
+
+                // Live MIDI out to external synths: the overlay's Connect button asks for
+                // access, or `?midiout=<port name>` connects at startup; `?midich=` maps
+                // voices to channels and `?midilatency=<ms>` offsets the timing. The
+                // Clock in port (or `?midiclock=<port name>`) locks tempo and beat phase
+                // to an external sequencer's MIDI clock
+                let voice_count = engine.borrow().voices.len();
+                let midi_out = Rc::new(RefCell::new(midi::MidiOut::new(voice_count)));
+                {
+                    let mut out = midi_out.borrow_mut();
+                    if let Some(channels) = dom::query_param("midich")
+                        .and_then(|s| MidiOutMap::parse_channels(&s, voice_count))
+                    {
+                        out.map.channels = channels;
+                    }
+                    if let Some(ms) = dom::query_param("midilatency").and_then(|s| s.parse().ok()) {
+                        out.map.set_latency_ms(ms);
+                    }
+                    let channels: Vec<String> = out
+                        .map
+                        .channels
+                        .iter()
+                        .map(|c| (c + 1).to_string())
+                        .collect();
+                    dom::set_input_value(&document, "midi-channels", &channels.join(","));
+                    dom::set_input_value(
+                        &document,
+                        "midi-latency",
+                        &out.map.latency_ms.to_string(),
+                    );
+                }
+                let clock_in = Rc::new(RefCell::new(midi::ClockIn::new(audio_ctx.clone())));
+                let midi_clock = clock_in.borrow().follower.clone();
+                let control_in = Rc::new(RefCell::new(wire_midi_learn(&document, &engine)));
+                let connect_midi = {
+                    let midi_out = midi_out.clone();
+                    let clock_in = clock_in.clone();
+                    let control_in = control_in.clone();
+                    let doc = document.clone();
+                    move |wanted: Option<String>, wanted_clock: Option<String>| {
+                        let midi_out = midi_out.clone();
+                        let clock_in = clock_in.clone();
+                        let control_in = control_in.clone();
+                        let doc = doc.clone();
+                        spawn_local(async move {
+                            let access = match midi::request_access().await {
+                                Ok(access) => access,
+                                Err(e) => {
+                                    log::warn!("[midi] access denied: {:?}", e);
+                                    return;
+                                }
+                            };
+                            let find = |ports: &[(String, String)], wanted: Option<String>| {
+                                find_by_name(ports, &wanted?).map(str::to_string)
+                            };
+                            let with_off = |ports: &[(String, String)]| {
+                                let mut options = vec![("off".to_string(), "Off".to_string())];
+                                options.extend(ports.iter().cloned());
+                                options
+                            };
+                            let ports = midi::output_ports(&access);
+                            dom::set_select_options(&doc, "midi-out", &with_off(&ports));
+                            let mut out = midi_out.borrow_mut();
+                            out.set_access(access.clone());
+                            if let Some(id) = find(&ports, wanted) {
+                                out.select_output(Some(&id));
+                                dom::set_select_value(&doc, "midi-out", &id);
+                            }
+                            let inputs = midi::input_ports(&access);
+                            dom::set_select_options(&doc, "midi-clock", &with_off(&inputs));
+                            control_in.borrow_mut().listen(&access);
+                            let mut clock = clock_in.borrow_mut();
+                            clock.set_access(access);
+                            if let Some(id) = find(&inputs, wanted_clock) {
+                                clock.select_input(Some(&id));
+                                dom::set_select_value(&doc, "midi-clock", &id);
+                            }
+                        });
+                    }
+                };
+                let wanted_out = dom::query_param("midiout");
+                let wanted_clock = dom::query_param("midiclock");
+                if wanted_out.is_some() || wanted_clock.is_some() {
+                    connect_midi(wanted_out, wanted_clock);
+                }
+                dom::add_click_listener(&document, "midi-connect", move || {
+                    connect_midi(None, None)
+                });
+                dom::add_select_change_listener(&document, "midi-clock", move |id| {
+                    clock_in.borrow_mut().select_input(Some(&id));
+                });
+                {
+                    let midi_out = midi_out.clone();
+                    dom::add_select_change_listener(&document, "midi-out", move |id| {
+                        midi_out.borrow_mut().select_output(Some(&id));
+                    });
+                }
+                {
+                    let midi_out = midi_out.clone();
+                    dom::add_input_listener(&document, "midi-channels", "change", move |s| {
+                        if let Some(channels) = MidiOutMap::parse_channels(&s, voice_count) {
+                            let mut out = midi_out.borrow_mut();
+                            out.all_notes_off();
+                            out.map.channels = channels;
+                        }
+                    });
+                }
+                {
+                    let midi_out = midi_out.clone();
+                    dom::add_input_listener(&document, "midi-latency", "change", move |s| {
+                        if let Ok(ms) = s.parse() {
+                            midi_out.borrow_mut().map.set_latency_ms(ms);
+                        }
+                    });
+                }
+
+                // MIDI import: each part of the clip seeds a voice's Markov generator
+                {
+                    let engine = engine.clone();
+                    let history = history.clone();
+                    dom::add_file_listener(&document, "import-midi", move |bytes| {
+                        let parts = match parse_smf(&bytes) {
+                            Ok(notes) => melodies(&notes),
+                            Err(e) => {
+                                log::warn!("[import] MIDI rejected: {}", e);
+                                return;
+                            }
+                        };
+                        if parts.is_empty() {
+                            log::warn!("[import] MIDI file has no notes");
+                            return;
+                        }
+                        let cmds = {
+                            let eng = engine.borrow();
+                            (0..eng.voices.len())
+                                .filter_map(|voice| {
+                                    let melody = &parts[voice % parts.len()];
+                                    let seed = eng.fresh_seed(voice);
+                                    let generator = MarkovGenerator::from_melody(melody, seed)?;
+                                    Some(Command::SetGenerator {
+                                        voice,
+                                        generator: Box::new(generator),
+                                    })
+                                })
+                                .collect()
+                        };
+                        history
+                            .borrow_mut()
+                            .execute(&mut engine.borrow_mut(), Command::Batch(cmds));
+                        log::info!("[import] MIDI clip with {} parts", parts.len());
+                    });
+                }
+
+                // Replays for bug reports: the seed and every input since the engine
+                // was built, downloaded as a `.replay` file; importing one rebuilds
+                // the engine from that seed and does the inputs again on their steps
+                {
+                    let engine = engine.clone();
+                    let doc = document.clone();
+                    dom::add_click_listener(&document, "export-replay", move || {
+                        let eng = engine.borrow();
+                        let replay = Replay {
+                            seed: eng.seed(),
+                            inputs: eng.input_log().to_vec(),
+                        };
+                        let (text, skipped) = replay.encode();
+                        let name = format!("geno-1-seed{}.replay", eng.seed());
+                        dom::download_bytes(&doc, &name, "text/plain", text.as_bytes());
+                        log::info!("[export] replay of {} inputs", replay.inputs.len());
+                        if eng.input_log_is_full() {
+                            let last = replay.inputs.last().map_or(0, |(step, _)| *step);
+                            log::warn!("[export] input log full, replay stops at step {last}");
+                            overlay::show_toast(
+                                &doc,
+                                &format!("Replay log is full: it only reaches step {last}"),
+                            );
+                        } else if skipped > 0 {
+                            log::warn!("[export] {skipped} inputs can't be replayed");
+                            overlay::show_toast(
+                                &doc,
+                                &format!("Replay saved without {skipped} inputs it can't repeat"),
+                            );
+                        }
+                    });
+                }
+                {
+                    let engine = engine.clone();
+                    let history = history.clone();
+                    let doc = document.clone();
+                    dom::add_file_listener(&document, "import-replay", move |bytes| {
+                        let Some(replay) = Replay::decode(&String::from_utf8_lossy(&bytes)) else {
+                            log::warn!("[import] not a replay file");
+                            overlay::show_toast(&doc, "Not a replay file");
+                            return;
+                        };
+                        let startup = preset_bank().swap_remove(0);
+                        let mut fresh =
+                            MusicEngine::new(startup.voices, startup.params, replay.seed);
+                        {
+                            // Loaded tables, samples and mappings stay to hand
+                            let old = engine.borrow();
+                            fresh.wavetables = old.wavetables.clone();
+                            fresh.samples = old.samples.clone();
+                            fresh.controls = old.controls.clone();
+                        }
+                        log::info!("[import] replay of {} inputs", replay.inputs.len());
+                        fresh.queue_replay(replay.inputs);
+                        engine.borrow_mut().replace_with(fresh);
+                        *history.borrow_mut() = History::new();
+                        let session = SessionConfig { seed: replay.seed };
+                        overlay::update_session_seed(&doc, &session.label());
+                        overlay::update_signature(
+                            &doc,
+                            &engine
+                                .borrow()
+                                .signature()
+                                .to_svg(constants::SIGNATURE_SIZE_PX),
+                        );
+                        overlay::show_toast(&doc, &format!("Replaying {}", session.label()));
+                    });
+                }
+
+                // A single-cycle audio file becomes a wavetable for the lead voice
+                {
+                    let engine = engine.clone();
+                    let history = history.clone();
+                    let audio_ctx = audio_ctx.clone();
+                    dom::add_file_listener(&document, "import-wavetable", move |bytes| {
+                        let engine = engine.clone();
+                        let history = history.clone();
+                        let audio_ctx = audio_ctx.clone();
+                        spawn_local(async move {
+                            let cycle = match audio::decode_first_channel(&audio_ctx, &bytes).await
+                            {
+                                Ok(cycle) => cycle,
+                                Err(e) => {
+                                    log::warn!("[import] wavetable rejected: {:?}", e);
+                                    return;
+                                }
+                            };
+                            let Some(table) = Wavetable::from_samples("imported", &cycle) else {
+                                log::warn!("[import] wavetable is silent or too short");
+                                return;
+                            };
+                            let waveform = engine.borrow_mut().load_wavetable(table);
+                            let cmd = Command::SetWaveform {
+                                voice: constants::WAVEFORM_VOICE,
+                                waveform,
+                            };
+                            history.borrow_mut().execute(&mut engine.borrow_mut(), cmd);
+                            log::info!("[import] wavetable from {} samples", cycle.len());
+                        });
+                    });
+                }
+
+                // A recording becomes a sampler for the lead voice, pitched from middle C
+                {
+                    let engine = engine.clone();
+                    let history = history.clone();
+                    let audio_ctx = audio_ctx.clone();
+                    let loop_region =
+                        dom::query_param("sampleloop").and_then(|s| LoopRegion::parse(&s));
+                    dom::add_file_listener(&document, "import-sample", move |bytes| {
+                        let engine = engine.clone();
+                        let history = history.clone();
+                        let audio_ctx = audio_ctx.clone();
+                        spawn_local(async move {
+                            let frames = match audio::decode_first_channel(&audio_ctx, &bytes).await
+                            {
+                                Ok(frames) => frames,
+                                Err(e) => {
+                                    log::warn!("[import] sample rejected: {:?}", e);
+                                    return;
+                                }
+                            };
+                            let rate = audio_ctx.sample_rate() as u32;
+                            let Some(sample) = Sample::new("imported", rate, frames) else {
+                                log::warn!("[import] sample is empty");
+                                return;
+                            };
+                            let duration = sample.duration_sec();
+                            let index = engine.borrow_mut().load_sample(sample);
+                            let sampler = SamplerConfig {
+                                loop_region,
+                                ..SamplerConfig::one_shot(index, DEFAULT_SAMPLE_ROOT_HZ)
+                            };
+                            let cmd = Command::SetSampler {
+                                voice: constants::WAVEFORM_VOICE,
+                                sampler: Some(sampler),
+                            };
+                            history.borrow_mut().execute(&mut engine.borrow_mut(), cmd);
+                            log::info!("[import] sample of {:.2} s", duration);
+                        });
+                    });
+                }
+
+                // A recorded impulse response (picked, or dropped anywhere on the
+                // page) replaces the procedural reverb
+                {
+                    let ctx = audio_ctx.clone();
+                    let fx = fx.clone();
+                    let doc = document.clone();
+                    let load_ir = Rc::new(move |bytes: Vec<u8>| {
+                        let ctx = ctx.clone();
+                        let fx = fx.clone();
+                        let doc = doc.clone();
+                        spawn_local(async move {
+                            match audio::decode_audio(&ctx, &bytes).await {
+                                Ok(ir) => {
+                                    audio::set_reverb_impulse(&ctx, &fx, &ir);
+                                    dom::set_select_value(&doc, "reverb-preset", "custom");
+                                    log::info!("[import] reverb IR of {:.2} s", ir.duration());
+                                }
+                                Err(e) => log::warn!("[import] reverb IR rejected: {:?}", e),
+                            }
+                        });
+                    });
+                    let picked = load_ir.clone();
+                    dom::add_file_listener(&document, "import-reverb", move |bytes| picked(bytes));
+                    dom::add_audio_drop_listener(&document, move |bytes| load_ir(bytes));
+                }
+
+                // Metronome panel: click on/off, its level, and bars of count-in
+                // before playback resumes from a pause
+                overlay::update_metronome(&document, &engine.borrow().metronome());
+                {
+                    let update = {
+                        let engine = engine.clone();
+                        move |f: &dyn Fn(&mut Metronome)| {
+                            let mut m = engine.borrow().metronome();
+                            f(&mut m);
+                            engine.borrow_mut().set_metronome(m);
+                        }
+                    };
+                    let on = update.clone();
+                    dom::add_checkbox_listener(&document, "metronome-on", move |checked| {
+                        on(&|m| m.enabled = checked);
+                    });
+                    let level = update.clone();
+                    dom::add_input_listener(&document, "metronome-level", "input", move |s| {
+                        if let Ok(percent) = s.parse::<f32>() {
+                            level(&|m| m.level = (percent / 100.0).clamp(0.0, 1.0));
+                        }
+                    });
+                    dom::add_select_change_listener(&document, "count-in", move |s| {
+                        if let Ok(bars) = s.parse::<u32>() {
+                            update(&|m| m.count_in_bars = bars.min(MAX_COUNT_IN_BARS));
+                        }
+                    });
+                }
+
+                // Looper panel: record a take of taps, drags and mutes over
+                // the bars chosen, then play it back on the grid
+                let looper = Rc::new(RefCell::new(Looper::default()));
+                overlay::update_looper(&document, &looper.borrow());
+                {
+                    let grid_now = {
+                        let engine = engine.clone();
+                        let audio_ctx = audio_ctx.clone();
+                        move || engine.borrow().grid_position(audio_ctx.current_time())
+                    };
+                    let looper_for_bars = looper.clone();
+                    let doc = document.clone();
+                    dom::add_select_change_listener(&document, "loop-bars", move |s| {
+                        if let Some(bars) = s.parse().ok().filter(|b| LOOP_BAR_CHOICES.contains(b))
+                        {
+                            looper_for_bars.borrow_mut().set_bars(bars);
+                            overlay::update_looper(&doc, &looper_for_bars.borrow());
+                        }
+                    });
+                    let looper_for_record = looper.clone();
+                    let doc = document.clone();
+                    let now = grid_now.clone();
+                    dom::add_click_listener(&document, "loop-record", move || {
+                        looper_for_record.borrow_mut().record(now());
+                        log::info!("[looper] recording");
+                        overlay::update_looper(&doc, &looper_for_record.borrow());
+                    });
+                    let looper_for_play = looper.clone();
+                    let doc = document.clone();
+                    dom::add_click_listener(&document, "loop-play", move || {
+                        looper_for_play.borrow_mut().toggle_play(grid_now());
+                        log::info!("[looper] {:?}", looper_for_play.borrow().state());
+                        overlay::update_looper(&doc, &looper_for_play.borrow());
+                    });
+                    let looper_for_clear = looper.clone();
+                    let doc = document.clone();
+                    dom::add_click_listener(&document, "loop-clear", move || {
+                        looper_for_clear.borrow_mut().clear();
+                        overlay::update_looper(&doc, &looper_for_clear.borrow());
+                    });
+                }
+
+                // Automation panel: record tempo, key, scale, detune, drags, sends
+                // and density changes as a timeline, then play it back from the top
+                let automation = Rc::new(RefCell::new(Automation::default()));
+                overlay::update_automation(&document, &automation.borrow());
+                {
+                    let grid_now = {
+                        let engine = engine.clone();
+                        let audio_ctx = audio_ctx.clone();
+                        move || engine.borrow().grid_position(audio_ctx.current_time())
+                    };
+                    let automation_for_record = automation.clone();
+                    let engine = engine.clone();
+                    let doc = document.clone();
+                    let now = grid_now.clone();
+                    dom::add_click_listener(&document, "automation-record", move || {
+                        let mut automation = automation_for_record.borrow_mut();
+                        if automation.state() == AutomationState::Recording {
+                            automation.stop_recording(now());
+                            log::info!("[automation] recorded {} bars", automation.bars());
+                        } else {
+                            automation.record(now(), automation_snapshot(&engine.borrow()));
+                            log::info!("[automation] recording");
+                        }
+                        overlay::update_automation(&doc, &automation);
+                    });
+                    let automation_for_play = automation.clone();
+                    let doc = document.clone();
+                    dom::add_click_listener(&document, "automation-play", move || {
+                        automation_for_play.borrow_mut().toggle_play(grid_now());
+                        log::info!("[automation] {:?}", automation_for_play.borrow().state());
+                        overlay::update_automation(&doc, &automation_for_play.borrow());
+                    });
+                    let automation_for_clear = automation.clone();
+                    let doc = document.clone();
+                    dom::add_click_listener(&document, "automation-clear", move || {
+                        automation_for_clear.borrow_mut().clear();
+                        overlay::update_automation(&doc, &automation_for_clear.borrow());
+                    });
+                }
 
                 // Keyboard controls
+                events::wire_voice_selection(
+                    &document,
+                    keys.clone(),
+                    engine.clone(),
+                    history.clone(),
+                    selected_voice.clone(),
+                );
                 events::wire_global_keydown(
+                    keys.clone(),
                     engine.clone(),
+                    history.clone(),
                     paused.clone(),
-                    master_gain.clone(),
+                    master_volume.clone(),
                     canvas_for_click_inner.clone(),
                 );
 
+                // Clipboard paste applies a copied settings string
+                events::wire_clipboard_paste(engine.clone(), history.clone());
+
                 // Pointer handlers (move/down/up)
                 events::wire_input_handlers(events::InputWiring {
                     canvas: canvas_for_click_inner.clone(),
                     engine: engine.clone(),
+                    history: history.clone(),
                     mouse_state: mouse_state.clone(),
                     hover_index: hover_index.clone(),
-                    drag_state: drag_state.clone(),
+                    presses: presses.clone(),
+                    gesture: Rc::new(RefCell::new(TwoFingerGesture::default())),
+                    timings: dom::query_param("press")
+                        .and_then(|s| PressTimings::parse(&s))
+                        .unwrap_or_default(),
+                    taps: Rc::new(RefCell::new(TapTracker::default())),
+                    pointer_speed: Rc::new(Cell::new(PointerSpeed::default())),
+                    press_pressure: Rc::new(Cell::new(None)),
+                    volume: master_volume.clone(),
                     voice_gains: voice_gains.clone(),
                     delay_sends: delay_sends.clone(),
                     reverb_sends: reverb_sends.clone(),
                     audio_ctx: audio_ctx.clone(),
                     queued_ripple_uv: queued_ripple_uv.clone(),
+                    strum: Rc::new(RefCell::new(Strum::default())),
+                    queued_streak: queued_streak.clone(),
+                    hold: Rc::new(RefCell::new(BackgroundHold::default())),
+                    looper: looper.clone(),
+                    automation: automation.clone(),
+                    camera: camera.clone(),
+                    camera_zoom: camera_zoom.clone(),
                 });
 
+                // Engine state-change subscribers
+                let mut event_bus = EventBus::new();
+                events::subscribe_hint_overlay(&mut event_bus, engine.clone());
+                {
+                    // Keep the send sliders in step with undo/redo
+                    let doc = document.clone();
+                    event_bus.subscribe(Box::new(move |ev| {
+                        if let EngineEvent::SendsChanged { voice, sends } = ev {
+                            let level = format!("send-level-{}", voice);
+                            let delay = format!("send-delay-{}", voice);
+                            let reverb = format!("send-reverb-{}", voice);
+                            dom::set_input_value(&doc, &level, &sends.level.to_string());
+                            dom::set_input_value(&doc, &delay, &sends.delay.to_string());
+                            dom::set_input_value(&doc, &reverb, &sends.reverb.to_string());
+                        }
+                    }));
+                }
+                {
+                    // Keep the lock boxes in step with right-clicks and undo
+                    let doc = document.clone();
+                    event_bus.subscribe(Box::new(move |ev| {
+                        if let EngineEvent::LockChanged { voice, locked } = ev {
+                            dom::set_checked(&doc, &format!("lock-voice-{}", voice), *locked);
+                        }
+                    }));
+                }
+                {
+                    let recorder = midi_recorder.clone();
+                    let ctx = audio_ctx.clone();
+                    event_bus.subscribe(Box::new(move |ev| {
+                        if let EngineEvent::BpmChanged(bpm) = ev {
+                            recorder.borrow_mut().record_tempo(ctx.current_time(), *bpm);
+                        }
+                    }));
+                }
+                audio::subscribe_voice_fades(
+                    &mut event_bus,
+                    engine.clone(),
+                    audio_ctx.clone(),
+                    voice_fades.clone(),
+                );
+                audio::subscribe_metronome(&mut event_bus, fx.click.clone());
+                {
+                    // Keep the metronome controls in step with 'N'
+                    let doc = document.clone();
+                    event_bus.subscribe(Box::new(move |ev| {
+                        if let EngineEvent::MetronomeChanged(m) = ev {
+                            overlay::update_metronome(&doc, m);
+                        }
+                    }));
+                }
+                audio::subscribe_sidechain(&mut event_bus, audio_ctx.clone(), voice_ducks.clone());
+                {
+                    // Changes from keys, panels, knobs and undo go on the
+                    // automation timeline while it records
+                    let automation = automation.clone();
+                    let engine = engine.clone();
+                    let audio_ctx = audio_ctx.clone();
+                    event_bus.subscribe(Box::new(move |ev| {
+                        if let Some(cmd) = event_command(ev) {
+                            let at = engine.borrow().grid_position(audio_ctx.current_time());
+                            automation.borrow_mut().capture(at, cmd);
+                        }
+                    }));
+                }
+
+                // The effect settings in the form their URL parameters take, as saved
+                // with the session and presets
+                let capture_settings = {
+                    let stereo_width = stereo_width.clone();
+                    let master_eq = master_eq.clone();
+                    let bloom = bloom.clone();
+                    let tonemap = tonemap.clone();
+                    let exposure = exposure.clone();
+                    let lens = lens.clone();
+                    let mirror = mirror.clone();
+                    let antialias = antialias.clone();
+                    let stars = stars.clone();
+                    let visualizer = visualizer.clone();
+                    let reverb_id = reverb_id.clone();
+                    Rc::new(move || -> Vec<(String, String)> {
+                        let mut settings = vec![
+                            ("width", stereo_width.get().to_string()),
+                            ("eq", master_eq.borrow().param()),
+                            ("bloom", bloom.get().param()),
+                            ("tonemap", tonemap.get().id().to_string()),
+                            ("exposure", exposure.get().param()),
+                            ("dof", lens.get().dof_param()),
+                            ("vignette", lens.get().vignette.to_string()),
+                            ("mirror", mirror.get().param()),
+                            ("aa", antialias.get().id().to_string()),
+                            ("stars", stars.get().to_string()),
+                            ("visual", visualizer.get().id().to_string()),
+                        ];
+                        if let Some(id) = reverb_id.get() {
+                            settings.push(("reverb", id.to_string()));
+                        }
+                        settings
+                            .into_iter()
+                            .map(|(name, value)| (name.to_string(), value))
+                            .collect()
+                    })
+                };
+                {
+                    // Saving waits while a crashed session's autosave is on offer
+                    let saving = Rc::new(Cell::new(resume.offered().is_none()));
+                    if resume.offered().is_some() {
+                        wire_recovery_prompt(&document, &saving);
+                    }
+                    let capture_settings = capture_settings.clone();
+                    wire_session_save(&engine, move || capture_settings(), saving);
+                }
+
+                // Presets panel: the session saved under a name, loaded back with
+                // its seed and effect settings, or exported and imported as a
+                // JSON file
+                {
+                    let doc = document.clone();
+                    let stereo_width = stereo_width.clone();
+                    let master_eq = master_eq.clone();
+                    let fx = fx.clone();
+                    let bloom = bloom.clone();
+                    let tonemap = tonemap.clone();
+                    let exposure = exposure.clone();
+                    let lens = lens.clone();
+                    let mirror = mirror.clone();
+                    let antialias = antialias.clone();
+                    let stars = stars.clone();
+                    let visualizer = visualizer.clone();
+                    let apply_setting = move |name: &str, value: &str| match name {
+                        "width" => {
+                            if let Some(width) = parse_width(value) {
+                                stereo_width.set(width);
+                                dom::set_input_value(&doc, "stereo-width", &width.to_string());
+                            }
+                        }
+                        "eq" => {
+                            if let Some(eq) = MasterEq::parse(value) {
+                                *master_eq.borrow_mut() = eq;
+                                audio::apply_master_eq(&fx, &eq);
+                                for (band, id) in
+                                    ["eq-low", "eq-mid", "eq-high"].into_iter().enumerate()
+                                {
+                                    dom::set_input_value(
+                                        &doc,
+                                        id,
+                                        &eq.bands[band].gain_db.to_string(),
+                                    );
+                                }
+                            }
+                        }
+                        "reverb" => {
+                            if let Some(preset) = reverb_preset_by_id(value) {
+                                select_reverb(preset);
+                                dom::set_select_value(&doc, "reverb-preset", preset.id);
+                            }
+                        }
+                        "bloom" => {
+                            if let Some(b) = Bloom::parse(value) {
+                                bloom.set(b);
+                                overlay::update_bloom(&doc, &b);
+                            }
+                        }
+                        "tonemap" => {
+                            if let Some(t) = Tonemap::from_id(value) {
+                                tonemap.set(t);
+                                overlay::update_exposure(&doc, t, &exposure.get());
+                            }
+                        }
+                        "exposure" => {
+                            if let Some(e) = Exposure::parse(value) {
+                                exposure.set(e);
+                                overlay::update_exposure(&doc, tonemap.get(), &e);
+                            }
+                        }
+                        "dof" => {
+                            if let Some(l) = lens.get().with_dof_param(value) {
+                                lens.set(l);
+                                overlay::update_lens(&doc, &l);
+                            }
+                        }
+                        "vignette" => {
+                            if let Some(l) = lens.get().with_vignette_param(value) {
+                                lens.set(l);
+                                overlay::update_lens(&doc, &l);
+                            }
+                        }
+                        "mirror" => {
+                            if let Some(m) = Mirror::from_param(value) {
+                                mirror.set(m);
+                                overlay::update_mirror(&doc, &m);
+                            }
+                        }
+                        "aa" => {
+                            if let Some(aa) = Antialias::from_id(value) {
+                                antialias.set(aa);
+                                dom::set_select_value(&doc, "antialias", aa.id());
+                            }
+                        }
+                        "stars" => {
+                            if let Some(v) = parse_stars(value) {
+                                stars.set(v);
+                                dom::set_input_value(&doc, "stars", &v.to_string());
+                            }
+                        }
+                        "visual" => {
+                            if let Some(v) = Visualizer::from_id(value) {
+                                visualizer.set(v);
+                                overlay::update_visualizer(&doc, v);
+                            }
+                        }
+                        _ => {}
+                    };
+                    wire_presets(
+                        &document,
+                        &engine,
+                        &history,
+                        capture_settings,
+                        apply_setting,
+                    );
+                }
+
                 // Scheduler + renderer loop driven by requestAnimationFrame
                 let frame_ctx = Rc::new(RefCell::new(frame::FrameContext {
                     engine: engine.clone(),
+                    event_bus,
                     paused: paused.clone(),
+                    lifecycle,
                     pulses: pulses.clone(),
                     hover_index: hover_index.clone(),
+                    selected_voice,
                     canvas: canvas_for_click_inner.clone(),
                     mouse: mouse_state.clone(),
                     audio_ctx: audio_ctx.clone(),
                     listener: listener_for_tick.clone(),
+                    voice_fades,
+                    voice_ducks,
                     voice_gains: voice_gains.clone(),
                     delay_sends: delay_sends.clone(),
                     reverb_sends: reverb_sends.clone(),
                     voice_panners,
                     reverb_wet: reverb_wet.clone(),
+                    click_bus: fx.click.clone(),
+                    fx: fx.clone(),
+                    stereo_width,
                     delay_wet: delay_wet.clone(),
                     delay_feedback: delay_feedback.clone(),
                     sat_pre: sat_pre.clone(),
@@ -275,8 +2630,50 @@ async fn init() -> anyhow::Result<()> {
                     sat_dry: sat_dry.clone(),
                     analyser: analyser.clone(),
                     analyser_buf: analyser_buf.clone(),
+                    onset_input,
+                    pulse_source,
+                    listen_only,
+                    visual_layers,
+                    spectrum_bars: SpectrumBars::default(),
+                    scope_samples: Vec::new(),
+                    scope_points: [0.0; SCOPE_POINTS],
                     gpu,
+                    displays,
+                    attract,
+                    attract_restore: None,
+                    input_seen,
+                    sketch,
+                    shader_reload,
+                    render_mode,
+                    theme,
+                    bloom,
+                    exposure,
+                    tonemap,
+                    lens,
+                    mirror,
+                    stars,
+                    antialias,
+                    safe_visuals,
+                    render_scale_mode,
+                    render_scale,
+                    canvas_status,
+                    perf_hud,
+                    profiler: FrameProfiler::default(),
+                    screenshot,
+                    video,
+                    cinematic,
+                    mirror_bars: BarCounter::default(),
+                    choreographer: Choreographer::new(
+                        engine.borrow().seed(),
+                        CameraPose::home(constants::CAMERA_Z),
+                    ),
+                    camera,
+                    camera_zoom,
+                    visualizer,
                     queued_ripple_uv: queued_ripple_uv.clone(),
+                    queued_streak,
+                    looper,
+                    automation,
                     last_instant: Instant::now(),
                     prev_uv: [0.5, 0.5],
                     swirl_energy: 0.0,
@@ -284,6 +2681,25 @@ async fn init() -> anyhow::Result<()> {
                     swirl_vel: [0.0, 0.0],
                     swirl_initialized: false,
                     pulse_energy: [0.0, 0.0, 0.0],
+                    midi_recorder,
+                    midi_out,
+                    midi_clock,
+                    pending_pulses: Vec::new(),
+                    pending_bursts: Vec::new(),
+                    musical_clock: MusicalClock::default(),
+                    voice_trails: Vec::new(),
+                    packed_trails: Vec::new(),
+                    note_allocator: VoiceAllocator::new(
+                        engine
+                            .borrow()
+                            .configs
+                            .iter()
+                            .map(|c| c.polyphony)
+                            .collect(),
+                    ),
+                    auto_gain: AutoGain::default(),
+                    worklet_synth,
+                    synth_assets: audio::SynthAssets::default(),
                 }));
                 // Start RAF loop
                 frame::start_loop(frame_ctx);
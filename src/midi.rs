@@ -0,0 +1,220 @@
+use crate::audio;
+use crate::core::clock_sync::MidiClockFollower;
+use crate::core::midi_learn::parse_cc;
+use crate::core::midi_out::{from_performance_ms, to_performance_ms, MidiOutMap};
+use crate::core::NoteEvent;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys as web;
+
+/// Ask the browser for MIDI access (prompts the user the first time).
+pub async fn request_access() -> Result<web::MidiAccess, JsValue> {
+    let navigator = web::window()
+        .ok_or_else(|| JsValue::from_str("no window"))?
+        .navigator();
+    Ok(JsFuture::from(navigator.request_midi_access()?)
+        .await?
+        .unchecked_into())
+}
+
+/// `(id, name)` of every MIDI output the browser can see.
+pub fn output_ports(access: &web::MidiAccess) -> Vec<(String, String)> {
+    ports(&access.outputs().values())
+}
+
+/// `(id, name)` of every MIDI input the browser can see.
+pub fn input_ports(access: &web::MidiAccess) -> Vec<(String, String)> {
+    ports(&access.inputs().values())
+}
+
+fn ports(values: &js_sys::Iterator) -> Vec<(String, String)> {
+    let mut ports = Vec::new();
+    if let Ok(Some(values)) = js_sys::try_iter(values) {
+        for port in values.flatten() {
+            let port: web::MidiPort = port.unchecked_into();
+            let name = port.name().unwrap_or_else(|| port.id());
+            ports.push((port.id(), name));
+        }
+    }
+    ports
+}
+
+/// Live MIDI output of the engine's notes: each voice on its own channel,
+/// timed to line up with the audio.
+pub struct MidiOut {
+    pub map: MidiOutMap,
+    access: Option<web::MidiAccess>,
+    output: Option<web::MidiOutput>,
+}
+
+impl MidiOut {
+    pub fn new(voices: usize) -> MidiOut {
+        MidiOut {
+            map: MidiOutMap::new(voices),
+            access: None,
+            output: None,
+        }
+    }
+
+    pub fn set_access(&mut self, access: web::MidiAccess) {
+        self.access = Some(access);
+    }
+
+    /// Send to output port `id` (`None` or an unknown id turns MIDI out
+    /// off); notes still sounding on the old port are silenced.
+    pub fn select_output(&mut self, id: Option<&str>) {
+        self.all_notes_off();
+        self.output = id.and_then(|id| self.access.as_ref()?.outputs().get(id));
+        log::info!(
+            "[midi] output {}",
+            self.output
+                .as_ref()
+                .and_then(|o| o.name())
+                .unwrap_or_else(|| "off".into())
+        );
+    }
+
+    /// Queue `events` (stamped on the audio clock) with timestamps at which
+    /// they will be heard: `audio_now_sec` plus `output_latency_sec`, plus the
+    /// map's offset.
+    pub fn send_notes(&self, events: &[NoteEvent], audio_now_sec: f64, output_latency_sec: f64) {
+        let (Some(output), Some(perf)) =
+            (&self.output, web::window().and_then(|w| w.performance()))
+        else {
+            return;
+        };
+        let perf_now = perf.now();
+        for msg in events
+            .iter()
+            .filter_map(|ev| self.map.note_messages(ev))
+            .flatten()
+        {
+            let at = to_performance_ms(msg.at_sec + output_latency_sec, audio_now_sec, perf_now);
+            let data = js_sys::Uint8Array::from(&msg.bytes[..]);
+            _ = output.send_with_timestamp(&data, at);
+        }
+    }
+
+    /// Drop anything queued and silence every mapped channel.
+    pub fn all_notes_off(&self) {
+        if let Some(output) = &self.output {
+            output.clear();
+            for msg in self.map.all_notes_off() {
+                _ = output.send(&js_sys::Uint8Array::from(&msg[..]));
+            }
+        }
+    }
+}
+
+/// Incoming MIDI clock from one input port, restamped on the audio clock
+/// (as heard, so output latency is taken off) and fed to a
+/// `MidiClockFollower` the frame loop locks the engine to.
+pub struct ClockIn {
+    pub follower: Rc<RefCell<MidiClockFollower>>,
+    audio_ctx: web::AudioContext,
+    access: Option<web::MidiAccess>,
+    input: Option<web::MidiInput>,
+    handler: Option<Closure<dyn FnMut(web::MidiMessageEvent)>>,
+}
+
+impl ClockIn {
+    pub fn new(audio_ctx: web::AudioContext) -> ClockIn {
+        ClockIn {
+            follower: Rc::new(RefCell::new(MidiClockFollower::default())),
+            audio_ctx,
+            access: None,
+            input: None,
+            handler: None,
+        }
+    }
+
+    pub fn set_access(&mut self, access: web::MidiAccess) {
+        self.access = Some(access);
+    }
+
+    /// Follow clock on input port `id` (`None` or an unknown id turns sync
+    /// off and the engine back to its own tempo).
+    pub fn select_input(&mut self, id: Option<&str>) {
+        if let Some(old) = self.input.take() {
+            old.set_onmidimessage(None);
+        }
+        self.follower.borrow_mut().reset();
+        self.input = id.and_then(|id| self.access.as_ref()?.inputs().get(id));
+        self.handler = self.input.as_ref().map(|input| {
+            let follower = self.follower.clone();
+            let ctx = self.audio_ctx.clone();
+            let handler = Closure::wrap(Box::new(move |ev: web::MidiMessageEvent| {
+                let Some(&status) = ev.data().ok().as_ref().and_then(|d| d.first()) else {
+                    return;
+                };
+                // System real-time messages only: clock, start, continue, stop
+                if status < 0xF8 {
+                    return;
+                }
+                let Some(perf) = web::window().and_then(|w| w.performance()) else {
+                    return;
+                };
+                let at = from_performance_ms(ev.time_stamp(), ctx.current_time(), perf.now())
+                    - audio::output_latency_sec(&ctx);
+                follower.borrow_mut().handle(status, at);
+            }) as Box<dyn FnMut(_)>);
+            input.set_onmidimessage(Some(handler.as_ref().unchecked_ref()));
+            handler
+        });
+        log::info!(
+            "[midi] clock in {}",
+            self.input
+                .as_ref()
+                .and_then(|i| i.name())
+                .unwrap_or_else(|| "off".into())
+        );
+    }
+}
+
+/// Control changes from every MIDI input, for mapped knobs and MIDI-learn.
+/// `on_cc` is given `(channel, controller, value)`.
+pub struct ControlIn {
+    inputs: Vec<web::MidiInput>,
+    handler: Closure<dyn FnMut(web::MidiMessageEvent)>,
+}
+
+impl ControlIn {
+    pub fn new(mut on_cc: impl FnMut(u8, u8, u8) + 'static) -> ControlIn {
+        let handler = Closure::wrap(Box::new(move |ev: web::MidiMessageEvent| {
+            if let Some((channel, cc, value)) = ev.data().ok().as_deref().and_then(parse_cc) {
+                on_cc(channel, cc, value);
+            }
+        }) as Box<dyn FnMut(_)>);
+        ControlIn {
+            inputs: Vec::new(),
+            handler,
+        }
+    }
+
+    /// Listen on every input `access` can see now (replacing any listened
+    /// to before). Listening alongside `ClockIn` on the same port is fine.
+    pub fn listen(&mut self, access: &web::MidiAccess) {
+        for old in self.inputs.drain(..) {
+            _ = old.remove_event_listener_with_callback(
+                "midimessage",
+                self.handler.as_ref().unchecked_ref(),
+            );
+        }
+        if let Ok(Some(values)) = js_sys::try_iter(&access.inputs().values()) {
+            for input in values.flatten() {
+                let input: web::MidiInput = input.unchecked_into();
+                _ = input.add_event_listener_with_callback(
+                    "midimessage",
+                    self.handler.as_ref().unchecked_ref(),
+                );
+                // Listeners added this way don't open the port by themselves
+                _ = input.open();
+                self.inputs.push(input);
+            }
+        }
+        log::info!("[midi] control in on {} port(s)", self.inputs.len());
+    }
+}
@@ -1,3 +1,22 @@
+use crate::core::automation::{Automation, AutomationState};
+use crate::core::bloom::Bloom;
+use crate::core::diagnostics::Diagnostics;
+use crate::core::keymap::{Action, KeyChord, Keymap};
+use crate::core::lens::Lens;
+use crate::core::looper::{LoopState, Looper};
+use crate::core::metronome::Metronome;
+use crate::core::midi_learn::{CcMap, CcTarget, MidiLearn};
+use crate::core::mirror::Mirror;
+use crate::core::mixer::MasterMixer;
+use crate::core::named_presets::NamedPresets;
+use crate::core::profiler::PerfStats;
+use crate::core::render_scale::RenderScaleMode;
+use crate::core::spectrum::VisualLayers;
+use crate::core::theme::Theme;
+use crate::core::tonemap::{Exposure, Tonemap};
+use crate::core::visualizer::Visualizer;
+use crate::core::ParamLocks;
+use wasm_bindgen::JsCast;
 use web_sys as web;
 
 #[inline]
@@ -44,7 +63,13 @@ pub fn toggle(document: &web::Document) {
 }
 
 /// Update the hint overlay with current engine state
-pub fn update_hint(document: &web::Document, detune_cents: f32, bpm: f32, scale_name: &str) {
+pub fn update_hint(
+    document: &web::Document,
+    detune_cents: f32,
+    bpm: f32,
+    scale_name: &str,
+    groove_name: &str,
+) {
     if let Some(el) = document.get_element_by_id("hint-overlay") {
         let detune_text = if detune_cents.abs() < 0.1 {
             "Detune: 0¢".to_string()
@@ -55,10 +80,11 @@ pub fn update_hint(document: &web::Document, detune_cents: f32, bpm: f32, scale_
 
         let bpm_text = format!("BPM: {:.0}", bpm);
         let scale_text = format!("Scale: {}", scale_name);
+        let groove_text = format!("Groove: {}", groove_name);
 
         let hint_html = format!(
-            "<div style='color: #cfe7ff; font: 13px system-ui; background: rgba(10, 14, 24, 0.8); padding: 8px 12px; border-radius: 6px; border: 1px solid rgba(80, 110, 150, 0.35);'>{} • {} • {}</div>",
-            detune_text, bpm_text, scale_text
+            "<div style='color: #cfe7ff; font: 13px system-ui; background: rgba(10, 14, 24, 0.8); padding: 8px 12px; border-radius: 6px; border: 1px solid rgba(80, 110, 150, 0.35);'>{} • {} • {} • {}</div>",
+            detune_text, bpm_text, scale_text, groove_text
         );
 
         el.set_inner_html(&hint_html);
@@ -71,3 +97,346 @@ pub fn show_hint(document: &web::Document) {
         el.set_attribute("style", "").ok();
     }
 }
+
+/// Replace the signature glyph shown on the start overlay
+pub fn update_signature(document: &web::Document, svg: &str) {
+    if let Some(el) = document.get_element_by_id("signature") {
+        el.set_inner_html(svg);
+    }
+}
+
+/// Show the session seed (e.g. `seed=4217`) under the signature glyph
+pub fn update_session_seed(document: &web::Document, label: &str) {
+    if let Some(el) = document.get_element_by_id("session-seed") {
+        el.set_text_content(Some(label));
+    }
+}
+
+/// Label the record button with what pressing it will do next
+pub fn update_record_button(document: &web::Document, recording: bool) {
+    if let Some(el) = document.get_element_by_id("export-record") {
+        el.set_text_content(Some(if recording { "Stop" } else { "Record" }));
+    }
+}
+
+/// Show a video export's progress (0..1) on its button, which stops it
+pub fn update_video_button(document: &web::Document, progress: Option<f32>) {
+    if let Some(el) = document.get_element_by_id("export-video") {
+        let text = match progress {
+            Some(p) => format!("Stop {:.0}%", p * 100.0),
+            None => "Video".to_string(),
+        };
+        el.set_text_content(Some(&text));
+    }
+}
+
+/// Show the master level on the Output panel's slider, readout and mute button
+pub fn update_volume(document: &web::Document, mixer: &MasterMixer) {
+    let percent = (mixer.volume * 100.0).round();
+    crate::dom::set_input_value(document, "master-volume", &percent.to_string());
+    if let Some(el) = document.get_element_by_id("master-volume-label") {
+        let text = if mixer.muted {
+            "Muted".to_string()
+        } else {
+            format!("{}%", percent)
+        };
+        el.set_text_content(Some(&text));
+        _ = el.class_list().toggle_with_force("muted", mixer.muted);
+    }
+    if let Some(el) = document.get_element_by_id("master-mute") {
+        el.set_text_content(Some(if mixer.muted { "Unmute" } else { "Mute" }));
+    }
+}
+
+/// Show the metronome settings on its panel
+pub fn update_metronome(document: &web::Document, metronome: &Metronome) {
+    crate::dom::set_checked(document, "metronome-on", metronome.enabled);
+    let percent = (metronome.level * 100.0).round();
+    crate::dom::set_input_value(document, "metronome-level", &percent.to_string());
+    crate::dom::set_select_value(document, "count-in", &metronome.count_in_bars.to_string());
+}
+
+/// Show or hide the start overlay's offer to restore a crashed session
+pub fn show_recovery(document: &web::Document, show: bool) {
+    if let Some(el) = document.get_element_by_id("recovery") {
+        _ = el.class_list().toggle_with_force("hidden", !show);
+    }
+}
+
+/// Show the looper's state, take and length on its panel
+pub fn update_looper(document: &web::Document, looper: &Looper) {
+    let actions = looper.action_count();
+    let status = match looper.state() {
+        LoopState::Empty => "empty".to_string(),
+        LoopState::Recording => format!("recording… {actions}"),
+        LoopState::Playing => format!("playing {actions}"),
+        LoopState::Stopped => format!("stopped, {actions} kept"),
+    };
+    if let Some(el) = document.get_element_by_id("loop-status") {
+        el.set_text_content(Some(&status));
+    }
+    if let Some(el) = document.get_element_by_id("loop-play") {
+        let playing = looper.state() == LoopState::Playing;
+        el.set_text_content(Some(if playing { "Stop" } else { "Play" }));
+    }
+    crate::dom::set_select_value(document, "loop-bars", &looper.bars().to_string());
+}
+
+/// The worst problem in the status banner (hidden when there is none worth
+/// showing) and the full list in the Diagnostics panel.
+pub fn update_diagnostics(document: &web::Document, diagnostics: &Diagnostics) {
+    if let Some(el) = document.get_element_by_id("status-banner") {
+        let headline = diagnostics.headline();
+        el.set_text_content(headline.as_deref());
+        _ = el
+            .class_list()
+            .toggle_with_force("hidden", headline.is_none());
+        _ = el
+            .class_list()
+            .toggle_with_force("fatal", diagnostics.is_fatal());
+    }
+    if let Some(el) = document.get_element_by_id("diagnostics") {
+        el.set_text_content(Some(&diagnostics.text()));
+    }
+}
+
+/// Show the automation timeline's state and take on its panel
+pub fn update_automation(document: &web::Document, automation: &Automation) {
+    let changes = automation.change_count();
+    let bars = automation.bars();
+    let status = match automation.state() {
+        AutomationState::Empty => "empty".to_string(),
+        AutomationState::Recording => format!("recording… {changes}"),
+        AutomationState::Playing => format!("playing {changes} over {bars} bars"),
+        AutomationState::Stopped => format!("{changes} changes over {bars} bars"),
+    };
+    if let Some(el) = document.get_element_by_id("automation-status") {
+        el.set_text_content(Some(&status));
+    }
+    let recording = automation.state() == AutomationState::Recording;
+    if let Some(el) = document.get_element_by_id("automation-record") {
+        el.set_text_content(Some(if recording { "Stop" } else { "Record" }));
+    }
+    if let Some(el) = document.get_element_by_id("automation-play") {
+        let playing = automation.state() == AutomationState::Playing;
+        el.set_text_content(Some(if playing { "Stop" } else { "Play" }));
+    }
+}
+
+/// List the saved presets on the Presets panel, picking `selected`
+pub fn update_presets(document: &web::Document, presets: &NamedPresets, selected: Option<&str>) {
+    let options: Vec<(String, String)> = presets
+        .names()
+        .map(|name| (name.to_string(), name.to_string()))
+        .collect();
+    crate::dom::set_select_options(document, "preset-list", &options);
+    if let Some(name) = selected {
+        crate::dom::set_select_value(document, "preset-list", name);
+    }
+}
+
+pub fn update_visualizer(document: &web::Document, visualizer: Visualizer) {
+    crate::dom::set_select_value(document, "visualizer", visualizer.id());
+}
+
+/// Show the bloom settings on its panel
+pub fn update_bloom(document: &web::Document, bloom: &Bloom) {
+    crate::dom::set_input_value(document, "bloom-strength", &bloom.strength.to_string());
+    crate::dom::set_input_value(document, "bloom-threshold", &bloom.threshold.to_string());
+    crate::dom::set_select_value(document, "bloom-quality", &bloom.passes.to_string());
+}
+
+/// Show the tonemap and exposure settings on their panel
+pub fn update_exposure(document: &web::Document, tonemap: Tonemap, exposure: &Exposure) {
+    crate::dom::set_select_value(document, "tonemap", tonemap.id());
+    crate::dom::set_input_value(document, "exposure-ev", &exposure.ev.to_string());
+    crate::dom::set_checked(document, "exposure-auto", exposure.auto);
+}
+
+/// Show the lens settings in the Visuals controls
+pub fn update_lens(document: &web::Document, lens: &Lens) {
+    crate::dom::set_select_value(document, "dof", lens.dof.id());
+    crate::dom::set_input_value(document, "dof-focus", &lens.focus.to_string());
+    crate::dom::set_input_value(document, "vignette", &lens.vignette.to_string());
+}
+
+/// Show the mirror settings in the Visuals controls
+pub fn update_mirror(document: &web::Document, mirror: &Mirror) {
+    let segments = match mirror.segments {
+        0 => "off".to_string(),
+        n => n.to_string(),
+    };
+    crate::dom::set_select_value(document, "mirror", &segments);
+    crate::dom::set_checked(document, "mirror-bars", mirror.step_on_bars);
+}
+
+/// Show the render scale choice and the scale currently in use
+pub fn update_render_scale(document: &web::Document, mode: RenderScaleMode, scale: f32) {
+    crate::dom::set_select_value(document, "render-scale", &mode.id());
+    update_render_scale_readout(document, scale);
+}
+
+/// Show the scale the HDR/bloom chain renders at (it moves in Auto)
+pub fn update_render_scale_readout(document: &web::Document, scale: f32) {
+    if let Some(el) = document.get_element_by_id("render-scale-readout") {
+        el.set_text_content(Some(&format!("{:.1}%", scale * 100.0)));
+    }
+}
+
+/// Show or hide the performance HUD
+pub fn show_perf_hud(document: &web::Document, show: bool) {
+    if let Some(el) = document.get_element_by_id("perf-hud") {
+        _ = el.class_list().toggle_with_force("hidden", !show);
+    }
+}
+
+/// Refresh the performance HUD's figures
+pub fn update_perf_hud(document: &web::Document, stats: &PerfStats) {
+    if let Some(el) = document.get_element_by_id("perf-hud") {
+        el.set_text_content(Some(&stats.hud_text()));
+    }
+}
+
+/// Show shader compile errors over the canvas, or hide the panel when there
+/// are none
+pub fn update_shader_errors(document: &web::Document, text: &str) {
+    if let Some(el) = document.get_element_by_id("shader-errors") {
+        el.set_text_content(Some(text));
+        _ = el.class_list().toggle_with_force("hidden", text.is_empty());
+    }
+}
+
+/// Name the voice selected from the keyboard and where it sits (read out by
+/// screen readers), or hide the readout when none is
+pub fn update_voice_selection(document: &web::Document, label: Option<&str>) {
+    if let Some(el) = document.get_element_by_id("voice-selection") {
+        el.set_text_content(label);
+        _ = el.class_list().toggle_with_force("hidden", label.is_none());
+    }
+}
+
+/// Flash a short message (what a scroll just changed) that fades on its own
+pub fn show_toast(document: &web::Document, text: &str) {
+    if let Some(el) = document.get_element_by_id("toast") {
+        el.set_text_content(Some(text));
+        // Swapping between two copies of the fade restarts it
+        let list = el.class_list();
+        let again = list.contains("toast-a");
+        _ = list.toggle_with_force("toast-a", !again);
+        _ = list.toggle_with_force("toast-b", again);
+    }
+}
+
+/// Replace the signature glyph shown above the attract prompt
+pub fn update_attract_signature(document: &web::Document, svg: &str) {
+    if let Some(el) = document.get_element_by_id("attract-signature") {
+        el.set_inner_html(svg);
+    }
+}
+
+pub fn show_attract_prompt(document: &web::Document, show: bool) {
+    if let Some(el) = document.get_element_by_id("attract-prompt") {
+        _ = el.class_list().toggle_with_force("hidden", !show);
+    }
+}
+
+pub fn update_theme(document: &web::Document, theme: &Theme) {
+    crate::dom::set_select_value(document, "theme", theme.id);
+}
+
+pub fn update_visual_layers(document: &web::Document, layers: VisualLayers) {
+    crate::dom::set_checked(document, "layer-spectrum", layers.spectrum);
+    crate::dom::set_checked(document, "layer-scope", layers.scope);
+}
+
+/// List every rebindable action with its keys in the Keys panel; the action
+/// waiting for a key shows a prompt instead
+pub fn update_keymap(document: &web::Document, keymap: &Keymap, capturing: Option<Action>) {
+    let Some(list) = document.get_element_by_id("keymap") else {
+        return;
+    };
+    let rows: String = Action::ALL
+        .iter()
+        .map(|&action| {
+            let keys = if capturing == Some(action) {
+                "press a key…".to_string()
+            } else {
+                let labels: Vec<String> = keymap.chords(action).map(KeyChord::label).collect();
+                if labels.is_empty() {
+                    "unbound".to_string()
+                } else {
+                    labels.join(" / ")
+                }
+            };
+            format!(
+                "<li>{}: <button class=\"kbd\" data-action=\"{}\">{}</button></li>",
+                action.name(),
+                action.id(),
+                escape_html(&keys)
+            )
+        })
+        .collect();
+    list.set_inner_html(&rows);
+}
+
+/// List every parameter a controller can drive with the controller mapped
+/// to it, and the Learn button's state; while learning, the parameter picked
+/// waits for a knob and a knob moved waits for a parameter
+pub fn update_cc_map(document: &web::Document, map: &CcMap, learn: &MidiLearn, voices: usize) {
+    if let Some(button) = document.get_element_by_id("midi-learn") {
+        let label = match (learn.active, learn.source) {
+            (false, _) => "Learn".to_string(),
+            (true, None) => "Cancel".to_string(),
+            (true, Some((ch, cc))) => format!("CC {cc} ch {}: pick…", ch + 1),
+        };
+        button.set_text_content(Some(&label));
+    }
+    let Some(list) = document.get_element_by_id("cc-map") else {
+        return;
+    };
+    let rows: String = CcTarget::all(voices)
+        .into_iter()
+        .map(|target| {
+            let source = if learn.active && learn.target == Some(target) {
+                "move a control…".to_string()
+            } else {
+                match map.source(target) {
+                    Some((ch, cc)) => format!("CC {cc} ch {}", ch + 1),
+                    None => "unmapped".to_string(),
+                }
+            };
+            format!(
+                "<li>{}: <button class=\"kbd\" data-target=\"{}\">{}</button></li>",
+                target.label(),
+                target.id(),
+                source
+            )
+        })
+        .collect();
+    list.set_inner_html(&rows);
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn is_checked(document: &web::Document, id: &str) -> bool {
+    document
+        .get_element_by_id(id)
+        .and_then(|el| el.dyn_into::<web::HtmlInputElement>().ok())
+        .map(|input| input.checked())
+        .unwrap_or(false)
+}
+
+/// Read the dice lock checkboxes from the overlay panel
+pub fn read_dice_locks(document: &web::Document) -> ParamLocks {
+    ParamLocks {
+        bpm: is_checked(document, "lock-bpm"),
+        root: is_checked(document, "lock-root"),
+        scale: is_checked(document, "lock-scale"),
+        detune: is_checked(document, "lock-detune"),
+        sequences: is_checked(document, "lock-sequences"),
+    }
+}
@@ -1,15 +1,101 @@
-use glam::Vec3;
+use crate::constants::{SPREAD, VOICE_SPHERE_RADIUS, Z_OFFSET};
+use crate::core::antialias::{Antialias, MSAA_SAMPLES};
+use crate::core::bloom::Bloom;
+use crate::core::custom_shader::with_custom;
+use crate::core::displays::DisplaySlice;
+use crate::core::glyphs::{GlyphAtlas, GlyphQuad};
+use crate::core::lens::{DofQuality, Lens, DOF_FOCUS_RANGE, MAX_DOF_BLUR};
+use crate::core::musical_clock::ClockPulse;
+use crate::core::particles::{
+    Particle, ParticleEmitter, PARTICLE_DRAG_PER_SEC, PARTICLE_GRAVITY, PARTICLE_MAX_LIFE_SEC,
+};
+use crate::core::profiler::GpuTimings;
+use crate::core::render_scale::{MAX_RENDER_SCALE, MIN_RENDER_SCALE};
+use crate::core::ripple::Ripples;
+use crate::core::screenshot::Screenshot;
+use crate::core::shader_reload::{Shader, ShaderSources};
+use crate::core::spectrum::{VisualLayers, SPECTRUM_BANDS};
+use crate::core::starfield::star_shift;
+use crate::core::strum::Streak;
+use crate::core::theme::{Theme, NEBULA};
+use crate::core::tonemap::{Exposure, Tonemap};
+use crate::core::visualizer::{Crossfade, Visualizer};
+use crate::core::voice_style::VoiceStyle;
+use glam::{Mat4, Vec3};
+use helpers::rgba;
 use web_sys as web;
 
+mod capture;
+mod exposure;
+mod fxaa;
 mod helpers;
+mod lens;
+mod particles;
 mod post;
+mod scene;
 mod targets;
+mod text;
+mod timing;
 mod waves;
 use targets::RenderTargets;
 
 pub use crate::camera::screen_to_world_ray;
 
-use waves::{create_waves_resources, VoicePacked, WavesResources, WavesUniforms};
+use capture::ScreenCapture;
+use exposure::{create_exposure_resources, ExposureResources};
+use fxaa::{create_fxaa_resources, FxaaResources};
+use lens::{create_lens_resources, LensResources, LensUniforms};
+use particles::{create_particle_resources, ParticleResources, ParticleUniforms};
+use scene::{create_scene_resources, SceneResources, SceneUniforms};
+use text::{create_text_resources, TextResources, TextUniforms};
+use timing::{GpuTimer, SPAN_BLOOM, SPAN_COMPOSITE, SPAN_SCENE};
+use waves::{
+    create_waves_resources, VoicePacked, WavesResources, WavesUniforms, AUDIO_BINS_LEN, TRAIL_SLOTS,
+};
+
+/// Which layers are drawn into the HDR target before post-processing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// Fullscreen audio-reactive waves only
+    #[default]
+    Waves,
+    /// Instanced 3D voice billboards over the plain background
+    Scene,
+    /// Voice billboards composited over the waves
+    Hybrid,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 3] = [RenderMode::Waves, RenderMode::Scene, RenderMode::Hybrid];
+
+    /// Short identifier used in URLs
+    pub fn id(self) -> &'static str {
+        match self {
+            RenderMode::Waves => "waves",
+            RenderMode::Scene => "scene",
+            RenderMode::Hybrid => "hybrid",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|m| m.id().eq_ignore_ascii_case(id))
+    }
+
+    pub fn next(self) -> Self {
+        let idx = Self::ALL.iter().position(|m| *m == self).unwrap_or(0);
+        Self::ALL[(idx + 1) % Self::ALL.len()]
+    }
+
+    fn draws_waves(self) -> bool {
+        matches!(self, RenderMode::Waves | RenderMode::Hybrid)
+    }
+
+    fn draws_scene(self) -> bool {
+        matches!(self, RenderMode::Scene | RenderMode::Hybrid)
+    }
+}
 
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -20,6 +106,18 @@ pub(crate) struct PostUniforms {
     blur_dir: [f32; 2],
     bloom_strength: f32,
     threshold: f32,
+    bloom_tint: [f32; 4],
+    fade: f32,
+    exposure: f32,
+    auto_exposure: f32,
+    tonemap: u32,
+    clock: [f32; 4],
+    grit: f32,
+    mirror_segments: u32,
+    mirror_center: [f32; 2],
+    safe_peak: f32,
+    flash_limit: f32,
+    _pad: [f32; 2],
 }
 
 pub struct GpuState<'a> {
@@ -27,8 +125,11 @@ pub struct GpuState<'a> {
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
-    // Waves full-screen layer
-    waves: WavesResources,
+    // Layers drawn into HDR; pipelines are built the first time a mode needs them
+    render_mode: RenderMode,
+    hdr_format: wgpu::TextureFormat,
+    waves: Option<WavesResources>,
+    scene: Option<SceneResources>,
     // Post-processing resources
     targets: RenderTargets,
     linear_sampler: wgpu::Sampler,
@@ -50,15 +151,86 @@ pub struct GpuState<'a> {
     clear_color: wgpu::Color,
     cam_eye: Vec3,
     cam_target: Vec3,
+    // This window's tile of a spanned camera, if it is a display window
+    display_slice: Option<DisplaySlice>,
     time_accum: f32,
     ambient_energy: f32,
     swirl_uv: [f32; 2],
     swirl_strength: f32,
     swirl_active: f32,
-    // Click/tap ripple state
-    ripple_uv: [f32; 2],
-    ripple_t0: f32,
-    ripple_amp: f32,
+    // Click/tap ripples, several at once
+    ripples: Ripples,
+    // Where recent strums swept
+    streak: Streak,
+    // Beat and bar pulse from the engine's grid
+    clock: ClockPulse,
+    // Bloom strength, threshold and blur iterations
+    bloom: Bloom,
+    // Exposure and tonemap in the composite; the auto-exposure pass only
+    // runs while it is switched on
+    exposure: Exposure,
+    tonemap: Tonemap,
+    // Depth of field and vignette after the composite; the pass and its
+    // target are created the first time either is switched on
+    lens: Lens,
+    lens_res: Option<LensResources>,
+    // Anti-aliasing as it runs on this device (MSAA falls back to FXAA where
+    // the HDR target can't be multisampled); the FXAA pass and its target
+    // are created the first time it is switched on
+    antialias: Antialias,
+    msaa_supported: bool,
+    fxaa: Option<FxaaResources>,
+    // Saturator drive mirrored in the composite (chromatic aberration, grain)
+    grit: f32,
+    // Kaleidoscope segments the composite folds into around the swirl (0 = off)
+    mirror_segments: u32,
+    // Starfield brightness behind the waves (0 = off)
+    stars: f32,
+    // Peak brightness cap and flash limiter in the post chain
+    safe_visuals: bool,
+    // Fraction of the canvas resolution the HDR/bloom chain renders at; the
+    // composite upsamples it
+    render_scale: f32,
+    // Pass timings for the performance HUD: the timer is created the first
+    // time profiling is switched on, if the device has timestamp queries
+    timestamps_supported: bool,
+    profiling: bool,
+    timer: Option<GpuTimer>,
+    // Status text drawn over the finished frame; the atlas and pipeline are
+    // created the first time there is any, and the quads are laid out again
+    // when the text or the canvas size changes
+    text: Option<TextResources>,
+    overlay_text: String,
+    text_quads: Vec<GlyphQuad>,
+    text_laid_out_for: Option<(u32, u32)>,
+    // Screenshot asked for (at this multiple of the canvas size), drawn after
+    // the next frame, and the one being read back
+    screenshot_scale: Option<u32>,
+    capture: Option<ScreenCapture>,
+    auto_exposure: ExposureResources,
+    // Analyser layers and their latest data (bars, then scope samples)
+    layers: VisualLayers,
+    audio_bins: [f32; AUDIO_BINS_LEN],
+    // Colours for every layer and the clear
+    theme: &'static Theme,
+    // Voice motion trails (see `VoiceTrail::pack`)
+    trails: [[f32; 4]; TRAIL_SLOTS],
+    // Each voice's packed `VoiceStyle`, read by the waves and scene shaders
+    voice_styles: [[f32; 4]; 3],
+    // Voice picked from the keyboard, ringed in the waves and scene
+    selected_voice: Option<usize>,
+    // Voices pinned against drags, marked with ticks
+    locked_voices: [bool; 3],
+    // Fullscreen visualizer, and the one fading out after a switch
+    visualizer: Crossfade,
+    // Note-burst particles: created with the first burst, and only stepped
+    // and drawn until the last one has faded
+    particles: Option<ParticleResources>,
+    emitter: ParticleEmitter,
+    burst: Vec<Particle>,
+    particles_live_until: f32,
+    // Shaders loaded by shader reloading in place of the built-in ones
+    shaders: ShaderSources,
 }
 
 impl<'a> GpuState<'a> {
@@ -84,10 +256,16 @@ impl<'a> GpuState<'a> {
                      - Graphics drivers not compatible with WebGPU"
                 )
             })?;
+        // Timestamp queries, when offered, time the passes for the performance HUD
+        let timestamps_supported = adapter.features().contains(wgpu::Features::TIMESTAMP_QUERY);
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
+                    required_features: if timestamps_supported {
+                        wgpu::Features::TIMESTAMP_QUERY
+                    } else {
+                        wgpu::Features::empty()
+                    },
                     // Use default limits on web to avoid passing unknown fields to older WebGPU impls
                     required_limits: wgpu::Limits::default(),
                     memory_hints: wgpu::MemoryHints::Performance,
@@ -130,38 +308,17 @@ impl<'a> GpuState<'a> {
         };
         surface.configure(&device, &config);
 
-        // Offscreen HDR targets (scene and bloom) at full and half resolution
+        // Offscreen HDR targets (scene, fade and bloom) at full and half
+        // resolution until a render scale is set
         let hdr_format = wgpu::TextureFormat::Rgba16Float;
-        let (hdr_tex, hdr_view) = helpers::create_color_texture_device(
-            &device,
-            "hdr_tex",
-            width,
-            height,
-            hdr_format,
-            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-        );
-        let bloom_w = (width.max(1) / 2).max(1);
-        let bloom_h = (height.max(1) / 2).max(1);
         let bloom_format = wgpu::TextureFormat::Rgba16Float;
-        let (bloom_a, bloom_a_view) = helpers::create_color_texture_device(
-            &device,
-            "bloom_a",
-            bloom_w,
-            bloom_h,
-            bloom_format,
-            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-        );
-        let (bloom_b, bloom_b_view) = helpers::create_color_texture_device(
-            &device,
-            "bloom_b",
-            bloom_w,
-            bloom_h,
-            bloom_format,
-            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-        );
-
-        // Waves fullscreen pass (drawn into HDR before bloom)
-        let waves = create_waves_resources(&device, hdr_format);
+        let targets = RenderTargets::new(&device, width, height, 1);
+        let msaa_supported = [hdr_format, helpers::DEPTH_FORMAT].iter().all(|f| {
+            adapter
+                .get_texture_format_features(*f)
+                .flags
+                .sample_count_supported(MSAA_SAMPLES)
+        });
 
         // Post shader + pipelines
         let post_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -179,88 +336,23 @@ impl<'a> GpuState<'a> {
             ..Default::default()
         });
         let post = post::create_post_resources(&device, &post_shader, bloom_format, format);
-        let bg_hdr = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("bg_hdr"),
-            layout: &post.bgl0,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&hdr_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&linear_sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: post.uniform_buffer.as_entire_binding(),
-                },
-            ],
-        });
-        let bg_from_bloom_a = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("bg_from_bloom_a"),
-            layout: &post.bgl0,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&bloom_a_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&linear_sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: post.uniform_buffer.as_entire_binding(),
-                },
-            ],
-        });
-        let bg_from_bloom_b = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("bg_from_bloom_b"),
-            layout: &post.bgl0,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&bloom_b_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&linear_sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: post.uniform_buffer.as_entire_binding(),
-                },
-            ],
-        });
-        let bg_bloom_a_only = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("bg_bloom_a_only"),
-            layout: &post.bgl1,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&bloom_a_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&linear_sampler),
-                },
-            ],
-        });
-        let bg_bloom_b_only = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("bg_bloom_b_only"),
-            layout: &post.bgl1,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&bloom_b_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&linear_sampler),
-                },
-            ],
-        });
+        let auto_exposure = create_exposure_resources(
+            &device,
+            &queue,
+            &targets.hdr_view,
+            crate::core::EXPOSURE_WGSL,
+        );
+        let (bg_hdr, bg_from_bloom_a, bg_from_bloom_b, bg_bloom_a_only, bg_bloom_b_only) =
+            post::rebuild_bind_groups(
+                &device,
+                &post,
+                &linear_sampler,
+                &targets.hdr_view,
+                &targets.fade_view,
+                &targets.bloom_a_view,
+                &targets.bloom_b_view,
+                &auto_exposure.state_buffer,
+            );
 
         let bright_pipeline = post.bright_pipeline.clone();
         let blur_pipeline = post.blur_pipeline.clone();
@@ -271,15 +363,11 @@ impl<'a> GpuState<'a> {
             device,
             queue,
             config,
-            waves,
-            targets: RenderTargets::new(
-                hdr_tex,
-                hdr_view,
-                bloom_a,
-                bloom_a_view,
-                bloom_b,
-                bloom_b_view,
-            ),
+            render_mode: RenderMode::default(),
+            hdr_format,
+            waves: None,
+            scene: None,
+            targets,
             linear_sampler,
             post,
             bg_hdr,
@@ -292,42 +380,100 @@ impl<'a> GpuState<'a> {
             composite_pipeline,
             width,
             height,
-            clear_color: wgpu::Color {
-                r: 0.03,
-                g: 0.04,
-                b: 0.08,
-                a: 1.0,
-            },
+            clear_color: clear_color(&NEBULA, 0.0),
             cam_eye: Vec3::new(0.0, 0.0, camera_z),
             cam_target: Vec3::ZERO,
+            display_slice: None,
             time_accum: 0.0,
             ambient_energy: 0.0,
             swirl_uv: [0.5, 0.5],
             swirl_strength: 0.0,
             swirl_active: 0.0,
-            ripple_uv: [0.5, 0.5],
-            ripple_t0: -1.0,
-            ripple_amp: 0.0,
+            ripples: Ripples::default(),
+            streak: Streak::default(),
+            clock: ClockPulse::default(),
+            bloom: Bloom::default(),
+            exposure: Exposure::default(),
+            tonemap: Tonemap::default(),
+            lens: Lens::default(),
+            lens_res: None,
+            antialias: Antialias::Off,
+            msaa_supported,
+            fxaa: None,
+            grit: 0.0,
+            mirror_segments: 0,
+            stars: 0.0,
+            safe_visuals: false,
+            render_scale: MAX_RENDER_SCALE,
+            timestamps_supported,
+            profiling: false,
+            timer: None,
+            text: None,
+            overlay_text: String::new(),
+            text_quads: Vec::new(),
+            text_laid_out_for: None,
+            screenshot_scale: None,
+            capture: None,
+            auto_exposure,
+            layers: VisualLayers::default(),
+            audio_bins: [0.0; AUDIO_BINS_LEN],
+            theme: &NEBULA,
+            trails: [[0.0; 4]; TRAIL_SLOTS],
+            voice_styles: [VoiceStyle::DEFAULT.pack(); 3],
+            selected_voice: None,
+            locked_voices: [false; 3],
+            visualizer: Crossfade::new(Visualizer::default()),
+            particles: None,
+            emitter: ParticleEmitter::new(rand::random()),
+            burst: Vec::new(),
+            particles_live_until: 0.0,
+            shaders: ShaderSources::default(),
         })
     }
     pub fn set_ambient_clear(&mut self, energy01: f32) {
-        // Subtle brighten and slight hue shift with ambient energy
         let e = energy01.clamp(0.0, 1.0);
-        let boost = 0.06 * e; // up to +0.06
-        self.clear_color = wgpu::Color {
-            r: (0.03 + boost * 0.8) as f64,
-            g: (0.04 + boost * 0.9) as f64,
-            b: (0.08 + boost * 0.5) as f64,
-            a: 1.0,
-        };
+        self.clear_color = clear_color(self.theme, e);
         self.ambient_energy = e;
     }
 
+    /// Recolour every layer; takes effect on the next frame.
+    pub fn set_theme(&mut self, theme: &'static Theme) {
+        if !std::ptr::eq(theme, self.theme) {
+            log::info!("[render] theme={}", theme.id);
+            self.theme = theme;
+            self.clear_color = clear_color(theme, self.ambient_energy);
+        }
+    }
+
+    /// Crossfade to another fullscreen visualizer; its pipeline is created on
+    /// the next rendered frame.
+    pub fn set_visualizer(&mut self, visualizer: Visualizer) {
+        if visualizer != self.visualizer.current {
+            log::info!("[render] visualizer={}", visualizer.id());
+            self.visualizer.switch(visualizer);
+        }
+    }
+
+    /// Switch the visible layers; pipelines for a newly needed layer are created
+    /// on the next rendered frame.
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        if mode != self.render_mode {
+            log::info!("[render] mode={}", mode.id());
+            self.render_mode = mode;
+        }
+    }
+
     pub fn set_camera(&mut self, eye: Vec3, target: Vec3) {
         self.cam_eye = eye;
         self.cam_target = target;
     }
 
+    /// Draw only this window's part of a camera spanned across several
+    /// (`None` for the whole view).
+    pub fn set_display_slice(&mut self, slice: Option<DisplaySlice>) {
+        self.display_slice = slice;
+    }
+
     pub fn set_swirl(&mut self, uv: [f32; 2], strength: f32, active: bool) {
         self.swirl_uv = uv;
         self.swirl_strength = strength;
@@ -335,10 +481,328 @@ impl<'a> GpuState<'a> {
     }
 
     pub fn set_ripple(&mut self, uv: [f32; 2], amp: f32) {
-        self.ripple_uv = uv;
-        self.ripple_amp = amp.clamp(0.0, 1.5);
         // Anchor ripple start to current accumulated time so shader can compute age
-        self.ripple_t0 = self.time_accum;
+        self.ripples.push(uv, amp, self.time_accum);
+    }
+
+    /// Extend the strum streak to `uv`.
+    pub fn add_streak_point(&mut self, uv: [f32; 2]) {
+        self.streak.push(uv, self.time_accum);
+    }
+
+    /// Where the music is in its beat and bar, for the background pulse,
+    /// camera breathing and bloom swell.
+    pub fn set_clock(&mut self, clock: ClockPulse) {
+        self.clock = clock;
+    }
+
+    pub fn set_bloom(&mut self, bloom: Bloom) {
+        self.bloom = bloom;
+    }
+
+    pub fn set_exposure(&mut self, exposure: Exposure, tonemap: Tonemap) {
+        self.exposure = exposure;
+        self.tonemap = tonemap;
+    }
+
+    /// How hard the saturator is driven (`FxLevels::grit`), for the
+    /// composite's chromatic aberration and grain.
+    pub fn set_grit(&mut self, grit: f32) {
+        self.grit = grit.clamp(0.0, 1.0);
+    }
+
+    /// Fold the composite `segments` ways around the swirl centre (0 = off).
+    pub fn set_mirror(&mut self, segments: u32) {
+        self.mirror_segments = segments;
+    }
+
+    /// Cap the peak brightness and hold back flashes, for photosensitive
+    /// viewers; the limiter settles afresh each time it is switched on.
+    pub fn set_safe_visuals(&mut self, on: bool) {
+        if on != self.safe_visuals {
+            log::info!("[render] safe visuals={on}");
+            self.safe_visuals = on;
+            if on {
+                self.auto_exposure.reset_flash_limit(&self.queue);
+            }
+        }
+    }
+
+    pub fn set_stars(&mut self, stars: f32) {
+        self.stars = stars.clamp(0.0, 1.0);
+    }
+
+    /// Smooth edges with `antialias`, or its fallback on this device. The
+    /// layers' pipelines are rebuilt for a new sample count on the next frame.
+    pub fn set_antialias(&mut self, antialias: Antialias) {
+        let antialias = antialias.supported(self.msaa_supported);
+        if antialias == self.antialias {
+            return;
+        }
+        log::info!("[render] antialias={}", antialias.id());
+        let samples_changed = antialias.samples() != self.antialias.samples();
+        self.antialias = antialias;
+        if samples_changed {
+            self.waves = None;
+            self.scene = None;
+            // Dropping the pool clears any sparks still alive in it
+            self.particles = None;
+            self.lens_res = None;
+            self.recreate_targets_if_needed();
+        }
+    }
+
+    pub fn set_lens(&mut self, lens: Lens) {
+        self.lens = lens;
+    }
+
+    /// Render the HDR/bloom chain at `scale` of the canvas resolution; the
+    /// targets are rebuilt when that changes their size.
+    pub fn set_render_scale(&mut self, scale: f32) {
+        let scale = scale.clamp(MIN_RENDER_SCALE, MAX_RENDER_SCALE);
+        if scale != self.render_scale {
+            log::info!("[render] scale={scale}");
+            self.render_scale = scale;
+            self.recreate_targets_if_needed();
+        }
+    }
+
+    /// The device, for compiling reloaded shaders before they are used.
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    /// The source `shader`'s pipelines are built from now.
+    pub fn shader(&self, shader: Shader) -> &str {
+        shader_source(&self.shaders, shader)
+    }
+
+    /// Rebuild the pipelines `shader` feeds from `source`. Layers not yet
+    /// created pick it up when they are; the future resolves to any
+    /// validation error from the rebuild (a pipeline that no longer matches
+    /// its bindings, say).
+    pub fn reload_shader(
+        &mut self,
+        shader: Shader,
+        source: String,
+    ) -> impl std::future::Future<Output = Option<wgpu::Error>> {
+        log::info!("[render] reloading {}", shader.file());
+        self.shaders.set(shader, source);
+        let source = shader_source(&self.shaders, shader);
+        let samples = self.antialias.samples();
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        match shader {
+            Shader::Post => {
+                let module = self
+                    .device
+                    .create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("post_shader"),
+                        source: wgpu::ShaderSource::Wgsl(source.into()),
+                    });
+                // The bloom targets share the HDR format
+                self.post = post::create_post_resources(
+                    &self.device,
+                    &module,
+                    self.hdr_format,
+                    self.config.format,
+                );
+                self.bright_pipeline = self.post.bright_pipeline.clone();
+                self.blur_pipeline = self.post.blur_pipeline.clone();
+                self.composite_pipeline = self.post.composite_pipeline.clone();
+                self.rebuild_post_bind_groups();
+            }
+            Shader::Exposure => {
+                self.auto_exposure = create_exposure_resources(
+                    &self.device,
+                    &self.queue,
+                    &self.targets.hdr_view,
+                    source,
+                );
+                self.rebuild_post_bind_groups();
+            }
+            Shader::Waves | Shader::Custom => {
+                if let Some(waves) = &mut self.waves {
+                    let source = waves_source(&self.shaders);
+                    *waves =
+                        create_waves_resources(&self.device, self.hdr_format, samples, &source);
+                    waves.ensure_pipeline(&self.device, self.visualizer.current);
+                }
+            }
+            Shader::Scene => {
+                if let Some(scene) = &mut self.scene {
+                    *scene = create_scene_resources(&self.device, self.hdr_format, samples, source);
+                }
+            }
+            Shader::Particles => {
+                // Sparks still in flight are dropped with the old pool
+                if let Some(particles) = &mut self.particles {
+                    *particles =
+                        create_particle_resources(&self.device, self.hdr_format, samples, source);
+                }
+            }
+            Shader::Text => {
+                if let Some(text) = &mut self.text {
+                    *text = create_text_resources(
+                        &self.device,
+                        &self.queue,
+                        self.config.format,
+                        source,
+                    );
+                    self.text_laid_out_for = None;
+                }
+            }
+            Shader::Lens => {
+                if let Some(lens) = &mut self.lens_res {
+                    *lens = create_lens_resources(
+                        &self.device,
+                        self.config.format,
+                        self.targets.samples,
+                        source,
+                    );
+                }
+            }
+            Shader::Fxaa => {
+                if let Some(fxaa) = &mut self.fxaa {
+                    *fxaa = create_fxaa_resources(&self.device, self.config.format, source);
+                }
+            }
+        }
+        self.device.pop_error_scope()
+    }
+
+    /// Time the render passes on the GPU (where supported) while the
+    /// performance HUD is showing.
+    pub fn set_profiling(&mut self, on: bool) {
+        self.profiling = on;
+        if on && self.timestamps_supported && self.timer.is_none() {
+            self.timer = Some(GpuTimer::new(&self.device, &self.queue));
+        }
+    }
+
+    /// Latest scene, bloom and composite GPU times; `None` without
+    /// timestamp queries.
+    pub fn gpu_timings(&self) -> Option<GpuTimings> {
+        self.timer.as_ref().and_then(GpuTimer::latest)
+    }
+
+    /// Text drawn in a panel at the bottom left of the canvas, over the
+    /// finished frame; empty draws nothing.
+    pub fn set_overlay_text(&mut self, text: &str) {
+        if text != self.overlay_text {
+            self.overlay_text = text.to_string();
+            self.text_laid_out_for = None;
+        }
+    }
+
+    /// Draw the next frame again at `scale` times the canvas size (and the
+    /// full render scale) for a screenshot; ignored while one is pending.
+    pub fn request_screenshot(&mut self, scale: u32) {
+        if self.screenshot_scale.is_none() && self.capture.is_none() {
+            self.screenshot_scale = Some(scale.max(1));
+        }
+    }
+
+    /// The screenshot asked for, once it has been read back.
+    pub fn take_screenshot(&mut self) -> Option<Screenshot> {
+        let shot = self.capture.as_ref()?.poll()?;
+        self.capture = None;
+        if shot.is_none() {
+            log::warn!("[screenshot] readback failed");
+        }
+        shot
+    }
+
+    /// This frame's voice motion trails, packed `TRAIL_POINTS` per voice.
+    pub fn set_trails(&mut self, trails: &[[f32; 4]]) {
+        for (dst, src) in self.trails.iter_mut().zip(trails) {
+            *dst = *src;
+        }
+    }
+
+    /// How each voice is drawn, from its `VoiceConfig::style`.
+    pub fn set_voice_styles(&mut self, styles: &[VoiceStyle]) {
+        for (dst, src) in self.voice_styles.iter_mut().zip(styles) {
+            *dst = src.pack();
+        }
+    }
+
+    /// The voice to ring as selected, if any.
+    pub fn set_selected_voice(&mut self, voice: Option<usize>) {
+        self.selected_voice = voice;
+    }
+
+    /// Which voices are locked in place.
+    pub fn set_locked_voices(&mut self, locked: &[bool]) {
+        for (dst, src) in self.locked_voices.iter_mut().zip(locked) {
+            *dst = *src;
+        }
+    }
+
+    // Theme colour per voice, with marks in w: +1 selected, +2 locked
+    fn voice_colors(&self) -> [[f32; 4]; 3] {
+        std::array::from_fn(|i| {
+            let selected = if self.selected_voice == Some(i) {
+                1.0
+            } else {
+                0.0
+            };
+            let locked = if self.locked_voices[i] { 2.0 } else { 0.0 };
+            rgba(self.theme.voices[i], selected + locked)
+        })
+    }
+
+    /// Start the visual clock afresh, with no ripples, streaks, fades or
+    /// particles in flight and note bursts drawn from `seed`, so a video
+    /// export of a seed draws the same frames every time.
+    pub fn restart_visuals(&mut self, seed: u64) {
+        self.time_accum = 0.0;
+        self.ripples = Ripples::default();
+        self.streak = Streak::default();
+        self.visualizer = Crossfade::new(self.visualizer.current);
+        // Dropping the pool clears any sparks still alive in it
+        self.particles = None;
+        self.emitter = ParticleEmitter::new(seed);
+        self.burst.clear();
+        self.particles_live_until = 0.0;
+    }
+
+    /// Burst of particles for a note now audible from voice `voice_index` at
+    /// engine-space `position`, coloured from the theme's voice colour.
+    pub fn note_burst(
+        &mut self,
+        voice_index: usize,
+        position: Vec3,
+        frequency_hz: f32,
+        velocity: f32,
+    ) {
+        let resources = self.particles.get_or_insert_with(|| {
+            create_particle_resources(
+                &self.device,
+                self.hdr_format,
+                self.antialias.samples(),
+                shader_source(&self.shaders, Shader::Particles),
+            )
+        });
+        let origin = (position * SPREAD + Z_OFFSET).to_array();
+        let color = self.theme.voices[voice_index % 3];
+        let first = self
+            .emitter
+            .burst(origin, frequency_hz, velocity, color, &mut self.burst);
+        resources.upload(&self.queue, first, &self.burst);
+        self.particles_live_until = self.time_accum + PARTICLE_MAX_LIFE_SEC;
+    }
+
+    /// Show or hide the analyser layers, with this frame's spectrum bars
+    /// (`SPECTRUM_BANDS` levels) and scope samples (`SCOPE_POINTS`).
+    pub fn set_audio_layers(&mut self, layers: VisualLayers, bars: &[f32], scope: &[f32]) {
+        self.layers = layers;
+        let (bar_bins, scope_bins) = self.audio_bins.split_at_mut(SPECTRUM_BANDS);
+        for (dst, src) in bar_bins.iter_mut().zip(bars) {
+            *dst = *src;
+        }
+        for (dst, src) in scope_bins.iter_mut().zip(scope) {
+            *dst = *src;
+        }
     }
 
     pub fn resize_if_needed(&mut self, width: u32, height: u32) {
@@ -351,12 +815,37 @@ impl<'a> GpuState<'a> {
             self.config.width = width;
             self.config.height = height;
             self.surface.configure(&self.device, &self.config);
+            self.recreate_targets_if_needed();
+        }
+    }
 
-            // Recreate offscreen render targets and dependent bind groups
-            self.targets.recreate(&self.device, width, height);
+    /// Size of the HDR and fade targets: the canvas at the render scale.
+    fn target_size(&self) -> (u32, u32) {
+        let scaled = |px: u32| ((px as f32 * self.render_scale).round() as u32).max(1);
+        (scaled(self.width), scaled(self.height))
+    }
 
-            // Rebuild bind groups that reference these views
-            self.rebuild_post_bind_groups();
+    fn recreate_targets_if_needed(&mut self) {
+        let (width, height) = self.target_size();
+        let samples = self.antialias.samples();
+        if (width, height, samples)
+            == (
+                self.targets.width,
+                self.targets.height,
+                self.targets.samples,
+            )
+        {
+            return;
+        }
+        // Recreate offscreen render targets and dependent bind groups
+        self.targets.recreate(&self.device, width, height, samples);
+
+        // Rebuild bind groups that reference these views
+        self.rebuild_post_bind_groups();
+        self.auto_exposure
+            .rebind(&self.device, &self.targets.hdr_view);
+        if let Some(lens) = &mut self.lens_res {
+            lens.invalidate();
         }
     }
 
@@ -372,82 +861,165 @@ impl<'a> GpuState<'a> {
         let view = frame
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
+        let encoder = self.encode_frame(&view, dt_sec, voice_positions, pulse_energy);
+        self.queue.submit(Some(encoder.finish()));
+        if let Some(timer) = &mut self.timer {
+            timer.after_submit();
+        }
+        frame.present();
+        if let Some(scale) = self.screenshot_scale.take() {
+            self.capture_frame(scale, voice_positions, pulse_energy);
+        }
+        Ok(())
+    }
+
+    /// Draw the same moment again, at `scale` times the canvas size, into a
+    /// texture read back for `take_screenshot`.
+    fn capture_frame(&mut self, scale: u32, voice_positions: &[Vec3], pulse_energy: &[f32]) {
+        let max_dim = self.device.limits().max_texture_dimension_2d;
+        let scale = scale.min(max_dim / self.width.max(self.height)).max(1);
+        let (width, height) = (self.width * scale, self.height * scale);
+        let Some(capture) = ScreenCapture::new(&self.device, width, height, self.config.format)
+        else {
+            log::warn!("[screenshot] unsupported format {:?}", self.config.format);
+            return;
+        };
+        let restore = (self.width, self.height, self.render_scale);
+        self.width = width;
+        self.height = height;
+        self.render_scale = MAX_RENDER_SCALE;
+        self.recreate_targets_if_needed();
+        // Untimed, and without stepping the particles or any fades
+        let timer = self.timer.take();
+        let mut encoder = self.encode_frame(&capture.view, 0.0, voice_positions, pulse_energy);
+        capture.copy(&mut encoder);
+        self.queue.submit(Some(encoder.finish()));
+        capture.after_submit();
+        self.timer = timer;
+        (self.width, self.height, self.render_scale) = restore;
+        self.recreate_targets_if_needed();
+        log::info!("[screenshot] {width}x{height}");
+        self.capture = Some(capture);
+    }
+
+    /// Record the whole frame (layers, bloom, composite and text) into `view`.
+    fn encode_frame(
+        &mut self,
+        view: &wgpu::TextureView,
+        dt_sec: f32,
+        voice_positions: &[Vec3],
+        pulse_energy: &[f32],
+    ) -> wgpu::CommandEncoder {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("encoder"),
             });
+        if self.render_mode.draws_waves() && self.waves.is_none() {
+            self.waves = Some(create_waves_resources(
+                &self.device,
+                self.hdr_format,
+                self.antialias.samples(),
+                &waves_source(&self.shaders),
+            ));
+        }
+        if self.render_mode.draws_scene() && self.scene.is_none() {
+            self.scene = Some(create_scene_resources(
+                &self.device,
+                self.hdr_format,
+                self.antialias.samples(),
+                shader_source(&self.shaders, Shader::Scene),
+            ));
+        }
+        self.visualizer.advance(dt_sec);
+        let outgoing = self
+            .visualizer
+            .previous
+            .filter(|_| self.render_mode.draws_waves());
+        if let (true, Some(waves)) = (self.render_mode.draws_waves(), &mut self.waves) {
+            waves.ensure_pipeline(&self.device, self.visualizer.current);
+            if let Some(previous) = outgoing {
+                waves.ensure_pipeline(&self.device, previous);
+            }
+        }
+        self.write_layer_uniforms(dt_sec.max(0.0), voice_positions, pulse_energy);
+        if let Some(timer) = &mut self.timer {
+            timer.begin_frame(self.profiling);
+        }
+        if let (true, Some(particles)) = (self.particles_live(), &self.particles) {
+            if dt_sec > 0.0 {
+                particles.step(&mut encoder);
+            }
+        }
+
+        // The visualizer being switched away from (with the billboards, so they
+        // hold steady) goes to its own target for the composite to fade out
+        if let Some(previous) = outgoing {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("fade_pass"),
+                color_attachments: &[Some(
+                    self.targets
+                        .layer_attachment(&self.targets.fade_view, self.clear_color),
+                )],
+                depth_stencil_attachment: Some(self.depth_attachment()),
+                timestamp_writes: self.timestamps(SPAN_SCENE, true, false),
+                occlusion_query_set: None,
+            });
+            self.draw_layers(&mut rpass, previous);
+        }
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("scene_pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.targets.hdr_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.clear_color),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
+                color_attachments: &[Some(
+                    self.targets
+                        .layer_attachment(&self.targets.hdr_view, self.clear_color),
+                )],
+                depth_stencil_attachment: Some(self.depth_attachment()),
+                timestamp_writes: self.timestamps(SPAN_SCENE, outgoing.is_none(), true),
                 occlusion_query_set: None,
             });
-            let w = WavesUniforms {
-                resolution: [self.width as f32, self.height as f32],
-                time: self.time_accum,
-                ambient: self.ambient_energy,
-                voices: [
-                    VoicePacked {
-                        pos_pulse: [
-                            voice_positions[0].x,
-                            voice_positions[0].y,
-                            voice_positions[0].z,
-                            pulse_energy[0],
-                        ],
-                    },
-                    VoicePacked {
-                        pos_pulse: [
-                            voice_positions[1].x,
-                            voice_positions[1].y,
-                            voice_positions[1].z,
-                            pulse_energy[1],
-                        ],
-                    },
-                    VoicePacked {
-                        pos_pulse: [
-                            voice_positions[2].x,
-                            voice_positions[2].y,
-                            voice_positions[2].z,
-                            pulse_energy[2],
-                        ],
-                    },
-                ],
-                swirl_uv: [
-                    self.swirl_uv[0].clamp(0.0, 1.0),
-                    self.swirl_uv[1].clamp(0.0, 1.0),
-                ],
-                swirl_strength: if self.swirl_active > 0.5 { 1.4 } else { 0.0 },
-                swirl_active: self.swirl_active,
-                ripple_uv: self.ripple_uv,
-                ripple_t0: self.ripple_t0,
-                ripple_amp: self.ripple_amp,
-            };
-            self.queue
-                .write_buffer(&self.waves.uniform_buffer, 0, bytemuck::bytes_of(&w));
-            rpass.set_pipeline(&self.waves.pipeline);
-            rpass.set_bind_group(0, &self.waves.bind_group, &[]);
-            rpass.draw(0..3, 0..1);
+            self.draw_layers(&mut rpass, self.visualizer.current);
         }
-
-        let res = [self.width as f32 / 2.0, self.height as f32 / 2.0];
-        post::write_post_uniforms(
-            &self.queue,
-            &self.post.uniform_buffer,
+        if self.exposure.auto || self.safe_visuals {
+            self.auto_exposure.adapt(
+                &self.queue,
+                &mut encoder,
+                dt_sec.max(0.0),
+                self.exposure.auto,
+                self.safe_visuals,
+            );
+        }
+        let fade = if outgoing.is_some() {
+            self.visualizer.outgoing_weight()
+        } else {
+            0.0
+        };
+        // Bloom runs at half the HDR target's size
+        let res = [
+            self.targets.width as f32 / 2.0,
+            self.targets.height as f32 / 2.0,
+        ];
+        let mut uniforms = post::post_uniforms(
             res,
             self.time_accum,
             self.ambient_energy,
-            [0.0, 0.0],
+            self.theme,
+            &self.bloom,
+            &self.exposure,
+            self.tonemap,
+            fade,
+            self.clock.pack(),
+            self.grit,
+            self.mirror_segments,
+            // Centred while exporting, when there is no pointer to follow
+            if self.swirl_active > 0.5 {
+                self.swirl_uv
+            } else {
+                [0.5, 0.5]
+            },
+            self.safe_visuals,
         );
+        post::write_post_slot(&self.queue, &self.post, 0, &uniforms);
 
         // Pass 2: bright pass → bloom_a
         post::blit(
@@ -457,81 +1029,424 @@ impl<'a> GpuState<'a> {
             wgpu::Color::BLACK,
             &self.bright_pipeline,
             &self.bg_hdr,
+            self.post.slot_offset(0),
             None,
+            self.timestamps(SPAN_BLOOM, true, false),
         );
 
-        // Pass 3: blur horizontal bloom_a -> bloom_b
-        post::write_post_uniforms(
-            &self.queue,
-            &self.post.uniform_buffer,
-            res,
-            self.time_accum,
-            self.ambient_energy,
-            [1.0, 0.0],
-        );
-        post::blit(
-            &mut encoder,
-            "blur_h",
-            &self.targets.bloom_b_view,
-            wgpu::Color::BLACK,
-            &self.blur_pipeline,
-            &self.bg_from_bloom_a,
-            None,
-        );
-
-        // Pass 4: blur vertical bloom_b -> bloom_a
-        post::write_post_uniforms(
-            &self.queue,
-            &self.post.uniform_buffer,
-            res,
-            self.time_accum,
-            self.ambient_energy,
-            [0.0, 1.0],
-        );
-        post::blit(
-            &mut encoder,
-            "blur_v",
-            &self.targets.bloom_a_view,
-            wgpu::Color::BLACK,
-            &self.blur_pipeline,
-            &self.bg_from_bloom_b,
-            None,
-        );
+        // Pass 3: blur iterations, each horizontal bloom_a -> bloom_b then
+        // vertical bloom_b -> bloom_a
+        for pass in 0..self.bloom.passes {
+            let (h_slot, v_slot) = (1 + 2 * pass, 2 + 2 * pass);
+            let last = pass + 1 == self.bloom.passes;
+            uniforms.blur_dir = [1.0, 0.0];
+            post::write_post_slot(&self.queue, &self.post, h_slot, &uniforms);
+            post::blit(
+                &mut encoder,
+                "blur_h",
+                &self.targets.bloom_b_view,
+                wgpu::Color::BLACK,
+                &self.blur_pipeline,
+                &self.bg_from_bloom_a,
+                self.post.slot_offset(h_slot),
+                None,
+                None,
+            );
+            uniforms.blur_dir = [0.0, 1.0];
+            post::write_post_slot(&self.queue, &self.post, v_slot, &uniforms);
+            post::blit(
+                &mut encoder,
+                "blur_v",
+                &self.targets.bloom_a_view,
+                wgpu::Color::BLACK,
+                &self.blur_pipeline,
+                &self.bg_from_bloom_b,
+                self.post.slot_offset(v_slot),
+                None,
+                self.timestamps(SPAN_BLOOM, false, last),
+            );
+        }
 
-        // Pass 5: composite to swapchain
-        post::write_post_uniforms(
-            &self.queue,
-            &self.post.uniform_buffer,
-            res,
-            self.time_accum,
-            self.ambient_energy,
-            [0.0, 0.0],
-        );
+        // Pass 4: composite to swapchain, or to the source of the lens pass
+        // (pass 5) or the FXAA pass (pass 6), whichever runs first
+        if self.lens.active() {
+            self.prepare_lens();
+        }
+        if self.antialias.fxaa() {
+            self.prepare_fxaa();
+        }
+        let lens = self
+            .lens_res
+            .as_ref()
+            .filter(|_| self.lens.active())
+            .and_then(|l| l.source());
+        let fxaa = self
+            .fxaa
+            .as_ref()
+            .filter(|_| self.antialias.fxaa())
+            .and_then(|f| f.source());
+        let lens_target = fxaa.unwrap_or(view);
         post::blit(
             &mut encoder,
             "composite",
-            &view,
+            lens.unwrap_or(lens_target),
             self.clear_color,
             &self.composite_pipeline,
             &self.bg_hdr,
+            self.post.slot_offset(0),
             Some(&self.bg_bloom_a_only),
+            self.timestamps(SPAN_COMPOSITE, true, lens.is_none() && fxaa.is_none()),
         );
+        if let (Some(_), Some(lens)) = (lens, &self.lens_res) {
+            lens.draw(
+                &mut encoder,
+                lens_target,
+                self.timestamps(SPAN_COMPOSITE, false, fxaa.is_none()),
+            );
+        }
+        if let (Some(_), Some(fxaa)) = (fxaa, &self.fxaa) {
+            fxaa.draw(
+                &mut encoder,
+                view,
+                self.timestamps(SPAN_COMPOSITE, false, true),
+            );
+        }
 
-        self.queue.submit(Some(encoder.finish()));
-        frame.present();
-        Ok(())
+        if !self.overlay_text.is_empty() {
+            self.draw_overlay_text(&mut encoder, view);
+        }
+
+        if let Some(timer) = &self.timer {
+            timer.resolve(&mut encoder);
+        }
+        encoder
+    }
+}
+
+/// Camera clip planes; the lens pass turns depth back into distance with them.
+const NEAR_PLANE: f32 = 0.1;
+const FAR_PLANE: f32 = 100.0;
+
+/// Canvas height per font pixel of overlay text (so 2× at 720p).
+const TEXT_SCALE_PER_PX: f32 = 360.0;
+
+/// Overlay text and its panel, in linear colour (the swapchain is sRGB):
+/// the pale blue and translucent navy of the DOM overlays.
+const TEXT_COLOR: [f32; 4] = [0.62, 0.8, 1.0, 0.95];
+const TEXT_PANEL_COLOR: [f32; 4] = [0.003, 0.0044, 0.009, 0.8];
+
+/// The WGSL compiled into the app for `shader`.
+pub fn built_in_shader(shader: Shader) -> &'static str {
+    match shader {
+        Shader::Post => crate::core::POST_WGSL,
+        Shader::Waves => crate::core::WAVES_WGSL,
+        Shader::Scene => crate::core::SCENE_WGSL,
+        Shader::Exposure => crate::core::EXPOSURE_WGSL,
+        Shader::Particles => crate::core::PARTICLES_WGSL,
+        Shader::Text => crate::core::TEXT_WGSL,
+        Shader::Lens => crate::core::LENS_WGSL,
+        Shader::Fxaa => crate::core::FXAA_WGSL,
+        Shader::Custom => crate::core::CUSTOM_WGSL,
+    }
+}
+
+/// The source a pipeline is built from: the one shader reloading loaded, or
+/// the one built in.
+fn shader_source(shaders: &ShaderSources, shader: Shader) -> &str {
+    shaders
+        .get(shader)
+        .unwrap_or_else(|| built_in_shader(shader))
+}
+
+/// The waves module: waves.wgsl with the custom visualizer appended.
+fn waves_source(shaders: &ShaderSources) -> String {
+    with_custom(
+        shader_source(shaders, Shader::Waves),
+        shader_source(shaders, Shader::Custom),
+    )
+}
+
+/// The theme's deep background, brightened a little (and shifted slightly in
+/// hue) with ambient energy.
+fn clear_color(theme: &Theme, ambient: f32) -> wgpu::Color {
+    let boost = 0.06 * ambient; // up to +0.06
+    let [r, g, b] = theme.background[0];
+    wgpu::Color {
+        r: (r + boost * 0.8) as f64,
+        g: (g + boost * 0.9) as f64,
+        b: (b + boost * 0.5) as f64,
+        a: 1.0,
     }
 }
 
 impl<'a> GpuState<'a> {
+    /// Upload this frame's uniforms (and analyser data) for the layers the
+    /// render mode draws, and for the particles while any are alive.
+    fn write_layer_uniforms(&self, dt_sec: f32, voice_positions: &[Vec3], pulse_energy: &[f32]) {
+        if let (true, Some(waves)) = (self.render_mode.draws_waves(), &self.waves) {
+            let star_shift = star_shift(self.cam_eye);
+            let w = WavesUniforms {
+                resolution: [self.width as f32, self.height as f32],
+                time: self.time_accum,
+                ambient: self.ambient_energy,
+                voices: [0, 1, 2].map(|i| VoicePacked {
+                    pos_pulse: voice_positions[i].extend(pulse_energy[i]).to_array(),
+                    style: self.voice_styles[i],
+                }),
+                swirl_uv: [
+                    self.swirl_uv[0].clamp(0.0, 1.0),
+                    self.swirl_uv[1].clamp(0.0, 1.0),
+                ],
+                swirl_strength: if self.swirl_active > 0.5 { 1.4 } else { 0.0 },
+                swirl_active: self.swirl_active,
+                spectrum_on: if self.layers.spectrum { 1.0 } else { 0.0 },
+                scope_on: if self.layers.scope { 1.0 } else { 0.0 },
+                trails_on: if self.trails.iter().any(|p| p[3] > 0.0) {
+                    1.0
+                } else {
+                    0.0
+                },
+                stars: self.stars,
+                bg_deep: rgba(self.theme.background[0], 1.0),
+                bg_lift: rgba(self.theme.background[1], 1.0),
+                voice_colors: self.voice_colors(),
+                accent: rgba(self.theme.accent, 1.0),
+                trails: self.trails,
+                ripples: self.ripples.pack(self.time_accum),
+                clock: self.clock.pack(),
+                star_shift: [star_shift[0], star_shift[1], 0.0, 0.0],
+                streak: self.streak.pack(self.time_accum),
+            };
+            self.queue
+                .write_buffer(&waves.uniform_buffer, 0, bytemuck::bytes_of(&w));
+            if self.layers.any() {
+                self.queue.write_buffer(
+                    &waves.bins_buffer,
+                    0,
+                    bytemuck::cast_slice(&self.audio_bins),
+                );
+            }
+        }
+        let (view_proj, right, up) = self.camera();
+        if let (true, Some(scene)) = (self.render_mode.draws_scene(), &self.scene) {
+            let u = SceneUniforms {
+                view_proj,
+                cam_right: right.extend(0.0).to_array(),
+                cam_up: up.extend(0.0).to_array(),
+                cam_eye: self.cam_eye.extend(1.0).to_array(),
+                // Voices live in engine space; place them as picking does
+                voices: [0, 1, 2].map(|i| {
+                    (voice_positions[i] * SPREAD + Z_OFFSET)
+                        .extend(pulse_energy[i])
+                        .to_array()
+                }),
+                voice_colors: self.voice_colors(),
+                voice_styles: self.voice_styles,
+                time: self.time_accum,
+                ambient: self.ambient_energy,
+                sphere_radius: VOICE_SPHERE_RADIUS,
+                _pad: 0.0,
+            };
+            self.queue
+                .write_buffer(&scene.uniform_buffer, 0, bytemuck::bytes_of(&u));
+        }
+        if let (true, Some(particles)) = (self.particles_live(), &self.particles) {
+            let u = ParticleUniforms {
+                view_proj,
+                cam_right: right.extend(0.0).to_array(),
+                cam_up: up.extend(0.0).to_array(),
+                dt: dt_sec,
+                time: self.time_accum,
+                drag: PARTICLE_DRAG_PER_SEC,
+                gravity: PARTICLE_GRAVITY,
+            };
+            self.queue
+                .write_buffer(&particles.uniform_buffer, 0, bytemuck::bytes_of(&u));
+        }
+    }
+
+    /// View-projection matrix and the camera's right and up vectors, for
+    /// billboards in world space.
+    fn camera(&self) -> ([[f32; 4]; 4], Vec3, Vec3) {
+        let aspect = self.width as f32 / self.height.max(1) as f32;
+        let fov = std::f32::consts::FRAC_PI_4 * self.clock.zoom();
+        let proj = match self.display_slice {
+            Some(slice) => slice.projection(fov, aspect, NEAR_PLANE, FAR_PLANE),
+            None => Mat4::perspective_rh(fov, aspect, NEAR_PLANE, FAR_PLANE),
+        };
+        let view_m = Mat4::look_at_rh(self.cam_eye, self.cam_target, Vec3::Y);
+        let forward = (self.cam_target - self.cam_eye).normalize_or_zero();
+        let right = forward.cross(Vec3::Y).normalize_or_zero();
+        let up = right.cross(forward);
+        ((proj * view_m).to_cols_array_2d(), right, up)
+    }
+
+    /// Lay out the overlay text if it or the canvas changed, and draw it.
+    fn draw_overlay_text(&mut self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let text = self.text.get_or_insert_with(|| {
+            create_text_resources(
+                &self.device,
+                &self.queue,
+                self.config.format,
+                shader_source(&self.shaders, Shader::Text),
+            )
+        });
+        let size = (self.width, self.height);
+        if self.text_laid_out_for != Some(size) {
+            // Whole screen pixels per font pixel, larger on bigger canvases
+            let scale = (self.height as f32 / TEXT_SCALE_PER_PX).round().max(1.0);
+            let margin = 12.0;
+            let padding = 3.0 * scale;
+            let [_, text_h] = GlyphAtlas::measure(&self.overlay_text, scale);
+            let origin = [
+                margin + padding,
+                self.height as f32 - margin - padding - text_h,
+            ];
+            self.text_quads.clear();
+            text.atlas.layout_panel(
+                &self.overlay_text,
+                origin,
+                scale,
+                TEXT_COLOR,
+                TEXT_PANEL_COLOR,
+                padding,
+                &mut self.text_quads,
+            );
+            text.upload(&self.queue, &self.text_quads);
+            let u = TextUniforms {
+                screen: [self.width as f32, self.height as f32],
+                _pad: [0.0; 2],
+            };
+            self.queue
+                .write_buffer(&text.uniform_buffer, 0, bytemuck::bytes_of(&u));
+            self.text_laid_out_for = Some(size);
+        }
+        text.draw(encoder, view);
+    }
+
+    /// Timestamp writes for a pass of `span`, when this frame is timed.
+    fn timestamps(
+        &self,
+        span: u32,
+        begins: bool,
+        ends: bool,
+    ) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.timer
+            .as_ref()
+            .and_then(|t| t.writes(span, begins, ends))
+    }
+
+    /// Create the lens pass if needed, size it to the frame and upload its
+    /// uniforms.
+    fn prepare_lens(&mut self) {
+        let lens = self.lens_res.get_or_insert_with(|| {
+            create_lens_resources(
+                &self.device,
+                self.config.format,
+                self.targets.samples,
+                shader_source(&self.shaders, Shader::Lens),
+            )
+        });
+        lens.prepare(
+            &self.device,
+            self.width,
+            self.height,
+            self.config.format,
+            &self.linear_sampler,
+            &self.targets.depth_view,
+        );
+        // Only the 3D layers write depth; the waves alone would all be
+        // out of focus
+        let dof = if self.render_mode.draws_scene() {
+            self.lens.dof
+        } else {
+            DofQuality::Off
+        };
+        let u = LensUniforms {
+            resolution: [self.width as f32, self.height as f32],
+            time: self.time_accum,
+            downbeat: self.clock.downbeat,
+            near: NEAR_PLANE,
+            far: FAR_PLANE,
+            focus: self.lens.focus,
+            focus_range: DOF_FOCUS_RANGE,
+            max_blur: MAX_DOF_BLUR,
+            taps: dof.taps(),
+            vignette: self.lens.vignette,
+            _pad: 0.0,
+        };
+        self.queue
+            .write_buffer(&lens.uniform_buffer, 0, bytemuck::bytes_of(&u));
+    }
+
+    /// Create the FXAA pass if needed and size it to the frame.
+    fn prepare_fxaa(&mut self) {
+        self.fxaa
+            .get_or_insert_with(|| {
+                create_fxaa_resources(
+                    &self.device,
+                    self.config.format,
+                    shader_source(&self.shaders, Shader::Fxaa),
+                )
+            })
+            .prepare(
+                &self.device,
+                self.width,
+                self.height,
+                self.config.format,
+                &self.linear_sampler,
+            );
+    }
+
+    /// The shared depth buffer, cleared for a pass of layers and kept for
+    /// the lens pass.
+    fn depth_attachment(&self) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+        wgpu::RenderPassDepthStencilAttachment {
+            view: &self.targets.depth_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }
+    }
+
+    fn particles_live(&self) -> bool {
+        self.time_accum < self.particles_live_until
+    }
+
+    /// Draw the render mode's layers, with `visualizer` as the fullscreen one,
+    /// then any live particles.
+    fn draw_layers(&self, rpass: &mut wgpu::RenderPass<'_>, visualizer: Visualizer) {
+        if let (true, Some(waves)) = (self.render_mode.draws_waves(), &self.waves) {
+            if let Some(pipeline) = waves.pipeline(visualizer) {
+                rpass.set_pipeline(pipeline);
+                rpass.set_bind_group(0, &waves.bind_group, &[]);
+                rpass.draw(0..3, 0..1);
+            }
+        }
+        if let (true, Some(scene)) = (self.render_mode.draws_scene(), &self.scene) {
+            rpass.set_bind_group(0, &scene.bind_group, &[]);
+            rpass.set_pipeline(&scene.sphere_pipeline);
+            rpass.draw(0..6, 0..3);
+            rpass.set_pipeline(&scene.pipeline);
+            rpass.draw(0..6, 0..3);
+        }
+        if let (true, Some(particles)) = (self.particles_live(), &self.particles) {
+            particles.draw(rpass);
+        }
+    }
+
     fn rebuild_post_bind_groups(&mut self) {
         let (bg_hdr, bg_from_a, bg_from_b, bg_a_only, bg_b_only) = post::rebuild_bind_groups(
             &self.device,
             &self.post,
             &self.linear_sampler,
             &self.targets.hdr_view,
+            &self.targets.fade_view,
             &self.targets.bloom_a_view,
             &self.targets.bloom_b_view,
+            &self.auto_exposure.state_buffer,
         );
         self.bg_hdr = bg_hdr;
         self.bg_from_bloom_a = bg_from_a;
@@ -0,0 +1,132 @@
+use super::helpers;
+use crate::core::screenshot::Screenshot;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+// Readback states, set by the map callback
+const MAP_PENDING: u8 = 0;
+const MAP_READY: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+/// A frame rendered into its own texture for a screenshot, and the buffer it
+/// is copied to for reading back.
+///
+/// The frame is drawn and copied in one submission; the buffer is mapped
+/// after it, and `poll` hands over the pixels once the mapping lands, so
+/// nothing waits on the GPU.
+pub(crate) struct ScreenCapture {
+    texture: wgpu::Texture,
+    pub(crate) view: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    row_stride: u32,
+    bgra: bool,
+    map_state: Arc<AtomicU8>,
+}
+
+impl ScreenCapture {
+    /// A `width`×`height` target in the swapchain's `format`; `None` for
+    /// formats without 8-bit RGBA texels to read back.
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Option<Self> {
+        let bgra = match format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            _ => return None,
+        };
+        let (texture, view) = helpers::create_color_texture(
+            device,
+            "screenshot_tex",
+            width,
+            height,
+            format,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        );
+        // Buffer copies need rows padded to the copy alignment
+        let row_stride = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("screenshot_readback"),
+            size: row_stride as u64 * height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            texture,
+            view,
+            buffer,
+            width,
+            height,
+            row_stride,
+            bgra,
+            map_state: Arc::new(AtomicU8::new(MAP_PENDING)),
+        })
+    }
+
+    /// Copy the rendered frame into the readback buffer.
+    pub(crate) fn copy(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.row_stride),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Start reading back the frame just submitted.
+    pub(crate) fn after_submit(&self) {
+        let map_state = self.map_state.clone();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let state = if result.is_ok() {
+                    MAP_READY
+                } else {
+                    MAP_FAILED
+                };
+                map_state.store(state, Ordering::Release);
+            });
+    }
+
+    /// `None` while the readback is in flight, then the screenshot, or
+    /// `Some(None)` if the mapping failed.
+    pub(crate) fn poll(&self) -> Option<Option<Screenshot>> {
+        match self.map_state.load(Ordering::Acquire) {
+            MAP_PENDING => None,
+            MAP_READY => {
+                let shot = {
+                    let data = self.buffer.slice(..).get_mapped_range();
+                    Screenshot::from_rows(
+                        self.width,
+                        self.height,
+                        &data,
+                        self.row_stride as usize,
+                        self.bgra,
+                    )
+                };
+                self.buffer.unmap();
+                Some(Some(shot))
+            }
+            _ => Some(None),
+        }
+    }
+}
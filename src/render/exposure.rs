@@ -0,0 +1,192 @@
+use crate::core::theme::{SAFE_FLASH_RATIO, SAFE_SETTLE_PER_SEC};
+use crate::core::tonemap::{
+    AUTO_EXPOSURE_ADAPT_PER_SEC, AUTO_EXPOSURE_KEY, AUTO_EXPOSURE_MAX_GAIN, AUTO_EXPOSURE_MIN_GAIN,
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct ExposureUniforms {
+    pub(crate) dt: f32,
+    pub(crate) key: f32,
+    pub(crate) min_gain: f32,
+    pub(crate) max_gain: f32,
+    pub(crate) adapt_per_sec: f32,
+    pub(crate) auto_on: f32,
+    pub(crate) flash_ratio: f32,
+    pub(crate) settle_per_sec: f32,
+}
+
+/// Auto-exposure and the flash limiter: a compute pass measuring the HDR
+/// frame and the gains it adapts, kept in a storage buffer the composite
+/// reads.
+pub(crate) struct ExposureResources {
+    pub(crate) pipeline: wgpu::ComputePipeline,
+    pub(crate) bgl: wgpu::BindGroupLayout,
+    pub(crate) uniform_buffer: wgpu::Buffer,
+    pub(crate) state_buffer: wgpu::Buffer,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+pub(crate) fn create_exposure_resources(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    hdr_view: &wgpu::TextureView,
+    source: &str,
+) -> ExposureResources {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("exposure_shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("exposure_bgl"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+    let pl = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("exposure_pl"),
+        bind_group_layouts: &[&bgl],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("exposure_pipeline"),
+        layout: Some(&pl),
+        module: &shader,
+        entry_point: Some("cs_exposure"),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+    let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("exposure_uniforms"),
+        size: std::mem::size_of::<ExposureUniforms>() as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    // Storage bindings are at least 16 bytes: the gain, the flash limiter's
+    // settled level and its gain
+    let state_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("exposure_state"),
+        size: 16,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    // Start from unity gain so switching auto-exposure on eases in
+    queue.write_buffer(
+        &state_buffer,
+        0,
+        bytemuck::cast_slice(&[1.0f32, 0.0, 1.0, 0.0]),
+    );
+    let bind_group = exposure_bind_group(device, &bgl, hdr_view, &uniform_buffer, &state_buffer);
+
+    ExposureResources {
+        pipeline,
+        bgl,
+        uniform_buffer,
+        state_buffer,
+        bind_group,
+    }
+}
+
+fn exposure_bind_group(
+    device: &wgpu::Device,
+    bgl: &wgpu::BindGroupLayout,
+    hdr_view: &wgpu::TextureView,
+    uniform_buffer: &wgpu::Buffer,
+    state_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("exposure_bg"),
+        layout: bgl,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(hdr_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: state_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+impl ExposureResources {
+    /// Point the measurement at a recreated HDR target.
+    pub(crate) fn rebind(&mut self, device: &wgpu::Device, hdr_view: &wgpu::TextureView) {
+        self.bind_group = exposure_bind_group(
+            device,
+            &self.bgl,
+            hdr_view,
+            &self.uniform_buffer,
+            &self.state_buffer,
+        );
+    }
+
+    /// Start the flash limiter afresh, settling at the next frame measured.
+    pub(crate) fn reset_flash_limit(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.state_buffer, 4, bytemuck::cast_slice(&[0.0f32, 1.0]));
+    }
+
+    /// Measure this frame's HDR image and move the auto-exposure gain (if
+    /// `auto`) and the flash limiter (if `limit_flashes`) `dt_sec` further on.
+    pub(crate) fn adapt(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        dt_sec: f32,
+        auto: bool,
+        limit_flashes: bool,
+    ) {
+        let u = ExposureUniforms {
+            dt: dt_sec,
+            key: AUTO_EXPOSURE_KEY,
+            min_gain: AUTO_EXPOSURE_MIN_GAIN,
+            max_gain: AUTO_EXPOSURE_MAX_GAIN,
+            adapt_per_sec: AUTO_EXPOSURE_ADAPT_PER_SEC,
+            auto_on: if auto { 1.0 } else { 0.0 },
+            flash_ratio: if limit_flashes { SAFE_FLASH_RATIO } else { 0.0 },
+            settle_per_sec: SAFE_SETTLE_PER_SEC,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&u));
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("auto_exposure"),
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bind_group, &[]);
+        cpass.dispatch_workgroups(1, 1, 1);
+    }
+}
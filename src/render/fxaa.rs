@@ -0,0 +1,134 @@
+use super::helpers;
+
+/// FXAA pass at the end of the chain: whatever would have drawn into the
+/// swapchain (the composite or the lens pass) draws into the pass's own
+/// target instead, which is smoothed on the way to the screen.
+pub(crate) struct FxaaResources {
+    pipeline: wgpu::RenderPipeline,
+    bgl: wgpu::BindGroupLayout,
+    // Finished frame, at the swapchain's size and format
+    target: Option<(wgpu::Texture, wgpu::TextureView)>,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+pub(crate) fn create_fxaa_resources(
+    device: &wgpu::Device,
+    surface_format: wgpu::TextureFormat,
+    source: &str,
+) -> FxaaResources {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("fxaa_shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("fxaa_bgl"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    });
+    let pl = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("fxaa_pl"),
+        bind_group_layouts: &[&bgl],
+        push_constant_ranges: &[],
+    });
+    let pipeline =
+        helpers::make_post_pipeline(device, &pl, &shader, "fs_fxaa", surface_format, None);
+
+    FxaaResources {
+        pipeline,
+        bgl,
+        target: None,
+        bind_group: None,
+    }
+}
+
+impl FxaaResources {
+    /// Size the source target to `width`×`height`, if that has changed.
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        sampler: &wgpu::Sampler,
+    ) {
+        let stale = self.target.as_ref().is_none_or(|(tex, _)| {
+            (tex.width(), tex.height(), tex.format()) != (width, height, format)
+        });
+        if !stale {
+            return;
+        }
+        let (tex, view) = helpers::create_color_texture(
+            device,
+            "fxaa_source",
+            width,
+            height,
+            format,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("fxaa_bg"),
+            layout: &self.bgl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        }));
+        self.target = Some((tex, view));
+    }
+
+    /// The view the frame draws into, once prepared.
+    pub(crate) fn source(&self) -> Option<&wgpu::TextureView> {
+        self.target.as_ref().map(|(_, view)| view)
+    }
+
+    /// Draw the smoothed frame into `target`.
+    pub(crate) fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'_>>,
+    ) {
+        let Some(bind_group) = &self.bind_group else {
+            return;
+        };
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("fxaa_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -7,6 +7,19 @@ pub fn create_color_texture_device(
     height: u32,
     format: wgpu::TextureFormat,
     usage: wgpu::TextureUsages,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    create_multisampled_texture(device, label, width, height, format, 1, usage)
+}
+
+/// A render target with `sample_count` samples per pixel (1 for a plain one).
+pub fn create_multisampled_texture(
+    device: &wgpu::Device,
+    label: &str,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    usage: wgpu::TextureUsages,
 ) -> (wgpu::Texture, wgpu::TextureView) {
     let tex = device.create_texture(&wgpu::TextureDescriptor {
         label: Some(label),
@@ -16,7 +29,7 @@ pub fn create_color_texture_device(
             depth_or_array_layers: 1,
         },
         mip_level_count: 1,
-        sample_count: 1,
+        sample_count,
         dimension: wgpu::TextureDimension::D2,
         format,
         usage,
@@ -37,6 +50,31 @@ pub fn create_color_texture(
     create_color_texture_device(device, label, width, height, format, usage)
 }
 
+/// Depth buffer shared by the layers drawn into the HDR target.
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Depth state for a layer drawn into the HDR target: `write` for solid
+/// geometry, tested with `compare` (`Always` for fullscreen layers, `Less`
+/// for glows that should hide behind the voices).
+pub fn depth_state(write: bool, compare: wgpu::CompareFunction) -> Option<wgpu::DepthStencilState> {
+    Some(wgpu::DepthStencilState {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: write,
+        depth_compare: compare,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+    })
+}
+
+/// Multisampling for a layer drawn into the HDR target, matching its
+/// `samples` per pixel.
+pub fn multisample_state(samples: u32) -> wgpu::MultisampleState {
+    wgpu::MultisampleState {
+        count: samples,
+        ..Default::default()
+    }
+}
+
 pub fn make_post_pipeline(
     device: &wgpu::Device,
     layout: &wgpu::PipelineLayout,
@@ -71,3 +109,8 @@ pub fn make_post_pipeline(
         multiview: None,
     })
 }
+
+/// Pad a theme colour to a uniform-friendly vec4.
+pub fn rgba(rgb: [f32; 3], a: f32) -> [f32; 4] {
+    [rgb[0], rgb[1], rgb[2], a]
+}
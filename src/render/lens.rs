@@ -0,0 +1,226 @@
+use super::helpers;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct LensUniforms {
+    pub(crate) resolution: [f32; 2],
+    pub(crate) time: f32,
+    pub(crate) downbeat: f32,
+    pub(crate) near: f32,
+    pub(crate) far: f32,
+    pub(crate) focus: f32,
+    pub(crate) focus_range: f32,
+    pub(crate) max_blur: f32,
+    pub(crate) taps: u32,
+    pub(crate) vignette: f32,
+    pub(crate) _pad: f32,
+}
+
+/// Lens pass between the composite and the swapchain: the composite draws
+/// into the pass's own target instead, which is read back with the scene's
+/// depth buffer for depth of field and the vignette.
+pub(crate) struct LensResources {
+    pipeline: wgpu::RenderPipeline,
+    bgl: wgpu::BindGroupLayout,
+    pub(crate) uniform_buffer: wgpu::Buffer,
+    // Composite output, at the swapchain's size and format
+    target: Option<(wgpu::Texture, wgpu::TextureView)>,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+pub(crate) fn create_lens_resources(
+    device: &wgpu::Device,
+    surface_format: wgpu::TextureFormat,
+    depth_samples: u32,
+    source: &str,
+) -> LensResources {
+    // With MSAA the depth buffer is multisampled; sample 0 is read either way
+    let multisampled = depth_samples > 1;
+    let source = if multisampled {
+        source.replace("texture_depth_2d", "texture_depth_multisampled_2d")
+    } else {
+        source.to_string()
+    };
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("lens_shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("lens_bgl"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Depth,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+    let pl = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("lens_pl"),
+        bind_group_layouts: &[&bgl],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("lens_pipeline"),
+        layout: Some(&pl),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_fullscreen"),
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_lens"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: surface_format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        cache: None,
+        multiview: None,
+    });
+    let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("lens_uniforms"),
+        size: std::mem::size_of::<LensUniforms>() as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    LensResources {
+        pipeline,
+        bgl,
+        uniform_buffer,
+        target: None,
+        bind_group: None,
+    }
+}
+
+impl LensResources {
+    /// Forget the bind group, after the depth buffer it reads is recreated.
+    pub(crate) fn invalidate(&mut self) {
+        self.bind_group = None;
+    }
+
+    /// Size the composite target to `width`×`height` and bind it with
+    /// `depth_view`, if either has changed.
+    pub(crate) fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        sampler: &wgpu::Sampler,
+        depth_view: &wgpu::TextureView,
+    ) {
+        let stale = self.target.as_ref().is_none_or(|(tex, _)| {
+            (tex.width(), tex.height(), tex.format()) != (width, height, format)
+        });
+        if stale {
+            self.target = Some(helpers::create_color_texture(
+                device,
+                "lens_source",
+                width,
+                height,
+                format,
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            ));
+            self.bind_group = None;
+        }
+        let (_, view) = self.target.as_ref().expect("lens target just created");
+        if self.bind_group.is_none() {
+            self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("lens_bg"),
+                layout: &self.bgl,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(depth_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: self.uniform_buffer.as_entire_binding(),
+                    },
+                ],
+            }));
+        }
+    }
+
+    /// The view the composite draws into, once prepared.
+    pub(crate) fn source(&self) -> Option<&wgpu::TextureView> {
+        self.target.as_ref().map(|(_, view)| view)
+    }
+
+    /// Draw the lensed composite into `target`.
+    pub(crate) fn draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'_>>,
+    ) {
+        let Some(bind_group) = &self.bind_group else {
+            return;
+        };
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("lens_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
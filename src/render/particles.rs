@@ -0,0 +1,227 @@
+use super::helpers;
+use crate::core::particles::{Particle, MAX_PARTICLES};
+
+/// Particle as stored in the pool (`Particle` in particles.wgsl).
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct GpuParticle {
+    pub(crate) pos_age: [f32; 4],
+    pub(crate) vel_life: [f32; 4],
+    pub(crate) color_size: [f32; 4],
+}
+
+impl From<&Particle> for GpuParticle {
+    fn from(p: &Particle) -> Self {
+        GpuParticle {
+            pos_age: p.pos_age,
+            vel_life: p.vel_life,
+            color_size: p.color_size,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct ParticleUniforms {
+    pub(crate) view_proj: [[f32; 4]; 4],
+    pub(crate) cam_right: [f32; 4],
+    pub(crate) cam_up: [f32; 4],
+    pub(crate) dt: f32,
+    pub(crate) time: f32,
+    pub(crate) drag: f32,
+    pub(crate) gravity: f32,
+}
+
+/// Threads per compute workgroup (`@workgroup_size` in particles.wgsl).
+const WORKGROUP_SIZE: u32 = 64;
+
+pub(crate) struct ParticleResources {
+    pub(crate) step_pipeline: wgpu::ComputePipeline,
+    pub(crate) draw_pipeline: wgpu::RenderPipeline,
+    pub(crate) uniform_buffer: wgpu::Buffer,
+    pub(crate) pool_buffer: wgpu::Buffer,
+    pub(crate) step_bind_group: wgpu::BindGroup,
+    pub(crate) draw_bind_group: wgpu::BindGroup,
+}
+
+/// The particle pool (all slots dead to begin with), the compute pass that
+/// steps it and the additive pipeline that draws it into the HDR target.
+pub(crate) fn create_particle_resources(
+    device: &wgpu::Device,
+    hdr_format: wgpu::TextureFormat,
+    samples: u32,
+    source: &str,
+) -> ParticleResources {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("particles_shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let uniform_entry = wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+    let step_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("particles_step_bgl"),
+        entries: &[
+            uniform_entry,
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+    let draw_bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("particles_draw_bgl"),
+        entries: &[
+            uniform_entry,
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+    let step_pl = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("particles_step_pl"),
+        bind_group_layouts: &[&step_bgl],
+        push_constant_ranges: &[],
+    });
+    let draw_pl = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("particles_draw_pl"),
+        bind_group_layouts: &[&draw_bgl],
+        push_constant_ranges: &[],
+    });
+    let step_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("particles_step_pipeline"),
+        layout: Some(&step_pl),
+        module: &shader,
+        entry_point: Some("cs_step"),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    });
+    let additive = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    };
+    let draw_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("particles_draw_pipeline"),
+        layout: Some(&draw_pl),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_particle"),
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: helpers::depth_state(false, wgpu::CompareFunction::Less),
+        multisample: helpers::multisample_state(samples),
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_particle"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: hdr_format,
+                blend: Some(wgpu::BlendState {
+                    color: additive,
+                    alpha: additive,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        cache: None,
+        multiview: None,
+    });
+    let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("particles_uniforms"),
+        size: std::mem::size_of::<ParticleUniforms>() as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let pool_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("particles_pool"),
+        size: (MAX_PARTICLES * std::mem::size_of::<GpuParticle>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let step_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("particles_step_bg"),
+        layout: &step_bgl,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: pool_buffer.as_entire_binding(),
+            },
+        ],
+    });
+    let draw_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("particles_draw_bg"),
+        layout: &draw_bgl,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: pool_buffer.as_entire_binding(),
+            },
+        ],
+    });
+
+    ParticleResources {
+        step_pipeline,
+        draw_pipeline,
+        uniform_buffer,
+        pool_buffer,
+        step_bind_group,
+        draw_bind_group,
+    }
+}
+
+impl ParticleResources {
+    /// Write a burst into the pool starting at slot `first`.
+    pub(crate) fn upload(&self, queue: &wgpu::Queue, first: usize, burst: &[Particle]) {
+        let gpu: Vec<GpuParticle> = burst.iter().map(GpuParticle::from).collect();
+        let offset = (first * std::mem::size_of::<GpuParticle>()) as u64;
+        queue.write_buffer(&self.pool_buffer, offset, bytemuck::cast_slice(&gpu));
+    }
+
+    /// Advance every live particle by the dt in the uniforms.
+    pub(crate) fn step(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("particles_step"),
+            timestamp_writes: None,
+        });
+        cpass.set_pipeline(&self.step_pipeline);
+        cpass.set_bind_group(0, &self.step_bind_group, &[]);
+        cpass.dispatch_workgroups((MAX_PARTICLES as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    pub(crate) fn draw(&self, rpass: &mut wgpu::RenderPass<'_>) {
+        rpass.set_pipeline(&self.draw_pipeline);
+        rpass.set_bind_group(0, &self.draw_bind_group, &[]);
+        rpass.draw(0..6, 0..MAX_PARTICLES as u32);
+    }
+}
@@ -1,21 +1,38 @@
-use crate::constants;
+use super::helpers::rgba;
+use crate::core::bloom::{Bloom, MAX_BLOOM_PASSES};
+use crate::core::theme::{Theme, SAFE_PEAK_LUMA};
+use crate::core::tonemap::{Exposure, Tonemap};
 use wgpu;
 
 pub(crate) struct PostResources {
     pub(crate) bgl0: wgpu::BindGroupLayout, // tex+sampler+uniform
-    pub(crate) bgl1: wgpu::BindGroupLayout, // tex+sampler
+    pub(crate) bgl1: wgpu::BindGroupLayout, // bloom tex+sampler, fade tex, exposure
+    /// One `PostUniforms` slot per pass (`POST_SLOTS`), bound with a dynamic
+    /// offset
     pub(crate) uniform_buffer: wgpu::Buffer,
+    pub(crate) slot_stride: u32,
     pub(crate) bright_pipeline: wgpu::RenderPipeline,
     pub(crate) blur_pipeline: wgpu::RenderPipeline,
     pub(crate) composite_pipeline: wgpu::RenderPipeline,
 }
 
+/// Uniform slots: the bright pass and composite share slot 0, then a
+/// horizontal and a vertical slot per blur iteration.
+pub(crate) const POST_SLOTS: u32 = 1 + 2 * MAX_BLOOM_PASSES;
+
+impl PostResources {
+    pub(crate) fn slot_offset(&self, slot: u32) -> u32 {
+        slot * self.slot_stride
+    }
+}
+
 pub(crate) fn create_post_resources(
     device: &wgpu::Device,
     post_shader: &wgpu::ShaderModule,
     bloom_format: wgpu::TextureFormat,
     swap_format: wgpu::TextureFormat,
 ) -> PostResources {
+    let uniforms_size = std::mem::size_of::<super::PostUniforms>() as u64;
     let bgl0 = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         label: Some("post_bgl0"),
         entries: &[
@@ -40,8 +57,8 @@ pub(crate) fn create_post_resources(
                 visibility: wgpu::ShaderStages::FRAGMENT,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(uniforms_size),
                 },
                 count: None,
             },
@@ -66,11 +83,33 @@ pub(crate) fn create_post_resources(
                 ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                 count: None,
             },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
         ],
     });
+    let slot_stride = (uniforms_size as u32)
+        .next_multiple_of(device.limits().min_uniform_buffer_offset_alignment);
     let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
         label: Some("post_uniforms"),
-        size: std::mem::size_of::<super::PostUniforms>() as u64,
+        size: (POST_SLOTS * slot_stride) as u64,
         usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         mapped_at_creation: false,
     });
@@ -113,12 +152,14 @@ pub(crate) fn create_post_resources(
         bgl0,
         bgl1,
         uniform_buffer,
+        slot_stride,
         bright_pipeline,
         blur_pipeline,
         composite_pipeline,
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn blit(
     encoder: &mut wgpu::CommandEncoder,
     label: &str,
@@ -126,7 +167,9 @@ pub(crate) fn blit(
     clear: wgpu::Color,
     pipeline: &wgpu::RenderPipeline,
     bg0: &wgpu::BindGroup,
+    bg0_offset: u32,
     bg1: Option<&wgpu::BindGroup>,
+    timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'_>>,
 ) {
     let mut r = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
         label: Some(label),
@@ -139,11 +182,11 @@ pub(crate) fn blit(
             },
         })],
         depth_stencil_attachment: None,
-        timestamp_writes: None,
+        timestamp_writes,
         occlusion_query_set: None,
     });
     r.set_pipeline(pipeline);
-    r.set_bind_group(0, bg0, &[]);
+    r.set_bind_group(0, bg0, &[bg0_offset]);
     if let Some(g1) = bg1 {
         r.set_bind_group(1, g1, &[]);
     }
@@ -151,13 +194,16 @@ pub(crate) fn blit(
     drop(r);
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn rebuild_bind_groups(
     device: &wgpu::Device,
     post: &super::post::PostResources,
     linear_sampler: &wgpu::Sampler,
     hdr_view: &wgpu::TextureView,
+    fade_view: &wgpu::TextureView,
     bloom_a_view: &wgpu::TextureView,
     bloom_b_view: &wgpu::TextureView,
+    exposure_state: &wgpu::Buffer,
 ) -> (
     wgpu::BindGroup, // bg_hdr
     wgpu::BindGroup, // bg_from_bloom_a
@@ -165,6 +211,11 @@ pub(crate) fn rebuild_bind_groups(
     wgpu::BindGroup, // bg_bloom_a_only
     wgpu::BindGroup, // bg_bloom_b_only
 ) {
+    let uniform_slot = wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+        buffer: &post.uniform_buffer,
+        offset: 0,
+        size: wgpu::BufferSize::new(std::mem::size_of::<super::PostUniforms>() as u64),
+    });
     let bg_hdr = device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: Some("bg_hdr"),
         layout: &post.bgl0,
@@ -179,7 +230,7 @@ pub(crate) fn rebuild_bind_groups(
             },
             wgpu::BindGroupEntry {
                 binding: 2,
-                resource: post.uniform_buffer.as_entire_binding(),
+                resource: uniform_slot.clone(),
             },
         ],
     });
@@ -197,7 +248,7 @@ pub(crate) fn rebuild_bind_groups(
             },
             wgpu::BindGroupEntry {
                 binding: 2,
-                resource: post.uniform_buffer.as_entire_binding(),
+                resource: uniform_slot.clone(),
             },
         ],
     });
@@ -215,7 +266,7 @@ pub(crate) fn rebuild_bind_groups(
             },
             wgpu::BindGroupEntry {
                 binding: 2,
-                resource: post.uniform_buffer.as_entire_binding(),
+                resource: uniform_slot,
             },
         ],
     });
@@ -231,6 +282,14 @@ pub(crate) fn rebuild_bind_groups(
                 binding: 1,
                 resource: wgpu::BindingResource::Sampler(linear_sampler),
             },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(fade_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: exposure_state.as_entire_binding(),
+            },
         ],
     });
     let bg_bloom_b_only = device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -245,6 +304,14 @@ pub(crate) fn rebuild_bind_groups(
                 binding: 1,
                 resource: wgpu::BindingResource::Sampler(linear_sampler),
             },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(fade_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: exposure_state.as_entire_binding(),
+            },
         ],
     });
     (
@@ -256,21 +323,58 @@ pub(crate) fn rebuild_bind_groups(
     )
 }
 
-pub(crate) fn write_post_uniforms(
-    queue: &wgpu::Queue,
-    buffer: &wgpu::Buffer,
+/// Uniforms shared by every post pass this frame; blur passes set their own
+/// `blur_dir` before writing their slot.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn post_uniforms(
     resolution: [f32; 2],
     time: f32,
     ambient: f32,
-    blur_dir: [f32; 2],
-) {
-    let post = super::PostUniforms {
+    theme: &Theme,
+    bloom: &Bloom,
+    exposure: &Exposure,
+    tonemap: Tonemap,
+    fade: f32,
+    clock: [f32; 4],
+    grit: f32,
+    mirror_segments: u32,
+    mirror_center: [f32; 2],
+    safe_visuals: bool,
+) -> super::PostUniforms {
+    super::PostUniforms {
         resolution,
         time,
         ambient,
-        blur_dir,
-        bloom_strength: constants::BLOOM_STRENGTH,
-        threshold: constants::BLOOM_THRESHOLD,
-    };
-    queue.write_buffer(buffer, 0, bytemuck::bytes_of(&post));
+        blur_dir: [0.0, 0.0],
+        bloom_strength: bloom.strength,
+        threshold: bloom.threshold,
+        bloom_tint: rgba(theme.bloom_tint, theme.saturation),
+        fade,
+        exposure: exposure.gain(),
+        auto_exposure: if exposure.auto { 1.0 } else { 0.0 },
+        tonemap: tonemap.shader_index(),
+        clock,
+        grit,
+        mirror_segments,
+        mirror_center,
+        safe_peak: if safe_visuals { SAFE_PEAK_LUMA } else { 0.0 },
+        flash_limit: if safe_visuals { 1.0 } else { 0.0 },
+        _pad: [0.0; 2],
+    }
+}
+
+/// Write one pass's uniforms into its slot; the pass then binds group 0 at
+/// `post.slot_offset(slot)`. Each pass needs its own slot because every
+/// write lands before the frame's passes run.
+pub(crate) fn write_post_slot(
+    queue: &wgpu::Queue,
+    post: &PostResources,
+    slot: u32,
+    uniforms: &super::PostUniforms,
+) {
+    queue.write_buffer(
+        &post.uniform_buffer,
+        post.slot_offset(slot) as u64,
+        bytemuck::bytes_of(uniforms),
+    );
 }
@@ -0,0 +1,133 @@
+use super::helpers;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct SceneUniforms {
+    pub(crate) view_proj: [[f32; 4]; 4],
+    pub(crate) cam_right: [f32; 4],
+    pub(crate) cam_up: [f32; 4],
+    pub(crate) cam_eye: [f32; 4],
+    pub(crate) voices: [[f32; 4]; 3],
+    pub(crate) voice_colors: [[f32; 4]; 3],
+    pub(crate) voice_styles: [[f32; 4]; 3],
+    pub(crate) time: f32,
+    pub(crate) ambient: f32,
+    pub(crate) sphere_radius: f32,
+    pub(crate) _pad: f32,
+}
+
+pub(crate) struct SceneResources {
+    /// Solid voice spheres, written to the depth buffer
+    pub(crate) sphere_pipeline: wgpu::RenderPipeline,
+    /// Glow billboards around them, hidden where a sphere is in front
+    pub(crate) pipeline: wgpu::RenderPipeline,
+    pub(crate) uniform_buffer: wgpu::Buffer,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+/// Instanced voice spheres, drawn solid with depth, and glow billboards
+/// blended additively over whatever is already in the HDR target (clear
+/// colour or the waves layer).
+pub(crate) fn create_scene_resources(
+    device: &wgpu::Device,
+    hdr_format: wgpu::TextureFormat,
+    samples: u32,
+    source: &str,
+) -> SceneResources {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("scene_shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("scene_bgl"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+    let pl = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("scene_pl"),
+        bind_group_layouts: &[&bgl],
+        push_constant_ranges: &[],
+    });
+    let additive = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    };
+    let make_pipeline = |label: &str,
+                         vs: &str,
+                         fs: &str,
+                         blend: Option<wgpu::BlendState>,
+                         depth: Option<wgpu::DepthStencilState>| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&pl),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some(vs),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: depth,
+            multisample: helpers::multisample_state(samples),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some(fs),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: hdr_format,
+                    blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            cache: None,
+            multiview: None,
+        })
+    };
+    let sphere_pipeline = make_pipeline(
+        "scene_sphere_pipeline",
+        "vs_sphere",
+        "fs_sphere",
+        None,
+        helpers::depth_state(true, wgpu::CompareFunction::Less),
+    );
+    let pipeline = make_pipeline(
+        "scene_pipeline",
+        "vs_scene",
+        "fs_scene",
+        Some(wgpu::BlendState {
+            color: additive,
+            alpha: additive,
+        }),
+        helpers::depth_state(false, wgpu::CompareFunction::Less),
+    );
+    let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("scene_uniforms"),
+        size: std::mem::size_of::<SceneUniforms>() as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("scene_bg"),
+        layout: &bgl,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: uniform_buffer.as_entire_binding(),
+        }],
+    });
+
+    SceneResources {
+        sphere_pipeline,
+        pipeline,
+        uniform_buffer,
+        bind_group,
+    }
+}
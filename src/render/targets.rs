@@ -3,42 +3,49 @@ use wgpu;
 
 /// Offscreen color targets for the render pipeline.
 ///
-/// Contains a full-resolution HDR scene color and two half-resolution bloom
-/// ping-pong textures. Views are pre-created for convenience.
+/// Contains an HDR scene color, a target for the visualizer fading out during
+/// a switch and the depth buffer they share, all at the render scale's
+/// resolution, and two half-resolution bloom ping-pong textures. Views are
+/// pre-created for convenience.
 ///
 /// - `hdr_*` hold the main scene color in Rgba16Float for post-processing.
+/// - `fade_*` hold the outgoing visualizer, mixed in by the composite.
+/// - `depth_*` let the voice spheres hide what is behind them, and give the
+///   lens pass each pixel's distance.
+/// - `msaa_*`, with MSAA on, is the multisampled colour the layers draw into
+///   (resolved into `hdr_*` or `fade_*`); the depth buffer is multisampled to
+///   match.
 /// - `bloom_*` are half-res buffers used for bright-pass and blur.
 pub(crate) struct RenderTargets {
+    #[allow(dead_code)] // held alongside its view
     pub(crate) hdr_tex: wgpu::Texture,
     pub(crate) hdr_view: wgpu::TextureView,
+    #[allow(dead_code)] // held alongside its view
+    pub(crate) fade_tex: wgpu::Texture,
+    pub(crate) fade_view: wgpu::TextureView,
+    #[allow(dead_code)] // held alongside its view
+    pub(crate) depth_tex: wgpu::Texture,
+    pub(crate) depth_view: wgpu::TextureView,
+    #[allow(dead_code)] // held alongside its view
+    pub(crate) msaa_tex: Option<wgpu::Texture>,
+    pub(crate) msaa_view: Option<wgpu::TextureView>,
+    #[allow(dead_code)] // held alongside its view
     pub(crate) bloom_a: wgpu::Texture,
     pub(crate) bloom_a_view: wgpu::TextureView,
+    #[allow(dead_code)] // held alongside its view
     pub(crate) bloom_b: wgpu::Texture,
     pub(crate) bloom_b_view: wgpu::TextureView,
+    /// Size of the HDR and fade targets
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// Samples per pixel of the layers' colour and depth
+    pub(crate) samples: u32,
 }
 
 impl RenderTargets {
-    pub(crate) fn new(
-        hdr_tex: wgpu::Texture,
-        hdr_view: wgpu::TextureView,
-        bloom_a: wgpu::Texture,
-        bloom_a_view: wgpu::TextureView,
-        bloom_b: wgpu::Texture,
-        bloom_b_view: wgpu::TextureView,
-    ) -> Self {
-        Self {
-            hdr_tex,
-            hdr_view,
-            bloom_a,
-            bloom_a_view,
-            bloom_b,
-            bloom_b_view,
-        }
-    }
-
-    pub(crate) fn recreate(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+    pub(crate) fn new(device: &wgpu::Device, width: u32, height: u32, samples: u32) -> Self {
         let hdr_format = wgpu::TextureFormat::Rgba16Float;
-        (self.hdr_tex, self.hdr_view) = helpers::create_color_texture(
+        let (hdr_tex, hdr_view) = helpers::create_color_texture(
             device,
             "hdr_tex",
             width,
@@ -46,10 +53,41 @@ impl RenderTargets {
             hdr_format,
             wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
         );
+        let (fade_tex, fade_view) = helpers::create_color_texture(
+            device,
+            "fade_tex",
+            width,
+            height,
+            hdr_format,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let (depth_tex, depth_view) = helpers::create_multisampled_texture(
+            device,
+            "depth_tex",
+            width,
+            height,
+            helpers::DEPTH_FORMAT,
+            samples,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let (msaa_tex, msaa_view) = if samples > 1 {
+            let (tex, view) = helpers::create_multisampled_texture(
+                device,
+                "msaa_tex",
+                width,
+                height,
+                hdr_format,
+                samples,
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            );
+            (Some(tex), Some(view))
+        } else {
+            (None, None)
+        };
         let bw = (width.max(1) / 2).max(1);
         let bh = (height.max(1) / 2).max(1);
         let bloom_format = wgpu::TextureFormat::Rgba16Float;
-        (self.bloom_a, self.bloom_a_view) = helpers::create_color_texture(
+        let (bloom_a, bloom_a_view) = helpers::create_color_texture(
             device,
             "bloom_a",
             bw,
@@ -57,7 +95,7 @@ impl RenderTargets {
             bloom_format,
             wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
         );
-        (self.bloom_b, self.bloom_b_view) = helpers::create_color_texture(
+        let (bloom_b, bloom_b_view) = helpers::create_color_texture(
             device,
             "bloom_b",
             bw,
@@ -65,5 +103,54 @@ impl RenderTargets {
             bloom_format,
             wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
         );
+        Self {
+            hdr_tex,
+            hdr_view,
+            fade_tex,
+            fade_view,
+            depth_tex,
+            depth_view,
+            msaa_tex,
+            msaa_view,
+            bloom_a,
+            bloom_a_view,
+            bloom_b,
+            bloom_b_view,
+            width,
+            height,
+            samples,
+        }
+    }
+
+    pub(crate) fn recreate(
+        &mut self,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        samples: u32,
+    ) {
+        *self = Self::new(device, width, height, samples);
+    }
+
+    /// Colour attachment for a pass drawing the layers into `resolved` (the
+    /// HDR or fade target): directly, or through the multisampled colour
+    /// with MSAA on.
+    pub(crate) fn layer_attachment<'v>(
+        &'v self,
+        resolved: &'v wgpu::TextureView,
+        clear: wgpu::Color,
+    ) -> wgpu::RenderPassColorAttachment<'v> {
+        let (view, resolve_target, store) = match &self.msaa_view {
+            Some(msaa) => (msaa, Some(resolved), wgpu::StoreOp::Discard),
+            None => (resolved, None, wgpu::StoreOp::Store),
+        };
+        wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(clear),
+                store,
+            },
+        }
     }
 }
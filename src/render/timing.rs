@@ -0,0 +1,148 @@
+use crate::core::profiler::GpuTimings;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+/// Timed stages: scene, bloom and composite, each a begin/end query pair.
+pub(crate) const SPAN_SCENE: u32 = 0;
+pub(crate) const SPAN_BLOOM: u32 = 1;
+pub(crate) const SPAN_COMPOSITE: u32 = 2;
+const SPANS: u32 = 3;
+const QUERIES: u32 = SPANS * 2;
+const RESULTS_SIZE: u64 = QUERIES as u64 * 8;
+
+// Readback states, set by the map callback
+const MAP_PENDING: u8 = 0;
+const MAP_READY: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+/// GPU pass timings from timestamp queries, where the device supports them.
+///
+/// The timestamps are resolved into a buffer copied out for mapping; while a
+/// readback is in flight, frames go untimed, so the figures trail the frame
+/// by a few frames and nothing waits on the GPU.
+pub(crate) struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    period_ns: f32,
+    map_state: Arc<AtomicU8>,
+    in_flight: bool,
+    // Whether this frame's passes write timestamps
+    timing: bool,
+    latest: Option<GpuTimings>,
+}
+
+impl GpuTimer {
+    pub(crate) fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("pass_timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: QUERIES,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("timestamp_resolve"),
+            size: RESULTS_SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("timestamp_readback"),
+            size: RESULTS_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period_ns: queue.get_timestamp_period(),
+            map_state: Arc::new(AtomicU8::new(MAP_PENDING)),
+            in_flight: false,
+            timing: false,
+            latest: None,
+        }
+    }
+
+    /// Collect a finished readback, and time this frame if `enabled` and no
+    /// readback is still in flight.
+    pub(crate) fn begin_frame(&mut self, enabled: bool) {
+        let state = if self.in_flight {
+            self.map_state.swap(MAP_PENDING, Ordering::Acquire)
+        } else {
+            MAP_PENDING
+        };
+        if state == MAP_FAILED {
+            self.in_flight = false;
+        } else if state == MAP_READY {
+            {
+                let data = self.readback_buffer.slice(..).get_mapped_range();
+                let ticks: &[u64] = bytemuck::cast_slice(&data);
+                let span_ms = |span: u32| {
+                    let (begin, end) = (ticks[span as usize * 2], ticks[span as usize * 2 + 1]);
+                    end.saturating_sub(begin) as f32 * self.period_ns / 1.0e6
+                };
+                self.latest = Some(GpuTimings {
+                    scene_ms: span_ms(SPAN_SCENE),
+                    bloom_ms: span_ms(SPAN_BLOOM),
+                    composite_ms: span_ms(SPAN_COMPOSITE),
+                });
+            }
+            self.readback_buffer.unmap();
+            self.in_flight = false;
+        }
+        self.timing = enabled && !self.in_flight;
+    }
+
+    /// Timestamp writes for a pass starting and/or ending `span`, when this
+    /// frame is timed.
+    pub(crate) fn writes(
+        &self,
+        span: u32,
+        begins: bool,
+        ends: bool,
+    ) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.timing.then(|| wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: begins.then_some(span * 2),
+            end_of_pass_write_index: ends.then_some(span * 2 + 1),
+        })
+    }
+
+    /// Resolve this frame's timestamps into the readback buffer.
+    pub(crate) fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.timing {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..QUERIES, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            RESULTS_SIZE,
+        );
+    }
+
+    /// Start reading back the frame just submitted.
+    pub(crate) fn after_submit(&mut self) {
+        if !self.timing {
+            return;
+        }
+        self.in_flight = true;
+        let map_state = self.map_state.clone();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let state = if result.is_ok() {
+                    MAP_READY
+                } else {
+                    MAP_FAILED
+                };
+                map_state.store(state, Ordering::Release);
+            });
+    }
+
+    pub(crate) fn latest(&self) -> Option<GpuTimings> {
+        self.latest
+    }
+}
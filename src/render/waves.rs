@@ -1,9 +1,17 @@
+use super::helpers;
+use crate::core::ripple::MAX_RIPPLES;
+use crate::core::spectrum::{SCOPE_POINTS, SPECTRUM_BANDS};
+use crate::core::strum::STREAK_POINTS;
+use crate::core::trails::TRAIL_POINTS;
+use crate::core::visualizer::Visualizer;
 use wgpu;
 
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct VoicePacked {
     pub(crate) pos_pulse: [f32; 4],
+    /// Glow, wave frequency, shape, trail seconds (see `VoiceStyle::pack`)
+    pub(crate) style: [f32; 4],
 }
 
 #[repr(C)]
@@ -16,86 +24,170 @@ pub(crate) struct WavesUniforms {
     pub(crate) swirl_uv: [f32; 2],
     pub(crate) swirl_strength: f32,
     pub(crate) swirl_active: f32,
-    pub(crate) ripple_uv: [f32; 2],
-    pub(crate) ripple_t0: f32,
-    pub(crate) ripple_amp: f32,
+    // Analyser layers, 1.0 when drawn
+    pub(crate) spectrum_on: f32,
+    pub(crate) scope_on: f32,
+    // 1.0 while any voice has a motion trail
+    pub(crate) trails_on: f32,
+    // Starfield brightness (0 = off)
+    pub(crate) stars: f32,
+    // Theme colours (rgb used)
+    pub(crate) bg_deep: [f32; 4],
+    pub(crate) bg_lift: [f32; 4],
+    pub(crate) voice_colors: [[f32; 4]; 3],
+    pub(crate) accent: [f32; 4],
+    // Motion trails: `TRAIL_POINTS` per voice, each xyz + brightness
+    pub(crate) trails: [[f32; 4]; TRAIL_SLOTS],
+    // Click/tap ripples (see `Ripples::pack`)
+    pub(crate) ripples: [[f32; 4]; MAX_RIPPLES],
+    // Musical clock (see `ClockPulse::pack`)
+    pub(crate) clock: [f32; 4],
+    // Camera offset for the star layers' parallax (see `star_shift`)
+    pub(crate) star_shift: [f32; 4],
+    // Strum streak (see `Streak::pack`)
+    pub(crate) streak: [[f32; 4]; STREAK_POINTS],
 }
 
+/// Analyser data for the spectrum and scope layers, as one storage buffer:
+/// `SPECTRUM_BANDS` bar levels (0..1) then `SCOPE_POINTS` samples (-1..1).
+pub(crate) const AUDIO_BINS_LEN: usize = SPECTRUM_BANDS + SCOPE_POINTS;
+
+/// Trail points for all three voices (`trails` in waves.wgsl).
+pub(crate) const TRAIL_SLOTS: usize = 3 * TRAIL_POINTS;
+
+/// Shared bindings for every visualizer; each mode's pipeline is built the
+/// first time it is shown.
 pub(crate) struct WavesResources {
-    pub(crate) pipeline: wgpu::RenderPipeline,
+    shader: wgpu::ShaderModule,
+    layout: wgpu::PipelineLayout,
+    hdr_format: wgpu::TextureFormat,
+    samples: u32,
+    pipelines: [Option<wgpu::RenderPipeline>; Visualizer::ALL.len()],
     pub(crate) uniform_buffer: wgpu::Buffer,
+    pub(crate) bins_buffer: wgpu::Buffer,
     pub(crate) bind_group: wgpu::BindGroup,
 }
 
 pub(crate) fn create_waves_resources(
     device: &wgpu::Device,
     hdr_format: wgpu::TextureFormat,
+    samples: u32,
+    source: &str,
 ) -> WavesResources {
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
         label: Some("waves_shader"),
-        source: wgpu::ShaderSource::Wgsl(crate::core::WAVES_WGSL.into()),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
     });
     let bgl = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         label: Some("waves_bgl"),
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
             },
-            count: None,
-        }],
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
     });
     let pl = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("waves_pl"),
         bind_group_layouts: &[&bgl],
         push_constant_ranges: &[],
     });
-    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("waves_pipeline"),
-        layout: Some(&pl),
-        vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: Some("vs_fullscreen"),
-            buffers: &[],
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        },
-        primitive: wgpu::PrimitiveState::default(),
-        depth_stencil: None,
-        multisample: wgpu::MultisampleState::default(),
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: Some("fs_waves"),
-            targets: &[Some(wgpu::ColorTargetState {
-                format: hdr_format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        }),
-        cache: None,
-        multiview: None,
-    });
     let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
         label: Some("waves_uniforms"),
         size: std::mem::size_of::<WavesUniforms>() as u64,
         usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         mapped_at_creation: false,
     });
+    let bins_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("waves_audio_bins"),
+        size: (AUDIO_BINS_LEN * std::mem::size_of::<f32>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
     let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
         label: Some("waves_bg"),
         layout: &bgl,
-        entries: &[wgpu::BindGroupEntry {
-            binding: 0,
-            resource: uniform_buffer.as_entire_binding(),
-        }],
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: bins_buffer.as_entire_binding(),
+            },
+        ],
     });
 
     WavesResources {
-        pipeline,
+        shader,
+        layout: pl,
+        hdr_format,
+        samples,
+        pipelines: Default::default(),
         uniform_buffer,
+        bins_buffer,
         bind_group,
     }
 }
+
+impl WavesResources {
+    pub(crate) fn ensure_pipeline(&mut self, device: &wgpu::Device, visualizer: Visualizer) {
+        if self.pipelines[visualizer.index()].is_none() {
+            self.pipelines[visualizer.index()] = Some(self.create_pipeline(device, visualizer));
+        }
+    }
+
+    /// The pipeline for `visualizer`, once `ensure_pipeline` has built it.
+    pub(crate) fn pipeline(&self, visualizer: Visualizer) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines[visualizer.index()].as_ref()
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        visualizer: Visualizer,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(visualizer.id()),
+            layout: Some(&self.layout),
+            vertex: wgpu::VertexState {
+                module: &self.shader,
+                entry_point: Some("vs_fullscreen"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: helpers::depth_state(false, wgpu::CompareFunction::Always),
+            multisample: helpers::multisample_state(self.samples),
+            fragment: Some(wgpu::FragmentState {
+                module: &self.shader,
+                entry_point: Some(visualizer.entry_point()),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.hdr_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            cache: None,
+            multiview: None,
+        })
+    }
+}
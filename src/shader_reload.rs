@@ -0,0 +1,226 @@
+use crate::core::custom_shader::{check_custom_shader, custom_line, with_custom};
+use crate::core::shader_reload::{compile_message, Shader, SHADER_POLL_SEC};
+use crate::overlay;
+use crate::render;
+use std::cell::{Cell, RefCell};
+use std::future::Future;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys as web;
+
+/// Shaders swapped into the renderer at run time. In development mode
+/// (`?shaders=on`) the WGSL files are fetched over HTTP every
+/// `SHADER_POLL_SEC`; any that have changed, and any custom visualizer code
+/// submitted from the overlay, are compiled and, if they compile, swapped in.
+/// Compiler errors are shown over the canvas and the last good pipelines keep
+/// drawing.
+pub struct ShaderReload {
+    // Where to fetch the files from, in development mode
+    base: Option<String>,
+    since_poll: f32,
+    // Submitted from the page, compiled on the next frame
+    submitted: Rc<RefCell<Vec<(Shader, String)>>>,
+    in_flight: Rc<Cell<bool>>,
+    // Text last fetched for each shader, so only edits are compiled
+    fetched: Rc<RefCell<Vec<String>>>,
+    // Compiled cleanly, waiting for the renderer
+    ready: Rc<RefCell<Vec<(Shader, String)>>>,
+    // Latest error for each shader, if it has one
+    errors: Rc<RefCell<Vec<Option<String>>>>,
+}
+
+impl ShaderReload {
+    pub fn new(base: Option<String>) -> ShaderReload {
+        if let Some(base) = &base {
+            log::info!("[shaders] reloading from {base}");
+        }
+        ShaderReload {
+            base,
+            since_poll: 0.0,
+            submitted: Rc::new(RefCell::new(Vec::new())),
+            in_flight: Rc::new(Cell::new(false)),
+            fetched: Rc::new(RefCell::new(
+                Shader::ALL
+                    .iter()
+                    .map(|&s| render::built_in_shader(s).to_string())
+                    .collect(),
+            )),
+            ready: Rc::new(RefCell::new(Vec::new())),
+            errors: Rc::new(RefCell::new(vec![None; Shader::ALL.len()])),
+        }
+    }
+
+    /// Where the page submits shaders to be compiled and swapped in.
+    pub fn queue(&self) -> Rc<RefCell<Vec<(Shader, String)>>> {
+        self.submitted.clone()
+    }
+
+    /// Compile what has been submitted and, once `SHADER_POLL_SEC` has passed
+    /// since the last round finished, fetch the files again, compiling any
+    /// that changed. `g` supplies the device and the sources in use, which the
+    /// waves and custom code are compiled against.
+    pub fn poll(&mut self, dt_sec: f32, g: &render::GpuState) {
+        if self.in_flight.get() {
+            return;
+        }
+        self.since_poll += dt_sec.max(0.0);
+        let base = self
+            .base
+            .clone()
+            .filter(|_| self.since_poll >= SHADER_POLL_SEC);
+        let submitted = std::mem::take(&mut *self.submitted.borrow_mut());
+        if base.is_none() && submitted.is_empty() {
+            return;
+        }
+        if base.is_some() {
+            self.since_poll = 0.0;
+        }
+        self.in_flight.set(true);
+        let device = g.device().clone();
+        let mut waves = g.shader(Shader::Waves).to_string();
+        let mut custom = g.shader(Shader::Custom).to_string();
+        let in_flight = self.in_flight.clone();
+        let fetched = self.fetched.clone();
+        let ready = self.ready.clone();
+        let errors = self.errors.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let mut candidates = submitted;
+            if let Some(base) = base {
+                for shader in Shader::ALL {
+                    let url = format!("{base}{}", shader.file());
+                    match fetch_text(&url).await {
+                        Ok(source) if fetched.borrow()[shader.index()] != source => {
+                            fetched.borrow_mut()[shader.index()] = source.clone();
+                            candidates.push((shader, source));
+                        }
+                        Ok(_) => {}
+                        Err(e) => log::warn!("[shaders] fetch {url} failed: {:?}", e),
+                    }
+                }
+            }
+            for (shader, source) in candidates {
+                let message = compile_errors(&device, shader, &source, &waves, &custom).await;
+                if message.is_none() {
+                    // Later candidates compile against this one
+                    match shader {
+                        Shader::Waves => waves = source.clone(),
+                        Shader::Custom => custom = source.clone(),
+                        _ => {}
+                    }
+                    ready.borrow_mut().push((shader, source));
+                }
+                show_error(&errors, shader, message);
+            }
+            in_flight.set(false);
+        });
+    }
+
+    /// Shaders that compiled since the last call, oldest first.
+    pub fn take_ready(&self) -> Vec<(Shader, String)> {
+        std::mem::take(&mut self.ready.borrow_mut())
+    }
+
+    /// Show the outcome of rebuilding the pipelines from `shader` (see
+    /// `GpuState::reload_shader`).
+    pub fn watch_rebuild(
+        &self,
+        shader: Shader,
+        validation: impl Future<Output = Option<wgpu::Error>> + 'static,
+    ) {
+        let errors = self.errors.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Some(e) = validation.await {
+                show_error(
+                    &errors,
+                    shader,
+                    Some(compile_message(shader, None, &e.to_string())),
+                );
+            }
+        });
+    }
+}
+
+/// Compile `source` as `shader`'s module would be built and gather its
+/// errors, one per line. The waves and custom code share a module, so each is
+/// compiled with the other in use; errors in the custom code are numbered
+/// from its own first line.
+async fn compile_errors(
+    device: &wgpu::Device,
+    shader: Shader,
+    source: &str,
+    waves: &str,
+    custom: &str,
+) -> Option<String> {
+    let module_source = match shader {
+        Shader::Waves => with_custom(source, custom),
+        Shader::Custom => {
+            if let Err(e) = check_custom_shader(source) {
+                return Some(compile_message(shader, None, &e.to_string()));
+            }
+            with_custom(waves, source)
+        }
+        _ => source.to_string(),
+    };
+    // A module that fails to compile is also a validation error; the
+    // compilation info already says why
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(shader.file()),
+        source: wgpu::ShaderSource::Wgsl(module_source.into()),
+    });
+    let scope = device.pop_error_scope();
+    let info = module.get_compilation_info().await;
+    _ = scope.await;
+    let lines: Vec<String> = info
+        .messages
+        .iter()
+        .filter(|m| m.message_type == wgpu::CompilationMessageType::Error)
+        .map(|m| {
+            let location = m.location.and_then(|l| {
+                let line = match shader {
+                    Shader::Custom => custom_line(waves, source, l.line_number)?,
+                    _ => l.line_number,
+                };
+                Some((line, l.line_position))
+            });
+            compile_message(shader, location, &m.message)
+        })
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+fn show_error(errors: &RefCell<Vec<Option<String>>>, shader: Shader, message: Option<String>) {
+    match &message {
+        Some(m) => log::error!("[shaders] {m}"),
+        None => log::info!("[shaders] {} compiled", shader.file()),
+    }
+    errors.borrow_mut()[shader.index()] = message;
+    let text = errors
+        .borrow()
+        .iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n");
+    if let Some(document) = web::window().and_then(|w| w.document()) {
+        overlay::update_shader_errors(&document, &text);
+    }
+}
+
+/// GET `url`, bypassing the HTTP cache so edits show up straight away.
+async fn fetch_text(url: &str) -> Result<String, wasm_bindgen::JsValue> {
+    let window = web::window().ok_or("no window")?;
+    let init = web::RequestInit::new();
+    init.set_cache(web::RequestCache::NoStore);
+    let response: web::Response = JsFuture::from(window.fetch_with_str_and_init(url, &init))
+        .await?
+        .dyn_into()?;
+    if !response.ok() {
+        return Err(format!("HTTP {}", response.status()).into());
+    }
+    JsFuture::from(response.text()?)
+        .await?
+        .as_string()
+        .ok_or_else(|| "not text".into())
+}
@@ -0,0 +1,81 @@
+use crate::core::sketch::{css_rgba, SketchDot};
+use crate::core::theme::Theme;
+use wasm_bindgen::JsCast;
+use web_sys as web;
+
+/// Canvas2D stand-in for the renderer when WebGPU is unavailable: the voices
+/// as coloured circles over the theme's background, with their trails.
+pub struct Sketch {
+    canvas: web::HtmlCanvasElement,
+    ctx: web::CanvasRenderingContext2d,
+}
+
+impl Sketch {
+    /// Draw on the app canvas, or, if a failed WebGPU attempt has already
+    /// claimed it, on a canvas laid over it.
+    pub fn new(app_canvas: &web::HtmlCanvasElement) -> Option<Sketch> {
+        if let Some(ctx) = context_2d(app_canvas) {
+            return Some(Sketch {
+                canvas: app_canvas.clone(),
+                ctx,
+            });
+        }
+        let document = app_canvas.owner_document()?;
+        let canvas: web::HtmlCanvasElement =
+            document.create_element("canvas").ok()?.dyn_into().ok()?;
+        canvas.set_id("sketch-canvas");
+        _ = canvas.set_attribute(
+            "style",
+            "position: fixed; inset: 0; width: 100vw; height: 100vh; pointer-events: none",
+        );
+        app_canvas.after_with_node_1(&canvas).ok()?;
+        let ctx = context_2d(&canvas)?;
+        log::info!("[sketch] drawing on an overlay canvas");
+        Some(Sketch { canvas, ctx })
+    }
+
+    /// Paint one frame; `width`×`height` is the app canvas's backing size.
+    pub fn draw(&self, theme: &Theme, dots: &[SketchDot], width: u32, height: u32) {
+        if self.canvas.width() != width || self.canvas.height() != height {
+            self.canvas.set_width(width);
+            self.canvas.set_height(height);
+        }
+        let [deep, lifted] = theme.background;
+        let gradient = self
+            .ctx
+            .create_radial_gradient(
+                0.5 * width as f64,
+                0.5 * height as f64,
+                0.0,
+                0.5 * width as f64,
+                0.5 * height as f64,
+                0.75 * width.max(height) as f64,
+            )
+            .ok();
+        match gradient {
+            Some(g) => {
+                _ = g.add_color_stop(0.0, &css_rgba(lifted, 1.0));
+                _ = g.add_color_stop(1.0, &css_rgba(deep, 1.0));
+                self.ctx.set_fill_style_canvas_gradient(&g);
+            }
+            None => self.ctx.set_fill_style_str(&css_rgba(deep, 1.0)),
+        }
+        self.ctx.fill_rect(0.0, 0.0, width as f64, height as f64);
+        for dot in dots {
+            self.ctx.begin_path();
+            _ = self.ctx.arc(
+                dot.x as f64,
+                dot.y as f64,
+                dot.radius.max(0.5) as f64,
+                0.0,
+                std::f64::consts::TAU,
+            );
+            self.ctx.set_fill_style_str(&dot.fill);
+            self.ctx.fill();
+        }
+    }
+}
+
+fn context_2d(canvas: &web::HtmlCanvasElement) -> Option<web::CanvasRenderingContext2d> {
+    canvas.get_context("2d").ok()??.dyn_into().ok()
+}
@@ -0,0 +1,143 @@
+use crate::core::video::{FrameSequence, VideoFrame, VIDEO_MIME_TYPES};
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys as web;
+
+/// Delay before the soundtrack and the first frame, so the recorder is
+/// running when they arrive.
+const VIDEO_LEAD_IN_SEC: f64 = 0.2;
+
+/// A video export in progress.
+///
+/// The piece's frames are drawn at a fixed timestep, paced by the audio
+/// clock, and each is handed to the recorder as it is drawn; the offline
+/// bounce plays into the same recording (and the speakers) as the
+/// soundtrack. The recorder encodes in real time, so an export takes as
+/// long as the piece.
+pub struct VideoExport {
+    sequence: FrameSequence,
+    start_sec: f64,
+    recorder: web::MediaRecorder,
+    track: web::CanvasCaptureMediaStreamTrack,
+    soundtrack: web::AudioScheduledSourceNode,
+    restarted: bool,
+}
+
+impl VideoExport {
+    /// Start recording `canvas` with `samples` (interleaved stereo from the
+    /// bounce) as the soundtrack; `on_done` gets the finished file once the
+    /// recorder stops.
+    pub fn start(
+        audio_ctx: &web::AudioContext,
+        canvas: &web::HtmlCanvasElement,
+        sequence: FrameSequence,
+        samples: &[f32],
+        on_done: impl Fn(web::Blob) + 'static,
+    ) -> Result<Self, JsValue> {
+        let mime = VIDEO_MIME_TYPES
+            .into_iter()
+            .find(|m| web::MediaRecorder::is_type_supported(m))
+            .ok_or_else(|| JsValue::from_str("video: no supported recorder format"))?;
+
+        // Frames are pushed one at a time with `request_frame`
+        let stream = canvas.capture_stream_with_frame_request_rate(0.0)?;
+        let track: web::CanvasCaptureMediaStreamTrack =
+            stream.get_video_tracks().get(0).dyn_into()?;
+
+        let frames = (samples.len() / 2).max(1) as u32;
+        let buffer = audio_ctx.create_buffer(2, frames, audio_ctx.sample_rate())?;
+        for ch in 0..2 {
+            let channel: Vec<f32> = samples.iter().skip(ch).step_by(2).copied().collect();
+            buffer.copy_to_channel(&channel, ch as i32)?;
+        }
+        let soundtrack = audio_ctx.create_buffer_source()?;
+        soundtrack.set_buffer(Some(&buffer));
+        let sink = audio_ctx.create_media_stream_destination()?;
+        soundtrack.connect_with_audio_node(&sink)?;
+        soundtrack.connect_with_audio_node(&audio_ctx.destination())?;
+        for audio in sink.stream().get_audio_tracks().iter() {
+            stream.add_track(&audio.dyn_into()?);
+        }
+
+        let opts = web::MediaRecorderOptions::new();
+        opts.set_mime_type(mime);
+        let recorder =
+            web::MediaRecorder::new_with_media_stream_and_media_recorder_options(&stream, &opts)?;
+        let chunks = Rc::new(RefCell::new(js_sys::Array::new()));
+        {
+            let chunks = chunks.clone();
+            let on_data = Closure::wrap(Box::new(move |ev: web::BlobEvent| {
+                if let Some(blob) = ev.data().filter(|b| b.size() > 0.0) {
+                    chunks.borrow().push(&blob);
+                }
+            }) as Box<dyn FnMut(_)>);
+            recorder.set_ondataavailable(Some(on_data.as_ref().unchecked_ref()));
+            on_data.forget();
+        }
+        {
+            let on_stop = Closure::wrap(Box::new(move || {
+                let opts = web::BlobPropertyBag::new();
+                opts.set_type(mime);
+                let parts = chunks.replace(js_sys::Array::new());
+                match web::Blob::new_with_blob_sequence_and_options(&parts, &opts) {
+                    Ok(blob) => on_done(blob),
+                    Err(e) => log::warn!("[video] {:?}", e),
+                }
+            }) as Box<dyn FnMut()>);
+            recorder.set_onstop(Some(on_stop.as_ref().unchecked_ref()));
+            on_stop.forget();
+        }
+        recorder.start()?;
+
+        let start_sec = audio_ctx.current_time() + VIDEO_LEAD_IN_SEC;
+        soundtrack.start_with_when(start_sec)?;
+        log::info!(
+            "[video] {} frames at {} fps, {}",
+            sequence.frame_count(),
+            sequence.fps(),
+            mime
+        );
+        Ok(Self {
+            sequence,
+            start_sec,
+            recorder,
+            track,
+            soundtrack: soundtrack.into(),
+            restarted: false,
+        })
+    }
+
+    /// The frame due at `audio_time`, if a new one is.
+    pub fn next_frame(&mut self, audio_time: f64) -> Option<VideoFrame> {
+        if audio_time < self.start_sec {
+            return None;
+        }
+        self.sequence.advance(audio_time - self.start_sec)
+    }
+
+    /// True the first time it is asked, when the visuals should start afresh.
+    pub fn take_restart(&mut self) -> bool {
+        !std::mem::replace(&mut self.restarted, true)
+    }
+
+    /// Hand `frame`, just drawn, to the recorder; returns how far through
+    /// the piece it is (0..1).
+    pub fn frame_drawn(&self, frame: &VideoFrame) -> f32 {
+        self.track.request_frame();
+        (frame.index + 1) as f32 / self.sequence.frame_count().max(1) as f32
+    }
+
+    /// Whether the last frame has been drawn.
+    pub fn finished(&self) -> bool {
+        self.sequence.finished()
+    }
+
+    /// Stop recording (early, or at the end); the file follows in `on_done`.
+    pub fn stop(&self) {
+        _ = self.soundtrack.stop();
+        _ = self.recorder.stop();
+        log::info!("[video] stopped");
+    }
+}
@@ -0,0 +1,263 @@
+// AudioWorklet synth for geno-1: renders every voice's notes off the main thread.
+//
+// The main thread (src/audio.rs, `WorkletSynth`) posts one Float64Array per
+// frame holding fixed-size records (RECORD_LEN doubles each):
+//   [OP_NOTE,    id, voice, waveform, frequencyHz, velocity, startSec, gateSec,
+//                attackSec, decaySec, sustain, releaseSec, curve,
+//                filterMode, cutoffHz, q, envOctaves, pan, startPhase,
+//                fmRatio, fmIndex, fmAttackSec, fmDecaySec, fmSustain,
+//                fmReleaseSec, fmCurve,
+//                sample, playbackRate, sampleStartSec, loopStartSec, loopEndSec]
+//   [OP_RELEASE, id, atSec, fadeSec,  0, ...]
+// Waveform 0 = sine, 1 = saw, 2 = triangle, WAVE_TABLE_BASE + i = wavetable
+// i, whose single-cycle samples arrive beforehand as a separate
+// { wavetable: i, samples: Float32Array } message (sine until they do).
+// Output N carries voice N (stereo), wired to that voice's fade/gain chain.
+// A unison note arrives as one record per detuned layer, all with the same
+// id; each layer is panned (equal power) within the voice's stereo spread.
+// Envelope and waveforms match the OscillatorNode path and `Adsr::level_at`
+// in src/core/envelope.rs: linear attack, then decay to the sustain level
+// while the gate is open, then release; curve 0 = linear, 1 = exponential
+// (time constant of a quarter of the segment).
+// filterMode -1 = no filter, 0 = lowpass, 1 = bandpass: a state-variable
+// filter like `SvfState` in src/core/filter.rs, its (key-tracked) cutoff
+// opened by envOctaves at the envelope's peak.
+// fmIndex 0 = no FM; otherwise a sine modulator at fmRatio × the note's
+// frequency bends the carrier's phase by up to fmIndex radians, following
+// its own envelope (`FmParams::phase_offset` in src/core/fm.rs).
+// sample -1 = oscillator; otherwise recording `sample` (sent beforehand as
+// { sample, frames: Float32Array, rate }) replaces it, read at playbackRate
+// (times the layer's detune) from sampleStartSec and wrapping inside the loop
+// if it is a valid section (`SamplerConfig::position_at`).
+
+const RECORD_LEN = 31;
+const OP_NOTE = 0;
+const OP_RELEASE = 1;
+const CURVE_EXPONENTIAL = 1;
+const WAVE_TABLE_BASE = 3;
+const FILTER_NONE = -1;
+const FILTER_BANDPASS = 1;
+const TWO_PI = 2 * Math.PI;
+
+// Matches `Adsr::time_constant`
+function timeConstant(segSec) {
+  return Math.max(segSec / 4, 1e-4);
+}
+
+// Envelope stored at data[i..i + 5]: attack, decay, sustain, release, curve
+function readAdsr(data, i) {
+  return {
+    attack: data[i],
+    decay: data[i + 1],
+    sustain: Math.min(Math.max(data[i + 2], 0), 1),
+    release: data[i + 3],
+    curve: data[i + 4],
+  };
+}
+
+// Level while the gate is open (`Adsr::held_level`)
+function heldLevel(env, age) {
+  if (age < env.attack) return age / env.attack;
+  if (env.decay <= 0) return env.sustain;
+  const td = age - env.attack;
+  if (env.curve === CURVE_EXPONENTIAL) {
+    return env.sustain + (1 - env.sustain) * Math.exp(-td / timeConstant(env.decay));
+  }
+  return 1 + (env.sustain - 1) * Math.min(td / env.decay, 1);
+}
+
+// 0..1 level `age` seconds after note-on (`Adsr::level_at`)
+function adsrLevel(env, age, gate) {
+  if (age < 0 || age >= gate + env.release) return 0;
+  if (age < gate) return heldLevel(env, age);
+  const tr = age - gate;
+  const from = heldLevel(env, gate);
+  return env.curve === CURVE_EXPONENTIAL
+    ? from * Math.exp(-tr / timeConstant(env.release))
+    : from * (1 - tr / env.release);
+}
+
+// PolyBLEP residual to band-limit the saw's discontinuity
+function polyBlep(t, dt) {
+  if (t < dt) {
+    t /= dt;
+    return t + t - t * t - 1;
+  }
+  if (t > 1 - dt) {
+    t = (t - 1) / dt;
+    return t * t + t + t + 1;
+  }
+  return 0;
+}
+
+class GenoSynthProcessor extends AudioWorkletProcessor {
+  constructor(options) {
+    super();
+    this.voices = (options.processorOptions && options.processorOptions.voices) || 1;
+    this.notes = [];
+    this.tables = [];
+    this.samples = [];
+    this.port.onmessage = (ev) => this.receive(ev.data);
+  }
+
+  receive(data) {
+    if (data.wavetable !== undefined) {
+      this.tables[data.wavetable] = data.samples;
+      return;
+    }
+    if (data.sample !== undefined) {
+      this.samples[data.sample] = { frames: data.frames, rate: data.rate };
+      return;
+    }
+    for (let i = 0; i + RECORD_LEN <= data.length; i += RECORD_LEN) {
+      const op = data[i];
+      if (op === OP_NOTE) {
+        this.notes.push({
+          id: data[i + 1],
+          voice: data[i + 2],
+          waveform: data[i + 3],
+          freq: data[i + 4],
+          velocity: data[i + 5],
+          start: data[i + 6],
+          gate: data[i + 7],
+          amp: readAdsr(data, i + 8),
+          filterMode: data[i + 13],
+          cutoff: data[i + 14],
+          q: Math.max(data[i + 15], 0.1),
+          envOctaves: data[i + 16],
+          ic1eq: 0,
+          ic2eq: 0,
+          // Equal-power pan law, as StereoPannerNode for mono input
+          left: Math.cos(((data[i + 17] + 1) * Math.PI) / 4),
+          right: Math.sin(((data[i + 17] + 1) * Math.PI) / 4),
+          phase: data[i + 18],
+          fmRatio: data[i + 19],
+          fmIndex: data[i + 20],
+          fmEnv: readAdsr(data, i + 21),
+          modPhase: 0,
+          sample: data[i + 26],
+          rate: data[i + 27],
+          sampleStart: data[i + 28],
+          loopStart: data[i + 29],
+          loopEnd: data[i + 30],
+          release: Infinity,
+          fade: 0,
+        });
+      } else if (op === OP_RELEASE) {
+        // Every unison layer of the note
+        for (const note of this.notes) {
+          if (note.id !== data[i + 1]) continue;
+          note.release = data[i + 2];
+          note.fade = data[i + 3];
+        }
+      }
+    }
+  }
+
+  // Amplitude envelope level (0..1) times any steal fade
+  envelope(note, t) {
+    let level = adsrLevel(note.amp, t - note.start, note.gate);
+    if (t >= note.release) {
+      // Same curve as setTargetAtTime(0, release, fade / 3)
+      level *= Math.exp((-3 * (t - note.release)) / Math.max(note.fade, 1e-4));
+    }
+    return level;
+  }
+
+  // One step of the topology-preserving SVF
+  filter(note, x, level) {
+    const opened = note.cutoff * Math.pow(2, note.envOctaves * level);
+    const cutoff = Math.min(Math.max(opened, 20), 20000, sampleRate * 0.49);
+    const g = Math.tan((Math.PI * cutoff) / sampleRate);
+    const k = 1 / note.q;
+    const a1 = 1 / (1 + g * (g + k));
+    const a2 = g * a1;
+    const a3 = g * a2;
+    const v3 = x - note.ic2eq;
+    const v1 = a1 * note.ic1eq + a2 * v3;
+    const v2 = note.ic2eq + a2 * note.ic1eq + a3 * v3;
+    note.ic1eq = 2 * v1 - note.ic1eq;
+    note.ic2eq = 2 * v2 - note.ic2eq;
+    return note.filterMode === FILTER_BANDPASS ? v1 : v2;
+  }
+
+  // Carrier phase offset (cycles) from the FM modulator
+  fmOffset(note, age, dt) {
+    if (note.fmIndex === 0) return 0;
+    const m = note.modPhase;
+    note.modPhase = (m + note.freq * note.fmRatio * dt) % 1;
+    const index = note.fmIndex * adsrLevel(note.fmEnv, age, note.gate);
+    return (index * Math.sin(TWO_PI * m)) / TWO_PI;
+  }
+
+  // Recording value `age` seconds into the note, or null if it hasn't arrived
+  playSample(note, age) {
+    const rec = this.samples[note.sample];
+    if (!rec) return null;
+    const duration = rec.frames.length / rec.rate;
+    let pos = note.sampleStart + age * note.rate;
+    const span = note.loopEnd - note.loopStart;
+    if (note.loopStart >= 0 && note.loopEnd <= duration && span > 1e-3 && pos >= note.loopEnd) {
+      pos = note.loopStart + ((pos - note.loopStart) % span);
+    }
+    const x = pos * rec.rate;
+    const i = Math.floor(x);
+    if (i < 0 || i >= rec.frames.length) return 0;
+    const a = rec.frames[i];
+    const b = i + 1 < rec.frames.length ? rec.frames[i + 1] : 0;
+    return a + (b - a) * (x - i);
+  }
+
+  sample(note, dt, offset) {
+    const p0 = note.phase;
+    note.phase = (p0 + note.freq * dt) % 1;
+    const p = (((p0 + offset) % 1) + 1) % 1;
+    if (note.waveform >= WAVE_TABLE_BASE) {
+      const table = this.tables[note.waveform - WAVE_TABLE_BASE];
+      if (table) {
+        const pos = p * table.length;
+        const i = Math.floor(pos);
+        const a = table[i];
+        return a + (table[(i + 1) % table.length] - a) * (pos - i);
+      }
+    }
+    switch (note.waveform) {
+      case 1: // saw
+        return 2 * p - 1 - polyBlep(p, note.freq * dt);
+      case 2: // triangle
+        return 1 - 4 * Math.abs(p - 0.5);
+      default: // sine
+        return Math.sin(TWO_PI * p);
+    }
+  }
+
+  process(_inputs, outputs) {
+    const dt = 1 / sampleRate;
+    const frames = outputs[0][0].length;
+    for (const out of outputs) for (const ch of out) ch.fill(0);
+    for (const note of this.notes) {
+      const out = outputs[note.voice];
+      if (!out) continue;
+      const [left, right] = out;
+      for (let i = 0; i < frames; i++) {
+        const t = currentTime + i * dt;
+        if (t < note.start) continue;
+        const level = this.envelope(note, t);
+        const age = t - note.start;
+        let x = note.sample >= 0 ? this.playSample(note, age) : null;
+        if (x === null) x = this.sample(note, dt, this.fmOffset(note, age, dt));
+        if (note.filterMode !== FILTER_NONE) x = this.filter(note, x, level);
+        const gain = note.velocity * level;
+        left[i] += x * gain * note.left;
+        right[i] += x * gain * note.right;
+      }
+    }
+    const end = currentTime + frames * dt;
+    this.notes = this.notes.filter(
+      (n) => n.start + n.gate + n.amp.release > end && n.release + 2 * n.fade > end,
+    );
+    return true;
+  }
+}
+
+registerProcessor("geno-synth", GenoSynthProcessor);
@@ -0,0 +1,203 @@
+// Host-side tests for the per-frame smoothing/FX math used by the web render loop.
+// The main crate is wasm-only, so we include the pure-Rust module directly.
+
+mod frame_math {
+    include!("../src/core/frame_math.rs");
+}
+
+use frame_math::*;
+use rand::{Rng, SeedableRng};
+
+/// Frame intervals from a 120 Hz display up to a badly stuttering tab.
+const DTS: [f32; 6] = [0.008, 0.016, 0.033, 0.050, 0.075, 0.100];
+
+/// Run `seconds` of frames at a fixed `dt`.
+fn frames(dt: f32, seconds: f32) -> usize {
+    (seconds / dt).round() as usize
+}
+
+#[test]
+fn pulses_stay_bounded_and_decay_to_rest_at_any_dt() {
+    for dt in DTS {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let mut pulses = [0.0f32; 3];
+        let mut energy = [0.0f32; 3];
+        for _ in 0..frames(dt, 5.0) {
+            // Random note hits, capped as in the frame loop
+            for e in energy.iter_mut() {
+                if rng.gen::<f32>() < 0.2 {
+                    *e = (*e + rng.gen::<f32>()).min(MAX_PULSE_ENERGY);
+                }
+            }
+            smooth_pulses(&mut pulses, &mut energy, dt);
+            for p in pulses {
+                assert!((0.0..=1.5).contains(&p), "dt {dt}: pulse {p} out of range");
+            }
+        }
+        for _ in 0..frames(dt, 10.0) {
+            smooth_pulses(&mut pulses, &mut energy, dt);
+        }
+        assert!(
+            pulses.iter().all(|p| *p < 1e-3),
+            "dt {dt}: pulses should settle"
+        );
+    }
+}
+
+#[test]
+fn pulse_response_is_frame_rate_independent() {
+    // The same note hit must look the same after 0.2 s, whatever the frame rate
+    let after = |dt: f32| {
+        let mut pulses = [0.0f32; 3];
+        let mut energy = [1.0f32, 0.0, 0.0];
+        for _ in 0..frames(dt, 0.2) {
+            smooth_pulses(&mut pulses, &mut energy, dt);
+        }
+        pulses[0]
+    };
+    let reference = after(0.001);
+    for dt in DTS {
+        assert!(
+            (after(dt) - reference).abs() < 0.08,
+            "dt {dt}: {} vs {reference}",
+            after(dt)
+        );
+    }
+}
+
+#[test]
+fn swirl_converges_on_target_and_stays_in_unit_square() {
+    for dt in DTS {
+        let mut init = false;
+        let mut pos = [0.0f32; 2];
+        let mut vel = [0.0f32; 2];
+        step_inertial_swirl(&mut init, &mut pos, &mut vel, [0.1, 0.1], dt);
+        assert!(init);
+        assert_eq!(pos, [0.1, 0.1], "first call snaps to the pointer");
+
+        let target = [0.8, 0.6];
+        let mut prev = pos;
+        for _ in 0..frames(dt, 20.0) {
+            step_inertial_swirl(&mut init, &mut pos, &mut vel, target, dt);
+            let step = ((pos[0] - prev[0]).powi(2) + (pos[1] - prev[1]).powi(2)).sqrt();
+            assert!(
+                step <= SWIRL_MAX_STEP_PER_SEC * dt + 1e-5,
+                "dt {dt}: step {step}"
+            );
+            assert!(pos.iter().all(|c| (0.0..=1.0).contains(c)));
+            prev = pos;
+        }
+        assert!(
+            (pos[0] - target[0]).abs() < 0.01 && (pos[1] - target[1]).abs() < 0.01,
+            "dt {dt}: settled at {pos:?}"
+        );
+    }
+}
+
+#[test]
+fn swirl_energy_stays_normalized_and_relaxes_when_idle() {
+    for dt in DTS {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(2);
+        let mut energy = 0.0f32;
+        let mut uv = [0.5f32, 0.5];
+        for _ in 0..frames(dt, 3.0) {
+            let next = [rng.gen::<f32>(), rng.gen::<f32>()];
+            let vel = [rng.gen_range(-2.0..2.0), rng.gen_range(-2.0..2.0)];
+            energy = blend_swirl_energy(energy, uv, next, vel, dt, rng.gen());
+            assert!((0.0..=1.0).contains(&energy), "dt {dt}: energy {energy}");
+            uv = next;
+        }
+        for _ in 0..200 {
+            energy = blend_swirl_energy(energy, uv, uv, [0.0, 0.0], dt, false);
+        }
+        assert!(energy < 1e-6, "dt {dt}: idle energy {energy}");
+    }
+}
+
+#[test]
+fn fx_levels_are_within_safe_gain_ranges() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+    for _ in 0..2000 {
+        let energy = rng.gen::<f32>();
+        let uv = [rng.gen::<f32>(), rng.gen::<f32>()];
+        let fx = fx_levels(energy, uv);
+        assert!(
+            fx.reverb_wet >= FX_REVERB_BASE && fx.reverb_wet <= FX_REVERB_BASE + FX_REVERB_SPAN
+        );
+        assert!((0.0..=1.0).contains(&fx.delay_wet));
+        assert!(
+            (0.0..=0.95).contains(&fx.delay_feedback),
+            "feedback must stay below unity"
+        );
+        assert!((FX_SAT_DRIVE_MIN..=FX_SAT_DRIVE_MAX).contains(&fx.sat_drive));
+        assert!((fx.sat_wet + fx.sat_dry - 1.0).abs() < 1e-6);
+    }
+    // Centre of the screen at rest: no echo, mid fizz
+    let calm = fx_levels(0.0, [0.5, 0.5]);
+    assert_eq!(calm.reverb_wet, FX_REVERB_BASE);
+    assert_eq!(calm.delay_wet, FX_DELAY_WET_BASE);
+
+    // Grit follows the saturator: none when clean, rising with fizz
+    assert_eq!(fx_levels(0.0, [0.0, 0.0]).grit(), 0.0);
+    let fizzy = fx_levels(0.0, [1.0, 1.0]).grit();
+    assert!(calm.grit() > 0.0 && calm.grit() < fizzy && fizzy <= 1.0);
+}
+
+#[test]
+fn voice_mix_follows_position_and_swirl() {
+    let centre = voice_mix(glam::Vec3::ZERO, 0.0, VoiceSends::DEFAULT);
+    assert_eq!(centre.level, LEVEL_BASE + LEVEL_SPAN);
+    assert_eq!(
+        (centre.delay_send, centre.reverb_send),
+        (D_SEND_BASE, R_SEND_BASE)
+    );
+    // Off to the side and far away: quieter, more echo and more room
+    let far = voice_mix(glam::Vec3::new(3.0, 0.0, 0.0), 0.0, VoiceSends::DEFAULT);
+    assert!(far.level < centre.level);
+    assert!(far.delay_send > centre.delay_send && far.reverb_send > centre.reverb_send);
+    // Swirl boosts sends without passing their ceilings
+    let stirred = voice_mix(glam::Vec3::new(3.0, 0.0, 0.0), 1.0, VoiceSends::DEFAULT);
+    assert!(stirred.reverb_send > far.reverb_send);
+    assert!(stirred.delay_send <= D_SEND_CLAMP_MAX && stirred.reverb_send <= R_SEND_CLAMP_MAX);
+    // Explicit sends replace the base levels; the modulation rides on top
+    let dry = VoiceSends {
+        delay: 0.0,
+        reverb: 0.0,
+        level: 0.5,
+    };
+    let dry_centre = voice_mix(glam::Vec3::ZERO, 0.0, dry);
+    assert_eq!((dry_centre.delay_send, dry_centre.reverb_send), (0.0, 0.0));
+    assert!(voice_mix(glam::Vec3::new(3.0, 0.0, 0.0), 0.0, dry).reverb_send > 0.0);
+    // The voice's volume scales its level
+    assert_eq!(dry_centre.level, 0.5 * centre.level);
+    let hot = VoiceSends {
+        delay: 2.0,
+        reverb: -1.0,
+        level: 1.5,
+    };
+    assert_eq!(
+        hot.clamped(),
+        VoiceSends {
+            delay: VoiceSends::MAX,
+            reverb: 0.0,
+            level: VoiceSends::MAX,
+        }
+    );
+}
+
+#[test]
+fn param_smoothing_matches_set_target_at_time_at_any_dt() {
+    // setTargetAtTime: v(t) = target + (v0 - target)·e^(-t/τ)
+    for dt in DTS {
+        let n = frames(dt, 0.1);
+        let expected = 1.0 - (-(n as f32 * dt) / PARAM_SMOOTH_TAU_SEC).exp();
+        let mut v = 0.0;
+        for _ in 0..n {
+            v = one_pole(v, 1.0, dt, PARAM_SMOOTH_TAU_SEC);
+        }
+        assert!((v - expected).abs() < 1e-4, "dt {dt}: {v} vs {expected}");
+    }
+    // No time, no movement; no time constant, a jump
+    assert_eq!(one_pole(0.3, 1.0, 0.0, PARAM_SMOOTH_TAU_SEC), 0.3);
+    assert_eq!(one_pole(0.3, 1.0, 0.016, 0.0), 1.0);
+}
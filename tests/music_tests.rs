@@ -2,12 +2,261 @@
 // The main crate is wasm-only, so we include the pure-Rust module directly.
 
 #![allow(dead_code)]
+mod ambisonics {
+    include!("../src/core/ambisonics.rs");
+}
+mod antialias {
+    include!("../src/core/antialias.rs");
+}
+mod attract {
+    include!("../src/core/attract.rs");
+}
+mod automation {
+    include!("../src/core/automation.rs");
+}
+mod bloom {
+    include!("../src/core/bloom.rs");
+}
+mod bounce {
+    include!("../src/core/bounce.rs");
+}
+mod bus {
+    include!("../src/core/bus.rs");
+}
+mod choreography {
+    include!("../src/core/choreography.rs");
+}
+mod clock_sync {
+    include!("../src/core/clock_sync.rs");
+}
+mod custom_shader {
+    include!("../src/core/custom_shader.rs");
+}
+mod device {
+    include!("../src/core/device.rs");
+}
+mod diagnostics {
+    include!("../src/core/diagnostics.rs");
+}
+mod dice {
+    include!("../src/core/dice.rs");
+}
+mod displays {
+    include!("../src/core/displays.rs");
+}
+mod effects {
+    include!("../src/core/effects.rs");
+}
+mod envelope {
+    include!("../src/core/envelope.rs");
+}
+mod eq {
+    include!("../src/core/eq.rs");
+}
+mod filter {
+    include!("../src/core/filter.rs");
+}
+mod fm {
+    include!("../src/core/fm.rs");
+}
+mod frame_math {
+    include!("../src/core/frame_math.rs");
+}
+mod generator {
+    include!("../src/core/generator.rs");
+}
+mod gesture {
+    include!("../src/core/gesture.rs");
+}
+mod glyphs {
+    include!("../src/core/glyphs.rs");
+}
+mod groove {
+    include!("../src/core/groove.rs");
+}
+mod history {
+    include!("../src/core/history.rs");
+}
+mod keymap {
+    include!("../src/core/keymap.rs");
+}
+mod latency {
+    include!("../src/core/latency.rs");
+}
+mod lens {
+    include!("../src/core/lens.rs");
+}
+mod lifecycle {
+    include!("../src/core/lifecycle.rs");
+}
+mod looper {
+    include!("../src/core/looper.rs");
+}
+mod metronome {
+    include!("../src/core/metronome.rs");
+}
+mod midi_learn {
+    include!("../src/core/midi_learn.rs");
+}
+mod midi_out {
+    include!("../src/core/midi_out.rs");
+}
+mod mirror {
+    include!("../src/core/mirror.rs");
+}
+mod mixer {
+    include!("../src/core/mixer.rs");
+}
+mod morph {
+    include!("../src/core/morph.rs");
+}
+mod motion {
+    include!("../src/core/motion.rs");
+}
 mod music {
     include!("../src/core/music.rs");
 }
+mod musical_clock {
+    include!("../src/core/musical_clock.rs");
+}
+mod named_presets {
+    include!("../src/core/named_presets.rs");
+}
+mod note_repeat {
+    include!("../src/core/note_repeat.rs");
+}
+mod onset {
+    include!("../src/core/onset.rs");
+}
+mod output {
+    include!("../src/core/output.rs");
+}
+mod particles {
+    include!("../src/core/particles.rs");
+}
+mod physics {
+    include!("../src/core/physics.rs");
+}
+mod polyphony {
+    include!("../src/core/polyphony.rs");
+}
+mod preset {
+    include!("../src/core/preset.rs");
+}
+mod profiler {
+    include!("../src/core/profiler.rs");
+}
+mod recording {
+    include!("../src/core/recording.rs");
+}
+mod recovery {
+    include!("../src/core/recovery.rs");
+}
+mod render_scale {
+    include!("../src/core/render_scale.rs");
+}
+mod replay {
+    include!("../src/core/replay.rs");
+}
+mod reverb {
+    include!("../src/core/reverb.rs");
+}
+mod ripple {
+    include!("../src/core/ripple.rs");
+}
+mod sampler {
+    include!("../src/core/sampler.rs");
+}
+mod saved_session {
+    include!("../src/core/saved_session.rs");
+}
+mod screenshot {
+    include!("../src/core/screenshot.rs");
+}
+mod selection {
+    include!("../src/core/selection.rs");
+}
+mod session {
+    include!("../src/core/session.rs");
+}
+mod sidechain {
+    include!("../src/core/sidechain.rs");
+}
+mod shader_reload {
+    include!("../src/core/shader_reload.rs");
+}
+mod share {
+    include!("../src/core/share.rs");
+}
+mod sketch {
+    include!("../src/core/sketch.rs");
+}
+mod signature {
+    include!("../src/core/signature.rs");
+}
+mod smf {
+    include!("../src/core/smf.rs");
+}
+mod spatial {
+    include!("../src/core/spatial.rs");
+}
+mod spectrum {
+    include!("../src/core/spectrum.rs");
+}
+mod starfield {
+    include!("../src/core/starfield.rs");
+}
+mod stereo {
+    include!("../src/core/stereo.rs");
+}
+mod strum {
+    include!("../src/core/strum.rs");
+}
+mod tonemap {
+    include!("../src/core/tonemap.rs");
+}
+mod theme {
+    include!("../src/core/theme.rs");
+}
+mod touch {
+    include!("../src/core/touch.rs");
+}
+mod trails {
+    include!("../src/core/trails.rs");
+}
+mod unison {
+    include!("../src/core/unison.rs");
+}
+mod video {
+    include!("../src/core/video.rs");
+}
+mod visualizer {
+    include!("../src/core/visualizer.rs");
+}
+mod voice_style {
+    include!("../src/core/voice_style.rs");
+}
+mod wavetable {
+    include!("../src/core/wavetable.rs");
+}
+mod wheel {
+    include!("../src/core/wheel.rs");
+}
 
+use envelope::*;
+use eq::*;
+use filter::*;
+use fm::*;
+use frame_math::VoiceSends;
 use music::*;
+use polyphony::*;
+use sampler::*;
+use sidechain::*;
+use spatial::*;
 use std::time::Duration;
+use unison::*;
+use voice_style::*;
+use wavetable::*;
 
 fn make_engine() -> MusicEngine {
     let configs = vec![
@@ -17,6 +266,18 @@ fn make_engine() -> MusicEngine {
             trigger_probability: 0.4,
             octave_offset: -1,
             base_duration: 0.4,
+            loop_steps: 0,
+            beat_weights: &[],
+            hysteresis: Hysteresis::NONE,
+            spatial: SpatialHints::DEFAULT,
+            polyphony: Polyphony::DEFAULT,
+            envelope: Adsr::DEFAULT,
+            filter: None,
+            unison: Unison::OFF,
+            fm: None,
+            sampler: None,
+            sends: VoiceSends::DEFAULT,
+            style: VoiceStyle::DEFAULT,
         },
         VoiceConfig {
             waveform: Waveform::Saw,
@@ -24,6 +285,18 @@ fn make_engine() -> MusicEngine {
             trigger_probability: 0.6,
             octave_offset: 0,
             base_duration: 0.25,
+            loop_steps: 0,
+            beat_weights: &[],
+            hysteresis: Hysteresis::NONE,
+            spatial: SpatialHints::DEFAULT,
+            polyphony: Polyphony::DEFAULT,
+            envelope: Adsr::DEFAULT,
+            filter: None,
+            unison: Unison::OFF,
+            fm: None,
+            sampler: None,
+            sends: VoiceSends::DEFAULT,
+            style: VoiceStyle::DEFAULT,
         },
         VoiceConfig {
             waveform: Waveform::Triangle,
@@ -31,6 +304,18 @@ fn make_engine() -> MusicEngine {
             trigger_probability: 0.3,
             octave_offset: 1,
             base_duration: 0.6,
+            loop_steps: 0,
+            beat_weights: &[],
+            hysteresis: Hysteresis::NONE,
+            spatial: SpatialHints::DEFAULT,
+            polyphony: Polyphony::DEFAULT,
+            envelope: Adsr::DEFAULT,
+            filter: None,
+            unison: Unison::OFF,
+            fm: None,
+            sampler: None,
+            sends: VoiceSends::DEFAULT,
+            style: VoiceStyle::DEFAULT,
         },
     ];
     let params = EngineParams::default();
@@ -278,6 +563,18 @@ fn engine_schedule_with_detune() {
         trigger_probability: 1.0,
         octave_offset: 0,
         base_duration: 0.25,
+        loop_steps: 0,
+        beat_weights: &[],
+        hysteresis: Hysteresis::NONE,
+        spatial: SpatialHints::DEFAULT,
+        polyphony: Polyphony::DEFAULT,
+        envelope: Adsr::DEFAULT,
+        filter: None,
+        unison: Unison::OFF,
+        fm: None,
+        sampler: None,
+        sends: VoiceSends::DEFAULT,
+        style: VoiceStyle::DEFAULT,
     }];
     let params = EngineParams {
         scale: &[0.0],
@@ -337,3 +634,4332 @@ fn detune_round_trip_accuracy() {
         );
     }
 }
+
+#[test]
+fn engine_schedule_stamps_events_on_grid_despite_jitter() {
+    // One always-firing voice so every grid step produces an event
+    let configs = vec![VoiceConfig {
+        waveform: Waveform::Sine,
+        base_position: glam::Vec3::ZERO,
+        trigger_probability: 1.0,
+        octave_offset: 0,
+        base_duration: 0.25,
+        loop_steps: 0,
+        beat_weights: &[],
+        hysteresis: Hysteresis::NONE,
+        spatial: SpatialHints::DEFAULT,
+        polyphony: Polyphony::DEFAULT,
+        envelope: Adsr::DEFAULT,
+        filter: None,
+        unison: Unison::OFF,
+        fm: None,
+        sampler: None,
+        sends: VoiceSends::DEFAULT,
+        style: VoiceStyle::DEFAULT,
+    }];
+    let mut engine = MusicEngine::new(configs, EngineParams::default(), 7);
+    let step = engine.step_duration_sec();
+    let start = 12.5;
+    let lookahead = 0.12;
+
+    // Irregular frame times (8..40 ms) must not disturb grid spacing
+    let mut events = Vec::new();
+    let mut now = start;
+    for k in 0..200 {
+        engine.schedule(now, lookahead, &mut events);
+        now += [0.008, 0.016, 0.040, 0.012][k % 4];
+    }
+    assert!(events.len() > 10);
+    for (k, ev) in events.iter().enumerate() {
+        let expected = start + step * k as f64;
+        assert!(
+            (ev.start_time_sec - expected).abs() < 1e-9,
+            "step {k}: start {} expected {expected}",
+            ev.start_time_sec
+        );
+        assert!(ev.start_time_sec <= now + lookahead);
+    }
+
+    // A stalled clock (pause, background tab) restarts the grid instead of bursting
+    let before = events.len();
+    let resumed = now + 5.0;
+    engine.schedule(resumed, lookahead, &mut events);
+    let burst = &events[before..];
+    assert!(burst.len() <= (lookahead / step) as usize + 1);
+    assert!((burst[0].start_time_sec - resumed).abs() < 1e-9);
+}
+
+#[test]
+fn signature_is_deterministic_and_seed_sensitive() {
+    let a = signature::pitch_class_signature(IONIAN, 60, 42);
+    let b = signature::pitch_class_signature(IONIAN, 60, 42);
+    assert_eq!(a, b);
+    // C major: C D E F G A B
+    assert_eq!(a.pitch_class_mask, 0b1010_1011_0101);
+    // Octave duplicate collapses onto the root
+    assert_eq!(a.spokes.len(), 7);
+    assert!((2..=6).contains(&a.symmetry));
+
+    let c = signature::pitch_class_signature(IONIAN, 60, 43);
+    assert_eq!(c.pitch_class_mask, a.pitch_class_mask);
+    assert_ne!(c, a, "different seeds should give different glyphs");
+
+    let svg = make_engine().signature().to_svg(96);
+    assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+}
+
+#[test]
+fn history_undo_redo_restores_state() {
+    use history::{Command, History};
+    let mut engine = make_engine();
+    let mut history = History::new();
+
+    engine.toggle_mute(0);
+    history.execute(&mut engine, Command::ToggleSolo { voice: 2 });
+    history.execute(&mut engine, Command::SetBpm(140.0));
+    assert_eq!(engine.params.bpm, 140.0);
+
+    assert!(history.undo(&mut engine));
+    assert_eq!(engine.params.bpm, 110.0);
+    assert!(history.undo(&mut engine));
+    // Undoing solo restores the pre-solo mute flags, not "all unmuted"
+    assert!(engine.voices[0].muted);
+    assert!(!engine.voices[1].muted);
+    assert!(!history.undo(&mut engine));
+
+    assert!(history.redo(&mut engine));
+    assert!(engine.voices[0].muted && engine.voices[1].muted && !engine.voices[2].muted);
+    assert!(history.redo(&mut engine));
+    assert_eq!(engine.params.bpm, 140.0);
+    assert!(!history.redo(&mut engine));
+
+    // Locking a voice pins it, and undo frees it again
+    history.execute(&mut engine, Command::ToggleLock { voice: 1 });
+    assert!(engine.is_locked(1) && !engine.is_locked(0));
+    assert!(history.undo(&mut engine));
+    assert!(!engine.is_locked(1));
+}
+
+#[test]
+fn history_reseed_undo_replays_same_sequence() {
+    use history::{Command, History};
+    let step = Duration::from_secs_f64(60.0 / 110.0 / 2.0);
+    let mut engine = make_engine();
+    let mut history = History::new();
+
+    let mut reference = make_engine();
+    let mut expected = Vec::new();
+    for _ in 0..32 {
+        reference.tick(step, &mut expected);
+    }
+
+    history.execute(
+        &mut engine,
+        Command::Batch(
+            (0..3)
+                .map(|v| Command::Reseed {
+                    voice: v,
+                    seed: None,
+                })
+                .collect(),
+        ),
+    );
+    assert!(history.undo(&mut engine));
+    let mut got = Vec::new();
+    for _ in 0..32 {
+        engine.tick(step, &mut got);
+    }
+    assert_eq!(got.len(), expected.len());
+    for (a, b) in got.iter().zip(&expected) {
+        assert_eq!(a.voice_index, b.voice_index);
+        assert_eq!(a.frequency_hz, b.frequency_hz);
+    }
+}
+
+#[test]
+fn engine_emits_state_change_events() {
+    use bus::{EngineEvent, EventBus};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut engine = make_engine();
+    engine.set_bpm(120.0);
+    engine.toggle_solo(1);
+    engine.toggle_mute(1);
+    engine.reseed_voice(2, Some(9));
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut bus = EventBus::new();
+    let sink = seen.clone();
+    bus.subscribe(Box::new(move |ev| sink.borrow_mut().push(ev.clone())));
+    bus.dispatch(&engine.drain_events());
+
+    assert_eq!(
+        *seen.borrow(),
+        vec![
+            EngineEvent::BpmChanged(120.0),
+            EngineEvent::MuteChanged {
+                voice: 0,
+                muted: true
+            },
+            EngineEvent::MuteChanged {
+                voice: 2,
+                muted: true
+            },
+            EngineEvent::SoloChanged { voice: Some(1) },
+            EngineEvent::MuteChanged {
+                voice: 1,
+                muted: true
+            },
+            EngineEvent::VoiceReseeded {
+                voice: 2,
+                seed: Some(9)
+            },
+        ]
+    );
+    assert!(engine.drain_events().is_empty());
+}
+
+#[test]
+fn dice_respects_locks_and_safe_ranges() {
+    use history::Command;
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+    let mut engine = make_engine();
+    let locks = dice::ParamLocks {
+        scale: true,
+        sequences: true,
+        ..Default::default()
+    };
+    for _ in 0..50 {
+        let cmd = dice::roll_dice(&engine, &locks, &mut rng);
+        if let Command::Batch(cmds) = &cmd {
+            assert!(cmds
+                .iter()
+                .all(|c| !matches!(c, Command::SetScale(_) | Command::Reseed { .. })));
+        } else {
+            panic!("dice should produce a batch");
+        }
+        engine.apply(&cmd);
+        assert!(engine.params.bpm >= dice::DICE_BPM_MIN && engine.params.bpm <= dice::DICE_BPM_MAX);
+        assert!(engine.params.detune_cents.abs() <= dice::DICE_DETUNE_MAX_CENTS);
+        assert_eq!(engine.params.scale, EngineParams::default().scale);
+    }
+
+    let all = dice::ParamLocks {
+        bpm: true,
+        root: true,
+        scale: true,
+        detune: true,
+        sequences: true,
+    };
+    assert!(matches!(dice::roll_dice(&engine, &all, &mut rng), Command::Batch(c) if c.is_empty()));
+}
+
+#[test]
+fn share_string_round_trips_and_applies_undoably() {
+    let mut engine = make_engine();
+    engine.set_bpm(96.0);
+    engine.set_root_midi(62);
+    engine.set_scale(DORIAN);
+    engine.set_detune_cents(-15.0);
+    engine.toggle_mute(1);
+    let state = share::ShareState::from_engine(&engine);
+    let text = state.encode();
+    assert_eq!(text, "g1;bpm=96;root=62;scale=dor;detune=-15;mute=010");
+    assert_eq!(share::ShareState::decode(&format!("  {text}\n")), Ok(state));
+
+    let mut fresh = make_engine();
+    let mut history = history::History::new();
+    let decoded = share::ShareState::decode(&text).expect("valid share string");
+    history.execute(&mut fresh, decoded.to_command());
+    assert_eq!(fresh.params.bpm, 96.0);
+    assert_eq!(fresh.params.scale, DORIAN);
+    assert!(fresh.voices[1].muted && !fresh.voices[0].muted);
+    assert!(history.undo(&mut fresh));
+    assert_eq!(fresh.params.bpm, EngineParams::default().bpm);
+    assert!(!fresh.voices[1].muted, "undo should restore mutes");
+
+    assert!(share::ShareState::decode("hello").is_err());
+    assert!(share::ShareState::decode("g1;scale=nope").is_err());
+    assert!(share::ShareState::decode("g1;bpm=fast").is_err());
+    // Clamping would keep a NaN, which stops the scheduler
+    assert!(share::ShareState::decode("g1;bpm=NaN").is_err());
+    assert!(share::ShareState::decode("g1;detune=inf").is_err());
+    let mut engine = make_engine();
+    let step = engine.step_duration_sec();
+    engine.set_bpm(f32::NAN);
+    engine.set_bpm(0.0);
+    assert_eq!(engine.step_duration_sec(), step);
+    // Unknown fields are skipped, missing ones fall back to defaults
+    let partial = share::ShareState::decode("g1;future=1;bpm=500").unwrap();
+    assert_eq!(partial.bpm, 240.0);
+    assert_eq!(partial.root_midi, EngineParams::default().root_midi);
+}
+
+#[test]
+fn ambisonic_encoding_and_bformat_export() {
+    use glam::Vec3;
+    // AmbiX order W, Y (left), Z (up), X (front); listener faces -Z
+    assert_eq!(
+        ambisonics::foa_encode(Vec3::new(0.0, 0.0, -2.0)),
+        [1.0, 0.0, 0.0, 1.0]
+    );
+    assert_eq!(
+        ambisonics::foa_encode(Vec3::new(-1.0, 0.0, 0.0)),
+        [1.0, 1.0, 0.0, 0.0]
+    );
+    assert_eq!(
+        ambisonics::foa_encode(Vec3::new(0.0, 3.0, 0.0)),
+        [1.0, 0.0, 1.0, 0.0]
+    );
+    assert_eq!(ambisonics::foa_encode(Vec3::ZERO), [1.0, 0.0, 0.0, 0.0]);
+
+    let mut engine = make_engine();
+    let sr = 8_000;
+    let samples = ambisonics::render_bformat(&mut engine, Vec3::new(0.0, 0.0, 2.5), 2.0, sr);
+    assert_eq!(samples.len(), 2 * sr as usize * ambisonics::FOA_CHANNELS);
+    let w_energy: f32 = samples.iter().step_by(4).map(|s| s * s).sum();
+    assert!(w_energy > 0.0, "export should contain audio");
+    assert!(samples.iter().all(|s| s.is_finite()));
+
+    let wav = ambisonics::wav_bytes(&samples, 4, sr);
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(u16::from_le_bytes([wav[22], wav[23]]), 4, "channel count");
+    // Four channels are written as WAVE_FORMAT_EXTENSIBLE, with no speaker mask
+    assert_eq!(u32::from_le_bytes([wav[16], wav[17], wav[18], wav[19]]), 40);
+    assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), 0xFFFE);
+    assert_eq!(u32::from_le_bytes([wav[40], wav[41], wav[42], wav[43]]), 0);
+    assert_eq!(&wav[60..64], b"data");
+    assert_eq!(wav.len(), 68 + samples.len() * 2);
+    let riff_len = u32::from_le_bytes([wav[4], wav[5], wav[6], wav[7]]) as usize;
+    assert_eq!(riff_len + 8, wav.len());
+
+    // Stereo stays plain PCM
+    let stereo = ambisonics::wav_bytes(&[0.0; 8], 2, sr);
+    assert_eq!(u16::from_le_bytes([stereo[20], stereo[21]]), 1);
+    assert_eq!(stereo.len(), 44 + 16);
+}
+
+#[test]
+fn polymeter_voices_repeat_over_their_own_loop_lengths() {
+    use history::{Command, History};
+    let mut engine = make_engine();
+    for v in &mut engine.configs {
+        v.trigger_probability = 0.5;
+    }
+    let lengths = [5u32, 7, 16];
+    for (voice, &steps) in lengths.iter().enumerate() {
+        engine.set_loop_steps(voice, steps);
+    }
+    let step = engine.step_duration_sec();
+    // Per-voice pattern of (step index, frequency) over 5*7*16 steps
+    let total = 5 * 7 * 16;
+    let mut patterns = vec![Vec::new(); 3];
+    let mut events = Vec::new();
+    engine.schedule(0.0, (total as f64 - 0.5) * step, &mut events);
+    for ev in &events {
+        let k = (ev.start_time_sec / step).round() as usize;
+        patterns[ev.voice_index].push((k, ev.frequency_hz.to_bits()));
+    }
+    for (voice, &steps) in lengths.iter().enumerate() {
+        let fired = &patterns[voice];
+        assert!(!fired.is_empty(), "voice {voice} should play");
+        for &(k, f) in fired {
+            let next = k + steps as usize;
+            if next < total {
+                assert!(
+                    fired.contains(&(next, f)),
+                    "voice {voice} should repeat step {k} after {steps} steps"
+                );
+            }
+        }
+    }
+
+    // Loop length changes are undoable
+    let mut history = History::new();
+    history.execute(&mut engine, Command::SetLoopSteps { voice: 0, steps: 3 });
+    assert_eq!(engine.configs[0].loop_steps, 3);
+    assert!(history.undo(&mut engine));
+    assert_eq!(engine.configs[0].loop_steps, 5);
+}
+
+#[test]
+fn groove_templates_shift_onsets_off_the_grid() {
+    let configs = vec![
+        VoiceConfig {
+            waveform: Waveform::Sine,
+            base_position: glam::Vec3::ZERO,
+            trigger_probability: 1.0,
+            octave_offset: 0,
+            base_duration: 0.25,
+            loop_steps: 0,
+            beat_weights: &[],
+            hysteresis: Hysteresis::NONE,
+            spatial: SpatialHints::DEFAULT,
+            polyphony: Polyphony::DEFAULT,
+            envelope: Adsr::DEFAULT,
+            filter: None,
+            unison: Unison::OFF,
+            fm: None,
+            sampler: None,
+            sends: VoiceSends::DEFAULT,
+            style: VoiceStyle::DEFAULT,
+        };
+        3
+    ];
+    let mut engine = MusicEngine::new(configs, EngineParams::default(), 11);
+    let step = engine.step_duration_sec();
+    let mut history = history::History::new();
+    history.execute(&mut engine, history::Command::SetGroove(&groove::SWING_55));
+    assert_eq!(engine.params.groove.name, "Swing 55%");
+
+    let mut events = Vec::new();
+    engine.schedule(0.0, 3.5 * step, &mut events);
+    for ev in &events {
+        let k = (ev.start_time_sec / step).floor();
+        let offset = ev.start_time_sec / step - k;
+        let expected = if k as u64 % 2 == 1 { 0.1 } else { 0.0 };
+        assert!(
+            (offset - expected).abs() < 1e-6,
+            "step {k}: offset {offset}"
+        );
+    }
+
+    // Rushed hats only moves the top voice, and ahead of the beat
+    assert!(history.undo(&mut engine));
+    assert_eq!(engine.params.groove.name, "Straight");
+    let rushed = &groove::RUSHED_HATS;
+    for step_index in 0..8 {
+        assert_eq!(rushed.offset(0, step_index), 0.0);
+        assert!(rushed.offset(2, step_index) < 0.0);
+    }
+    for g in groove::GROOVES {
+        for v in 0..3 {
+            for s in 0..16 {
+                assert!(
+                    g.offset(v, s).abs() < 0.5,
+                    "{} stays within half a step",
+                    g.name
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn output_latency_estimate_combines_and_sanitizes_reports() {
+    use latency::{estimate_output_latency, MAX_OUTPUT_LATENCY_SEC};
+    assert_eq!(estimate_output_latency(None, None), 0.0);
+    assert!((estimate_output_latency(Some(0.01), Some(0.2)) - 0.21).abs() < 1e-12);
+    // Bluetooth-only report, missing base latency
+    assert_eq!(estimate_output_latency(None, Some(0.18)), 0.18);
+    assert_eq!(estimate_output_latency(Some(f64::NAN), Some(-1.0)), 0.0);
+    assert_eq!(
+        estimate_output_latency(Some(0.1), Some(3.0)),
+        MAX_OUTPUT_LATENCY_SEC
+    );
+}
+
+#[test]
+fn beat_weights_condition_triggers_on_bar_position() {
+    let configs = vec![VoiceConfig {
+        waveform: Waveform::Sine,
+        base_position: glam::Vec3::ZERO,
+        trigger_probability: 0.8,
+        octave_offset: 0,
+        base_duration: 0.25,
+        loop_steps: 0,
+        // Only ever on the downbeat and beat 3 of an eight-step bar
+        beat_weights: &[2.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0],
+        hysteresis: Hysteresis::NONE,
+        spatial: SpatialHints::DEFAULT,
+        polyphony: Polyphony::DEFAULT,
+        envelope: Adsr::DEFAULT,
+        filter: None,
+        unison: Unison::OFF,
+        fm: None,
+        sampler: None,
+        sends: VoiceSends::DEFAULT,
+        style: VoiceStyle::DEFAULT,
+    }];
+    let mut engine = MusicEngine::new(configs, EngineParams::default(), 5);
+    assert_eq!(engine.trigger_probability_at(0, 0), 1.0, "clamped to 1");
+    assert_eq!(engine.trigger_probability_at(0, 12), 0.8);
+    assert_eq!(engine.trigger_probability_at(0, 9), 0.0);
+
+    let step = engine.step_duration_sec();
+    let mut events = Vec::new();
+    engine.schedule(0.0, 799.5 * step, &mut events);
+    let mut per_position = [0usize; 8];
+    for ev in &events {
+        per_position[((ev.start_time_sec / step).round() as usize) % 8] += 1;
+    }
+    assert_eq!(per_position[0], 100, "downbeat always fires");
+    assert!(per_position[4] > 60 && per_position[4] < 100);
+    assert_eq!(
+        per_position.iter().sum::<usize>(),
+        per_position[0] + per_position[4]
+    );
+}
+
+#[test]
+fn output_presets_are_distinct_and_safe() {
+    use output::*;
+    assert_eq!(OUTPUT_PRESETS[0], HEADPHONES, "headphones is the default");
+    for (i, p) in OUTPUT_PRESETS.iter().enumerate() {
+        assert_eq!(output_preset_by_id(&p.id.to_uppercase()), Some(p));
+        assert!(OUTPUT_PRESETS[i + 1..].iter().all(|q| q.id != p.id));
+        assert!(
+            p.limiter_ceiling_db <= 0.0,
+            "{} must not exceed 0 dBFS",
+            p.name
+        );
+        assert!(p.comp_ratio >= 1.0 && p.master_gain > 0.0 && p.master_gain <= 1.0);
+        assert!((0.0..=1.0).contains(&p.sub_level));
+    }
+    assert!(output_preset_by_id("stadium").is_none());
+    assert_eq!(LAPTOP_SPEAKERS.sub_level, 0.0, "laptop drivers get no sub");
+    assert!(OUTPUT_PRESETS
+        .iter()
+        .all(|p| GALLERY_QUIET.master_gain <= p.master_gain));
+}
+
+#[test]
+fn hybrid_scales_move_one_degree_at_a_time() {
+    use morph::hybrid_scale;
+    assert_eq!(hybrid_scale(IONIAN, AEOLIAN, 0.0), IONIAN.to_vec());
+    assert_eq!(hybrid_scale(IONIAN, AEOLIAN, 1.0), AEOLIAN.to_vec());
+    // Ionian -> Aeolian flattens the 3rd, 6th and 7th: each step changes one note
+    let mut prev = IONIAN.to_vec();
+    let mut distinct = 0;
+    for k in 0..=40 {
+        let h = hybrid_scale(IONIAN, AEOLIAN, k as f32 / 40.0);
+        if h != prev {
+            let moved = h.iter().filter(|d| !prev.contains(d)).count();
+            assert_eq!(moved, 1, "one note changes at a time: {prev:?} -> {h:?}");
+            distinct += 1;
+            prev = h;
+        }
+    }
+    assert_eq!(distinct, 3);
+    // Common tones (root, 5th, octave) are always present
+    for k in 0..=10 {
+        let h = hybrid_scale(LYDIAN, C_MAJOR_PENTATONIC, k as f32 / 10.0);
+        for d in [0.0, 7.0, 12.0] {
+            assert!(h.contains(&d));
+        }
+    }
+}
+
+#[test]
+fn engine_morphs_scale_over_bars_and_undo_snaps_back() {
+    use history::{Command, History};
+    let configs = vec![VoiceConfig {
+        waveform: Waveform::Sine,
+        base_position: glam::Vec3::ZERO,
+        trigger_probability: 1.0,
+        octave_offset: 0,
+        base_duration: 0.25,
+        loop_steps: 0,
+        beat_weights: &[],
+        hysteresis: Hysteresis::NONE,
+        spatial: SpatialHints::DEFAULT,
+        polyphony: Polyphony::DEFAULT,
+        envelope: Adsr::DEFAULT,
+        filter: None,
+        unison: Unison::OFF,
+        fm: None,
+        sampler: None,
+        sends: VoiceSends::DEFAULT,
+        style: VoiceStyle::DEFAULT,
+    }];
+    let params = EngineParams {
+        scale: IONIAN,
+        ..EngineParams::default()
+    };
+    let mut engine = MusicEngine::new(configs, params, 3);
+    let mut history = History::new();
+    history.execute(
+        &mut engine,
+        Command::MorphScale {
+            scale: AEOLIAN,
+            bars: 2,
+        },
+    );
+    assert_eq!(engine.params.scale, AEOLIAN, "target reported immediately");
+    assert_eq!(
+        engine.active_scale(),
+        IONIAN,
+        "but notes start from the old scale"
+    );
+    engine.drain_events();
+
+    let step = engine.step_duration_sec();
+    let mut events = Vec::new();
+    engine.schedule(0.0, (2 * STEPS_PER_BAR) as f64 * step, &mut events);
+    let hybrids: Vec<Vec<f32>> = engine
+        .drain_events()
+        .into_iter()
+        .filter_map(|e| match e {
+            bus::EngineEvent::HybridScale(h) => Some(h),
+            _ => None,
+        })
+        .collect();
+    assert!(
+        hybrids.len() >= 3,
+        "in-between scales are announced: {hybrids:?}"
+    );
+    assert_eq!(hybrids.last().map(|h| &h[..]), Some(AEOLIAN));
+    assert_eq!(engine.active_scale(), AEOLIAN);
+
+    assert!(history.undo(&mut engine));
+    assert_eq!(engine.active_scale(), IONIAN);
+}
+
+#[test]
+fn mute_and_solo_fade_out_instead_of_cutting() {
+    let mut engine = make_engine();
+    engine.configs[1].trigger_probability = 1.0;
+    engine.set_bpm(240.0); // 0.125 s steps, so one lands inside the fade
+    let mut events = Vec::new();
+    engine.schedule(10.0, 0.0, &mut events);
+    assert_eq!(engine.voices[1].fade.gain_at(10.0), 1.0);
+
+    engine.toggle_mute(1);
+    let fade = engine.voices[1].fade;
+    assert_eq!((fade.from_gain, fade.target_gain), (1.0, 0.0));
+    assert_eq!(fade.start_sec, 10.0);
+    assert!((fade.gain_at(10.0 + MUTE_FADE_SEC as f64 / 2.0) - 0.5).abs() < 1e-5);
+
+    // Notes keep coming while the fade is audible, then stop
+    events.clear();
+    engine.schedule(10.0, 2.0, &mut events);
+    let end = 10.0 + MUTE_FADE_SEC as f64;
+    let voice1: Vec<f64> = events
+        .iter()
+        .filter(|e| e.voice_index == 1)
+        .map(|e| e.start_time_sec)
+        .collect();
+    assert!(!voice1.is_empty());
+    assert!(voice1.iter().all(|t| *t < end), "{voice1:?}");
+
+    // Unmuting part-way through a fade ramps back up from the current gain
+    engine.schedule(10.1, 0.0, &mut events);
+    engine.toggle_mute(1);
+    let back = engine.voices[1].fade;
+    assert!((back.from_gain - fade.gain_at(10.1)).abs() < 1e-6);
+    assert_eq!(back.target_gain, 1.0);
+
+    // Solo fades the other voices the same way
+    engine.toggle_solo(0);
+    assert_eq!(engine.voices[0].fade.target_gain, 1.0);
+    assert_eq!(engine.voices[2].fade.target_gain, 0.0);
+    assert_eq!(engine.voices[2].fade.ramp_sec, MUTE_FADE_SEC);
+}
+
+#[test]
+fn hysteresis_spaces_or_clusters_notes_after_a_fire() {
+    let onsets = |hysteresis: Hysteresis| {
+        let mut engine = make_engine();
+        engine.configs[0].trigger_probability = 0.5;
+        engine.configs[0].hysteresis = hysteresis;
+        let step = engine.step_duration_sec();
+        let mut events = Vec::new();
+        engine.schedule(0.0, step * 3999.5, &mut events);
+        let mut fired = vec![false; 4000];
+        for e in events.iter().filter(|e| e.voice_index == 0) {
+            fired[(e.start_time_sec / step).round() as usize] = true;
+        }
+        fired
+    };
+    // P(fire | fired on the previous step)
+    let after_fire = |fired: &[bool]| {
+        let pairs = fired.windows(2).filter(|w| w[0]).count() as f32;
+        fired.windows(2).filter(|w| w[0] && w[1]).count() as f32 / pairs
+    };
+
+    let free = onsets(Hysteresis::NONE);
+    assert!((after_fire(&free) - 0.5).abs() < 0.05);
+
+    // A hard refractory period: never two notes within three steps
+    let spaced = onsets(Hysteresis {
+        steps: 3,
+        factor: 0.0,
+    });
+    let idx: Vec<usize> = (0..spaced.len()).filter(|&i| spaced[i]).collect();
+    assert!(idx.len() > 100);
+    assert!(idx.windows(2).all(|w| w[1] - w[0] > 3));
+
+    let clustered = onsets(Hysteresis {
+        steps: 1,
+        factor: 1.8,
+    });
+    assert!(after_fire(&clustered) > 0.85);
+}
+
+#[test]
+fn transpose_and_octave_shift_emit_events_and_undo() {
+    use bus::EngineEvent;
+    use history::{Command, History};
+
+    let mut engine = make_engine();
+    let mut history = History::new();
+    history.execute(&mut engine, Command::Transpose(3));
+    assert_eq!(engine.params.root_midi, 63);
+    history.execute(&mut engine, Command::Transpose(100));
+    assert_eq!(engine.params.root_midi, MAX_ROOT_MIDI);
+    // Already at the top: no change, no event
+    engine.drain_events();
+    engine.transpose(1);
+    assert!(engine.drain_events().is_empty());
+
+    history.execute(
+        &mut engine,
+        Command::ShiftOctave {
+            voice: 2,
+            octaves: 5,
+        },
+    );
+    assert_eq!(engine.configs[2].octave_offset, MAX_OCTAVE_OFFSET);
+    assert_eq!(
+        engine.drain_events(),
+        vec![EngineEvent::OctaveChanged {
+            voice: 2,
+            octave: MAX_OCTAVE_OFFSET
+        }]
+    );
+
+    assert!(history.undo(&mut engine));
+    assert_eq!(engine.configs[2].octave_offset, 1);
+    assert!(history.undo(&mut engine));
+    assert!(history.undo(&mut engine));
+    assert_eq!(engine.params.root_midi, 60);
+
+    // The shifted octave is what gets scheduled
+    engine.set_octave_offset(1, -2);
+    engine.configs[1].trigger_probability = 1.0;
+    let mut events = Vec::new();
+    engine.schedule(0.0, 2.0, &mut events);
+    let lowest = midi_to_hz(60.0 - 24.0);
+    let highest = midi_to_hz(60.0 - 24.0 + 12.0);
+    assert!(events
+        .iter()
+        .filter(|e| e.voice_index == 1)
+        .all(|e| e.frequency_hz >= lowest - 0.01 && e.frequency_hz <= highest + 0.01));
+}
+
+#[test]
+fn custom_generators_drive_their_voice() {
+    use generator::{NoteSpec, StepContext, VoiceGenerator};
+
+    // Walks up the scale on every step, ignoring probability
+    #[derive(Clone)]
+    struct Arpeggio {
+        next: usize,
+    }
+    impl VoiceGenerator for Arpeggio {
+        fn next_step(&mut self, ctx: &StepContext) -> Option<NoteSpec> {
+            let degree = ctx.scale[self.next % ctx.scale.len()];
+            self.next += 1;
+            Some(NoteSpec {
+                midi: ctx.root_midi as f32 + degree,
+                velocity: 0.8,
+                duration_sec: 0.1,
+            })
+        }
+        fn box_clone(&self) -> Box<dyn VoiceGenerator> {
+            Box::new(self.clone())
+        }
+    }
+
+    let mut engine = make_engine();
+    engine.set_generator(1, Box::new(Arpeggio { next: 0 }));
+    let step = engine.step_duration_sec();
+    let mut events = Vec::new();
+    engine.schedule(0.0, step * 7.5, &mut events);
+    let pitches: Vec<f32> = events
+        .iter()
+        .filter(|e| e.voice_index == 1)
+        .map(|e| e.frequency_hz)
+        .collect();
+    let expected: Vec<f32> = (0..8)
+        .map(|k| midi_to_hz(60.0 + C_MAJOR_PENTATONIC[k % C_MAJOR_PENTATONIC.len()]))
+        .collect();
+    assert_eq!(pitches.len(), 8);
+    for (p, e) in pitches.iter().zip(&expected) {
+        assert!((p - e).abs() < 1e-3, "{p} vs {e}");
+    }
+
+    // Looping restores the generator's own state at each loop start
+    engine.set_loop_steps(1, 3);
+    events.clear();
+    engine.schedule(step * 8.0, step * 5.5, &mut events);
+    let looped: Vec<f32> = events
+        .iter()
+        .filter(|e| e.voice_index == 1)
+        .map(|e| e.frequency_hz)
+        .collect();
+    assert_eq!(looped.len(), 6);
+    assert_eq!(looped[..3], looped[3..]);
+}
+
+#[test]
+fn smf_export_has_tempo_map_and_a_track_per_voice() {
+    use smf::{hz_to_midi, MidiRecorder, SMF_PPQ};
+
+    assert!((hz_to_midi(440.0) - 69.0).abs() < 1e-4);
+    assert!((hz_to_midi(midi_to_hz(61.3)) - 61.3).abs() < 1e-3);
+
+    let mut engine = make_engine();
+    let mut recorder = MidiRecorder::new(3, engine.params.bpm);
+    let mut events = Vec::new();
+    engine.schedule(5.0, 4.0, &mut events);
+    recorder.record_tempo(9.0, 140.0);
+    engine.set_bpm(140.0);
+    engine.schedule(9.0, 4.0, &mut events);
+    for ev in &events {
+        recorder.record(ev);
+    }
+    assert_eq!(recorder.note_count(), events.len());
+    let bytes = recorder.to_smf();
+
+    assert_eq!(&bytes[0..4], b"MThd");
+    assert_eq!(u16::from_be_bytes([bytes[8], bytes[9]]), 1, "type 1");
+    assert_eq!(
+        u16::from_be_bytes([bytes[10], bytes[11]]),
+        4,
+        "tempo + 3 voices"
+    );
+    assert_eq!(u16::from_be_bytes([bytes[12], bytes[13]]), SMF_PPQ);
+
+    // Walk the track chunks and check their contents
+    let mut pos = 14;
+    let mut tracks = Vec::new();
+    while pos < bytes.len() {
+        assert_eq!(&bytes[pos..pos + 4], b"MTrk");
+        let len = u32::from_be_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        tracks.push(&bytes[pos + 8..pos + 8 + len]);
+        pos += 8 + len;
+    }
+    assert_eq!(pos, bytes.len());
+    assert_eq!(tracks.len(), 4);
+    let count = |track: &[u8], pattern: &[u8]| {
+        track
+            .windows(pattern.len())
+            .filter(|w| *w == pattern)
+            .count()
+    };
+    assert_eq!(count(tracks[0], &[0xFF, 0x51, 0x03]), 2, "two tempos");
+    for (voice, track) in tracks[1..].iter().enumerate() {
+        assert!(track.ends_with(&[0x00, 0xFF, 0x2F, 0x00]));
+        let notes = events.iter().filter(|e| e.voice_index == voice).count();
+        let bends = track.iter().filter(|&&b| b == 0xE0 | voice as u8).count();
+        assert!(bends >= notes, "voice {voice}: a bend per note");
+    }
+}
+
+#[test]
+fn smf_import_round_trips_export_and_seeds_markov_generators() {
+    use generator::MarkovGenerator;
+    use history::{Command, History};
+    use smf::{hz_to_midi, melodies, parse_smf, MidiRecorder};
+
+    // Export a known session, then read it back
+    let mut engine = make_engine();
+    let mut events = Vec::new();
+    engine.schedule(0.0, 6.0, &mut events);
+    let mut recorder = MidiRecorder::new(3, engine.params.bpm);
+    for ev in &events {
+        recorder.record(ev);
+    }
+    let notes = parse_smf(&recorder.to_smf()).expect("own export parses");
+    assert_eq!(notes.len(), events.len());
+    let parts = melodies(&notes);
+    assert_eq!(parts.len(), 3);
+    for (voice, part) in parts.iter().enumerate() {
+        let expected: Vec<u8> = events
+            .iter()
+            .filter(|e| e.voice_index == voice)
+            .map(|e| hz_to_midi(e.frequency_hz).round() as u8)
+            .collect();
+        let mut got = part.clone();
+        let mut want = expected.clone();
+        got.sort_unstable();
+        want.sort_unstable();
+        assert_eq!(got, want, "voice {voice}");
+    }
+    let beat = 60.0 / engine.params.bpm as f64;
+    assert!((notes[0].start_beats - events[0].start_time_sec / beat).abs() < 0.01);
+    assert!(notes.iter().all(|n| n.duration_beats > 0.0));
+
+    assert!(parse_smf(b"RIFF....").is_err());
+    assert!(parse_smf(&recorder.to_smf()[..30]).is_err());
+
+    // A rising three-note figure only ever moves within its own pitches
+    let melody = [62u8, 64, 67, 64, 62];
+    let markov = MarkovGenerator::from_melody(&melody, 1).unwrap();
+    assert!(MarkovGenerator::from_melody(&[], 1).is_none());
+    let mut history = History::new();
+    history.execute(
+        &mut engine,
+        Command::SetGenerator {
+            voice: 0,
+            generator: Box::new(markov),
+        },
+    );
+    engine.configs[0].trigger_probability = 1.0;
+    engine.configs[0].octave_offset = 0;
+    events.clear();
+    engine.schedule(10.0, 5.0, &mut events);
+    let allowed: Vec<f32> = [0.0, 2.0, 5.0]
+        .iter()
+        .map(|o| midi_to_hz(60.0 + o))
+        .collect();
+    let voice0: Vec<_> = events.iter().filter(|e| e.voice_index == 0).collect();
+    assert!(voice0.len() > 10);
+    assert!(voice0
+        .iter()
+        .all(|e| allowed.iter().any(|a| (a - e.frequency_hz).abs() < 1e-2)));
+
+    // Undo puts the built-in generator back
+    assert!(history.undo(&mut engine));
+    events.clear();
+    engine.schedule(20.0, 5.0, &mut events);
+    assert!(events
+        .iter()
+        .filter(|e| e.voice_index == 0)
+        .any(|e| !allowed.iter().any(|a| (a - e.frequency_hz).abs() < 1e-2)));
+}
+
+#[test]
+fn spatial_hints_place_the_sound_source() {
+    let pos = glam::Vec3::new(1.0, 0.0, 0.0);
+    // Default follows the voice position exactly
+    assert_eq!(SpatialHints::DEFAULT.source_position(pos, 3.0), pos);
+
+    // Fixed pan keeps the distance but moves to the pan angle
+    let hard_left = SpatialHints {
+        fixed_pan: Some(-1.0),
+        ..SpatialHints::DEFAULT
+    };
+    let src = hard_left.source_position(pos, 0.0);
+    assert!((src.x + 1.0).abs() < 1e-5 && src.z.abs() < 1e-5);
+    let centre = SpatialHints {
+        fixed_pan: Some(0.0),
+        ..SpatialHints::DEFAULT
+    };
+    let src = centre.source_position(glam::Vec3::new(0.0, 0.0, 2.0), 0.0);
+    assert!(src.x.abs() < 1e-5 && (src.z + 2.0).abs() < 1e-5);
+
+    // Orbit stays on a circle of the given radius around the position
+    let orbit = SpatialHints {
+        orbit_radius: 0.5,
+        orbit_speed: 1.0,
+        ..SpatialHints::DEFAULT
+    };
+    for t in [0.0, 0.7, 2.5] {
+        let d = orbit.source_position(pos, t).distance(pos);
+        assert!((d - 0.5).abs() < 1e-5);
+    }
+    assert_ne!(
+        orbit.source_position(pos, 0.0),
+        orbit.source_position(pos, 1.0)
+    );
+}
+
+#[test]
+fn auto_motion_moves_unheld_voices_from_where_they_are() {
+    use motion::{MotionMode, MOTION_RADIUS};
+    let mut engine = make_engine();
+    let start: Vec<_> = engine.voices.iter().map(|v| v.position).collect();
+    let mut events = Vec::new();
+
+    // Still by default
+    engine.tick(Duration::from_secs(2), &mut events);
+    assert!(engine
+        .voices
+        .iter()
+        .zip(&start)
+        .all(|(v, s)| v.position == *s));
+
+    for mode in [MotionMode::Orbit, MotionMode::Lissajous] {
+        engine.set_motion(mode);
+        engine.set_held(1, true);
+        let before: Vec<_> = engine.voices.iter().map(|v| v.position).collect();
+        // Small first step: no jump when motion starts
+        engine.advance_motion(Duration::from_millis(10));
+        for (v, b) in engine.voices.iter().zip(&before) {
+            assert!(v.position.distance(*b) < 0.01);
+        }
+        for _ in 0..40 {
+            engine.tick(Duration::from_millis(250), &mut events);
+        }
+        assert_ne!(engine.voices[0].position, before[0]);
+        assert_ne!(engine.voices[2].position, before[2]);
+        assert_eq!(engine.voices[1].position, before[1], "held voice stays put");
+        for (v, b) in engine.voices.iter().zip(&before) {
+            assert!(v.position.distance(*b) <= 2.0 * MOTION_RADIUS + 1e-4);
+        }
+        engine.set_held(1, false);
+    }
+
+    // A locked voice stays put too, and carries on once unlocked
+    engine.set_motion(MotionMode::Orbit);
+    engine.toggle_lock(2);
+    let locked_at = engine.voices[2].position;
+    for _ in 0..8 {
+        engine.tick(Duration::from_millis(250), &mut events);
+    }
+    assert_eq!(engine.voices[2].position, locked_at);
+    assert!(engine
+        .drain_events()
+        .contains(&bus::EngineEvent::LockChanged {
+            voice: 2,
+            locked: true
+        }));
+    engine.toggle_lock(2);
+    engine.tick(Duration::from_millis(250), &mut events);
+    assert_ne!(engine.voices[2].position, locked_at);
+
+    // Dropping a voice re-centres its path there
+    engine.set_motion(MotionMode::Orbit);
+    let dropped = glam::Vec3::new(0.2, 0.0, 0.4);
+    engine.set_voice_position(0, dropped);
+    engine.advance_motion(Duration::from_millis(5));
+    assert!(engine.voices[0].position.distance(dropped) < 0.01);
+
+    // Undo returns to still
+    let inverse = engine.apply(&history::Command::SetMotion(MotionMode::Lissajous));
+    assert!(matches!(
+        inverse,
+        history::Command::SetMotion(MotionMode::Orbit)
+    ));
+    assert!(engine
+        .drain_events()
+        .contains(&bus::EngineEvent::MotionChanged(MotionMode::Lissajous)));
+}
+
+#[test]
+fn springs_pull_dropped_voices_home_and_keep_them_apart() {
+    use physics::PhysicsParams;
+    let mut engine = make_engine();
+    let home = engine.configs[0].base_position;
+    engine.set_physics(Some(PhysicsParams::DEFAULT));
+
+    // Dropped far away, the voice settles back near its base position
+    let dropped = glam::Vec3::new(-2.0, 0.0, 1.5);
+    engine.set_voice_position(0, dropped);
+    for _ in 0..600 {
+        engine.advance_motion(Duration::from_millis(16));
+    }
+    assert!(engine.voices[0].position.distance(home) < 0.05);
+
+    // A held voice stays put while the one it overlaps is pushed away
+    engine.set_held(1, true);
+    engine.set_voice_position(1, home);
+    for _ in 0..600 {
+        engine.advance_motion(Duration::from_millis(16));
+    }
+    assert_eq!(engine.voices[1].position, home);
+    let gap = engine.voices[0].position.distance(home);
+    assert!(gap > 0.05, "repelled by the held voice, gap={gap}");
+
+    // Switching off leaves voices where they are
+    let inverse = engine.apply(&history::Command::SetPhysics(None));
+    assert!(matches!(inverse, history::Command::SetPhysics(Some(_))));
+    let before = engine.voices[0].position;
+    engine.advance_motion(Duration::from_secs(1));
+    assert_eq!(engine.voices[0].position, before);
+
+    assert_eq!(
+        PhysicsParams::parse("8, 2.5").map(|p| (p.stiffness, p.damping)),
+        Some((8.0, 2.5))
+    );
+    assert_eq!(PhysicsParams::parse("8"), None);
+    assert_eq!(PhysicsParams::parse("-1,2"), None);
+}
+
+#[test]
+fn session_seed_makes_randomized_actions_reproducible() {
+    use rand::Rng;
+    use session::SessionConfig;
+    assert_eq!(
+        SessionConfig::parse("4217"),
+        Some(SessionConfig { seed: 4217 })
+    );
+    assert_eq!(
+        SessionConfig::parse(" seed=4217 ").map(|s| s.seed),
+        Some(4217)
+    );
+    assert_eq!(SessionConfig::parse("seed=abc"), None);
+    assert_eq!(SessionConfig { seed: 4217 }.label(), "seed=4217");
+
+    let build = |seed: u64| MusicEngine::new(make_engine().configs, EngineParams::default(), seed);
+    let (mut a, mut b) = (build(4217), build(4217));
+    assert_eq!(a.seed(), 4217);
+    let draws = |e: &mut MusicEngine| -> Vec<u32> { (0..4).map(|_| e.fork_rng().gen()).collect() };
+    assert_eq!(draws(&mut a), draws(&mut b));
+    assert_ne!(draws(&mut build(4217)), draws(&mut build(4218)));
+
+    // Dice rolls from forked RNGs replay identically
+    let locks = dice::ParamLocks::default();
+    let roll = |e: &mut MusicEngine| {
+        let mut rng = e.fork_rng();
+        let cmd = dice::roll_dice(e, &locks, &mut rng);
+        e.apply(&cmd);
+        (e.params.bpm, e.params.root_midi, e.params.detune_cents)
+    };
+    assert_eq!(roll(&mut a), roll(&mut b));
+}
+
+#[test]
+fn voice_allocator_caps_polyphony_and_steals_by_policy() {
+    let oldest = Polyphony {
+        max_notes: 2,
+        steal: StealPolicy::Oldest,
+    };
+    let quietest = Polyphony {
+        max_notes: 2,
+        steal: StealPolicy::Quietest,
+    };
+    let mut alloc = VoiceAllocator::new(vec![oldest, quietest]);
+
+    // Oldest: the third overlapping note cuts the first
+    assert!(alloc.allocate(0, 0.0, 2.0, 0.5, "a").is_empty());
+    assert!(alloc.allocate(0, 0.1, 2.0, 0.9, "b").is_empty());
+    assert_eq!(alloc.allocate(0, 0.2, 2.0, 0.7, "c"), vec!["a"]);
+    // Notes that already ended free their slot
+    assert!(alloc.allocate(0, 2.5, 3.0, 0.7, "d").is_empty());
+
+    // Quietest: the softest sounding note gives way, whatever its age
+    assert!(alloc.allocate(1, 0.0, 2.0, 0.9, "loud").is_empty());
+    assert!(alloc.allocate(1, 0.1, 2.0, 0.3, "soft").is_empty());
+    assert_eq!(alloc.allocate(1, 0.2, 2.0, 0.6, "mid"), vec!["soft"]);
+    assert_eq!(alloc.allocate(1, 0.3, 2.0, 0.8, "x"), vec!["mid"]);
+
+    // Unknown voices are ignored
+    assert!(alloc.allocate(5, 0.0, 1.0, 1.0, "z").is_empty());
+}
+
+#[test]
+fn auto_gain_follows_sounding_notes_smoothly() {
+    let mut alloc = VoiceAllocator::new(vec![Polyphony::DEFAULT; 3]);
+    for (i, voice) in [0, 0, 1, 1, 2, 2, 2, 2].into_iter().enumerate() {
+        alloc.allocate(voice, i as f64 * 0.1, 2.0, 0.8, i);
+    }
+    // Notes scheduled ahead count; finished ones don't
+    assert_eq!(alloc.sounding(0.0), 8);
+    assert_eq!(alloc.sounding(2.0), 0);
+
+    assert_eq!(auto_gain(0), 1.0);
+    assert_eq!(auto_gain(HEADROOM_NOTES as usize), 1.0);
+    let dense = auto_gain(4 * HEADROOM_NOTES as usize);
+    assert!((dense - 0.5).abs() < 1e-6);
+
+    // One time constant covers about 63% of the way, and it settles
+    let mut gain = AutoGain::default();
+    let g = gain.update(16, AUTO_GAIN_TIME_CONSTANT_SEC);
+    assert!((g - (1.0 - 0.5 * (1.0 - (-1.0f32).exp()))).abs() < 1e-4);
+    for _ in 0..200 {
+        gain.update(16, 1.0 / 60.0);
+    }
+    assert!((gain.gain - 0.5).abs() < 1e-3);
+    // A zero-length frame leaves it alone
+    assert_eq!(gain.update(1, 0.0), gain.gain);
+}
+
+#[test]
+fn presets_lerp_and_morph_over_bars() {
+    use preset::{preset_bank, Preset};
+    let bank = preset_bank();
+    assert!(bank.len() >= 4);
+    let (a, b) = (&bank[0], &bank[1]);
+
+    let start = Preset::lerp(a, b, 0.0);
+    assert_eq!(start.params.bpm, a.params.bpm);
+    assert_eq!(start.name, a.name);
+    let mid = Preset::lerp(a, b, 0.25);
+    assert!((mid.params.bpm - (a.params.bpm * 0.75 + b.params.bpm * 0.25)).abs() < 1e-3);
+    assert!(std::ptr::eq(mid.params.scale, a.params.scale));
+    let late = Preset::lerp(a, b, 0.75);
+    assert!(std::ptr::eq(late.params.scale, b.params.scale));
+    assert_eq!(Preset::lerp(a, b, 1.0).params.root_midi, b.params.root_midi);
+    let p = Preset::lerp(a, b, 0.5).voices[0].base_duration;
+    assert!((p - (a.voices[0].base_duration + b.voices[0].base_duration) / 2.0).abs() < 1e-5);
+
+    // Morph over one bar: tempo glides, then lands exactly on the target
+    let mut engine = MusicEngine::new(a.voices.clone(), a.params.clone(), 7);
+    let inverse = engine.apply(&history::Command::MorphToPreset {
+        preset: Box::new(b.clone()),
+        bars: 1,
+    });
+    let mut events = Vec::new();
+    let mut seen = Vec::new();
+    for _ in 0..(STEPS_PER_BAR + 2) {
+        engine.schedule(
+            engine.step_duration_sec() * seen.len() as f64,
+            0.0,
+            &mut events,
+        );
+        seen.push(engine.params.bpm);
+    }
+    assert!(seen
+        .iter()
+        .any(|&bpm| bpm < a.params.bpm && bpm > b.params.bpm));
+    assert_eq!(engine.params.bpm, b.params.bpm);
+    assert_eq!(engine.params.root_midi, b.params.root_midi);
+    assert_eq!(engine.configs[0].base_duration, b.voices[0].base_duration);
+    assert!(engine
+        .drain_events()
+        .contains(&bus::EngineEvent::PresetChanged(b.name)));
+
+    // Undo jumps back to where the morph started
+    engine.apply(&inverse);
+    assert_eq!(engine.params.bpm, a.params.bpm);
+    assert!(std::ptr::eq(engine.params.scale, a.params.scale));
+    assert_eq!(engine.configs[2].octave_offset, a.voices[2].octave_offset);
+}
+
+#[test]
+fn adsr_envelope_shapes_attack_decay_sustain_and_release() {
+    let linear = Adsr {
+        attack_sec: 0.1,
+        decay_sec: 0.2,
+        sustain: 0.5,
+        release_sec: 0.4,
+        curve: EnvelopeCurve::Linear,
+    };
+    let close = |a: f32, b: f32| (a - b).abs() < 1e-4;
+    assert_eq!(linear.level_at(-0.01, 1.0), 0.0);
+    assert!(close(linear.level_at(0.05, 1.0), 0.5));
+    assert!(close(linear.level_at(0.1, 1.0), 1.0));
+    assert!(close(linear.level_at(0.2, 1.0), 0.75));
+    assert!(close(linear.level_at(0.8, 1.0), 0.5));
+    // Release runs from the sustain level to silence
+    assert!(close(linear.level_at(1.2, 1.0), 0.25));
+    assert_eq!(linear.level_at(1.41, 1.0), 0.0);
+    // A gate shorter than the attack releases from wherever the attack got to
+    assert!(close(linear.gate_level(0.05), 0.5));
+    assert!(close(linear.level_at(0.25, 0.05), 0.25));
+
+    let exp = Adsr {
+        curve: EnvelopeCurve::Exponential,
+        ..linear
+    };
+    let mid_decay = exp.level_at(0.2, 1.0);
+    assert!(mid_decay > 0.5 && mid_decay < linear.level_at(0.2, 1.0));
+    assert!(close(exp.level_at(1.0 - 1e-3, 1.0), 0.5));
+    assert!(exp.level_at(1.2, 1.0) < linear.level_at(1.2, 1.0));
+    assert_eq!(exp.level_at(1.41, 1.0), 0.0);
+}
+
+#[test]
+fn voice_filter_tracks_keys_opens_with_envelope_and_filters() {
+    let f = VoiceFilter {
+        mode: FilterMode::Lowpass,
+        cutoff_hz: 1000.0,
+        resonance: 0.707,
+        env_octaves: 2.0,
+        key_tracking: 0.5,
+    };
+    let close = |a: f32, b: f32| (a - b).abs() < 0.5;
+    assert!(close(f.base_cutoff(KEY_TRACK_REF_HZ), 1000.0));
+    // Two octaves up the note, half tracking moves the cutoff one octave
+    assert!(close(f.base_cutoff(KEY_TRACK_REF_HZ * 4.0), 2000.0));
+    assert!(close(f.cutoff_at(KEY_TRACK_REF_HZ, 1.0), 4000.0));
+    assert!(close(f.cutoff_at(KEY_TRACK_REF_HZ, 0.5), 2000.0));
+    assert_eq!(f.cutoff_at(KEY_TRACK_REF_HZ * 64.0, 1.0), 20_000.0);
+
+    // The SVF passes tones below the cutoff and attenuates those far above
+    let sr = 48_000.0;
+    let rms = |freq: f32, mode: FilterMode| {
+        let mut svf = SvfState::default();
+        let mut sum = 0.0;
+        for n in 0..4800 {
+            let x = (std::f32::consts::TAU * freq * n as f32 / sr).sin();
+            let y = svf.process(x, mode, 1000.0, 0.707, sr);
+            if n >= 2400 {
+                sum += y * y;
+            }
+        }
+        (sum / 2400.0).sqrt()
+    };
+    let unity = std::f32::consts::FRAC_1_SQRT_2;
+    assert!(rms(100.0, FilterMode::Lowpass) > 0.95 * unity);
+    assert!(rms(8000.0, FilterMode::Lowpass) < 0.05 * unity);
+    assert!(rms(1000.0, FilterMode::Bandpass) > rms(100.0, FilterMode::Bandpass) * 4.0);
+}
+
+#[test]
+fn unison_layers_spread_detune_and_pan_symmetrically() {
+    assert_eq!(Unison::OFF.layers().len(), 1);
+    assert_eq!(Unison::OFF.layers()[0].detune_cents, 0.0);
+    assert_eq!(Unison::OFF.layer_gain(), 1.0);
+
+    let u = Unison {
+        voices: 3,
+        detune_cents: 20.0,
+        stereo_spread: 0.5,
+    };
+    let layers = u.layers();
+    let detunes: Vec<f32> = layers.iter().map(|l| l.detune_cents).collect();
+    let pans: Vec<f32> = layers.iter().map(|l| l.pan).collect();
+    assert_eq!(detunes, vec![-10.0, 0.0, 10.0]);
+    assert_eq!(pans, vec![-0.5, 0.0, 0.5]);
+    // Start phases are staggered
+    assert!(layers[0].phase != layers[1].phase);
+    assert!((u.layer_gain() - 1.0 / 3f32.sqrt()).abs() < 1e-6);
+}
+
+#[test]
+fn wavetables_load_play_and_cycle_with_the_waveform_key() {
+    // Any cycle length is resampled, DC-free and peak-normalized
+    let square: Vec<f32> = (0..100).map(|i| if i < 50 { 3.0 } else { 1.0 }).collect();
+    let table = Wavetable::from_samples("square", &square).unwrap();
+    assert_eq!(table.samples().len(), WAVETABLE_LEN);
+    assert!((table.sample(0.25) - 1.0).abs() < 1e-4);
+    assert!((table.sample(0.75) + 1.0).abs() < 1e-4);
+    assert!(Wavetable::from_samples("flat", &[0.5; 64]).is_none());
+
+    // Harmonics recover the additive recipe (sines land in the imaginary part)
+    let organ = &builtin_wavetables()[WAVETABLE_ORGAN as usize];
+    let (real, imag) = organ.harmonics(6);
+    assert_eq!((real[0], imag[0]), (0.0, 0.0));
+    assert!(real.iter().all(|r| r.abs() < 1e-3));
+    let ratio = imag[2] / imag[1];
+    assert!((ratio - 0.6).abs() < 1e-3, "{ratio}");
+    assert!(imag[5].abs() < 1e-3);
+
+    let mut engine = make_engine();
+    let builtins = engine.wavetables.len();
+    let imported = engine.load_wavetable(table);
+    assert_eq!(imported, Waveform::Wavetable(builtins as u16));
+
+    let inverse = engine.apply(&history::Command::SetWaveform {
+        voice: 0,
+        waveform: imported,
+    });
+    assert_eq!(engine.configs[0].waveform, imported);
+    assert!(engine
+        .drain_events()
+        .contains(&bus::EngineEvent::WaveformChanged {
+            voice: 0,
+            waveform: imported,
+        }));
+    engine.apply(&inverse);
+    assert_eq!(engine.configs[0].waveform, Waveform::Sine);
+
+    // The cycle visits every table once, then wraps back to a sine
+    let mut w = Waveform::Sine;
+    let mut seen = Vec::new();
+    for _ in 0..(3 + engine.wavetables.len()) {
+        w = w.next(engine.wavetables.len());
+        seen.push(w);
+    }
+    assert_eq!(seen[2], Waveform::Wavetable(0));
+    assert_eq!(seen[seen.len() - 2], imported);
+    assert_eq!(*seen.last().unwrap(), Waveform::Sine);
+}
+
+#[test]
+fn fm_index_follows_its_envelope_and_bends_the_carrier_phase() {
+    let bell = FmParams::BELL;
+    assert_eq!(bell.modulator_hz(200.0), 700.0);
+    // Bright at the strike, fading while the note is still held
+    let strike = bell.index_at(0.001, 2.0);
+    assert!((strike - bell.index).abs() < 1e-3);
+    assert!(bell.index_at(1.0, 2.0) < 0.1 * strike);
+    assert_eq!(bell.index_at(3.0, 2.0), 0.0);
+
+    // Phase offset is bounded by index / 2π and vanishes with the index
+    let bound = bell.index / std::f32::consts::TAU;
+    let max = (0..2000)
+        .map(|i| bell.phase_offset(200.0, i as f32 * 1e-5, 2.0).abs())
+        .fold(0.0, f32::max);
+    assert!(max > 0.5 * bound && max <= bound + 1e-6);
+    assert_eq!(bell.phase_offset(200.0, 3.0, 2.0), 0.0);
+
+    // The electric piano keeps some index through its sustain
+    let ep = FmParams::ELECTRIC_PIANO;
+    assert!((ep.index_at(1.5, 2.0) - ep.index * 0.15).abs() < 0.05);
+}
+
+#[test]
+fn sampler_repitches_by_rate_and_wraps_inside_its_loop() {
+    let sample = Sample::new("ramp", 10, (0..20).map(|i| i as f32).collect()).unwrap();
+    assert_eq!(sample.duration_sec(), 2.0);
+    assert!((sample.read(0.25) - 2.5).abs() < 1e-6);
+    assert_eq!(sample.read(-0.1), 0.0);
+    assert_eq!(sample.read(2.5), 0.0);
+    assert!(Sample::new("empty", 44_100, Vec::new()).is_none());
+
+    let mut sampler = SamplerConfig::one_shot(0, 220.0);
+    assert_eq!(sampler.playback_rate(440.0), 2.0);
+    // One-shots run straight through (and past the end, where reads are silent)
+    assert_eq!(sampler.position_at(2.0, 1.5, 2.0), 3.0);
+
+    assert_eq!(
+        LoopRegion::parse(" 0.5, 1.5"),
+        Some(LoopRegion {
+            start_sec: 0.5,
+            end_sec: 1.5
+        })
+    );
+    assert_eq!(LoopRegion::parse("1.5,0.5"), None);
+    assert_eq!(LoopRegion::parse("x,1"), None);
+    sampler.loop_region = LoopRegion::parse("0.5,1.5");
+    assert!((sampler.position_at(2.0, 1.0, 1.0) - 1.0).abs() < 1e-6);
+    assert!((sampler.position_at(2.0, 1.75, 1.0) - 0.75).abs() < 1e-6);
+    // A loop running past the recording is ignored
+    assert!(sampler.active_loop(1.0).is_none());
+
+    let mut engine = make_engine();
+    let index = engine.load_sample(sample);
+    assert_eq!(index, 0);
+    let inverse = engine.apply(&history::Command::SetSampler {
+        voice: 1,
+        sampler: Some(sampler),
+    });
+    assert_eq!(engine.configs[1].sampler, Some(sampler));
+    assert!(engine
+        .drain_events()
+        .contains(&bus::EngineEvent::SamplerChanged {
+            voice: 1,
+            sample: Some(0),
+        }));
+    engine.apply(&inverse);
+    assert_eq!(engine.configs[1].sampler, None);
+}
+
+#[test]
+fn master_eq_parses_clamps_and_shapes_the_spectrum() {
+    let eq = MasterEq::parse("-3, 0,20").unwrap();
+    assert_eq!(eq.bands[0].gain_db, -3.0);
+    assert_eq!(eq.bands[2].gain_db, MAX_EQ_GAIN_DB);
+    assert!(MasterEq::parse("1,2").is_none());
+    assert!(MasterEq::parse("1,x,2").is_none());
+
+    let sr = 48_000;
+    // Steady-state level of a sine at `freq` through `eq`, relative to unfiltered (dB)
+    let gain_db = |eq: &MasterEq, freq: f32| {
+        let mut samples: Vec<f32> = (0..sr)
+            .map(|n| (std::f32::consts::TAU * freq * n as f32 / sr as f32).sin())
+            .collect();
+        eq.process_interleaved(&mut samples, 1, sr as u32);
+        let tail = &samples[sr / 2..];
+        let rms = (tail.iter().map(|s| s * s).sum::<f32>() / tail.len() as f32).sqrt();
+        20.0 * (rms / std::f32::consts::FRAC_1_SQRT_2).log10()
+    };
+    // Flat passes everything through
+    assert!(gain_db(&MasterEq::FLAT, 1000.0).abs() < 0.05);
+
+    let mut eq = MasterEq::FLAT;
+    eq.set_gain(0, 6.0);
+    eq.set_gain(1, -6.0);
+    eq.set_gain(3, 6.0); // no such band
+    assert!((gain_db(&eq, 30.0) - 6.0).abs() < 0.3);
+    assert!((gain_db(&eq, 1000.0) + 6.0).abs() < 0.3);
+    assert!(gain_db(&eq, 15_000.0).abs() < 0.3);
+
+    // Interleaved channels are filtered independently
+    let mut stereo = vec![1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+    eq.process_interleaved(&mut stereo, 2, sr as u32);
+    assert!(stereo.iter().skip(1).step_by(2).all(|s| *s == 0.0));
+}
+
+#[test]
+fn sidechain_ducks_the_other_voices_after_each_trigger_note() {
+    assert_eq!(Sidechain::parse("0,9"), Some(Sidechain::DEFAULT));
+    let custom = Sidechain::parse("2, 6, 0.02, 0.5").unwrap();
+    assert_eq!((custom.trigger_voice, custom.release_sec), (2, 0.5));
+    assert!(Sidechain::parse("0,9,0.1").is_none());
+    assert!(Sidechain::parse("0,-3").is_none());
+
+    let sc = Sidechain::DEFAULT;
+    assert!(!sc.ducks(0) && sc.ducks(1));
+    assert!((20.0 * sc.ducked_gain().log10() + 9.0).abs() < 1e-4);
+
+    // The follower settles at the ducked gain while open, back at unity after
+    let mut follower = DuckFollower::default();
+    let dt = 1.0 / 1000.0;
+    let mut gain = 1.0;
+    for _ in 0..50 {
+        gain = follower.next(&sc, true, dt);
+    }
+    assert!((gain - sc.ducked_gain()).abs() < 1e-3);
+    for _ in 0..1000 {
+        gain = follower.next(&sc, false, dt);
+    }
+    assert!((gain - 1.0).abs() < 1e-3);
+
+    let mut engine = make_engine();
+    let inverse = engine.apply(&history::Command::SetSidechain(Some(sc)));
+    assert_eq!(engine.sidechain(), Some(sc));
+    assert!(engine
+        .drain_events()
+        .contains(&bus::EngineEvent::SidechainChanged(Some(sc))));
+    engine.apply(&inverse);
+    assert_eq!(engine.sidechain(), None);
+
+    // Ducking only ever takes level away from the offline mix
+    let listener = glam::Vec3::new(0.0, 0.0, 2.5);
+    let mut plain = make_engine();
+    let dry = ambisonics::render_bformat(&mut plain, listener, 2.0, 8000);
+    let mut ducked = make_engine();
+    ducked.set_sidechain(Some(Sidechain {
+        depth_db: 40.0,
+        ..sc
+    }));
+    let wet = ambisonics::render_bformat(&mut ducked, listener, 2.0, 8000);
+    let energy = |s: &[f32]| s.iter().map(|x| x * x).sum::<f32>();
+    assert!(energy(&wet) < energy(&dry));
+}
+
+#[test]
+fn voice_sends_are_clamped_undoable_and_survive_presets() {
+    let mut engine = make_engine();
+    assert_eq!(engine.configs[1].sends, VoiceSends::DEFAULT);
+    engine.drain_events();
+
+    let wet = VoiceSends {
+        delay: 0.5,
+        reverb: 3.0,
+        level: 0.8,
+    };
+    let inverse = engine.apply(&history::Command::SetSends {
+        voice: 1,
+        sends: wet,
+    });
+    let clamped = VoiceSends {
+        delay: 0.5,
+        reverb: VoiceSends::MAX,
+        level: 0.8,
+    };
+    assert_eq!(engine.configs[1].sends, clamped);
+    assert!(engine
+        .drain_events()
+        .contains(&bus::EngineEvent::SendsChanged {
+            voice: 1,
+            sends: clamped,
+        }));
+    // Other voices keep their own sends
+    assert_eq!(engine.configs[0].sends, VoiceSends::DEFAULT);
+
+    // Sends are mixer state: switching presets leaves them alone
+    let bank = preset::preset_bank();
+    engine.apply_preset(&bank[1]);
+    assert_eq!(engine.configs[1].sends, clamped);
+
+    engine.apply(&inverse);
+    assert_eq!(engine.configs[1].sends, VoiceSends::DEFAULT);
+    let bad = engine.apply(&history::Command::SetSends {
+        voice: 9,
+        sends: wet,
+    });
+    assert!(matches!(bad, history::Command::Batch(c) if c.is_empty()));
+}
+
+#[test]
+fn recording_keeps_exactly_the_frames_between_start_and_stop() {
+    let sr = 48_000;
+    assert_eq!(recording::frame_at(1.5, sr), 72_000);
+    assert_eq!(recording::frame_at(-1.0, sr), 0);
+
+    // Start and stop fall mid-quantum; blocks arrive on 128-frame boundaries
+    let start = recording::frame_at(0.01, sr);
+    let stop = recording::frame_at(0.02, sr);
+    let mut take = recording::Recording::new(sr, start);
+    let block = 128;
+    let mut first = 0;
+    while first < stop + block {
+        let left: Vec<f32> = (first..first + block).map(|f| f as f32).collect();
+        let right: Vec<f32> = left.iter().map(|x| -x).collect();
+        take.push_block(first, &left, &right);
+        if first > start {
+            take.stop_at(stop);
+        }
+        first += block;
+    }
+    assert_eq!(take.frames(), (stop - start) as usize);
+    assert!((take.duration_sec() - 0.01).abs() < 1e-6);
+
+    let wav = take.to_wav();
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(u16::from_le_bytes([wav[22], wav[23]]), 2);
+    assert_eq!(wav.len(), 44 + take.frames() * 2 * 2);
+
+    // A block the capture missed leaves silence in place, not a shift
+    let mut gappy = recording::Recording::new(sr, 0);
+    gappy.push_block(0, &[0.5; 4], &[0.5; 4]);
+    gappy.push_block(8, &[0.5; 4], &[0.5; 4]);
+    gappy.stop_at(16);
+    assert_eq!(gappy.frames(), 16);
+    let wav = gappy.to_wav();
+    let sample = |frame: usize| i16::from_le_bytes([wav[44 + frame * 4], wav[45 + frame * 4]]);
+    assert!(sample(2) > 0 && sample(5) == 0 && sample(9) > 0 && sample(14) == 0);
+}
+
+#[test]
+fn reverb_presets_generate_distinct_decaying_stereo_impulses() {
+    assert_eq!(reverb::reverb_preset_by_id("PLATE"), Some(&reverb::PLATE));
+    assert!(reverb::reverb_preset_by_id("cathedral").is_none());
+
+    let sr = 8000.0;
+    let energy = |s: &[f32]| s.iter().map(|x| x * x).sum::<f32>();
+    for preset in reverb::REVERB_PRESETS {
+        let [left, right] = preset.impulse(sr);
+        assert_eq!(
+            left.len(),
+            (sr * preset.length_sec) as usize,
+            "{}",
+            preset.id
+        );
+        assert_ne!(
+            left, right,
+            "{}: channels should be decorrelated",
+            preset.id
+        );
+        // Tails decay: the last quarter carries far less than the first
+        let q = left.len() / 4;
+        assert!(
+            energy(&left[3 * q..]) < 0.2 * energy(&left[..q]),
+            "{}",
+            preset.id
+        );
+        // Deterministic, so presets sound the same every session
+        assert_eq!(preset.impulse(sr)[0], left);
+    }
+
+    // Pre-delay leaves the start silent; the room's first taps land within ~60 ms
+    let [shimmer, _] = reverb::SHIMMER.impulse(sr);
+    assert!(shimmer[..(0.02 * sr) as usize - 1]
+        .iter()
+        .all(|s| *s == 0.0));
+    let [room, _] = reverb::ROOM.impulse(sr);
+    let peak = room.iter().map(|s| s.abs()).fold(0.0, f32::max);
+    let peak_at = room.iter().position(|s| s.abs() == peak).unwrap();
+    assert!(peak_at < (0.07 * sr) as usize);
+}
+
+#[test]
+fn bounce_schedule_is_deterministic_and_covers_the_whole_length() {
+    assert_eq!(bounce::parse_bounce_seconds(" 90 "), Some(90.0));
+    assert_eq!(
+        bounce::parse_bounce_seconds("3600"),
+        Some(bounce::MAX_BOUNCE_SEC)
+    );
+    assert!(bounce::parse_bounce_seconds("0").is_none());
+    assert!(bounce::parse_bounce_seconds("long").is_none());
+
+    let engine = make_engine();
+    let step = 128.0 / 48_000.0;
+    let a = bounce::schedule_piece(&mut engine.clone(), 20.0, step);
+    let b = bounce::schedule_piece(&mut engine.clone(), 20.0, step);
+    let key = |n: &bounce::ScheduledNote| {
+        (
+            n.event.voice_index,
+            n.event.start_time_sec,
+            n.event.frequency_hz,
+        )
+    };
+    assert!(!a.is_empty());
+    assert_eq!(
+        a.iter().map(key).collect::<Vec<_>>(),
+        b.iter().map(key).collect::<Vec<_>>()
+    );
+    // Stamped on a grid starting at 0, in order, and running to the end
+    assert!(a
+        .windows(2)
+        .all(|w| w[0].event.start_time_sec <= w[1].event.start_time_sec));
+    assert!(a[0].event.start_time_sec >= 0.0);
+    assert!(a.last().unwrap().event.start_time_sec > 18.0);
+    assert!(a.iter().all(|n| n.event.start_time_sec <= 20.0 + step));
+}
+
+#[test]
+fn midi_out_maps_voices_to_channels_with_an_offset() {
+    use midi_out::*;
+    let mut map = MidiOutMap::new(3);
+    assert_eq!(map.channels, vec![0, 1, 2]);
+    assert_eq!(
+        MidiOutMap::parse_channels("1, 2,10", 3),
+        Some(vec![0, 1, 9])
+    );
+    assert!(MidiOutMap::parse_channels("1,2", 3).is_none());
+    assert!(MidiOutMap::parse_channels("1,2,17", 3).is_none());
+    map.channels = vec![0, 1, 9];
+    map.set_latency_ms(-2000.0);
+    assert_eq!(map.latency_ms, -MAX_MIDI_LATENCY_MS);
+    map.set_latency_ms(20.0);
+
+    // A4 a little sharp on the pad: bend up, note-on, note-off later
+    let ev = NoteEvent {
+        voice_index: 2,
+        frequency_hz: 440.0 * 2f32.powf(0.4 / 12.0),
+        velocity: 1.0,
+        start_time_sec: 1.0,
+        duration_sec: 0.5,
+    };
+    let [bend, on, off] = map.note_messages(&ev).unwrap();
+    assert_eq!(bend.bytes[0], 0xE9);
+    assert!((u16::from(bend.bytes[2]) << 7 | u16::from(bend.bytes[1])) > 8192);
+    assert_eq!(on.bytes, [0x99, 69, 127]);
+    assert_eq!(off.bytes, [0x89, 69, 0]);
+    assert!((on.at_sec - 1.02).abs() < 1e-9 && (off.at_sec - 1.52).abs() < 1e-9);
+    let unmapped = NoteEvent {
+        voice_index: 5,
+        ..ev
+    };
+    assert!(map.note_messages(&unmapped).is_none());
+
+    // Shared channels are silenced once
+    map.channels = vec![0, 0, 9];
+    assert_eq!(map.all_notes_off(), vec![[0xB0, 123, 0], [0xB9, 123, 0]]);
+
+    // Audio-clock times become performance timestamps; the past means now
+    assert_eq!(to_performance_ms(10.5, 10.0, 1000.0), 1500.0);
+    assert_eq!(to_performance_ms(9.0, 10.0, 1000.0), 1000.0);
+}
+
+#[test]
+fn offline_effects_echo_on_the_beat_and_decay() {
+    use effects::*;
+    assert!((beat_delay_sec(120.0, 0.75) - 0.375).abs() < 1e-6);
+
+    // An impulse comes back one delay later, then fades repeat by repeat
+    let sr = 8000.0;
+    let mut delay = TempoDelay::new(sr, 0.1);
+    let wet: Vec<f32> = (0..4000)
+        .map(|i| delay.process(if i == 0 { 1.0 } else { 0.0 }, 0.6))
+        .collect();
+    let first_peak =
+        |range: std::ops::Range<usize>| wet[range].iter().map(|s| s.abs()).fold(0.0, f32::max);
+    assert!(wet[..790].iter().all(|s| s.abs() < 1e-6));
+    let (one, two) = (first_peak(800..1600), first_peak(1600..2400));
+    assert!(one > 0.05 && two < one, "{one} {two}");
+
+    // The FDN tail is decorrelated across its lines and dies away
+    let mut reverb = FdnReverb::new(sr, 1.0, 0.3);
+    let tail: Vec<[f32; 4]> = (0..16000)
+        .map(|i| reverb.process(if i == 0 { 1.0 } else { 0.0 }))
+        .collect();
+    let energy = |range: std::ops::Range<usize>, line: usize| {
+        tail[range].iter().map(|o| o[line] * o[line]).sum::<f32>()
+    };
+    assert!(energy(0..4000, 0) > 0.0);
+    assert!(energy(12000..16000, 0) < 1e-3 * energy(0..4000, 0));
+    assert_ne!(tail[2000][0], tail[2000][1]);
+}
+
+#[test]
+fn long_silent_tails_settle_to_zero_without_denormals() {
+    use effects::*;
+    let sr = 8000.0;
+    let settled = |s: f32| s == 0.0 || s.is_normal();
+
+    // A minute of silence after one impulse: every sample stays normal, and
+    // the loops end on exact zero rather than creeping through subnormals
+    let mut delay = TempoDelay::new(sr, 0.1);
+    let mut reverb = FdnReverb::new(sr, 3.0, 0.3);
+    let mut svf = SvfState::default();
+    let band = eq::EqBand {
+        kind: eq::EqBandKind::Peaking,
+        freq_hz: 1000.0,
+        gain_db: 6.0,
+        q: 4.0,
+    };
+    let mut biquad = eq::Biquad::for_band(&band, sr);
+    let mut last = [1.0f32; 4];
+    for i in 0..(60.0 * sr) as usize {
+        let x = if i == 0 { 1.0 } else { 0.0 };
+        let echo = delay.process(x, 0.6);
+        let tail = reverb.process(x);
+        let filtered = svf.process(x, FilterMode::Lowpass, 500.0, 4.0, sr);
+        let eq = biquad.process(x);
+        assert!(settled(echo) && tail.into_iter().all(settled), "sample {i}");
+        assert!(settled(filtered) && settled(eq), "sample {i}");
+        last = [echo, tail[0], filtered, eq];
+    }
+    assert_eq!(last, [0.0; 4]);
+
+    // The DC blocker removes an offset but passes a tone
+    let mut dc = DcBlocker::new(sr);
+    let offset: Vec<f32> = (0..8000).map(|_| dc.process(0.5)).collect();
+    assert!(offset[0] > 0.49 && offset[7999].abs() < 1e-3);
+    let mut dc = DcBlocker::new(sr);
+    let tone: Vec<f32> = (0..8000)
+        .map(|i| dc.process((std::f32::consts::TAU * 440.0 * i as f32 / sr).sin()))
+        .collect();
+    let peak = tone[4000..].iter().map(|s| s.abs()).fold(0.0, f32::max);
+    assert!(peak > 0.98 && peak < 1.02, "{peak}");
+    assert_eq!(flush_denormal(1e-30), 0.0);
+    assert_eq!(flush_denormal(-0.25), -0.25);
+}
+
+#[test]
+fn midi_clock_locks_tempo_and_phase() {
+    use clock_sync::*;
+    // 120 BPM = 48 pulses a second, Start at 1 s
+    let mut follower = MidiClockFollower::default();
+    let pulse = 60.0 / 120.0 / MIDI_CLOCK_PPQ as f64;
+    assert!(follower.lock(1.0).is_none());
+    follower.handle(0xFA, 1.0);
+    for i in 0..50 {
+        follower.handle(0xF8, 1.0 + i as f64 * pulse);
+    }
+    assert!((follower.bpm().unwrap() - 120.0).abs() < 1e-3);
+    let lock = follower.lock(2.05).unwrap();
+    assert_eq!(lock.step, 4);
+    assert!((lock.at_sec - 2.0).abs() < 1e-9);
+    assert!((lock.step_duration_sec() - 0.25).abs() < 1e-9);
+
+    // Clock gone quiet, or transport stopped: back to the internal tempo
+    assert!(follower.lock(2.0 + CLOCK_TIMEOUT_SEC + 0.1).is_none());
+    follower.handle(0xFC, 2.1);
+    assert!(follower.lock(2.05).is_none());
+    follower.handle(0xFB, 2.1);
+    assert!(follower.lock(2.05).is_some());
+    // Start begins counting from the downbeat again
+    follower.handle(0xFA, 2.1);
+    assert!(follower.lock(2.1).is_none());
+    follower.handle(0xF8, 2.1);
+    assert_eq!(follower.lock(2.1).unwrap().step, 0);
+
+    // The engine adopts the tempo and lands its steps on the sender's grid
+    let mut engine = make_engine();
+    engine.set_bpm(97.0);
+    let mut events = Vec::new();
+    engine.schedule(0.3, 0.0, &mut events);
+    engine.lock_to_transport(&lock);
+    assert_eq!(engine.params.bpm, 120.0);
+    events.clear();
+    for i in 0..40 {
+        engine.schedule(2.0 + i as f64 * 0.1, 0.2, &mut events);
+    }
+    assert!(!events.is_empty());
+    for ev in &events {
+        let steps = (ev.start_time_sec - lock.at_sec) / 0.25;
+        assert!(
+            (steps - steps.round()).abs() < 1e-6,
+            "{}",
+            ev.start_time_sec
+        );
+    }
+}
+
+#[test]
+fn device_requests_parse_and_find_outputs_by_name() {
+    use device::*;
+    assert_eq!(DeviceRequest::parse_sample_rate("44100"), Some(44_100.0));
+    assert!(DeviceRequest::parse_sample_rate("1000").is_none());
+    assert!(DeviceRequest::parse_sample_rate("fast").is_none());
+
+    assert_eq!(
+        BufferRequest::parse(" Playback"),
+        Some(BufferRequest::Playback)
+    );
+    assert_eq!(
+        BufferRequest::parse("256"),
+        Some(BufferRequest::Frames(256))
+    );
+    assert_eq!(
+        BufferRequest::parse("1000000"),
+        Some(BufferRequest::Frames(MAX_BUFFER_FRAMES))
+    );
+    assert!(BufferRequest::parse("0").is_none());
+    assert_eq!(BufferRequest::Balanced.category(), Some("balanced"));
+    assert_eq!(BufferRequest::Frames(480).seconds(48_000.0), Some(0.01));
+    assert!(BufferRequest::Interactive.seconds(48_000.0).is_none());
+
+    let outputs = vec![
+        ("a1".to_string(), "MacBook Speakers".to_string()),
+        ("b2".to_string(), "Focusrite Scarlett 2i2".to_string()),
+    ];
+    assert_eq!(find_by_name(&outputs, "scarlett"), Some("b2"));
+    assert!(find_by_name(&outputs, "motu").is_none());
+    assert!(find_by_name(&outputs, " ").is_none());
+}
+
+#[test]
+fn master_mixer_steps_mutes_and_round_trips() {
+    use mixer::*;
+    let mut m = MasterMixer::default();
+    m.nudge(3);
+    assert_eq!(m.volume, 1.0);
+    m.nudge(-4);
+    assert!((m.volume - (1.0 - 4.0 * VOLUME_STEP)).abs() < 1e-6);
+    m.toggle_mute();
+    assert_eq!(m.gain(), 0.0);
+    // Turning down keeps the mute, turning up lifts it
+    m.nudge(-1);
+    assert!(m.muted);
+    let stored = m.encode();
+    assert_eq!(stored, "0.75,muted");
+    assert_eq!(MasterMixer::parse(&stored), Some(m));
+    m.nudge(1);
+    assert!(!m.muted && (m.gain() - 0.8).abs() < 1e-6);
+
+    assert_eq!(MasterMixer::parse("3").unwrap().volume, 1.0);
+    assert!(MasterMixer::parse("0.5,loud").is_none());
+    assert!(MasterMixer::parse("NaN").is_none());
+    assert!(MasterMixer::parse("").is_none());
+}
+
+#[test]
+fn metronome_clicks_beats_and_counts_in_without_notes() {
+    use metronome::*;
+    assert_eq!(
+        Metronome::parse("on,9,0.8"),
+        Some(Metronome {
+            enabled: true,
+            level: 0.8,
+            count_in_bars: MAX_COUNT_IN_BARS,
+        })
+    );
+    assert_eq!(Metronome::parse("off").unwrap().count_in_bars, 0);
+    assert!(Metronome::parse("loud").is_none());
+    assert!(Metronome::parse("on,1,0.5,2").is_none());
+
+    let mut engine = make_engine();
+    let step = engine.step_duration_sec();
+    let mut events = Vec::new();
+    // Off by default: no clicks
+    engine.schedule(0.0, 4.0 * step, &mut events);
+    assert!(engine.drain_clicks().is_empty());
+
+    // One click per beat (two steps), accented on the bar line
+    engine.set_metronome(Metronome {
+        enabled: true,
+        ..Metronome::default()
+    });
+    assert!(engine
+        .drain_events()
+        .iter()
+        .any(|e| matches!(e, bus::EngineEvent::MetronomeChanged(m) if m.enabled)));
+    engine.restart_grid();
+    engine.schedule(10.0, 2.0 * STEPS_PER_BAR as f64 * step - 1e-6, &mut events);
+    let clicks = engine.drain_clicks();
+    assert_eq!(clicks.len(), 8);
+    assert_eq!(clicks.iter().filter(|c| c.accent).count(), 2);
+    assert!((clicks[1].at_sec - clicks[0].at_sec - 2.0 * step).abs() < 1e-9);
+
+    // Count-in: a bar of clicks from the bar line, then the notes come back
+    engine.set_metronome(Metronome {
+        enabled: false,
+        count_in_bars: 1,
+        ..Metronome::default()
+    });
+    engine.start_count_in();
+    events.clear();
+    engine.schedule(20.0, STEPS_PER_BAR as f64 * step - 1e-6, &mut events);
+    let clicks = engine.drain_clicks();
+    assert!(events.is_empty());
+    assert_eq!(clicks.len(), 4);
+    assert!(clicks[0].accent && (clicks[0].at_sec - 20.0).abs() < 1e-9);
+    for i in 1..40 {
+        engine.schedule(20.0 + i as f64 * step, 0.0, &mut events);
+    }
+    assert!(engine.drain_clicks().is_empty());
+    assert!(events
+        .iter()
+        .all(|e| e.start_time_sec >= 20.0 + STEPS_PER_BAR as f64 * step - 0.1));
+    assert!(!events.is_empty());
+}
+
+#[test]
+fn spectral_flux_finds_hits_and_ignores_steady_sound_and_decays() {
+    use onset::*;
+    assert_eq!(spectral_flux(&[1.0, 2.0], &[2.0, 1.0]), 0.5);
+    assert_eq!(PulseSource::from_id("MIC"), Some(PulseSource::Mic));
+    assert!(PulseSource::from_id("lasers").is_none());
+
+    // A steady hum with a little noise, a hit every 30 frames, then decay
+    let mut detector = OnsetDetector::default();
+    let mut hits = Vec::new();
+    let mut level = 0.0f32;
+    for frame in 0..120 {
+        let t = frame as f64 / 60.0;
+        level = if frame % 30 == 15 { 1.0 } else { level * 0.7 };
+        let jitter = 0.001 * ((frame * 7919) % 13) as f32;
+        let spectrum: Vec<f32> = (0..64)
+            .map(|bin| 0.05 + jitter + level / (1 + bin) as f32)
+            .collect();
+        if let Some(onset) = detector.process(&spectrum, t) {
+            assert!(onset.strength > 1.0);
+            hits.push(frame);
+        }
+    }
+    assert_eq!(hits, vec![15, 45, 75, 105]);
+
+    // dB input: silent bins count as zero, and silence never triggers
+    let mut detector = OnsetDetector::default();
+    for frame in 0..30 {
+        let silent = vec![f32::NEG_INFINITY; 16];
+        assert!(detector.process_db(&silent, frame as f64 / 60.0).is_none());
+    }
+}
+
+#[test]
+fn band_levels_split_bass_mids_and_highs() {
+    use onset::*;
+    // 100 Hz bins centred at 50, 150, 250..: two bass bins, mids up to 2 kHz
+    let mut spectrum = vec![-100.0f32; 64];
+    spectrum[0] = 0.0;
+    spectrum[1] = 0.0;
+    spectrum[30] = f32::NEG_INFINITY;
+    let [bass, mid, high] = band_levels(&spectrum, 100.0);
+    assert_eq!(bass, 1.0);
+    assert_eq!(mid, 0.0);
+    assert_eq!(high, 0.0);
+    assert_eq!(band_levels(&[], 100.0), [0.0; 3]);
+}
+
+#[test]
+fn stereo_width_scales_the_side_and_keeps_the_mono_sum() {
+    use stereo::*;
+    let apply = |l: f32, r: f32, width: f32| {
+        let (direct, cross) = width_gains(width);
+        (direct * l + cross * r, direct * r + cross * l)
+    };
+    // As mixed at 1, folded to mono at 0
+    assert_eq!(apply(0.8, 0.2, 1.0), (0.8, 0.2));
+    assert_eq!(apply(0.8, 0.2, 0.0), (0.5, 0.5));
+    // Any width leaves L + R alone and scales L - R
+    for width in [0.0, 0.5, 1.3, MAX_STEREO_WIDTH] {
+        let (l, r) = apply(0.8, 0.2, width);
+        assert!((l + r - 1.0).abs() < 1e-6);
+        assert!((l - r - 0.6 * width).abs() < 1e-6);
+    }
+    assert_eq!(parse_width("9"), Some(MAX_STEREO_WIDTH));
+    assert!(parse_width("wide").is_none());
+    assert_eq!(swirl_width(1.0, 0.0), 1.0);
+    assert!((swirl_width(1.0, 1.0) - (1.0 + SWIRL_WIDTH_DEPTH)).abs() < 1e-6);
+    assert_eq!(swirl_width(MAX_STEREO_WIDTH, 1.0), MAX_STEREO_WIDTH);
+}
+
+#[test]
+fn spectrum_layers_band_log_scale_and_trigger_the_scope() {
+    use spectrum::*;
+    assert_eq!(
+        VisualLayers::parse("scope, spectrum"),
+        Some(VisualLayers {
+            spectrum: true,
+            scope: true
+        })
+    );
+    assert_eq!(VisualLayers::parse("none"), Some(VisualLayers::default()));
+    assert_eq!(VisualLayers::parse("bars"), None);
+    let mut layers = VisualLayers::default();
+    for _ in 0..4 {
+        layers = layers.next();
+        assert!(layers.any() || layers == VisualLayers::default());
+    }
+    assert_eq!(layers, VisualLayers::default());
+
+    // A 1 kHz peak in a 1024-point spectrum at 48 kHz lights one band,
+    // about halfway along the log axis; low bands never come up empty-handed
+    let bin_hz = 48_000.0 / 1024.0;
+    let mut db = vec![f32::NEG_INFINITY; 512];
+    db[(1000.0 / bin_hz) as usize] = SPECTRUM_CEIL_DB;
+    let mut bands = [0.0; SPECTRUM_BANDS];
+    log_bands(&db, bin_hz, &mut bands);
+    let lit: Vec<usize> = (0..SPECTRUM_BANDS).filter(|&b| bands[b] > 0.99).collect();
+    let expected = ((1000.0f32 / SPECTRUM_MIN_HZ).ln() / (SPECTRUM_MAX_HZ / SPECTRUM_MIN_HZ).ln()
+        * SPECTRUM_BANDS as f32) as usize;
+    assert!(
+        !lit.is_empty() && lit.iter().all(|b| b.abs_diff(expected) <= 1),
+        "{lit:?}"
+    );
+    db.fill(-60.0);
+    log_bands(&db, bin_hz, &mut bands);
+    assert!(bands.iter().all(|b| (b - 0.5).abs() < 1e-6));
+
+    // Bars jump up and ease down
+    let mut bars = SpectrumBars::default();
+    bars.update(&db, bin_hz, 1.0 / 60.0);
+    assert!((bars.bands[0] - 0.5).abs() < 1e-6);
+    db.fill(f32::NEG_INFINITY);
+    bars.update(&db, bin_hz, 1.0 / 60.0);
+    assert!(bars.bands[0] > 0.3 && bars.bands[0] < 0.5);
+
+    // The scope starts on a rising zero crossing, whatever the block's phase
+    let tone = |phase: f32| -> Vec<f32> {
+        (0..1024)
+            .map(|i| (std::f32::consts::TAU * (i as f32 / 64.0 + phase)).sin())
+            .collect()
+    };
+    let (mut a, mut b) = ([0.0; SCOPE_POINTS], [0.0; SCOPE_POINTS]);
+    scope_trace(&tone(0.3), &mut a);
+    scope_trace(&tone(0.7), &mut b);
+    assert!(a.iter().zip(&b).all(|(x, y)| (x - y).abs() < 0.1));
+    assert!(a[0].abs() < 0.1 && a[2] > a[0]);
+
+    // The shader indexes its storage buffer with the same sizes
+    let wgsl = include_str!("../shaders/waves.wgsl");
+    assert!(wgsl.contains(&format!("const SPECTRUM_BANDS: u32 = {SPECTRUM_BANDS}u;")));
+    assert!(wgsl.contains(&format!("const SCOPE_POINTS: u32 = {SCOPE_POINTS}u;")));
+}
+
+#[test]
+fn themes_cycle_and_resolve_by_id() {
+    use theme::*;
+    assert_eq!(Theme::from_id("Ember"), Some(&EMBER));
+    assert_eq!(Theme::from_id(" contrast "), Some(&HIGH_CONTRAST));
+    assert_eq!(Theme::from_id("sepia"), None);
+
+    // The key visits every built-in once, then wraps
+    let mut seen = vec![THEMES[0].id];
+    let mut t = &THEMES[0];
+    for _ in 1..THEMES.len() {
+        t = t.next();
+        assert!(!seen.contains(&t.id));
+        seen.push(t.id);
+    }
+    assert_eq!(t.next().id, THEMES[0].id);
+
+    // Nebula keeps the original clear colour and voice palette
+    assert_eq!(NEBULA.background[0], [0.03, 0.04, 0.08]);
+    assert_eq!(NEBULA.voices[0], [0.18, 0.45, 1.05]);
+    for t in &THEMES {
+        let colours = t
+            .background
+            .iter()
+            .chain(&t.voices)
+            .chain([&t.accent, &t.bloom_tint]);
+        assert!(
+            colours.flatten().all(|c| (0.0..=2.0).contains(c)),
+            "{}",
+            t.id
+        );
+        assert!((0.0..=1.5).contains(&t.saturation));
+    }
+}
+
+#[test]
+fn colour_blind_safe_themes_keep_voices_apart() {
+    use theme::*;
+    // Machado et al. (2009) full-severity simulation matrices, on linear RGB
+    const PROTANOPIA: [[f32; 3]; 3] = [
+        [0.152286, 1.052583, -0.204868],
+        [0.114503, 0.786281, 0.099216],
+        [-0.003882, -0.048116, 1.051998],
+    ];
+    const DEUTERANOPIA: [[f32; 3]; 3] = [
+        [0.367322, 0.860646, -0.227968],
+        [0.280085, 0.672501, 0.047413],
+        [-0.011820, 0.042940, 0.968881],
+    ];
+    const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    // Displayed (sRGB-encoded, clipped) colour as seen through `m`
+    let seen = |m: &[[f32; 3]; 3], c: [f32; 3]| {
+        m.map(|row| {
+            let x = (row[0] * c[0] + row[1] * c[1] + row[2] * c[2]).clamp(0.0, 1.0);
+            if x <= 0.003_130_8 {
+                12.92 * x
+            } else {
+                1.055 * x.powf(1.0 / 2.4) - 0.055
+            }
+        })
+    };
+    let closest_voices = |t: &Theme, m: &[[f32; 3]; 3]| {
+        let v = t.voices.map(|c| seen(m, c));
+        [(0, 1), (0, 2), (1, 2)]
+            .map(|(a, b)| {
+                let d: f32 = (0..3).map(|k| (v[a][k] - v[b][k]).powi(2)).sum();
+                d.sqrt()
+            })
+            .into_iter()
+            .fold(f32::MAX, f32::min)
+    };
+
+    for t in [&HARBOUR, &OKABE_ITO] {
+        assert!(THEMES.contains(t));
+        assert!(t.name.contains("colour-blind safe"));
+        for m in [&IDENTITY, &PROTANOPIA, &DEUTERANOPIA] {
+            assert!(closest_voices(t, m) > 0.3, "{}", t.id);
+        }
+    }
+    // Unlike a palette that leans on red against green
+    assert!(closest_voices(&HIGH_CONTRAST, &DEUTERANOPIA) < 0.3);
+
+    // The flash limiter's state, as the composite reads it
+    let wgsl = include_str!("../shaders/exposure.wgsl");
+    assert!(wgsl.contains("flash_gain: f32,"));
+}
+
+#[test]
+fn visualizer_modes_cycle_and_crossfade() {
+    use visualizer::*;
+    assert_eq!(Visualizer::from_id("Tunnel"), Some(Visualizer::Tunnel));
+    assert_eq!(Visualizer::from_id("plasma"), None);
+    let mut v = Visualizer::default();
+    for _ in 0..Visualizer::ALL.len() {
+        v = v.next();
+    }
+    assert_eq!(v, Visualizer::Waves);
+
+    // Every mode has its entry point in the shared shader
+    let wgsl = custom_shader::with_custom(
+        include_str!("../shaders/waves.wgsl"),
+        include_str!("../shaders/custom.wgsl"),
+    );
+    for v in Visualizer::ALL {
+        assert!(
+            wgsl.contains(&format!("fn {}(", v.entry_point())),
+            "{}",
+            v.id()
+        );
+    }
+
+    // A switch fades the old mode out over VISUALIZER_FADE_SEC, eased
+    let mut fade = Crossfade::new(Visualizer::Waves);
+    assert_eq!(fade.outgoing_weight(), 0.0);
+    fade.switch(Visualizer::Tunnel);
+    assert_eq!(fade.previous, Some(Visualizer::Waves));
+    assert_eq!(fade.outgoing_weight(), 1.0);
+    let dt = VISUALIZER_FADE_SEC / 8.0;
+    let mut last = 1.0;
+    for _ in 0..4 {
+        fade.advance(dt);
+        assert!(fade.outgoing_weight() < last);
+        last = fade.outgoing_weight();
+    }
+    assert!((last - 0.5).abs() < 1e-4);
+
+    // Going back mid-fade reverses it rather than jumping
+    fade.advance(dt);
+    let before = fade.outgoing_weight();
+    fade.switch(Visualizer::Waves);
+    assert_eq!(fade.previous, Some(Visualizer::Tunnel));
+    assert!((fade.outgoing_weight() - (1.0 - before)).abs() < 1e-4);
+
+    // Late in a fade, a third mode fades out the one now mostly visible
+    fade.advance(dt * 2.0);
+    fade.switch(Visualizer::Particles);
+    assert_eq!(fade.previous, Some(Visualizer::Waves));
+    fade.advance(VISUALIZER_FADE_SEC);
+    assert_eq!(fade.previous, None);
+    assert_eq!(fade.current, Visualizer::Particles);
+    assert_eq!(fade.outgoing_weight(), 0.0);
+}
+
+#[test]
+fn note_bursts_scale_with_velocity_and_fill_the_pool_in_order() {
+    use particles::*;
+    let wgsl = include_str!("../shaders/particles.wgsl");
+    assert!(wgsl.contains(&format!("const MAX_PARTICLES: u32 = {MAX_PARTICLES}u;")));
+
+    let mut emitter = ParticleEmitter::new(7);
+    let mut soft = Vec::new();
+    let mut loud = Vec::new();
+    let origin = [1.0, -0.5, 2.0];
+    let blue = [0.2, 0.4, 1.0];
+    assert_eq!(emitter.burst(origin, 220.0, 0.0, blue, &mut soft), 0);
+    assert_eq!(
+        emitter.burst(origin, 220.0, 1.0, blue, &mut loud),
+        BURST_MIN_PARTICLES
+    );
+    assert_eq!(soft.len(), BURST_MIN_PARTICLES);
+    assert_eq!(loud.len(), BURST_MAX_PARTICLES);
+    for p in soft.iter().chain(&loud) {
+        assert_eq!(p.pos_age, [1.0, -0.5, 2.0, 0.0]);
+        assert!(p.vel_life[3] > 0.0 && p.vel_life[3] <= PARTICLE_MAX_LIFE_SEC);
+    }
+    let speed = |ps: &[Particle]| {
+        ps.iter()
+            .map(|p| glam::Vec3::from_slice(&p.vel_life[..3]).length())
+            .sum::<f32>()
+            / ps.len() as f32
+    };
+    assert!(speed(&loud) > 2.0 * speed(&soft));
+
+    // High notes spray upward; low ones spread level
+    let mut high = Vec::new();
+    let mut low = Vec::new();
+    emitter.burst(origin, 1760.0, 0.8, blue, &mut high);
+    emitter.burst(origin, 55.0, 0.8, blue, &mut low);
+    let rise = |ps: &[Particle]| ps.iter().map(|p| p.vel_life[1]).sum::<f32>() / ps.len() as f32;
+    assert!(rise(&high) > rise(&low) + 0.5);
+
+    // Octaves share a colour; other pitch classes shift it; greys stay grey
+    let a3 = burst_color(blue, 220.0, 0.5);
+    let a5 = burst_color(blue, 880.0, 0.5);
+    let e4 = burst_color(blue, 329.63, 0.5);
+    for i in 0..3 {
+        assert!((a3[i] - a5[i]).abs() < 1e-4);
+    }
+    assert!((0..3).any(|i| (a3[i] - e4[i]).abs() > 0.05));
+    let grey = burst_color([0.5, 0.5, 0.5], 329.63, 1.0);
+    assert!(grey.iter().all(|c| (c - 0.75).abs() < 1e-4));
+
+    // Bursts that would run off the end of the pool start again at slot 0
+    let mut last = 0;
+    let mut wrapped = false;
+    for _ in 0..(MAX_PARTICLES / BURST_MAX_PARTICLES + 1) {
+        let first = emitter.burst(origin, 440.0, 1.0, blue, &mut loud);
+        assert!(first + loud.len() <= MAX_PARTICLES);
+        wrapped |= first < last;
+        last = first;
+    }
+    assert!(wrapped);
+}
+
+#[test]
+fn voice_trails_follow_movement_and_fade() {
+    use trails::*;
+    let wgsl = include_str!("../shaders/waves.wgsl");
+    assert!(wgsl.contains(&format!("const TRAIL_POINTS: u32 = {TRAIL_POINTS}u;")));
+    assert!(wgsl.contains(&format!("array<vec4<f32>, {}>", 3 * TRAIL_POINTS)));
+
+    // A voice that stays put leaves nothing to draw
+    let mut trail = VoiceTrail::default();
+    let mut packed = [[0.0f32; 4]; TRAIL_POINTS];
+    for _ in 0..30 {
+        trail.update([0.5, 0.0, 0.5], 1.0 / 60.0);
+    }
+    trail.pack([0.5, 0.0, 0.5], &mut packed);
+    assert!(packed.iter().all(|p| p[3] == 0.0));
+
+    // Dragging lays points behind it, newest first, never more than fit
+    for i in 1..=40 {
+        trail.update([0.5 + 0.05 * i as f32, 0.0, 0.5], 1.0 / 60.0);
+    }
+    let head = [2.5, 0.0, 0.5];
+    trail.pack(head, &mut packed);
+    assert!(packed.iter().all(|p| p[3] > 0.0));
+    assert_eq!(packed[0], [2.5, 0.0, 0.5, 1.0]);
+    assert!((packed[1][0] - 2.45).abs() < 1e-4);
+    for pair in packed.windows(2) {
+        assert!(pair[1][0] < pair[0][0]);
+        assert!(pair[1][3] <= pair[0][3]);
+    }
+
+    // Once the voice stops, the streak fades out over TRAIL_FADE_SEC
+    trail.update(head, TRAIL_FADE_SEC * 0.5);
+    trail.pack(head, &mut packed);
+    assert!(packed[1][3] > 0.0 && packed[1][3] <= 0.5);
+    trail.update(head, TRAIL_FADE_SEC * 0.5);
+    trail.pack(head, &mut packed);
+    assert!(packed.iter().all(|p| p[3] == 0.0));
+}
+
+#[test]
+fn voice_styles_pack_for_the_shaders_blend_and_set_trail_length() {
+    use trails::*;
+    let waves = include_str!("../shaders/waves.wgsl");
+    let scene = include_str!("../shaders/scene.wgsl");
+    assert!(waves.contains("style: vec4<f32>"));
+    assert!(scene.contains("voice_styles: array<vec4<f32>, 3>"));
+
+    // Out-of-range values are kept drawable; the shape travels as its index
+    let wild = VoiceStyle {
+        glow: 50.0,
+        wave_frequency: 0.0,
+        shape: VoiceShape::Sparkle,
+        trail_sec: 1.5,
+    };
+    let [glow, frequency, shape, trail_sec] = wild.pack();
+    assert_eq!(glow, VoiceStyle::GLOW_RANGE.1);
+    assert_eq!(frequency, VoiceStyle::WAVE_FREQUENCY_RANGE.0);
+    assert_eq!(shape, 2.0);
+    assert_eq!(trail_sec, 1.5);
+    assert_eq!(VoiceShape::ALL.map(VoiceShape::index), [0, 1, 2]);
+
+    // The startup bass is drawn heavier and slower than the sparkling lead
+    let geno = &preset::preset_bank()[0];
+    let (bass, lead) = (geno.voices[0].style, geno.voices[1].style);
+    assert!(bass.glow > lead.glow && bass.wave_frequency < lead.wave_frequency);
+    assert!(bass.trail_sec > lead.trail_sec);
+    assert_eq!(lead.shape, VoiceShape::Sparkle);
+
+    // Continuous fields blend; the shape switches half way
+    let mid = VoiceStyle::DEFAULT.lerp(&wild, 0.25);
+    assert!((mid.glow - (1.0 + 49.0 * 0.25)).abs() < 1e-4);
+    assert_eq!(mid.shape, VoiceShape::Orb);
+    assert_eq!(
+        VoiceStyle::DEFAULT.lerp(&wild, 0.5).shape,
+        VoiceShape::Sparkle
+    );
+
+    // A shorter trail fades sooner than the default one
+    let mut short = VoiceTrail::default();
+    let mut long = VoiceTrail::default();
+    short.set_fade_sec(0.5);
+    let mut packed = [[0.0f32; 4]; TRAIL_POINTS];
+    for trail in [&mut short, &mut long] {
+        trail.update([0.0; 3], 0.0);
+        trail.update([1.0, 0.0, 0.0], 0.0);
+        trail.update([1.0, 0.0, 0.0], 0.6);
+    }
+    short.pack([1.0, 0.0, 0.0], &mut packed);
+    assert!(packed.iter().all(|p| p[3] == 0.0));
+    long.pack([1.0, 0.0, 0.0], &mut packed);
+    assert!(packed[1][3] > 0.0);
+}
+
+#[test]
+fn every_tap_gets_its_own_ripple_until_the_slots_run_out() {
+    use ripple::*;
+
+    let wgsl = include_str!("../shaders/waves.wgsl");
+    assert!(wgsl.contains(&format!("const MAX_RIPPLES: u32 = {MAX_RIPPLES}u;")));
+
+    // Rapid taps each keep their own ring
+    let mut ripples = Ripples::default();
+    ripples.push([0.2, 0.3], 1.0, 10.0);
+    ripples.push([0.7, 0.6], 2.0, 10.1);
+    let packed = ripples.pack(10.2);
+    assert_eq!(packed[0], [0.2, 0.3, 10.0, 1.0]);
+    assert_eq!(packed[1], [0.7, 0.6, 10.1, 1.5]);
+    assert!(packed[2..].iter().all(|r| *r == [0.0; 4]));
+
+    // Decayed ripples drop out and their slots are reused
+    let later = 10.0 + RIPPLE_LIFETIME_SEC + 0.05;
+    assert_eq!(ripples.pack(later)[0], [0.0; 4]);
+    ripples.push([0.5, 0.5], 1.0, later);
+    assert_eq!(ripples.pack(later)[0], [0.5, 0.5, later, 1.0]);
+
+    // Once every slot is live the oldest ring gives way
+    let mut full = Ripples::default();
+    for i in 0..MAX_RIPPLES {
+        full.push([0.1, 0.1], 1.0, i as f32 * 0.1);
+    }
+    let newest = MAX_RIPPLES as f32 * 0.1;
+    full.push([0.9, 0.9], 1.0, newest);
+    let packed = full.pack(newest);
+    assert_eq!(packed[0], [0.9, 0.9, newest, 1.0]);
+    assert!(packed[1..].iter().all(|r| r[3] > 0.0 && r[0] == 0.1));
+}
+
+#[test]
+fn musical_clock_pulses_on_heard_beats_and_swells_on_downbeats() {
+    use musical_clock::*;
+    let wgsl = include_str!("../shaders/waves.wgsl");
+    assert!(wgsl.contains(&format!(
+        "1.0 - {BEAT_ZOOM} * u.clock.z - {DOWNBEAT_ZOOM} * u.clock.w"
+    )));
+
+    // Beats are scheduled with the metronome off too, one per beat
+    let mut engine = make_engine();
+    let step = engine.step_duration_sec();
+    let mut events = Vec::new();
+    engine.schedule(0.0, STEPS_PER_BAR as f64 * step - 1e-6, &mut events);
+    assert!(engine.drain_clicks().is_empty());
+    let beats = engine.drain_beats();
+    assert_eq!(beats.len(), BEATS_PER_BAR as usize);
+    assert!(beats[0].accent && beats[1..].iter().all(|b| !b.accent));
+
+    // Nothing moves until the first beat is heard
+    let mut clock = MusicalClock::default();
+    clock.queue(beats);
+    clock.advance(-0.01);
+    assert_eq!(clock.sample(-0.01), ClockPulse::default());
+    assert_eq!(clock.sample(-0.01).zoom(), 1.0);
+
+    // The downbeat flashes, zooms in and then eases back
+    clock.advance(0.0);
+    let on = clock.sample(0.0);
+    assert_eq!((on.beat, on.downbeat, on.bar_phase), (1.0, 1.0, 0.0));
+    assert!(on.zoom() < 1.0);
+    let later = clock.sample(step);
+    assert!(later.beat < 0.5 && later.downbeat > later.beat);
+    assert!(later.beat_phase > 0.0);
+    assert!(later.zoom() > on.zoom());
+
+    // An ordinary beat flashes without a new downbeat swell, and the bar
+    // phase keeps counting across it
+    let beat_sec = 2.0 * step;
+    clock.advance(beat_sec);
+    let second = clock.sample(beat_sec);
+    assert_eq!(second.beat, 1.0);
+    assert!(second.downbeat < later.downbeat);
+    assert!((second.bar_phase - 1.0 / BEATS_PER_BAR as f32).abs() < 1e-4);
+    assert_eq!(second.pack(), [0.0, second.bar_phase, 1.0, second.downbeat]);
+    // Beat phase now runs at the heard tempo
+    assert!((clock.sample(beat_sec + step).beat_phase - 0.5).abs() < 1e-4);
+}
+
+#[test]
+fn bloom_settings_parse_clamp_and_cycle_quality() {
+    use bloom::*;
+    assert_eq!(
+        Bloom::parse("1.2, 0.5, 3"),
+        Some(Bloom {
+            strength: 1.2,
+            threshold: 0.5,
+            passes: 3,
+        })
+    );
+    // Omitted values keep their defaults; out-of-range ones are clamped
+    assert_eq!(
+        Bloom::parse("0"),
+        Some(Bloom {
+            strength: 0.0,
+            ..Bloom::default()
+        })
+    );
+    let wild = Bloom::parse("9,-1,12").unwrap();
+    assert_eq!(wild.strength, MAX_BLOOM_STRENGTH);
+    assert_eq!(wild.threshold, 0.0);
+    assert_eq!(wild.passes, MAX_BLOOM_PASSES);
+    assert_eq!(Bloom::parse("1,1,0").unwrap().passes, MIN_BLOOM_PASSES);
+    assert!(Bloom::parse("bright").is_none());
+    assert!(Bloom::parse("NaN").is_none());
+    assert!(Bloom::parse("1,0.5,2,4").is_none());
+
+    // Quality steps through every pass count and wraps
+    let mut b = Bloom::default();
+    assert_eq!(b.passes, MIN_BLOOM_PASSES);
+    for expected in (MIN_BLOOM_PASSES + 1..=MAX_BLOOM_PASSES).chain([MIN_BLOOM_PASSES]) {
+        b = b.next_quality();
+        assert_eq!(b.passes, expected);
+    }
+    assert_eq!(b.with_strength(-BLOOM_STRENGTH_STEP).strength, 0.0);
+}
+
+#[test]
+fn tonemaps_match_the_composite_and_exposure_parses() {
+    use tonemap::*;
+    let wgsl = include_str!("../shaders/post.wgsl");
+    for (t, name) in [
+        (Tonemap::Aces, "ACES"),
+        (Tonemap::Reinhard, "REINHARD"),
+        (Tonemap::None, "NONE"),
+    ] {
+        let decl = format!("const TONEMAP_{name}: u32 = {}u;", t.shader_index());
+        assert!(wgsl.contains(&decl), "{decl}");
+        assert_eq!(Tonemap::from_id(&t.id().to_uppercase()), Some(t));
+    }
+    assert_eq!(Tonemap::default(), Tonemap::Aces);
+    assert!(Tonemap::from_id("filmic").is_none());
+
+    assert_eq!(
+        Exposure::parse("+0.5"),
+        Some(Exposure {
+            ev: 0.5,
+            auto: false,
+        })
+    );
+    assert_eq!(
+        Exposure::parse("auto"),
+        Some(Exposure {
+            ev: 0.0,
+            auto: true
+        })
+    );
+    assert_eq!(Exposure::parse("Auto, -9").unwrap().ev, -MAX_EXPOSURE_EV);
+    assert!(Exposure::parse("bright").is_none());
+    assert!(Exposure::parse("auto,1,2").is_none());
+    assert!(Exposure::parse("inf").is_none());
+    assert_eq!(Exposure::default().gain(), 1.0);
+    assert_eq!(Exposure::default().with_ev(1.0).gain(), 2.0);
+}
+
+#[test]
+fn lens_settings_parse_and_match_the_lens_pass() {
+    use lens::*;
+    let wgsl = include_str!("../shaders/lens.wgsl");
+    assert!(wgsl.contains("var depth_tex: texture_depth_2d;"));
+    assert!(!Lens::default().active());
+    assert_eq!(Lens::default().focus, DEFAULT_FOCUS_DISTANCE);
+
+    // Quality tiers gather more taps each
+    let taps: Vec<u32> = DofQuality::ALL.iter().map(|q| q.taps()).collect();
+    assert_eq!(taps[0], 0);
+    assert!(taps.windows(2).all(|w| w[0] < w[1]));
+    for q in DofQuality::ALL {
+        assert_eq!(DofQuality::from_id(&q.id().to_uppercase()), Some(q));
+    }
+
+    let lens = Lens::default().with_dof_param("high, 6").unwrap();
+    assert_eq!((lens.dof, lens.focus), (DofQuality::High, 6.0));
+    assert!(lens.active());
+    // A focus out of range is clamped; an omitted one is kept
+    assert_eq!(
+        lens.with_dof_param("low,99").unwrap().focus,
+        MAX_FOCUS_DISTANCE
+    );
+    assert_eq!(lens.with_dof_param("medium").unwrap().focus, 6.0);
+    assert!(Lens::default().with_dof_param("blurry").is_none());
+    assert!(Lens::default().with_dof_param("low,1,2").is_none());
+    assert!(Lens::default().with_dof_param("low,NaN").is_none());
+
+    // The vignette alone runs the pass
+    let vignette = Lens::default().with_vignette_param("on").unwrap();
+    assert_eq!(vignette.vignette, DEFAULT_VIGNETTE);
+    assert!(vignette.active());
+    assert_eq!(
+        Lens::default().with_vignette_param("3").unwrap().vignette,
+        1.0
+    );
+    assert!(Lens::default().with_vignette_param("dark").is_none());
+}
+
+#[test]
+fn mirror_parses_and_steps_segments_on_bars() {
+    use mirror::*;
+    use musical_clock::{BarCounter, ClockPulse};
+    let wgsl = include_str!("../shaders/post.wgsl");
+    assert!(wgsl.contains("mirror_segments: u32,"));
+    assert_eq!(Mirror::default().segments_at(5), 0);
+
+    let held = Mirror::from_param("4").unwrap();
+    assert_eq!((held.segments, held.step_on_bars), (4, false));
+    assert!((0..8).all(|bar| held.segments_at(bar) == 4));
+    assert_eq!(Mirror::from_param("OFF").unwrap(), Mirror::default());
+    assert!(Mirror::from_param("3").is_none());
+    assert!(Mirror::from_param("6,beats").is_none());
+    assert!(Mirror::from_param("6,bars,x").is_none());
+
+    // Stepping starts from the chosen count and cycles through the rest
+    let stepping = Mirror::from_param("6, bars").unwrap();
+    let counts: Vec<u32> = (0..4).map(|bar| stepping.segments_at(bar)).collect();
+    assert_eq!(counts, vec![6, 2, 4, 6]);
+    // Switched off, stepping has nothing to step through
+    let off = Mirror {
+        segments: 0,
+        step_on_bars: true,
+    };
+    assert_eq!(off.segments_at(3), 0);
+
+    // Bars are counted as the bar phase wraps
+    let mut bars = BarCounter::default();
+    let counted: Vec<u32> = [0.1, 0.6, 0.95, 0.02, 0.5, 0.99, 0.0]
+        .into_iter()
+        .map(|bar_phase| {
+            bars.update(&ClockPulse {
+                bar_phase,
+                ..ClockPulse::default()
+            })
+        })
+        .collect();
+    assert_eq!(counted, vec![0, 0, 0, 1, 1, 1, 2]);
+}
+
+#[test]
+fn antialias_parses_and_falls_back_to_fxaa() {
+    use antialias::*;
+    let wgsl = include_str!("../shaders/fxaa.wgsl");
+    assert!(wgsl.contains("fn fs_fxaa("));
+    for aa in Antialias::ALL {
+        assert_eq!(Antialias::from_id(&aa.id().to_uppercase()), Some(aa));
+    }
+    assert!(Antialias::from_id("ssaa").is_none());
+    assert_eq!(Antialias::default(), Antialias::Off);
+
+    assert_eq!(Antialias::Msaa.samples(), MSAA_SAMPLES);
+    assert_eq!(Antialias::Fxaa.samples(), 1);
+    assert!(Antialias::Fxaa.fxaa() && !Antialias::Msaa.fxaa());
+    // Without multisampling, MSAA falls back to the FXAA pass
+    assert_eq!(Antialias::Msaa.supported(true), Antialias::Msaa);
+    assert_eq!(Antialias::Msaa.supported(false), Antialias::Fxaa);
+    assert_eq!(Antialias::Off.supported(false), Antialias::Off);
+}
+
+#[test]
+fn starfield_parses_and_parallaxes_with_the_camera() {
+    use glam::Vec3;
+    use starfield::*;
+    let wgsl = include_str!("../shaders/waves.wgsl");
+    assert!(wgsl.contains("star_shift: vec4<f32>,"));
+
+    assert_eq!(parse_stars("on"), Some(DEFAULT_STARS));
+    assert_eq!(parse_stars(" OFF "), Some(0.0));
+    assert_eq!(parse_stars("0.3"), Some(0.3));
+    assert_eq!(parse_stars("4"), Some(1.0));
+    assert_eq!(parse_stars("-1"), Some(0.0));
+    assert!(parse_stars("bright").is_none());
+    assert!(parse_stars("NaN").is_none());
+
+    // Still at the resting view; shifted with the eye as it moves off it
+    assert_eq!(star_shift(Vec3::new(0.0, 0.0, 6.0)), [0.0, 0.0]);
+    let shift = star_shift(Vec3::new(2.0, -1.0, 5.0));
+    assert_eq!(shift, [2.0 * STAR_PARALLAX, -STAR_PARALLAX]);
+}
+
+#[test]
+fn render_scale_parses_and_adapts_to_frame_time() {
+    use render_scale::*;
+
+    assert_eq!(RenderScaleMode::parse("auto"), Some(RenderScaleMode::Auto));
+    assert_eq!(
+        RenderScaleMode::parse(" 0.75 "),
+        Some(RenderScaleMode::Fixed(0.75))
+    );
+    assert_eq!(
+        RenderScaleMode::parse("0.2"),
+        Some(RenderScaleMode::Fixed(MIN_RENDER_SCALE))
+    );
+    assert_eq!(RenderScaleMode::parse("NaN"), None);
+    assert_eq!(RenderScaleMode::parse("huge"), None);
+    assert_eq!(RenderScaleMode::Fixed(0.875).id(), "0.875");
+
+    // Fixed scales ignore the frame time
+    let mut fixed = RenderScale::new(RenderScaleMode::Fixed(0.75));
+    for _ in 0..200 {
+        assert_eq!(fixed.update(0.05), 0.75);
+    }
+
+    // Slow frames step down one notch per second over budget, to the floor
+    let mut rs = RenderScale::new(RenderScaleMode::Auto);
+    assert_eq!(rs.scale(), MAX_RENDER_SCALE);
+    let frames = |rs: &mut RenderScale, sec: f32, dt: f32| {
+        for _ in 0..(sec / dt).round() as usize {
+            rs.update(dt);
+        }
+        rs.scale()
+    };
+    assert_eq!(frames(&mut rs, 0.5, 0.03), MAX_RENDER_SCALE);
+    assert_eq!(frames(&mut rs, 0.6, 0.03), 0.875);
+    assert_eq!(frames(&mut rs, 10.0, 0.03), MIN_RENDER_SCALE);
+
+    // Hitches longer than a frame can be (a hidden tab) are ignored
+    assert_eq!(rs.update(2.0), MIN_RENDER_SCALE);
+
+    // Fast frames step back up after a few seconds
+    assert_eq!(frames(&mut rs, SCALE_UP_AFTER_SEC - 0.5, 0.01), 0.5);
+    assert_eq!(frames(&mut rs, 1.0, 0.01), 0.625);
+
+    // A step up that proves too slow is undone, and the next try waits longer
+    assert_eq!(frames(&mut rs, 1.5, 0.03), 0.5);
+    assert_eq!(frames(&mut rs, SCALE_UP_AFTER_SEC + 0.5, 0.01), 0.5);
+    assert_eq!(frames(&mut rs, SCALE_UP_AFTER_SEC, 0.01), 0.625);
+
+    // Fixing the scale holds it
+    rs.set_mode(RenderScaleMode::Fixed(1.0));
+    assert_eq!(frames(&mut rs, 5.0, 0.1), 1.0);
+}
+
+#[test]
+fn frame_profiler_averages_frames_and_counts_dropped_refreshes() {
+    use profiler::*;
+
+    let mut p = FrameProfiler::default();
+    let mut refreshes = 0;
+    for _ in 0..60 {
+        if p.record(1.0 / 60.0, 0.004) {
+            refreshes += 1;
+        }
+    }
+    // A refresh every quarter second
+    assert_eq!(refreshes, 4);
+    let stats = p.stats(0, None);
+    assert!((stats.fps - 60.0).abs() < 0.5, "{}", stats.fps);
+    assert!((stats.cpu_ms - 4.0).abs() < 0.01);
+    assert_eq!(stats.dropped, 0);
+
+    // A frame three intervals long missed two refreshes; hidden-tab gaps
+    // aren't frames at all
+    p.record(3.0 / 60.0, 0.004);
+    p.record(5.0, 0.004);
+    let stats = p.stats(0, None);
+    assert_eq!(stats.dropped, 2);
+    assert!((stats.worst_frame_ms - 50.0).abs() < 0.1);
+    assert!(stats.hud_text().contains("gpu n/a"));
+
+    let gpu = GpuTimings {
+        scene_ms: 1.0,
+        bloom_ms: 0.5,
+        composite_ms: 0.25,
+    };
+    let text = p.stats(12, Some(gpu)).hud_text();
+    assert!(text.contains("gpu 1.75 ms"), "{text}");
+    assert!(text.contains("oscillators 12"), "{text}");
+
+    // Unison layers and the FM modulator each count as an oscillator
+    let mut engine = make_engine();
+    engine.configs[0].unison.voices = 3;
+    engine.configs[0].fm = Some(FmParams::BELL);
+    assert_eq!(note_oscillators(&engine.configs[0]), 4);
+
+    let mut alloc = VoiceAllocator::new(vec![Polyphony::DEFAULT; 2]);
+    alloc.allocate(0, 0.0, 1.0, 0.8, ());
+    alloc.allocate(0, 0.1, 1.0, 0.8, ());
+    alloc.allocate(1, 0.0, 0.5, 0.8, ());
+    assert_eq!(alloc.sounding_on(0, 0.6), 2);
+    assert_eq!(alloc.sounding_on(1, 0.6), 0);
+    assert_eq!(alloc.sounding_on(7, 0.0), 0);
+}
+
+#[test]
+fn glyph_atlas_rasterises_the_font_and_lays_out_panels() {
+    use glyphs::*;
+
+    let atlas = GlyphAtlas::new();
+    assert_eq!(atlas.pixels.len(), (atlas.width * atlas.height) as usize);
+    assert!(atlas.pixels.iter().all(|&p| p == 0 || p == 255));
+
+    assert_eq!(GlyphAtlas::measure("", 2.0), [0.0, 0.0]);
+    assert_eq!(
+        GlyphAtlas::measure("AB\nC", 2.0),
+        [
+            (GLYPH_ADVANCE + GLYPH_W) as f32 * 2.0,
+            (LINE_HEIGHT + GLYPH_H) as f32 * 2.0
+        ]
+    );
+
+    let white = [1.0; 4];
+    let navy = [0.0, 0.0, 0.1, 0.8];
+    let mut quads = Vec::new();
+    atlas.layout_panel("a b\nC", [10.0, 20.0], 2.0, white, navy, 4.0, &mut quads);
+    // Panel first, then a quad per visible glyph (spaces are skipped)
+    assert_eq!(quads.len(), 4);
+    assert_eq!(quads[0].color, navy);
+    assert_eq!(quads[0].pos, [6.0, 16.0]);
+    assert_eq!(quads[0].uv_min, quads[0].uv_max);
+    let [a, b, c] = [quads[1], quads[2], quads[3]];
+    assert_eq!(a.pos, [10.0, 20.0]);
+    assert_eq!(b.pos, [10.0 + 2.0 * 2.0 * GLYPH_ADVANCE as f32, 20.0]);
+    assert_eq!(c.pos, [10.0, 20.0 + 2.0 * LINE_HEIGHT as f32]);
+    assert_eq!(a.size, [2.0 * GLYPH_W as f32, 2.0 * GLYPH_H as f32]);
+
+    // Lowercase shares the capitals, and each glyph's cell holds its bitmap:
+    // the top row of 'A' is lit in the middle and clear at the corners
+    let mut upper = Vec::new();
+    atlas.layout_panel("A", [0.0, 0.0], 1.0, white, [0.0; 4], 0.0, &mut upper);
+    assert_eq!(upper.len(), 1);
+    assert_eq!(upper[0].uv_min, a.uv_min);
+    let texel = |uv: [f32; 2], dx: u32, dy: u32| {
+        let x = (uv[0] * atlas.width as f32).round() as u32 + dx;
+        let y = (uv[1] * atlas.height as f32).round() as u32 + dy;
+        atlas.pixels[(y * atlas.width + x) as usize]
+    };
+    assert_eq!(texel(a.uv_min, 0, 0), 0);
+    assert_eq!(texel(a.uv_min, 2, 0), 255);
+    assert_eq!(texel(a.uv_min, 0, 4), 255);
+
+    // Characters outside the font fall back to '?'
+    let mut unknown = Vec::new();
+    let mut question = Vec::new();
+    atlas.layout_panel("~", [0.0, 0.0], 1.0, white, [0.0; 4], 0.0, &mut unknown);
+    atlas.layout_panel("?", [0.0, 0.0], 1.0, white, [0.0; 4], 0.0, &mut question);
+    assert_eq!(unknown[0].uv_min, question[0].uv_min);
+}
+
+#[test]
+fn screenshot_unpacks_padded_rows_and_encodes_a_png() {
+    use screenshot::*;
+
+    // Two BGRA pixels per row, rows padded to 12 bytes
+    let rows = [
+        [1, 2, 3, 255, 4, 5, 6, 255, 9, 9, 9, 9],
+        [7, 8, 9, 255, 10, 11, 12, 255, 9, 9, 9, 9],
+    ]
+    .concat();
+    let shot = Screenshot::from_rows(2, 2, &rows, 12, true);
+    assert_eq!(
+        shot.rgba,
+        [3, 2, 1, 255, 6, 5, 4, 255, 9, 8, 7, 255, 12, 11, 10, 255]
+    );
+    assert_eq!(
+        Screenshot::from_rows(2, 2, &rows, 12, false).rgba[..4],
+        [1, 2, 3, 255]
+    );
+
+    let be32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+    let chunks = |png: &[u8]| {
+        let mut out = Vec::new();
+        let mut at = 8;
+        while at < png.len() {
+            let len = be32(&png[at..]) as usize;
+            let kind = png[at + 4..at + 8].to_vec();
+            out.push((kind, png[at + 8..at + 8 + len].to_vec()));
+            at += 12 + len;
+        }
+        assert_eq!(at, png.len());
+        out
+    };
+    let png = shot.to_png();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    let parts = chunks(&png);
+    let kinds: Vec<&[u8]> = parts.iter().map(|(k, _)| k.as_slice()).collect();
+    assert_eq!(kinds, [&b"IHDR"[..], b"IDAT", b"IEND"]);
+    assert_eq!(parts[0].1, [0, 0, 0, 2, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+    // IEND's CRC is the same in every PNG
+    assert_eq!(&png[png.len() - 4..], [0xae, 0x42, 0x60, 0x82]);
+
+    // The zlib stream's stored blocks hold the filtered RGB scanlines
+    let inflate = |z: &[u8]| {
+        assert_eq!(((z[0] as u32) << 8 | z[1] as u32) % 31, 0);
+        let mut raw = Vec::new();
+        let mut at = 2;
+        loop {
+            let last = z[at] & 1 == 1;
+            let len = u16::from_le_bytes([z[at + 1], z[at + 2]]) as usize;
+            assert_eq!(!u16::from_le_bytes([z[at + 3], z[at + 4]]) as usize, len);
+            raw.extend_from_slice(&z[at + 5..at + 5 + len]);
+            at += 5 + len;
+            if last {
+                break;
+            }
+        }
+        assert_eq!(at + 4, z.len());
+        raw
+    };
+    assert_eq!(
+        inflate(&parts[1].1),
+        [0, 3, 2, 1, 6, 5, 4, 0, 9, 8, 7, 12, 11, 10]
+    );
+
+    // Large frames split across several stored blocks
+    let big = Screenshot {
+        width: 200,
+        height: 200,
+        rgba: vec![128; 200 * 200 * 4],
+    };
+    let parts = chunks(&big.to_png());
+    let raw = inflate(&parts[1].1);
+    assert_eq!(raw.len(), 200 * (200 * 3 + 1));
+    assert!(raw
+        .chunks(601)
+        .all(|row| row[0] == 0 && row[1..].iter().all(|&b| b == 128)));
+}
+
+#[test]
+fn video_frames_step_the_bounced_piece_at_a_fixed_rate() {
+    use video::*;
+
+    assert_eq!(parse_video_fps(" 24 "), Some(24));
+    assert_eq!(parse_video_fps("240"), Some(MAX_VIDEO_FPS));
+    assert!(parse_video_fps("0").is_none());
+    assert!(parse_video_fps("fast").is_none());
+
+    let engine = make_engine();
+    let step = 128.0 / 48_000.0;
+    let notes = bounce::schedule_piece(&mut engine.clone(), 10.0, step);
+    let mut seq = FrameSequence::new(&mut engine.clone(), 10.0, step, 30);
+    assert_eq!(seq.frame_count(), 300);
+
+    // One frame at a time when drawn on schedule
+    let first = seq.advance(0.0).unwrap();
+    assert_eq!(first.index, 0);
+    assert!((first.dt_sec - 1.0 / 30.0).abs() < 1e-6);
+    assert!(seq.advance(0.01).is_none());
+    assert_eq!(seq.advance(1.5 / 30.0).unwrap().index, 1);
+
+    // A late frame skips ahead but keeps every note's burst
+    let late = seq.advance(5.01).unwrap();
+    assert_eq!(late.index, 150);
+    assert!((late.dt_sec - 149.0 / 30.0).abs() < 1e-4);
+    let heard = notes
+        .iter()
+        .filter(|n| n.event.start_time_sec <= 5.0)
+        .count();
+    let mut bursts = first.bursts.len() + late.bursts.len();
+    let mut replay = FrameSequence::new(&mut engine.clone(), 10.0, step, 30);
+    let mut drawn = Vec::new();
+    for i in 0..=150 {
+        drawn.push(replay.advance((i as f64 + 0.5) / 30.0).unwrap());
+    }
+    bursts += drawn[1].bursts.len();
+    assert_eq!(bursts, heard);
+    assert_eq!(drawn.iter().map(|f| f.bursts.len()).sum::<usize>(), heard);
+    assert!(heard > 0);
+
+    // The same seed draws the same frames whatever the pacing
+    assert_eq!(drawn[150].pulses, late.pulses);
+    assert_eq!(drawn[150].clock, late.clock);
+    assert!(drawn.iter().any(|f| f.pulses.iter().any(|&p| p > 0.1)));
+    assert!(drawn.iter().any(|f| f.clock.beat > 0.5));
+
+    assert!(!seq.finished());
+    assert_eq!(seq.advance(60.0).unwrap().index, 299);
+    assert!(seq.finished());
+    assert!(seq.advance(61.0).is_none());
+}
+
+#[test]
+fn choreography_moves_through_seeded_shots_and_eases_home() {
+    use choreography::*;
+    use musical_clock::ClockPulse;
+    let home = CameraPose::home(6.0);
+    let step = 1.0 / 30.0;
+    // Two-second bars, with a beat flash at the top of each second
+    let clock_at = |t: f32| ClockPulse {
+        beat_phase: t.fract(),
+        bar_phase: (t / 2.0).fract(),
+        beat: (-t.fract() * 6.0).exp(),
+        downbeat: 0.0,
+    };
+    let run = |seed: u64| {
+        let mut c = Choreographer::new(seed, home);
+        // Off by default: the resting view
+        assert_eq!(c.update(step, &clock_at(0.0)), home);
+        c.set_active(true);
+        (1..=(40.0 / step) as usize)
+            .map(|i| c.update(step, &clock_at(i as f32 * step)))
+            .collect::<Vec<_>>()
+    };
+
+    let poses = run(7);
+    // The first shot eases in from the resting view, with no jumps anywhere
+    assert!(poses[0].eye.distance(home.eye) < 0.01);
+    for pair in poses.windows(2) {
+        assert!(pair[0].eye.distance(pair[1].eye) < 0.1);
+        assert!(pair[0].target.distance(pair[1].target) < 0.1);
+    }
+    // It moves, keeping the scene in view
+    assert!(poses.iter().any(|p| p.eye.distance(home.eye) > 0.5));
+    assert!(poses.iter().all(|p| {
+        let d = p.eye.distance(p.target);
+        (3.0..=9.0).contains(&d) && p.eye.z > 1.0
+    }));
+
+    // The same seed gives the same choreography; another seed another
+    assert_eq!(run(7), poses);
+    assert_ne!(run(8), poses);
+
+    // Turning it off eases back to the resting view
+    let mut c = Choreographer::new(7, home);
+    c.set_active(true);
+    for i in 0..300 {
+        c.update(step, &clock_at(i as f32 * step));
+    }
+    c.set_active(false);
+    let mut pose = home;
+    for i in 0..=(SHOT_BLEND_SEC / step) as usize + 1 {
+        pose = c.update(step, &clock_at(10.0 + i as f32 * step));
+    }
+    assert_eq!(pose, home);
+}
+
+#[test]
+fn sketch_fallback_places_voices_like_the_waves_and_draws_trails() {
+    use sketch::*;
+    use trails::*;
+
+    // The centre is the middle of the canvas; x and z span the shorter side
+    assert_eq!(sketch_point([0.0, 5.0, 0.0], 800.0, 400.0), (400.0, 200.0));
+    let (x, y) = sketch_point([1.0, 0.0, -1.0], 800.0, 400.0);
+    assert!((x - (400.0 + 400.0 * SKETCH_SCALE)).abs() < 1e-3);
+    assert!((y - (200.0 - 400.0 * SKETCH_SCALE)).abs() < 1e-3);
+
+    assert_eq!(css_rgba([0.0, 1.0, 4.0], 0.5), "rgba(0,255,255,0.500)");
+    assert_eq!(css_rgba([0.5, 0.5, 0.5], 2.0), "rgba(188,188,188,1.000)");
+
+    // A still voice has no trail: just its circle, swelling with the pulse
+    let theme = &theme::NEBULA;
+    let mut trail = VoiceTrail::default();
+    let mut packed = vec![[0.0; 4]; TRAIL_POINTS];
+    trail.update([0.0; 3], 0.1);
+    trail.pack([0.0; 3], &mut packed);
+    let still = sketch_dots(theme, &[[0.0; 3]], &[0.0], &packed, 400.0, 400.0);
+    assert_eq!(still.len(), 1);
+    let pulsing = sketch_dots(theme, &[[0.0; 3]], &[1.0], &packed, 400.0, 400.0);
+    assert!(pulsing[0].radius > still[0].radius);
+
+    // A moved voice leaves fading points, drawn beneath it
+    trail.update([0.5, 0.0, 0.0], 0.1);
+    trail.update([1.0, 0.0, 0.0], 0.5);
+    trail.pack([1.0, 0.0, 0.0], &mut packed);
+    let moved = sketch_dots(theme, &[[1.0, 0.0, 0.0]], &[0.0], &packed, 400.0, 400.0);
+    assert_eq!(moved.len(), 3);
+    // Newest first, so the older point is the fainter, smaller one
+    assert!(moved[0].radius > moved[1].radius);
+    assert_eq!(moved[2].x, sketch_point([1.0, 0.0, 0.0], 400.0, 400.0).0);
+    assert!(moved[2].radius > moved[0].radius);
+}
+
+#[test]
+fn shader_reload_parses_its_base_and_keeps_only_changed_sources() {
+    use shader_reload::*;
+
+    assert_eq!(
+        parse_shader_base("on").as_deref(),
+        Some(DEFAULT_SHADER_BASE)
+    );
+    assert_eq!(
+        parse_shader_base(" 1 ").as_deref(),
+        Some(DEFAULT_SHADER_BASE)
+    );
+    assert!(parse_shader_base("off").is_none());
+    assert!(parse_shader_base("").is_none());
+    assert_eq!(
+        parse_shader_base("http://localhost:8080/shaders").as_deref(),
+        Some("http://localhost:8080/shaders/")
+    );
+    assert_eq!(parse_shader_base("/wgsl/").as_deref(), Some("/wgsl/"));
+
+    // Every shader names a file the dev server can hand out
+    for shader in Shader::ALL {
+        let path = format!("{}/shaders/{}", env!("CARGO_MANIFEST_DIR"), shader.file());
+        assert!(std::path::Path::new(&path).exists(), "{path}");
+        assert_eq!(Shader::ALL[shader.index()], shader);
+    }
+
+    let mut sources = ShaderSources::default();
+    assert!(sources.get(Shader::Waves).is_none());
+    assert!(sources.set(Shader::Waves, "fn a() {}".into()));
+    assert!(!sources.set(Shader::Waves, "fn a() {}".into()));
+    assert!(sources.set(Shader::Waves, "fn b() {}".into()));
+    assert_eq!(sources.get(Shader::Waves), Some("fn b() {}"));
+    assert!(sources.get(Shader::Post).is_none());
+
+    assert_eq!(
+        compile_message(Shader::Fxaa, Some((12, 5)), "unknown identifier"),
+        "fxaa.wgsl:12:5: unknown identifier"
+    );
+    assert_eq!(
+        compile_message(Shader::Post, None, "layout mismatch"),
+        "post.wgsl: layout mismatch"
+    );
+}
+
+#[test]
+fn custom_shaders_are_checked_and_their_errors_point_at_their_own_lines() {
+    use custom_shader::*;
+    let builtin = include_str!("../shaders/custom.wgsl");
+    assert_eq!(check_custom_shader(builtin), Ok(()));
+    let plasma =
+        "fn custom (uv: vec2<f32>, p: vec2<f32>) -> vec3<f32> {\n    return vec3<f32>(uv, 0.0);\n}";
+    assert_eq!(check_custom_shader(plasma), Ok(()));
+
+    assert_eq!(
+        check_custom_shader("  // nothing\n"),
+        Err(CustomShaderError::Empty)
+    );
+    assert_eq!(
+        check_custom_shader("fn customise() -> f32 { return 1.0; }"),
+        Err(CustomShaderError::MissingFunction)
+    );
+    // Mentioned only in a comment doesn't count
+    assert_eq!(
+        check_custom_shader("/* fn custom( */ fn other() {}"),
+        Err(CustomShaderError::MissingFunction)
+    );
+    assert_eq!(
+        check_custom_shader(&format!("@fragment\n{plasma}")),
+        Err(CustomShaderError::Declares("@fragment"))
+    );
+    assert_eq!(
+        check_custom_shader(&format!(
+            "@group(1) @binding(0) var<uniform> x: f32;\n{plasma}"
+        )),
+        Err(CustomShaderError::Declares("@group"))
+    );
+    let long = format!(
+        "{plasma}\n{}",
+        "// padding\n".repeat(MAX_CUSTOM_SHADER_BYTES / 8)
+    );
+    assert_eq!(check_custom_shader(&long), Err(CustomShaderError::TooLong));
+
+    // The module is the waves shader, the custom code, then its entry point
+    let waves = "fn a() {}\nfn b() {}\n\n";
+    let module = with_custom(waves, plasma);
+    assert!(module.starts_with("fn a() {}\nfn b() {}\nfn custom (uv"));
+    assert!(module.contains("fn fs_custom(inp: VsOut)"));
+    let lines: Vec<&str> = module.lines().collect();
+    assert_eq!(
+        lines[2],
+        "fn custom (uv: vec2<f32>, p: vec2<f32>) -> vec3<f32> {"
+    );
+    assert_eq!(custom_line(waves, plasma, 3), Some(1));
+    assert_eq!(custom_line(waves, plasma, 5), Some(3));
+    assert_eq!(custom_line(waves, plasma, 2), None);
+    assert_eq!(custom_line(waves, plasma, 6), None);
+}
+
+#[test]
+fn display_windows_tile_one_wide_camera_and_carry_the_scene() {
+    use displays::*;
+    use glam::{Mat4, Vec3, Vec4};
+    assert_eq!(
+        DisplaySlice::parse(" 2/3 "),
+        Some(DisplaySlice { index: 1, count: 3 })
+    );
+    for bad in ["0/3", "4/3", "1/0", "1/9", "2", "a/b"] {
+        assert_eq!(DisplaySlice::parse(bad), None, "{bad}");
+    }
+    assert_eq!(DisplaySlice { index: 2, count: 4 }.param(), "3/4");
+
+    // A point lands in the tile covering its part of the wide view, at the
+    // same place across the span
+    let (fov, aspect) = (std::f32::consts::FRAC_PI_4, 16.0 / 9.0);
+    let wide = Mat4::perspective_rh(fov, aspect * 3.0, 0.1, 100.0);
+    let ndc_x = |m: Mat4, p: Vec3| {
+        let c = m * Vec4::new(p.x, p.y, p.z, 1.0);
+        c.x / c.w
+    };
+    for x in [-4.0, -1.0, 0.0, 0.5, 3.0] {
+        let p = Vec3::new(x, 0.3, -5.0);
+        let span_x = ndc_x(wide, p);
+        for index in 0..3 {
+            let tile = DisplaySlice { index, count: 3 }.projection(fov, aspect, 0.1, 100.0);
+            let left = -1.0 + 2.0 * index as f32 / 3.0;
+            let expected = 3.0 * (span_x - left) - 1.0;
+            assert!((ndc_x(tile, p) - expected).abs() < 1e-4);
+        }
+    }
+    // One window is the plain camera
+    let single = DisplaySlice { index: 0, count: 1 }.projection(fov, aspect, 0.1, 100.0);
+    assert!(single.abs_diff_eq(Mat4::perspective_rh(fov, aspect, 0.1, 100.0), 1e-6));
+
+    let frame = DisplayFrame {
+        positions: [[-1.0, 0.0, 0.2], [1.0, 0.5, 0.0], [0.0, 0.0, -1.0]],
+        pulse_energy: [0.8, 0.0, 1.2],
+        clock: musical_clock::ClockPulse {
+            beat_phase: 0.25,
+            bar_phase: 0.5,
+            beat: 0.4,
+            downbeat: 0.1,
+        },
+        camera: choreography::CameraPose {
+            eye: Vec3::new(0.5, 0.2, 6.0),
+            target: Vec3::ZERO,
+        },
+        swirl: [0.4, 0.6, 0.9],
+        theme: 3,
+        visualizer: 1,
+        render_mode: 2,
+        bursts: vec![DisplayBurst {
+            voice: 2,
+            position: Vec3::new(0.0, 0.0, -1.0),
+            frequency_hz: 440.0,
+            velocity: 0.7,
+        }],
+    };
+    let packed = frame.pack();
+    assert_eq!(DisplayFrame::unpack(&packed), Some(frame));
+    assert_eq!(DisplayFrame::unpack(&packed[..packed.len() - 1]), None);
+}
+
+#[test]
+fn attract_mode_takes_over_when_idle_and_hands_back_on_input() {
+    use attract::*;
+    use rand::SeedableRng;
+    assert_eq!(parse_attract("on"), Some(Some(DEFAULT_ATTRACT_IDLE_SEC)));
+    assert_eq!(parse_attract(" 45 "), Some(Some(45.0)));
+    assert_eq!(parse_attract("off"), Some(None));
+    assert_eq!(parse_attract("0.5"), None);
+    assert_eq!(parse_attract("soon"), None);
+
+    let mut a = Attract::new(Some(10.0));
+    assert_eq!(a.update(6.0, false), None);
+    // Input restarts the wait
+    assert_eq!(a.update(1.0, true), None);
+    assert_eq!(a.update(6.0, false), None);
+    assert_eq!(a.update(4.0, false), Some(AttractEvent::Entered));
+    assert!(a.is_active());
+    assert_eq!(a.update(ATTRACT_RETONE_SEC - 1.0, false), None);
+    assert_eq!(a.update(1.0, false), Some(AttractEvent::Retone));
+    // Any input ends it at once, and the wait starts over
+    assert_eq!(a.update(0.0, true), Some(AttractEvent::Left));
+    assert!(!a.is_active());
+    assert_eq!(a.update(9.0, false), None);
+
+    // Switching it off while it runs ends it; off, it never starts
+    assert_eq!(a.update(1.0, false), Some(AttractEvent::Entered));
+    a.set_idle_after_sec(None);
+    assert_eq!(a.update(0.1, false), Some(AttractEvent::Left));
+    assert_eq!(a.update(1000.0, false), None);
+
+    // A new key is a white-key root in a church mode
+    let mut engine = make_engine();
+    let mut rng = rand::rngs::StdRng::seed_from_u64(9);
+    for _ in 0..20 {
+        engine.apply(&random_tonality(&mut rng));
+        assert!([60, 62, 64, 65, 67, 69, 71].contains(&engine.params.root_midi));
+        let modes = [
+            IONIAN, DORIAN, PHRYGIAN, LYDIAN, MIXOLYDIAN, AEOLIAN, LOCRIAN,
+        ];
+        assert!(modes.contains(&engine.params.scale));
+    }
+}
+
+#[test]
+fn touch_presses_tell_taps_long_presses_and_drags_per_pointer() {
+    use glam::Vec3;
+    use touch::*;
+
+    let press = |pointer_id: i32, voice: usize| VoicePress {
+        pointer_id,
+        voice,
+        start_px: [100.0, 100.0],
+        start_sec: 2.0,
+        origin: Vec3::ZERO,
+        plane_z_world: -3.0,
+        dragging: false,
+    };
+
+    // A wobble inside the slop is still a tap; held still it becomes a long press
+    let timings = PressTimings::default();
+    let mut p = press(1, 0);
+    assert!(!p.moved_to([106.0, 104.0]));
+    assert_eq!(p.kind(2.2, &timings), PressKind::Tap);
+    assert_eq!(p.kind(2.0 + LONG_PRESS_SEC, &timings), PressKind::LongPress);
+    // Past the slop it is a drag for good, even back where it began
+    assert!(p.moved_to([100.0, 120.0]));
+    assert!(p.moved_to([100.0, 100.0]));
+    assert_eq!(p.kind(5.0, &timings), PressKind::Drag);
+
+    // Two fingers hold two voices; a voice or pointer already holding is refused
+    let mut presses = VoicePresses::default();
+    assert!(presses.press(press(1, 0)));
+    assert!(presses.press(press(2, 2)));
+    assert!(!presses.press(press(3, 0)));
+    assert!(!presses.press(press(2, 1)));
+    presses.get_mut(2).unwrap().moved_to([160.0, 100.0]);
+    assert!(!presses.get_mut(1).unwrap().dragging);
+
+    let second = presses.release(2).unwrap();
+    assert_eq!(
+        (second.voice, second.kind(2.1, &timings)),
+        (2, PressKind::Drag)
+    );
+    assert_eq!(presses.release(2), None);
+    // The freed voice can be taken by another finger
+    assert!(presses.press(press(3, 2)));
+    assert_eq!(presses.release(1).map(|p| p.voice), Some(0));
+}
+
+#[test]
+fn taps_wait_out_the_double_tap_window_and_timings_are_configurable() {
+    use touch::*;
+
+    let timings = PressTimings::parse("800,400").unwrap();
+    assert_eq!(timings.long_press_sec, 0.8);
+    assert_eq!(timings.double_tap_sec, 0.4);
+    assert_eq!(
+        PressTimings::parse("700").map(|t| t.double_tap_sec),
+        Some(DOUBLE_TAP_SEC)
+    );
+    assert_eq!(PressTimings::parse("10,300"), None);
+    assert_eq!(PressTimings::parse("fast"), None);
+
+    // A slower long press turns what was a long press back into a tap
+    let press = VoicePress {
+        pointer_id: 1,
+        voice: 0,
+        start_px: [0.0, 0.0],
+        start_sec: 1.0,
+        origin: glam::Vec3::ZERO,
+        plane_z_world: 0.0,
+        dragging: false,
+    };
+    assert_eq!(
+        press.kind(1.6, &PressTimings::default()),
+        PressKind::LongPress
+    );
+    assert_eq!(press.kind(1.6, &timings), PressKind::Tap);
+
+    // A second tap on the same voice in time is a double tap, never a mute
+    let mut taps = TapTracker::default();
+    let TapOutcome::Pending(first) = taps.tap(1, 10.0, &timings) else {
+        panic!("first tap should wait");
+    };
+    assert_eq!(taps.tap(1, 10.3, &timings), TapOutcome::Double);
+    assert_eq!(taps.expire(first), None);
+
+    // Too slow, or on another voice, and each tap stands alone
+    let TapOutcome::Pending(slow) = taps.tap(1, 20.0, &timings) else {
+        panic!("tap should wait");
+    };
+    assert_eq!(taps.expire(slow), Some(1));
+    let TapOutcome::Pending(a) = taps.tap(0, 30.0, &timings) else {
+        panic!("tap should wait");
+    };
+    let TapOutcome::Pending(b) = taps.tap(2, 30.1, &timings) else {
+        panic!("tap on another voice should wait");
+    };
+    assert_eq!(taps.expire(a), None);
+    assert_eq!(taps.expire(b), Some(2));
+}
+
+#[test]
+fn two_finger_gestures_pinch_the_zoom_and_swirl_the_voices() {
+    use gesture::*;
+    use glam::Vec3;
+
+    let mut g = TwoFingerGesture::default();
+    // One finger is a tap, not a gesture
+    assert!(!g.down(1, [100.0, 100.0]));
+    assert_eq!(g.moved(1, [110.0, 100.0]), None);
+    assert!(!g.up(1));
+
+    assert!(!g.down(1, [100.0, 100.0]));
+    assert!(g.down(2, [200.0, 100.0]));
+    assert!(!g.down(3, [150.0, 150.0]));
+    assert!(g.is_active());
+
+    // Spreading the fingers to twice as far apart halves the distance
+    let step = g.moved(2, [300.0, 100.0]).unwrap();
+    assert!((step.scale - 2.0).abs() < 1e-5);
+    assert!(step.rotate_rad.abs() < 1e-5);
+    assert!((step.zoom(1.0) - 0.5).abs() < 1e-5);
+    assert_eq!(step.zoom(0.6), ZOOM_RANGE.0);
+
+    // A quarter turn swirls a voice a quarter of the way around the centre
+    let step = g.moved(2, [100.0, 300.0]).unwrap();
+    assert!((step.rotate_rad - std::f32::consts::FRAC_PI_2).abs() < 1e-4);
+    let turned = step.rotate(Vec3::new(1.0, 0.0, 0.0));
+    assert!((turned - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-4);
+    // Across the ±π seam it takes the short way round
+    g.moved(2, [0.0, 101.0]);
+    let step = g.moved(2, [0.0, 99.0]).unwrap();
+    assert!(step.rotate_rad.abs() < 0.1);
+
+    // Both fingers' releases belong to the gesture, so neither plays a note
+    assert!(g.up(1));
+    assert!(!g.is_active());
+    assert_eq!(g.moved(2, [50.0, 50.0]), None);
+    assert!(g.up(2));
+    assert!(!g.up(2));
+}
+
+#[test]
+fn keymap_rebinds_keys_and_saves_only_the_changes() {
+    use keymap::*;
+
+    let mut keys = Keymap::default();
+    // Letters are one key either case; Shift picks its own binding if it has one
+    assert_eq!(keys.action("m", false), Some(Action::Motion));
+    assert_eq!(keys.action("M", true), Some(Action::Mute));
+    assert_eq!(keys.action("R", true), Some(Action::ReseedAll));
+    assert_eq!(keys.action("}", true), Some(Action::OctaveUp));
+    assert_eq!(keys.action(" ", false), Some(Action::Pause));
+    assert_eq!(keys.action("c", false), None);
+    assert!(keys.changes().is_empty());
+
+    // Taking a key from another action leaves that one unbound
+    let displaced = keys.bind(Action::ReseedAll, KeyChord::parse("Shift+M"));
+    assert_eq!(displaced, Some(Action::Mute));
+    assert_eq!(keys.action("M", true), Some(Action::ReseedAll));
+    assert_eq!(keys.action("r", false), None);
+    assert_eq!(keys.chords(Action::Mute).count(), 0);
+    assert_eq!(
+        keys.bind(Action::TempoUp, KeyChord::parse("Space")),
+        Some(Action::Pause)
+    );
+    assert_eq!(keys.action(" ", false), Some(Action::TempoUp));
+
+    // Only what changed is saved, and it loads back the same
+    let changes = keys.changes();
+    assert!(changes.contains(&("mute", String::new())));
+    assert!(changes.contains(&("reseed", "Shift+M".to_string())));
+    assert!(changes.contains(&("tempo-up", "Space".to_string())));
+    let loaded = Keymap::with_changes(changes.iter().map(|(a, k)| (*a, k.as_str())));
+    assert_eq!(loaded, keys);
+    // Unknown actions and keys from another version are skipped
+    let loaded = Keymap::with_changes([("warp", "w"), ("theme", "F2 Shift+")]);
+    assert_eq!(loaded.action("F2", false), Some(Action::Theme));
+    assert_eq!(loaded.action("i", false), None);
+
+    for action in Action::ALL {
+        assert_eq!(Action::from_id(action.id()), Some(action));
+    }
+    assert_eq!(KeyChord::new("M", true).label(), "Shift+M");
+    assert_eq!(
+        KeyChord::parse(&KeyChord::new(" ", false).label()),
+        Some(KeyChord::new(" ", false))
+    );
+}
+
+#[test]
+fn keyboard_selection_cycles_voices_and_nudges_within_reach() {
+    use glam::Vec3;
+    use selection::*;
+
+    assert_eq!(cycle_voice(None, 3, true), Some(0));
+    assert_eq!(cycle_voice(None, 3, false), Some(2));
+    assert_eq!(cycle_voice(Some(2), 3, true), Some(0));
+    assert_eq!(cycle_voice(Some(0), 3, false), Some(2));
+    assert_eq!(cycle_voice(Some(7), 3, true), Some(0));
+    assert_eq!(cycle_voice(Some(0), 0, true), None);
+    let keys = keymap::Keymap::default();
+    assert_eq!(
+        keys.action("Tab", true),
+        Some(keymap::Action::SelectPrevVoice)
+    );
+
+    // Up moves away from the camera; Shift's fine step is smaller
+    let up = arrow_direction("ArrowUp").unwrap();
+    let moved = nudge_position(Vec3::ZERO, up, NUDGE_STEP, 1.0);
+    assert!((moved - Vec3::new(0.0, 0.0, -NUDGE_STEP)).length() < 1e-6);
+    let down = arrow_direction("ArrowDown").unwrap();
+    let moved = nudge_position(moved, down, NUDGE_FINE_STEP, 1.0);
+    assert!((moved.z + NUDGE_STEP - NUDGE_FINE_STEP).abs() < 1e-6);
+    assert_eq!(arrow_direction("Enter"), None);
+
+    // Nudging lands back on the ground plane and stops at the drag radius
+    let right = arrow_direction("ArrowRight").unwrap();
+    let moved = nudge_position(Vec3::new(0.95, 0.4, 0.0), right, NUDGE_STEP, 1.0);
+    assert!((moved - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-6);
+
+    assert_eq!(
+        selection_label(1, Vec3::new(0.3, 0.0, -0.2)),
+        "Lead at x 0.30, z -0.20"
+    );
+}
+
+#[test]
+fn wheel_notches_pick_their_target_and_density_is_undoable() {
+    use history::{Command, History};
+    use wheel::*;
+
+    assert_eq!(WheelTarget::pick(Some(1), false), WheelTarget::Octave(1));
+    assert_eq!(WheelTarget::pick(Some(1), true), WheelTarget::Density(1));
+    assert_eq!(WheelTarget::pick(None, false), WheelTarget::Volume);
+    assert_eq!(WheelTarget::pick(None, true), WheelTarget::Tempo);
+
+    // Trackpad crumbs add up to a notch; scrolling up is positive
+    let mut notches = WheelNotches::default();
+    assert_eq!(notches.scroll(WheelTarget::Volume, -60.0, 100.0), 0);
+    assert_eq!(notches.scroll(WheelTarget::Volume, -60.0, 100.0), 1);
+    assert_eq!(notches.scroll(WheelTarget::Volume, 250.0, 100.0), -2);
+    // A new target starts from nothing
+    assert_eq!(notches.scroll(WheelTarget::Tempo, -90.0, 100.0), 0);
+    // Firefox's three lines are one mouse-wheel click
+    assert_eq!(
+        notches.scroll(WheelTarget::Octave(0), wheel_delta_px(3.0, 1), 100.0),
+        -1
+    );
+
+    let mut engine = make_engine();
+    let mut history = History::new();
+    let before = engine.configs[0].trigger_probability;
+    let density = before + DENSITY_STEP;
+    history.execute(&mut engine, Command::SetDensity { voice: 0, density });
+    assert_eq!(engine.configs[0].trigger_probability, density);
+    history.execute(
+        &mut engine,
+        Command::SetDensity {
+            voice: 0,
+            density: 3.0,
+        },
+    );
+    assert_eq!(engine.configs[0].trigger_probability, 1.0);
+    assert!(engine
+        .drain_events()
+        .contains(&bus::EngineEvent::DensityChanged {
+            voice: 0,
+            density: 1.0
+        }));
+    assert!(history.undo(&mut engine) && history.undo(&mut engine));
+    assert_eq!(engine.configs[0].trigger_probability, before);
+}
+
+#[test]
+fn midi_learn_maps_controllers_in_either_order_and_round_trips() {
+    use midi_learn::*;
+
+    assert_eq!(parse_cc(&[0xB9, 74, 100]), Some((9, 74, 100)));
+    assert_eq!(parse_cc(&[0x90, 60, 100]), None);
+
+    // Knob first, then parameter
+    let mut learn = MidiLearn::default();
+    assert_eq!(learn.heard((0, 74)), None, "not learning yet");
+    learn.start();
+    assert_eq!(learn.heard((0, 74)), None);
+    assert_eq!(learn.picked(CcTarget::Bpm), Some(((0, 74), CcTarget::Bpm)));
+    assert!(!learn.active);
+    // Parameter first, then knob
+    learn.start();
+    assert_eq!(learn.picked(CcTarget::DelaySend(2)), None);
+    assert_eq!(learn.heard((9, 7)), Some(((9, 7), CcTarget::DelaySend(2))));
+
+    let mut map = CcMap::default();
+    map.bind((0, 74), CcTarget::Bpm);
+    map.bind((9, 7), CcTarget::DelaySend(2));
+    // Remapping a knob or a parameter drops its old pairing
+    map.bind((0, 74), CcTarget::Level(1));
+    assert_eq!(map.target((0, 74)), Some(CcTarget::Level(1)));
+    assert_eq!(map.source(CcTarget::Bpm), None);
+    assert_eq!(map.encode(), "10:7=delay-2 1:74=level-1");
+    assert_eq!(CcMap::decode(&map.encode()), map);
+    assert_eq!(CcMap::decode("17:1=bpm 1:74=nope 2:10=density-0"), {
+        let mut m = CcMap::default();
+        m.bind((1, 10), CcTarget::Density(0));
+        m
+    });
+    for target in CcTarget::all(3) {
+        assert_eq!(CcTarget::parse(&target.id()), Some(target));
+    }
+
+    let mut engine = make_engine();
+    let bpm = CcTarget::Bpm.command(127, &engine).unwrap();
+    engine.apply(&bpm);
+    assert_eq!(engine.params.bpm, CC_BPM_MAX);
+    let level = CcTarget::Level(1).command(0, &engine).unwrap();
+    engine.apply(&level);
+    assert_eq!(engine.configs[1].sends.level, 0.0);
+    assert!(CcTarget::Density(7).command(64, &engine).is_none());
+
+    // Mappings travel with presets; a preset without any keeps the player's
+    engine.controls = map.clone();
+    let saved = preset::Preset::from_engine(&engine, "mine");
+    engine.controls = CcMap::default();
+    engine.apply_preset(&saved);
+    assert_eq!(engine.controls, map);
+    engine.apply_preset(&preset::preset_bank()[1]);
+    assert_eq!(engine.controls, map);
+}
+
+#[test]
+fn background_taps_land_harder_when_fast_or_pressed() {
+    use touch::*;
+
+    // A pointer sweeping 20 px every 10 ms settles towards 2000 px/s, and
+    // slows to nothing once it stops
+    let mut speed = PointerSpeed::default();
+    for i in 0..20 {
+        speed.moved([i as f32 * 20.0, 0.0], i as f64 * 0.01);
+    }
+    let sweeping = speed.speed(0.19);
+    assert!(sweeping > 1500.0 && sweeping <= 2000.0, "{sweeping}");
+    assert!(speed.speed(1.0) < 1.0);
+
+    let still = TapExpression::new(0.5, 0.0, None);
+    let fast = TapExpression::new(0.5, TAP_FAST_PX_PER_SEC, None);
+    let hard = TapExpression::new(0.5, 0.0, Some(1.0));
+    let soft = TapExpression::new(0.5, 0.0, Some(0.1));
+    for louder in [fast, hard] {
+        assert!(louder.velocity > still.velocity);
+        assert!(louder.cutoff_hz > still.cutoff_hz);
+        assert!(louder.ripple_amp > still.ripple_amp);
+    }
+    assert!(soft.velocity < still.velocity && soft.cutoff_hz < still.cutoff_hz);
+    // Height still sets the base, and everything stays in range
+    assert!(TapExpression::new(1.0, 0.0, None).velocity > still.velocity);
+    let max = TapExpression::new(1.0, 1e6, Some(1.0));
+    assert_eq!(max.velocity, 1.0);
+    assert_eq!(max.cutoff_hz, TAP_CUTOFF_MAX_HZ);
+
+    assert_eq!(measured_pressure("mouse", 0.5), None);
+    assert_eq!(measured_pressure("pen", 0.0), None);
+    assert_eq!(measured_pressure("pen", 0.8), Some(0.8));
+}
+
+#[test]
+fn strums_pluck_the_scale_notes_crossed_with_their_spacing() {
+    use strum::*;
+    let wgsl = include_str!("../shaders/waves.wgsl");
+    assert!(wgsl.contains(&format!("const STREAK_POINTS: u32 = {STREAK_POINTS}u;")));
+    assert!(wgsl.contains(&format!(
+        "const STREAK_LIFETIME_SEC: f32 = {STREAK_LIFETIME_SEC};"
+    )));
+
+    // C major pentatonic from C4 to C6
+    let strings = strum_strings(C_MAJOR_PENTATONIC, 60);
+    assert_eq!(
+        strings,
+        [60.0, 62.0, 64.0, 67.0, 69.0, 72.0, 74.0, 76.0, 79.0, 81.0, 84.0]
+    );
+    assert_eq!(background_uvx(background_midi(0.25)), 0.25);
+
+    let mut strum = Strum::default();
+    assert!(strum.sweep(1.0, 0.0, &strings).is_empty(), "not started");
+    strum.start(background_uvx(61.0), 0.0);
+    // 61 up to 68 over 50 ms crosses D, E and G, spaced as they were passed
+    let crossed = strum.sweep(background_uvx(68.0), 0.05, &strings);
+    let notes: Vec<f32> = crossed.iter().map(|c| c.0).collect();
+    assert_eq!(notes, [62.0, 64.0, 67.0]);
+    assert!((crossed[0].1 - 0.05 / 7.0).abs() < 1e-6);
+    assert!((crossed[2].1 - 0.05 * 6.0 / 7.0).abs() < 1e-6);
+    // Twice as fast, twice as close
+    let back = strum.sweep(background_uvx(61.0), 0.075, &strings);
+    let notes: Vec<f32> = back.iter().map(|c| c.0).collect();
+    assert_eq!(notes, [67.0, 64.0, 62.0]);
+    assert!((back[0].1 - 0.025 / 7.0).abs() < 1e-6);
+    // Ending on a string doesn't pluck it again on the way out
+    strum.sweep(background_uvx(64.0), 0.1, &strings);
+    let on = strum.sweep(background_uvx(66.0), 0.11, &strings);
+    assert!(on.is_empty());
+    assert!(strum.stop(), "it crossed strings");
+    assert!(!strum.is_active());
+    strum.start(0.5, 1.0);
+    assert!(!strum.stop(), "a Shift+click crosses nothing");
+
+    // The streak joins points close in time and starts afresh after a gap
+    let mut streak = Streak::default();
+    streak.push([0.1, 0.5], 0.0);
+    streak.push([0.2, 0.5], 0.02);
+    streak.push([0.8, 0.5], 0.5);
+    let packed = streak.pack(0.5);
+    let lit: Vec<[f32; 4]> = packed.into_iter().filter(|p| p[3] > 0.0).collect();
+    assert_eq!(lit.len(), 3);
+    assert_eq!([lit[0][3], lit[1][3], lit[2][3]], [1.0, 2.0, 1.0]);
+    assert_eq!(lit[2][0], 0.8, "newest last");
+    // Faded points drop out
+    assert_eq!(streak.pack(0.7).iter().filter(|p| p[3] > 0.0).count(), 1);
+}
+
+#[test]
+fn held_background_notes_repeat_on_the_grid_until_released() {
+    use note_repeat::*;
+    let mut hold = BackgroundHold::default();
+    let serial = hold.press(1, [100.0, 100.0], [0.25, 0.5]);
+    hold.moved_to(1, [103.0, 104.0]);
+    assert_eq!(
+        hold.ripen(serial),
+        Some([0.25, 0.5]),
+        "still within the slop"
+    );
+    assert_eq!(hold.ripen(serial), None, "ripens once");
+    assert!(!hold.release(2), "another pointer");
+    assert!(hold.release(1));
+    let moved = hold.press(1, [0.0, 0.0], [0.5, 0.5]);
+    hold.moved_to(1, [30.0, 0.0]);
+    assert_eq!(hold.ripen(moved), None, "a move is a swirl, not a hold");
+    let early = hold.press(1, [0.0, 0.0], [0.5, 0.5]);
+    let late = hold.press(1, [0.0, 0.0], [0.5, 0.5]);
+    assert_eq!(hold.ripen(early), None, "an earlier press's timer");
+    assert!(hold.ripen(late).is_some());
+    assert!(hold.cancel());
+
+    let mut engine = make_engine();
+    let step = engine.step_duration_sec();
+    let mut events = Vec::new();
+    engine.schedule(0.0, 0.0, &mut events);
+    assert!(!engine.release_note(), "nothing held");
+    let note = BackgroundNote {
+        voice: 1,
+        frequency_hz: 440.0,
+        velocity: 0.5,
+        cutoff_hz: 4000.0,
+        duration_sec: 0.4,
+    };
+    engine.hold_note(note);
+    engine.schedule(0.0, 4.0 * step - 1e-6, &mut events);
+    let hits = engine.drain_repeats();
+    assert_eq!(hits.len(), 3, "each step after the one already scheduled");
+    for (k, hit) in hits.iter().enumerate() {
+        assert_eq!(hit.note, note);
+        assert!((hit.time_sec - (k + 1) as f64 * step).abs() < 1e-9);
+    }
+    assert!(engine.drain_repeats().is_empty());
+    assert!(engine.release_note());
+    engine.schedule(0.0, 8.0 * step, &mut events);
+    assert!(engine.drain_repeats().is_empty(), "stops on release");
+}
+
+#[test]
+fn the_looper_replays_a_take_on_the_grid_every_loop() {
+    use looper::*;
+    let bar = STEPS_PER_BAR as f64;
+    let mute = LoopAction::Mute { voice: 1 };
+    let drag = LoopAction::Move {
+        voice: 0,
+        position: glam::Vec3::new(0.5, 0.0, -0.5),
+    };
+
+    let mut looper = Looper::default();
+    looper.capture(1.0, mute);
+    assert_eq!(looper.state(), LoopState::Empty, "not recording");
+    looper.toggle_play(0.0);
+    assert_eq!(looper.state(), LoopState::Empty, "nothing to play");
+
+    // Recording mid-bar keeps the actions in their place from the bar line
+    looper.set_bars(1);
+    looper.record(bar * 3.0 + 2.0);
+    looper.capture(bar * 3.0 + 2.5, mute);
+    looper.capture(bar * 3.0 + 6.0, drag);
+    assert!(looper.advance(bar * 3.0 + 7.0).is_empty());
+    assert_eq!(looper.state(), LoopState::Recording);
+    assert_eq!(looper.action_count(), 2);
+    // Once the bar is over the take plays from the next bar line
+    assert!(looper.advance(bar * 4.0 + 2.0).is_empty());
+    assert_eq!(looper.state(), LoopState::Playing);
+    assert_eq!(looper.advance(bar * 4.0 + 3.0), [mute]);
+    assert_eq!(
+        looper.advance(bar * 5.0 + 3.0),
+        [drag, mute],
+        "in grid order"
+    );
+    assert!(looper.advance(bar * 5.0 + 3.0).is_empty());
+    // A jump of more than a loop (or backwards) plays nothing
+    assert!(looper.advance(bar * 9.0).is_empty());
+    assert!(looper.advance(bar * 2.0).is_empty());
+
+    // Stopped takes are kept; a new length waits for the next take
+    looper.toggle_play(bar * 2.0);
+    assert_eq!(looper.state(), LoopState::Stopped);
+    assert!(looper.advance(bar * 3.0).is_empty());
+    looper.set_bars(4);
+    assert_eq!(looper.bars(), 4);
+    looper.toggle_play(bar * 3.0);
+    assert_eq!(
+        looper.advance(bar * 3.0 + 3.0),
+        [mute],
+        "still one bar long"
+    );
+    looper.clear();
+    assert_eq!(looper.state(), LoopState::Empty);
+
+    // A recording with nothing in it ends empty
+    looper.record(0.0);
+    looper.advance(bar * 4.0);
+    assert_eq!(looper.state(), LoopState::Empty);
+
+    // The engine's grid position at a time on its scheduling clock
+    let mut engine = make_engine();
+    let step = engine.step_duration_sec();
+    assert_eq!(engine.grid_position(5.0), 0.0, "before the first schedule");
+    let mut events = Vec::new();
+    engine.schedule(10.0, 2.5 * step, &mut events);
+    assert!((engine.grid_position(10.0)).abs() < 1e-9);
+    assert!((engine.grid_position(10.0 + 1.5 * step) - 1.5).abs() < 1e-9);
+}
+
+#[test]
+fn saved_sessions_round_trip_and_restore_the_engine() {
+    use history::Command;
+    use saved_session::*;
+    let mut engine = make_engine();
+    engine.apply(&Command::SetBpm(97.0));
+    engine.apply(&Command::ToggleMute { voice: 2 });
+    engine.apply(&Command::SetPosition {
+        voice: 1,
+        position: glam::Vec3::new(0.5, 0.0, -1.25),
+    });
+    engine.apply(&Command::SetDensity {
+        voice: 0,
+        density: 0.25,
+    });
+    engine.apply(&Command::SetOctave {
+        voice: 1,
+        octave: -1,
+    });
+    engine.apply(&Command::SetWaveform {
+        voice: 2,
+        waveform: Waveform::Wavetable(1),
+    });
+    engine.apply(&Command::SetMotion(motion::MotionMode::Orbit));
+    let settings = vec![("bloom".to_string(), "1.2,0.5,3".to_string())];
+    let saved = SavedSession::capture(&engine, settings);
+    let text = saved.encode();
+    assert!(text.starts_with("g1;bpm=97;"));
+    let back = SavedSession::decode(&text).expect("decodes");
+    assert_eq!(back, saved);
+    assert_eq!(back.setting("bloom"), Some("1.2,0.5,3"));
+    assert_eq!(back.setting("eq"), None);
+
+    let mut fresh = make_engine();
+    fresh.apply(&back.restore_command(fresh.voices.len()));
+    assert_eq!(fresh.params.bpm, 97.0);
+    assert!(fresh.voices[2].muted);
+    assert_eq!(fresh.voices[1].position, glam::Vec3::new(0.5, 0.0, -1.25));
+    assert_eq!(fresh.configs[0].trigger_probability, 0.25);
+    assert_eq!(fresh.configs[1].octave_offset, -1);
+    assert_eq!(fresh.configs[2].waveform, Waveform::Wavetable(1));
+    assert_eq!(fresh.motion(), motion::MotionMode::Orbit);
+
+    // Newer lines are skipped; a broken voice drops it and those after it
+    let newer = text.replacen("\nseed=", "\nfuture=1\nseed=", 1);
+    assert_eq!(SavedSession::decode(&newer), Some(saved.clone()));
+    let broken = text
+        .replacen(";saw", ";kazoo", 1)
+        .replacen(";sine", ";kazoo", 1);
+    assert!(SavedSession::decode(&broken).unwrap().voices.len() < 3);
+    assert_eq!(SavedSession::decode("not a session"), None);
+    for w in [
+        Waveform::Sine,
+        Waveform::Saw,
+        Waveform::Triangle,
+        Waveform::Wavetable(7),
+    ] {
+        assert_eq!(Waveform::from_id(&w.id()), Some(w));
+    }
+
+    // Effect settings come back through the same parsers the URL uses
+    let bloom = bloom::Bloom::parse("1.2,0.5,3").unwrap();
+    assert_eq!(bloom::Bloom::parse(&bloom.param()), Some(bloom));
+    let exposure = tonemap::Exposure {
+        ev: -0.5,
+        auto: true,
+    };
+    assert_eq!(tonemap::Exposure::parse(&exposure.param()), Some(exposure));
+    let mut eq = eq::MasterEq::FLAT;
+    eq.set_gain(1, -3.5);
+    assert_eq!(eq::MasterEq::parse(&eq.param()), Some(eq));
+    for m in ["off", "4", "6,bars"] {
+        assert_eq!(mirror::Mirror::from_param(m).unwrap().param(), m);
+    }
+    let lens = lens::Lens::default().with_dof_param("high,6").unwrap();
+    assert_eq!(
+        lens::Lens::default().with_dof_param(&lens.dof_param()),
+        Some(lens)
+    );
+}
+
+#[test]
+fn named_presets_save_replace_and_round_trip_their_pairs() {
+    use history::Command;
+    use midi_learn::CcTarget;
+    use named_presets::*;
+    use saved_session::SavedSession;
+    let mut engine = make_engine();
+    engine.controls.bind((0, 74), CcTarget::Bpm);
+    let slow = SavedSession::capture(&engine, vec![("stars".to_string(), "0.5".to_string())]);
+    engine.apply(&Command::SetBpm(140.0));
+    let fast = SavedSession::capture(&engine, Vec::new());
+
+    let mut presets = NamedPresets::default();
+    assert_eq!(
+        presets.save("  Slow  ", slow.clone()),
+        Some("Slow".to_string())
+    );
+    assert_eq!(presets.save("   ", slow.clone()), None);
+    let long = "x".repeat(MAX_PRESET_NAME_CHARS + 5);
+    assert_eq!(
+        presets.save(&long, fast.clone()).map(|n| n.len()),
+        Some(MAX_PRESET_NAME_CHARS)
+    );
+    presets.save("Fast", slow.clone());
+    presets.save("Fast", fast.clone());
+    let names: Vec<&str> = presets.names().collect();
+    assert_eq!(names, ["Slow", &long[..MAX_PRESET_NAME_CHARS], "Fast"]);
+    assert_eq!(presets.get("Fast"), Some(&fast));
+
+    // Mappings travel with the preset
+    assert_eq!(presets.get("Slow").unwrap().controls, engine.controls);
+
+    let pairs = presets.to_pairs();
+    let mut imported = NamedPresets::default();
+    let added = imported.merge_pairs(pairs.iter().map(|(n, s)| (n.as_str(), s.as_str())));
+    assert_eq!(added, 3);
+    assert_eq!(imported, presets);
+
+    // Importing replaces same-named presets and skips unreadable ones
+    let added = imported.merge_pairs([("Slow", pairs[2].1.as_str()), ("Bad", "not a session")]);
+    assert_eq!(added, 1);
+    assert_eq!(imported.get("Slow"), Some(&fast));
+    assert_eq!(imported.get("Bad"), None);
+
+    assert!(imported.remove("Slow"));
+    assert!(!imported.remove("Slow"));
+    assert_eq!(imported.names().count(), 2);
+}
+
+#[test]
+fn automation_records_parameter_changes_and_replays_them_from_the_top() {
+    use automation::*;
+    use history::Command;
+    let engine = make_engine();
+    let bar = music::STEPS_PER_BAR as f64;
+    let mut auto = Automation::default();
+    auto.capture(1.0, Command::SetBpm(90.0));
+    assert_eq!(auto.state(), AutomationState::Empty);
+
+    // Recording starts from the bar line and snapshots the parameters
+    auto.record(bar + 3.0, automation_snapshot(&engine));
+    auto.capture(bar + 4.0, Command::SetBpm(120.0));
+    auto.capture(bar + 4.1, Command::SetBpm(125.0));
+    auto.capture(bar + 4.1, Command::ToggleMute { voice: 0 });
+    auto.capture(bar + 6.0, Command::SetBpm(130.0));
+    let bus_event = bus::EngineEvent::DensityChanged {
+        voice: 1,
+        density: 0.5,
+    };
+    auto.capture(bar + 7.0, event_command(&bus_event).unwrap());
+    assert_eq!(auto.change_count(), 3);
+    auto.stop_recording(2.0 * bar + 1.0);
+    assert_eq!(auto.bars(), 2);
+    assert_eq!(auto.state(), AutomationState::Stopped);
+
+    // Play waits for the next bar line, then starts from the snapshot
+    auto.toggle_play(5.0 * bar + 2.0);
+    assert!(auto.advance(6.0 * bar - 1.0).is_empty());
+    let first = auto.advance(6.0 * bar + 4.5);
+    let start_bpm = engine.params.bpm;
+    assert!(matches!(first.first(), Some(Command::SetBpm(b)) if *b == start_bpm));
+    assert_eq!(first.len(), automation_snapshot(&engine).len() + 1);
+    assert!(matches!(first.last(), Some(Command::SetBpm(125.0))));
+    assert!(matches!(
+        auto.advance(6.0 * bar + 8.0)[..],
+        [
+            Command::SetBpm(130.0),
+            Command::SetDensity {
+                voice: 1,
+                density: 0.5
+            }
+        ]
+    ));
+    assert_eq!(auto.state(), AutomationState::Playing);
+    assert!(auto.advance(8.0 * bar).is_empty());
+    assert_eq!(auto.state(), AutomationState::Stopped);
+
+    auto.clear();
+    assert_eq!(auto.state(), AutomationState::Empty);
+    assert!(event_command(&bus::EngineEvent::MuteChanged {
+        voice: 0,
+        muted: true
+    })
+    .is_none());
+}
+
+#[test]
+fn replays_rebuild_the_same_notes_from_the_seed_and_input_log() {
+    use history::{Command, History};
+    use replay::*;
+    let run = |engine: &mut MusicEngine, steps: usize| -> Vec<(usize, f32)> {
+        let mut notes = Vec::new();
+        for _ in 0..steps {
+            let mut events = Vec::new();
+            engine.tick(std::time::Duration::from_millis(125), &mut events);
+            notes.extend(events.iter().map(|e| (e.voice_index, e.frequency_hz)));
+        }
+        notes
+    };
+
+    let mut original = make_engine();
+    let mut history = History::new();
+    let mut notes = run(&mut original, 20);
+    history.execute(&mut original, Command::SetBpm(132.0));
+    history.execute(
+        &mut original,
+        Command::Reseed {
+            voice: 1,
+            seed: None,
+        },
+    );
+    history.execute(&mut original, Command::SetScale(music::DORIAN));
+    original.set_voice_position(0, glam::Vec3::new(0.5, 0.0, 0.5));
+    original.set_voice_position(0, glam::Vec3::new(0.75, 0.0, 0.5));
+    notes.extend(run(&mut original, 20));
+    history.undo(&mut original);
+    history.execute(&mut original, Command::ToggleMute { voice: 2 });
+    notes.extend(run(&mut original, 40));
+
+    // Both drags before the same step are logged as the last one
+    let positions = original
+        .input_log()
+        .iter()
+        .filter(|(_, i)| matches!(i, ReplayInput::Command(Command::SetPosition { .. })))
+        .count();
+    assert_eq!(positions, 1);
+
+    let replay = Replay {
+        seed: original.seed(),
+        inputs: original.input_log().to_vec(),
+    };
+    let (text, skipped) = replay.encode();
+    assert!(text.starts_with(REPLAY_HEADER));
+    assert_eq!(skipped, 0);
+    let back = Replay::decode(&text).expect("decodes");
+    assert_eq!(back.seed, replay.seed);
+    assert_eq!(back.inputs.len(), replay.inputs.len());
+
+    let mut again = make_engine();
+    again.queue_replay(back.inputs);
+    assert!(again.is_replaying());
+    assert_eq!(run(&mut again, 80), notes);
+    assert!(!again.is_replaying());
+    assert_eq!(again.params.scale, original.params.scale);
+    assert_eq!(again.voices[0].position, original.voices[0].position);
+    assert_eq!(again.input_log().len(), original.input_log().len());
+
+    // Commands that can't be written down are counted, not written
+    let generator = Replay {
+        seed: 1,
+        inputs: vec![(
+            0,
+            ReplayInput::Command(Command::SetSampler {
+                voice: 0,
+                sampler: None,
+            }),
+        )],
+    };
+    assert_eq!(generator.encode().1, 1);
+    assert!(Replay::decode("seed=1").is_none());
+    for line in [
+        "mutes 010 1",
+        "groove 2",
+        "morphscale 4 lyd",
+        "reseed 2 99",
+        "countin 1",
+    ] {
+        let input = Replay::decode(&format!("{REPLAY_HEADER}\nseed=1\n7 {line}")).unwrap();
+        assert_eq!(input.encode().0.lines().last(), Some(&*format!("7 {line}")));
+    }
+}
+
+#[test]
+fn resume_restores_clean_exits_and_offers_crashed_sessions() {
+    use recovery::Resume;
+    use saved_session::SavedSession;
+    let saved = SavedSession::capture(&make_engine(), Vec::new());
+    assert_eq!(Resume::decide(None, true), Resume::Fresh);
+    assert_eq!(Resume::decide(None, false).restored(), None);
+
+    let clean = Resume::decide(Some(saved.clone()), false);
+    assert_eq!(clean.restored(), Some(&saved));
+    assert_eq!(clean.offered(), None);
+
+    // A visit still marked running never left: offer its autosave instead
+    let crashed = Resume::decide(Some(saved.clone()), true);
+    assert_eq!(crashed.restored(), None);
+    assert_eq!(crashed.offered(), Some(&saved));
+
+    // A `?seed=` link of another seed starts from that seed, not the save
+    let other = saved.seed + 1;
+    assert_eq!(clean.clone().for_url_seed(Some(other)), Resume::Fresh);
+    assert_eq!(crashed.clone().for_url_seed(Some(other)), Resume::Fresh);
+    assert_eq!(clean.clone().for_url_seed(Some(saved.seed)), clean);
+    assert_eq!(clean.clone().for_url_seed(None), clean);
+
+    // nor are its effect settings, which come from the restored session
+    let mut tweaked = SavedSession::capture(&make_engine(), Vec::new());
+    tweaked
+        .settings
+        .push(("bloom".to_string(), "2,0.5,3".to_string()));
+    let link = Resume::decide(Some(tweaked), false).for_url_seed(Some(4217));
+    assert!(link.restored().and_then(|s| s.setting("bloom")).is_none());
+}
+
+#[test]
+fn hidden_page_holds_the_scheduler_and_resyncs_without_a_burst() {
+    use lifecycle::{AudioLifecycle, SchedulerGate};
+    let mut life = AudioLifecycle::default();
+    // Hidden while playing: the audio is suspended and the scheduler holds
+    assert!(life.hide(true));
+    assert_eq!(life.gate(true), SchedulerGate::Hold);
+    assert!(life.show(true, false));
+    // Still waiting for the context to run again (an iOS gesture)
+    assert_eq!(life.gate(false), SchedulerGate::Hold);
+    assert_eq!(life.gate(true), SchedulerGate::Resync);
+    assert_eq!(life.gate(true), SchedulerGate::Run);
+    // A paused session is left alone, unless the system stopped its audio
+    assert!(!life.hide(false));
+    assert!(!life.show(false, false));
+    assert!(life.show(true, false));
+
+    use history::Command;
+    let mut engine = make_engine();
+    engine.apply(&Command::SetBpm(240.0));
+    let mut events = Vec::new();
+    let mut now = 10.0;
+    while now < 11.0 {
+        engine.schedule(now, 0.12, &mut events);
+        now += 0.016;
+    }
+    _ = engine.drain_beats();
+    // Back after a gap shorter than the scheduler's own lag limit: the grid
+    // carries on from here rather than catching up on the missed steps
+    let back = now + 0.24;
+    let mut stale = engine.clone();
+    engine.resync_grid(back);
+    events.clear();
+    engine.schedule(back, 0.12, &mut events);
+    stale.schedule(back, 0.12, &mut Vec::new());
+    assert!(events.iter().all(|ev| ev.start_time_sec >= back - 1e-9));
+    assert!(engine.drain_beats().iter().all(|b| b.at_sec >= back - 1e-9));
+    assert!(engine.grid_position(back) < stale.grid_position(back));
+}
+
+#[test]
+fn diagnostics_headline_the_worst_problem_and_list_everything() {
+    use diagnostics::{Diagnostics, Severity};
+    let mut d = Diagnostics::default();
+    d.capability("WebGPU", false);
+    d.capability("Web MIDI", true);
+    d.capability("WebGPU", true);
+    assert_eq!(d.headline(), None);
+    assert_eq!(d.text(), "WebGPU: yes\nWeb MIDI: yes\nNo problems");
+
+    // Notices alone don't raise the banner
+    d.report(Severity::Notice, "recording", "unavailable");
+    assert_eq!(d.headline(), None);
+    assert!(!d.is_fatal());
+
+    d.report(Severity::Degraded, "graphics", "no WebGPU adapter");
+    d.report(
+        Severity::Fatal,
+        "audio",
+        "couldn't create a Master GainNode",
+    );
+    d.report(Severity::Fatal, "audio", "later");
+    assert!(d.is_fatal());
+    assert_eq!(
+        d.headline().as_deref(),
+        Some("Audio failed: couldn't create a Master GainNode (3 more in Diagnostics)")
+    );
+    assert!(d
+        .text()
+        .ends_with("[note] recording: unavailable\n[degraded] graphics: no WebGPU adapter\n[failed] audio: couldn't create a Master GainNode\n[failed] audio: later"));
+}
+
+#[test]
+fn loaded_preset_plays_the_notes_it_was_saved_with() {
+    use history::Command;
+    use saved_session::SavedSession;
+    let build = |seed| {
+        let startup = preset::preset_bank().swap_remove(0);
+        MusicEngine::new(startup.voices, startup.params, seed)
+    };
+    let notes = |engine: &mut MusicEngine| {
+        let mut events = Vec::new();
+        for k in 0..400 {
+            engine.schedule(k as f64 * 0.02, 0.1, &mut events);
+        }
+        events
+            .iter()
+            .map(|ev| (ev.voice_index, ev.frequency_hz, ev.start_time_sec))
+            .collect::<Vec<_>>()
+    };
+    let mut saved_from = build(11);
+    saved_from.apply(&Command::SetBpm(132.0));
+    saved_from.apply(&Command::SetDensity {
+        voice: 1,
+        density: 0.9,
+    });
+    let preset = SavedSession::capture(&saved_from, Vec::new());
+
+    // Loaded into a session started from another seed
+    let other = build(99);
+    let mut loaded = preset.rebuild(&other);
+    assert_eq!(loaded.seed(), 11);
+    let expected = notes(&mut saved_from);
+    assert!(!expected.is_empty());
+    assert_eq!(notes(&mut loaded), expected);
+
+    // Restoring the settings alone keeps the other seed's melodies
+    let mut restored_only = build(99);
+    restored_only.apply(&preset.restore_command(3));
+    assert_ne!(notes(&mut restored_only), expected);
+}
+
+#[test]
+fn screenshot_is_stamped_with_the_signature_in_its_corner() {
+    use screenshot::Screenshot;
+
+    let (w, h) = (200u32, 120u32);
+    let mut shot = Screenshot {
+        width: w,
+        height: h,
+        rgba: [0, 0, 0, 255].repeat((w * h) as usize),
+    };
+    let signature = make_engine().signature();
+    shot.stamp_signature(&signature);
+
+    let lit = |x0: u32, y0: u32, x1: u32, y1: u32| {
+        (y0..y1)
+            .flat_map(|y| (x0..x1).map(move |x| ((y * w + x) * 4) as usize))
+            .filter(|&at| shot.rgba[at..at + 3] != [0, 0, 0])
+            .count()
+    };
+    assert!(lit(w / 2, h / 2, w, h) > 0, "glyph drawn bottom right");
+    assert_eq!(lit(0, 0, w / 2, h / 2), 0, "rest of the frame untouched");
+    assert!(shot.rgba.as_chunks::<4>().0.iter().all(|px| px[3] == 255));
+
+    // The stroke colour matches the SVG's hsl(hue, 70%, 70%)
+    let red = signature::Signature {
+        hue: 0.0,
+        ..signature
+    };
+    assert_eq!(red.stroke_rgb(), [232, 125, 125]);
+
+    // Too small to read: left alone
+    let mut tiny = Screenshot {
+        width: 16,
+        height: 16,
+        rgba: vec![0; 16 * 16 * 4],
+    };
+    tiny.stamp_signature(&red);
+    assert!(tiny.rgba.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn input_log_coalesces_moves_and_stops_at_its_cap() {
+    use history::Command;
+    use replay::*;
+    let positions = |engine: &MusicEngine| {
+        engine
+            .input_log()
+            .iter()
+            .filter(|(_, i)| matches!(i, ReplayInput::Command(Command::SetPosition { .. })))
+            .count()
+    };
+
+    // A gesture moving every voice each frame logs one place per voice a step
+    let mut engine = make_engine();
+    for frame in 0..30 {
+        for voice in 0..engine.voices.len() {
+            engine.set_voice_position(voice, glam::Vec3::new(frame as f32 * 0.01, 0.0, 0.0));
+        }
+    }
+    assert_eq!(positions(&engine), engine.voices.len());
+    assert!(engine
+        .input_log()
+        .iter()
+        .all(|(_, i)| matches!(i, ReplayInput::Command(Command::SetPosition { position, .. }) if position.x == 0.29)));
+
+    // Another command in between keeps the earlier move
+    engine.apply(&Command::ToggleMute { voice: 0 });
+    engine.set_voice_position(0, glam::Vec3::ZERO);
+    assert_eq!(positions(&engine), engine.voices.len() + 1);
+
+    let mut engine = make_engine();
+    assert!(!engine.input_log_is_full());
+    for i in 0..MAX_REPLAY_INPUTS + 10 {
+        engine.apply(&Command::SetBpm(100.0 + (i % 40) as f32));
+    }
+    assert_eq!(engine.input_log().len(), MAX_REPLAY_INPUTS);
+    assert!(engine.input_log_is_full());
+    assert!((engine.params.bpm - (100.0 + ((MAX_REPLAY_INPUTS + 9) % 40) as f32)).abs() < 1e-3);
+}
+
+#[test]
+fn replacing_the_engine_announces_the_mutes_locks_and_settings_it_resets() {
+    use bus::EngineEvent;
+    use history::Command;
+    let mut engine = make_engine();
+    engine.toggle_mute(1);
+    engine.toggle_lock(2);
+    engine.toggle_solo(0);
+    // Long enough for the mute fades to settle
+    engine.tick(std::time::Duration::from_secs(1), &mut Vec::new());
+    engine.drain_events();
+    engine.apply(&Command::SetRootMidi(62));
+
+    let mut fresh = make_engine();
+    fresh.apply(&Command::SetBpm(90.0));
+    engine.replace_with(fresh);
+    let events = engine.drain_events();
+
+    // Undrained events come first, then the swap's
+    assert_eq!(events[0], EngineEvent::RootChanged(62));
+    for voice in 1..engine.voices.len() {
+        assert!(events.contains(&EngineEvent::MuteChanged {
+            voice,
+            muted: false
+        }));
+    }
+    assert!(!events.contains(&EngineEvent::MuteChanged {
+        voice: 0,
+        muted: false
+    }));
+    assert!(events.contains(&EngineEvent::LockChanged {
+        voice: 2,
+        locked: false
+    }));
+    assert!(events.contains(&EngineEvent::SoloChanged { voice: None }));
+    assert!(events.contains(&EngineEvent::RootChanged(60)));
+    assert!(events.contains(&EngineEvent::BpmChanged(90.0)));
+    assert!(!events
+        .iter()
+        .any(|e| matches!(e, EngineEvent::WaveformChanged { .. })));
+
+    // A voice that was silent fades back in rather than jumping
+    assert!(!engine.voices[1].muted);
+    assert_eq!(engine.voices[1].fade.target_gain, 1.0);
+    assert_eq!(engine.voices[1].fade.from_gain, 0.0);
+}
+
+#[test]
+fn grooves_are_matched_by_name_not_address() {
+    use bus::EngineEvent;
+    use history::Command;
+    let mut engine = make_engine();
+    let preset = preset::preset_bank().swap_remove(1);
+    engine.apply(&Command::ApplyPreset(Box::new(preset.clone())));
+    engine.drain_events();
+
+    // The same groove again, through another reference to it, isn't a change
+    engine.apply(&Command::ApplyPreset(Box::new(preset)));
+    assert!(!engine
+        .drain_events()
+        .iter()
+        .any(|e| matches!(e, EngineEvent::GrooveChanged(_))));
+}